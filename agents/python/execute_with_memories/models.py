@@ -8,5 +8,14 @@ class QueryRequest(BaseModel):
     stream: Optional[bool] = False
     tools: Optional[List[Dict[str, Any]]] = None
 
+class ToolInvocation(BaseModel):
+    tool_use_id: str
+    tool_name: str
+    latency_ms: int
+
 class QueryResponse(BaseModel):
-    text: str
\ No newline at end of file
+    text: str
+    # Name of the tool used to answer the query, if any
+    tool_name: Optional[str] = None
+    # MCP tool calls made to answer the query, each timed on its own
+    tool_invocations: List[ToolInvocation] = []
@@ -2,8 +2,9 @@ from fastapi import APIRouter, HTTPException, Depends
 from fastapi.responses import StreamingResponse, JSONResponse
 from typing import AsyncGenerator, Dict, Any, Optional
 import logging
+import time
 
-from models import QueryRequest, QueryResponse
+from models import QueryRequest, QueryResponse, ToolInvocation
 from services import call_anthropic
 from tool_executor import process_tool_use_response, make_follow_up_request
 from mcp_client import MCPClient, get_mcp_client
@@ -47,10 +48,12 @@ async def query_anthropic_route(
                 # Process the tool use response
                 try:
                     # 1. Parse the tool use block and execute the tool using the injected mcp_client
+                    tool_start = time.monotonic()
                     tool_use_id, tool_name, tool_result = await process_tool_use_response(
                         response_data,
                         mcp_client=mcp_client
                     )
+                    tool_latency_ms = int((time.monotonic() - tool_start) * 1000)
 
                     if not tool_use_id:
                         logger.error("Failed to extract tool use information")
@@ -92,7 +95,15 @@ async def query_anthropic_route(
                             if content.get("type") == "text"
                         ])
                         logger.info(f"Returning final response after tool use: '{final_text[:50]}...'")
-                        return QueryResponse(text=final_text)
+                        return QueryResponse(
+                            text=final_text,
+                            tool_name=tool_name,
+                            tool_invocations=[ToolInvocation(
+                                tool_use_id=tool_use_id,
+                                tool_name=tool_name,
+                                latency_ms=tool_latency_ms,
+                            )],
+                        )
 
                 except Exception as e:
                     logger.exception(f"Error during tool execution flow: {e}")
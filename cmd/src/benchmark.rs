@@ -0,0 +1,289 @@
+use std::net::SocketAddr;
+use std::time::{Duration, Instant};
+use alloy_primitives::B256;
+use alloy_signer::Signer;
+use alloy_signer_local::PrivateKeySigner;
+use color_eyre::{Result, eyre::anyhow};
+use jsonrpsee::{
+    rpc_params,
+    core::client::ClientT,
+    http_client::HttpClient,
+};
+use serde_json::{json, Value};
+use sha3::{Digest, Keccak256};
+use tokio::time;
+use tracing::{info, warn};
+
+use rpc::rpc_client::create_http_rpc_client;
+use p2p_network::types::{
+    AccessCondition,
+    AccessKey,
+    Reverie,
+    ReverieType,
+    create_spawn_challenge,
+};
+
+/// How long to wait for every keyfrag provider to hold its cfrag before giving up
+const DISTRIBUTION_TIMEOUT: Duration = Duration::from_secs(30);
+const DISTRIBUTION_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct LatencyPercentiles {
+    pub p50: Duration,
+    pub p95: Duration,
+    pub p99: Duration,
+}
+
+impl LatencyPercentiles {
+    /// Nearest-rank percentiles, None if there are no samples
+    pub fn from_samples(samples: &[Duration]) -> Option<Self> {
+        if samples.is_empty() {
+            return None
+        }
+        let mut sorted = samples.to_vec();
+        sorted.sort();
+        let percentile = |p: usize| {
+            let rank = (p * sorted.len()).div_ceil(100).max(1);
+            sorted[rank - 1]
+        };
+        Some(Self {
+            p50: percentile(50),
+            p95: percentile(95),
+            p99: percentile(99),
+        })
+    }
+}
+
+/// Latencies of each spawn and reconstruct iteration, per stage
+#[derive(Debug, Clone, Default)]
+pub(crate) struct BenchmarkReport {
+    /// `spawn_memory_reverie` round trip: encryption, keyfrag creation and broadcast
+    pub spawn: Vec<Duration>,
+    /// From spawn returning until every kfrag provider holds its cfrag
+    pub distribution: Vec<Duration>,
+    /// `decrypt_reverie` round trip on the target vessel: requesting cfrags and decrypting them
+    pub reconstruction: Vec<Duration>,
+}
+
+impl BenchmarkReport {
+    /// Formats a table of p50/p95/p99 latencies, one row per stage
+    pub fn format_table(&self) -> String {
+        let mut table = format!(
+            "{:<16} {:>8} {:>10} {:>10} {:>10}",
+            "STAGE", "SAMPLES", "P50", "P95", "P99"
+        );
+        for (stage, samples) in [
+            ("spawn", &self.spawn),
+            ("distribution", &self.distribution),
+            ("reconstruction", &self.reconstruction),
+        ] {
+            let row = match LatencyPercentiles::from_samples(samples) {
+                Some(LatencyPercentiles { p50, p95, p99 }) => format!(
+                    "\n{:<16} {:>8} {:>10} {:>10} {:>10}",
+                    stage,
+                    samples.len(),
+                    format!("{}ms", p50.as_millis()),
+                    format!("{}ms", p95.as_millis()),
+                    format!("{}ms", p99.as_millis()),
+                ),
+                None => format!("\n{:<16} {:>8} {:>10} {:>10} {:>10}", stage, 0, "-", "-", "-"),
+            };
+            table.push_str(&row);
+        }
+        table
+    }
+}
+
+/// Spawns a dummy memory reverie `iterations` times on the node at `rpc_address`, and
+/// reconstructs it on its target vessel, timing each stage. The target vessel is found
+/// among `peer_rpc_addresses` by its umbral public key. Distribution is measured by
+/// polling the node states of `peer_rpc_addresses`, and skipped if there are none.
+pub(crate) async fn run_benchmark(
+    rpc_address: &SocketAddr,
+    peer_rpc_addresses: &[SocketAddr],
+    threshold: usize,
+    total_frags: usize,
+    iterations: usize,
+) -> Result<BenchmarkReport> {
+
+    let client = create_http_rpc_client(rpc_address).await?;
+    let mut peer_clients = vec![];
+    for peer_rpc_address in peer_rpc_addresses {
+        peer_clients.push(create_http_rpc_client(peer_rpc_address).await?);
+    }
+    if peer_clients.is_empty() {
+        warn!("No --ports given, skipping fragment distribution latency");
+    }
+
+    let mut report = BenchmarkReport::default();
+    for iteration in 0..iterations {
+        // a fresh key per reverie, so every spawn is a distinct reverie
+        let signer = PrivateKeySigner::random();
+        let access_condition = AccessCondition::Ecdsa(signer.address());
+        let memory_secrets = json!({ "memories": format!("benchmark reverie {}", iteration) });
+        let challenge = create_spawn_challenge(&memory_secrets, &access_condition);
+        let spawn_signature = AccessKey::EcdsaSignature(
+            signer.sign_hash(&keccak256(challenge.as_bytes())).await?.as_bytes().to_vec()
+        );
+
+        let started_at = Instant::now();
+        let reverie: Reverie = client.request(
+            "spawn_memory_reverie",
+            rpc_params![
+                memory_secrets,
+                threshold,
+                total_frags,
+                access_condition,
+                spawn_signature,
+                None::<Vec<String>>
+            ]
+        ).await?;
+        report.spawn.push(started_at.elapsed());
+
+        if !peer_clients.is_empty() {
+            let started_at = Instant::now();
+            wait_for_cfrag_providers(&peer_clients, &reverie.id, total_frags).await?;
+            report.distribution.push(started_at.elapsed());
+        }
+
+        // only the target vessel can decrypt the cfrags re-encrypted to its key
+        let target_vessel_client = find_target_vessel(
+            std::iter::once(&client).chain(peer_clients.iter()),
+            &serde_json::to_value(&reverie.target_public_key)?,
+        ).await.ok_or_else(|| anyhow!(
+            "Target vessel of reverie {} is not among the benchmarked nodes, add its RPC port to --ports",
+            reverie.id
+        ))?;
+
+        let access_key = AccessKey::from(signer.sign_hash(&keccak256(reverie.id.as_bytes())).await?);
+        let started_at = Instant::now();
+        let _decrypted: Value = target_vessel_client.request(
+            "decrypt_reverie",
+            rpc_params![
+                reverie.id.clone(),
+                ReverieType::Memory,
+                access_key
+            ]
+        ).await?;
+        report.reconstruction.push(started_at.elapsed());
+
+        info!("Benchmark iteration {}/{}: reverie {}", iteration + 1, iterations, reverie.id);
+    }
+
+    Ok(report)
+}
+
+/// Polls node states until `total_frags` nodes hold a cfrag of the reverie
+async fn wait_for_cfrag_providers(
+    peer_clients: &[HttpClient],
+    reverie_id: &str,
+    total_frags: usize,
+) -> Result<()> {
+    let started_at = Instant::now();
+    loop {
+        let mut providers = 0;
+        for client in peer_clients {
+            // nodes that are unreachable hold no cfrags we can reconstruct from
+            if let Ok(state) = client.request::<Value, _>("get_node_state", rpc_params![]).await {
+                if holds_cfrag(&state, reverie_id) {
+                    providers += 1;
+                }
+            }
+        }
+        if providers >= total_frags {
+            return Ok(())
+        }
+        if started_at.elapsed() > DISTRIBUTION_TIMEOUT {
+            return Err(anyhow!(
+                "Only {}/{} kfrag providers of {} hold a cfrag after {:?}",
+                providers,
+                total_frags,
+                reverie_id,
+                DISTRIBUTION_TIMEOUT
+            ))
+        }
+        time::sleep(DISTRIBUTION_POLL_INTERVAL).await;
+    }
+}
+
+/// The client of the node whose umbral public key is `target_public_key`
+async fn find_target_vessel<'a>(
+    clients: impl Iterator<Item = &'a HttpClient>,
+    target_public_key: &Value,
+) -> Option<&'a HttpClient> {
+    for client in clients {
+        if let Ok(state) = client.request::<Value, _>("get_node_state", rpc_params![]).await {
+            if has_umbral_public_key(&state, target_public_key) {
+                return Some(client)
+            }
+        }
+    }
+    None
+}
+
+fn has_umbral_public_key(node_state: &Value, umbral_public_key: &Value) -> bool {
+    node_state["_umbral_public_key"] == *umbral_public_key
+}
+
+fn holds_cfrag(node_state: &Value, reverie_id: &str) -> bool {
+    node_state["peer_manager"]["1_cfrags_summary"]
+        .as_array()
+        .map(|cfrags| cfrags.iter().any(|cfrag| cfrag["reverie_id"] == reverie_id))
+        .unwrap_or(false)
+}
+
+fn keccak256(bytes: &[u8]) -> B256 {
+    B256::from_slice(Keccak256::digest(bytes).as_slice())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn latency_percentiles_use_nearest_rank() {
+        let samples: Vec<Duration> = (1..=100).rev().map(Duration::from_millis).collect();
+        let percentiles = LatencyPercentiles::from_samples(&samples).unwrap();
+        assert_eq!(percentiles.p50, Duration::from_millis(50));
+        assert_eq!(percentiles.p95, Duration::from_millis(95));
+        assert_eq!(percentiles.p99, Duration::from_millis(99));
+
+        // a single iteration is every percentile
+        let single = LatencyPercentiles::from_samples(&[Duration::from_millis(7)]).unwrap();
+        assert_eq!(single.p50, Duration::from_millis(7));
+        assert_eq!(single.p99, Duration::from_millis(7));
+
+        assert!(LatencyPercentiles::from_samples(&[]).is_none());
+    }
+
+    #[test]
+    fn report_table_marks_unmeasured_stages() {
+        let report = BenchmarkReport {
+            spawn: vec![Duration::from_millis(120)],
+            distribution: vec![],
+            reconstruction: vec![Duration::from_millis(80)],
+        };
+        let table = report.format_table();
+        assert!(table.contains("120ms"), "{}", table);
+        assert!(table.lines().any(|row| row.starts_with("distribution") && row.contains('-')), "{}", table);
+    }
+
+    #[test]
+    fn holds_cfrag_matches_reverie_id() {
+        let state = json!({
+            "peer_manager": { "1_cfrags_summary": [{ "reverie_id": "reverie_1234", "frag_num": 0 }] }
+        });
+        assert!(holds_cfrag(&state, "reverie_1234"));
+        assert!(!holds_cfrag(&state, "reverie_5678"));
+        assert!(!holds_cfrag(&json!({}), "reverie_1234"));
+    }
+
+    #[test]
+    fn target_vessel_is_matched_by_umbral_public_key() {
+        let target_public_key = json!("02a1b2c3");
+        let state = json!({ "_node_name": "node2", "_umbral_public_key": "02a1b2c3" });
+        assert!(has_umbral_public_key(&state, &target_public_key));
+        assert!(!has_umbral_public_key(&state, &json!("03d4e5f6")));
+        assert!(!has_umbral_public_key(&json!({}), &target_public_key));
+    }
+}
@@ -1,6 +1,7 @@
 use clap::Parser;
 use serde::{Deserialize, Serialize};
 use std::net::SocketAddr;
+use std::path::PathBuf;
 use p2p_network::types::AccessKey;
 
 #[derive(Parser, Serialize, Deserialize, Clone, Debug)]
@@ -24,17 +25,43 @@ pub enum CliArgument {
         agent_nonce: usize,
     },
 
+    /// Spawns an agent from exactly one of the agent secrets sources
+    #[clap(group(clap::ArgGroup::new("agent_secrets").required(true).args([
+        "agent_secrets_json",
+        "agent_secrets_file",
+        "agent_secrets_stdin",
+        "secret_key_seed",
+    ])))]
     SpawnAgent {
         #[clap(long)]
         threshold: usize,
         #[clap(long)]
         total_frags: usize,
-        // #[clap(long)]
-        // secret_key_seed: usize,
+
+        /// AgentSecretsJson passed inline. It is visible in the process list,
+        /// so prefer --agent-secrets-file or --agent-secrets-stdin
+        #[clap(long)]
+        agent_secrets_json: Option<String>,
+
+        /// Path to an AgentSecretsJson file
+        #[clap(long)]
+        agent_secrets_file: Option<PathBuf>,
+
+        /// Read the AgentSecretsJson from stdin
+        #[clap(long)]
+        agent_secrets_stdin: bool,
+
+        /// Spawns a test agent with dummy secrets (1 = auron, 2 = beatrix, ...), for local networks only
+        #[clap(long)]
+        secret_key_seed: Option<usize>,
     },
 
     TriggerNodeFailure,
 
+    /// Lists connected peers with their vessel status, heartbeat recency and reputation.
+    /// Calls the `list_connected_peers` RPC; `get_connected_peers` only returns peer ids
+    GetConnectedPeers,
+
     GetNodeStates {
         #[clap(long, value_parser, num_args = 1.., value_delimiter = ',')]
         ports: Vec<String>,
@@ -60,6 +87,14 @@ pub enum CliArgument {
         /// Verifying public key
         #[clap(long)]
         verifying_public_key: String,
+
+        /// Signature over the spawn challenge by the verifying key, proving ownership of it
+        #[clap(long)]
+        signature: Option<AccessKey>,
+
+        /// Peer ids to place keyfrags on first, other peers are only used if there aren't enough
+        #[clap(long, value_parser, num_args = 1.., value_delimiter = ',')]
+        preferred_kfrag_providers: Option<Vec<String>>,
     },
 
     #[clap(name = "execute-with-memory-reverie")]
@@ -76,4 +111,40 @@ pub enum CliArgument {
         #[clap(long)]
         signature: AccessKey,
     },
+
+    /// Spawns and reconstructs dummy memory reveries, printing p50/p95/p99 latencies
+    /// of spawning, fragment distribution and reconstruction
+    Benchmark {
+        /// Minimum number of fragments needed for reconstruction
+        #[clap(long)]
+        threshold: usize,
+
+        /// Total number of fragments to create
+        #[clap(long)]
+        total_frags: usize,
+
+        /// Number of reveries to spawn and reconstruct
+        #[clap(long, default_value_t = 10)]
+        iterations: usize,
+
+        /// RPC ports of the network's nodes, polled to time fragment distribution and
+        /// to find the target vessel that reconstructs each reverie
+        #[clap(long, value_parser, num_args = 1.., value_delimiter = ',')]
+        ports: Option<Vec<String>>,
+    },
+
+    #[clap(name = "decrypt-reverie")]
+    DecryptReverie {
+        /// The ID of the reverie to decrypt
+        #[clap(long)]
+        reverie_id: String,
+
+        /// The type of the reverie (Memory, Agent, SovereignAgent)
+        #[clap(long)]
+        reverie_type: String,
+
+        /// AccessCondition: Signature required to access the reverie
+        #[clap(long)]
+        signature: AccessKey,
+    },
 }
@@ -1,6 +1,8 @@
+mod benchmark;
 mod commands;
 
 use std::collections::{HashMap, HashSet};
+use std::net::SocketAddr;
 use std::cmp::Ord;
 use itertools::Itertools;
 use commands::{Cmd, CliArgument};
@@ -23,6 +25,8 @@ use libp2p::PeerId;
 use serde_json::Value;
 use tracing::{info, warn, error};
 
+use runtime::llm::AgentSecretsSource;
+
 use rpc::rpc_client::{
     parse_ws_url,
     create_ws_rpc_client,
@@ -31,6 +35,7 @@ use rpc::rpc_client::{
 use p2p_network::{
     node_client::RestartReason,
     types::{
+        ConnectedPeer,
         NodeKeysWithVesselStatus,
         ReverieId,
         ReverieType,
@@ -56,7 +61,6 @@ async fn main() -> Result<()> {
 
     let cmd = Cmd::parse();
     let ip = cmd.rpc_server_address.ip();
-    let port = cmd.rpc_server_address.port();
 
     match cmd.argument {
 
@@ -79,7 +83,7 @@ async fn main() -> Result<()> {
             for (frag_num, peers) in peers_sorted_by_fragments {
                 let peer_names = peers.iter()
                     .map(|peer_id| get_node_name(peer_id))
-                    .collect::<Vec<&str>>();
+                    .collect::<Vec<String>>();
 
                 info!("Fragment({}): {:?}", format!("{}", frag_num).green(), peer_names);
             }
@@ -88,18 +92,26 @@ async fn main() -> Result<()> {
         CliArgument::SpawnAgent {
             threshold,
             total_frags,
+            agent_secrets_json,
+            agent_secrets_file,
+            agent_secrets_stdin,
+            secret_key_seed,
         } => {
 
-            let client = create_http_rpc_client(&cmd.rpc_server_address).await?;
-            // Read local AgentSecretJson file and send to node over a secure channel/TLS.
+            let agent_secrets_source = match (agent_secrets_json, agent_secrets_file, agent_secrets_stdin, secret_key_seed) {
+                (Some(json), ..) => AgentSecretsSource::Json(json),
+                (_, Some(path), ..) => AgentSecretsSource::File(path),
+                (_, _, true, _) => AgentSecretsSource::Stdin,
+                (_, _, _, Some(seed)) => AgentSecretsSource::Seed(seed),
+                // clap already requires one of the agent_secrets args
+                _ => return Err(anyhow!("No agent secrets source given")),
+            };
+            // Read before connecting, so a bad secrets source fails fast.
             // TODO: user will send this over a secure channel and commit the hash onchain
             // along with some payment.
-            // TODO: use port as seed for prototyping, replace
-            let secret_key_seed = port % 10;
+            let agent_secrets_json = agent_secrets_source.read()?;
 
-            let agent_secrets_json = runtime::llm::read_agent_secrets(
-                secret_key_seed as usize
-            );
+            let client = create_http_rpc_client(&cmd.rpc_server_address).await?;
 
             let NodeKeysWithVesselStatus {
                 peer_id,
@@ -142,15 +154,24 @@ async fn main() -> Result<()> {
             }
         }
 
+        CliArgument::GetConnectedPeers => {
+            let client = create_http_rpc_client(&cmd.rpc_server_address).await?;
+            let connected_peers: Vec<ConnectedPeer> = client.request(
+                "list_connected_peers",
+                rpc_params![]
+            ).await?;
+
+            info!("{} connected peers:\n{}", connected_peers.len(), format_connected_peers(&connected_peers));
+        }
+
         CliArgument::GetNodeStates { ports } => {
 
             let mut clients: Vec<(u16, Client)> = vec![];
 
-            for port_str in ports {
-                let port = port_str.parse::<u16>().unwrap();
-                match create_ws_rpc_client(&cmd.rpc_server_address).await {
-                    Ok(c) => clients.push((port, c)),
-                    Err(_) => error!("Cannot connect to port {}, skipping.", port_str),
+            for rpc_address in rpc_addresses_for_ports(&cmd.rpc_server_address, &ports) {
+                match create_ws_rpc_client(&rpc_address).await {
+                    Ok(c) => clients.push((rpc_address.port(), c)),
+                    Err(_) => error!("Cannot connect to port {}, skipping.", rpc_address.port()),
                 }
             }
 
@@ -226,6 +247,8 @@ async fn main() -> Result<()> {
             threshold,
             total_frags,
             verifying_public_key,
+            signature,
+            preferred_kfrag_providers,
         } => {
             let client = create_http_rpc_client(&cmd.rpc_server_address).await?;
 
@@ -239,7 +262,9 @@ async fn main() -> Result<()> {
                     memory_secrets,
                     threshold,
                     total_frags,
-                    verifying_public_key
+                    verifying_public_key,
+                    signature,
+                    preferred_kfrag_providers
                 ]
             ).await?;
 
@@ -266,12 +291,7 @@ async fn main() -> Result<()> {
             let reverie_id = ReverieId::from(reverie_id);
 
             // Parse reverie_type from string
-            let reverie_type = match reverie_type.as_str() {
-                "Memory" => ReverieType::Memory,
-                "Agent" => ReverieType::Agent(ReverieNameWithNonce("default".to_string(), 0)),
-                "SovereignAgent" => ReverieType::SovereignAgent(ReverieNameWithNonce("default".to_string(), 0)),
-                _ => return Err(anyhow!("Invalid reverie type: {}", reverie_type))
-            };
+            let reverie_type = parse_reverie_type(&reverie_type)?;
 
             client.request::<(), _>(
                 "execute_with_memory_reverie",
@@ -284,8 +304,145 @@ async fn main() -> Result<()> {
 
             info!("{}", format!("Successfully executed memory reverie").green());
         }
+
+        CliArgument::Benchmark {
+            threshold,
+            total_frags,
+            iterations,
+            ports,
+        } => {
+            if threshold > total_frags {
+                return Err(anyhow!("Threshold must be less than or equal to total fragments"));
+            }
+            let peer_rpc_addresses = rpc_addresses_for_ports(
+                &cmd.rpc_server_address,
+                &ports.unwrap_or_default()
+            );
+
+            let report = benchmark::run_benchmark(
+                &cmd.rpc_server_address,
+                &peer_rpc_addresses,
+                threshold,
+                total_frags,
+                iterations,
+            ).await?;
+
+            info!("{}
+{}",
+                format!("Benchmark of {} iterations, threshold {} of {} frags:", iterations, threshold, total_frags).green(),
+                report.format_table()
+            );
+        }
+
+        CliArgument::DecryptReverie {
+            reverie_id,
+            reverie_type,
+            signature,
+        } => {
+            let client = create_http_rpc_client(&cmd.rpc_server_address).await?;
+
+            let reverie_id = ReverieId::from(reverie_id);
+            let reverie_type = parse_reverie_type(&reverie_type)?;
+
+            let decrypted_reverie: Value = client.request(
+                "decrypt_reverie",
+                rpc_params![
+                    reverie_id.clone(),
+                    reverie_type,
+                    signature
+                ]
+            ).await?;
+
+            info!("{}\n{}",
+                format!("Decrypted Reverie {}:", reverie_id).green(),
+                serde_json::to_string_pretty(&decrypted_reverie)?
+            );
+        }
     }
 
     Ok(())
 }
 
+
+/// Parses a ReverieType from its CLI name (Memory, McpPlugin, Agent, SovereignAgent)
+fn parse_reverie_type(reverie_type: &str) -> Result<ReverieType> {
+    match reverie_type {
+        "Memory" => Ok(ReverieType::Memory),
+        "McpPlugin" => Ok(ReverieType::McpPlugin),
+        "Agent" => Ok(ReverieType::Agent(ReverieNameWithNonce("default".to_string(), 0))),
+        "SovereignAgent" => Ok(ReverieType::SovereignAgent(ReverieNameWithNonce("default".to_string(), 0))),
+        _ => Err(anyhow!("Invalid reverie type: {}", reverie_type))
+    }
+}
+
+/// Formats connected peers as a table, one row per peer
+fn format_connected_peers(connected_peers: &[ConnectedPeer]) -> String {
+    let mut table = format!(
+        "{:<16} {:<14} {:<18} {:>14} {:>10}",
+        "NODE", "PEER_ID", "VESSEL_STATUS", "HEARTBEAT_AGO", "REPUTATION"
+    );
+    for peer in connected_peers {
+        let vessel_status = peer.vessel_status
+            .map(|status| format!("{:?}", status))
+            .unwrap_or_else(|| "-".to_string());
+        let last_heartbeat = peer.last_heartbeat_secs
+            .map(|secs| format!("{}s", secs))
+            .unwrap_or_else(|| "-".to_string());
+        table.push_str(&format!(
+            "\n{:<16} {:<14} {:<18} {:>14} {:>10.2}",
+            peer.node_name,
+            short_peer_id(&peer.peer_id),
+            vessel_status,
+            last_heartbeat,
+            peer.reputation
+        ));
+    }
+    table
+}
+
+/// Swaps each port into the base RPC address so every node is queried separately.
+/// Ports that fail to parse are skipped.
+fn rpc_addresses_for_ports(base: &SocketAddr, ports: &[String]) -> Vec<SocketAddr> {
+    ports.iter()
+        .filter_map(|port_str| match port_str.parse::<u16>() {
+            Ok(port) => {
+                let mut rpc_address = *base;
+                rpc_address.set_port(port);
+                Some(rpc_address)
+            }
+            Err(_) => {
+                error!("Invalid port {}, skipping.", port_str);
+                None
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rpc_addresses_for_ports_yields_distinct_addresses() {
+        let base: SocketAddr = "127.0.0.1:9901".parse().unwrap();
+        let ports = vec!["9901".to_string(), "9902".to_string(), "9903".to_string()];
+
+        let addresses = rpc_addresses_for_ports(&base, &ports);
+
+        assert_eq!(addresses.len(), ports.len());
+        assert_eq!(addresses.iter().collect::<HashSet<_>>().len(), ports.len());
+        for (address, port_str) in addresses.iter().zip(ports.iter()) {
+            assert_eq!(address.ip(), base.ip());
+            assert_eq!(address.port().to_string(), *port_str);
+        }
+    }
+
+    #[test]
+    fn rpc_addresses_for_ports_skips_invalid_ports() {
+        let base: SocketAddr = "127.0.0.1:9901".parse().unwrap();
+        let ports = vec!["9902".to_string(), "not_a_port".to_string()];
+
+        let addresses = rpc_addresses_for_ports(&base, &ports);
+        assert_eq!(addresses, vec!["127.0.0.1:9902".parse::<SocketAddr>().unwrap()]);
+    }
+}
@@ -6,7 +6,8 @@ use std::{
 };
 use serde::{Deserialize, Serialize};
 use serde_json::json;
-use tracing::{info, error, warn};
+use tokio::sync::Notify;
+use tracing::{debug, info, error, warn};
 
 use axum::{
     extract::{State, ConnectInfo, Request},
@@ -19,11 +20,11 @@ use axum::{
     body::{Body, to_bytes},
 };
 use axum_server::tls_rustls::RustlsConfig;
-use std::path::PathBuf;
 use std::fs;
 
-use crate::config::{API_SERVER_CERT_PATH, API_SERVER_KEY_PATH};
+use crate::config::{CertConfig, CertRotationCheck, check_cert_rotation};
 use crate::config::EnvVars;
+use crate::body_limits::is_length_limit_error;
 
 // Imports for signature verification
 use ed25519_dalek::VerifyingKey as EdVerifyingKey;
@@ -70,6 +71,18 @@ impl ApiKeyPayload {
 pub struct ApiState {
     key_store: ApiKeyStore,
     p2p_node_public_key: Arc<EdVerifyingKey>,
+    max_body_size: usize,
+    timestamp_window_secs: i64,
+}
+
+/// Body of an `/add_api_key` request. An existing key for the same reverie_id is only
+/// replaced when `overwrite` is set, so a reused reverie_id can't silently clobber it.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct AddApiKeyRequest {
+    #[serde(flatten)]
+    pub payload: ApiKeyPayload,
+    #[serde(default)]
+    pub overwrite: bool,
 }
 
 #[derive(Deserialize, Serialize, Debug)]
@@ -102,50 +115,162 @@ pub fn generate_digest_hash(
     canonical_string
 }
 
-// Updated middleware to verify node signature
-async fn verify_node_request(
-    State(state): State<ApiState>,
-    headers: HeaderMap,
-    request: Request,
-    next: Next,
-) -> Result<Response, StatusCode> {
-    info!("Verifying node request signature...");
+/// Default max seconds between a signed request's X-Node-Timestamp and now, either way
+pub const DEFAULT_NODE_REQUEST_TIMESTAMP_WINDOW_SECS: u64 = 60;
+
+/// Why a node request failed signature verification. Responds with the status code
+/// and a JSON body carrying a machine-readable `reason`, so callers can tell the failures apart.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NodeAuthError {
+    MissingSignatureHeader,
+    MissingTimestampHeader,
+    InvalidTimestamp,
+    StaleTimestamp,
+    FutureTimestamp,
+    MalformedSignature,
+    InvalidSignatureLength,
+    InvalidSignature,
+    PayloadTooLarge,
+    InternalError,
+}
+
+impl NodeAuthError {
+    pub fn reason(&self) -> &'static str {
+        match self {
+            NodeAuthError::MissingSignatureHeader => "missing_signature_header",
+            NodeAuthError::MissingTimestampHeader => "missing_timestamp_header",
+            NodeAuthError::InvalidTimestamp => "invalid_timestamp",
+            NodeAuthError::StaleTimestamp => "stale_timestamp",
+            NodeAuthError::FutureTimestamp => "future_timestamp",
+            NodeAuthError::MalformedSignature => "malformed_signature",
+            NodeAuthError::InvalidSignatureLength => "invalid_signature_length",
+            NodeAuthError::InvalidSignature => "invalid_signature",
+            NodeAuthError::PayloadTooLarge => "payload_too_large",
+            NodeAuthError::InternalError => "internal_error",
+        }
+    }
+
+    pub fn status_code(&self) -> StatusCode {
+        match self {
+            NodeAuthError::MissingSignatureHeader
+            | NodeAuthError::MissingTimestampHeader
+            | NodeAuthError::StaleTimestamp
+            | NodeAuthError::FutureTimestamp
+            | NodeAuthError::InvalidSignature => StatusCode::UNAUTHORIZED,
+            NodeAuthError::InvalidTimestamp
+            | NodeAuthError::MalformedSignature
+            | NodeAuthError::InvalidSignatureLength => StatusCode::BAD_REQUEST,
+            NodeAuthError::PayloadTooLarge => StatusCode::PAYLOAD_TOO_LARGE,
+            NodeAuthError::InternalError => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    fn message(&self) -> &'static str {
+        match self {
+            NodeAuthError::MissingSignatureHeader => "Missing X-Node-Signature header",
+            NodeAuthError::MissingTimestampHeader => "Missing X-Node-Timestamp header",
+            NodeAuthError::InvalidTimestamp => "Invalid X-Node-Timestamp header",
+            NodeAuthError::StaleTimestamp => "Timestamp too far in the past",
+            NodeAuthError::FutureTimestamp => "Timestamp too far in the future",
+            NodeAuthError::MalformedSignature => "Invalid Base64 signature header",
+            NodeAuthError::InvalidSignatureLength => "Invalid signature length, expected 64 bytes",
+            NodeAuthError::InvalidSignature => "Invalid signature",
+            NodeAuthError::PayloadTooLarge => "Request body exceeds max body size",
+            NodeAuthError::InternalError => "Internal server error",
+        }
+    }
+}
 
+impl IntoResponse for NodeAuthError {
+    fn into_response(self) -> Response {
+        (self.status_code(), Json(json!({
+            "error": self.message(),
+            "reason": self.reason(),
+        }))).into_response()
+    }
+}
+
+/// Reads the X-Node-Timestamp and X-Node-Signature headers of a node request,
+/// rejecting timestamps more than `timestamp_window_secs` before or after `now`
+fn parse_signature_headers(
+    headers: &HeaderMap,
+    now: i64,
+    timestamp_window_secs: i64,
+) -> Result<(&str, ed25519_dalek::Signature), NodeAuthError> {
     // 1. Extract Headers (Signature and Timestamp)
     let signature_b64 = headers.get("X-Node-Signature")
         .and_then(|v| v.to_str().ok())
         .ok_or_else(|| {
             warn!("Missing X-Node-Signature header");
-            StatusCode::UNAUTHORIZED
+            NodeAuthError::MissingSignatureHeader
         })?;
     let timestamp_str = headers.get("X-Node-Timestamp")
         .and_then(|v| v.to_str().ok())
         .ok_or_else(|| {
             warn!("Missing X-Node-Timestamp header");
-            StatusCode::UNAUTHORIZED
+            NodeAuthError::MissingTimestampHeader
         })?;
 
     // 2. Parse Timestamp and check window
     let timestamp = timestamp_str.parse::<i64>().map_err(|_| {
         warn!("Invalid X-Node-Timestamp header: {}", timestamp_str);
-        StatusCode::BAD_REQUEST
+        NodeAuthError::InvalidTimestamp
     })?;
-    let now = chrono::Utc::now().timestamp();
-    if (now - timestamp).abs() > 60 { // 60 second window
-        warn!("Timestamp outside acceptable window: {} (now={})", timestamp, now);
-        return Err(StatusCode::UNAUTHORIZED);
+    // Rejected separately, a future timestamp usually means the node's clock is ahead
+    if now.saturating_sub(timestamp) > timestamp_window_secs {
+        warn!("Timestamp {} is {}s in the past, outside the {}s window (now={})",
+            timestamp, now.saturating_sub(timestamp), timestamp_window_secs, now);
+        return Err(NodeAuthError::StaleTimestamp);
+    }
+    if timestamp.saturating_sub(now) > timestamp_window_secs {
+        warn!("Timestamp {} is {}s in the future, outside the {}s window (now={})",
+            timestamp, timestamp.saturating_sub(now), timestamp_window_secs, now);
+        return Err(NodeAuthError::FutureTimestamp);
     }
 
     // 3. Decode Signature
     let signature_bytes = base64_standard.decode(signature_b64).map_err(|_| {
         warn!("Invalid Base64 signature header");
-        StatusCode::BAD_REQUEST
+        NodeAuthError::MalformedSignature
     })?;
     let signature_array: [u8; 64] = signature_bytes.as_slice().try_into().map_err(|_| {
         warn!("Invalid signature length: expected 64 bytes, got {}", signature_bytes.len());
-        StatusCode::BAD_REQUEST
+        NodeAuthError::InvalidSignatureLength
     })?;
-    let signature = ed25519_dalek::Signature::from_bytes(&signature_array);
+
+    Ok((timestamp_str, ed25519_dalek::Signature::from_bytes(&signature_array)))
+}
+
+/// Verifies the node's signature over a request's canonical string
+fn verify_signature(
+    p2p_node_public_key: &EdVerifyingKey,
+    method: &str,
+    path: &str,
+    timestamp_str: &str,
+    body_bytes: &[u8],
+    signature: &ed25519_dalek::Signature,
+) -> Result<(), NodeAuthError> {
+    let canonical_string = generate_digest_hash(method, path, timestamp_str, body_bytes);
+    p2p_node_public_key.verify(canonical_string.as_bytes(), signature).map_err(|_| {
+        warn!("Invalid signature for request: {} {}", method, path);
+        NodeAuthError::InvalidSignature
+    })
+}
+
+// Updated middleware to verify node signature
+async fn verify_node_request(
+    State(state): State<ApiState>,
+    headers: HeaderMap,
+    request: Request,
+    next: Next,
+) -> Result<Response, NodeAuthError> {
+    info!("Verifying node request signature...");
+
+    let (timestamp_str, signature) = parse_signature_headers(
+        &headers,
+        chrono::Utc::now().timestamp(),
+        state.timestamp_window_secs
+    )?;
 
     // 4. Reconstruct Signed Payload
     let method = request.method().clone();
@@ -153,22 +278,24 @@ async fn verify_node_request(
     // clone extensions and headers before consuming the request
     let extensions = request.extensions().clone();
     let original_headers = request.headers().clone();
-    let body_bytes = to_bytes(request.into_body(), usize::MAX).await.map_err(|e| {
+    let body_bytes = to_bytes(request.into_body(), state.max_body_size).await.map_err(|e| {
+        if is_length_limit_error(&e) {
+            warn!("Request body exceeds max body size of {} bytes", state.max_body_size);
+            return NodeAuthError::PayloadTooLarge;
+        }
         error!("Failed to read request body bytes for signature verification: {}", e);
-        StatusCode::INTERNAL_SERVER_ERROR
+        NodeAuthError::InternalError
     })?;
-    let canonical_string = generate_digest_hash(
+
+    // 5. Verify Signature
+    verify_signature(
+        &state.p2p_node_public_key,
         method.as_str(),
         &path,
         timestamp_str,
-        &body_bytes
-    );
-
-    // 5. Verify Signature
-    if state.p2p_node_public_key.verify(canonical_string.as_bytes(), &signature).is_err() {
-        warn!("Invalid signature for request: {} {}", method, path);
-        return Err(StatusCode::UNAUTHORIZED);
-    }
+        &body_bytes,
+        &signature
+    )?;
     info!("Node request signature verified successfully for {} {}", method, path);
 
     // 6. Reconstruct the request
@@ -185,7 +312,7 @@ async fn verify_node_request(
         .body(Body::from(body_bytes))
         .map_err(|e| {
             error!("Failed to reconstruct request: {}", e);
-            StatusCode::INTERNAL_SERVER_ERROR
+            NodeAuthError::InternalError
         })?;
 
     // Pass the reconstructed request to the next middleware/handler
@@ -195,12 +322,27 @@ async fn verify_node_request(
 async fn add_api_key(
     State(key_store): State<ApiKeyStore>,
     ConnectInfo(addr): ConnectInfo<SocketAddr>,
-    Json(payload): Json<ApiKeyPayload>,
+    Json(AddApiKeyRequest { payload, overwrite }): Json<AddApiKeyRequest>,
 ) -> impl IntoResponse {
     info!("Received request to add/update API key: {} from {}", &payload.reverie_id, addr);
 
     match key_store.write() {
         Ok(mut store) => {
+            if let Some(existing) = store.get(&payload.reverie_id) {
+                // Re-sending the same key is a no-op
+                let unchanged = existing.api_key_type == payload.api_key_type
+                    && existing.api_key == payload.api_key
+                    && existing.spender == payload.spender
+                    && existing.spender_type == payload.spender_type;
+                if !unchanged && !overwrite {
+                    warn!("API key already exists for: {}, not overwriting", payload.reverie_id);
+                    return (StatusCode::CONFLICT, Json(json!({
+                        "error": "API key already exists for reverie_id",
+                        "reverie_id": payload.reverie_id,
+                        "api_key_type": existing.api_key_type,
+                    })))
+                }
+            }
             store.insert(payload.reverie_id.clone(), payload.clone());
             info!("Successfully stored API key: {}", payload.reverie_id);
             (StatusCode::OK, Json(json!({ "status": "success" })))
@@ -247,27 +389,31 @@ pub async fn run_internal_api_server(
     key_store: ApiKeyStore,
     env_vars: Arc<EnvVars>,
     p2p_node_public_key: Arc<EdVerifyingKey>, // Add the new parameter
+    ca_rotation_due: Arc<Notify>, // notified when the proxy must restart to rotate its CA
 ) -> Result<()> {
 
     let addr = SocketAddr::from(([0, 0, 0, 0], env_vars.INTERNAL_API_KEY_SERVER_PORT));
     info!("Internal API server attempting to start on {}", addr);
 
     info!("Loading standard TLS configuration for API server");
+    let cert_config = CertConfig::from_env_vars(&env_vars);
     let tls_config = RustlsConfig::from_pem_file(
-        PathBuf::from(API_SERVER_CERT_PATH),
-        PathBuf::from(API_SERVER_KEY_PATH)
+        cert_config.api_server_cert_path.clone(),
+        cert_config.api_server_key_path.clone()
     )
     .await
     .map_err(|e| anyhow!("Failed to load server TLS config from PEM files: {}", e))?;
     info!("Standard TLS configuration loaded.");
+    tokio::spawn(rotate_certs_periodically(cert_config.clone(), tls_config.clone(), ca_rotation_due));
 
     // Log the server certificate that will be used by the internal API server
-    match fs::read_to_string(API_SERVER_CERT_PATH) {
+    let cert_path = cert_config.api_server_cert_path.display();
+    match fs::read_to_string(&cert_config.api_server_cert_path) {
         Ok(cert_pem) => {
-            info!("Internal API Server WILL USE server certificate ({}):\n{}", API_SERVER_CERT_PATH, cert_pem);
+            info!("Internal API Server WILL USE server certificate ({}):\n{}", cert_path, cert_pem);
         }
         Err(e) => {
-            warn!("Could not read back server certificate for logging {}: {}", API_SERVER_CERT_PATH, e);
+            warn!("Could not read back server certificate for logging {}: {}", cert_path, e);
         }
     }
 
@@ -275,6 +421,8 @@ pub async fn run_internal_api_server(
     let shared_state_for_auth = ApiState {
         key_store: key_store.clone(),
         p2p_node_public_key: p2p_node_public_key, // Use the passed-in key
+        max_body_size: env_vars.MAX_BODY_SIZE_BYTES,
+        timestamp_window_secs: i64::try_from(env_vars.NODE_REQUEST_TIMESTAMP_WINDOW_SECS).unwrap_or(i64::MAX),
     };
 
     // Router for authenticated routes
@@ -301,4 +449,192 @@ pub async fn run_internal_api_server(
         .map_err(|e| anyhow!("Internal API server failed: {}", e))?;
 
     Ok(())
-}
\ No newline at end of file
+}
+
+/// Re-checks the certificates every `rotation_check_interval_secs`, hot-reloading the API server
+/// certificate when it is rotated. Stops once the CA needs rotating, after notifying `ca_rotation_due`.
+async fn rotate_certs_periodically(
+    cert_config: CertConfig,
+    tls_config: RustlsConfig,
+    ca_rotation_due: Arc<Notify>,
+) {
+    let mut interval = tokio::time::interval(std::time::Duration::from_secs(cert_config.rotation_check_interval_secs));
+    // The first tick completes immediately, and the certs were just checked on startup
+    interval.tick().await;
+    loop {
+        interval.tick().await;
+        match check_cert_rotation(&cert_config) {
+            Ok(CertRotationCheck::Current) => {
+                debug!("Certificates are not due for rotation");
+            }
+            Ok(CertRotationCheck::ApiServerCertRotated) => {
+                match tls_config.reload_from_pem_file(
+                    cert_config.api_server_cert_path.clone(),
+                    cert_config.api_server_key_path.clone()
+                ).await {
+                    Ok(()) => info!("Reloaded rotated API server certificate from {}", cert_config.api_server_cert_path.display()),
+                    Err(e) => error!("Failed to reload rotated API server certificate: {}", e),
+                }
+            }
+            Ok(CertRotationCheck::CaRotationDue) => {
+                warn!("CA certificate at {} is due for rotation, restarting the proxy", cert_config.ca_cert_path.display());
+                ca_rotation_due.notify_one();
+                return;
+            }
+            Err(e) => error!("Certificate rotation check failed: {}", e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn add_request(api_key_type: &str, api_key: &str, overwrite: bool) -> AddApiKeyRequest {
+        AddApiKeyRequest {
+            payload: ApiKeyPayload::new(
+                "reverie_1234".to_string(),
+                api_key_type.to_string(),
+                api_key.to_string(),
+                "spender_1".to_string(),
+                "eth".to_string(),
+            ),
+            overwrite,
+        }
+    }
+
+    async fn send_add_api_key(key_store: &ApiKeyStore, request: AddApiKeyRequest) -> (StatusCode, serde_json::Value) {
+        let addr = SocketAddr::from(([127, 0, 0, 1], 7070));
+        let response = add_api_key(
+            State(key_store.clone()),
+            ConnectInfo(addr),
+            Json(request)
+        ).await.into_response();
+        let status = response.status();
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, serde_json::from_slice(&body).unwrap())
+    }
+
+    #[tokio::test]
+    async fn add_api_key_rejects_conflicting_insert_unless_overwrite() {
+        let key_store: ApiKeyStore = Arc::new(RwLock::new(HashMap::new()));
+
+        let (status, _) = send_add_api_key(&key_store, add_request("ANTHROPIC_API_KEY", "sk-ant-1", false)).await;
+        assert_eq!(status, StatusCode::OK);
+        // resending the same key is idempotent
+        let (status, _) = send_add_api_key(&key_store, add_request("ANTHROPIC_API_KEY", "sk-ant-1", false)).await;
+        assert_eq!(status, StatusCode::OK);
+
+        let (status, body) = send_add_api_key(&key_store, add_request("OPENAI_API_KEY", "sk-openai-1", false)).await;
+        assert_eq!(status, StatusCode::CONFLICT);
+        assert_eq!(body["api_key_type"], "ANTHROPIC_API_KEY");
+        assert_eq!(key_store.read().unwrap()["reverie_1234"].api_key, "sk-ant-1");
+
+        let (status, _) = send_add_api_key(&key_store, add_request("OPENAI_API_KEY", "sk-openai-1", true)).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(key_store.read().unwrap()["reverie_1234"].api_key, "sk-openai-1");
+    }
+
+    fn signed_headers(signing_key: &ed25519_dalek::SigningKey, timestamp: i64, body: &[u8]) -> HeaderMap {
+        use ed25519_dalek::Signer;
+        let timestamp_str = timestamp.to_string();
+        let canonical_string = generate_digest_hash("POST", "/add_api_key", &timestamp_str, body);
+        let signature = signing_key.sign(canonical_string.as_bytes());
+        let mut headers = HeaderMap::new();
+        headers.insert("X-Node-Signature", base64_standard.encode(signature.to_bytes()).parse().unwrap());
+        headers.insert("X-Node-Timestamp", timestamp_str.parse().unwrap());
+        headers
+    }
+
+    async fn error_body(error: NodeAuthError) -> (StatusCode, serde_json::Value) {
+        let response = error.into_response();
+        let status = response.status();
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, serde_json::from_slice(&body).unwrap())
+    }
+
+    const WINDOW_SECS: i64 = 60;
+
+    #[tokio::test]
+    async fn node_auth_failures_have_distinct_reason_codes() {
+        let signing_key = ed25519_dalek::SigningKey::from_bytes(&[7u8; 32]);
+        let now = 1_700_000_000;
+        let body = br#"{"reverie_id":"reverie_1234"}"#;
+
+        // missing header
+        let mut headers = signed_headers(&signing_key, now, body);
+        headers.remove("X-Node-Signature");
+        let error = parse_signature_headers(&headers, now, WINDOW_SECS).unwrap_err();
+        assert_eq!(error_body(error).await, (StatusCode::UNAUTHORIZED, json!({
+            "error": "Missing X-Node-Signature header",
+            "reason": "missing_signature_header",
+        })));
+
+        // stale timestamp
+        let headers = signed_headers(&signing_key, now - WINDOW_SECS - 1, body);
+        let error = parse_signature_headers(&headers, now, WINDOW_SECS).unwrap_err();
+        let (status, error_json) = error_body(error).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        assert_eq!(error_json["reason"], "stale_timestamp");
+
+        // malformed base64
+        let mut headers = signed_headers(&signing_key, now, body);
+        headers.insert("X-Node-Signature", "not base64!".parse().unwrap());
+        let error = parse_signature_headers(&headers, now, WINDOW_SECS).unwrap_err();
+        let (status, error_json) = error_body(error).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(error_json["reason"], "malformed_signature");
+
+        // wrong signature: signed by another key
+        let headers = signed_headers(&ed25519_dalek::SigningKey::from_bytes(&[8u8; 32]), now, body);
+        let (timestamp_str, signature) = parse_signature_headers(&headers, now, WINDOW_SECS).unwrap();
+        let error = verify_signature(
+            &signing_key.verifying_key(),
+            "POST",
+            "/add_api_key",
+            timestamp_str,
+            body,
+            &signature
+        ).unwrap_err();
+        let (status, error_json) = error_body(error).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        assert_eq!(error_json["reason"], "invalid_signature");
+
+        // correctly signed
+        let headers = signed_headers(&signing_key, now, body);
+        let (timestamp_str, signature) = parse_signature_headers(&headers, now, WINDOW_SECS).unwrap();
+        assert!(verify_signature(&signing_key.verifying_key(), "POST", "/add_api_key", timestamp_str, body, &signature).is_ok());
+    }
+
+    #[test]
+    fn timestamps_are_checked_against_the_configured_window() {
+        let signing_key = ed25519_dalek::SigningKey::from_bytes(&[7u8; 32]);
+        let now = 1_700_000_000;
+        let window_secs = 300;
+        let check = |timestamp: i64| {
+            parse_signature_headers(&signed_headers(&signing_key, timestamp, b""), now, window_secs)
+                .map(|_| ())
+        };
+
+        // in window, either side of now
+        assert_eq!(check(now), Ok(()));
+        assert_eq!(check(now - window_secs), Ok(()));
+        assert_eq!(check(now + window_secs), Ok(()));
+
+        assert_eq!(check(now - window_secs - 1), Err(NodeAuthError::StaleTimestamp));
+        assert_eq!(check(now + window_secs + 1), Err(NodeAuthError::FutureTimestamp));
+        assert_eq!(NodeAuthError::FutureTimestamp.reason(), "future_timestamp");
+    }
+
+    #[test]
+    fn add_api_key_request_defaults_to_no_overwrite() {
+        let request: AddApiKeyRequest = serde_json::from_value(json!({
+            "reverie_id": "reverie_1234",
+            "api_key_type": "ANTHROPIC_API_KEY",
+            "api_key": "sk-ant-1",
+            "spender": "spender_1",
+            "spender_type": "eth",
+        })).unwrap();
+        assert!(!request.overwrite);
+    }
+}
@@ -0,0 +1,108 @@
+use std::error::Error as StdError;
+use bytes::Bytes;
+use http_body::Body as HttpBody;
+use http_body_util::{BodyExt, Full, LengthLimitError, Limited};
+use hudsucker::{
+    hyper::{HeaderMap, Response, StatusCode, header::{CONTENT_LENGTH, CONTENT_TYPE}},
+    Body,
+};
+use serde_json::json;
+
+/// Default cap on request and response bodies buffered by the proxy (10 MiB)
+pub const DEFAULT_MAX_BODY_SIZE_BYTES: usize = 10 * 1024 * 1024;
+
+#[derive(Debug)]
+pub enum CollectBodyError {
+    /// Body exceeded the configured max size and was not buffered
+    TooLarge,
+    Other(Box<dyn StdError + Send + Sync>),
+}
+
+/// Declared Content-Length, if the header is present and valid
+pub fn content_length(headers: &HeaderMap) -> Option<usize> {
+    headers
+        .get(CONTENT_LENGTH)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse::<usize>().ok())
+}
+
+/// True if the declared Content-Length is over `max_bytes`, so the body can be rejected
+/// (or passed through) without reading it
+pub fn exceeds_declared_limit(headers: &HeaderMap, max_bytes: usize) -> bool {
+    content_length(headers).map_or(false, |len| len > max_bytes)
+}
+
+/// Buffers a body in memory, failing with `TooLarge` as soon as more than `max_bytes` are read
+pub async fn collect_limited<B>(body: B, max_bytes: usize) -> Result<Bytes, CollectBodyError>
+where
+    B: HttpBody<Data = Bytes>,
+    B::Error: Into<Box<dyn StdError + Send + Sync>>,
+{
+    match Limited::new(body, max_bytes).collect().await {
+        Ok(collected) => Ok(collected.to_bytes()),
+        Err(e) if is_length_limit_error(&*e) => Err(CollectBodyError::TooLarge),
+        Err(e) => Err(CollectBodyError::Other(e)),
+    }
+}
+
+/// Walks the error's source chain looking for a body length limit error
+pub fn is_length_limit_error(err: &(dyn StdError + 'static)) -> bool {
+    let mut current = Some(err);
+    while let Some(e) = current {
+        if e.downcast_ref::<LengthLimitError>().is_some() {
+            return true;
+        }
+        current = e.source();
+    }
+    false
+}
+
+/// HTTP 413 response returned to the client instead of forwarding an oversized request
+pub fn payload_too_large_response(max_bytes: usize) -> Response<Body> {
+    let body = json!({
+        "error": {
+            "type": "request_too_large",
+            "message": format!("Request body exceeds the proxy limit of {} bytes", max_bytes),
+        }
+    });
+    Response::builder()
+        .status(StatusCode::PAYLOAD_TOO_LARGE)
+        .header(CONTENT_TYPE, "application/json")
+        .body(Body::from(Full::new(Bytes::from(body.to_string()))))
+        .expect("Failed to build 413 response")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hudsucker::hyper::header::HeaderValue;
+
+    #[tokio::test]
+    async fn test_oversized_request_body_returns_413() {
+        let max_bytes = 16;
+        let body = Body::from(Full::new(Bytes::from(vec![b'a'; max_bytes + 1])));
+
+        match collect_limited(body, max_bytes).await {
+            Err(CollectBodyError::TooLarge) => {}
+            other => panic!("expected TooLarge, got {:?}", other),
+        }
+        assert_eq!(payload_too_large_response(max_bytes).status(), StatusCode::PAYLOAD_TOO_LARGE);
+
+        // Bodies within the limit are buffered as-is
+        let body = Body::from(Full::new(Bytes::from(vec![b'a'; max_bytes])));
+        let bytes = collect_limited(body, max_bytes).await.unwrap();
+        assert_eq!(bytes.len(), max_bytes);
+    }
+
+    #[test]
+    fn test_declared_content_length_over_limit() {
+        let mut headers = HeaderMap::new();
+        assert!(!exceeds_declared_limit(&headers, 16));
+
+        headers.insert(CONTENT_LENGTH, HeaderValue::from_static("16"));
+        assert!(!exceeds_declared_limit(&headers, 16));
+
+        headers.insert(CONTENT_LENGTH, HeaderValue::from_static("17"));
+        assert!(exceeds_declared_limit(&headers, 16));
+    }
+}
@@ -1,5 +1,17 @@
 use std::env;
 use tracing::info;
+use crate::body_limits::DEFAULT_MAX_BODY_SIZE_BYTES;
+use crate::tee_body::DEFAULT_TEE_FULL_BODY_MAX_BYTES;
+use crate::registration::{DEFAULT_KEY_REGISTRATION_MAX_ATTEMPTS, DEFAULT_KEY_REGISTRATION_INITIAL_BACKOFF_MS};
+use crate::api_key_delegation_server::DEFAULT_NODE_REQUEST_TIMESTAMP_WINDOW_SECS;
+use crate::delegation::{
+    DEFAULT_ANTHROPIC_DELEGATION_FLAG,
+    DEFAULT_OPENAI_DELEGATION_FLAG,
+    DEFAULT_DEEPSEEK_DELEGATION_FLAG,
+    DEFAULT_DELEGATION_ALLOWED_HOSTS,
+    parse_hosts,
+};
+use super::{DEFAULT_CERT_VALIDITY_DAYS, DEFAULT_CERT_ROTATE_BEFORE_DAYS, DEFAULT_CERT_ROTATION_CHECK_INTERVAL_SECS};
 
 #[derive(Debug, Clone)]
 #[allow(non_snake_case)]
@@ -7,6 +19,24 @@ pub struct EnvVars {
     pub REPORT_USAGE_URL: String,
     pub INTERNAL_API_KEY_SERVER_PORT: u16,
     pub HUDSUCKER_PROXY_PORT: u16,
+    pub CLIENT_RATE_LIMIT_PER_MINUTE: u32,
+    pub MAX_BODY_SIZE_BYTES: usize,
+    pub TEE_FULL_BODY_MAX_BYTES: usize,
+    pub CERT_VALIDITY_DAYS: u32,
+    pub CERT_ROTATE_BEFORE_DAYS: u32,
+    pub CERT_ROTATION_CHECK_INTERVAL_SECS: u64,
+    pub API_SERVER_TLS_CERT_PATH: Option<String>,
+    pub API_SERVER_TLS_KEY_PATH: Option<String>,
+    pub KEY_REGISTRATION_MAX_ATTEMPTS: u32,
+    pub KEY_REGISTRATION_INITIAL_BACKOFF_MS: u64,
+    pub LOG_REDACT_SECRETS: bool,
+    pub LOG_BODY_MAX_BYTES: Option<usize>,
+    pub ANTHROPIC_DELEGATION_FLAG: String,
+    pub OPENAI_DELEGATION_FLAG: String,
+    pub DEEPSEEK_DELEGATION_FLAG: String,
+    pub DELEGATION_ALLOWED_HOSTS: Vec<String>,
+    pub DELEGATION_BLOCK_UNLISTED_HOSTS: bool,
+    pub NODE_REQUEST_TIMESTAMP_WINDOW_SECS: u64,
 }
 
 #[allow(non_snake_case)]
@@ -37,10 +67,166 @@ impl EnvVars {
                 7666
             });
 
+        // Max delegated requests per minute for each client, 0 disables rate limiting
+        let CLIENT_RATE_LIMIT_PER_MINUTE = env::var("CLIENT_RATE_LIMIT_PER_MINUTE")
+            .ok()
+            .and_then(|n| n.parse::<u32>().ok())
+            .unwrap_or_else(|| {
+                info!("CLIENT_RATE_LIMIT_PER_MINUTE not set or invalid, using default: 60");
+                60
+            });
+
+        // Max request/response body size buffered by the proxy and internal API server
+        let MAX_BODY_SIZE_BYTES = env::var("MAX_BODY_SIZE_BYTES")
+            .ok()
+            .and_then(|n| n.parse::<usize>().ok())
+            .unwrap_or_else(|| {
+                info!("MAX_BODY_SIZE_BYTES not set or invalid, using default: {}", DEFAULT_MAX_BODY_SIZE_BYTES);
+                DEFAULT_MAX_BODY_SIZE_BYTES
+            });
+
+        // Responses larger than this stream through without being teed for usage logging,
+        // only their headers are logged
+        let TEE_FULL_BODY_MAX_BYTES = env::var("TEE_FULL_BODY_MAX_BYTES")
+            .ok()
+            .and_then(|n| n.parse::<usize>().ok())
+            .unwrap_or_else(|| {
+                info!("TEE_FULL_BODY_MAX_BYTES not set or invalid, using default: {}", DEFAULT_TEE_FULL_BODY_MAX_BYTES);
+                DEFAULT_TEE_FULL_BODY_MAX_BYTES
+            });
+
+        // Validity of generated CA and API server certs, regenerated on startup and by a check
+        // every CERT_ROTATION_CHECK_INTERVAL_SECS when within CERT_ROTATE_BEFORE_DAYS of expiry
+        let CERT_VALIDITY_DAYS = env::var("CERT_VALIDITY_DAYS")
+            .ok()
+            .and_then(|n| n.parse::<u32>().ok())
+            .unwrap_or_else(|| {
+                info!("CERT_VALIDITY_DAYS not set or invalid, using default: {}", DEFAULT_CERT_VALIDITY_DAYS);
+                DEFAULT_CERT_VALIDITY_DAYS
+            });
+
+        let CERT_ROTATE_BEFORE_DAYS = env::var("CERT_ROTATE_BEFORE_DAYS")
+            .ok()
+            .and_then(|n| n.parse::<u32>().ok())
+            .unwrap_or_else(|| {
+                info!("CERT_ROTATE_BEFORE_DAYS not set or invalid, using default: {}", DEFAULT_CERT_ROTATE_BEFORE_DAYS);
+                DEFAULT_CERT_ROTATE_BEFORE_DAYS
+            });
+
+        let CERT_ROTATION_CHECK_INTERVAL_SECS = env::var("CERT_ROTATION_CHECK_INTERVAL_SECS")
+            .ok()
+            .and_then(|n| n.parse::<u64>().ok())
+            .filter(|n| *n > 0)
+            .unwrap_or_else(|| {
+                info!("CERT_ROTATION_CHECK_INTERVAL_SECS not set or invalid, using default: {}", DEFAULT_CERT_ROTATION_CHECK_INTERVAL_SECS);
+                DEFAULT_CERT_ROTATION_CHECK_INTERVAL_SECS
+            });
+
+        // Externally managed API server cert and key, used instead of a self-signed cert
+        let API_SERVER_TLS_CERT_PATH = env::var("API_SERVER_TLS_CERT_PATH").ok().filter(|p| !p.is_empty());
+        let API_SERVER_TLS_KEY_PATH = env::var("API_SERVER_TLS_KEY_PATH").ok().filter(|p| !p.is_empty());
+
+        // Retries for registering the proxy's public key with the p2p-node on startup
+        let KEY_REGISTRATION_MAX_ATTEMPTS = env::var("KEY_REGISTRATION_MAX_ATTEMPTS")
+            .ok()
+            .and_then(|n| n.parse::<u32>().ok())
+            .unwrap_or_else(|| {
+                info!("KEY_REGISTRATION_MAX_ATTEMPTS not set or invalid, using default: {}", DEFAULT_KEY_REGISTRATION_MAX_ATTEMPTS);
+                DEFAULT_KEY_REGISTRATION_MAX_ATTEMPTS
+            });
+
+        let KEY_REGISTRATION_INITIAL_BACKOFF_MS = env::var("KEY_REGISTRATION_INITIAL_BACKOFF_MS")
+            .ok()
+            .and_then(|n| n.parse::<u64>().ok())
+            .unwrap_or_else(|| {
+                info!("KEY_REGISTRATION_INITIAL_BACKOFF_MS not set or invalid, using default: {}", DEFAULT_KEY_REGISTRATION_INITIAL_BACKOFF_MS);
+                DEFAULT_KEY_REGISTRATION_INITIAL_BACKOFF_MS
+            });
+
+        // Masks API keys and secret headers in logs, disable only for local debugging
+        let LOG_REDACT_SECRETS = env::var("LOG_REDACT_SECRETS")
+            .ok()
+            .and_then(|b| b.parse::<bool>().ok())
+            .unwrap_or_else(|| {
+                info!("LOG_REDACT_SECRETS not set or invalid, using default: true");
+                true
+            });
+
+        // Max bytes of request/response bodies logged, 0 omits bodies.
+        // Defaults to omitting bodies when redacting secrets, otherwise logs them in full
+        let LOG_BODY_MAX_BYTES = env::var("LOG_BODY_MAX_BYTES")
+            .ok()
+            .and_then(|n| n.parse::<usize>().ok())
+            .or_else(|| LOG_REDACT_SECRETS.then_some(0));
+
+        // Placeholder API keys which clients send to have a provider's key injected,
+        // set to an empty string to disable delegation for that provider
+        let ANTHROPIC_DELEGATION_FLAG = env::var("ANTHROPIC_DELEGATION_FLAG")
+            .unwrap_or_else(|_| {
+                info!("ANTHROPIC_DELEGATION_FLAG not set, using default: {}", DEFAULT_ANTHROPIC_DELEGATION_FLAG);
+                DEFAULT_ANTHROPIC_DELEGATION_FLAG.to_string()
+            });
+
+        let OPENAI_DELEGATION_FLAG = env::var("OPENAI_DELEGATION_FLAG")
+            .unwrap_or_else(|_| {
+                info!("OPENAI_DELEGATION_FLAG not set, using default: {}", DEFAULT_OPENAI_DELEGATION_FLAG);
+                DEFAULT_OPENAI_DELEGATION_FLAG.to_string()
+            });
+
+        let DEEPSEEK_DELEGATION_FLAG = env::var("DEEPSEEK_DELEGATION_FLAG")
+            .unwrap_or_else(|_| {
+                info!("DEEPSEEK_DELEGATION_FLAG not set, using default: {}", DEFAULT_DEEPSEEK_DELEGATION_FLAG);
+                DEFAULT_DEEPSEEK_DELEGATION_FLAG.to_string()
+            });
+
+        // Exact upstream hosts which delegated keys may be injected into
+        let DELEGATION_ALLOWED_HOSTS = parse_hosts(&env::var("DELEGATION_ALLOWED_HOSTS")
+            .unwrap_or_else(|_| {
+                info!("DELEGATION_ALLOWED_HOSTS not set, using default: {}", DEFAULT_DELEGATION_ALLOWED_HOSTS);
+                DEFAULT_DELEGATION_ALLOWED_HOSTS.to_string()
+            }));
+
+        // Rejects requests to hosts not on DELEGATION_ALLOWED_HOSTS instead of forwarding
+        // them without injection, so agents can only reach the allowlisted LLM APIs
+        let DELEGATION_BLOCK_UNLISTED_HOSTS = env::var("DELEGATION_BLOCK_UNLISTED_HOSTS")
+            .ok()
+            .and_then(|b| b.parse::<bool>().ok())
+            .unwrap_or_else(|| {
+                info!("DELEGATION_BLOCK_UNLISTED_HOSTS not set or invalid, using default: false");
+                false
+            });
+
+        // Seconds a signed node request's timestamp may be behind or ahead of the proxy's clock
+        let NODE_REQUEST_TIMESTAMP_WINDOW_SECS = env::var("NODE_REQUEST_TIMESTAMP_WINDOW_SECS")
+            .ok()
+            .and_then(|n| n.parse::<u64>().ok())
+            .unwrap_or_else(|| {
+                info!("NODE_REQUEST_TIMESTAMP_WINDOW_SECS not set or invalid, using default: {}", DEFAULT_NODE_REQUEST_TIMESTAMP_WINDOW_SECS);
+                DEFAULT_NODE_REQUEST_TIMESTAMP_WINDOW_SECS
+            });
+
         EnvVars {
             REPORT_USAGE_URL,
             INTERNAL_API_KEY_SERVER_PORT,
             HUDSUCKER_PROXY_PORT,
+            CLIENT_RATE_LIMIT_PER_MINUTE,
+            MAX_BODY_SIZE_BYTES,
+            TEE_FULL_BODY_MAX_BYTES,
+            CERT_VALIDITY_DAYS,
+            CERT_ROTATE_BEFORE_DAYS,
+            CERT_ROTATION_CHECK_INTERVAL_SECS,
+            API_SERVER_TLS_CERT_PATH,
+            API_SERVER_TLS_KEY_PATH,
+            KEY_REGISTRATION_MAX_ATTEMPTS,
+            KEY_REGISTRATION_INITIAL_BACKOFF_MS,
+            LOG_REDACT_SECRETS,
+            LOG_BODY_MAX_BYTES,
+            ANTHROPIC_DELEGATION_FLAG,
+            OPENAI_DELEGATION_FLAG,
+            DEEPSEEK_DELEGATION_FLAG,
+            DELEGATION_ALLOWED_HOSTS,
+            DELEGATION_BLOCK_UNLISTED_HOSTS,
+            NODE_REQUEST_TIMESTAMP_WINDOW_SECS,
         }
     }
 }
\ No newline at end of file
@@ -2,13 +2,16 @@ use std::{
     fs,
     fmt, fmt::Display,
     error::Error as StdError,
-    path::Path,
+    path::PathBuf,
 };
 use p256::ecdsa::{SigningKey, VerifyingKey as P256VerifyingKey};
 use rand_core::OsRng;
 use rcgen::{KeyPair, CertificateParams, BasicConstraints, Certificate, IsCa, PKCS_ECDSA_P256_SHA256, SanType, Ia5String};
 use tracing::*;
 use color_eyre::eyre::{Result, anyhow};
+use time::{Duration as TimeDuration, OffsetDateTime};
+use x509_parser::pem::parse_x509_pem;
+use super::EnvVars;
 
 // /certs_src is mounted from host into container, and shared with python LLM server
 // for Python LLM server to trust llm-proxy as middleman
@@ -27,54 +30,217 @@ pub fn generate_signing_key() -> Result<(SigningKey, P256VerifyingKey), Box<dyn
     Ok((signing_key, verifying_key))
 }
 
-pub fn generate_ca() -> Result<(KeyPair, Certificate), Box<dyn StdError + Send + Sync>> {
-    let cert_path = Path::new(CA_CERT_PATH);
-    let key_path = Path::new(CA_KEY_PATH);
+/// Default validity of the generated CA and internal API server certificates
+pub const DEFAULT_CERT_VALIDITY_DAYS: u32 = 365;
+/// Default window before expiry in which certificates are regenerated
+pub const DEFAULT_CERT_ROTATE_BEFORE_DAYS: u32 = 30;
+/// Default interval between certificate expiry checks while the proxy is running
+pub const DEFAULT_CERT_ROTATION_CHECK_INTERVAL_SECS: u64 = 24 * 60 * 60;
 
-    info!("Generating new CA certificate and key at: {}, {}", CA_CERT_PATH, CA_KEY_PATH);
+const SECONDS_PER_DAY: i64 = 24 * 60 * 60;
+
+/// Where the CA and internal API server certificates are kept, and how long generated ones are valid for
+#[derive(Debug, Clone)]
+pub struct CertConfig {
+    pub ca_cert_path: PathBuf,
+    pub ca_key_path: PathBuf,
+    pub api_server_cert_path: PathBuf,
+    pub api_server_key_path: PathBuf,
+    pub validity_days: u32,
+    pub rotate_before_days: u32,
+    pub rotation_check_interval_secs: u64,
+    // API server cert and key are supplied externally, and never generated or rotated here
+    pub external_api_server_cert: bool,
+}
+
+impl Default for CertConfig {
+    fn default() -> Self {
+        Self {
+            ca_cert_path: PathBuf::from(CA_CERT_PATH),
+            ca_key_path: PathBuf::from(CA_KEY_PATH),
+            api_server_cert_path: PathBuf::from(API_SERVER_CERT_PATH),
+            api_server_key_path: PathBuf::from(API_SERVER_KEY_PATH),
+            validity_days: DEFAULT_CERT_VALIDITY_DAYS,
+            rotate_before_days: DEFAULT_CERT_ROTATE_BEFORE_DAYS,
+            rotation_check_interval_secs: DEFAULT_CERT_ROTATION_CHECK_INTERVAL_SECS,
+            external_api_server_cert: false,
+        }
+    }
+}
+
+impl CertConfig {
+    pub fn from_env_vars(env_vars: &EnvVars) -> Self {
+        let mut config = Self {
+            validity_days: env_vars.CERT_VALIDITY_DAYS,
+            rotate_before_days: env_vars.CERT_ROTATE_BEFORE_DAYS,
+            rotation_check_interval_secs: env_vars.CERT_ROTATION_CHECK_INTERVAL_SECS,
+            ..Self::default()
+        };
+        match (&env_vars.API_SERVER_TLS_CERT_PATH, &env_vars.API_SERVER_TLS_KEY_PATH) {
+            (Some(cert_path), Some(key_path)) => {
+                config.api_server_cert_path = PathBuf::from(cert_path);
+                config.api_server_key_path = PathBuf::from(key_path);
+                config.external_api_server_cert = true;
+            }
+            (None, None) => {}
+            _ => warn!("API_SERVER_TLS_CERT_PATH and API_SERVER_TLS_KEY_PATH must be set together, generating a self-signed API server cert instead"),
+        }
+        config
+    }
+}
+
+/// Sets a certificate to be valid from now (backdated an hour for clock skew) for `validity_days`
+fn set_validity(params: &mut CertificateParams, validity_days: u32) {
+    let now = OffsetDateTime::now_utc();
+    params.not_before = now - TimeDuration::hours(1);
+    params.not_after = now + TimeDuration::days(validity_days as i64);
+}
+
+/// True if the certificate expires within `rotate_before_days` from now, or already has
+pub fn cert_needs_rotation(cert_pem: &str, rotate_before_days: u32) -> Result<bool> {
+    let (_, pem) = parse_x509_pem(cert_pem.as_bytes())
+        .map_err(|e| anyhow!("Invalid certificate PEM: {}", e))?;
+    let cert = pem.parse_x509()
+        .map_err(|e| anyhow!("Invalid certificate: {}", e))?;
+
+    let rotate_at = cert.validity().not_after.timestamp() - rotate_before_days as i64 * SECONDS_PER_DAY;
+    Ok(OffsetDateTime::now_utc().unix_timestamp() >= rotate_at)
+}
+
+/// True if the certificate is signed by the CA certificate's key
+fn cert_issued_by(cert_pem: &str, ca_cert_pem: &str) -> Result<bool> {
+    let (_, pem) = parse_x509_pem(cert_pem.as_bytes())
+        .map_err(|e| anyhow!("Invalid certificate PEM: {}", e))?;
+    let cert = pem.parse_x509()
+        .map_err(|e| anyhow!("Invalid certificate: {}", e))?;
+    let (_, ca_pem) = parse_x509_pem(ca_cert_pem.as_bytes())
+        .map_err(|e| anyhow!("Invalid CA certificate PEM: {}", e))?;
+    let ca_cert = ca_pem.parse_x509()
+        .map_err(|e| anyhow!("Invalid CA certificate: {}", e))?;
+
+    Ok(cert.verify_signature(Some(ca_cert.public_key())).is_ok())
+}
+
+/// Reuses the CA at the configured paths unless it's missing, unreadable or nearing expiry,
+/// in which case a new CA is generated.
+pub fn ensure_ca(config: &CertConfig) -> Result<(KeyPair, Certificate)> {
+    match load_ca(config) {
+        Ok(Some(ca)) => {
+            info!("Reusing CA certificate and key at: {}, {}", config.ca_cert_path.display(), config.ca_key_path.display());
+            return Ok(ca);
+        }
+        Ok(None) => {}
+        Err(e) => warn!("Existing CA at {} is unusable, regenerating: {}", config.ca_cert_path.display(), e),
+    }
+    generate_ca(config)
+}
+
+fn load_ca(config: &CertConfig) -> Result<Option<(KeyPair, Certificate)>> {
+    if !config.ca_cert_path.exists() || !config.ca_key_path.exists() {
+        return Ok(None);
+    }
+    let cert_pem = fs::read_to_string(&config.ca_cert_path)?;
+    if cert_needs_rotation(&cert_pem, config.rotate_before_days)? {
+        info!("CA certificate at {} expires within {} days, rotating", config.ca_cert_path.display(), config.rotate_before_days);
+        return Ok(None);
+    }
+
+    let key_pair = KeyPair::from_pem(&fs::read_to_string(&config.ca_key_path)?)
+        .map_err(|e| anyhow!("Failed to parse CA key: {}", e))?;
+    let params = CertificateParams::from_ca_cert_pem(&cert_pem)
+        .map_err(|e| anyhow!("Failed to parse CA certificate: {}", e))?;
+    // Re-signing the same params with the same key yields a CA that verifies identically,
+    // so certificates it signs chain to the CA certificate already on disk
+    let ca_cert = params.self_signed(&key_pair)
+        .map_err(|e| anyhow!("Failed to load CA certificate: {}", e))?;
+
+    if !cert_issued_by(&ca_cert.pem(), &cert_pem)? {
+        return Err(anyhow!("CA key does not match CA certificate"));
+    }
+    Ok(Some((key_pair, ca_cert)))
+}
+
+pub fn generate_ca(config: &CertConfig) -> Result<(KeyPair, Certificate)> {
+    let cert_path = config.ca_cert_path.as_path();
+    let key_path = config.ca_key_path.as_path();
+
+    info!("Generating new CA certificate and key at: {}, {}", cert_path.display(), key_path.display());
     // Ensure parent directory exists
     if let Some(parent) = cert_path.parent() { fs::create_dir_all(parent)?; }
     if let Some(parent) = key_path.parent() { fs::create_dir_all(parent)?; }
 
-    let mut params = CertificateParams::new(vec!["LLM Proxy Generated CA".to_string()])?;
+    let mut params = CertificateParams::new(vec!["LLM Proxy Generated CA".to_string()])
+        .map_err(|e| anyhow!(e))?;
     params.is_ca = IsCa::Ca(BasicConstraints::Constrained(0)); // Set as CA
     params.key_usages = vec![
         rcgen::KeyUsagePurpose::KeyCertSign,
         rcgen::KeyUsagePurpose::CrlSign,
     ];
+    set_validity(&mut params, config.validity_days);
     // Generate an ECDSA P-256 key pair explicitly
-    let key_pair = KeyPair::generate_for(&rcgen::PKCS_ECDSA_P256_SHA256)?;
+    let key_pair = KeyPair::generate_for(&rcgen::PKCS_ECDSA_P256_SHA256)
+        .map_err(|e| anyhow!(e))?;
 
     // Create the certificate using the params and the generated key pair
     let ca_cert = params.self_signed(&key_pair)
-         .map_err(|e| format!("Failed to self-sign CA certificate: {}", e))?;
+         .map_err(|e| anyhow!("Failed to self-sign CA certificate: {}", e))?;
 
     // Save the generated key and certificate
     let key_pem = key_pair.serialize_pem(); // Serialize the generated key pair
     let cert_pem = ca_cert.pem();
 
     fs::write(key_path, key_pem)
-        .map_err(|e| format!("Failed to write CA key: {}", e))?;
+        .map_err(|e| anyhow!("Failed to write CA key: {}", e))?;
 
     fs::write(cert_path, cert_pem)
-        .map_err(|e| format!("Failed to write CA certificate: {}", e))?;
+        .map_err(|e| anyhow!("Failed to write CA certificate: {}", e))?;
 
-    info!("Saved new CA key and certificate.");
+    info!("Saved new CA key and certificate, valid for {} days.", config.validity_days);
     Ok((key_pair, ca_cert))
 }
 
-pub fn write_api_server_pem_files(
+/// Makes sure the internal API server has a TLS cert and key. Existing files are reused while
+/// they are signed by the current CA and not nearing expiry, otherwise new ones are generated.
+/// Externally managed certs are only checked, never overwritten.
+pub fn ensure_api_server_pem_files(
     ca_cert: &Certificate,
     ca_key_pair: &KeyPair,
+    config: &CertConfig,
 ) -> Result<()> {
-    let cert_path = Path::new(API_SERVER_CERT_PATH);
-    let key_path = Path::new(API_SERVER_KEY_PATH);
+    let cert_path = config.api_server_cert_path.as_path();
+    let key_path = config.api_server_key_path.as_path();
 
-    if cert_path.exists() && key_path.exists() {
-        info!("API server certificate and key files already exist at: {}, {}", API_SERVER_CERT_PATH, API_SERVER_KEY_PATH);
-        // TODO: Optionally add validation that the existing cert matches expected SANs/issuer?
+    if config.external_api_server_cert {
+        if !cert_path.exists() || !key_path.exists() {
+            return Err(anyhow!(
+                "Externally managed API server certificate or key not found at: {}, {}",
+                cert_path.display(),
+                key_path.display()
+            ));
+        }
+        info!("Using externally managed API server certificate and key at: {}, {}", cert_path.display(), key_path.display());
+        if cert_needs_rotation(&fs::read_to_string(cert_path)?, config.rotate_before_days)? {
+            warn!("Externally managed API server certificate at {} expires within {} days", cert_path.display(), config.rotate_before_days);
+        }
         return Ok(());
     }
+
+    if cert_path.exists() && key_path.exists() {
+        let reusable = fs::read_to_string(cert_path)
+            .map_err(|e| anyhow!(e))
+            .and_then(|cert_pem| {
+                Ok(!cert_needs_rotation(&cert_pem, config.rotate_before_days)?
+                    && cert_issued_by(&cert_pem, &ca_cert.pem())?)
+            });
+        match reusable {
+            Ok(true) => {
+                info!("Reusing API server certificate and key at: {}, {}", cert_path.display(), key_path.display());
+                return Ok(());
+            }
+            Ok(false) => info!("API server certificate at {} is nearing expiry or was signed by another CA, rotating", cert_path.display()),
+            Err(e) => warn!("Existing API server certificate at {} is unusable, regenerating: {}", cert_path.display(), e),
+        }
+    }
     info!("Generating new API server PEM certificate and key files, signed by CA...");
 
     // Ensure parent directory exists
@@ -91,6 +257,7 @@ pub fn write_api_server_pem_files(
     params.is_ca = IsCa::NoCa;
     params.key_usages = vec![rcgen::KeyUsagePurpose::KeyEncipherment, rcgen::KeyUsagePurpose::DigitalSignature];
     params.extended_key_usages = vec![rcgen::ExtendedKeyUsagePurpose::ServerAuth];
+    set_validity(&mut params, config.validity_days);
 
     let server_key_pair = KeyPair::generate_for(&PKCS_ECDSA_P256_SHA256)
         .map_err(|e| anyhow!(e))?;
@@ -108,11 +275,39 @@ pub fn write_api_server_pem_files(
     fs::write(key_path, server_key_pem)
         .map_err(|e| anyhow!("Failed to write API server key PEM: {}", e))?;
 
-    info!("Saved new API server PEM cert and key to: {}, {}", API_SERVER_CERT_PATH, API_SERVER_KEY_PATH);
+    info!("Saved new API server PEM cert and key to: {}, {}", cert_path.display(), key_path.display());
 
     Ok(())
 }
 
+/// Outcome of re-checking the certificates of a running proxy
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CertRotationCheck {
+    /// Neither certificate is within the rotation window
+    Current,
+    /// The API server certificate was regenerated, and should be reloaded
+    ApiServerCertRotated,
+    /// The CA is missing or nearing expiry. It can't be swapped under a running proxy,
+    /// so the proxy must restart to regenerate it
+    CaRotationDue,
+}
+
+/// Periodic counterpart to the startup checks: rotates the API server certificate in place
+/// when it nears expiry, and reports when the CA itself needs rotating.
+pub fn check_cert_rotation(config: &CertConfig) -> Result<CertRotationCheck> {
+    let (ca_key_pair, ca_cert) = match load_ca(config)? {
+        Some(ca) => ca,
+        None => return Ok(CertRotationCheck::CaRotationDue),
+    };
+    let server_pem = fs::read_to_string(&config.api_server_cert_path).ok();
+    ensure_api_server_pem_files(&ca_cert, &ca_key_pair, config)?;
+    if fs::read_to_string(&config.api_server_cert_path).ok() == server_pem {
+        Ok(CertRotationCheck::Current)
+    } else {
+        Ok(CertRotationCheck::ApiServerCertRotated)
+    }
+}
+
 #[derive(Debug)]
 pub enum UsageKeypairError {
     PrivateKeyEncoding(String),
@@ -149,3 +344,90 @@ impl From<std::io::Error> for UsageKeypairError {
         Self::FileError(e.to_string())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_cert_config(validity_days: u32, rotate_before_days: u32) -> CertConfig {
+        let dir = std::env::temp_dir().join(format!("llm-proxy-certs-{}", nanoid::nanoid!()));
+        CertConfig {
+            ca_cert_path: dir.join("ca.cer"),
+            ca_key_path: dir.join("ca.key"),
+            api_server_cert_path: dir.join("internal_api.crt"),
+            api_server_key_path: dir.join("internal_api.key"),
+            validity_days,
+            rotate_before_days,
+            rotation_check_interval_secs: DEFAULT_CERT_ROTATION_CHECK_INTERVAL_SECS,
+            external_api_server_cert: false,
+        }
+    }
+
+    #[test]
+    fn test_valid_certs_are_reused() -> Result<()> {
+        let config = temp_cert_config(365, 30);
+        let (ca_key_pair, ca_cert) = ensure_ca(&config)?;
+        ensure_api_server_pem_files(&ca_cert, &ca_key_pair, &config)?;
+        let ca_pem = fs::read_to_string(&config.ca_cert_path)?;
+        let server_pem = fs::read_to_string(&config.api_server_cert_path)?;
+        assert!(!cert_needs_rotation(&server_pem, config.rotate_before_days)?);
+
+        // A restart reuses both the CA and the API server cert it signed
+        let (ca_key_pair, ca_cert) = ensure_ca(&config)?;
+        ensure_api_server_pem_files(&ca_cert, &ca_key_pair, &config)?;
+        assert_eq!(fs::read_to_string(&config.ca_cert_path)?, ca_pem);
+        assert_eq!(fs::read_to_string(&config.api_server_cert_path)?, server_pem);
+
+        fs::remove_dir_all(config.ca_cert_path.parent().unwrap()).ok();
+        Ok(())
+    }
+
+    #[test]
+    fn test_near_expiry_certs_are_rotated() -> Result<()> {
+        // Certs valid for 10 days are already inside the 30 day rotation window
+        let config = temp_cert_config(10, 30);
+        let (ca_key_pair, ca_cert) = ensure_ca(&config)?;
+        ensure_api_server_pem_files(&ca_cert, &ca_key_pair, &config)?;
+        let ca_pem = fs::read_to_string(&config.ca_cert_path)?;
+        let server_pem = fs::read_to_string(&config.api_server_cert_path)?;
+        assert!(cert_needs_rotation(&server_pem, config.rotate_before_days)?);
+
+        let (ca_key_pair, ca_cert) = ensure_ca(&config)?;
+        ensure_api_server_pem_files(&ca_cert, &ca_key_pair, &config)?;
+        let rotated_server_pem = fs::read_to_string(&config.api_server_cert_path)?;
+        assert_ne!(fs::read_to_string(&config.ca_cert_path)?, ca_pem);
+        assert_ne!(rotated_server_pem, server_pem);
+        // The new API server cert is signed by the new CA
+        assert!(cert_issued_by(&rotated_server_pem, &ca_cert.pem())?);
+
+        fs::remove_dir_all(config.ca_cert_path.parent().unwrap()).ok();
+        Ok(())
+    }
+
+    #[test]
+    fn test_periodic_check_rotates_api_server_cert_and_flags_ca() -> Result<()> {
+        // Long-lived CA, with an API server cert already inside the 30 day rotation window
+        let config = temp_cert_config(365, 30);
+        let (ca_key_pair, ca_cert) = ensure_ca(&config)?;
+        ensure_api_server_pem_files(&ca_cert, &ca_key_pair, &CertConfig { validity_days: 10, ..config.clone() })?;
+        let server_pem = fs::read_to_string(&config.api_server_cert_path)?;
+
+        assert_eq!(check_cert_rotation(&config)?, CertRotationCheck::ApiServerCertRotated);
+        let rotated_server_pem = fs::read_to_string(&config.api_server_cert_path)?;
+        assert_ne!(rotated_server_pem, server_pem);
+        assert!(cert_issued_by(&rotated_server_pem, &ca_cert.pem())?);
+        assert_eq!(check_cert_rotation(&config)?, CertRotationCheck::Current);
+
+        // A CA nearing expiry needs a restart, and is left on disk until then
+        let short_lived = temp_cert_config(10, 30);
+        let (ca_key_pair, ca_cert) = ensure_ca(&short_lived)?;
+        ensure_api_server_pem_files(&ca_cert, &ca_key_pair, &short_lived)?;
+        let ca_pem = fs::read_to_string(&short_lived.ca_cert_path)?;
+        assert_eq!(check_cert_rotation(&short_lived)?, CertRotationCheck::CaRotationDue);
+        assert_eq!(fs::read_to_string(&short_lived.ca_cert_path)?, ca_pem);
+
+        fs::remove_dir_all(config.ca_cert_path.parent().unwrap()).ok();
+        fs::remove_dir_all(short_lived.ca_cert_path.parent().unwrap()).ok();
+        Ok(())
+    }
+}
@@ -4,6 +4,11 @@ pub mod tee_body;
 pub mod tee_body_sse;
 pub mod config;
 pub mod types;
+pub mod rate_limit;
+pub mod body_limits;
+pub mod registration;
+pub mod log_redaction;
+pub mod delegation;
 
 pub mod api_key_delegation_server;
 pub use api_key_delegation_server::generate_digest_hash;
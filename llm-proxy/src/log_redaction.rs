@@ -0,0 +1,168 @@
+use hudsucker::hyper::header::{HeaderName, HeaderValue};
+use tracing::info;
+use crate::config::EnvVars;
+
+/// Log target for API keys, secret headers and request/response bodies.
+/// Filter it out with `RUST_LOG=info,llm_proxy::sensitive=off`
+pub const SENSITIVE_LOG_TARGET: &str = "llm_proxy::sensitive";
+
+/// Default log filter when RUST_LOG is not set, which excludes the sensitive target
+pub const DEFAULT_LOG_FILTER: &str = "info,llm_proxy::sensitive=off";
+
+const REDACTED: &str = "[REDACTED]";
+
+/// Headers whose values are masked when redacting secrets
+const SENSITIVE_HEADERS: [&str; 4] = ["x-api-key", "authorization", "proxy-authorization", "cookie"];
+
+/// Controls how API keys, secret headers and bodies are logged by the proxy
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LogRedaction {
+    /// Fully masks API keys and secret headers
+    pub redact_secrets: bool,
+    /// Max bytes of a body to log: None logs bodies in full, Some(0) omits them
+    pub body_max_bytes: Option<usize>,
+}
+
+impl Default for LogRedaction {
+    fn default() -> Self {
+        Self {
+            redact_secrets: true,
+            body_max_bytes: Some(0),
+        }
+    }
+}
+
+impl LogRedaction {
+    pub fn from_env_vars(env_vars: &EnvVars) -> Self {
+        Self {
+            redact_secrets: env_vars.LOG_REDACT_SECRETS,
+            body_max_bytes: env_vars.LOG_BODY_MAX_BYTES,
+        }
+    }
+
+    pub fn log_api_key(&self, key_name: &str, api_key: &str, request_id: &str) {
+        if self.redact_secrets {
+            info!("Request {}: Injecting API key '{}': {}", request_id, key_name, REDACTED);
+            return;
+        }
+        let key_len = api_key.len();
+        let prefix = api_key.chars().take(5).collect::<String>();
+        let suffix = api_key.chars().skip(key_len.saturating_sub(4)).collect::<String>();
+        info!(
+            target: SENSITIVE_LOG_TARGET,
+            "Request {}: Injecting API key '{}': {}...{}",
+            request_id, key_name, prefix, suffix
+        );
+    }
+
+    pub fn log_header(&self, request_id: &str, name: &HeaderName, value: &HeaderValue) {
+        if !SENSITIVE_HEADERS.contains(&name.as_str()) {
+            info!("Request {}: Header: {} = {:?}", request_id, name, value);
+        } else if self.redact_secrets {
+            info!("Request {}: Header: {} = {}", request_id, name, REDACTED);
+        } else {
+            info!(target: SENSITIVE_LOG_TARGET, "Request {}: Header: {} = {:?}", request_id, name, value);
+        }
+    }
+
+    /// Logs a request or response body (`label` e.g. "Request abc"), truncated or omitted
+    /// according to `body_max_bytes`
+    pub fn log_body(&self, label: &str, body: &[u8]) {
+        let logged_bytes = match self.body_max_bytes {
+            Some(0) => {
+                info!("{}: Body: <omitted {} bytes>", label, body.len());
+                return;
+            }
+            Some(max_bytes) => &body[..body.len().min(max_bytes)],
+            None => body,
+        };
+        let truncated = if logged_bytes.len() < body.len() {
+            format!(" <truncated, {} of {} bytes>", logged_bytes.len(), body.len())
+        } else {
+            String::new()
+        };
+        match std::str::from_utf8(body) {
+            Ok(_) => info!(
+                target: SENSITIVE_LOG_TARGET,
+                "{}: Body:\n{}{}",
+                label, String::from_utf8_lossy(logged_bytes), truncated
+            ),
+            Err(_) => info!("{}: Body: <Non-UTF8 data: {} bytes>", label, body.len()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Clone, Default)]
+    struct CapturedLogs(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for CapturedLogs {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    /// Runs `f` with every log target enabled, returning the captured output
+    fn capture_logs(f: impl FnOnce()) -> String {
+        let logs = CapturedLogs::default();
+        let writer = logs.clone();
+        let subscriber = tracing_subscriber::fmt()
+            .with_max_level(tracing::Level::TRACE)
+            .with_ansi(false)
+            .with_writer(move || writer.clone())
+            .finish();
+        tracing::subscriber::with_default(subscriber, f);
+        let output = logs.0.lock().unwrap().clone();
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn test_redacted_logs_contain_no_key_characters() {
+        let api_key = "sk-ant-REDACTED";
+        let body = format!(r#"{{"messages":[{{"role":"user","content":"secret prompt {}"}}]}}"#, api_key);
+        let redaction = LogRedaction::default();
+
+        let output = capture_logs(|| {
+            redaction.log_api_key("reverie_123", api_key, "req_1");
+            redaction.log_header(
+                "req_1",
+                &HeaderName::from_static("x-api-key"),
+                &HeaderValue::from_str(api_key).unwrap()
+            );
+            redaction.log_body("Request req_1", body.as_bytes());
+        });
+
+        assert!(output.contains(REDACTED));
+        assert!(output.contains("<omitted"));
+        // Neither the key, nor the prefix/suffix logged when unredacted, nor the prompt
+        assert!(!output.contains(api_key));
+        assert!(!output.contains(&api_key[..5]));
+        assert!(!output.contains(&api_key[api_key.len() - 4..]));
+        assert!(!output.contains("secret prompt"));
+
+        // Unredacted, the key prefix is logged to the sensitive target
+        let unredacted = LogRedaction { redact_secrets: false, body_max_bytes: None };
+        let output = capture_logs(|| unredacted.log_api_key("reverie_123", api_key, "req_1"));
+        assert!(output.contains(SENSITIVE_LOG_TARGET));
+        assert!(output.contains(&api_key[..5]));
+    }
+
+    #[test]
+    fn test_body_is_truncated_to_max_bytes() {
+        let redaction = LogRedaction { redact_secrets: true, body_max_bytes: Some(8) };
+        let output = capture_logs(|| redaction.log_body("Request req_1", b"0123456789abcdef"));
+
+        assert!(output.contains("01234567"));
+        assert!(!output.contains("89abcdef"));
+        assert!(output.contains("<truncated, 8 of 16 bytes>"));
+    }
+}
@@ -5,6 +5,11 @@ mod usage;
 mod config;
 mod api_key_delegation_server;
 mod types;
+mod rate_limit;
+mod body_limits;
+mod registration;
+mod log_redaction;
+mod delegation;
 use std::{
     net::SocketAddr,
     error::Error as StdError,
@@ -15,7 +20,7 @@ use std::{
 use chrono::Utc;
 use color_eyre::{Result, eyre::anyhow};
 use hudsucker::{
-    hyper::{self, Request, Response, header::{HeaderName, HeaderValue, CONTENT_TYPE}},
+    hyper::{self, HeaderMap, Request, Response, header::{CONTENT_ENCODING, CONTENT_TYPE}},
     rustls::crypto::aws_lc_rs,
     rustls::crypto::CryptoProvider,
     tokio_tungstenite::tungstenite::Message,
@@ -24,12 +29,12 @@ use hudsucker::{
 };
 use reqwest::Client as ReqwestClient;
 use serde::{Deserialize, Serialize};
-use http_body_util::{Full, BodyExt};
+use bytes::Bytes;
+use http_body_util::Full;
+use tokio::sync::Notify;
 use tracing::{debug, error, info, warn};
 use p256::ecdsa::{SigningKey, Signature, signature::Signer};
 use serde_json::{Value, json};
-use rand::seq::SliceRandom;
-use rand::thread_rng;
 use ed25519_dalek::VerifyingKey as EdVerifyingKey;
 use pkcs8::DecodePublicKey;
 use pem::{Pem, encode};
@@ -38,21 +43,26 @@ use elliptic_curve::pkcs8::EncodePublicKey;
 
 use crate::config::{
     EnvVars,
+    CertConfig,
     generate_signing_key,
-    generate_ca,
-    write_api_server_pem_files
+    ensure_ca,
+    ensure_api_server_pem_files
 };
 use crate::usage::{log_sse_response_task, log_regular_response_task};
+use crate::tee_body::{TeeMode, tee_response_body, usage_metadata};
 use crate::api_key_delegation_server::{run_internal_api_server, ApiKeyStore};
+use crate::rate_limit::{ClientRateLimiter, rate_limited_response};
+use crate::registration::{RetryPolicy, send_json_rpc_with_retries};
+use crate::log_redaction::{LogRedaction, DEFAULT_LOG_FILTER};
+use crate::delegation::{DelegationFlags, select_delegated_key, unlisted_host_response};
+use crate::body_limits::{CollectBodyError, collect_limited, exceeds_declared_limit, payload_too_large_response};
 
 
-static ANTHROPIC_DELEGATE_API_KEY_FLAG: &str = "sk-ant-delegated-api-key";
-
 #[derive(Debug, Default, Clone, Serialize, Deserialize)]
 struct LLMProxyRequestContext {
     request_id: String,
     request_url: Option<String>,
-    linked_tool_use_id: Option<String>,
+    linked_tool_use_ids: Vec<String>,
     reverie_id: Option<String>,
     spender: Option<String>,
     spender_type: Option<String>,
@@ -63,17 +73,9 @@ struct LogHandler {
     signing_key: Arc<SigningKey>,
     env: Arc<EnvVars>,
     api_key_store: ApiKeyStore,
-}
-
-// Helper function for safe logging of API keys
-fn log_api_key_info(key_name: &str, api_key: &str, request_id: &str) {
-    let key_len = api_key.len();
-    let prefix = api_key.chars().take(5).collect::<String>();
-    let suffix = api_key.chars().skip(key_len.saturating_sub(4)).collect::<String>();
-    info!(
-        "Request {}: Injecting API key '{}': {}...{}",
-        request_id, key_name, prefix, suffix
-    );
+    rate_limiter: ClientRateLimiter,
+    log_redaction: LogRedaction,
+    delegation_flags: DelegationFlags,
 }
 
 impl HttpHandler for LogHandler {
@@ -92,12 +94,21 @@ impl HttpHandler for LogHandler {
         info!("===== Intercepted Request {}: {} ====", request_id, url);
         info!("Request {}: Method: {}", request_id, parts.method);
         for (name, value) in &parts.headers {
-            info!("Request {}: Header: {} = {:?}", request_id, name, value);
+            self.log_redaction.log_header(&request_id, name, value);
         }
 
-        let body_bytes = match body.collect().await {
-            Ok(collected) => collected.to_bytes(),
-            Err(e) => {
+        let max_body_size = self.env.MAX_BODY_SIZE_BYTES;
+        if exceeds_declared_limit(&parts.headers, max_body_size) {
+            warn!("Request {}: Content-Length exceeds max body size of {} bytes, rejecting.", request_id, max_body_size);
+            return payload_too_large_response(max_body_size).into();
+        }
+        let body_bytes = match collect_limited(body, max_body_size).await {
+            Ok(bytes) => bytes,
+            Err(CollectBodyError::TooLarge) => {
+                warn!("Request {}: Body exceeds max body size of {} bytes, rejecting.", request_id, max_body_size);
+                return payload_too_large_response(max_body_size).into();
+            }
+            Err(CollectBodyError::Other(e)) => {
                 error!("Request {}: Failed to collect request body: {}", request_id, e);
                 return Request::from_parts(parts, Body::empty()).into();
             }
@@ -110,37 +121,40 @@ impl HttpHandler for LogHandler {
             parts.uri,
             parts.uri.host()
         );
-        if let Some(host) = parts.uri.host() {
-            if host.contains("anthropic.com") {
-                debug!("Request {}: Detected Anthropic API request.", request_id);
-                let x_api_key_header_value = parts.headers.get(HeaderName::from_static("x-api-key"));
-
-                if is_anthropic_delegation_request(x_api_key_header_value) {
-                    let store = self.api_key_store.read().expect("API key store lock poisoned");
-                    let anthropic_keys: Vec<_> = store.values()
-                        .filter(|payload| payload.api_key_type.eq_ignore_ascii_case("ANTHROPIC_API_KEY"))
-                        .collect();
-
-                    if anthropic_keys.is_empty() {
-                        warn!("Request {}: Proxy injection failed: No Anthropic keys found in store for delegation.", request_id);
-                    } else {
-                        if let Some(&selected_payload) = anthropic_keys.choose(&mut thread_rng()) {
-                            let api_key = &selected_payload.api_key;
-                            reverie_id_for_context = Some(selected_payload.reverie_id.clone());
-                            spender_for_context = Some(selected_payload.spender.clone());
-                            spender_type_for_context = Some(selected_payload.spender_type.clone());
-
-                            match HeaderValue::from_str(api_key) {
-                                Ok(header_value) => {
-                                    log_api_key_info(&selected_payload.reverie_id, api_key, &request_id);
-                                    parts.headers.insert(HeaderName::from_static("x-api-key"), header_value);
-                                }
-                                Err(e) => {
-                                    error!("Request {}: Failed to create HeaderValue for selected Anthropic API key (Reverie: {}): {}", request_id, selected_payload.reverie_id, e);
-                                }
+        let host = parts.uri.host().unwrap_or_default().to_string();
+        if self.env.DELEGATION_BLOCK_UNLISTED_HOSTS && !self.delegation_flags.is_allowed_host(&host) {
+            warn!("Request {}: Host '{}' is not on the delegation allowlist, rejecting.", request_id, host);
+            return unlisted_host_response(&host).into();
+        }
+        if let Some(flagged_provider) = self.delegation_flags.delegation_target(&parts.headers) {
+            let request_provider = self.delegation_flags.delegation_target_for_request(&host, parts.uri.path(), &parts.headers);
+            if !self.delegation_flags.is_allowed_host(&host) {
+                warn!("Request {}: {:?} delegation flag sent to host '{}', not on the delegation allowlist, skipping injection.", request_id, flagged_provider, host);
+            } else if request_provider.is_none() {
+                warn!("Request {}: {:?} delegation flag sent to host '{}', skipping injection.", request_id, flagged_provider, host);
+            } else if let Some(provider) = request_provider {
+                debug!("Request {}: Detected {:?} API request.", request_id, provider);
+                let client = ctx.client_addr.ip().to_string();
+                if !self.rate_limiter.try_acquire(&client) {
+                    warn!("Request {}: Client {} exceeded rate limit, returning 429.", request_id, client);
+                    return rate_limited_response(&client).into();
+                }
+                match select_delegated_key(&self.api_key_store, provider) {
+                    None => {
+                        warn!("Request {}: Proxy injection failed: No {:?} keys found in store for delegation.", request_id, provider);
+                    }
+                    Some(selected_payload) => {
+                        let api_key = &selected_payload.api_key;
+                        match provider.inject_api_key(&mut parts.headers, api_key) {
+                            Ok(()) => {
+                                self.log_redaction.log_api_key(&selected_payload.reverie_id, api_key, &request_id);
+                                reverie_id_for_context = Some(selected_payload.reverie_id.clone());
+                                spender_for_context = Some(selected_payload.spender.clone());
+                                spender_type_for_context = Some(selected_payload.spender_type.clone());
+                            }
+                            Err(e) => {
+                                error!("Request {}: Failed to inject selected {:?} API key (Reverie: {}): {}", request_id, provider, selected_payload.reverie_id, e);
                             }
-                        } else {
-                            warn!("Request {}: Failed to randomly select an Anthropic key even though keys were found for delegation.", request_id);
                         }
                     }
                 }
@@ -148,23 +162,18 @@ impl HttpHandler for LogHandler {
         }
         // -- End API Key Injection Logic --
 
-        if let Ok(body_str) = std::str::from_utf8(&body_bytes) {
-            info!("Request {}: Body:\n{}", request_id, body_str);
-        } else {
-            info!("Request {}: Body: <Non-UTF8 data: {} bytes>", request_id, body_bytes.len());
-        }
+        self.log_redaction.log_body(&format!("Request {}", request_id), &body_bytes);
 
-        let mut request_context = LLMProxyRequestContext {
+        let request_context = LLMProxyRequestContext {
             request_id: request_id.clone(),
             request_url: Some(url.clone()),
-            linked_tool_use_id: None,
+            linked_tool_use_ids: find_tool_use_ids_in_request(&request_id, &parts.headers, &body_bytes, max_body_size),
             reverie_id: reverie_id_for_context,
             spender: spender_for_context,
             spender_type: spender_type_for_context,
         };
-        if let Some(found_tool_use_id) = find_tool_use_id_in_request_body(&body_bytes) {
-             info!("Request {}: Found linked tool_use_id: {}", request_id, found_tool_use_id);
-            request_context.linked_tool_use_id = Some(found_tool_use_id);
+        if !request_context.linked_tool_use_ids.is_empty() {
+            info!("Request {}: Found linked tool_use_ids: {:?}", request_id, request_context.linked_tool_use_ids);
         }
         if let Err(e) = ctx.set_request_context(request_context) {
             error!("Request {}: Failed to set request context: {}", request_id, e);
@@ -183,7 +192,7 @@ impl HttpHandler for LogHandler {
         let (
             request_id,
             request_url,
-            linked_tool_use_id,
+            linked_tool_use_ids,
             reverie_id,
             spender,
             spender_type,
@@ -193,18 +202,18 @@ impl HttpHandler for LogHandler {
             (
                 request_context.request_id.clone(),
                 request_context.request_url.clone(),
-                request_context.linked_tool_use_id.clone(),
+                request_context.linked_tool_use_ids.clone(),
                 request_context.reverie_id.clone(),
                 request_context.spender.clone(),
                 request_context.spender_type.clone(),
             )
         } else {
             warn!("Response: Could not retrieve request context from HttpContext field! Generating new ID.");
-            (create_request_id(), None, None, None, None, None)
+            (create_request_id(), None, vec![], None, None, None)
         };
 
         info!("Response for {}: Intercepted response with status: {}", request_id, parts.status);
-        debug!("Response {}: Using linked_tool_use_id: {:?}", request_id, linked_tool_use_id);
+        debug!("Response {}: Using linked_tool_use_ids: {:?}", request_id, linked_tool_use_ids);
 
         let content_type = parts.headers.get(CONTENT_TYPE).and_then(|v| v.to_str().ok());
         let is_sse = content_type.map_or(false, |ct| ct.starts_with("text/event-stream"));
@@ -212,16 +221,26 @@ impl HttpHandler for LogHandler {
         let key_arc = self.signing_key.clone();
         let report_url = self.env.REPORT_USAGE_URL.clone();
 
+        let max_body_size = self.env.MAX_BODY_SIZE_BYTES;
+        if exceeds_declared_limit(&parts.headers, max_body_size) {
+            warn!(
+                "Response {}: Content-Length exceeds max body size of {} bytes, passing through without logging usage.",
+                request_id, max_body_size
+            );
+            return Response::from_parts(parts, body);
+        }
+
         let response_body;
         if is_sse {
             info!("Response {}: SSE stream detected, using SSE logging task.", request_id);
-            let (teed_body, receiver) = crate::tee_body_sse::tee_body_sse(body, request_url.as_deref());
+            let (teed_body, receiver, dropped_chunks) = crate::tee_body_sse::tee_body_sse(body, request_url.as_deref());
             tokio::spawn(log_sse_response_task(
                 receiver,
+                dropped_chunks,
                 headers_for_log,
                 key_arc,
                 request_url,
-                linked_tool_use_id,
+                linked_tool_use_ids,
                 reverie_id,
                 report_url,
                 request_id.clone(),
@@ -230,14 +249,27 @@ impl HttpHandler for LogHandler {
             ));
             response_body = teed_body;
         } else {
+            let tee_mode = TeeMode::for_response(
+                &parts.headers,
+                self.env.TEE_FULL_BODY_MAX_BYTES.min(max_body_size)
+            );
+            let (teed_body, receiver) = tee_response_body(body, tee_mode);
+            let Some(receiver) = receiver else {
+                info!(
+                    "Response {}: Body over the full tee threshold, streaming through without usage logging. Metadata: {:?}",
+                    request_id, usage_metadata(&headers_for_log)
+                );
+                return Response::from_parts(parts, teed_body);
+            };
             info!("Response {}: Non-SSE response detected, using full body logging task.", request_id);
-            let (teed_body, receiver) = crate::tee_body::tee_body(body);
             tokio::spawn(log_regular_response_task(
                 receiver,
                 headers_for_log,
                 key_arc,
+                self.log_redaction,
+                max_body_size,
                 request_url,
-                linked_tool_use_id,
+                linked_tool_use_ids,
                 reverie_id,
                 report_url,
                 request_id.clone(),
@@ -259,46 +291,60 @@ impl WebSocketHandler for LogHandler {
     }
 }
 
-fn is_anthropic_delegation_request(option_header_value: Option<&HeaderValue>) -> bool {
-    let mut needs_delegation = false;
-    if let Some(header_api_key_value) = option_header_value {
-        if header_api_key_value == ANTHROPIC_DELEGATE_API_KEY_FLAG {
-            needs_delegation = true;
-            debug!("Request: x-api-key header is the delegation key value, attempting injection from store.");
-        } else {
-            debug!("Request: x-api-key header present but not the delegation key value ('{:?}'), skipping direct injection attempt based on this.", header_api_key_value);
+/// Decodes the request body per its Content-Encoding before looking up tool_use_ids.
+/// The body is still forwarded upstream in its original encoding.
+fn find_tool_use_ids_in_request(
+    request_id: &str,
+    headers: &HeaderMap,
+    body_bytes: &Bytes,
+    max_body_size: usize,
+) -> Vec<String> {
+    let content_encoding = headers.get(CONTENT_ENCODING).and_then(|h| h.to_str().ok());
+    match parser::decompress_body(content_encoding, body_bytes, max_body_size) {
+        Ok(decompressed_bytes) => find_tool_use_ids_in_request_body(&decompressed_bytes),
+        Err(e) => {
+            warn!("Request {}: Failed to decompress {:?} request body: {}", request_id, content_encoding, e);
+            Vec::new()
         }
-    } else {
-        debug!("Request: No x-api-key header found. This request does not use explicit delegation via x-api-key.");
-    };
-    return needs_delegation
+    }
 }
 
-fn find_tool_use_id_in_request_body(body_bytes: &[u8]) -> Option<String> {
-    let json_body: Value = serde_json::from_slice(body_bytes).ok()?;
-    let messages = json_body.get("messages")?.as_array()?;
-    for (msg_idx, msg) in messages.iter().enumerate() {
-        match msg.get("role").and_then(Value::as_str) {
-            Some("user") => debug!("find_tool_use_id: Found message with role 'user' at index {}.", msg_idx),
-            _ => continue,
+/// Collects the tool_use_ids of every tool_result in the request's user messages,
+/// including tool_results nested in another content block, so usage can be linked to each
+fn find_tool_use_ids_in_request_body(body_bytes: &[u8]) -> Vec<String> {
+    let mut tool_use_ids = Vec::new();
+    let Ok(json_body) = serde_json::from_slice::<Value>(body_bytes) else {
+        return tool_use_ids;
+    };
+    let messages = json_body.get("messages").and_then(Value::as_array).into_iter().flatten();
+    for (msg_idx, msg) in messages.enumerate() {
+        if msg.get("role").and_then(Value::as_str) != Some("user") {
+            continue;
         }
-        let content_array = match msg.get("content").and_then(Value::as_array) {
-            Some(arr) => arr,
-            None => continue,
-        };
-        for item in content_array.iter() {
-            match item.get("type").and_then(Value::as_str) {
-                Some("tool_result") => {
-                    match item.get("tool_use_id").and_then(Value::as_str) {
-                        Some(id_str) => return Some(id_str.to_string()),
-                        None => warn!("find_tool_use_id: 'tool_result' item missing string 'tool_use_id' field."),
+        debug!("find_tool_use_ids: Found message with role 'user' at index {}.", msg_idx);
+        if let Some(content) = msg.get("content") {
+            collect_tool_use_ids(content, &mut tool_use_ids);
+        }
+    }
+    tool_use_ids
+}
+
+fn collect_tool_use_ids(content: &Value, tool_use_ids: &mut Vec<String>) {
+    for item in content.as_array().into_iter().flatten() {
+        if item.get("type").and_then(Value::as_str) == Some("tool_result") {
+            match item.get("tool_use_id").and_then(Value::as_str) {
+                Some(id_str) => {
+                    if !tool_use_ids.iter().any(|id| id == id_str) {
+                        tool_use_ids.push(id_str.to_string());
                     }
-                },
-                _ => continue,
+                }
+                None => warn!("find_tool_use_ids: 'tool_result' item missing string 'tool_use_id' field."),
             }
         }
+        if let Some(nested_content) = item.get("content") {
+            collect_tool_use_ids(nested_content, tool_use_ids);
+        }
     }
-    None
 }
 
 fn create_request_id() -> String {
@@ -322,8 +368,13 @@ async fn main() -> Result<(), Box<dyn StdError + Send + Sync>> {
     CryptoProvider::install_default(aws_lc_rs::default_provider()).ok();
     // Initialize color_eyre for error reporting
     color_eyre::install()?;
-    // Initialize tracing (logging)
-    tracing_subscriber::fmt().init();
+    // Initialize tracing (logging). Sensitive logs (keys, bodies) are off unless enabled in RUST_LOG
+    tracing_subscriber::fmt()
+        .with_env_filter(
+            tracing_subscriber::EnvFilter::try_from_default_env()
+                .unwrap_or_else(|_| tracing_subscriber::EnvFilter::new(DEFAULT_LOG_FILTER))
+        )
+        .init();
 
     info!("Starting LLM proxy...");
     let env_vars = Arc::new(EnvVars::load());
@@ -369,9 +420,10 @@ async fn main() -> Result<(), Box<dyn StdError + Send + Sync>> {
     // The endpoint URL for jsonrpsee is typically the base URL, method is in payload.
     let rpc_endpoint_url = p2p_node_rpc_url.trim_end_matches('/').to_string();
 
-    let (ca_key_pair, ca_cert) = generate_ca()?;
+    let cert_config = CertConfig::from_env_vars(&env_vars);
+    let (ca_key_pair, ca_cert) = ensure_ca(&cert_config)?;
     // write API key server PEM files after signing them using the CA (for internal API key server TLS)
-    write_api_server_pem_files(&ca_cert, &ca_key_pair)?;
+    ensure_api_server_pem_files(&ca_cert, &ca_key_pair, &cert_config)?;
 
     // Get the CA certificate in PEM format
     let ca_cert_pem_string = ca_cert.pem();
@@ -390,41 +442,37 @@ async fn main() -> Result<(), Box<dyn StdError + Send + Sync>> {
         id: json!(1),                      // Request ID (can be a number or string)
     };
 
+    // The p2p-node may still be starting up, so retry registration with backoff
+    let registration_policy = RetryPolicy::new(
+        env_vars.KEY_REGISTRATION_MAX_ATTEMPTS,
+        std::time::Duration::from_millis(env_vars.KEY_REGISTRATION_INITIAL_BACKOFF_MS),
+    );
     tokio::spawn(async move {
-        info!("Attempting to send JSON-RPC request for LLM Proxy public key to p2p-node at {}", rpc_endpoint_url);
+        info!("Registering LLM Proxy public key with p2p-node at {}", rpc_endpoint_url);
         let client = ReqwestClient::new();
-        match client.post(&rpc_endpoint_url)
-                    .json(&json_rpc_payload)
-                    .send().await {
-            Ok(response) => {
-                let status = response.status();
-                match response.text().await {
-                    Ok(text) => {
-                        if status.is_success() {
-                            info!("Successfully sent LLM Proxy key registration to p2p-node. Status: {}. Response: {}", status, text);
-                        } else {
-                            error!("LLM Proxy key registration request to p2p-node failed. Status: {}. Body: {}", status, text);
-                        }
-                    }
-                    Err(e) => {
-                         error!("LLM Proxy key registration request to p2p-node failed. Status: {}. Error reading response body: {}", status, e);
-                    }
-                }
-            }
-            Err(e) => {
-                error!("Error sending LLM Proxy key registration request to p2p-node (reqwest error): {}", e);
-            }
+        if let Err(e) = send_json_rpc_with_retries(
+            &client,
+            &rpc_endpoint_url,
+            &json_rpc_payload,
+            &registration_policy,
+        ).await {
+            error!("LLM Proxy key registration with p2p-node failed: {}", e);
         }
     });
 
     // Spawn the internal API server task
     let api_key_store_clone = api_key_store.clone();
     let env_vars_clone = env_vars.clone();
+    // The CA can't be swapped under a running proxy, so a due CA rotation shuts the proxy
+    // down, for it to be restarted and regenerate the CA on startup
+    let ca_rotation_due = Arc::new(Notify::new());
+    let ca_rotation_due_clone = ca_rotation_due.clone();
     tokio::spawn(async move {
         if let Err(e) = run_internal_api_server(
             api_key_store_clone,
             env_vars_clone,
             p2p_node_public_key_arc, // Pass the loaded key
+            ca_rotation_due_clone,
         ).await {
             error!("Internal API server failed: {}", e);
         }
@@ -443,6 +491,9 @@ async fn main() -> Result<(), Box<dyn StdError + Send + Sync>> {
         signing_key: llm_proxy_signing_key_arc.clone(), // Use the Arc from loaded/generated key
         env: env_vars.clone(),
         api_key_store: api_key_store.clone(),
+        rate_limiter: ClientRateLimiter::new(env_vars.CLIENT_RATE_LIMIT_PER_MINUTE),
+        log_redaction: LogRedaction::from_env_vars(&env_vars),
+        delegation_flags: DelegationFlags::from_env_vars(&env_vars),
     };
 
     let proxy = Proxy::builder()
@@ -451,7 +502,7 @@ async fn main() -> Result<(), Box<dyn StdError + Send + Sync>> {
         .with_rustls_client(aws_lc_rs::default_provider())
         .with_http_handler(log_handler.clone())
         .with_websocket_handler(log_handler)
-        .with_graceful_shutdown(shutdown_signal())
+        .with_graceful_shutdown(shutdown_signal(ca_rotation_due))
         .build()
         .map_err(|e| anyhow!("Failed to build proxy: {}", e))?;
 
@@ -461,11 +512,97 @@ async fn main() -> Result<(), Box<dyn StdError + Send + Sync>> {
     Ok(())
 }
 
-async fn shutdown_signal() {
-    tokio::signal::ctrl_c()
-        .await
-        .unwrap_or_else(|err| {
-            error!("Failed to install CTRL+C signal handler: {}", err);
+async fn shutdown_signal(ca_rotation_due: Arc<Notify>) {
+    tokio::select! {
+        result = tokio::signal::ctrl_c() => {
+            result.unwrap_or_else(|err| {
+                error!("Failed to install CTRL+C signal handler: {}", err);
+            });
+            info!("CTRL+C signal received, shutting down.");
+        }
+        _ = ca_rotation_due.notified() => {
+            info!("CA certificate rotation due, shutting down to regenerate it on restart.");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_tool_use_ids_without_tool_results() {
+        let body = json!({
+            "messages": [
+                { "role": "user", "content": "What's the weather in Paris?" },
+                { "role": "user", "content": [{ "type": "text", "text": "And in Rome?" }] },
+            ]
+        });
+        assert!(find_tool_use_ids_in_request_body(body.to_string().as_bytes()).is_empty());
+        assert!(find_tool_use_ids_in_request_body(b"not json").is_empty());
+    }
+
+    #[test]
+    fn test_find_tool_use_ids_with_one_tool_result() {
+        let body = json!({
+            "messages": [
+                { "role": "user", "content": "What's the weather in Paris?" },
+                { "role": "assistant", "content": [{ "type": "tool_use", "id": "toolu_01", "name": "get_weather", "input": {} }] },
+                { "role": "user", "content": [{ "type": "tool_result", "tool_use_id": "toolu_01", "content": "18C" }] },
+            ]
         });
-    info!("CTRL+C signal received, shutting down.");
-}
\ No newline at end of file
+        assert_eq!(find_tool_use_ids_in_request_body(body.to_string().as_bytes()), vec!["toolu_01"]);
+    }
+
+    #[test]
+    fn test_find_tool_use_ids_across_messages_and_nested_blocks() {
+        let body = json!({
+            "messages": [
+                { "role": "user", "content": [
+                    { "type": "tool_result", "tool_use_id": "toolu_01", "content": "18C" },
+                    { "type": "tool_result", "tool_use_id": "toolu_02", "content": "21C" },
+                ]},
+                // tool_results in assistant messages are ignored
+                { "role": "assistant", "content": [{ "type": "tool_result", "tool_use_id": "toolu_99" }] },
+                { "role": "user", "content": [
+                    { "type": "text", "text": "Here are the forecasts" },
+                    { "type": "tool_result", "tool_use_id": "toolu_03", "content": [
+                        { "type": "tool_result", "tool_use_id": "toolu_04", "content": "nested" },
+                    ]},
+                    // repeated ids are linked once
+                    { "type": "tool_result", "tool_use_id": "toolu_01", "content": "18C" },
+                ]},
+            ]
+        });
+        assert_eq!(
+            find_tool_use_ids_in_request_body(body.to_string().as_bytes()),
+            vec!["toolu_01", "toolu_02", "toolu_03", "toolu_04"]
+        );
+    }
+
+    #[test]
+    fn test_find_tool_use_ids_in_gzipped_request() {
+        use std::io::Write;
+        use flate2::{write::GzEncoder, Compression};
+        use crate::body_limits::DEFAULT_MAX_BODY_SIZE_BYTES;
+
+        let body = json!({
+            "messages": [
+                { "role": "user", "content": [{ "type": "tool_result", "tool_use_id": "toolu_01", "content": "18C" }] },
+            ]
+        });
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(body.to_string().as_bytes()).unwrap();
+        let gzipped_body = Bytes::from(encoder.finish().unwrap());
+
+        let mut headers = HeaderMap::new();
+        assert!(find_tool_use_ids_in_request("request_test", &headers, &gzipped_body, DEFAULT_MAX_BODY_SIZE_BYTES).is_empty());
+
+        headers.insert(CONTENT_ENCODING, "gzip".parse().unwrap());
+        assert_eq!(find_tool_use_ids_in_request("request_test", &headers, &gzipped_body, DEFAULT_MAX_BODY_SIZE_BYTES), vec!["toolu_01"]);
+
+        // Bodies that fail to decode link no tool_use_ids
+        let plain_body = Bytes::from(body.to_string());
+        assert!(find_tool_use_ids_in_request("request_test", &headers, &plain_body, DEFAULT_MAX_BODY_SIZE_BYTES).is_empty());
+    }
+}
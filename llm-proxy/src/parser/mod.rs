@@ -27,6 +27,8 @@ pub enum ParseError {
     JsonParsing(serde_json::Error),
     UrlParsing(url::ParseError),
     UnsupportedProvider,
+    /// Decompressed body exceeded the max body size, in bytes
+    DecompressedTooLarge(usize),
 }
 
 impl fmt::Display for ParseError {
@@ -36,6 +38,7 @@ impl fmt::Display for ParseError {
             ParseError::JsonParsing(e) => write!(f, "JSON parsing failed: {}", e),
             ParseError::UrlParsing(e) => write!(f, "URL parsing failed: {}", e),
             ParseError::UnsupportedProvider => write!(f, "Unsupported API provider"),
+            ParseError::DecompressedTooLarge(max_size) => write!(f, "Decompressed body exceeds max body size of {} bytes", max_size),
         }
     }
 }
@@ -47,6 +50,7 @@ impl Error for ParseError {
             ParseError::JsonParsing(e) => Some(e),
             ParseError::UrlParsing(e) => Some(e),
             ParseError::UnsupportedProvider => None,
+            ParseError::DecompressedTooLarge(_) => None,
         }
     }
 }
@@ -67,6 +71,17 @@ pub trait UsageParser {
 }
 
 
+/// Whether a response with this `content-type` carries a JSON body, e.g. `application/json`,
+/// `application/problem+json`, or with parameters like `; charset=utf-8`.
+/// A missing content-type is assumed to be JSON, as some upstreams omit it.
+pub fn is_json_content_type(content_type: Option<&str>) -> bool {
+    let mime_type = match content_type {
+        None => return true,
+        Some(content_type) => content_type.split(';').next().unwrap_or("").trim().to_ascii_lowercase(),
+    };
+    mime_type == "application/json" || (mime_type.starts_with("application/") && mime_type.ends_with("+json"))
+}
+
 /// Parses decompressed bytes as JSON and extracts usage data based on API provider.
 pub fn parse_json_and_extract_usage(
     bytes: &[u8],
@@ -161,26 +176,71 @@ fn extract_generic_usage(json: &Value) -> Option<UsageData> {
     Some(usage_data)
 }
 
-/// Decompresses response body bytes based on Content-Encoding header.
+/// Decompresses body bytes based on Content-Encoding header. Decompression stops after
+/// `max_size` bytes, so a small compressed body can't expand without bound in memory.
 pub fn decompress_body(
     content_encoding: Option<&str>,
-    body_bytes: &Bytes
+    body_bytes: &Bytes,
+    max_size: usize,
 ) -> Result<Vec<u8>, ParseError> {
-    let mut decompressed_bytes = Vec::new();
-
     match content_encoding {
-        Some("gzip") => {
-            let mut decoder = GzDecoder::new(&body_bytes[..]);
-            decoder.read_to_end(&mut decompressed_bytes).map_err(ParseError::Decompression)?;
-        },
-        Some("deflate") => {
-            let mut decoder = DeflateDecoder::new(&body_bytes[..]);
-            decoder.read_to_end(&mut decompressed_bytes).map_err(ParseError::Decompression)?;
-        },
+        Some("gzip") => read_to_end_limited(GzDecoder::new(&body_bytes[..]), max_size),
+        Some("deflate") => read_to_end_limited(DeflateDecoder::new(&body_bytes[..]), max_size),
         _ => {
             // No compression or unknown, copy original bytes
-            decompressed_bytes.extend_from_slice(body_bytes);
+            Ok(body_bytes.to_vec())
         }
     }
+}
+
+/// Reads one byte past `max_size`, to tell a body of exactly `max_size` bytes from a larger one
+fn read_to_end_limited(decoder: impl Read, max_size: usize) -> Result<Vec<u8>, ParseError> {
+    let mut decompressed_bytes = Vec::new();
+    decoder
+        .take(max_size as u64 + 1)
+        .read_to_end(&mut decompressed_bytes)
+        .map_err(ParseError::Decompression)?;
+
+    if decompressed_bytes.len() > max_size {
+        return Err(ParseError::DecompressedTooLarge(max_size));
+    }
     Ok(decompressed_bytes)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use flate2::{write::GzEncoder, Compression};
+
+    fn gzip(bytes: &[u8]) -> Bytes {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::best());
+        encoder.write_all(bytes).unwrap();
+        Bytes::from(encoder.finish().unwrap())
+    }
+
+    #[test]
+    fn test_decompress_body_up_to_max_size() {
+        let body = gzip(&[b'a'; 1024]);
+        assert_eq!(decompress_body(Some("gzip"), &body, 1024).unwrap(), vec![b'a'; 1024]);
+        assert_eq!(decompress_body(None, &Bytes::from_static(b"{}"), 1024).unwrap(), b"{}".to_vec());
+    }
+
+    #[test]
+    fn test_gzip_bomb_is_rejected() {
+        // 64 MiB of zeros compresses to ~64 KiB
+        let max_size = 1024 * 1024;
+        let bomb = gzip(&vec![0u8; 64 * 1024 * 1024]);
+        assert!(bomb.len() < max_size);
+
+        match decompress_body(Some("gzip"), &bomb, max_size) {
+            Err(ParseError::DecompressedTooLarge(size)) => assert_eq!(size, max_size),
+            other => panic!("expected DecompressedTooLarge, got {:?}", other.map(|bytes| bytes.len())),
+        }
+        // one byte over is also rejected
+        assert!(matches!(
+            decompress_body(Some("gzip"), &gzip(&[b'a'; 1025]), 1024),
+            Err(ParseError::DecompressedTooLarge(1024))
+        ));
+    }
+}
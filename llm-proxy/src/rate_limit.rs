@@ -0,0 +1,117 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::Instant,
+};
+use bytes::Bytes;
+use http_body_util::Full;
+use hudsucker::{
+    hyper::{Response, StatusCode, header::CONTENT_TYPE},
+    Body,
+};
+use serde_json::json;
+
+/// Token bucket for a single client, refilled continuously at `requests_per_minute / 60` tokens per second
+#[derive(Debug, Clone)]
+struct TokenBucket {
+    tokens: f64,
+    last_refill: Instant,
+}
+
+/// Per-client rate limiter for delegated API key requests. Keyed by the requesting client
+/// rather than the delegated key's spender, since keys are picked at random per request.
+/// A `requests_per_minute` of 0 disables rate limiting.
+#[derive(Debug, Clone)]
+pub struct ClientRateLimiter {
+    requests_per_minute: u32,
+    buckets: Arc<Mutex<HashMap<String, TokenBucket>>>,
+}
+
+impl ClientRateLimiter {
+    pub fn new(requests_per_minute: u32) -> Self {
+        Self {
+            requests_per_minute,
+            buckets: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Takes a token from the client's bucket, returns false if the client is over their limit
+    pub fn try_acquire(&self, client: &str) -> bool {
+        self.try_acquire_at(client, Instant::now())
+    }
+
+    fn try_acquire_at(&self, client: &str, now: Instant) -> bool {
+        if self.requests_per_minute == 0 {
+            return true;
+        }
+        let capacity = self.requests_per_minute as f64;
+        let refill_per_sec = capacity / 60.0;
+
+        let mut buckets = self.buckets.lock().expect("rate limiter lock poisoned");
+        let bucket = buckets.entry(client.to_string()).or_insert(TokenBucket {
+            tokens: capacity,
+            last_refill: now,
+        });
+
+        let elapsed = now.saturating_duration_since(bucket.last_refill).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * refill_per_sec).min(capacity);
+        bucket.last_refill = now;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+/// HTTP 429 response returned to the client instead of forwarding the request
+pub fn rate_limited_response(client: &str) -> Response<Body> {
+    let body = json!({
+        "error": {
+            "type": "rate_limit_error",
+            "message": format!("Rate limit exceeded for client {}", client),
+        }
+    });
+    Response::builder()
+        .status(StatusCode::TOO_MANY_REQUESTS)
+        .header(CONTENT_TYPE, "application/json")
+        .body(Body::from(Full::new(Bytes::from(body.to_string()))))
+        .expect("Failed to build 429 response")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_nth_request_from_same_client_is_rate_limited() {
+        let limiter = ClientRateLimiter::new(3);
+        let now = Instant::now();
+
+        for _ in 0..3 {
+            assert!(limiter.try_acquire_at("172.18.0.5", now));
+        }
+        // 4th request within the same minute is rejected with a 429
+        assert!(!limiter.try_acquire_at("172.18.0.5", now));
+        assert_eq!(rate_limited_response("172.18.0.5").status(), StatusCode::TOO_MANY_REQUESTS);
+
+        // Other clients have their own bucket
+        assert!(limiter.try_acquire_at("172.18.0.6", now));
+
+        // One token is refilled every 20s at 3 requests/min
+        assert!(limiter.try_acquire_at("172.18.0.5", now + Duration::from_secs(20)));
+        assert!(!limiter.try_acquire_at("172.18.0.5", now + Duration::from_secs(20)));
+    }
+
+    #[test]
+    fn test_zero_limit_disables_rate_limiting() {
+        let limiter = ClientRateLimiter::new(0);
+        let now = Instant::now();
+        for _ in 0..100 {
+            assert!(limiter.try_acquire_at("172.18.0.5", now));
+        }
+    }
+}
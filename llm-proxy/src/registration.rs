@@ -0,0 +1,167 @@
+use std::time::Duration;
+use color_eyre::{Result, eyre::anyhow};
+use reqwest::Client as ReqwestClient;
+use serde::Serialize;
+use tracing::{info, warn, error};
+
+/// Default attempts at registering the proxy key before giving up
+pub const DEFAULT_KEY_REGISTRATION_MAX_ATTEMPTS: u32 = 10;
+/// Default delay before the 2nd attempt, doubled after each failed attempt
+pub const DEFAULT_KEY_REGISTRATION_INITIAL_BACKOFF_MS: u64 = 500;
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+/// Bounded exponential backoff for retrying a request
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    pub max_attempts: u32,
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+}
+
+impl RetryPolicy {
+    pub fn new(max_attempts: u32, initial_backoff: Duration) -> Self {
+        Self {
+            max_attempts: max_attempts.max(1),
+            initial_backoff,
+            max_backoff: MAX_BACKOFF,
+        }
+    }
+
+    /// Delay after the given (1-indexed) failed attempt
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let factor = 2u32.saturating_pow(attempt.saturating_sub(1));
+        self.initial_backoff.saturating_mul(factor).min(self.max_backoff)
+    }
+}
+
+/// Sends a JSON-RPC request to the p2p-node, retrying with backoff until the node accepts it.
+/// The node may not be listening yet when the proxy starts, so connection errors, non-2xx
+/// statuses and JSON-RPC error responses are all retried. Returns the number of attempts made.
+pub async fn send_json_rpc_with_retries<T: Serialize>(
+    client: &ReqwestClient,
+    rpc_endpoint_url: &str,
+    json_rpc_payload: &T,
+    policy: &RetryPolicy,
+) -> Result<u32> {
+    for attempt in 1..=policy.max_attempts {
+        info!("Sending JSON-RPC request to p2p-node at {} (attempt {}/{})", rpc_endpoint_url, attempt, policy.max_attempts);
+        match send_json_rpc(client, rpc_endpoint_url, json_rpc_payload).await {
+            Ok(response) => {
+                info!("p2p-node accepted JSON-RPC request on attempt {}. Response: {}", attempt, response);
+                return Ok(attempt);
+            }
+            Err(e) if attempt < policy.max_attempts => {
+                let backoff = policy.backoff(attempt);
+                warn!("JSON-RPC request to p2p-node failed (attempt {}/{}), retrying in {:?}: {}", attempt, policy.max_attempts, backoff, e);
+                tokio::time::sleep(backoff).await;
+            }
+            Err(e) => {
+                error!("JSON-RPC request to p2p-node failed after {} attempts: {}", attempt, e);
+                return Err(e);
+            }
+        }
+    }
+    unreachable!("RetryPolicy has at least one attempt")
+}
+
+async fn send_json_rpc<T: Serialize>(
+    client: &ReqwestClient,
+    rpc_endpoint_url: &str,
+    json_rpc_payload: &T,
+) -> Result<String> {
+    let response = client.post(rpc_endpoint_url)
+        .json(json_rpc_payload)
+        .send().await
+        .map_err(|e| anyhow!("reqwest error: {}", e))?;
+
+    let status = response.status();
+    let text = response.text().await
+        .map_err(|e| anyhow!("Status: {}. Error reading response body: {}", status, e))?;
+    if !status.is_success() {
+        return Err(anyhow!("Status: {}. Body: {}", status, text));
+    }
+    // JSON-RPC errors are returned with a 200 status
+    if let Ok(body) = serde_json::from_str::<serde_json::Value>(&text) {
+        if let Some(rpc_error) = body.get("error") {
+            return Err(anyhow!("JSON-RPC error: {}", rpc_error));
+        }
+    }
+    Ok(text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    /// Binds the address after `delay`, then answers one request with a JSON-RPC result
+    async fn spawn_delayed_rpc_node(addr: std::net::SocketAddr, delay: Duration) {
+        tokio::spawn(async move {
+            tokio::time::sleep(delay).await;
+            let listener = TcpListener::bind(addr).await.expect("mock node bind");
+            let (mut socket, _) = listener.accept().await.expect("mock node accept");
+            let mut buf = [0u8; 8192];
+            let _ = socket.read(&mut buf).await;
+
+            let body = json!({ "jsonrpc": "2.0", "id": 1, "result": "registered" }).to_string();
+            let response = format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                body.len(),
+                body
+            );
+            socket.write_all(response.as_bytes()).await.expect("mock node write");
+        });
+    }
+
+    /// Reserves a free local port, released so the mock node can bind it later
+    async fn free_local_addr() -> std::net::SocketAddr {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        listener.local_addr().unwrap()
+    }
+
+    fn registration_payload() -> serde_json::Value {
+        json!({ "jsonrpc": "2.0", "method": "register_llm_proxy_key", "params": {}, "id": 1 })
+    }
+
+    #[tokio::test]
+    async fn test_registration_succeeds_once_node_is_up() {
+        let addr = free_local_addr().await;
+        spawn_delayed_rpc_node(addr, Duration::from_millis(300)).await;
+
+        let policy = RetryPolicy::new(10, Duration::from_millis(50));
+        let attempts = send_json_rpc_with_retries(
+            &ReqwestClient::new(),
+            &format!("http://{}", addr),
+            &registration_payload(),
+            &policy,
+        ).await.unwrap();
+
+        // The first attempts fail with connection refused until the node binds its port
+        assert!(attempts > 1, "expected retries, registered on attempt {}", attempts);
+    }
+
+    #[tokio::test]
+    async fn test_registration_gives_up_after_max_attempts() {
+        let addr = free_local_addr().await;
+
+        let policy = RetryPolicy::new(3, Duration::from_millis(10));
+        let result = send_json_rpc_with_retries(
+            &ReqwestClient::new(),
+            &format!("http://{}", addr),
+            &registration_payload(),
+            &policy,
+        ).await;
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_backoff_doubles_up_to_max() {
+        let policy = RetryPolicy::new(10, Duration::from_millis(500));
+        assert_eq!(policy.backoff(1), Duration::from_millis(500));
+        assert_eq!(policy.backoff(2), Duration::from_secs(1));
+        assert_eq!(policy.backoff(3), Duration::from_secs(2));
+        assert_eq!(policy.backoff(10), MAX_BACKOFF);
+    }
+}
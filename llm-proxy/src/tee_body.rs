@@ -1,20 +1,68 @@
 use bytes::Bytes;
 use hudsucker::Body as HudsuckerBody; // Alias to avoid naming conflicts
+use hudsucker::hyper::HeaderMap;
 use http_body::{Body, Frame, SizeHint};
 use std::pin::Pin;
 use std::task::{Context, Poll};
 use tokio::sync::mpsc; // For the channel
 use http_body_util::BodyExt; // Import BodyExt trait for .boxed()
 use pin_project_lite::pin_project;
+use crate::body_limits::content_length;
 
 pub type ChannelError = Box<dyn std::error::Error + Send + Sync>;
 
+/// Default size above which response bodies stream through without being teed (1 MiB)
+pub const DEFAULT_TEE_FULL_BODY_MAX_BYTES: usize = 1024 * 1024;
+
+/// Response headers logged in place of the body when it isn't teed
+const USAGE_METADATA_HEADERS: [&str; 5] = [
+    "content-type",
+    "content-length",
+    "request-id",
+    "x-request-id",
+    "anthropic-ratelimit-tokens-remaining",
+];
+
+/// How a response body is captured for usage logging
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TeeMode {
+    /// Tee chunks to the logging task, until more than `max_bytes` have been teed
+    Full { max_bytes: usize },
+    /// Declared too large to tee, stream through and only log headers
+    MetadataOnly,
+}
+
+impl TeeMode {
+    /// Bodies with a Content-Length above `full_tee_max_bytes` aren't teed at all.
+    /// Bodies of unknown length are teed until they pass `full_tee_max_bytes`.
+    pub fn for_response(headers: &HeaderMap, full_tee_max_bytes: usize) -> Self {
+        match content_length(headers) {
+            Some(len) if len > full_tee_max_bytes => TeeMode::MetadataOnly,
+            _ => TeeMode::Full { max_bytes: full_tee_max_bytes },
+        }
+    }
+}
+
+/// Usage-relevant response headers, logged when the body is streamed through untouched
+pub fn usage_metadata(headers: &HeaderMap) -> Vec<(&'static str, String)> {
+    USAGE_METADATA_HEADERS.iter()
+        .filter_map(|name| {
+            let value = headers.get(*name)?.to_str().ok()?;
+            Some((*name, value.to_string()))
+        })
+        .collect()
+}
+
 // A Body wrapper that clones data frames and sends them through an MPSC channel.
+// Once more than `max_bytes` have been teed, an error is sent and the channel is dropped,
+// so the logging task stops buffering while the body keeps streaming to the client.
 pin_project! {
     pub struct TeeBody<B: Body> {
         #[pin]
         inner: B,
-        sender: mpsc::Sender<Result<Bytes, ChannelError>>,
+        sender: Option<mpsc::Sender<Result<Bytes, ChannelError>>>,
+        max_bytes: usize,
+        teed_bytes: usize,
     }
 }
 
@@ -23,8 +71,8 @@ where
     B: Body<Data = Bytes> + Unpin,
     B::Error: Into<ChannelError>,
 {
-    pub fn new(inner: B, sender: mpsc::Sender<Result<Bytes, ChannelError>>) -> Self {
-        Self { inner, sender }
+    pub fn new(inner: B, sender: mpsc::Sender<Result<Bytes, ChannelError>>, max_bytes: usize) -> Self {
+        Self { inner, sender: Some(sender), max_bytes, teed_bytes: 0 }
     }
 }
 
@@ -43,8 +91,16 @@ where
         let this = self.project();
         match this.inner.poll_frame(cx) {
             Poll::Ready(Some(Ok(frame))) => {
-                if let Some(data) = frame.data_ref() {
-                    let _ = this.sender.try_send(Ok(data.clone()));
+                if let (Some(data), Some(sender)) = (frame.data_ref(), this.sender.as_ref()) {
+                    *this.teed_bytes += data.len();
+                    if *this.teed_bytes > *this.max_bytes {
+                        let _ = sender.try_send(Err(format!(
+                            "Response body exceeded {} bytes, skipping logging", this.max_bytes
+                        ).into()));
+                        *this.sender = None;
+                    } else {
+                        let _ = sender.try_send(Ok(data.clone()));
+                    }
                 }
                 Poll::Ready(Some(Ok(frame)))
             }
@@ -69,13 +125,115 @@ where
     }
 }
 
-/// Creates a TeeBody wrapper and an MPSC receiver to capture body chunks.
-pub fn tee_body(body: HudsuckerBody) -> (HudsuckerBody, mpsc::Receiver<Result<Bytes, ChannelError>>) {
+/// Creates a TeeBody wrapper and an MPSC receiver to capture up to `max_bytes` of body chunks.
+pub fn tee_body(body: HudsuckerBody, max_bytes: usize) -> (HudsuckerBody, mpsc::Receiver<Result<Bytes, ChannelError>>) {
     // Create a channel with a buffer size (e.g., 100). Adjust as needed.
     // If the logger task falls behind, `try_send` in TeeBody will start dropping chunks.
     let (sender, receiver) = mpsc::channel(100);
-    let teed_body = TeeBody::new(body, sender);
+    let teed_body = TeeBody::new(body, sender, max_bytes);
     // HudsuckerBody implements From<BoxBody>, so we box our TeeBody
     let boxed_body = teed_body.boxed();
     (HudsuckerBody::from(boxed_body), receiver) // Explicitly convert using From
-}
\ No newline at end of file
+}
+
+/// Tees a response body per `mode`. `MetadataOnly` bodies are returned untouched,
+/// with no receiver, so nothing is buffered for logging.
+pub fn tee_response_body(
+    body: HudsuckerBody,
+    mode: TeeMode,
+) -> (HudsuckerBody, Option<mpsc::Receiver<Result<Bytes, ChannelError>>>) {
+    match mode {
+        TeeMode::Full { max_bytes } => {
+            let (teed_body, receiver) = tee_body(body, max_bytes);
+            (teed_body, Some(receiver))
+        }
+        TeeMode::MetadataOnly => (body, None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::stream;
+    use http_body_util::{Full, StreamBody};
+    use hudsucker::hyper::header::{CONTENT_LENGTH, HeaderValue};
+    use std::convert::Infallible;
+
+    #[tokio::test]
+    async fn test_oversized_response_streams_through_without_buffering() {
+        let chunks: Vec<Bytes> = (0..4).map(|_| Bytes::from(vec![b'a'; 8])).collect();
+        let inner = StreamBody::new(stream::iter(
+            chunks.into_iter().map(|c| Ok::<_, Infallible>(Frame::data(c)))
+        ));
+        let (sender, mut receiver) = mpsc::channel(100);
+        let teed_body = TeeBody::new(inner, sender, 16);
+
+        // The client still receives the whole body
+        let collected = teed_body.collect().await.unwrap().to_bytes();
+        assert_eq!(collected.len(), 32);
+
+        // The logging channel only receives chunks up to the limit, then an error
+        let mut logged = 0;
+        let mut overflowed = false;
+        while let Some(result) = receiver.recv().await {
+            match result {
+                Ok(chunk) => logged += chunk.len(),
+                Err(_) => overflowed = true,
+            }
+        }
+        assert_eq!(logged, 16);
+        assert!(overflowed);
+    }
+
+    async fn teed_bytes(receiver: Option<mpsc::Receiver<Result<Bytes, ChannelError>>>) -> usize {
+        let Some(mut receiver) = receiver else { return 0 };
+        let mut logged = 0;
+        while let Some(Ok(chunk)) = receiver.recv().await {
+            logged += chunk.len();
+        }
+        logged
+    }
+
+    #[tokio::test]
+    async fn test_small_body_teed_fully_and_large_body_streamed_without_buffering() {
+        let full_tee_max_bytes = 64;
+        let response = |len: usize| {
+            let mut headers = HeaderMap::new();
+            headers.insert(CONTENT_LENGTH, HeaderValue::from(len));
+            headers.insert("request-id", HeaderValue::from_static("req_123"));
+            let body = HudsuckerBody::from(Full::new(Bytes::from(vec![b'a'; len])));
+            (headers, body)
+        };
+
+        let (small_headers, small_body) = response(32);
+        let small_mode = TeeMode::for_response(&small_headers, full_tee_max_bytes);
+        assert_eq!(small_mode, TeeMode::Full { max_bytes: full_tee_max_bytes });
+        let (small_body, small_receiver) = tee_response_body(small_body, small_mode);
+        assert_eq!(small_body.collect().await.unwrap().to_bytes().len(), 32);
+        assert_eq!(teed_bytes(small_receiver).await, 32);
+
+        let (large_headers, large_body) = response(1024);
+        let large_mode = TeeMode::for_response(&large_headers, full_tee_max_bytes);
+        assert_eq!(large_mode, TeeMode::MetadataOnly);
+        let (large_body, large_receiver) = tee_response_body(large_body, large_mode);
+        assert!(large_receiver.is_none());
+        // The client still receives the whole body, and its headers are still logged
+        assert_eq!(large_body.collect().await.unwrap().to_bytes().len(), 1024);
+        assert_eq!(usage_metadata(&large_headers), vec![
+            ("content-length", "1024".to_string()),
+            ("request-id", "req_123".to_string()),
+        ]);
+    }
+
+    #[tokio::test]
+    async fn test_body_of_unknown_length_not_teed_past_threshold() {
+        // No Content-Length, so the body is teed until it passes the threshold
+        let mode = TeeMode::for_response(&HeaderMap::new(), 16);
+        assert_eq!(mode, TeeMode::Full { max_bytes: 16 });
+
+        let (body, receiver) = tee_response_body(HudsuckerBody::from(Full::new(Bytes::from(vec![b'a'; 32]))), mode);
+        assert_eq!(body.collect().await.unwrap().to_bytes().len(), 32);
+        // Its single 32 byte frame is over the threshold, so none of it is buffered for logging
+        assert_eq!(teed_bytes(receiver).await, 0);
+    }
+}
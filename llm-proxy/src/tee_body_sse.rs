@@ -1,25 +1,37 @@
 use bytes::Bytes;
+use std::collections::VecDeque;
 use std::io;
 use std::string::ToString;
 use hudsucker::Body as HudsuckerBody;
 use http_body::{Body, Frame, SizeHint};
 use std::pin::Pin;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::task::{Context, Poll};
 use tokio::sync::mpsc;
+use tokio::sync::mpsc::error::TrySendError;
 use std::error::Error as StdError;
 use pin_project_lite::pin_project;
 use http_body_util::BodyExt;
 use tracing;
 use crate::parser::{SSEParser, SSEChunk};
 
+/// Parsed SSE events buffered for the logging task before further events are dropped
+const SSE_LOG_CHANNEL_CAPACITY: usize = 100;
+
 // A Body wrapper specifically for SSE streams.
 // It parses SSE events and sends *complete events* through the channel.
+// The logging copy is lossy: if the logging task falls behind, text and other events
+// are dropped and counted in `dropped_chunks`, so the client stream is never held up.
+// Usage events are never dropped, they wait in `usage_backlog` until the channel has room.
 pin_project! {
     pub struct TeeBodySSE<B: Body> {
         #[pin]
         inner: B,
         sender: mpsc::Sender<SSEChunk>,
         parser: SSEParser,
+        dropped_chunks: Arc<AtomicUsize>,
+        usage_backlog: VecDeque<SSEChunk>,
     }
 }
 
@@ -33,8 +45,51 @@ where
             inner,
             sender,
             parser: SSEParser::new(request_url),
+            dropped_chunks: Arc::new(AtomicUsize::new(0)),
+            usage_backlog: VecDeque::new(),
         }
     }
+
+    /// Number of parsed SSE events dropped because the logging channel was full
+    fn dropped_chunks(&self) -> Arc<AtomicUsize> {
+        self.dropped_chunks.clone()
+    }
+}
+
+/// Events the usage report is built from, which the logging task must receive in order
+fn carries_usage(update: &SSEChunk) -> bool {
+    !matches!(update, SSEChunk::Text(_) | SSEChunk::Other(_))
+}
+
+/// Sends backlogged usage events while the logging channel has room
+fn flush_usage_backlog(sender: &mpsc::Sender<SSEChunk>, usage_backlog: &mut VecDeque<SSEChunk>) {
+    while let Some(update) = usage_backlog.pop_front() {
+        match sender.try_send(update) {
+            Ok(()) => {}
+            Err(TrySendError::Full(update)) => {
+                usage_backlog.push_front(update);
+                return
+            }
+            Err(TrySendError::Closed(_)) => return,
+        }
+    }
+}
+
+/// Once the stream ends, the remaining usage events are sent without holding up the client
+fn send_usage_backlog(sender: &mpsc::Sender<SSEChunk>, usage_backlog: &mut VecDeque<SSEChunk>) {
+    if usage_backlog.is_empty() {
+        return
+    }
+    let sender = sender.clone();
+    let usage_backlog = std::mem::take(usage_backlog);
+    tokio::spawn(async move {
+        for update in usage_backlog {
+            if sender.send(update).await.is_err() {
+                tracing::debug!("SSE logging channel closed, dropping usage update.");
+                return
+            }
+        }
+    });
 }
 
 // Implement the Body trait for TeeBodySSE
@@ -56,16 +111,39 @@ where
             Poll::Ready(Some(Ok(frame))) => {
                 if let Some(data) = frame.data_ref() {
                     let updates = proj.parser.process_chunk(data);
+                    flush_usage_backlog(proj.sender, proj.usage_backlog);
                     for update in updates {
-                        if proj.sender.try_send(update).is_err() {
-                            tracing::warn!("SSE logging channel full or closed, dropping update.");
+                        // queued behind earlier usage events, so they reach the logging task in order
+                        if carries_usage(&update) && !proj.usage_backlog.is_empty() {
+                            proj.usage_backlog.push_back(update);
+                            continue
+                        }
+                        match proj.sender.try_send(update) {
+                            Ok(()) => {}
+                            Err(TrySendError::Full(update)) if carries_usage(&update) => {
+                                proj.usage_backlog.push_back(update);
+                            }
+                            Err(TrySendError::Full(_)) => {
+                                if proj.dropped_chunks.fetch_add(1, Ordering::Relaxed) == 0 {
+                                    tracing::warn!("SSE logging channel full, dropping updates until the logging task catches up.");
+                                }
+                            }
+                            Err(TrySendError::Closed(_)) => {
+                                tracing::debug!("SSE logging channel closed, dropping update.");
+                            }
                         }
                     }
                 }
                 Poll::Ready(Some(Ok(frame)))
             }
-            Poll::Ready(Some(Err(e))) => Poll::Ready(Some(Err(e.into()))),
-            Poll::Ready(None) => Poll::Ready(None),
+            Poll::Ready(Some(Err(e))) => {
+                send_usage_backlog(proj.sender, proj.usage_backlog);
+                Poll::Ready(Some(Err(e.into())))
+            }
+            Poll::Ready(None) => {
+                send_usage_backlog(proj.sender, proj.usage_backlog);
+                Poll::Ready(None)
+            }
             Poll::Pending => Poll::Pending,
         }
     }
@@ -79,14 +157,117 @@ where
      }
 }
 
-/// Creates a TeeBodySSE wrapper and an MPSC receiver to capture parsed SSEChunks.
-pub fn tee_body_sse(body: HudsuckerBody, request_url: Option<&str>) -> (HudsuckerBody, mpsc::Receiver<SSEChunk>) {
-    let (sender, receiver) = mpsc::channel(100);
+/// Creates a TeeBodySSE wrapper and an MPSC receiver to capture parsed SSEChunks,
+/// along with a count of the SSEChunks dropped because the receiver fell behind.
+/// The count is final once the receiver is closed.
+pub fn tee_body_sse(
+    body: HudsuckerBody,
+    request_url: Option<&str>
+) -> (HudsuckerBody, mpsc::Receiver<SSEChunk>, Arc<AtomicUsize>) {
+    let (sender, receiver) = mpsc::channel(SSE_LOG_CHANNEL_CAPACITY);
     let teed_body = TeeBodySSE::new(body, sender, request_url);
+    let dropped_chunks = teed_body.dropped_chunks();
     let mapped_body = teed_body.map_err(|e| {
         let io_err = io::Error::new(io::ErrorKind::Other, e.to_string());
         hudsucker::Error::Io(io_err)
     });
     let boxed_body = mapped_body.boxed();
-    (HudsuckerBody::from(boxed_body), receiver)
+    (HudsuckerBody::from(boxed_body), receiver, dropped_chunks)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::stream;
+    use http_body_util::StreamBody;
+    use std::convert::Infallible;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_slow_logging_consumer_drops_chunks_without_blocking_client() {
+        let num_events = 50;
+        let events: Vec<Bytes> = (0..num_events)
+            .map(|i| Bytes::from(format!(
+                "event: content_block_delta\ndata: {{\"type\":\"content_block_delta\",\"delta\":{{\"text\":\"chunk {}\"}}}}\n\n", i
+            )))
+            .collect();
+        let expected_body = events.concat();
+        let inner = StreamBody::new(stream::iter(
+            events.into_iter().map(|c| Ok::<_, Infallible>(Frame::data(c)))
+        ));
+
+        // Logging channel only holds a couple of events, and is drained slowly
+        let (sender, mut receiver) = mpsc::channel(2);
+        let teed_body = TeeBodySSE::new(inner, sender, None);
+        let dropped_chunks = teed_body.dropped_chunks();
+        let slow_logger = tokio::spawn(async move {
+            let mut logged = vec![];
+            while let Some(update) = receiver.recv().await {
+                tokio::time::sleep(Duration::from_millis(20)).await;
+                logged.push(update);
+            }
+            logged
+        });
+
+        // The client receives the whole stream, in order
+        let collected = tokio::time::timeout(Duration::from_secs(1), teed_body.collect())
+            .await
+            .expect("client stream blocked on the logging consumer")
+            .unwrap()
+            .to_bytes();
+        assert_eq!(collected, expected_body);
+
+        let logged = slow_logger.await.unwrap();
+        let dropped = dropped_chunks.load(Ordering::Relaxed);
+        assert!(dropped > 0);
+        assert_eq!(logged.len() + dropped, num_events);
+        assert!(matches!(&logged[0], SSEChunk::Text(text) if text == "chunk 0"));
+    }
+
+    #[tokio::test]
+    async fn test_slow_logging_consumer_still_receives_every_usage_event() {
+        let text_delta = |i: usize| format!(
+            "event: content_block_delta\ndata: {{\"type\":\"content_block_delta\",\"delta\":{{\"text\":\"chunk {}\"}}}}\n\n", i
+        );
+        let mut events = vec![
+            "event: message_start\ndata: {\"type\":\"message_start\",\"message\":{\"usage\":{\"input_tokens\":25}}}\n\n".to_string(),
+        ];
+        events.extend((0..50).map(text_delta));
+        events.push("event: message_delta\ndata: {\"type\":\"message_delta\",\"usage\":{\"output_tokens\":50}}\n\n".to_string());
+        events.push("event: message_stop\ndata: {\"type\":\"message_stop\"}\n\n".to_string());
+        let inner = StreamBody::new(stream::iter(
+            events.into_iter().map(|c| Ok::<_, Infallible>(Frame::data(Bytes::from(c))))
+        ));
+
+        // Logging channel only holds a couple of events, and is drained slowly
+        let (sender, mut receiver) = mpsc::channel(2);
+        let teed_body = TeeBodySSE::new(inner, sender, None);
+        let dropped_chunks = teed_body.dropped_chunks();
+        let slow_logger = tokio::spawn(async move {
+            let mut logged = vec![];
+            while let Some(update) = receiver.recv().await {
+                tokio::time::sleep(Duration::from_millis(5)).await;
+                logged.push(update);
+            }
+            logged
+        });
+
+        tokio::time::timeout(Duration::from_secs(1), teed_body.collect())
+            .await
+            .expect("client stream blocked on the logging consumer")
+            .unwrap();
+
+        // only text deltas are dropped, usage events arrive in order
+        let logged = slow_logger.await.unwrap();
+        assert!(dropped_chunks.load(Ordering::Relaxed) > 0);
+        let usage_events: Vec<&SSEChunk> = logged.iter().filter(|update| carries_usage(update)).collect();
+        assert!(matches!(
+            usage_events.as_slice(),
+            [
+                SSEChunk::InputTokens { input_tokens: 25 },
+                SSEChunk::OutputTokens { output_tokens: 50 },
+                SSEChunk::Stop,
+            ]
+        ), "{:?}", usage_events);
+    }
 }
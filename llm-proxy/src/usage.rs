@@ -1,8 +1,9 @@
 use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use bytes::Bytes;
 use chrono::Utc;
 use color_eyre::eyre::{anyhow, Result};
-use hudsucker::hyper::header::{CONTENT_ENCODING, HeaderMap, HeaderValue};
+use hudsucker::hyper::header::{CONTENT_ENCODING, CONTENT_TYPE, HeaderMap, HeaderValue};
 use serde::{Deserialize, Serialize};
 use tokio::sync::mpsc::Receiver;
 use tracing::{info, error, warn, debug, trace};
@@ -22,6 +23,7 @@ use runtime::tee_attestation::{
     hash_payload_for_tdx_report_data,
 };
 use crate::parser;
+use crate::log_redaction::LogRedaction;
 use crate::tee_body::ChannelError;
 
 // Global static reqwest client with connection pooling
@@ -80,7 +82,7 @@ pub struct SignedUsageReport {
 pub struct UsageReportPayload {
     pub usage: UsageData,
     pub timestamp: i64, // Unix timestamp
-    pub linked_tool_use_id: Option<String>,
+    pub linked_tool_use_ids: Vec<String>, // tool_use_ids of every tool_result in the request
     pub request_id: String,
 }
 
@@ -130,8 +132,10 @@ fn process_and_log_regular_body(
     log_buffer: Vec<u8>,
     headers: &HeaderMap<HeaderValue>,
     signing_key: &Arc<SigningKey>,
+    log_redaction: &LogRedaction,
+    max_body_size: usize,
     request_url: Option<String>,
-    linked_tool_use_id: Option<String>,
+    linked_tool_use_ids: Vec<String>,
     reverie_id: Option<String>,
     report_target_url: String,
     request_id: String,
@@ -139,30 +143,35 @@ fn process_and_log_regular_body(
     spender_type: Option<String>,
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
 
+    // HTML error pages and other non-JSON bodies carry no usage
+    let content_type = headers.get(CONTENT_TYPE).and_then(|h| h.to_str().ok());
+    if !parser::is_json_content_type(content_type) {
+        debug!("Response {}: skipping usage extraction for non-JSON content-type: {:?}", request_id, content_type);
+        return Ok(());
+    }
+
     let content_encoding = headers.get(CONTENT_ENCODING).and_then(|h| h.to_str().ok());
     let decompressed_bytes = parser::decompress_body(
         content_encoding,
-        &Bytes::from(log_buffer)
+        &Bytes::from(log_buffer),
+        max_body_size,
     )?;
 
+    if decompressed_bytes.iter().all(u8::is_ascii_whitespace) {
+        debug!("Response {}: skipping usage extraction for empty body", request_id);
+        return Ok(());
+    }
+    log_redaction.log_body(&format!("Response {}", request_id), &decompressed_bytes);
+
     let mut usage_report_payload = match parser::parse_json_and_extract_usage(
         &decompressed_bytes,
         request_url.as_deref()
     ) {
         Err(e) => {
-            warn!("Failed to parse response body as JSON: {}. Falling back to string.", e);
-            if let Ok(body_str) = std::str::from_utf8(&decompressed_bytes) {
-                info!("Body (String): {} ", body_str);
-            } else {
-                info!("Body: <Non-UTF8 data: {} bytes>", decompressed_bytes.len());
-            }
+            warn!("Failed to parse response body as JSON: {}", e);
             Err(Box::new(e))
         }
-        Ok((json_value, usage_option)) => {
-            match serde_json::to_string_pretty(&json_value) {
-                Ok(pretty_json) => info!("Body (JSON): {} ", pretty_json),
-                Err(_) => info!("Body (Raw JSON): {:?}", json_value),
-            };
+        Ok((_json_value, usage_option)) => {
             let mut usage_data = match usage_option {
                 None => return Err(anyhow!("No usage data found in response.").into()),
                 Some(usage_data) => {
@@ -176,7 +185,7 @@ fn process_and_log_regular_body(
             Ok(UsageReportPayload {
                 usage: usage_data,
                 timestamp: Utc::now().timestamp(),
-                linked_tool_use_id: linked_tool_use_id.clone(),
+                linked_tool_use_ids: linked_tool_use_ids.clone(),
                 request_id: request_id.clone(),
             })
         }
@@ -215,8 +224,10 @@ pub async fn log_regular_response_task(
     mut receiver: Receiver<Result<Bytes, ChannelError>>,
     headers: HeaderMap<HeaderValue>,
     signing_key: Arc<SigningKey>,
+    log_redaction: LogRedaction,
+    max_body_size: usize,
     request_url: Option<String>,
-    linked_tool_use_id: Option<String>,
+    linked_tool_use_ids: Vec<String>,
     reverie_id: Option<String>,
     report_target_url: String,
     request_id: String,
@@ -244,8 +255,10 @@ pub async fn log_regular_response_task(
                 log_buffer,
                 &headers,
                 &signing_key,
+                &log_redaction,
+                max_body_size,
                 request_url,
-                linked_tool_use_id,
+                linked_tool_use_ids,
                 reverie_id,
                 report_target_url,
                 request_id.clone(),
@@ -264,10 +277,11 @@ pub async fn log_regular_response_task(
 /// Background task for SSE responses.
 pub async fn log_sse_response_task(
     mut receiver: Receiver<parser::SSEChunk>,
+    dropped_chunks: Arc<AtomicUsize>,
     _headers: HeaderMap<HeaderValue>,
     signing_key: Arc<SigningKey>,
     request_url: Option<String>,
-    linked_tool_use_id: Option<String>,
+    linked_tool_use_ids: Vec<String>,
     reverie_id: Option<String>,
     report_target_url: String,
     request_id: String,
@@ -313,7 +327,7 @@ pub async fn log_sse_response_task(
                     let payload = UsageReportPayload {
                         usage: final_usage_to_submit.clone(),
                         timestamp: Utc::now().timestamp(),
-                        linked_tool_use_id: linked_tool_use_id.clone(),
+                        linked_tool_use_ids: linked_tool_use_ids.clone(),
                         request_id: request_id.clone(),
                     };
                     // Log tool usage information if present
@@ -355,6 +369,67 @@ pub async fn log_sse_response_task(
         }
     }
     println!("--- SSE Event Stream Ended ---");
+    let dropped = dropped_chunks.load(Ordering::Relaxed);
+    if dropped > 0 {
+        warn!("Request {}: Dropped {} SSE events the logging task couldn't keep up with, usage may be incomplete", request_id, dropped);
+    }
     info!("[{}] Background SSE log task finished for request {}.", Utc::now().to_rfc3339(), request_id);
     info!("Final SSE Token Usage Submitted for request {}: {:?}", request_id, final_usage_to_submit);
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn process_body(body: &[u8], content_type: Option<&str>) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let mut headers = HeaderMap::new();
+        if let Some(content_type) = content_type {
+            headers.insert(CONTENT_TYPE, HeaderValue::from_str(content_type).unwrap());
+        }
+        let signing_key = Arc::new(SigningKey::from_slice(&[7u8; 32]).unwrap());
+        process_and_log_regular_body(
+            body.to_vec(),
+            &headers,
+            &signing_key,
+            &LogRedaction::default(),
+            crate::body_limits::DEFAULT_MAX_BODY_SIZE_BYTES,
+            Some("https://api.anthropic.com/v1/messages".to_string()),
+            vec![],
+            None,
+            // unreachable, a submitted report would fail to send
+            "http://127.0.0.1:1".to_string(),
+            "request_1".to_string(),
+            None,
+            None,
+        )
+    }
+
+    #[test]
+    fn test_json_content_types() {
+        assert!(parser::is_json_content_type(Some("application/json")));
+        assert!(parser::is_json_content_type(Some("application/json; charset=utf-8")));
+        assert!(parser::is_json_content_type(Some("Application/Problem+JSON")));
+        assert!(parser::is_json_content_type(None));
+        assert!(!parser::is_json_content_type(Some("text/html; charset=utf-8")));
+        assert!(!parser::is_json_content_type(Some("text/plain")));
+    }
+
+    #[test]
+    fn test_html_error_body_is_skipped_without_usage_report() {
+        let html = b"<html><body><h1>502 Bad Gateway</h1></body></html>";
+        assert!(process_body(html, Some("text/html")).is_ok());
+    }
+
+    #[test]
+    fn test_empty_body_is_skipped_without_usage_report() {
+        assert!(process_body(b"", Some("application/json")).is_ok());
+        assert!(process_body(b"  \n", None).is_ok());
+    }
+
+    #[test]
+    fn test_json_body_without_usage_still_errors() {
+        // JSON bodies are still parsed, so a missing usage field is reported
+        let err = process_body(br#"{"type": "error"}"#, Some("application/json")).unwrap_err();
+        assert!(err.to_string().contains("No usage data"), "{}", err);
+    }
+}
@@ -1,4 +1,8 @@
 use std::time::Duration;
+use runtime::tee_verifier::TeeVerifierConfig;
+
+/// Default max size of a heartbeat's TEE attestation. TDX QuoteV4s are usually under this.
+pub const DEFAULT_MAX_ATTESTATION_SIZE: usize = 16 * 1024;
 
 #[derive(Debug, Clone)]
 pub struct HeartbeatConfig {
@@ -9,6 +13,11 @@ pub struct HeartbeatConfig {
     /// Max failures allowed.
     /// If reached `HeartbeatHandler` will request closing of the connection.
     pub(crate) max_failures: u32,
+    /// Max bytes of a TEE attestation in heartbeats received from peers.
+    /// Larger heartbeats are dropped before being read in full or parsed.
+    pub(crate) max_attestation_size: usize,
+    /// Verifies the TEE attestations in heartbeats received from peers
+    pub(crate) tee_verifier: TeeVerifierConfig,
 }
 
 impl HeartbeatConfig {
@@ -16,17 +25,27 @@ impl HeartbeatConfig {
         send_timeout: Duration,
         idle_timeout: Duration,
         max_failures: u32,
+        max_attestation_size: usize,
+        tee_verifier: TeeVerifierConfig,
     ) -> Self {
         Self {
             send_timeout,
             idle_timeout,
             max_failures,
+            max_attestation_size,
+            tee_verifier,
         }
     }
 
     pub fn max_time_before_rotation(&self) -> Duration {
         self.send_timeout * self.max_failures.into()
     }
+
+    /// Max bytes of a received heartbeat message. Attestation bytes are JSON-encoded
+    /// as an array of numbers, taking up to 4 bytes each ("255,"), plus the envelope.
+    pub fn max_heartbeat_message_size(&self) -> u64 {
+        self.max_attestation_size as u64 * 4 + 1024
+    }
 }
 
 impl Default for HeartbeatConfig {
@@ -35,6 +54,8 @@ impl Default for HeartbeatConfig {
             send_timeout: Duration::from_secs(60),
             idle_timeout: Duration::from_secs(1),
             max_failures: 5,
+            max_attestation_size: DEFAULT_MAX_ATTESTATION_SIZE,
+            tee_verifier: TeeVerifierConfig::default(),
         }
     }
 }
\ No newline at end of file
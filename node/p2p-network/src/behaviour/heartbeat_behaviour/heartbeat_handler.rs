@@ -1,4 +1,4 @@
-use super::HEARTBEAT_PROTOCOL;
+use crate::protocols::protocol_ids;
 
 use std::{
     pin::Pin,
@@ -28,9 +28,9 @@ use tokio::time::{
     sleep,
     Sleep,
 };
-use tracing::debug;
+use tracing::{debug, warn};
 use super::{HeartbeatConfig, TeeAttestation};
-use super::tee_quote_parser;
+use super::tee_quote_parser::{self, OversizedHeartbeatPayload};
 
 
 #[derive(Debug, Clone)]
@@ -44,7 +44,9 @@ pub enum HeartbeatOutEvent {
     RequestLocalHeartbeatPayloadToSend,
     ResetFailureCount,
     IncrementFailureCount(u32),
-    RefreshLocalTeeAttestation
+    RefreshLocalTeeAttestation,
+    /// The peer sent a heartbeat over the max size, which was dropped
+    OversizedHeartbeatPayload(OversizedHeartbeatPayload),
 }
 
 /// Represents state of the Oubound stream
@@ -92,7 +94,7 @@ impl ConnectionHandler for HeartbeatHandler {
     type OutboundOpenInfo = ();
 
     fn listen_protocol(&self) -> SubstreamProtocol<ReadyUpgrade<&'static str>, ()> {
-        SubstreamProtocol::new(ReadyUpgrade::new(HEARTBEAT_PROTOCOL), ())
+        SubstreamProtocol::new(ReadyUpgrade::new(protocol_ids().heartbeat.as_str()), ())
     }
 
     fn connection_keep_alive(&self) -> bool {
@@ -108,13 +110,19 @@ impl ConnectionHandler for HeartbeatHandler {
         //// Inbound Events
         if let Some(inbound_stream) = self.inbound.as_mut() {
             match inbound_stream.poll_unpin(cx) {
-                Poll::Ready(Err(_)) => {
-                    debug!(target: "heartbeat", "Incoming heartbeat errored");
+                Poll::Ready(Err(e)) => {
                     self.inbound = None;
+                    if let Some(oversized) = e.downcast_ref::<OversizedHeartbeatPayload>() {
+                        warn!(target: "heartbeat", "Dropped incoming heartbeat: {}", oversized);
+                        return Poll::Ready(ConnectionHandlerEvent::NotifyBehaviour(
+                            HeartbeatOutEvent::OversizedHeartbeatPayload(oversized.clone()),
+                        ))
+                    }
+                    debug!(target: "heartbeat", "Incoming heartbeat errored");
                 }
                 Poll::Ready(Ok((stream, tee_attestation))) => {
                     // start waiting for the next `TeeAttestation`
-                    self.inbound = Some(tee_quote_parser::receive_heartbeat_payload(stream).boxed());
+                    self.inbound = Some(tee_quote_parser::receive_heartbeat_payload(stream, self.config.clone()).boxed());
                     // report newly received peer `TeeAttestation` to heartbeat_behaviour/mod.rs
                     return Poll::Ready(ConnectionHandlerEvent::NotifyBehaviour(
                         HeartbeatOutEvent::HeartbeatPayload(tee_attestation),
@@ -210,7 +218,7 @@ impl ConnectionHandler for HeartbeatHandler {
                     self.outbound = Some(OutboundState::NegotiatingStream);
 
                     return Poll::Ready(ConnectionHandlerEvent::OutboundSubstreamRequest {
-                        protocol: SubstreamProtocol::new(ReadyUpgrade::new(HEARTBEAT_PROTOCOL), ())
+                        protocol: SubstreamProtocol::new(ReadyUpgrade::new(protocol_ids().heartbeat.as_str()), ())
                             .with_timeout(self.config.send_timeout)
                     })
                 }
@@ -248,7 +256,7 @@ impl ConnectionHandler for HeartbeatHandler {
             ConnectionEvent::FullyNegotiatedInbound(FullyNegotiatedInbound {
                 protocol: stream, ..
             }) => {
-                self.inbound = Some(tee_quote_parser::receive_heartbeat_payload(stream).boxed());
+                self.inbound = Some(tee_quote_parser::receive_heartbeat_payload(stream, self.config.clone()).boxed());
             }
             ConnectionEvent::FullyNegotiatedOutbound(FullyNegotiatedOutbound {
                 protocol: stream, ..
@@ -2,7 +2,7 @@ pub(crate) mod heartbeat_handler;
 mod config;
 mod tee_quote_parser;
 
-use std::collections::VecDeque;
+use std::collections::{HashMap, VecDeque};
 use std::task::Poll;
 use std::sync::Arc;
 use color_eyre::{Result, eyre};
@@ -12,6 +12,7 @@ use libp2p::{
     core::{transport::PortUse, Endpoint},
     swarm::{
         derive_prelude::ConnectionId,
+        ConnectionClosed,
         ConnectionDenied,
         FromSwarm,
         NetworkBehaviour,
@@ -27,7 +28,7 @@ use libp2p::{
 use tokio::sync::mpsc;
 use tracing::debug;
 
-pub use config::HeartbeatConfig;
+pub use config::{HeartbeatConfig, DEFAULT_MAX_ATTESTATION_SIZE};
 use heartbeat_handler::{
     HeartbeatHandler,
     HeartbeatInEvent,
@@ -35,10 +36,13 @@ use heartbeat_handler::{
 };
 use runtime::tee_attestation;
 use runtime::tee_attestation::QuoteV4;
-pub use tee_quote_parser::TeeAttestation;
+use runtime::tee_verifier::TeeVerifier;
+pub use tee_quote_parser::{TeeAttestation, OversizedHeartbeatPayload};
 
 
-pub const HEARTBEAT_PROTOCOL: &str = "/reveries/heartbeat/0.0.1";
+/// Max number of heartbeats buffered for NodeClient subscribers.
+/// When full, the oldest heartbeat is dropped to make room for the latest one.
+pub const HEARTBEAT_CHANNEL_CAPACITY: usize = 100;
 
 #[derive(Debug, Clone)]
 enum HeartbeatAction {
@@ -66,6 +70,8 @@ pub struct HeartbeatBehaviour {
     /// and disconnect from Swarm, or shutdown the LLM runtime.
     pub(crate) internal_heartbeat_fail_sender: mpsc::Sender<HeartbeatConfig>,
 
+    /// Bounded channel exposing heartbeats to NodeClient subscribers.
+    /// Uses a drop-oldest policy so a slow or absent subscriber never blocks polling.
     pub(crate) heartbeat_sender: async_channel::Sender<TeePayloadOutEvent>,
 
     pending_events: VecDeque<HeartbeatAction>,
@@ -77,6 +83,21 @@ pub struct HeartbeatBehaviour {
     // It is not related to the PRE re-incarnation protocol--that is determined by external nodes
     // after they don't hear from this node for a while.
     pub(crate) internal_fail_count: std::sync::Arc<u32>,
+
+    /// Peers whose heartbeats were dropped for exceeding the max size,
+    /// drained by NetworkEvents to record the failure against them.
+    rejected_heartbeats: Vec<(PeerId, OversizedHeartbeatPayload)>,
+
+    /// Verifies TEE attestations in heartbeats received from peers
+    tee_verifier: Arc<dyn TeeVerifier>,
+
+    /// Peers whose heartbeats were dropped for failing attestation verification,
+    /// drained by NetworkEvents alongside `rejected_heartbeats`.
+    invalid_attestations: Vec<(PeerId, String)>,
+
+    /// Last attestation verified for each connected peer and the outcome, so the quote
+    /// a peer repeats in every heartbeat is verified once instead of on every poll
+    verified_attestations: HashMap<PeerId, (Vec<u8>, Result<(), String>)>,
 }
 
 impl HeartbeatBehaviour {
@@ -85,6 +106,7 @@ impl HeartbeatBehaviour {
         internal_heartbeat_fail_sender: mpsc::Sender<HeartbeatConfig>,
         heartbeat_sender: async_channel::Sender<TeePayloadOutEvent>,
     ) -> Self {
+        let tee_verifier = config.tee_verifier.verifier();
         Self {
             config,
             internal_heartbeat_fail_sender,
@@ -92,10 +114,22 @@ impl HeartbeatBehaviour {
             pending_events: VecDeque::default(),
             current_heartbeat_payload: TeeAttestation::default(),
             internal_fail_count: std::sync::Arc::new(0),
+            rejected_heartbeats: Vec::new(),
+            tee_verifier,
+            invalid_attestations: Vec::new(),
+            verified_attestations: HashMap::new(),
         }
     }
 
     pub fn set_tee_attestation(&mut self, tee_attestation: Vec<u8>) {
+        // peers would drop heartbeats carrying it
+        if tee_attestation.len() > self.config.max_attestation_size {
+            tracing::error!("TEE attestation of {} bytes exceeds max_attestation_size {}, not broadcasting it",
+                tee_attestation.len(),
+                self.config.max_attestation_size
+            );
+            return
+        }
         let quote = QuoteV4::from_bytes(&tee_attestation);
         // can't deserialize QuoteV4 back to bytes (unless we fork the lib), so save both.
         self.current_heartbeat_payload.tee_attestation = Some(quote);
@@ -123,6 +157,57 @@ impl HeartbeatBehaviour {
         }
     }
 
+    /// Adds `n` heartbeat failures, and pushes ShutdownIfMaxFailuresExceeded to
+    /// pending events for async processing in poll()
+    pub(crate) fn apply_failure(&mut self, n: u32) {
+        self.internal_fail_count = (self.internal_fail_count.saturating_add(n)).into();
+
+        debug!(target: "heartbeat",
+            "Sending Heartbeat timed out, failed {} time(s) with this connection",
+            self.internal_fail_count
+        );
+
+        self.pending_events.push_back(HeartbeatAction::ShutdownIfMaxFailuresExceeded);
+
+        debug!(target: "heartbeat", "Sending Heartbeat failed, {}/{} failures for this connection",
+            self.internal_fail_count,
+            self.config.max_failures
+        );
+    }
+
+    pub(crate) fn reset_failures(&mut self) {
+        self.internal_fail_count = 0.into();
+    }
+
+    pub(crate) fn drain_rejected_heartbeats(&mut self) -> Vec<(PeerId, OversizedHeartbeatPayload)> {
+        std::mem::take(&mut self.rejected_heartbeats)
+    }
+
+    pub(crate) fn drain_invalid_attestations(&mut self) -> Vec<(PeerId, String)> {
+        std::mem::take(&mut self.invalid_attestations)
+    }
+
+    /// Verifies a peer's heartbeat attestation, reusing the outcome when the peer
+    /// sends the same quote again. Missing attestations fail unless the verifier is a mock.
+    fn verify_attestation(&mut self, peer_id: PeerId, quote_bytes: Option<&Vec<u8>>) -> Result<(), String> {
+        let Some(quote_bytes) = quote_bytes else {
+            return match self.tee_verifier.requires_attestation() {
+                true => Err("Heartbeat is missing a TEE attestation".to_string()),
+                false => Ok(()),
+            }
+        };
+        if let Some((verified_quote, outcome)) = self.verified_attestations.get(&peer_id) {
+            if verified_quote == quote_bytes {
+                return outcome.clone()
+            }
+        }
+        let outcome = self.tee_verifier.verify(quote_bytes)
+            .map(|_report| ())
+            .map_err(|e| e.to_string());
+        self.verified_attestations.insert(peer_id, (quote_bytes.clone(), outcome.clone()));
+        outcome
+    }
+
     fn surface_shutdown_signal_to_container_manager(
         &mut self,
         cx: &mut std::task::Context<'_>
@@ -142,6 +227,11 @@ impl HeartbeatBehaviour {
     }
 }
 
+/// The runtime is shut down once heartbeat failures exceed `max_failures`
+pub(crate) fn should_shutdown(fail_count: u32, config: &HeartbeatConfig) -> bool {
+    fail_count > config.max_failures
+}
+
 impl NetworkBehaviour for HeartbeatBehaviour {
     type ConnectionHandler = HeartbeatHandler;
     type ToSwarm = TeePayloadOutEvent;
@@ -171,7 +261,11 @@ impl NetworkBehaviour for HeartbeatBehaviour {
         ))
     }
 
-    fn on_swarm_event(&mut self, _event: FromSwarm) {}
+    fn on_swarm_event(&mut self, event: FromSwarm) {
+        if let FromSwarm::ConnectionClosed(ConnectionClosed { peer_id, remaining_established: 0, .. }) = event {
+            self.verified_attestations.remove(&peer_id);
+        }
+    }
 
     // heartbeat_handler.rs propagates poll() events up to this handler
     fn on_connection_handler_event(
@@ -183,6 +277,14 @@ impl NetworkBehaviour for HeartbeatBehaviour {
         match event {
             // Incoming Heartbeats from other Peers
             HeartbeatOutEvent::HeartbeatPayload(latest_tee_attestation) => {
+                if let Err(e) = self.verify_attestation(
+                    peer_id,
+                    latest_tee_attestation.tee_attestation_bytes.as_ref()
+                ) {
+                    // dropped without a HeartbeatEvent, so the peer isn't marked fresh
+                    self.invalid_attestations.push((peer_id, e));
+                    return
+                }
                 // push onto pending_events, which will be poll()'d and executed
                 self.pending_events.push_back(
                     HeartbeatAction::HeartbeatEvent(TeePayloadOutEvent {
@@ -192,7 +294,7 @@ impl NetworkBehaviour for HeartbeatBehaviour {
                 );
             }
             HeartbeatOutEvent::ResetFailureCount => {
-                self.internal_fail_count = 0.into();
+                self.reset_failures();
             }
             // Dispatch request for a Heartbeat from other Peers
             HeartbeatOutEvent::RequestLocalHeartbeatPayloadToSend => {
@@ -209,20 +311,7 @@ impl NetworkBehaviour for HeartbeatBehaviour {
             }
             HeartbeatOutEvent::IncrementFailureCount(n) => {
                 // bubble up some command to EventLoop to shut the Runtime down
-                // push ShutdownIfMaxFailuresExceeded to pending events for async processing
-                self.internal_fail_count = (self.internal_fail_count.saturating_add(n)).into();
-
-                debug!(target: "heartbeat",
-                    "Sending Heartbeat timed out, failed {} time(s) with this connection",
-                    self.internal_fail_count
-                );
-
-                self.pending_events.push_back(HeartbeatAction::ShutdownIfMaxFailuresExceeded);
-
-                debug!(target: "heartbeat", "Sending Heartbeat failed, {}/{} failures for this connection",
-                    self.internal_fail_count,
-                    self.config.max_failures
-                );
+                self.apply_failure(n);
             }
             HeartbeatOutEvent::RefreshLocalTeeAttestation => {
 
@@ -234,6 +323,10 @@ impl NetworkBehaviour for HeartbeatBehaviour {
 
                 self.set_tee_attestation(tee_quote_bytes);
             }
+            HeartbeatOutEvent::OversizedHeartbeatPayload(oversized) => {
+                // dropped without a HeartbeatEvent, so the peer isn't marked fresh
+                self.rejected_heartbeats.push((peer_id, oversized));
+            }
         }
     }
 
@@ -247,12 +340,11 @@ impl NetworkBehaviour for HeartbeatBehaviour {
 
                 HeartbeatAction::HeartbeatEvent(tee_event) => {
 
-                    // send to NodeClient, expose stream to subscribers
-                    let _ = async {
-                        self.heartbeat_sender.send(tee_event.clone()).await
+                    // send to NodeClient, expose stream to subscribers.
+                    // If the channel is full, drop the oldest heartbeat instead of blocking.
+                    if let Ok(Some(dropped)) = self.heartbeat_sender.force_send(tee_event.clone()) {
+                        debug!(target: "heartbeat", "Heartbeat channel full, dropped oldest heartbeat from {}", dropped.peer_id);
                     }
-                    .boxed()
-                    .poll_unpin(cx);
 
                     return Poll::Ready(ToSwarm::GenerateEvent(tee_event))
                 },
@@ -280,7 +372,7 @@ impl NetworkBehaviour for HeartbeatBehaviour {
                         )
                     );
 
-                    if self.internal_fail_count.as_ref() > &self.config.max_failures {
+                    if should_shutdown(*self.internal_fail_count, &self.config) {
                         let _ = self.surface_shutdown_signal_to_container_manager(cx);
                     }
                     // return pending to async runtime and continue
@@ -293,3 +385,232 @@ impl NetworkBehaviour for HeartbeatBehaviour {
     }
 
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::task::noop_waker_ref;
+    use runtime::tee_verifier::TeeVerifierConfig;
+
+    fn heartbeat_event(block_height: u32) -> TeePayloadOutEvent {
+        let mut latest_tee_attestation = TeeAttestation::default();
+        latest_tee_attestation.block_height = block_height;
+        TeePayloadOutEvent {
+            peer_id: PeerId::random(),
+            latest_tee_attestation,
+        }
+    }
+
+    #[test]
+    fn full_heartbeat_channel_drops_oldest_without_blocking_poll() {
+        let (internal_heartbeat_fail_sender, _fail_receiver) = mpsc::channel(1);
+        let (heartbeat_sender, heartbeat_receiver) = async_channel::bounded(2);
+        let mut behaviour = HeartbeatBehaviour::new(
+            HeartbeatConfig::default(),
+            internal_heartbeat_fail_sender,
+            heartbeat_sender,
+        );
+
+        let mut cx = std::task::Context::from_waker(noop_waker_ref());
+        for block_height in 1..=5 {
+            behaviour.pending_events.push_back(HeartbeatAction::HeartbeatEvent(heartbeat_event(block_height)));
+            // poll must emit the event even though no subscriber drains the channel
+            match behaviour.poll(&mut cx) {
+                Poll::Ready(ToSwarm::GenerateEvent(event)) => {
+                    assert_eq!(event.latest_tee_attestation.block_height, block_height);
+                }
+                _ => panic!("expected heartbeat event for block_height {}", block_height),
+            }
+        }
+
+        assert_eq!(heartbeat_receiver.len(), 2);
+        let block_heights: Vec<u32> = std::iter::from_fn(|| heartbeat_receiver.try_recv().ok())
+            .map(|event| event.latest_tee_attestation.block_height)
+            .collect();
+        assert_eq!(block_heights, vec![4, 5]);
+    }
+
+    #[test]
+    fn oversized_heartbeat_is_dropped_and_recorded_against_peer() {
+        let (internal_heartbeat_fail_sender, _fail_receiver) = mpsc::channel(1);
+        let (heartbeat_sender, heartbeat_receiver) = async_channel::bounded(2);
+        let mut behaviour = HeartbeatBehaviour::new(
+            HeartbeatConfig::default(),
+            internal_heartbeat_fail_sender,
+            heartbeat_sender,
+        );
+        let peer_id = PeerId::random();
+        let oversized = OversizedHeartbeatPayload { size: 8 * 1024 * 1024, max_size: 16 * 1024 };
+
+        behaviour.on_connection_handler_event(
+            peer_id,
+            ConnectionId::new_unchecked(0),
+            HeartbeatOutEvent::OversizedHeartbeatPayload(oversized.clone()),
+        );
+
+        // no heartbeat reaches the swarm or subscribers
+        let mut cx = std::task::Context::from_waker(noop_waker_ref());
+        assert!(behaviour.poll(&mut cx).is_pending());
+        assert!(heartbeat_receiver.is_empty());
+
+        assert_eq!(behaviour.drain_rejected_heartbeats(), vec![(peer_id, oversized)]);
+        assert!(behaviour.drain_rejected_heartbeats().is_empty());
+    }
+
+    #[test]
+    fn heartbeat_attestations_are_checked_by_the_configured_verifier() -> Result<()> {
+        let (_quote, quote_bytes) = tee_attestation::generate_tee_attestation_with_data([0; 64], false)?;
+        let mut latest_tee_attestation = TeeAttestation::default();
+        latest_tee_attestation.tee_attestation_bytes = Some(quote_bytes);
+
+        let mut cx = std::task::Context::from_waker(noop_waker_ref());
+        for accept in [true, false] {
+            let (internal_heartbeat_fail_sender, _fail_receiver) = mpsc::channel(1);
+            let (heartbeat_sender, heartbeat_receiver) = async_channel::bounded(2);
+            let mut behaviour = HeartbeatBehaviour::new(
+                HeartbeatConfig {
+                    tee_verifier: TeeVerifierConfig::Mock { accept },
+                    ..HeartbeatConfig::default()
+                },
+                internal_heartbeat_fail_sender,
+                heartbeat_sender,
+            );
+            let peer_id = PeerId::random();
+
+            behaviour.on_connection_handler_event(
+                peer_id,
+                ConnectionId::new_unchecked(0),
+                HeartbeatOutEvent::HeartbeatPayload(latest_tee_attestation.clone()),
+            );
+
+            if accept {
+                match behaviour.poll(&mut cx) {
+                    Poll::Ready(ToSwarm::GenerateEvent(event)) => assert_eq!(event.peer_id, peer_id),
+                    _ => panic!("expected heartbeat event with an accepted attestation"),
+                }
+                assert_eq!(heartbeat_receiver.len(), 1);
+                assert!(behaviour.drain_invalid_attestations().is_empty());
+            } else {
+                assert!(behaviour.poll(&mut cx).is_pending());
+                assert!(heartbeat_receiver.is_empty());
+                let invalid_attestations = behaviour.drain_invalid_attestations();
+                assert_eq!(invalid_attestations.len(), 1);
+                assert_eq!(invalid_attestations[0].0, peer_id);
+            }
+        }
+        Ok(())
+    }
+
+    /// Accepts every quote, counting how many it was asked to verify
+    #[derive(Debug, Default)]
+    struct CountingVerifier {
+        verified: std::sync::atomic::AtomicUsize,
+    }
+
+    impl TeeVerifier for CountingVerifier {
+        fn verify(&self, _quote_bytes: &[u8]) -> Result<runtime::tee_verifier::AttestationReport> {
+            self.verified.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            Ok(runtime::tee_verifier::AttestationReport {
+                tee_type: "counting".to_string(),
+                report_data: vec![],
+            })
+        }
+    }
+
+    #[test]
+    fn repeated_attestations_are_verified_once_per_peer() {
+        let (internal_heartbeat_fail_sender, _fail_receiver) = mpsc::channel(1);
+        let (heartbeat_sender, _heartbeat_receiver) = async_channel::bounded(10);
+        let mut behaviour = HeartbeatBehaviour::new(
+            HeartbeatConfig::default(),
+            internal_heartbeat_fail_sender,
+            heartbeat_sender,
+        );
+        let verifier = Arc::new(CountingVerifier::default());
+        behaviour.tee_verifier = verifier.clone();
+
+        let heartbeat = |quote_bytes: Vec<u8>| {
+            let mut latest_tee_attestation = TeeAttestation::default();
+            latest_tee_attestation.tee_attestation_bytes = Some(quote_bytes);
+            HeartbeatOutEvent::HeartbeatPayload(latest_tee_attestation)
+        };
+        let peer_a = PeerId::random();
+        let peer_b = PeerId::random();
+        let connection_id = ConnectionId::new_unchecked(0);
+
+        behaviour.on_connection_handler_event(peer_a, connection_id, heartbeat(vec![1; 8]));
+        behaviour.on_connection_handler_event(peer_a, connection_id, heartbeat(vec![1; 8]));
+        assert_eq!(verifier.verified.load(std::sync::atomic::Ordering::SeqCst), 1);
+
+        // a new quote, or the same quote from another peer, is verified again
+        behaviour.on_connection_handler_event(peer_a, connection_id, heartbeat(vec![2; 8]));
+        behaviour.on_connection_handler_event(peer_b, connection_id, heartbeat(vec![2; 8]));
+        assert_eq!(verifier.verified.load(std::sync::atomic::Ordering::SeqCst), 3);
+        assert_eq!(behaviour.pending_events.len(), 4);
+    }
+
+    #[test]
+    fn missing_attestation_fails_unless_the_verifier_is_a_mock() {
+        for (tee_verifier, rejected) in [
+            (TeeVerifierConfig::Dcap, true),
+            (TeeVerifierConfig::Mock { accept: true }, false),
+        ] {
+            let (internal_heartbeat_fail_sender, _fail_receiver) = mpsc::channel(1);
+            let (heartbeat_sender, _heartbeat_receiver) = async_channel::bounded(2);
+            let mut behaviour = HeartbeatBehaviour::new(
+                HeartbeatConfig { tee_verifier, ..HeartbeatConfig::default() },
+                internal_heartbeat_fail_sender,
+                heartbeat_sender,
+            );
+            let peer_id = PeerId::random();
+
+            behaviour.on_connection_handler_event(
+                peer_id,
+                ConnectionId::new_unchecked(0),
+                HeartbeatOutEvent::HeartbeatPayload(TeeAttestation::default()),
+            );
+
+            let invalid_attestations = behaviour.drain_invalid_attestations();
+            assert_eq!(invalid_attestations.len(), rejected as usize, "{:?}", tee_verifier);
+            assert_eq!(behaviour.pending_events.is_empty(), rejected, "{:?}", tee_verifier);
+        }
+    }
+
+    #[test]
+    fn should_shutdown_only_after_max_failures_exceeded() {
+        let config = HeartbeatConfig::default();
+        assert!(!should_shutdown(0, &config));
+        assert!(!should_shutdown(config.max_failures, &config));
+        assert!(should_shutdown(config.max_failures + 1, &config));
+    }
+
+    #[test]
+    fn failures_beyond_max_signal_shutdown_until_reset() {
+        let (internal_heartbeat_fail_sender, mut fail_receiver) = mpsc::channel(1);
+        let (heartbeat_sender, _heartbeat_receiver) = async_channel::bounded(1);
+        let mut behaviour = HeartbeatBehaviour::new(
+            HeartbeatConfig::default(),
+            internal_heartbeat_fail_sender,
+            heartbeat_sender,
+        );
+        let max_failures = behaviour.config.max_failures;
+        let mut cx = std::task::Context::from_waker(noop_waker_ref());
+
+        // Reaching max_failures doesn't shut down
+        behaviour.apply_failure(max_failures);
+        assert!(behaviour.poll(&mut cx).is_pending());
+        assert!(fail_receiver.try_recv().is_err());
+
+        // A reset clears accumulated failures
+        behaviour.reset_failures();
+        behaviour.apply_failure(1);
+        assert_eq!(*behaviour.internal_fail_count, 1);
+        assert!(behaviour.poll(&mut cx).is_pending());
+        assert!(fail_receiver.try_recv().is_err());
+
+        // Exceeding max_failures surfaces the shutdown signal
+        behaviour.apply_failure(max_failures);
+        assert!(behaviour.poll(&mut cx).is_pending());
+        assert!(fail_receiver.try_recv().is_ok());
+    }
+}
@@ -7,6 +7,7 @@ use futures::{
 };
 use serde::{Deserialize, Serialize};
 use runtime::tee_attestation::QuoteV4;
+use super::HeartbeatConfig;
 
 const MSG_LEN_SIZE: u64 = 8;
 const HEARTBEAT_MESSAGE_MAX_SIZE: u64 = 1024*24; // 24 kb
@@ -66,9 +67,26 @@ impl From<TeeAttestationBytes> for TeeAttestation {
     }
 }
 
+/// A peer's heartbeat exceeded the configured max size, and was dropped unparsed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OversizedHeartbeatPayload {
+    pub size: u64,
+    pub max_size: u64,
+}
+
+impl std::fmt::Display for OversizedHeartbeatPayload {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "heartbeat payload of {} bytes exceeds max size of {} bytes", self.size, self.max_size)
+    }
+}
+
+impl std::error::Error for OversizedHeartbeatPayload {}
+
 /// Takes in a stream. Waits to receive next `TeeAttestationBytes`
-/// Returns the flushed stream and the received `TeeAttestationBytes`
-pub(super) async fn receive_heartbeat_payload<S>(mut stream: S) -> Result<(S, TeeAttestation)>
+/// Returns the flushed stream and the received `TeeAttestationBytes`.
+/// Messages or attestations over the config's max sizes are rejected before
+/// being read in full or parsed, with an `OversizedHeartbeatPayload` error.
+pub(super) async fn receive_heartbeat_payload<S>(mut stream: S, config: HeartbeatConfig) -> Result<(S, TeeAttestation)>
     where S: AsyncRead + AsyncWrite + Unpin,
 {
     let mut msg_len_array = [0u8; MSG_LEN_SIZE as usize];
@@ -79,6 +97,14 @@ pub(super) async fn receive_heartbeat_payload<S>(mut stream: S) -> Result<(S, Te
     // parse the msg length
     let msg_len: u64 = u64::from_be_bytes(msg_len_array);
 
+    // don't allocate a buffer for whatever length a peer claims
+    if msg_len > config.max_heartbeat_message_size() {
+        return Err(OversizedHeartbeatPayload {
+            size: msg_len,
+            max_size: config.max_heartbeat_message_size(),
+        }.into())
+    }
+
     // then read the rest of the payload
     let mut payload = vec![0u8; msg_len as usize];
     stream.read_exact(&mut payload).await?;
@@ -100,6 +126,13 @@ pub(super) async fn receive_heartbeat_payload<S>(mut stream: S) -> Result<(S, Te
             Err(e.into())
         }
         Ok(tee_attestation_bytes) => {
+            let attestation_size = tee_attestation_bytes.tee_attestation_bytes.as_ref().map_or(0, Vec::len);
+            if attestation_size > config.max_attestation_size {
+                return Err(OversizedHeartbeatPayload {
+                    size: attestation_size as u64,
+                    max_size: config.max_attestation_size as u64,
+                }.into())
+            }
             let tee_attestation = TeeAttestation::from(tee_attestation_bytes);
             Ok((stream, tee_attestation))
         }
@@ -129,4 +162,40 @@ pub(super) async fn send_heartbeat_payload<S>(mut stream: S, tee_attestation: Te
     stream.write_all(&full_msg_bytes).await?;
     stream.flush().await?;
     Ok(stream)
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::io::Cursor;
+
+    fn heartbeat_message(tee_attestation_bytes: Option<Vec<u8>>) -> Vec<u8> {
+        let msg_bytes = serde_json::to_vec(&TeeAttestationBytes {
+            tee_attestation_bytes,
+            block_height: 7,
+        }).unwrap();
+        [(msg_bytes.len() as u64).to_be_bytes().to_vec(), msg_bytes].concat()
+    }
+
+    fn config(max_attestation_size: usize) -> HeartbeatConfig {
+        HeartbeatConfig { max_attestation_size, ..HeartbeatConfig::default() }
+    }
+
+    #[tokio::test]
+    async fn oversized_attestation_is_rejected_before_parsing() {
+        let stream = Cursor::new(heartbeat_message(Some(vec![0u8; 512])));
+        let err = receive_heartbeat_payload(stream, config(256)).await.unwrap_err();
+        let oversized = err.downcast_ref::<OversizedHeartbeatPayload>().unwrap();
+        assert_eq!(oversized.size, 512);
+        assert_eq!(oversized.max_size, 256);
+
+        // a peer claiming a huge message length is rejected before reading it
+        let stream = Cursor::new(u64::MAX.to_be_bytes().to_vec());
+        let err = receive_heartbeat_payload(stream, config(256)).await.unwrap_err();
+        assert!(err.downcast_ref::<OversizedHeartbeatPayload>().is_some());
+
+        let stream = Cursor::new(heartbeat_message(None));
+        let (_stream, tee_attestation) = receive_heartbeat_payload(stream, config(256)).await.unwrap();
+        assert_eq!(tee_attestation.block_height, 7);
+    }
+}
@@ -2,10 +2,14 @@ pub(crate) mod heartbeat_behaviour;
 
 use color_eyre::Result;
 use libp2p::{
+    connection_limits,
+    dcutr,
     gossipsub,
     kad,
+    mdns,
+    relay,
     request_response,
-    swarm::NetworkBehaviour
+    swarm::{NetworkBehaviour, behaviour::toggle::Toggle}
 };
 use crate::types::{FragmentRequestEnum, FragmentResponseEnum};
 
@@ -14,6 +18,9 @@ use crate::types::{FragmentRequestEnum, FragmentResponseEnum};
 #[derive(NetworkBehaviour)]
 pub struct Behaviour {
 
+    /// Caps pending and established connections so churn can't grow peer state unbounded
+    pub connection_limits: connection_limits::Behaviour,
+
     // /// The Behaviour to manage connections to blocked peers.
     // blocked_peer: allow_block_list::Behaviour<allow_block_list::BlockedPeers>,
 
@@ -28,6 +35,15 @@ pub struct Behaviour {
     /// The Behaviour to identify peers.
     pub identify: libp2p_identify::Behaviour,
 
+    /// Optional mDNS discovery of peers on the local network, for zero-config dev setups
+    pub mdns: Toggle<mdns::tokio::Behaviour>,
+
+    /// Optional relay client, so nodes behind NAT can be reached through a relay's circuit
+    pub relay_client: Toggle<relay::client::Behaviour>,
+
+    /// Optional hole punching, to upgrade relayed connections to direct ones
+    pub dcutr: Toggle<dcutr::Behaviour>,
+
     // /// Deprecated in favor of request-response protocol
     // pub gossipsub: gossipsub::Behaviour
 }
@@ -1,23 +1,29 @@
 use std::{
     collections::hash_map::DefaultHasher,
     hash::{Hash, Hasher},
+    num::NonZeroUsize,
     time::Duration,
 };
 use color_eyre::Result;
 use color_eyre::eyre::anyhow;
 use libp2p::{
+    connection_limits::{self, ConnectionLimits},
+    dcutr,
     dns,
     gossipsub,
     identity,
     kad,
-    multiaddr::Multiaddr,
+    mdns,
+    multiaddr::{Multiaddr, Protocol},
     noise,
+    request_response,
+    swarm::Swarm,
     tcp,
     yamux,
     PeerId,
     StreamProtocol,
 };
-use tokio::sync::{mpsc, RwLock};
+use tokio::sync::{broadcast, mpsc, watch, RwLock};
 use std::sync::Arc;
 use std::path::Path;
 use ed25519_dalek::{VerifyingKey as EdVerifyingKey, PUBLIC_KEY_LENGTH};
@@ -28,49 +34,248 @@ use libp2p_identity::PublicKey;
 use std::env;
 use std::fs;
 
-use crate::SendError;
-use crate::types::NetworkEvent;
+use crate::{SendError, TryPeerId};
+use crate::types::{
+    NetworkEvent,
+    NODE_EVENT_CHANNEL_CAPACITY,
+    FragmentRequestEnum,
+    FragmentResponseEnum,
+    DEFAULT_MAX_REVERIE_PAYLOAD_SIZE,
+};
 use crate::behaviour::Behaviour;
 use crate::behaviour::heartbeat_behaviour::{
     HeartbeatBehaviour,
-    HeartbeatConfig
+    HeartbeatConfig,
+    TeePayloadOutEvent,
+    DEFAULT_MAX_ATTESTATION_SIZE,
+    HEARTBEAT_CHANNEL_CAPACITY,
 };
 use crate::network_events::{NetworkEvents, NodeIdentity};
 use crate::node_client::{NodeClient, ContainerManager};
 use crate::usage_db::init_usage_db;
 use crate::env_var::EnvVars;
 use crate::utils::pubkeys::generate_peer_keys;
+use crate::protocols::protocol_ids;
 use runtime::near_runtime::{NearConfig, NearRuntime};
+use runtime::evm_runtime::{EvmConfig, EvmRuntime};
+use runtime::tee_verifier::TeeVerifierConfig;
 
-/// Creates the network components, namely:
-/// - The network client to interact with the network layer from anywhere within your application.
-/// - The network event stream, e.g. for incoming requests.
-/// - The network task driving the network itself.
-pub async fn new(
-    secret_key_seed: Option<usize>,
-    listen_address: Vec<Multiaddr>,
-    bootstrap_nodes: Vec<(String, Multiaddr)>,
-) -> Result<NodeClient> {
+/// Swarm-level network options, set from the node's CLI opts.
+#[derive(Debug, Clone)]
+pub struct NetworkConfig {
+    /// Discover peers on the local network via mDNS, without a bootstrap list.
+    pub enable_mdns: bool,
+    /// Reserve circuits on relays and hole punch through them, for nodes behind NAT.
+    /// Listen on `<relay addr>/p2p/<relay id>/p2p-circuit` to be reachable via a relay.
+    pub enable_relay_client: bool,
+    /// Max inbound connections still being negotiated.
+    pub max_pending_incoming: Option<u32>,
+    /// Max established inbound connections. Further inbound connections are denied.
+    pub max_established_incoming: Option<u32>,
+    /// Max established connections in total (inbound and outbound).
+    pub max_established_total: Option<u32>,
+    /// Max established connections to a single peer.
+    pub max_established_per_peer: Option<u32>,
+    /// Time to wait for a response before a request-response request fails with a timeout.
+    pub request_timeout: Duration,
+    /// Number of times a timed out cfrag request is re-sent before failing.
+    pub cfrag_request_max_retries: u32,
+    /// Lifetime of Kademlia records stored on peers. Records of dead nodes age out after this.
+    pub record_ttl: Duration,
+    /// Interval at which this node republishes its own Kademlia records, refreshing their expiry.
+    /// Should be well under `record_ttl`.
+    pub record_republish_interval: Duration,
+    /// Number of closest peers a Kademlia record is stored on. Higher values keep vessel status
+    /// and Reverie holder records alive through more node failures, at the cost of more traffic.
+    pub kademlia_replication_factor: NonZeroUsize,
+    /// Max concurrent requests per Kademlia query.
+    pub kademlia_query_parallelism: NonZeroUsize,
+    /// Interval at which kfrag providers purge cfrags of expired reveries.
+    pub cfrag_expiry_sweep_interval: Duration,
+    /// Age after which pending network requests with no response are resolved as failed,
+    /// checked every `cfrag_expiry_sweep_interval`.
+    pub pending_request_timeout: Duration,
+    /// How long granted or denied contract access checks (e.g. NearContract `can_spend`)
+    /// are reused for identical fragment requests. Zero re-checks the chain every time.
+    pub access_decision_cache_ttl: Duration,
+    /// How long a next vessel waits for peers to confirm a failed vessel is offline before
+    /// dropping the vote. A new vote starts on the next heartbeat check if it is still offline.
+    pub respawn_vote_timeout: Duration,
+    /// Blocks a peer's heartbeat block_height may run ahead of its previous heartbeat's,
+    /// beyond one block per second elapsed, and a peer's first heartbeat may differ from
+    /// this node's block height. Heartbeats outside that range are ignored.
+    pub heartbeat_block_height_tolerance: u32,
+    /// Max size in bytes of a Reverie's plaintext secrets. Larger Reveries are not created,
+    /// and inbound ciphertexts or keyfrags above this size are rejected.
+    pub max_reverie_payload_size: usize,
+    /// Capacity of the NodeClient -> NetworkEvents command channel. Senders wait once it is full.
+    pub command_channel_capacity: usize,
+    /// Delay before re-dialing bootstrap peers while no connection is established.
+    /// Doubles after each failed attempt, up to `bootstrap_retry_max_backoff`.
+    pub bootstrap_retry_initial_backoff: Duration,
+    /// Max delay between bootstrap dial attempts.
+    pub bootstrap_retry_max_backoff: Duration,
+    /// Interval at which this node checks the kfrag providers of Reveries it broadcast.
+    pub kfrag_provider_sweep_interval: Duration,
+    /// Connected kfrag providers to keep above a Reverie's threshold. Below this, keyfrags
+    /// of disconnected providers are re-sent to fresh empty vessels.
+    pub kfrag_provider_safety_margin: usize,
+    /// Connected empty vessels required beyond a spawn's `total_frags`, 1 for the target vessel.
+    /// Spawns are rejected before encrypting when fewer are connected.
+    pub spawn_peer_margin: usize,
+    /// Reconstructing a Reverie from its cfrags (request, verify and decrypt) slower than
+    /// this logs a warning, as it eats into the LLM execution latency budget.
+    pub reconstruction_sla: Duration,
+    /// Time allowed on shutdown for the network event loop to re-publish vessel reveries
+    /// to Kademlia and exit.
+    pub shutdown_timeout: Duration,
+    /// Verifier for TEE attestations in peers' heartbeats. Heartbeats failing
+    /// verification are dropped and count against the peer's reputation.
+    pub tee_verifier: TeeVerifierConfig,
+}
 
-    let (
-        peer_id,
-        id_keys,
-        node_name,
-        umbral_key
-    ) = generate_peer_keys(secret_key_seed);
+const DEFAULT_MAX_PENDING_INCOMING: u32 = 32;
+const DEFAULT_MAX_ESTABLISHED_INCOMING: u32 = 128;
+const DEFAULT_MAX_ESTABLISHED_TOTAL: u32 = 256;
+const DEFAULT_REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+const DEFAULT_CFRAG_REQUEST_MAX_RETRIES: u32 = 2;
+const DEFAULT_RECORD_TTL: Duration = Duration::from_secs(300);
+const DEFAULT_RECORD_REPUBLISH_INTERVAL: Duration = Duration::from_secs(60);
+const DEFAULT_CFRAG_EXPIRY_SWEEP_INTERVAL: Duration = Duration::from_secs(60);
+const DEFAULT_PENDING_REQUEST_TIMEOUT: Duration = Duration::from_secs(300);
+const DEFAULT_ACCESS_DECISION_CACHE_TTL: Duration = Duration::from_secs(5);
+const DEFAULT_RESPAWN_VOTE_TIMEOUT: Duration = Duration::from_secs(10);
+const DEFAULT_HEARTBEAT_BLOCK_HEIGHT_TOLERANCE: u32 = 10;
+const DEFAULT_COMMAND_CHANNEL_CAPACITY: usize = 100;
+const DEFAULT_BOOTSTRAP_RETRY_INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+const DEFAULT_BOOTSTRAP_RETRY_MAX_BACKOFF: Duration = Duration::from_secs(30);
+const DEFAULT_KFRAG_PROVIDER_SWEEP_INTERVAL: Duration = Duration::from_secs(60);
+const DEFAULT_KFRAG_PROVIDER_SAFETY_MARGIN: usize = 1;
+const DEFAULT_SPAWN_PEER_MARGIN: usize = 1;
+const DEFAULT_RECONSTRUCTION_SLA: Duration = Duration::from_secs(5);
+const DEFAULT_SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(10);
 
-    let env_vars = EnvVars::load();
+impl Default for NetworkConfig {
+    fn default() -> Self {
+        Self {
+            enable_mdns: false,
+            enable_relay_client: false,
+            max_pending_incoming: Some(DEFAULT_MAX_PENDING_INCOMING),
+            max_established_incoming: Some(DEFAULT_MAX_ESTABLISHED_INCOMING),
+            max_established_total: Some(DEFAULT_MAX_ESTABLISHED_TOTAL),
+            max_established_per_peer: None,
+            request_timeout: DEFAULT_REQUEST_TIMEOUT,
+            cfrag_request_max_retries: DEFAULT_CFRAG_REQUEST_MAX_RETRIES,
+            record_ttl: DEFAULT_RECORD_TTL,
+            record_republish_interval: DEFAULT_RECORD_REPUBLISH_INTERVAL,
+            kademlia_replication_factor: kad::K_VALUE,
+            kademlia_query_parallelism: kad::ALPHA_VALUE,
+            cfrag_expiry_sweep_interval: DEFAULT_CFRAG_EXPIRY_SWEEP_INTERVAL,
+            pending_request_timeout: DEFAULT_PENDING_REQUEST_TIMEOUT,
+            access_decision_cache_ttl: DEFAULT_ACCESS_DECISION_CACHE_TTL,
+            respawn_vote_timeout: DEFAULT_RESPAWN_VOTE_TIMEOUT,
+            heartbeat_block_height_tolerance: DEFAULT_HEARTBEAT_BLOCK_HEIGHT_TOLERANCE,
+            max_reverie_payload_size: DEFAULT_MAX_REVERIE_PAYLOAD_SIZE,
+            command_channel_capacity: DEFAULT_COMMAND_CHANNEL_CAPACITY,
+            bootstrap_retry_initial_backoff: DEFAULT_BOOTSTRAP_RETRY_INITIAL_BACKOFF,
+            bootstrap_retry_max_backoff: DEFAULT_BOOTSTRAP_RETRY_MAX_BACKOFF,
+            kfrag_provider_sweep_interval: DEFAULT_KFRAG_PROVIDER_SWEEP_INTERVAL,
+            kfrag_provider_safety_margin: DEFAULT_KFRAG_PROVIDER_SAFETY_MARGIN,
+            spawn_peer_margin: DEFAULT_SPAWN_PEER_MARGIN,
+            reconstruction_sla: DEFAULT_RECONSTRUCTION_SLA,
+            shutdown_timeout: DEFAULT_SHUTDOWN_TIMEOUT,
+            tee_verifier: TeeVerifierConfig::default(),
+        }
+    }
+}
 
-    // TODO: used for determining which fragment the peer subscribes
-    // Replace with NODE_SEED_NUM
-    let seed = secret_key_seed.unwrap_or(0);
+impl NetworkConfig {
+    pub fn connection_limits(&self) -> ConnectionLimits {
+        ConnectionLimits::default()
+            .with_max_pending_incoming(self.max_pending_incoming)
+            .with_max_established_incoming(self.max_established_incoming)
+            .with_max_established(self.max_established_total)
+            .with_max_established_per_peer(self.max_established_per_peer)
+    }
 
-    let (heartbeat_failure_sender, heartbeat_failure_receiver) = tokio::sync::mpsc::channel(100);
-    let (heartbeat_sender, heartbeat_receiver) = async_channel::bounded(100);
-    let (command_sender, command_receiver) = mpsc::channel(100);
-    let (network_events_sender, network_events_receiver) = mpsc::channel(100);
+    /// Records put with `expires: None` get `record_ttl` on peers, while the publisher keeps
+    /// its own copy and republishes it with a fresh expiry every `record_republish_interval`.
+    pub fn kademlia_config(&self) -> kad::Config {
+        let mut config = kad::Config::new(
+            StreamProtocol::new(protocol_ids().kademlia.as_str())
+        );
+        config
+            .set_record_ttl(Some(self.record_ttl))
+            .set_publication_interval(Some(self.record_republish_interval))
+            // The record job only runs on the replication interval, so match it to republishing
+            .set_replication_interval(Some(self.record_republish_interval))
+            .set_provider_record_ttl(Some(self.record_ttl))
+            .set_provider_publication_interval(Some(self.record_republish_interval))
+            .set_replication_factor(self.kademlia_replication_factor)
+            .set_parallelism(self.kademlia_query_parallelism);
+        config
+    }
+
+    /// Delay before bootstrap retry `attempt` (from 0), doubling up to the max backoff
+    pub fn bootstrap_retry_backoff(&self, attempt: u32) -> Duration {
+        self.bootstrap_retry_initial_backoff
+            .saturating_mul(2u32.saturating_pow(attempt))
+            .min(self.bootstrap_retry_max_backoff)
+    }
+
+    pub fn request_response_behaviour(&self) -> request_response::cbor::Behaviour<FragmentRequestEnum, FragmentResponseEnum> {
+        request_response::cbor::Behaviour::new(
+            [(
+                StreamProtocol::new(protocol_ids().kfrags_requests.as_str()),
+                request_response::ProtocolSupport::Full,
+            )],
+            request_response::Config::default()
+                .with_request_timeout(self.request_timeout)
+        )
+    }
+}
+
+/// Splits bootstrap addresses into those with a `/p2p/` PeerId, and DNS addresses
+/// (`/dns`, `/dns4`, `/dns6`, `/dnsaddr`) without one. The PeerId of a DNS address is
+/// only learnt once it is resolved and dialed through the DNS transport.
+/// Addresses that are neither are logged and skipped.
+pub fn partition_bootstrap_addrs(
+    bootstrap_addrs: Vec<Multiaddr>
+) -> (Vec<(PeerId, Multiaddr)>, Vec<Multiaddr>) {
+
+    let mut bootstrap_peers = vec![];
+    let mut dns_bootstrap_addrs = vec![];
 
-    let swarm = libp2p::SwarmBuilder::with_existing_identity(id_keys.clone())
+    for addr in bootstrap_addrs {
+        match addr.try_into_peer_id() {
+            Ok(peer_id) => bootstrap_peers.push((peer_id, addr)),
+            Err(_) if is_dns_multiaddr(&addr) => dns_bootstrap_addrs.push(addr),
+            Err(_) => warn!("Bootstrap address {} has no /p2p/ PeerId and is not a DNS address, skipping", addr),
+        }
+    }
+
+    (bootstrap_peers, dns_bootstrap_addrs)
+}
+
+fn is_dns_multiaddr(addr: &Multiaddr) -> bool {
+    matches!(
+        addr.iter().next(),
+        Some(Protocol::Dns(_) | Protocol::Dns4(_) | Protocol::Dns6(_) | Protocol::Dnsaddr(_))
+    )
+}
+
+/// Builds the node's swarm with Kademlia, heartbeat, identify, mDNS, relay client, DCUtR and
+/// request-response behaviours, adding `bootstrap_peers` to Kademlia.
+pub(crate) fn build_swarm(
+    id_keys: IdentityKeypair,
+    peer_id: PeerId,
+    bootstrap_peers: &[(PeerId, Multiaddr)],
+    network_config: &NetworkConfig,
+    heartbeat_failure_sender: mpsc::Sender<HeartbeatConfig>,
+    heartbeat_sender: async_channel::Sender<TeePayloadOutEvent>,
+) -> Result<Swarm<Behaviour>> {
+
+    let swarm = libp2p::SwarmBuilder::with_existing_identity(id_keys)
         .with_tokio()
         .with_tcp(
             tcp::Config::default(),
@@ -80,37 +285,58 @@ pub async fn new(
         // QUIC has it's own connection timeout.
         .with_quic()
         .with_dns()?
-        .with_behaviour(|key| {
+        // The relay transport is inert unless the relay client behaviour is enabled
+        .with_relay_client(noise::Config::new, yamux::Config::default)?
+        .with_behaviour(|key, relay_client| {
 
             // Configure Kademlia for peer discovery
-            let mut kademlia = kad::Behaviour::new(
+            let mut kademlia = kad::Behaviour::with_config(
                 peer_id,
-                kad::store::MemoryStore::new(key.public().to_peer_id())
+                kad::store::MemoryStore::new(key.public().to_peer_id()),
+                network_config.kademlia_config()
             );
 
             // Enable Kademlia record publishing
             kademlia.set_mode(Some(kad::Mode::Server));
 
-            // Add bootstrap nodes to Kademlia and attempt DNS resolution
-            for (peer_id_str, addr) in &bootstrap_nodes {
-                if let Ok(bootstrap_peer_id) = peer_id_str.parse::<PeerId>() {
-                    kademlia.add_address(&bootstrap_peer_id, addr.clone());
-                    // Try to bootstrap immediately
-                    if let Err(e) = kademlia.bootstrap() {
-                        tracing::warn!("Failed to bootstrap Kademlia: {}", e);
-                    }
+            // Add bootstrap nodes to Kademlia
+            for (bootstrap_peer_id, addr) in bootstrap_peers {
+                kademlia.add_address(bootstrap_peer_id, addr.clone());
+                // Try to bootstrap immediately
+                if let Err(e) = kademlia.bootstrap() {
+                    tracing::warn!("Failed to bootstrap Kademlia: {}", e);
                 }
             }
 
             // Create identify behavior
             let identify = libp2p_identify::Behaviour::new(
                 libp2p_identify::Config::new(
-                "/my-node/1.0.0".to_string(),
+                protocol_ids().identify.clone(),
                 key.public(),
                 )
             );
 
+            // Local peer discovery, disabled unless explicitly enabled
+            let mdns = if network_config.enable_mdns {
+                Some(mdns::tokio::Behaviour::new(
+                    mdns::Config::default(),
+                    key.public().to_peer_id()
+                )?)
+            } else {
+                None
+            };
+
+            // Relayed connections for NAT'd nodes, disabled unless explicitly enabled
+            let (relay_client, dcutr) = if network_config.enable_relay_client {
+                (Some(relay_client), Some(dcutr::Behaviour::new(key.public().to_peer_id())))
+            } else {
+                (None, None)
+            };
+
             Ok(Behaviour {
+                connection_limits: connection_limits::Behaviour::new(
+                    network_config.connection_limits()
+                ),
                 kademlia,
                 heartbeat: HeartbeatBehaviour::new(
                     // send_timeout should be larger than idle_timeout
@@ -124,18 +350,19 @@ pub async fn new(
                         idle_timeout: Duration::from_millis(6_000),
                         // Max failures allowed. Requests disconnection if reached
                         max_failures: 1,
+                        // Heartbeats with larger TEE attestations are dropped unparsed
+                        max_attestation_size: DEFAULT_MAX_ATTESTATION_SIZE,
+                        // Verifies peers' heartbeat attestations, e.g. with DCAP or a mock
+                        tee_verifier: network_config.tee_verifier,
                     },
                     heartbeat_failure_sender,
                     heartbeat_sender,
                 ),
                 identify: identify,
-                request_response: libp2p::request_response::cbor::Behaviour::new(
-                    [(
-                        StreamProtocol::new("/reverie-kfrags-requests/1.0.0"),
-                        libp2p::request_response::ProtocolSupport::Full,
-                    )],
-                    libp2p::request_response::Config::default()
-                )
+                mdns: mdns.into(),
+                relay_client: relay_client.into(),
+                dcutr: dcutr.into(),
+                request_response: network_config.request_response_behaviour(),
             })
         })?
         .with_swarm_config(|c|
@@ -143,6 +370,68 @@ pub async fn new(
         )
         .build();
 
+    Ok(swarm)
+}
+
+/// Creates the network components, namely:
+/// - The network client to interact with the network layer from anywhere within your application.
+/// - The network event stream, e.g. for incoming requests.
+/// - The network task driving the network itself.
+pub async fn new(
+    secret_key_seed: Option<usize>,
+    listen_address: Vec<Multiaddr>,
+    bootstrap_addrs: Vec<Multiaddr>,
+    network_config: NetworkConfig,
+) -> Result<NodeClient> {
+
+    let (
+        bootstrap_peers,
+        dns_bootstrap_addrs
+    ) = partition_bootstrap_addrs(bootstrap_addrs);
+    // Re-dialed in the background if none are reachable at startup
+    let retry_bootstrap_addrs: Vec<Multiaddr> = bootstrap_peers.iter()
+        .map(|(_, addr)| addr.clone())
+        .chain(dns_bootstrap_addrs.iter().cloned())
+        .collect();
+
+    let (
+        peer_id,
+        id_keys,
+        node_name,
+        umbral_key
+    ) = generate_peer_keys(secret_key_seed);
+
+    let env_vars = EnvVars::load();
+
+    // TODO: used for determining which fragment the peer subscribes
+    // Replace with NODE_SEED_NUM
+    let seed = secret_key_seed.unwrap_or(0);
+
+    let (heartbeat_failure_sender, heartbeat_failure_receiver) = tokio::sync::mpsc::channel(100);
+    let (heartbeat_sender, heartbeat_receiver) = async_channel::bounded(HEARTBEAT_CHANNEL_CAPACITY);
+    let (command_sender, command_receiver) = mpsc::channel(network_config.command_channel_capacity.max(1));
+    let (network_events_sender, network_events_receiver) = mpsc::channel(100);
+    let (node_event_sender, _) = broadcast::channel(NODE_EVENT_CHANNEL_CAPACITY);
+    let (shutdown_sender, shutdown_receiver) = watch::channel(false);
+
+    let mut swarm = build_swarm(
+        id_keys.clone(),
+        peer_id,
+        &bootstrap_peers,
+        &network_config,
+        heartbeat_failure_sender,
+        heartbeat_sender,
+    )?;
+
+    // DNS bootstrap addresses are resolved by the DNS transport when dialed.
+    // Once connected, Identify adds the peer to Kademlia, which then bootstraps.
+    for addr in dns_bootstrap_addrs {
+        info!("Dialing DNS bootstrap address {} to resolve its PeerId", addr);
+        if let Err(e) = swarm.dial(addr.clone()) {
+            error!("Failed to dial DNS bootstrap address {}: {}", addr, e);
+        }
+    }
+
     let container_manager = Arc::new(RwLock::new(
         ContainerManager::new(
             std::time::Duration::from_secs(30),
@@ -161,6 +450,10 @@ pub async fn new(
     let near_runtime = Arc::new(
         NearRuntime::new(NearConfig::default())?
     );
+    // Create an EvmRuntime instance for on-chain access conditions
+    let evm_runtime = Arc::new(
+        EvmRuntime::new(EvmConfig::default()).await?
+    );
 
     // 1. First spawn listen to incoming commands and network events, run in the background.
     tokio::task::spawn(
@@ -169,9 +462,13 @@ pub async fn new(
             node_identity.clone(),
             command_receiver,
             network_events_sender,
+            node_event_sender.clone(),
             heartbeat_failure_receiver,
             container_manager.clone(),
             near_runtime.clone(),
+            evm_runtime,
+            network_config.clone(),
+            shutdown_receiver,
         ).init_listen_for_network_events()
     );
 
@@ -183,8 +480,13 @@ pub async fn new(
         command_sender,
         umbral_key,
         heartbeat_receiver,
+        node_event_sender,
         usage_db_pool,
         near_runtime.clone(),
+        network_config.max_reverie_payload_size,
+        network_config.spawn_peer_margin,
+        network_config.reconstruction_sla,
+        shutdown_sender,
     );
 
     // 2. Start listening for peers on the network
@@ -196,6 +498,487 @@ pub async fn new(
         nc.listen_to_network_events(network_events_receiver).await.ok();
     });
 
+    // 4. Periodically re-send keyfrags of disconnected providers to fresh vessels
+    let nc = node_client.clone();
+    let kfrag_provider_sweep_interval = network_config.kfrag_provider_sweep_interval;
+    let kfrag_provider_safety_margin = network_config.kfrag_provider_safety_margin;
+    tokio::spawn(async move {
+        nc.run_kfrag_provider_sweeps(kfrag_provider_sweep_interval, kfrag_provider_safety_margin).await;
+    });
+
+    // 5. Retry bootstrap dials in the background until the node joins the network
+    if !retry_bootstrap_addrs.is_empty() {
+        let nc = node_client.clone();
+        tokio::spawn(async move {
+            if let Err(e) = nc.retry_bootstrap_until_connected(retry_bootstrap_addrs, network_config).await {
+                error!("Bootstrap retry loop stopped: {}", e);
+            }
+        });
+    }
+
     Ok(node_client)
 }
 
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::StreamExt;
+    use libp2p::swarm::{ListenError, Swarm, SwarmEvent};
+
+    fn limited_swarm(limits: ConnectionLimits) -> Swarm<connection_limits::Behaviour> {
+        libp2p::SwarmBuilder::with_new_identity()
+            .with_tokio()
+            .with_tcp(
+                tcp::Config::default(),
+                noise::Config::new,
+                yamux::Config::default
+            ).unwrap()
+            .with_behaviour(|_| connection_limits::Behaviour::new(limits)).unwrap()
+            .with_swarm_config(|c| c.with_idle_connection_timeout(Duration::from_secs(60)))
+            .build()
+    }
+
+    #[tokio::test]
+    async fn inbound_connections_beyond_limit_are_denied() {
+        let network_config = NetworkConfig {
+            max_established_incoming: Some(1),
+            ..Default::default()
+        };
+
+        let mut listener = limited_swarm(network_config.connection_limits());
+        listener.listen_on("/ip4/127.0.0.1/tcp/0".parse().unwrap()).unwrap();
+        let listen_addr = loop {
+            if let SwarmEvent::NewListenAddr { address, .. } = listener.select_next_some().await {
+                break address;
+            }
+        };
+
+        for _ in 0..2 {
+            let mut dialer = limited_swarm(ConnectionLimits::default());
+            dialer.dial(listen_addr.clone()).unwrap();
+            tokio::spawn(async move {
+                loop { dialer.select_next_some().await; }
+            });
+        }
+
+        let mut established = 0;
+        let mut denied = 0;
+        tokio::time::timeout(Duration::from_secs(10), async {
+            while established + denied < 2 {
+                match listener.select_next_some().await {
+                    SwarmEvent::ConnectionEstablished { .. } => established += 1,
+                    SwarmEvent::IncomingConnectionError { error: ListenError::Denied { .. }, .. } => denied += 1,
+                    _ => {}
+                }
+            }
+        }).await.expect("timed out waiting for inbound connections");
+
+        assert_eq!(established, 1);
+        assert_eq!(denied, 1);
+        assert_eq!(listener.connected_peers().count(), 1);
+    }
+
+    fn request_response_swarm(
+        network_config: &NetworkConfig
+    ) -> Swarm<request_response::cbor::Behaviour<FragmentRequestEnum, FragmentResponseEnum>> {
+        libp2p::SwarmBuilder::with_new_identity()
+            .with_tokio()
+            .with_tcp(
+                tcp::Config::default(),
+                noise::Config::new,
+                yamux::Config::default
+            ).unwrap()
+            .with_behaviour(|_| network_config.request_response_behaviour()).unwrap()
+            .with_swarm_config(|c| c.with_idle_connection_timeout(Duration::from_secs(60)))
+            .build()
+    }
+
+    #[tokio::test]
+    async fn unanswered_cfrag_request_times_out() {
+        use crate::types::AccessKey;
+        use request_response::{Event, Message, OutboundFailure};
+
+        let network_config = NetworkConfig {
+            request_timeout: Duration::from_millis(500),
+            ..Default::default()
+        };
+
+        let mut requester = request_response_swarm(&network_config);
+        let mut provider = request_response_swarm(&network_config);
+
+        provider.listen_on("/ip4/127.0.0.1/tcp/0".parse().unwrap()).unwrap();
+        let provider_addr = loop {
+            if let SwarmEvent::NewListenAddr { address, .. } = provider.select_next_some().await {
+                break address;
+            }
+        };
+        let provider_peer_id = *provider.local_peer_id();
+        requester.add_peer_address(provider_peer_id, provider_addr);
+
+        // Provider receives the request but never responds, as if it hung mid-request
+        tokio::spawn(async move {
+            let mut held_channels = vec![];
+            loop {
+                if let SwarmEvent::Behaviour(Event::Message {
+                    message: Message::Request { channel, .. }, ..
+                }) = provider.select_next_some().await {
+                    held_channels.push(channel);
+                }
+            }
+        });
+
+        let sent_request_id = requester.behaviour_mut().send_request(
+            &provider_peer_id,
+            FragmentRequestEnum::GetFragmentRequest(
+                "reverie_test".to_string(),
+                AccessKey::UmbralSignature(vec![]),
+            )
+        );
+
+        let failure = tokio::time::timeout(Duration::from_secs(10), async {
+            loop {
+                if let SwarmEvent::Behaviour(Event::OutboundFailure { request_id, error, .. }) = requester.select_next_some().await {
+                    break (request_id, error);
+                }
+            }
+        }).await.expect("requester hung instead of timing out");
+
+        assert_eq!(failure.0, sent_request_id);
+        assert!(matches!(failure.1, OutboundFailure::Timeout));
+    }
+
+    fn kademlia_swarm(network_config: &NetworkConfig) -> Swarm<kad::Behaviour<kad::store::MemoryStore>> {
+        libp2p::SwarmBuilder::with_new_identity()
+            .with_tokio()
+            .with_tcp(
+                tcp::Config::default(),
+                noise::Config::new,
+                yamux::Config::default
+            ).unwrap()
+            .with_behaviour(|key| {
+                let mut kademlia = kad::Behaviour::with_config(
+                    key.public().to_peer_id(),
+                    kad::store::MemoryStore::new(key.public().to_peer_id()),
+                    network_config.kademlia_config()
+                );
+                kademlia.set_mode(Some(kad::Mode::Server));
+                kademlia
+            }).unwrap()
+            .with_swarm_config(|c| c.with_idle_connection_timeout(Duration::from_secs(60)))
+            .build()
+    }
+
+    #[tokio::test]
+    async fn vessel_status_record_expires_and_is_republished() {
+        use kad::store::RecordStore;
+        use std::time::Instant;
+        use crate::types::{PeerIdToNodeStatusKey, KademliaKeyTrait};
+
+        let network_config = NetworkConfig {
+            record_ttl: Duration::from_secs(30),
+            record_republish_interval: Duration::from_secs(1),
+            ..Default::default()
+        };
+
+        let mut publisher = kademlia_swarm(&network_config);
+        let mut peer = kademlia_swarm(&network_config);
+
+        peer.listen_on("/ip4/127.0.0.1/tcp/0".parse().unwrap()).unwrap();
+        let peer_addr = loop {
+            if let SwarmEvent::NewListenAddr { address, .. } = peer.select_next_some().await {
+                break address;
+            }
+        };
+        let peer_id = *peer.local_peer_id();
+        publisher.behaviour_mut().add_address(&peer_id, peer_addr);
+
+        let key = PeerIdToNodeStatusKey::from(*publisher.local_peer_id()).to_kad_key();
+        let put_at = Instant::now();
+        publisher.behaviour_mut().put_record(
+            kad::Record {
+                key: key.clone(),
+                value: b"vessel_status".to_vec(),
+                publisher: Some(*publisher.local_peer_id()),
+                expires: None,
+            },
+            kad::Quorum::One
+        ).unwrap();
+
+        // Wait for the peer to store the record, then for a republish to push its expiry back
+        let (first_expiry, refreshed_expiry) = tokio::time::timeout(Duration::from_secs(10), async {
+            let mut first_expiry = None;
+            loop {
+                tokio::select! {
+                    _ = publisher.select_next_some() => {},
+                    _ = peer.select_next_some() => {},
+                }
+                let stored_expiry = peer.behaviour_mut()
+                    .store_mut()
+                    .get(&key)
+                    .and_then(|record| record.expires);
+
+                match (first_expiry, stored_expiry) {
+                    (None, Some(expires)) => first_expiry = Some(expires),
+                    (Some(first), Some(expires)) if expires > first => break (first, expires),
+                    _ => {}
+                }
+            }
+        }).await.expect("vessel status record was not republished");
+
+        assert!(first_expiry > put_at);
+        assert!(first_expiry <= put_at + network_config.record_ttl + Duration::from_secs(1));
+        assert!(refreshed_expiry > first_expiry);
+    }
+
+    #[test]
+    fn kademlia_config_applies_replication_factor_and_parallelism() {
+        let network_config = NetworkConfig {
+            kademlia_replication_factor: NonZeroUsize::new(7).unwrap(),
+            kademlia_query_parallelism: NonZeroUsize::new(2).unwrap(),
+            ..Default::default()
+        };
+        // kad::Config has no getters, its Debug output includes the query config
+        let kademlia_config = format!("{:?}", network_config.kademlia_config());
+        assert!(kademlia_config.contains("replication_factor: 7"), "{}", kademlia_config);
+        assert!(kademlia_config.contains("parallelism: 2"), "{}", kademlia_config);
+
+        // defaults match libp2p's
+        let default_config = format!("{:?}", NetworkConfig::default().kademlia_config());
+        assert!(default_config.contains(&format!("replication_factor: {}", kad::K_VALUE)), "{}", default_config);
+        assert!(default_config.contains(&format!("parallelism: {}", kad::ALPHA_VALUE)), "{}", default_config);
+
+        // the swarm builds with the custom config
+        let _swarm = kademlia_swarm(&network_config);
+    }
+
+    #[test]
+    fn bootstrap_addrs_are_partitioned_by_peer_id_and_dns() {
+        let peer_id = PeerId::random();
+        let ip_addr: Multiaddr = format!("/ip4/127.0.0.1/tcp/9001/p2p/{}", peer_id).parse().unwrap();
+        let dns_addr_with_peer: Multiaddr = format!("/dns4/node1.example.com/tcp/9001/p2p/{}", peer_id).parse().unwrap();
+        let dns_addr: Multiaddr = "/dns4/node1.example.com/tcp/9001".parse().unwrap();
+        let dnsaddr: Multiaddr = "/dnsaddr/bootstrap.example.com".parse().unwrap();
+        let no_peer_id: Multiaddr = "/ip4/127.0.0.1/tcp/9001".parse().unwrap();
+
+        let (bootstrap_peers, dns_bootstrap_addrs) = partition_bootstrap_addrs(vec![
+            ip_addr.clone(),
+            dns_addr_with_peer.clone(),
+            dns_addr.clone(),
+            dnsaddr.clone(),
+            no_peer_id,
+        ]);
+
+        assert_eq!(bootstrap_peers, vec![(peer_id, ip_addr), (peer_id, dns_addr_with_peer)]);
+        assert_eq!(dns_bootstrap_addrs, vec![dns_addr, dnsaddr]);
+    }
+
+    #[tokio::test]
+    async fn dns_multiaddr_resolves_to_peer_id_when_dialed() {
+        let dns_swarm = || libp2p::SwarmBuilder::with_new_identity()
+            .with_tokio()
+            .with_tcp(
+                tcp::Config::default(),
+                noise::Config::new,
+                yamux::Config::default
+            ).unwrap()
+            .with_dns().unwrap()
+            .with_behaviour(|_| connection_limits::Behaviour::new(ConnectionLimits::default())).unwrap()
+            .with_swarm_config(|c| c.with_idle_connection_timeout(Duration::from_secs(60)))
+            .build();
+
+        let mut listener = dns_swarm();
+        listener.listen_on("/ip4/127.0.0.1/tcp/0".parse().unwrap()).unwrap();
+        let port = loop {
+            if let SwarmEvent::NewListenAddr { address, .. } = listener.select_next_some().await {
+                break address.iter()
+                    .find_map(|p| match p {
+                        Protocol::Tcp(port) => Some(port),
+                        _ => None,
+                    })
+                    .unwrap();
+            }
+        };
+        let listener_peer_id = *listener.local_peer_id();
+        tokio::spawn(async move {
+            loop { listener.select_next_some().await; }
+        });
+
+        // No /p2p/ suffix: the PeerId is only learnt by resolving and dialing
+        let dns_addr: Multiaddr = format!("/dns4/localhost/tcp/{}", port).parse().unwrap();
+        let (_, dns_bootstrap_addrs) = partition_bootstrap_addrs(vec![dns_addr.clone()]);
+        assert_eq!(dns_bootstrap_addrs, vec![dns_addr.clone()]);
+
+        let mut dialer = dns_swarm();
+        dialer.dial(dns_addr).unwrap();
+
+        let resolved_peer_id = tokio::time::timeout(Duration::from_secs(10), async {
+            loop {
+                match dialer.select_next_some().await {
+                    SwarmEvent::ConnectionEstablished { peer_id, .. } => break peer_id,
+                    SwarmEvent::OutgoingConnectionError { error, .. } => panic!("dial failed: {}", error),
+                    _ => {}
+                }
+            }
+        }).await.expect("timed out dialing DNS multiaddr");
+
+        assert_eq!(resolved_peer_id, listener_peer_id);
+    }
+
+    #[test]
+    fn bootstrap_retry_backoff_doubles_up_to_max() {
+        let network_config = NetworkConfig {
+            bootstrap_retry_initial_backoff: Duration::from_millis(500),
+            bootstrap_retry_max_backoff: Duration::from_secs(3),
+            ..Default::default()
+        };
+        let backoffs: Vec<Duration> = (0..5)
+            .map(|attempt| network_config.bootstrap_retry_backoff(attempt))
+            .collect();
+        assert_eq!(backoffs, vec![
+            Duration::from_millis(500),
+            Duration::from_secs(1),
+            Duration::from_secs(2),
+            Duration::from_secs(3),
+            Duration::from_secs(3),
+        ]);
+        // Saturates rather than overflowing after many failed attempts
+        assert_eq!(network_config.bootstrap_retry_backoff(u32::MAX), Duration::from_secs(3));
+    }
+
+    #[tokio::test]
+    async fn shutdown_signal_ends_network_event_loop() {
+        let network_config = NetworkConfig {
+            shutdown_timeout: Duration::from_secs(1),
+            ..Default::default()
+        };
+        let (peer_id, id_keys, node_name, umbral_key) = generate_peer_keys(Some(1));
+        let (heartbeat_failure_sender, heartbeat_failure_receiver) = mpsc::channel(1);
+        let (heartbeat_sender, _heartbeat_receiver) = async_channel::bounded(1);
+        let (command_sender, command_receiver) = mpsc::channel(1);
+        let (network_events_sender, _network_events_receiver) = mpsc::channel(1);
+        let (node_event_sender, _) = broadcast::channel(1);
+        let (shutdown_sender, shutdown_receiver) = watch::channel(false);
+
+        let swarm = build_swarm(
+            id_keys.clone(),
+            peer_id,
+            &[],
+            &network_config,
+            heartbeat_failure_sender,
+            heartbeat_sender,
+        ).unwrap();
+
+        let event_loop = tokio::spawn(
+            NetworkEvents::new(
+                swarm,
+                NodeIdentity::new(node_name.to_string(), peer_id, id_keys, 1, umbral_key),
+                command_receiver,
+                network_events_sender,
+                node_event_sender,
+                heartbeat_failure_receiver,
+                Arc::new(RwLock::new(ContainerManager::new(Duration::from_secs(30)))),
+                Arc::new(NearRuntime::new(NearConfig::default()).unwrap()),
+                Arc::new(EvmRuntime::new(EvmConfig::default()).await.unwrap()),
+                network_config,
+                shutdown_receiver,
+            ).init_listen_for_network_events()
+        );
+
+        shutdown_sender.send_replace(true);
+
+        tokio::time::timeout(Duration::from_secs(5), event_loop)
+            .await
+            .expect("network event loop did not exit on shutdown")
+            .unwrap();
+        assert!(command_sender.is_closed());
+    }
+
+    fn relay_client_swarm() -> Swarm<Behaviour> {
+        let network_config = NetworkConfig {
+            enable_relay_client: true,
+            ..Default::default()
+        };
+        let id_keys = IdentityKeypair::generate_ed25519();
+        let peer_id = id_keys.public().to_peer_id();
+        let (heartbeat_failure_sender, _) = mpsc::channel(1);
+        let (heartbeat_sender, _) = async_channel::bounded(1);
+        build_swarm(id_keys, peer_id, &[], &network_config, heartbeat_failure_sender, heartbeat_sender).unwrap()
+    }
+
+    #[tokio::test]
+    async fn nat_node_is_reachable_through_relay_circuit() {
+        use libp2p::relay;
+        use crate::behaviour::BehaviourEvent;
+        use crate::is_dialable;
+
+        let mut relay_server = libp2p::SwarmBuilder::with_new_identity()
+            .with_tokio()
+            .with_tcp(
+                tcp::Config::default(),
+                noise::Config::new,
+                yamux::Config::default
+            ).unwrap()
+            .with_behaviour(|key| relay::Behaviour::new(key.public().to_peer_id(), Default::default())).unwrap()
+            .with_swarm_config(|c| c.with_idle_connection_timeout(Duration::from_secs(60)))
+            .build();
+        relay_server.listen_on("/ip4/127.0.0.1/tcp/0".parse().unwrap()).unwrap();
+        let relay_addr = loop {
+            if let SwarmEvent::NewListenAddr { address, .. } = relay_server.select_next_some().await {
+                break address;
+            }
+        };
+        // Reservations advertise the relay's external addresses
+        relay_server.add_external_address(relay_addr.clone());
+        let relay_peer_id = *relay_server.local_peer_id();
+        tokio::spawn(async move {
+            loop { relay_server.select_next_some().await; }
+        });
+
+        // NAT'd node only listens through the relay
+        let mut nat_node = relay_client_swarm();
+        let nat_peer_id = *nat_node.local_peer_id();
+        let circuit_addr = relay_addr
+            .with(Protocol::P2p(relay_peer_id))
+            .with(Protocol::P2pCircuit);
+        nat_node.listen_on(circuit_addr.clone()).unwrap();
+        tokio::time::timeout(Duration::from_secs(10), async {
+            loop {
+                if let SwarmEvent::Behaviour(BehaviourEvent::RelayClient(
+                    relay::client::Event::ReservationReqAccepted { .. }
+                )) = nat_node.select_next_some().await {
+                    break;
+                }
+            }
+        }).await.expect("timed out reserving relay circuit");
+        tokio::spawn(async move {
+            loop { nat_node.select_next_some().await; }
+        });
+
+        let nat_node_addr = circuit_addr.with(Protocol::P2p(nat_peer_id));
+        assert!(is_dialable(&nat_node_addr));
+        assert!(is_dialable(&Multiaddr::empty()
+            .with(Protocol::P2p(relay_peer_id))
+            .with(Protocol::P2pCircuit)
+            .with(Protocol::P2p(nat_peer_id))));
+        assert!(!is_dialable(&Multiaddr::empty().with(Protocol::P2p(nat_peer_id))));
+        assert!(!is_dialable(&Multiaddr::empty().with(Protocol::P2pCircuit).with(Protocol::P2p(nat_peer_id))));
+
+        let mut dialer = relay_client_swarm();
+        dialer.dial(nat_node_addr).unwrap();
+        let relayed_endpoint = tokio::time::timeout(Duration::from_secs(10), async {
+            loop {
+                match dialer.select_next_some().await {
+                    SwarmEvent::ConnectionEstablished { peer_id, endpoint, .. } if peer_id == nat_peer_id => {
+                        break endpoint;
+                    }
+                    SwarmEvent::OutgoingConnectionError { error, .. } => panic!("relayed dial failed: {}", error),
+                    _ => {}
+                }
+            }
+        }).await.expect("timed out dialing NAT'd node through relay");
+
+        assert!(relayed_endpoint.is_relayed());
+    }
+}
@@ -4,11 +4,17 @@ pub mod behaviour;
 pub mod create_network;
 pub mod network_events;
 pub mod node_client;
+pub mod protocols;
 pub mod types;
 pub mod utils;
 pub mod usage_db;
 pub mod env_var;
 
+#[cfg(any(test, feature = "test-utils"))]
+pub mod test_utils;
+
+pub use utils::pubkeys::{derive_node_identity, NodeIdentityBundle};
+
 use color_eyre::{Result, eyre::anyhow, eyre};
 use std::{
     collections::HashSet,
@@ -99,53 +105,35 @@ pub trait TryPeerId {
 }
 
 impl TryPeerId for Multiaddr {
+    /// Only reads a trailing `/p2p/` PeerId. DNS addresses without one must be dialed
+    /// so the DNS transport can resolve them (see `create_network::partition_bootstrap_addrs`).
     fn try_into_peer_id(&self) -> Result<PeerId> {
         self.iter().last().and_then(|p| match p {
             multiaddr::Protocol::P2p(peer_id) => Some(peer_id),
-            multiaddr::Protocol::Dnsaddr(multiaddr) => {
-                tracing::warn!(
-                    "synchronous recursive dnsaddr resolution is not yet supported: {:?}",
-                    multiaddr
-                );
-                None
-            }
             _ => None,
         })
-        .ok_or_else(|| anyhow!("dnsaddr error"))
+        .ok_or_else(|| anyhow!("No /p2p/ PeerId in multiaddr: {}", self))
     }
 }
 
+/// A bare `/p2p/<peer id>` isn't dialable, the multiaddr needs a transport. Relayed addresses
+/// (`[<relay addr>]/p2p/<relay id>/p2p-circuit[/p2p/<peer id>]`) are dialable if they name the relay.
 pub fn is_dialable(multiaddr: &Multiaddr) -> bool {
-    // Check if the multiaddr is dialable
-    match multiaddr.protocol_stack().next() {
-        None => false,
-        Some(protocol) => protocol != "p2p",
+    let protocols = multiaddr.iter().collect::<Vec<multiaddr::Protocol>>();
+    match protocols.iter().position(|p| matches!(p, multiaddr::Protocol::P2pCircuit)) {
+        Some(circuit) => circuit > 0 && matches!(protocols[circuit - 1], multiaddr::Protocol::P2p(_)),
+        None => match protocols.first() {
+            None | Some(multiaddr::Protocol::P2p(_)) => false,
+            Some(_) => true,
+        },
     }
 }
 
-// TODO: temporary convenience functions
-pub fn get_node_name<'a>(peer_id: &libp2p::PeerId) -> &'a str {
-    match peer_id.to_base58().as_str() {
-        "12D3KooWPjceQrSwdWXPyLLeABRXmuqt69Rg3sBYbU1Nft9HyQ6X" => "ABE",
-        "12D3KooWH3uVF6wv47WnArKHk5p6cvgCJEb74UTmxztmQDc298L3" => "BOB",
-        "12D3KooWQYhTNQdmr3ArTeUHRYzFg94BKyTkoWBDWez9kSCVe2Xo" => "CARL",
-        "12D3KooWLJtG8fd2hkQzTn96MrLvThmnNQjTUFZwGEsLRz5EmSzc" => "DAN",
-        "12D3KooWSHj3RRbBjD15g6wekV8y3mm57Pobmps2g2WJm6F67Lay" => "EMMA",
-        "12D3KooWDMCQbZZvLgHiHntG1KwcHoqHPAxL37KvhgibWqFtpqUY" => "FAYE",
-        "12D3KooWLnZUpcaBwbz9uD1XsyyHnbXUrJRmxnsMiRnuCmvPix67" => "GREG",
-        "12D3KooWQ8vrERR8bnPByEjjtqV6hTWehaf8TmK7qR1cUsyrPpfZ" => "HANA",
-        "12D3KooWNRk8VBuTJTYyTbnJC7Nj2UN5jij4dJMo8wtSGT2hRzRP" => "IAN",
-        "12D3KooWFHNBwTxUgeHRcD3g4ieiXBmZGVyp6TKGWRKKEqYgCC1C" => "JACK",
-        "12D3KooWHbEputWi1fJAxoYgmvvDe3yP7acTACqmXKGYwMgN2daQ" => "KASS",
-        "12D3KooWCxnyz1JxC9y1RniRQVFe2cLaLHsYNc2SnXbM7yq5JBbJ" => "LARRY",
-        _ => "Unnamed",
-    }
+/// Name configured for the peer (see `utils::node_names`), otherwise its short PeerId
+pub fn get_node_name(peer_id: &libp2p::PeerId) -> String {
+    utils::node_names::resolve_node_name(peer_id)
 }
 
 pub fn get_node_name2(peer_id: &libp2p::PeerId) -> String {
-    let nname = get_node_name(peer_id);
-    match nname {
-        "Unnamed" => format!("{}", short_peer_id(peer_id)),
-        _ => format!("{}", nname),
-    }
+    get_node_name(peer_id)
 }
@@ -0,0 +1,101 @@
+use std::collections::HashMap;
+use std::future::Future;
+use color_eyre::Result;
+use tokio::time::{Duration, Instant};
+
+use crate::types::ReverieId;
+
+/// Identifies a contract-gated access check, e.g. a NearContract `can_spend` call.
+/// `condition` names the contract (and method or event) asked, so a decision from one
+/// contract is never reused for another.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub(super) struct AccessDecisionKey {
+    pub(super) reverie_id: ReverieId,
+    pub(super) condition: String,
+    pub(super) spender: String,
+    pub(super) amount: u128,
+}
+
+/// Short-lived cache of granted and denied contract access checks, so rapid repeated
+/// fragment requests don't each read the chain. A zero TTL disables caching.
+pub(super) struct AccessDecisionCache {
+    ttl: Duration,
+    decisions: HashMap<AccessDecisionKey, (Instant, bool)>,
+}
+
+impl AccessDecisionCache {
+    pub(super) fn new(ttl: Duration) -> Self {
+        Self { ttl, decisions: HashMap::new() }
+    }
+
+    /// Returns the cached decision for `key` if it is younger than the TTL, otherwise
+    /// runs `check` and caches its result. Errors are returned uncached.
+    pub(super) async fn decide<F, Fut>(&mut self, key: AccessDecisionKey, now: Instant, check: F) -> Result<bool>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<bool>>,
+    {
+        if self.ttl.is_zero() {
+            return check().await
+        }
+        if let Some((decided_at, granted)) = self.decisions.get(&key) {
+            if now.duration_since(*decided_at) < self.ttl {
+                return Ok(*granted)
+            }
+        }
+        let granted = check().await?;
+        self.decisions.insert(key, (now, granted));
+        Ok(granted)
+    }
+
+    /// Drops decisions older than the TTL
+    pub(super) fn purge_expired(&mut self, now: Instant) {
+        let ttl = self.ttl;
+        self.decisions.retain(|_, (decided_at, _)| now.duration_since(*decided_at) < ttl);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    fn near_contract_key() -> AccessDecisionKey {
+        AccessDecisionKey {
+            reverie_id: "reverie_1234".to_string(),
+            condition: "near:payments.testnet".to_string(),
+            spender: "alice.testnet".to_string(),
+            amount: 100,
+        }
+    }
+
+    #[tokio::test]
+    async fn rapid_identical_contract_checks_read_chain_once() {
+        let chain_calls = &AtomicUsize::new(0);
+        let can_spend = move || async move {
+            chain_calls.fetch_add(1, Ordering::SeqCst);
+            Ok::<bool, color_eyre::eyre::Error>(true)
+        };
+        let now = Instant::now();
+
+        let mut cache = AccessDecisionCache::new(Duration::from_secs(5));
+        assert!(cache.decide(near_contract_key(), now, can_spend).await.unwrap());
+        assert!(cache.decide(near_contract_key(), now + Duration::from_millis(10), can_spend).await.unwrap());
+        assert_eq!(chain_calls.load(Ordering::SeqCst), 1);
+
+        // a different amount is a different decision
+        let key = AccessDecisionKey { amount: 200, ..near_contract_key() };
+        assert!(cache.decide(key, now, can_spend).await.unwrap());
+        assert_eq!(chain_calls.load(Ordering::SeqCst), 2);
+
+        // expired decisions are checked again
+        assert!(cache.decide(near_contract_key(), now + Duration::from_secs(5), can_spend).await.unwrap());
+        assert_eq!(chain_calls.load(Ordering::SeqCst), 3);
+
+        // a zero TTL bypasses the cache
+        let mut cache = AccessDecisionCache::new(Duration::ZERO);
+        cache.decide(near_contract_key(), now, can_spend).await.unwrap();
+        cache.decide(near_contract_key(), now, can_spend).await.unwrap();
+        assert_eq!(chain_calls.load(Ordering::SeqCst), 5);
+    }
+}
@@ -1,14 +1,18 @@
+use std::collections::HashSet;
 use color_eyre::{Result, eyre::anyhow};
 use colored::Colorize;
 use libp2p::{kad, PeerId};
-use tracing::{info, error, warn};
+use tokio::time;
+use tracing::{info, debug, error, warn};
 
 use crate::node_client::NodeCommand;
 use crate::types::{
     AgentVesselInfo,
+    DistributionPolicy,
     FragmentRequestEnum,
     FragmentResponseEnum,
     NodeKeysWithVesselStatus,
+    NodeReadiness,
     VesselStatus,
     PeerIdToNodeStatusKey,
     ReverieKeyfragMessage,
@@ -19,11 +23,14 @@ use crate::types::{
     Reverie,
     ReverieIdToNameKey,
     ReverieIdToPeerId,
+    ReverieTypeIndexKey,
     KademliaKeyTrait,
+    split_reverie_ciphertext,
+    CIPHERTEXT_CHUNK_SIZE,
 };
 use crate::{get_node_name, short_peer_id};
 use crate::SendError;
-use super::NetworkEvents;
+use super::{NetworkEvents, PendingFragmentRequest, PendingProviders};
 
 
 impl NetworkEvents {
@@ -119,47 +126,67 @@ impl NetworkEvents {
                     keyfrag_providers,
                 },
             } => {
-                // 1. Save agent metadata
-                if let ReverieType::SovereignAgent(agent_name_nonce)
-                    | ReverieType::Agent(agent_name_nonce) = &reverie.reverie_type {
+                // 1. Save agent metadata, unless SaveReverieOnNetwork already put it with the ciphertext
+                let saved_on_network = DistributionPolicy::from(&reverie.reverie_type).save_on_network;
+                if !saved_on_network {
+                    if let ReverieType::SovereignAgent(agent_name_nonce)
+                        | ReverieType::Agent(agent_name_nonce) = &reverie.reverie_type {
 
-                    // Set broadcaster's peer info
-                    self.peer_manager.set_peer_info_agent_vessel(
-                        &AgentVesselInfo {
-                            reverie_id: reverie.id.clone(),
-                            reverie_type: reverie.reverie_type.clone(),
-                            threshold: reverie.threshold,
-                            total_frags: reverie.total_frags,
-                            current_vessel_peer_id: source_peer_id,
-                            next_vessel_peer_id: target_peer_id,
-                        }
-                    );
-                    // Put agent_name_nonce => reverie_id on DHT
-                    self.swarm.behaviour_mut().kademlia.put_record(
-                        kad::Record {
-                            key: agent_name_nonce.to_reverie_id().to_kad_key(),
-                            value: serde_json::to_vec(&reverie.id).expect("serde_json::to_vec(reverie_id)"),
-                            publisher: Some(self.node_id.peer_id),
-                            expires: None,
-                        },
-                        kad::Quorum::Majority
-                    ).expect("put_record err");
+                        // Set broadcaster's peer info
+                        self.peer_manager.set_peer_info_agent_vessel(
+                            &AgentVesselInfo {
+                                reverie_id: reverie.id.clone(),
+                                reverie_type: reverie.reverie_type.clone(),
+                                threshold: reverie.threshold,
+                                total_frags: reverie.total_frags,
+                                current_vessel_peer_id: source_peer_id,
+                                next_vessel_peer_id: target_peer_id,
+                            }
+                        );
+                        // Put agent_name_nonce => reverie_id on DHT
+                        self.swarm.behaviour_mut().kademlia.put_record(
+                            kad::Record {
+                                key: agent_name_nonce.to_reverie_id().to_kad_key(),
+                                value: serde_json::to_vec(&reverie.id).expect("serde_json::to_vec(reverie_id)"),
+                                publisher: Some(self.node_id.peer_id),
+                                expires: None,
+                            },
+                            kad::Quorum::Majority
+                        ).expect("put_record err");
+                    }
                 }
 
-                // Dispatch Reverie (ciphertext) to target vessel
-                self.swarm.behaviour_mut()
-                    .request_response
-                    .send_request(
-                        &ciphertext_holder,
-                        FragmentRequestEnum::SaveCiphertextRequest(
-                            ReverieMessage {
-                                reverie,
-                                source_peer_id,
-                                target_peer_id,
-                                keyfrag_providers,
-                            },
-                        )
-                    );
+                let reverie_msg = ReverieMessage {
+                    reverie,
+                    source_peer_id,
+                    target_peer_id,
+                    keyfrag_providers,
+                };
+
+                if ciphertext_holder == self.node_id.peer_id {
+                    // Re-keyed Reveries are held by this node, so save them locally
+                    if let Err(e) = self.save_vessel_reverie(reverie_msg) {
+                        error!("Failed to save reverie locally: {}", e);
+                    }
+                } else if reverie_msg.reverie.umbral_ciphertext.len() > CIPHERTEXT_CHUNK_SIZE {
+                    // Large ciphertexts are sent in chunks, reassembled by the target vessel
+                    for chunk in split_reverie_ciphertext(&reverie_msg, CIPHERTEXT_CHUNK_SIZE) {
+                        self.swarm.behaviour_mut()
+                            .request_response
+                            .send_request(
+                                &ciphertext_holder,
+                                FragmentRequestEnum::SaveCiphertextChunkRequest(chunk)
+                            );
+                    }
+                } else {
+                    // Dispatch Reverie (ciphertext) to target vessel
+                    self.swarm.behaviour_mut()
+                        .request_response
+                        .send_request(
+                            &ciphertext_holder,
+                            FragmentRequestEnum::SaveCiphertextRequest(reverie_msg)
+                        );
+                }
             }
             NodeCommand::GetReverie {
                 reverie_id,
@@ -171,7 +198,7 @@ impl NetworkEvents {
                         info!("{}", format!("GetReverie({}) from local node", reverie_id).green());
                         // get agent Reverie locally from the node.
                         let reverie = match self.peer_manager.get_reverie(&reverie_id) {
-                            Some(reverie) => Ok(reverie.clone()),
+                            Some(reverie) => Ok(self.peer_manager.verify_kfrag_providers(reverie.clone())),
                             None => Err(anyhow!("Reverie not found")),
                         };
                         sender.send(reverie).ok();
@@ -216,6 +243,72 @@ impl NetworkEvents {
 
                 self.pending.get_reverie_agent_name.insert(reverie_to_name_kadkey, sender);
             }
+            NodeCommand::GetReverieTypeProviders {
+                reverie_type,
+                sender,
+            } => {
+                let query_id = self.swarm.behaviour_mut()
+                    .kademlia
+                    .get_providers(ReverieTypeIndexKey::from(&reverie_type).to_kad_key());
+
+                self.pending.get_providers.insert(query_id, PendingProviders {
+                    sender,
+                    providers: Default::default(),
+                });
+            }
+            NodeCommand::GetReverieTypeEntries {
+                entries_key,
+                sender,
+            } => {
+                self.swarm.behaviour_mut()
+                    .kademlia
+                    .get_record(entries_key.to_kad_key());
+
+                self.pending.get_reverie_type_entries.insert(entries_key, sender);
+            }
+            NodeCommand::GetReverieHolders {
+                reverie_id,
+                sender,
+            } => {
+                let query_id = self.swarm.behaviour_mut()
+                    .kademlia
+                    .get_providers(ReverieIdToPeerId::from(reverie_id).to_kad_key());
+
+                self.pending.get_providers.insert(query_id, PendingProviders {
+                    sender,
+                    providers: Default::default(),
+                });
+            }
+            NodeCommand::GetReverieHolderRecord {
+                holder_key,
+                sender,
+            } => {
+                self.swarm.behaviour_mut()
+                    .kademlia
+                    .get_record(holder_key.to_kad_key());
+
+                self.pending.get_reverie_holder_records.insert(holder_key, sender);
+            }
+            NodeCommand::RequestReverieFromHolder {
+                reverie_id,
+                holder_peer_id,
+                sender,
+            } => {
+                info!("{}", format!("RequestReverieFromHolder for {} from {} {}",
+                    reverie_id,
+                    get_node_name(&holder_peer_id),
+                    short_peer_id(&holder_peer_id)
+                ).yellow());
+
+                let request_id = self.swarm.behaviour_mut()
+                    .request_response
+                    .send_request(
+                        &holder_peer_id,
+                        FragmentRequestEnum::GetCiphertextRequest(reverie_id)
+                    );
+
+                self.pending.request_reveries.insert(request_id, sender);
+            }
             NodeCommand::RequestCapsuleFragment {
                 reverie_id,
                 kfrag_provider_peer_id,
@@ -233,41 +326,55 @@ impl NetworkEvents {
                     .send_request(
                         &kfrag_provider_peer_id,
                         FragmentRequestEnum::GetFragmentRequest(
-                            reverie_id,
-                            access_key
+                            reverie_id.clone(),
+                            access_key.clone()
                         )
                     );
 
-                self.pending.request_fragments.insert(request_id, sender);
+                self.pending.request_fragments.insert(request_id, PendingFragmentRequest {
+                    sender,
+                    reverie_id,
+                    kfrag_provider_peer_id,
+                    access_key,
+                    attempts: 1,
+                });
             }
             NodeCommand::MarkPendingRespawnComplete {
                 prev_reverie_id,
                 prev_peer_id, // failed vessel's peer_id
                 prev_agent_name_nonce,
             } => {
+                // Only the previous Reverie's kfrag providers hold state for it,
+                // collected before marking complete prunes them
+                let mut prev_kfrag_providers: HashSet<PeerId> = self.peer_manager
+                    .get_kfrag_providers(&prev_reverie_id)
+                    .cloned()
+                    .unwrap_or_default();
+                if let Some(prev_reverie_msg) = self.peer_manager.reverie.get(&prev_reverie_id) {
+                    prev_kfrag_providers.extend(prev_reverie_msg.keyfrag_providers.iter().cloned());
+                }
+                prev_kfrag_providers.remove(&prev_peer_id);
+                prev_kfrag_providers.remove(&self.node_id.peer_id);
+
                 // 1) Mark respawn complete locally
-                self.mark_pending_respawn_complete(prev_peer_id, prev_agent_name_nonce.clone());
+                self.mark_pending_respawn_complete(
+                    prev_reverie_id.clone(),
+                    prev_peer_id,
+                    prev_agent_name_nonce.clone()
+                );
 
-                // 2) Get previous kfrag providers of the previous vessel
-                match self.peer_manager.kfrag_providers.get(&prev_reverie_id) {
-                    None => {
-                        tracing::warn!("No previous kfrag providers found for: {}", prev_reverie_id);
-                    },
-                    Some(prev_kfrag_providers) => {
-                        // 3) Broadcast to previous Kfrag Providers to do the same
-                        for peer_id in prev_kfrag_providers {
-                            self.swarm.behaviour_mut()
-                                .request_response
-                                .send_request(
-                                    &peer_id,
-                                    FragmentRequestEnum::MarkRespawnCompleteRequest {
-                                        prev_reverie_id: prev_reverie_id.clone(),
-                                        prev_peer_id: prev_peer_id.clone(),
-                                        prev_agent_name: prev_agent_name_nonce.clone(),
-                                    }
-                                );
-                        }
-                    }
+                // 2) Send a cleanup notice so the previous kfrag providers prune the same state
+                for peer_id in prev_kfrag_providers {
+                    self.swarm.behaviour_mut()
+                        .request_response
+                        .send_request(
+                            &peer_id,
+                            FragmentRequestEnum::MarkRespawnCompleteRequest {
+                                prev_reverie_id: prev_reverie_id.clone(),
+                                prev_peer_id: prev_peer_id.clone(),
+                                prev_agent_name: prev_agent_name_nonce.clone(),
+                            }
+                        );
                 }
 
             }
@@ -277,12 +384,54 @@ impl NetworkEvents {
                     Err(e) => sender.send(Err(Box::new(e))).ok(),
                 };
             }
+            NodeCommand::RetryBootstrap { bootstrap_addrs, sender } => {
+                let connected = self.swarm.connected_peers().next().is_some();
+                if !connected {
+                    for addr in bootstrap_addrs {
+                        debug!("{} Re-dialing bootstrap address {}", self.nname(), addr);
+                        if let Err(e) = self.swarm.dial(addr.clone()) {
+                            warn!("{} Failed to dial bootstrap address {}: {}", self.nname(), addr, e);
+                        }
+                    }
+                }
+                let bootstrapped = connected && match self.swarm.behaviour_mut().kademlia.bootstrap() {
+                    Ok(_) => true,
+                    Err(e) => {
+                        warn!("{} Failed to bootstrap Kademlia: {}", self.nname(), e);
+                        false
+                    }
+                };
+                sender.send(bootstrapped).ok();
+            }
             NodeCommand::GetConnectedPeers { responder } => {
                 let connected_peers: Vec<PeerId> = self.swarm.connected_peers().cloned().collect();
                 if responder.send(connected_peers.clone()).is_err() {
                     warn!("Failed to send connected peers response. Receiver likely dropped. Peers: {:?}", connected_peers);
                 }
             }
+            NodeCommand::GetConnectedPeerInfo { sender } => {
+                sender.send(self.peer_manager.connected_peer_info(self.swarm.connected_peers())).ok();
+            }
+            NodeCommand::GetReadiness { sender } => {
+                let readiness = NodeReadiness::new(
+                    self.swarm.connected_peers().count(),
+                    self.kademlia_bootstrapped,
+                );
+                sender.send(readiness).ok();
+            }
+            NodeCommand::GetPendingRequests { sender } => {
+                sender.send(self.pending.stats(time::Instant::now())).ok();
+            }
+            NodeCommand::GetKfragProvidersByFragNum { reverie_id, sender } => {
+                sender.send(self.peer_manager.get_kfrag_providers_by_frag_num(&reverie_id)).ok();
+            }
+            NodeCommand::GetPeerReputations { sender } => {
+                sender.send(self.peer_manager.peer_reputations()).ok();
+            }
+            NodeCommand::ReportPeerReputation { peer_id, event } => {
+                debug!("{} {:?} for {}", self.nname(), event, short_peer_id(&peer_id));
+                self.peer_manager.update_peer_reputation(peer_id, event);
+            }
             NodeCommand::SimulateNodeFailure { sender, reason } => {
                 info!("{}", format!("Simulating network failure: {}", self.nname()).red());
                 info!("{}", format!("Triggering heartbeat failure in 500ms: {:?}", reason).red());
@@ -290,6 +439,51 @@ impl NetworkEvents {
                 std::thread::sleep(std::time::Duration::from_millis(500));
                 self.simulate_heartbeat_failure().await;
             }
+            NodeCommand::ForceReincarnate { agent_name_nonce, sender } => {
+                sender.send(self.force_reincarnate(agent_name_nonce).await).ok();
+            }
+            NodeCommand::RequestVesselHandoff { agent_name_nonce, target_peer_id, sender } => {
+                info!("{}", format!("Handing off agent {} to {}", agent_name_nonce, get_node_name(&target_peer_id)).yellow());
+                let request_id = self.swarm.behaviour_mut()
+                    .request_response
+                    .send_request(
+                        &target_peer_id,
+                        FragmentRequestEnum::HandoffVesselRequest(agent_name_nonce)
+                    );
+
+                self.pending.vessel_handoffs.insert(request_id, sender);
+            }
+            NodeCommand::ReleaseVesselAgent { agent_name_nonce, sender } => {
+                let released_reverie_ids = self.peer_manager.release_vessel_agent(&agent_name_nonce);
+                self.remove_reverie_holder_kademlia(&released_reverie_ids);
+                self.update_reverie_type_indexes();
+                sender.send(released_reverie_ids).ok();
+            }
+            NodeCommand::GetVesselAgent { sender } => {
+                sender.send(self.peer_manager.vessel_agent.clone()).ok();
+            }
+            NodeCommand::GetVesselReveries { sender } => {
+                sender.send(self.peer_manager.vessel_reveries()).ok();
+            }
+            NodeCommand::RotateUmbralKey { umbral_key, sender } => {
+                info!("{}", format!("Rotating Umbral key to: {}", umbral_key.public_key).yellow());
+                self.node_id.umbral_key = umbral_key;
+
+                let vessel_status = match self.peer_manager.vessel_reveries().is_empty() {
+                    true => self.peer_manager.vessel_status(),
+                    false => VesselStatus::ActiveVessel,
+                };
+                // Peers pick up the new pubkeys when choosing vessels
+                let result = self.put_signed_vessel_status_kademlia(
+                    NodeKeysWithVesselStatus {
+                        peer_id: self.node_id.peer_id,
+                        umbral_public_key: self.node_id.umbral_key.public_key,
+                        umbral_verifying_public_key: self.node_id.umbral_key.verifying_public_key,
+                        vessel_status,
+                    }
+                );
+                sender.send(result).ok();
+            }
             NodeCommand::GetNodeState { sender } => {
                 let node_state = self.query_node_state().await;
                 sender.send(node_state).ok();
@@ -3,7 +3,13 @@ use libp2p::gossipsub;
 use super::NetworkEvents;
 
 /**
- * Not currently used
+ * Not currently used: the gossipsub behaviour is disabled and kfrag broadcasts,
+ * topic switches and respawn requests are sent over request-response instead,
+ * where the noise handshake already authenticates the sending PeerId.
+ *
+ * If gossipsub is re-enabled, build it with `gossipsub::MessageAuthenticity::Signed(id_keys)`
+ * and `gossipsub::ValidationMode::Strict` so unsigned or forged messages are dropped
+ * before they reach this handler.
  */
 impl NetworkEvents {
     pub async fn handle_gossipsub_event(&mut self, gevent: gossipsub::Event) -> Result<()> {
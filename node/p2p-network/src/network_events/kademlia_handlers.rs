@@ -9,7 +9,7 @@ use std::collections::HashSet;
 use tracing::{info, warn, debug};
 use serde::{Deserialize, Serialize};
 
-use crate::{get_node_name, short_peer_id, SendError};
+use crate::{get_node_name, short_peer_id};
 use crate::types::{
     PeerIdToNodeStatusKey,
     NodeKeysWithVesselStatus,
@@ -17,7 +17,10 @@ use crate::types::{
     ReverieIdToNameKey,
     ReverieId,
     ReverieMessage,
+    SignedReverieTypeEntries,
+    SignedReverieHolder,
     KademliaKey,
+    NodeEvent,
 };
 use super::NetworkEvents;
 
@@ -76,19 +79,25 @@ impl NetworkEvents {
 
             kad::QueryResult::GetProviders(Ok(p)) => match p {
                 kad::GetProvidersOk::FoundProviders { providers, .. } => {
-                    if let Some(sender) = self.pending.get_providers.remove(&query_id) {
+                    // Providers arrive in batches (local store first), collect until the query finishes
+                    if let Some(pending) = self.pending.get_providers.get_mut(&query_id) {
+                        pending.providers.extend(providers);
+                    }
+                }
+                kad::GetProvidersOk::FinishedWithNoAdditionalRecord { .. } => {
+                    if let Some(pending) = self.pending.get_providers.remove(&query_id) {
                         // send providers back
-                        sender.send(providers).map_err(SendError::from)?;
-
-                        self.swarm
-                            .behaviour_mut()
-                            .kademlia
-                            .query_mut(&query_id)
-                            .unwrap()
-                            .finish();
+                        pending.sender.send(pending.providers).ok();
                     }
+                },
+            }
+
+            kad::QueryResult::GetProviders(Err(e)) => {
+                // Timed out: send back whichever providers were found
+                if let Some(pending) = self.pending.get_providers.remove(&query_id) {
+                    debug!("{} GetProviders incomplete: {}", self.nname(), e);
+                    pending.sender.send(pending.providers).ok();
                 }
-                kad::GetProvidersOk::FinishedWithNoAdditionalRecord { .. } => {},
             }
 
             kad::QueryResult::GetRecord(Ok(
@@ -122,11 +131,37 @@ impl NetworkEvents {
                     KademliaKey::ReverieIdToReverie(reverie_id) => {
                         if let Some(oneshot_sender) = self.pending.get_reverie_from_network.remove(&reverie_id) {
                             let reverie_msg = serde_json::from_slice::<ReverieMessage>(&record.value)
+                                .map(|reverie_msg| self.peer_manager.verify_kfrag_providers(reverie_msg))
                                 .map_err(|e| anyhow!(e.to_string()));
 
                             oneshot_sender.send(reverie_msg).ok();
                         }
                     }
+                    KademliaKey::ReverieTypeEntriesKey(key) => {
+                        if let Some(sender) = self.pending.get_reverie_type_entries.remove(&key) {
+                            // Only entries signed by the vessel itself are accepted, the record's
+                            // publisher field is set by whoever stored it and can't be trusted
+                            match serde_json::from_slice::<SignedReverieTypeEntries>(&record.value) {
+                                Ok(signed_entries) => match signed_entries.verify(&key) {
+                                    Ok(()) => { sender.send(signed_entries.entries).ok(); },
+                                    Err(e) => warn!("{} Ignoring {} entries for {}: {}", self.nname(), key.reverie_type_kind, key.vessel_peer_id, e),
+                                },
+                                Err(e) => warn!("{}", e.to_string()),
+                            }
+                        }
+                    }
+                    KademliaKey::ReverieHolderKey(key) => {
+                        if let Some(sender) = self.pending.get_reverie_holder_records.remove(&key) {
+                            // Only records signed by the holder itself are accepted
+                            match serde_json::from_slice::<SignedReverieHolder>(&record.value) {
+                                Ok(signed_holder) => match signed_holder.verify(&key) {
+                                    Ok(()) => { sender.send(signed_holder).ok(); },
+                                    Err(e) => warn!("{} Ignoring holder record of {} for {}: {}", self.nname(), key.reverie_id, key.holder_peer_id, e),
+                                },
+                                Err(e) => warn!("{}", e.to_string()),
+                            }
+                        }
+                    }
                     KademliaKey::Unknown(s) => {
                         warn!("Unknown Kademlia key: {}", s);
                     }
@@ -139,9 +174,26 @@ impl NetworkEvents {
                     .finish();
             }
 
+            kad::QueryResult::GetRecord(Err(e)) => {
+                // Dropping the pending sender tells the NodeClient no entries were found
+                match KademliaKey::from(e.key()) {
+                    KademliaKey::ReverieTypeEntriesKey(key) => { self.pending.get_reverie_type_entries.remove(&key); }
+                    KademliaKey::ReverieHolderKey(key) => { self.pending.get_reverie_holder_records.remove(&key); }
+                    _ => {}
+                }
+                debug!("{}: GetRecord failed: {}", self.nname(), e);
+            }
+
             kad::QueryResult::PutRecord(Ok(kad::PutRecordOk { key })) => {}
 
-            kad::QueryResult::Bootstrap(Ok(kad::BootstrapOk { peer: peer_id, .. })) => {
+            kad::QueryResult::Bootstrap(Ok(kad::BootstrapOk { peer: peer_id, num_remaining })) => {
+                if num_remaining == 0 && !self.kademlia_bootstrapped {
+                    info!("{} Kademlia bootstrap complete", self.nname());
+                    self.kademlia_bootstrapped = true;
+                    self.emit_node_event(NodeEvent::Bootstrapped {
+                        connected_peers: self.swarm.connected_peers().count(),
+                    });
+                }
                 if peer_id == self.node_id.peer_id {
                     debug!("BootstrapOk: publishing NodeVesselStatus and Umbral PK {:?} {}\n",
                         get_node_name(&peer_id),
@@ -152,7 +204,7 @@ impl NetworkEvents {
                         peer_id: peer_id,
                         umbral_public_key: self.node_id.umbral_key.public_key,
                         umbral_verifying_public_key: self.node_id.umbral_key.verifying_public_key,
-                        vessel_status: self.peer_manager.vessel_status,
+                        vessel_status: self.peer_manager.vessel_status(),
                     };
                     // Publish signed vessel status during bootstrap
                     self.put_signed_vessel_status_kademlia(node_vessel_status)?;
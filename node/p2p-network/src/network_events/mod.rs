@@ -5,6 +5,10 @@ mod kademlia_handlers;
 mod request_response_handlers;
 mod reincarnation;
 mod query_node_state;
+mod pending_requests;
+mod access_decisions;
+mod respawn_votes;
+mod shutdown_rebroadcast;
 pub(crate) mod peer_manager;
 
 use std::collections::{HashMap, HashSet};
@@ -20,36 +24,52 @@ use libp2p::{
     swarm::Swarm,
     PeerId
 };
-use tokio::sync::{RwLock, mpsc, oneshot};
-use tracing::{info, warn, debug};
+use tokio::sync::{RwLock, broadcast, mpsc, oneshot, watch};
+use tracing::{info, warn, debug, error};
 
 use crate::{
     SendError,
     get_node_name,
 };
 use crate::behaviour::heartbeat_behaviour::HeartbeatConfig;
+use crate::create_network::NetworkConfig;
 use crate::node_client::NodeCommand;
 use crate::types::{
     FragmentNumber,
     NetworkEvent,
+    NodeEvent,
     RespawnId,
     PeerIdToNodeStatusKey,
     AgentVesselInfo,
     NodeKeysWithVesselStatus,
     VesselStatus,
     SignedVesselStatus,
+    Reverie,
     ReverieId,
     ReverieIdToNameKey,
     ReverieIdToPeerId,
     ReverieMessage,
+    ReverieType,
+    ReverieTypeEntriesKey,
+    ReverieTypeIndexEntry,
+    ReverieTypeIndexKey,
+    SignedReverieTypeEntries,
+    ReverieHolderKey,
+    SignedReverieHolder,
     KademliaKeyTrait,
+    AccessKey,
 };
 use crate::node_client::container_manager::{ContainerManager, RestartReason};
 use crate::env_var::NODE_SEED_NUM;
 use crate::behaviour::Behaviour;
 use runtime::reencrypt::UmbralKey;
 use runtime::near_runtime::NearRuntime;
+use runtime::evm_runtime::EvmRuntime;
 use peer_manager::PeerManager;
+use pending_requests::PendingMap;
+use access_decisions::AccessDecisionCache;
+use respawn_votes::RespawnVote;
+use shutdown_rebroadcast::{AfterRebroadcast, ShutdownRebroadcast};
 use tokio::time;
 use time::Duration;
 
@@ -90,60 +110,121 @@ pub struct NetworkEvents {
     command_receiver: mpsc::Receiver<NodeCommand>,
     // Network event sender
     network_event_sender: mpsc::Sender<NetworkEvent>,
+    // Lifecycle events for RPC subscribers
+    node_event_sender: broadcast::Sender<NodeEvent>,
     // tracks own heartbeat status
     internal_heartbeat_fail_receiver: mpsc::Receiver<HeartbeatConfig>,
     // tracks peer heartbeats status
     peer_heartbeat_checker: time::Interval,
+    // purges cfrags of expired reveries
+    expired_cfrags_sweeper: time::Interval,
     // Peer Manager State
     peer_manager: PeerManager,
     // pending P2p network requests
     pending: PendingRequests,
+    // recent contract access decisions for fragment requests
+    access_decisions: AccessDecisionCache,
     // GossipSub topics
     topics: HashMap<String, IdentTopic>,
     // Container Manager
     container_manager: Arc<RwLock<ContainerManager>>,
     // Near Runtime
-    near_runtime: Arc<NearRuntime>
+    near_runtime: Arc<NearRuntime>,
+    // EVM Runtime
+    evm_runtime: Arc<EvmRuntime>,
+    // Swarm-level network options
+    network_config: NetworkConfig,
+    // Set once a Kademlia bootstrap query completes
+    kademlia_bootstrapped: bool,
+    // Signals the event loop to flush state and exit
+    shutdown_receiver: watch::Receiver<bool>,
 }
 
 struct PendingRequests {
-    get_providers: HashMap<
+    get_providers: PendingMap<
         kad::QueryId,
-        oneshot::Sender<HashSet<PeerId>>
+        PendingProviders
     >,
-    get_node_vessels: HashMap<
+    get_reverie_type_entries: PendingMap<
+        ReverieTypeEntriesKey,
+        oneshot::Sender<Vec<ReverieTypeIndexEntry>>
+    >,
+    get_node_vessels: PendingMap<
         PeerIdToNodeStatusKey,
         mpsc::Sender<NodeKeysWithVesselStatus>
     >,
-    get_reverie_agent_name: HashMap<
+    get_reverie_agent_name: PendingMap<
         ReverieIdToNameKey,
         oneshot::Sender<Option<ReverieId>>
     >,
-    get_reverie_peer_id: HashMap<
+    get_reverie_peer_id: PendingMap<
         ReverieIdToPeerId,
         oneshot::Sender<Option<PeerId>>
     >,
-    get_reverie_from_network: HashMap<
+    get_reverie_from_network: PendingMap<
         ReverieId,
         oneshot::Sender<Result<ReverieMessage>>
     >,
-    request_fragments: HashMap<
+    get_reverie_holder_records: PendingMap<
+        ReverieHolderKey,
+        oneshot::Sender<SignedReverieHolder>
+    >,
+    request_fragments: PendingMap<
+        request_response::OutboundRequestId,
+        PendingFragmentRequest
+    >,
+    request_reveries: PendingMap<
+        request_response::OutboundRequestId,
+        oneshot::Sender<Result<ReverieMessage, SendError>>
+    >,
+    vessel_handoffs: PendingMap<
+        request_response::OutboundRequestId,
+        oneshot::Sender<Result<RespawnId, SendError>>
+    >,
+    respawns: PendingMap<RespawnId, ()>,
+    // Votes on whether a vessel failed, held by its next vessel until respawn or timeout
+    respawn_votes: PendingMap<RespawnId, RespawnVote>,
+    // RespawnVoteRequests sent, so a failed request counts as an abstention
+    respawn_vote_requests: PendingMap<
         request_response::OutboundRequestId,
-        oneshot::Sender<Result<Vec<u8>, SendError>>
+        RespawnId
     >,
-    respawns: HashSet<RespawnId>,
+    // Vessel reveries sent to kfrag providers before shutdown, awaiting acknowledgement
+    shutdown_rebroadcast: Option<ShutdownRebroadcast>,
+}
+
+/// Providers found so far by a GetProviders query, sent once the query finishes
+struct PendingProviders {
+    sender: oneshot::Sender<HashSet<PeerId>>,
+    providers: HashSet<PeerId>,
+}
+
+/// Outbound cfrag request, kept so it can be re-sent if the provider times out
+struct PendingFragmentRequest {
+    sender: oneshot::Sender<Result<Vec<u8>, SendError>>,
+    reverie_id: ReverieId,
+    kfrag_provider_peer_id: PeerId,
+    access_key: AccessKey,
+    attempts: u32,
 }
 
 impl PendingRequests {
     fn new() -> Self {
         Self {
             get_providers: Default::default(),
+            get_reverie_type_entries: Default::default(),
             get_node_vessels: Default::default(),
             get_reverie_agent_name: Default::default(),
             get_reverie_peer_id: Default::default(),
             get_reverie_from_network: Default::default(),
+            get_reverie_holder_records: Default::default(),
             request_fragments: Default::default(),
+            request_reveries: Default::default(),
+            vessel_handoffs: Default::default(),
             respawns: Default::default(),
+            respawn_votes: Default::default(),
+            respawn_vote_requests: Default::default(),
+            shutdown_rebroadcast: None,
         }
     }
 }
@@ -154,9 +235,13 @@ impl NetworkEvents {
         node_id: NodeIdentity,
         command_receiver: mpsc::Receiver<NodeCommand>,
         network_event_sender: mpsc::Sender<NetworkEvent>,
+        node_event_sender: broadcast::Sender<NodeEvent>,
         internal_heartbeat_fail_receiver: mpsc::Receiver<HeartbeatConfig>,
         container_manager: Arc<RwLock<ContainerManager>>,
-        near_runtime: Arc<NearRuntime>
+        near_runtime: Arc<NearRuntime>,
+        evm_runtime: Arc<EvmRuntime>,
+        network_config: NetworkConfig,
+        shutdown_receiver: watch::Receiver<bool>,
     ) -> Self {
         let node_name = node_id.node_name.clone();
         let peer_id = node_id.peer_id.clone();
@@ -165,13 +250,20 @@ impl NetworkEvents {
             node_id,
             command_receiver,
             network_event_sender,
+            node_event_sender,
             internal_heartbeat_fail_receiver,
             peer_heartbeat_checker: tokio::time::interval(Duration::from_secs(1)),
+            expired_cfrags_sweeper: tokio::time::interval(network_config.cfrag_expiry_sweep_interval),
             peer_manager: PeerManager::new(node_name, peer_id),
             pending: PendingRequests::new(),
+            access_decisions: AccessDecisionCache::new(network_config.access_decision_cache_ttl),
             topics: HashMap::new(),
             container_manager,
             near_runtime,
+            evm_runtime,
+            network_config,
+            kademlia_bootstrapped: false,
+            shutdown_receiver,
         }
     }
 
@@ -179,6 +271,18 @@ impl NetworkEvents {
         format!("{}{}", self.node_id.node_name.yellow(), ">".blue())
     }
 
+    /// Publishes a lifecycle event to RPC subscribers, dropped if nobody is subscribed
+    fn emit_node_event(&self, event: NodeEvent) {
+        self.node_event_sender.send(event).ok();
+    }
+
+    /// Sends a NetworkEvent to the NodeClient, and a redacted copy to RPC subscribers
+    async fn send_network_event(&self, event: NetworkEvent) -> Result<()> {
+        self.emit_node_event(NodeEvent::from(&event));
+        self.network_event_sender.send(event).await?;
+        Ok(())
+    }
+
     pub async fn init_listen_for_network_events(mut self) {
         // Start listening on specified addresses
         for addr in self.swarm.listeners() {
@@ -186,6 +290,9 @@ impl NetworkEvents {
         }
 
         loop {
+            let rebroadcast_deadline = self.pending.shutdown_rebroadcast.as_ref()
+                .map(|rebroadcast| rebroadcast.deadline);
+
             tokio::select! {
                 _ = self.peer_heartbeat_checker.tick() => {
 
@@ -196,19 +303,84 @@ impl NetworkEvents {
                     self.handle_peer_heartbeat_failure().await
                         .expect("error handling heartbeat failure");
                 }
+                _ = self.expired_cfrags_sweeper.tick() => {
+                    self.sweep_expired_cfrags();
+                    self.sweep_stale_reverie_chunks();
+                    self.sweep_stale_pending_requests();
+                    self.access_decisions.purge_expired(time::Instant::now());
+                    // withdraws expired vessel reveries from the reverie type index
+                    self.update_reverie_type_indexes();
+                }
                 swarm_event = self.swarm.select_next_some() => {
-                    self.handle_swarm_event(swarm_event).await.expect("swarm handler error");
+                    // A misbehaving peer must not be able to take down the event loop
+                    if let Err(e) = self.handle_swarm_event(swarm_event).await {
+                        error!("{} Error handling swarm event: {}", self.nname(), e);
+                    }
                 },
                 hb = self.internal_heartbeat_fail_receiver.recv() => match hb {
                     // internal Heartbeat failure
                     Some(hb_config) => self.handle_internal_heartbeat_failure(hb_config).await,
                     None => break // channel closed, shutting down the network event loop.
                 },
-                command = self.command_receiver.recv() => match command {
+                // Commands wait while vessel reveries are re-broadcast before shutdown
+                command = self.command_receiver.recv(), if rebroadcast_deadline.is_none() => match command {
                     Some(c) => self.handle_command(c).await,
                     None => return
                 },
+                // A dropped shutdown sender also means the node is going away
+                _ = self.shutdown_receiver.changed(), if rebroadcast_deadline.is_none() => {
+                    self.shutdown();
+                }
+                // Re-broadcast timed out, finished below
+                _ = time::sleep_until(rebroadcast_deadline.unwrap_or_else(time::Instant::now)),
+                    if rebroadcast_deadline.is_some() => {}
             }
+
+            // Re-broadcasts complete here rather than in a handler, so the swarm keeps
+            // being driven by this loop while kfrag providers respond
+            if self.finish_shutdown_rebroadcast().await {
+                self.internal_heartbeat_fail_receiver.close();
+                return
+            }
+        }
+    }
+
+    /// Stops accepting commands, and sends vessel reveries to their kfrag providers so they
+    /// outlive this node. The event loop exits once the re-broadcast settles.
+    fn shutdown(&mut self) {
+        info!("{} {}", self.nname(), "Shutting down network event loop".yellow());
+
+        // Commands still queued are dropped, so their callers get a closed channel error
+        self.command_receiver.close();
+        let mut dropped_commands = 0;
+        while self.command_receiver.try_recv().is_ok() {
+            dropped_commands += 1;
+        }
+        if dropped_commands > 0 {
+            warn!("{} Dropped {} queued commands on shutdown", self.nname(), dropped_commands);
+        }
+
+        self.start_shutdown_rebroadcast(AfterRebroadcast::Exit, self.network_config.shutdown_timeout);
+    }
+
+    fn sweep_expired_cfrags(&mut self) {
+        let expired_reverie_ids = self.peer_manager.purge_expired_cfrags(chrono::Utc::now().timestamp());
+        if !expired_reverie_ids.is_empty() {
+            info!("{} Purged cfrags of expired reveries: {:?}", self.nname(), expired_reverie_ids);
+        }
+    }
+
+    fn sweep_stale_reverie_chunks(&mut self) {
+        let stale_reverie_ids = self.peer_manager.purge_stale_reverie_chunks();
+        if !stale_reverie_ids.is_empty() {
+            warn!("{} Dropped incomplete chunked ciphertexts: {:?}", self.nname(), stale_reverie_ids);
+        }
+    }
+
+    fn sweep_stale_pending_requests(&mut self) {
+        let swept = self.pending.sweep_stale(self.network_config.pending_request_timeout, time::Instant::now());
+        if swept > 0 {
+            warn!("{} Swept {} pending requests with no response", self.nname(), swept);
         }
     }
 
@@ -222,6 +394,8 @@ impl NetworkEvents {
             .remove_record(&PeerIdToNodeStatusKey::from(peer_id).to_kad_key());
     }
 
+    /// Own records are put with `expires: None`: peers store them for `NetworkConfig::record_ttl`,
+    /// and Kademlia republishes them every `record_republish_interval` while this node is alive.
     fn put_signed_vessel_status_kademlia(&mut self, status: NodeKeysWithVesselStatus) -> Result<()> {
         let signed_status = SignedVesselStatus::new(status.clone(), &self.node_id.id_keys)?;
 
@@ -238,12 +412,147 @@ impl NetworkEvents {
         Ok(())
     }
 
-    fn put_reverie_holder_kademlia(&mut self, reverie_id: ReverieId, reverie_holder_peer_id: PeerId) -> Result<()> {
-        self.swarm.behaviour_mut().kademlia.put_record(
+    /// Saves a Reverie this node holds the ciphertext for, setting agent vessel metadata
+    /// and putting vessel status and the reverie holder on Kademlia.
+    fn save_vessel_reverie(&mut self, reverie_msg: ReverieMessage) -> Result<()> {
+        let ReverieMessage {
+            reverie,
+            source_peer_id,
+            target_peer_id,
+            keyfrag_providers,
+        } = reverie_msg;
+
+        // 1) Save Agent metadata if need be
+        if let ReverieType::Agent(..) | ReverieType::SovereignAgent(..) = reverie.reverie_type {
+
+            let agent_metadata = AgentVesselInfo {
+                reverie_id: reverie.id.clone(),
+                reverie_type: reverie.reverie_type.clone(),
+                threshold: reverie.threshold,
+                total_frags: reverie.total_frags,
+                current_vessel_peer_id: source_peer_id,
+                next_vessel_peer_id: target_peer_id,
+            };
+
+            self.peer_manager.set_peer_info_agent_vessel(&agent_metadata);
+            self.peer_manager.insert_reverie_metadata(
+                &reverie.id,
+                agent_metadata.clone()
+            );
+
+            // This node is now the agent's vessel
+            if target_peer_id == self.node_id.peer_id {
+                self.peer_manager.transition_to(VesselStatus::ActiveVessel)?;
+            }
+
+            // Put signed vessel status on Kademlia
+            self.put_signed_vessel_status_kademlia(
+                NodeKeysWithVesselStatus {
+                    peer_id: self.node_id.peer_id,
+                    umbral_public_key: self.node_id.umbral_key.public_key,
+                    umbral_verifying_public_key: self.node_id.umbral_key.verifying_public_key,
+                    vessel_status: self.peer_manager.vessel_status(),
+                }
+            )?;
+        }
+
+        // 2) Save Reverie locally on this node
+        self.peer_manager.insert_reverie(
+            &reverie.id,
+            ReverieMessage {
+                reverie: reverie.clone(),
+                source_peer_id,
+                target_peer_id,
+                keyfrag_providers,
+            },
+        );
+
+        self.emit_node_event(NodeEvent::ReverieSaved {
+            reverie_id: reverie.id.clone(),
+            reverie_type: reverie.reverie_type.clone(),
+            source_peer_id,
+            target_peer_id,
+        });
+
+        // 3) Index the reverie by type, if this node is its vessel
+        if target_peer_id == self.node_id.peer_id {
+            self.update_reverie_type_indexes();
+        }
+
+        // 4) Put reverie holder's PeerId on Kademlia
+        self.put_reverie_holder_kademlia(&reverie, target_peer_id)
+    }
+
+    /// Republishes the reverie type index for any ReverieType kind whose vessel reveries changed.
+    /// The vessel puts its reverieIds for a kind on Kademlia and provides the kind's index key,
+    /// withdrawing both once it holds no unexpired reveries of that kind.
+    pub(crate) fn update_reverie_type_indexes(&mut self) {
+        let now = chrono::Utc::now().timestamp();
+        for reverie_type_kind in ReverieType::KINDS {
+            if let Some(entries) = self.peer_manager.changed_reverie_type_index(reverie_type_kind, now) {
+                if let Err(e) = self.put_reverie_type_index_kademlia(reverie_type_kind, entries) {
+                    warn!("{} Failed to update {} reverie index: {}", self.nname(), reverie_type_kind, e);
+                }
+            }
+        }
+    }
+
+    fn put_reverie_type_index_kademlia(
+        &mut self,
+        reverie_type_kind: &str,
+        entries: Vec<ReverieTypeIndexEntry>
+    ) -> Result<()> {
+        let index_key = ReverieTypeIndexKey(reverie_type_kind.to_string()).to_kad_key();
+        let entries_key = ReverieTypeEntriesKey::new(reverie_type_kind, self.node_id.peer_id);
+        let signed_entries = SignedReverieTypeEntries::new(&entries_key, entries, &self.node_id.id_keys)?;
+        let entries_key = entries_key.to_kad_key();
+        let peer_id = self.node_id.peer_id;
+        let kademlia = &mut self.swarm.behaviour_mut().kademlia;
+
+        if signed_entries.entries.is_empty() {
+            kademlia.stop_providing(&index_key);
+            kademlia.remove_record(&entries_key);
+            return Ok(());
+        }
+
+        kademlia.put_record(
             kad::Record {
-                key: ReverieIdToPeerId::from(reverie_id.clone()).to_kad_key(),
+                key: entries_key,
+                value: serde_json::to_vec(&signed_entries)?,
+                publisher: Some(peer_id),
+                expires: None,
+            },
+            kad::Quorum::One
+        )?;
+        kademlia.start_providing(index_key)?;
+        Ok(())
+    }
+
+    /// Puts the reverie holder on Kademlia, and provides the holder key so
+    /// `get_providers` finds every peer holding a copy of the reverie.
+    /// Also puts this node's signed record of the copy it holds.
+    fn put_reverie_holder_kademlia(&mut self, reverie: &Reverie, reverie_holder_peer_id: PeerId) -> Result<()> {
+        let holder_key = ReverieIdToPeerId::from(reverie.id.clone()).to_kad_key();
+        let signed_holder_key = ReverieHolderKey::new(reverie.id.clone(), self.node_id.peer_id);
+        let signed_holder = SignedReverieHolder::new(&signed_holder_key, reverie, &self.node_id.id_keys)?;
+        let peer_id = self.node_id.peer_id;
+        let kademlia = &mut self.swarm.behaviour_mut().kademlia;
+
+        kademlia.start_providing(holder_key.clone())?;
+        kademlia.put_record(
+            kad::Record {
+                key: holder_key,
                 value: reverie_holder_peer_id.to_bytes(),
-                publisher: Some(self.node_id.peer_id),
+                publisher: Some(peer_id),
+                expires: None,
+            },
+            kad::Quorum::One
+        )?;
+        kademlia.put_record(
+            kad::Record {
+                key: signed_holder_key.to_kad_key(),
+                value: serde_json::to_vec(&signed_holder)?,
+                publisher: Some(peer_id),
                 expires: None,
             },
             kad::Quorum::One
@@ -251,4 +560,414 @@ impl NetworkEvents {
         Ok(())
     }
 
+    /// Stops providing the holder key of reveries this node dropped, so readers
+    /// no longer ask it for a copy, and removes its signed holder records
+    pub(crate) fn remove_reverie_holder_kademlia(&mut self, reverie_ids: &[ReverieId]) {
+        let peer_id = self.node_id.peer_id;
+        let kademlia = &mut self.swarm.behaviour_mut().kademlia;
+        for reverie_id in reverie_ids {
+            kademlia.stop_providing(&ReverieIdToPeerId::from(reverie_id.clone()).to_kad_key());
+            kademlia.remove_record(&ReverieHolderKey::new(reverie_id.clone(), peer_id).to_kad_key());
+        }
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use libp2p::{Multiaddr, noise, tcp, yamux};
+    use libp2p::request_response::{Event, Message};
+    use libp2p::swarm::SwarmEvent;
+    use runtime::near_runtime::NearConfig;
+    use runtime::evm_runtime::EvmConfig;
+    use crate::create_network::build_swarm;
+    use crate::types::{AccessCondition, FragmentRequestEnum, FragmentResponseEnum, Reverie};
+    use crate::utils::pubkeys::generate_peer_keys;
+
+    type RequestResponseSwarm = Swarm<request_response::cbor::Behaviour<FragmentRequestEnum, FragmentResponseEnum>>;
+
+    /// NetworkEvents without a running event loop, so tests can drive its swarm directly
+    pub(super) async fn test_network_events(network_config: NetworkConfig) -> NetworkEvents {
+        let (peer_id, id_keys, node_name, umbral_key) = generate_peer_keys(Some(1));
+        let (heartbeat_failure_sender, heartbeat_failure_receiver) = mpsc::channel(1);
+        let (heartbeat_sender, _) = async_channel::bounded(1);
+        let (_, command_receiver) = mpsc::channel(1);
+        let (network_events_sender, _) = mpsc::channel(1);
+        let (node_event_sender, _) = broadcast::channel(1);
+        let (_, shutdown_receiver) = watch::channel(false);
+
+        let swarm = build_swarm(
+            id_keys.clone(),
+            peer_id,
+            &[],
+            &network_config,
+            heartbeat_failure_sender,
+            heartbeat_sender,
+        ).unwrap();
+
+        NetworkEvents::new(
+            swarm,
+            NodeIdentity::new(node_name.to_string(), peer_id, id_keys, 1, umbral_key),
+            command_receiver,
+            network_events_sender,
+            node_event_sender,
+            heartbeat_failure_receiver,
+            Arc::new(RwLock::new(ContainerManager::new(Duration::from_secs(30)))),
+            Arc::new(NearRuntime::new(NearConfig::default()).unwrap()),
+            Arc::new(EvmRuntime::new(EvmConfig::default()).await.unwrap()),
+            network_config,
+            shutdown_receiver,
+        )
+    }
+
+    /// A peer that only speaks the request-response protocol, listening on localhost
+    pub(super) async fn listening_peer(network_config: &NetworkConfig) -> (RequestResponseSwarm, Multiaddr) {
+        let mut peer = libp2p::SwarmBuilder::with_new_identity()
+            .with_tokio()
+            .with_tcp(
+                tcp::Config::default(),
+                noise::Config::new,
+                yamux::Config::default
+            ).unwrap()
+            .with_behaviour(|_| network_config.request_response_behaviour()).unwrap()
+            .with_swarm_config(|c| c.with_idle_connection_timeout(Duration::from_secs(60)))
+            .build();
+
+        peer.listen_on("/ip4/127.0.0.1/tcp/0".parse().unwrap()).unwrap();
+        let addr = loop {
+            if let SwarmEvent::NewListenAddr { address, .. } = peer.select_next_some().await {
+                break address;
+            }
+        };
+        (peer, addr)
+    }
+
+    fn vessel_reverie(vessel_peer_id: PeerId, kfrag_provider: PeerId) -> ReverieMessage {
+        let umbral_key = UmbralKey::new(None);
+        let (capsule, ciphertext) = umbral_key.encrypt_bytes(&b"secrets".to_vec()).unwrap();
+        ReverieMessage {
+            reverie: Reverie::new(
+                "test reverie".to_string(),
+                ReverieType::Memory,
+                1,
+                1,
+                umbral_key.public_key,
+                umbral_key.verifying_public_key,
+                AccessCondition::Umbral(umbral_key.verifying_public_key),
+                capsule,
+                ciphertext,
+            ),
+            source_peer_id: PeerId::random(),
+            target_peer_id: vessel_peer_id,
+            keyfrag_providers: vec![kfrag_provider],
+        }
+    }
+
+    /// Drives the vessel's swarm until the re-broadcast settles
+    async fn drive_until_rebroadcast_settles(vessel: &mut NetworkEvents) -> AfterRebroadcast {
+        tokio::time::timeout(Duration::from_secs(10), async {
+            loop {
+                let swarm_event = vessel.swarm.select_next_some().await;
+                vessel.handle_swarm_event(swarm_event).await.ok();
+                if let Some(then) = vessel.complete_shutdown_rebroadcast() {
+                    break then;
+                }
+            }
+        }).await.expect("re-broadcast did not settle")
+    }
+
+    /// Sets up a vessel holding one reverie, and its kfrag provider. The provider signals
+    /// when the re-broadcast arrives, and holds its response until the test sends one.
+    async fn vessel_with_kfrag_provider() -> (
+        NetworkEvents,
+        ReverieMessage,
+        mpsc::Receiver<()>,
+        mpsc::Sender<FragmentResponseEnum>,
+    ) {
+        let network_config = NetworkConfig::default();
+        let mut vessel = test_network_events(network_config.clone()).await;
+        let (mut kfrag_provider, kfrag_provider_addr) = listening_peer(&network_config).await;
+        let kfrag_provider_peer_id = *kfrag_provider.local_peer_id();
+        vessel.swarm.add_peer_address(kfrag_provider_peer_id, kfrag_provider_addr);
+
+        let reverie_msg = vessel_reverie(vessel.node_id.peer_id, kfrag_provider_peer_id);
+        vessel.peer_manager.insert_reverie(&reverie_msg.reverie.id, reverie_msg.clone());
+
+        let (received_sender, received_receiver) = mpsc::channel(1);
+        let (response_sender, mut response_receiver) = mpsc::channel(1);
+        tokio::spawn(async move {
+            let mut held_channel = None;
+            loop {
+                tokio::select! {
+                    swarm_event = kfrag_provider.select_next_some() => {
+                        if let SwarmEvent::Behaviour(Event::Message {
+                            message: Message::Request { request: FragmentRequestEnum::SaveCiphertextRequest(..), channel, .. }, ..
+                        }) = swarm_event {
+                            held_channel = Some(channel);
+                            received_sender.send(()).await.ok();
+                        }
+                    }
+                    Some(response) = response_receiver.recv() => {
+                        if let Some(channel) = held_channel.take() {
+                            kfrag_provider.behaviour_mut().send_response(channel, response).ok();
+                        }
+                    }
+                }
+            }
+        });
+
+        (vessel, reverie_msg, received_receiver, response_sender)
+    }
+
+    /// Drives the vessel's swarm until its kfrag provider has received the re-broadcast
+    async fn drive_until_rebroadcast_received(vessel: &mut NetworkEvents, received: &mut mpsc::Receiver<()>) {
+        tokio::time::timeout(Duration::from_secs(10), async {
+            loop {
+                tokio::select! {
+                    swarm_event = vessel.swarm.select_next_some() => {
+                        vessel.handle_swarm_event(swarm_event).await.ok();
+                    }
+                    _ = received.recv() => break,
+                }
+            }
+        }).await.expect("kfrag provider never received the re-broadcast")
+    }
+
+    #[tokio::test]
+    async fn vessel_secrets_are_deleted_only_after_rebroadcast_is_acknowledged() {
+        let (mut vessel, reverie_msg, mut received, responses) = vessel_with_kfrag_provider().await;
+        vessel.start_shutdown_rebroadcast(AfterRebroadcast::Restart, Duration::from_secs(10));
+        drive_until_rebroadcast_received(&mut vessel, &mut received).await;
+
+        // kfrag provider has the reverie but hasn't acknowledged it, so nothing is deleted yet
+        assert!(vessel.complete_shutdown_rebroadcast().is_none());
+        assert_eq!(vessel.peer_manager.vessel_reveries(), vec![reverie_msg]);
+
+        responses.send(FragmentResponseEnum::SaveCiphertextResponse).await.unwrap();
+        assert!(matches!(drive_until_rebroadcast_settles(&mut vessel).await, AfterRebroadcast::Restart));
+        assert!(vessel.peer_manager.vessel_reveries().is_empty());
+    }
+
+    #[tokio::test]
+    async fn vessel_secrets_are_kept_when_rebroadcast_is_rejected() {
+        let (mut vessel, reverie_msg, mut received, responses) = vessel_with_kfrag_provider().await;
+        vessel.start_shutdown_rebroadcast(AfterRebroadcast::Restart, Duration::from_secs(10));
+        drive_until_rebroadcast_received(&mut vessel, &mut received).await;
+
+        responses.send(FragmentResponseEnum::SaveCiphertextFailedResponse(
+            SendError("Reverie exceeds max payload size".to_string())
+        )).await.unwrap();
+        assert!(matches!(drive_until_rebroadcast_settles(&mut vessel).await, AfterRebroadcast::Restart));
+        assert_eq!(vessel.peer_manager.vessel_reveries(), vec![reverie_msg]);
+    }
+
+    #[tokio::test]
+    async fn unanswered_cfrag_request_is_retried_then_fails() {
+        let network_config = NetworkConfig {
+            request_timeout: Duration::from_millis(300),
+            cfrag_request_max_retries: 1,
+            ..Default::default()
+        };
+        let mut requester = test_network_events(network_config.clone()).await;
+        let (mut kfrag_provider, kfrag_provider_addr) = listening_peer(&network_config).await;
+        let kfrag_provider_peer_id = *kfrag_provider.local_peer_id();
+        requester.swarm.add_peer_address(kfrag_provider_peer_id, kfrag_provider_addr);
+
+        // Kfrag provider receives each request but never responds, as if it hung mid-request
+        let (received_sender, mut received_receiver) = mpsc::channel(8);
+        tokio::spawn(async move {
+            let mut held_channels = vec![];
+            loop {
+                if let SwarmEvent::Behaviour(Event::Message {
+                    message: Message::Request { request: FragmentRequestEnum::GetFragmentRequest(..), channel, .. }, ..
+                }) = kfrag_provider.select_next_some().await {
+                    held_channels.push(channel);
+                    received_sender.send(()).await.ok();
+                }
+            }
+        });
+
+        let (sender, mut receiver) = oneshot::channel();
+        requester.handle_command(NodeCommand::RequestCapsuleFragment {
+            reverie_id: "reverie_test".to_string(),
+            kfrag_provider_peer_id,
+            access_key: AccessKey::UmbralSignature(vec![]),
+            sender,
+        }).await;
+
+        let result = tokio::time::timeout(Duration::from_secs(10), async {
+            loop {
+                tokio::select! {
+                    swarm_event = requester.swarm.select_next_some() => {
+                        requester.handle_swarm_event(swarm_event).await.ok();
+                    }
+                    result = &mut receiver => break result.unwrap(),
+                }
+            }
+        }).await.expect("pending cfrag request hung instead of failing");
+
+        assert!(result.is_err(), "cfrag request resolved without a response");
+        // the first request, then one retry after it timed out
+        let mut requests_received = 0;
+        while received_receiver.try_recv().is_ok() {
+            requests_received += 1;
+        }
+        assert_eq!(requests_received, 2);
+        assert_eq!(requester.pending.request_fragments.keys().count(), 0);
+    }
+
+    #[tokio::test]
+    async fn denied_inbound_connections_leave_no_peer_info() {
+        use libp2p::swarm::ListenError;
+
+        let network_config = NetworkConfig {
+            max_established_incoming: Some(1),
+            ..Default::default()
+        };
+        let mut node = test_network_events(network_config.clone()).await;
+        node.swarm.listen_on("/ip4/127.0.0.1/tcp/0".parse().unwrap()).unwrap();
+        let listen_addr = loop {
+            if let SwarmEvent::NewListenAddr { address, .. } = node.swarm.select_next_some().await {
+                break address;
+            }
+        };
+
+        let mut dialer_peer_ids = vec![];
+        for _ in 0..2 {
+            let (mut dialer, _) = listening_peer(&network_config).await;
+            dialer_peer_ids.push(*dialer.local_peer_id());
+            dialer.dial(listen_addr.clone()).unwrap();
+            tokio::spawn(async move {
+                loop { dialer.select_next_some().await; }
+            });
+        }
+
+        let mut established = 0;
+        let mut denied = 0;
+        tokio::time::timeout(Duration::from_secs(10), async {
+            while established + denied < 2 {
+                let swarm_event = node.swarm.select_next_some().await;
+                match &swarm_event {
+                    SwarmEvent::ConnectionEstablished { .. } => established += 1,
+                    SwarmEvent::IncomingConnectionError { error: ListenError::Denied { .. }, .. } => denied += 1,
+                    _ => {}
+                }
+                node.handle_swarm_event(swarm_event).await.ok();
+            }
+        }).await.expect("timed out waiting for inbound connections");
+
+        assert_eq!((established, denied), (1, 1));
+        // only the connected peer is tracked, the refused one left nothing behind
+        let connected: Vec<PeerId> = node.swarm.connected_peers().cloned().collect();
+        assert_eq!(connected.len(), 1);
+        let tracked: Vec<PeerId> = node.peer_manager.peer_info.keys().cloned().collect();
+        assert_eq!(tracked, connected);
+        assert!(dialer_peer_ids.contains(&connected[0]));
+    }
+
+    /// Sends a request from `peer` to `node`, driving both swarms until it is answered
+    async fn request_from_peer(
+        node: &mut NetworkEvents,
+        peer: &mut RequestResponseSwarm,
+        request: FragmentRequestEnum,
+    ) -> FragmentResponseEnum {
+        peer.behaviour_mut().send_request(&node.node_id.peer_id, request);
+        tokio::time::timeout(Duration::from_secs(10), async {
+            loop {
+                tokio::select! {
+                    swarm_event = node.swarm.select_next_some() => {
+                        node.handle_swarm_event(swarm_event).await.ok();
+                    }
+                    swarm_event = peer.select_next_some() => {
+                        if let SwarmEvent::Behaviour(Event::Message { message: Message::Response { response, .. }, .. }) = swarm_event {
+                            break response;
+                        }
+                    }
+                }
+            }
+        }).await.expect("request was not answered")
+    }
+
+    #[tokio::test]
+    async fn respawn_complete_is_only_accepted_from_the_next_vessel() {
+        use crate::types::ReverieNameWithNonce;
+
+        let network_config = NetworkConfig::default();
+        let mut kfrag_provider = test_network_events(network_config.clone()).await;
+        kfrag_provider.swarm.listen_on("/ip4/127.0.0.1/tcp/0".parse().unwrap()).unwrap();
+        let listen_addr = loop {
+            if let SwarmEvent::NewListenAddr { address, .. } = kfrag_provider.swarm.select_next_some().await {
+                break address;
+            }
+        };
+        let (mut peer, _) = listening_peer(&network_config).await;
+        peer.add_peer_address(kfrag_provider.node_id.peer_id, listen_addr);
+
+        // The kfrag provider holds a cfrag of the failed vessel's agent, next vessel is some other peer
+        let failed_vessel = PeerId::random();
+        let next_vessel = PeerId::random();
+        let agent_name = ReverieNameWithNonce("auron".to_string(), 0);
+        let reverie_msg = vessel_reverie(failed_vessel, kfrag_provider.node_id.peer_id);
+        let reverie_id = reverie_msg.reverie.id.clone();
+        let agent_vessel = |next_vessel_peer_id: PeerId| AgentVesselInfo {
+            reverie_id: reverie_id.clone(),
+            reverie_type: ReverieType::SovereignAgent(agent_name.clone()),
+            threshold: 1,
+            total_frags: 1,
+            current_vessel_peer_id: failed_vessel,
+            next_vessel_peer_id,
+        };
+        kfrag_provider.peer_manager.insert_kfrag_provider(kfrag_provider.node_id.peer_id, reverie_id.clone(), 0);
+        kfrag_provider.peer_manager.insert_reverie_metadata(&reverie_id, agent_vessel(next_vessel));
+
+        let request = FragmentRequestEnum::MarkRespawnCompleteRequest {
+            prev_reverie_id: reverie_id.clone(),
+            prev_peer_id: failed_vessel,
+            prev_agent_name: agent_name.clone(),
+        };
+        // Any other peer is answered, but the Reverie's state is kept
+        let response = request_from_peer(&mut kfrag_provider, &mut peer, request.clone()).await;
+        assert_eq!(response, FragmentResponseEnum::MarkRespawnCompleteResponse);
+        assert!(kfrag_provider.peer_manager.get_kfrag_providers(&reverie_id).is_some());
+        assert!(kfrag_provider.peer_manager.get_reverie_metadata(&reverie_id).is_some());
+
+        // The recorded next vessel's notice prunes it
+        let peer_id = *peer.local_peer_id();
+        kfrag_provider.peer_manager.insert_reverie_metadata(&reverie_id, agent_vessel(peer_id));
+        let response = request_from_peer(&mut kfrag_provider, &mut peer, request).await;
+        assert_eq!(response, FragmentResponseEnum::MarkRespawnCompleteResponse);
+        assert!(kfrag_provider.peer_manager.get_kfrag_providers(&reverie_id).is_none());
+        assert!(kfrag_provider.peer_manager.get_reverie_metadata(&reverie_id).is_none());
+    }
+
+    #[tokio::test]
+    async fn kfrag_provider_is_the_requesting_peer() {
+        let network_config = NetworkConfig::default();
+        let mut vessel = test_network_events(network_config.clone()).await;
+        vessel.swarm.listen_on("/ip4/127.0.0.1/tcp/0".parse().unwrap()).unwrap();
+        let listen_addr = loop {
+            if let SwarmEvent::NewListenAddr { address, .. } = vessel.swarm.select_next_some().await {
+                break address;
+            }
+        };
+        let (mut peer, _) = listening_peer(&network_config).await;
+        peer.add_peer_address(vessel.node_id.peer_id, listen_addr);
+        let reverie_id: ReverieId = "reverie_1234".to_string();
+
+        // A peer claiming another node holds a fragment is answered, but nothing is recorded
+        let request = FragmentRequestEnum::ProvidingFragmentRequest(reverie_id.clone(), 0, PeerId::random());
+        let response = request_from_peer(&mut vessel, &mut peer, request).await;
+        assert_eq!(response, FragmentResponseEnum::ProvidingFragmentResponse);
+        assert!(vessel.peer_manager.get_kfrag_providers(&reverie_id).is_none());
+
+        let peer_id = *peer.local_peer_id();
+        let request = FragmentRequestEnum::ProvidingFragmentRequest(reverie_id.clone(), 0, peer_id);
+        let response = request_from_peer(&mut vessel, &mut peer, request).await;
+        assert_eq!(response, FragmentResponseEnum::ProvidingFragmentResponse);
+        assert_eq!(
+            vessel.peer_manager.get_kfrag_providers(&reverie_id).unwrap(),
+            &HashSet::from([peer_id])
+        );
+    }
 }
@@ -30,6 +30,17 @@ impl HeartBeatData {
         self.last_heartbeat.elapsed()
     }
 
+    /// Highest block_height the peer can plausibly report now: its last reported height
+    /// plus one block per second since, within `tolerance`. None before the peer's first
+    /// heartbeat, which is checked against this node's own block height instead.
+    pub fn max_plausible_block_height(&self, tolerance: u32) -> Option<u32> {
+        if self.durations.is_empty() {
+            return None
+        }
+        let elapsed_blocks = u32::try_from(self.duration_since_last_heartbeat().as_secs()).unwrap_or(u32::MAX);
+        Some(self.tee_payload.block_height.saturating_add(elapsed_blocks).saturating_add(tolerance))
+    }
+
     pub fn average_time_between_heartbeats(&self) -> Duration {
         if self.durations.is_empty() {
             Duration::from_secs(0)
@@ -1,7 +1,9 @@
 pub mod heartbeat_data;
 pub mod peer_info;
+pub mod reverie_chunks;
 
 use colored::Colorize;
+use color_eyre::{Result, eyre::anyhow};
 use color_eyre::owo_colors::OwoColorize;
 use libp2p::PeerId;
 use serde::{Serialize, Deserialize};
@@ -11,15 +13,21 @@ use tracing::{warn, info};
 
 use crate::{get_node_name, short_peer_id};
 use crate::types::{
+    ConnectedPeer,
+    FragmentNumber,
     ReverieNameWithNonce,
+    NetworkEvent,
     VesselStatus,
     ReverieId,
     ReverieCapsulefrag,
+    ReverieCiphertextChunk,
     ReverieMessage,
     ReverieType,
+    ReverieTypeIndexEntry,
 };
 use crate::behaviour::heartbeat_behaviour::TeeAttestation;
-use peer_info::{PeerInfo, AgentVesselInfo};
+use peer_info::{PeerInfo, AgentVesselInfo, ReputationEvent};
+use reverie_chunks::{ReverieChunkAssembler, STALE_CHUNKS_TIMEOUT};
 
 
 #[derive(Debug, Clone, Hash, Eq, PartialEq)]
@@ -34,9 +42,10 @@ pub(crate) struct TrackReverieFragment {
 pub(crate) struct PeerManager {
     pub(crate) node_name: String,
     pub(crate) peer_id: PeerId,
-    pub(crate) vessel_status: VesselStatus,
+    // Only changed through transition_to
+    vessel_status: VesselStatus,
     // Tracks agent info if node is a vessel
-    pub(crate) vessel_agent: Option<serde_json::Value>,
+    pub(crate) vessel_agent: Option<AgentVesselInfo>,
     // Tracks Vessel Nodes
     pub(crate) peer_info: HashMap<PeerId, PeerInfo>,
     // Tracks which Peers hold which AgentFragments: {agent_name: {frag_num: [PeerId]}}
@@ -51,6 +60,14 @@ pub(crate) struct PeerManager {
     // Tracks which Fragments a Peer holds, so we know which fragments
     // to delete from a peer when a node fails
     pub(crate) peers_to_reverie_frags: HashMap<PeerId, HashSet<TrackReverieFragment>>,
+    // Partially received chunked ciphertexts
+    pub(crate) reverie_chunks: ReverieChunkAssembler,
+    // Failed vessels whose agent was reincarnated elsewhere: {prev_vessel: agent_name}
+    pub(crate) reincarnated_vessels: HashMap<PeerId, ReverieNameWithNonce>,
+    // Number of rejoining vessels detected still holding a reincarnated agent
+    pub(crate) duplicate_vessels_detected: u64,
+    // Reverie type index entries last published to Kademlia: {reverie_type_kind: entries}
+    published_reverie_type_index: HashMap<&'static str, Vec<ReverieTypeIndexEntry>>,
     // average heartbeat window for peers (number of entries to track)
     avg_window: u32
 }
@@ -68,10 +85,34 @@ impl PeerManager {
             reverie_metadata: HashMap::new(),
             reverie: HashMap::new(),
             peers_to_reverie_frags: HashMap::new(),
+            reverie_chunks: ReverieChunkAssembler::new(),
+            reincarnated_vessels: HashMap::new(),
+            duplicate_vessels_detected: 0,
+            published_reverie_type_index: HashMap::new(),
             avg_window: 10,
         }
     }
 
+    pub(crate) fn vessel_status(&self) -> VesselStatus {
+        self.vessel_status
+    }
+
+    /// Moves this node to a new VesselStatus, rejecting transitions not allowed by
+    /// `VesselStatus::can_transition_to`, e.g. a NeverVessel becoming an ActiveVessel.
+    pub(crate) fn transition_to(&mut self, new_status: VesselStatus) -> Result<()> {
+        if !self.vessel_status.can_transition_to(new_status) {
+            return Err(anyhow!(
+                "{} Illegal VesselStatus transition: {:?} -> {:?}",
+                self.node_name, self.vessel_status, new_status
+            ));
+        }
+        if self.vessel_status != new_status {
+            info!("{} VesselStatus: {:?} -> {:?}", self.node_name, self.vessel_status, new_status);
+        }
+        self.vessel_status = new_status;
+        Ok(())
+    }
+
     //////////////////////
     //// self.peer_info
     //////////////////////
@@ -106,14 +147,10 @@ impl PeerManager {
             next_vessel_peer_id,
         } = agent_vessel_info;
 
-        let (agent_name_nonce, is_agent) = match reverie_type {
-            ReverieType::Agent(agent_name_nonce) => (agent_name_nonce.clone(), true),
-            ReverieType::SovereignAgent(agent_name_nonce) => (agent_name_nonce.clone(), true),
-            _ => {
-                info!("Not setting vessel, received non-agent ReverieType: {:?}", reverie_type);
-                return
-            }
-        };
+        if !matches!(reverie_type, ReverieType::Agent(..) | ReverieType::SovereignAgent(..)) {
+            info!("Not setting vessel, received non-agent ReverieType: {:?}", reverie_type);
+            return
+        }
 
         println!("{}", format!("\tCurrent vessel:\t{}", get_node_name(&current_vessel_peer_id).bright_blue()));
         println!("{}", format!("\tNext vessel:\t{}", get_node_name(&next_vessel_peer_id).bright_blue()));
@@ -133,19 +170,36 @@ impl PeerManager {
         }
 
         if current_vessel_peer_id == &self.peer_id {
-            self.vessel_agent = Some(serde_json::json!({
-                "agent_name_nonce": agent_name_nonce.clone().to_string(),
-                "threshold": threshold.clone(),
-                "total_frags": total_frags.clone(),
-                "current_vessel_peer_id": current_vessel_peer_id,
-                "current_vessel_node_name": get_node_name(&current_vessel_peer_id),
-                "next_vessel_peer_id": next_vessel_peer_id,
-                "next_vessel_node_name":  get_node_name(&next_vessel_peer_id),
-            }));
+            self.vessel_agent = Some(agent_vessel_info.clone());
         }
     }
 
-    pub fn update_peer_heartbeat(&mut self, peer_id: PeerId, tee_payload: TeeAttestation) {
+    /// Records a peer's heartbeat. Heartbeats with a block_height implausibly far ahead of
+    /// the peer's previous heartbeat are rejected, so a peer can't inflate its block_height
+    /// to appear perpetually fresh. A peer's first heartbeat must be within
+    /// `block_height_tolerance` of `local_block_height`, so it can't start from an inflated one.
+    pub fn update_peer_heartbeat(
+        &mut self,
+        peer_id: PeerId,
+        tee_payload: TeeAttestation,
+        local_block_height: u32,
+        block_height_tolerance: u32,
+    ) -> Result<()> {
+        let max_block_height = self.peer_info.get(&peer_id)
+            .and_then(|peer_info| peer_info.heartbeat_data.max_plausible_block_height(block_height_tolerance));
+        let plausible_block_heights = match max_block_height {
+            Some(max_block_height) => 0..=max_block_height,
+            None => local_block_height.saturating_sub(block_height_tolerance)
+                ..=local_block_height.saturating_add(block_height_tolerance),
+        };
+        if !plausible_block_heights.contains(&tee_payload.block_height) {
+            return Err(anyhow!(
+                "Heartbeat block_height {} from {} is outside plausible range {:?}",
+                tee_payload.block_height,
+                short_peer_id(&peer_id),
+                plausible_block_heights
+            ))
+        }
         match self.peer_info.get_mut(&peer_id) {
             Some(peer_info) => {
                 peer_info.heartbeat_data.update(tee_payload);
@@ -156,6 +210,100 @@ impl PeerManager {
                 self.peer_info.insert(peer_id, new_peer_info);
             }
         };
+        Ok(())
+    }
+
+    /// Raises or lowers a tracked peer's reputation. Events for peers without PeerInfo,
+    /// e.g. ones that never connected, are ignored rather than tracking a new peer.
+    pub fn update_peer_reputation(&mut self, peer_id: PeerId, event: ReputationEvent) {
+        if let Some(peer_info) = self.peer_info.get_mut(&peer_id) {
+            peer_info.update_reputation(event);
+        }
+    }
+
+    pub fn peer_reputations(&self) -> HashMap<PeerId, f64> {
+        self.peer_info.iter()
+            .map(|(peer_id, peer_info)| (*peer_id, peer_info.reputation))
+            .collect()
+    }
+
+    /// Heartbeat recency and reputation of each connected peer, sorted by node name.
+    /// Vessel statuses are on Kademlia, so are left for the caller to fill in.
+    pub(crate) fn connected_peer_info<'a>(&self, connected_peers: impl Iterator<Item = &'a PeerId>) -> Vec<ConnectedPeer> {
+        let mut peers: Vec<ConnectedPeer> = connected_peers
+            .map(|peer_id| {
+                let peer_info = self.peer_info.get(peer_id);
+                ConnectedPeer {
+                    peer_id: *peer_id,
+                    node_name: get_node_name(peer_id),
+                    vessel_status: None,
+                    last_heartbeat_secs: peer_info
+                        .map(|peer_info| peer_info.heartbeat_data.duration_since_last_heartbeat().as_secs()),
+                    reputation: peer_info
+                        .map(|peer_info| peer_info.reputation)
+                        .unwrap_or(peer_info::DEFAULT_REPUTATION),
+                }
+            })
+            .collect();
+        peers.sort_by(|a, b| a.node_name.cmp(&b.node_name));
+        peers
+    }
+
+    /// Records that a failed vessel's agent is being reincarnated in another vessel
+    pub fn mark_vessel_reincarnated(&mut self, prev_vessel_peer_id: PeerId, agent_name: ReverieNameWithNonce) {
+        self.reincarnated_vessels.insert(prev_vessel_peer_id, agent_name);
+    }
+
+    /// Called on each heartbeat. A heartbeat from a vessel whose agent was already
+    /// reincarnated means two vessels may be running the same agent (split-brain).
+    pub fn detect_duplicate_vessel(&mut self, peer_id: &PeerId) -> Option<NetworkEvent> {
+        let agent_name = self.reincarnated_vessels.remove(peer_id)?;
+        self.duplicate_vessels_detected += 1;
+        Some(NetworkEvent::DuplicateVesselDetected {
+            agent_name,
+            peer_id: *peer_id,
+        })
+    }
+
+    /// Whether this node is the vessel holding the named agent's Reverie
+    pub fn is_vessel_for_agent(&self, agent_name: &ReverieNameWithNonce) -> bool {
+        self.vessel_reveries().iter().any(|reverie_msg| match &reverie_msg.reverie.reverie_type {
+            ReverieType::Agent(name) | ReverieType::SovereignAgent(name) => name == agent_name,
+            _ => false,
+        })
+    }
+
+    /// Whether a peer may tell this vessel to stand down an agent: only the agent's
+    /// recorded next vessel, or a kfrag provider of its Reverie, reincarnates it elsewhere.
+    pub(crate) fn can_stand_down_agent(&self, agent_name: &ReverieNameWithNonce, peer_id: &PeerId) -> bool {
+        let is_agent = |reverie_type: &ReverieType| match reverie_type {
+            ReverieType::Agent(name) | ReverieType::SovereignAgent(name) => name == agent_name,
+            _ => false,
+        };
+        let is_next_vessel = |agent_vessel: &AgentVesselInfo| {
+            is_agent(&agent_vessel.reverie_type) && agent_vessel.next_vessel_peer_id == *peer_id
+        };
+        if self.vessel_agent.as_ref().is_some_and(is_next_vessel) {
+            return true
+        }
+        self.vessel_reveries().iter()
+            .filter(|reverie_msg| is_agent(&reverie_msg.reverie.reverie_type))
+            .any(|reverie_msg| {
+                reverie_msg.keyfrag_providers.contains(peer_id)
+                    || self.reverie_metadata.get(&reverie_msg.reverie.id).is_some_and(is_next_vessel)
+            })
+    }
+
+    /// Whether a peer is the recorded next vessel of a Reverie's agent, the only peer
+    /// that respawns it, so the only one that may announce the respawn complete
+    pub(crate) fn is_next_vessel_for_reverie(&self, reverie_id: &ReverieId, peer_id: &PeerId) -> bool {
+        let is_next_vessel = |agent_vessel: &AgentVesselInfo| {
+            agent_vessel.reverie_id == *reverie_id && agent_vessel.next_vessel_peer_id == *peer_id
+        };
+        self.reverie_metadata.get(reverie_id).is_some_and(is_next_vessel)
+            || self.peer_info.values()
+                .filter_map(|peer_info| peer_info.agent_vessel.as_ref())
+                .any(is_next_vessel)
     }
 
     pub fn is_peer_offline(
@@ -288,10 +436,58 @@ impl PeerManager {
         }
     }
 
+    /// Prunes state left over from a Reverie whose agent was respawned into a new Reverie:
+    /// its kfrag providers, cfrags, metadata and ciphertext, and the failed vessel's peer_info.
+    /// `reincarnated_vessels` is kept, so the failed vessel is still caught if it rejoins.
+    pub(crate) fn prune_respawned_reverie(&mut self, prev_reverie_id: &ReverieId, prev_vessel_peer_id: &PeerId) {
+        self.kfrag_providers.remove(prev_reverie_id);
+        self.peers_to_reverie_frags.retain(|_, reverie_frags| {
+            reverie_frags.retain(|rf| &rf.reverie_id != prev_reverie_id);
+            !reverie_frags.is_empty()
+        });
+        self.cfrags.remove(prev_reverie_id);
+        self.reverie_metadata.remove(prev_reverie_id);
+        self.reverie.remove(prev_reverie_id);
+        self.remove_peer_info(prev_vessel_peer_id);
+    }
+
     pub fn get_kfrag_providers(&self, reverie_id: &ReverieId) -> Option<&HashSet<PeerId>> {
         self.kfrag_providers.get(reverie_id)
     }
 
+    /// Confirmed kfrag providers of a Reverie, grouped by the fragment they hold.
+    /// A fragment has several providers once lost providers are replaced.
+    pub fn get_kfrag_providers_by_frag_num(&self, reverie_id: &ReverieId) -> HashMap<FragmentNumber, HashSet<PeerId>> {
+        let mut providers_by_frag_num: HashMap<FragmentNumber, HashSet<PeerId>> = HashMap::new();
+        for peer_id in self.kfrag_providers.get(reverie_id).into_iter().flatten() {
+            let frag_nums = self.peers_to_reverie_frags.get(peer_id)
+                .into_iter()
+                .flatten()
+                .filter(|rf| &rf.reverie_id == reverie_id)
+                .map(|rf| rf.frag_num);
+            for frag_num in frag_nums {
+                providers_by_frag_num.entry(frag_num).or_default().insert(*peer_id);
+            }
+        }
+        providers_by_frag_num
+    }
+
+    /// Drops the kfrag providers a ReverieMessage claims that have not confirmed holding a
+    /// fragment to this node with a ProvidingFragmentRequest. Only the target vessel receives
+    /// those confirmations, and only it can decrypt the cfrags, so other nodes keep none.
+    pub(crate) fn verify_kfrag_providers(&self, mut reverie_msg: ReverieMessage) -> ReverieMessage {
+        let confirmed_providers = self.kfrag_providers.get(&reverie_msg.reverie.id);
+        reverie_msg.keyfrag_providers.retain(|peer_id| {
+            let confirmed = confirmed_providers.map_or(false, |hset| hset.contains(peer_id));
+            if !confirmed {
+                warn!("{} Ignoring unconfirmed kfrag provider {} for {}",
+                    self.nname(), get_node_name(peer_id), reverie_msg.reverie.id);
+            }
+            confirmed
+        });
+        reverie_msg
+    }
+
     //////////////////////
     //// self.cfrags
     //////////////////////
@@ -300,6 +496,17 @@ impl PeerManager {
         self.cfrags.get(reverie_id)
     }
 
+    /// Cfrag to release to a requester, unless the reverie expired at or before `now`
+    pub(crate) fn get_releasable_cfrags(&self, reverie_id: &ReverieId, now: i64) -> Result<&ReverieCapsulefrag> {
+        match self.get_cfrags(reverie_id) {
+            None => Err(anyhow!("{} No cfrag found for {}", self.nname(), reverie_id)),
+            Some(cfrag) if cfrag.is_expired(now) => {
+                Err(anyhow!("{} Reverie {} has expired, refusing to release cfrag", self.nname(), reverie_id))
+            }
+            Some(cfrag) => Ok(cfrag),
+        }
+    }
+
     pub(crate) fn get_reverie_metadata(&self, reverie_id: &ReverieId) -> Option<&AgentVesselInfo> {
         self.reverie_metadata.get(reverie_id)
     }
@@ -314,6 +521,21 @@ impl PeerManager {
             .insert_entry(cfrag);
     }
 
+    /// Removes cfrags (and their agent metadata) whose reverie expired at or before `now`
+    pub(crate) fn purge_expired_cfrags(&mut self, now: i64) -> Vec<ReverieId> {
+        let expired_reverie_ids = self.cfrags.iter()
+            .filter(|(_, cfrag)| cfrag.is_expired(now))
+            .map(|(reverie_id, _)| reverie_id.clone())
+            .collect::<Vec<ReverieId>>();
+
+        for reverie_id in expired_reverie_ids.iter() {
+            self.cfrags.remove(reverie_id);
+            self.reverie_metadata.remove(reverie_id);
+        }
+
+        expired_reverie_ids
+    }
+
     pub(crate) fn insert_reverie_metadata(&mut self, reverie_id: &ReverieId, agent_metadata: AgentVesselInfo) {
         self.reverie_metadata
             .entry(reverie_id.clone())
@@ -326,6 +548,103 @@ impl PeerManager {
             .insert_entry(reverie_message);
     }
 
+    /// Buffers a ciphertext chunk, returning the full ReverieMessage once all chunks arrived
+    pub(crate) fn insert_reverie_chunk(
+        &mut self,
+        chunk: ReverieCiphertextChunk,
+        max_ciphertext_size: usize
+    ) -> Result<Option<ReverieMessage>> {
+        self.reverie_chunks.insert(chunk, max_ciphertext_size)
+    }
+
+    /// Drops chunked ciphertexts still missing chunks after STALE_CHUNKS_TIMEOUT
+    pub(crate) fn purge_stale_reverie_chunks(&mut self) -> Vec<ReverieId> {
+        self.reverie_chunks.purge_stale(STALE_CHUNKS_TIMEOUT)
+    }
+
+    /// Reveries this node currently holds as the vessel
+    pub(crate) fn vessel_reveries(&self) -> Vec<ReverieMessage> {
+        self.reverie.values()
+            .filter(|reverie_msg| reverie_msg.target_peer_id == self.peer_id)
+            .cloned()
+            .collect()
+    }
+
+    /// Unexpired reveries of a ReverieType kind this node holds as the vessel,
+    /// returned only if they changed since last published to the reverie type index.
+    pub(crate) fn changed_reverie_type_index(
+        &mut self,
+        reverie_type_kind: &'static str,
+        now: i64
+    ) -> Option<Vec<ReverieTypeIndexEntry>> {
+        let mut entries = self.vessel_reveries()
+            .into_iter()
+            .filter(|reverie_msg| reverie_msg.reverie.reverie_type.kind() == reverie_type_kind)
+            .map(|reverie_msg| ReverieTypeIndexEntry {
+                reverie_id: reverie_msg.reverie.id,
+                expires_at: reverie_msg.reverie.expires_at,
+                description: reverie_msg.reverie.description,
+                tags: reverie_msg.reverie.tags,
+            })
+            .filter(|entry| !entry.is_expired(now))
+            .collect::<Vec<ReverieTypeIndexEntry>>();
+        entries.sort_by(|a, b| a.reverie_id.cmp(&b.reverie_id));
+
+        let published = self.published_reverie_type_index.get(reverie_type_kind);
+        if published.map_or(entries.is_empty(), |published| *published == entries) {
+            return None;
+        }
+        self.published_reverie_type_index.insert(reverie_type_kind, entries.clone());
+        Some(entries)
+    }
+
+    /// Deletes reverie ciphertexts and agent info this node holds as the vessel,
+    /// so a reincarnated agent can't run alongside this node's copy.
+    pub(crate) fn delete_vessel_secrets(&mut self) -> Vec<ReverieId> {
+        let peer_id = self.peer_id;
+        let vessel_reverie_ids = self.reverie.iter()
+            .filter(|(_, reverie_msg)| reverie_msg.target_peer_id == peer_id)
+            .map(|(reverie_id, _)| reverie_id.clone())
+            .collect::<Vec<ReverieId>>();
+
+        for reverie_id in vessel_reverie_ids.iter() {
+            self.reverie.remove(reverie_id);
+        }
+        self.vessel_agent = None;
+        if let Err(e) = self.transition_to(VesselStatus::EmptyVessel) {
+            warn!("{}", e);
+        }
+
+        vessel_reverie_ids
+    }
+
+    /// Deletes an agent's reverie ciphertexts and agent info after handing it off to
+    /// another vessel. Other reveries this node holds are kept.
+    pub(crate) fn release_vessel_agent(&mut self, agent_name: &ReverieNameWithNonce) -> Vec<ReverieId> {
+        let is_agent = |reverie_type: &ReverieType| match reverie_type {
+            ReverieType::Agent(name) | ReverieType::SovereignAgent(name) => name == agent_name,
+            _ => false,
+        };
+        let agent_reverie_ids = self.reverie.iter()
+            .filter(|(_, reverie_msg)| is_agent(&reverie_msg.reverie.reverie_type))
+            .map(|(reverie_id, _)| reverie_id.clone())
+            .collect::<Vec<ReverieId>>();
+
+        for reverie_id in agent_reverie_ids.iter() {
+            self.reverie.remove(reverie_id);
+        }
+        if !agent_reverie_ids.is_empty() && self.vessel_reveries().is_empty() {
+            if let Err(e) = self.transition_to(VesselStatus::EmptyVessel) {
+                warn!("{}", e);
+            }
+        }
+        if self.vessel_agent.as_ref().is_some_and(|agent_vessel| is_agent(&agent_vessel.reverie_type)) {
+            self.vessel_agent = None;
+        }
+
+        agent_reverie_ids
+    }
+
     pub(crate) fn held_cfrags_summary(&self) -> Vec<serde_json::Value> {
         self.cfrags.iter().map(|(reverie_id, cfrag)| {
 
@@ -377,3 +696,471 @@ impl PeerManager {
 pub trait Punisher {
     fn excommmunicate_peer(&mut self, peer_id: PeerId);
 }
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{AccessCondition, AccessKey, Reverie};
+    use runtime::reencrypt::UmbralKey;
+
+    fn reverie_message(source_peer_id: PeerId, target_peer_id: PeerId) -> ReverieMessage {
+        let umbral_key = UmbralKey::new(None);
+        let (capsule, ciphertext) = umbral_key.encrypt_bytes(&b"secrets".to_vec()).unwrap();
+        ReverieMessage {
+            reverie: Reverie::new(
+                "test reverie".to_string(),
+                ReverieType::Memory,
+                2,
+                3,
+                umbral_key.public_key,
+                umbral_key.verifying_public_key,
+                AccessCondition::Umbral(umbral_key.public_key),
+                capsule,
+                ciphertext,
+            ),
+            source_peer_id,
+            target_peer_id,
+            keyfrag_providers: vec![],
+        }
+    }
+
+    fn capsule_frag(reverie_id: &ReverieId, expires_at: Option<i64>) -> ReverieCapsulefrag {
+        let umbral_key = UmbralKey::new(None);
+        ReverieCapsulefrag {
+            version: crate::types::REVERIE_WIRE_VERSION,
+            id: reverie_id.clone(),
+            reverie_type: ReverieType::Memory,
+            frag_num: 0,
+            threshold: 2,
+            umbral_capsule_frag: vec![],
+            source_pubkey: umbral_key.public_key,
+            source_verifying_pubkey: umbral_key.verifying_public_key,
+            target_pubkey: umbral_key.public_key,
+            target_verifying_pubkey: umbral_key.verifying_public_key,
+            access_condition: AccessCondition::Umbral(umbral_key.public_key),
+            kfrag_provider_peer_id: PeerId::random(),
+            expires_at,
+        }
+    }
+
+    #[test]
+    fn cfrag_released_for_p256_signed_reverie_id() {
+        use p256::ecdsa::{SigningKey, Signature as P256Signature, signature::Signer as _};
+        use sha3::{Digest, Keccak256};
+
+        let signing_key = SigningKey::from_slice(&[9u8; 32]).unwrap();
+        let pubkey_hex = hex::encode(signing_key.verifying_key().to_encoded_point(true).as_bytes());
+
+        let mut peer_manager = PeerManager::new("test".to_string(), PeerId::random());
+        let reverie_id: ReverieId = "reverie_p256".to_string();
+        let mut cfrag = capsule_frag(&reverie_id, None);
+        cfrag.access_condition = AccessCondition::P256(pubkey_hex);
+        peer_manager.insert_cfrags(&reverie_id, cfrag);
+
+        let signature: P256Signature = signing_key.sign(&Keccak256::digest(reverie_id.as_bytes()));
+        let access_key = AccessKey::P256Signature(signature.to_bytes().to_vec());
+
+        let released = peer_manager.get_releasable_cfrags(&reverie_id, 0).unwrap();
+        assert!(access_key.verify_access(&released.access_condition, &reverie_id));
+    }
+
+    #[test]
+    fn cfrags_released_before_expiry() {
+        let mut peer_manager = PeerManager::new("test".to_string(), PeerId::random());
+        let reverie_id: ReverieId = "reverie_expiring".to_string();
+        peer_manager.insert_cfrags(&reverie_id, capsule_frag(&reverie_id, Some(1_000)));
+
+        assert!(peer_manager.get_releasable_cfrags(&reverie_id, 999).is_ok());
+        assert!(peer_manager.purge_expired_cfrags(999).is_empty());
+        assert!(peer_manager.get_cfrags(&reverie_id).is_some());
+    }
+
+    #[test]
+    fn cfrags_denied_and_swept_after_expiry() {
+        let mut peer_manager = PeerManager::new("test".to_string(), PeerId::random());
+        let expired_id: ReverieId = "reverie_expired".to_string();
+        let no_expiry_id: ReverieId = "reverie_no_expiry".to_string();
+        peer_manager.insert_cfrags(&expired_id, capsule_frag(&expired_id, Some(1_000)));
+        peer_manager.insert_cfrags(&no_expiry_id, capsule_frag(&no_expiry_id, None));
+
+        assert!(peer_manager.get_releasable_cfrags(&expired_id, 1_000).is_err());
+        assert!(peer_manager.get_releasable_cfrags(&no_expiry_id, 1_000).is_ok());
+
+        assert_eq!(peer_manager.purge_expired_cfrags(1_000), vec![expired_id.clone()]);
+        assert!(peer_manager.get_cfrags(&expired_id).is_none());
+        assert!(peer_manager.get_cfrags(&no_expiry_id).is_some());
+    }
+
+    #[test]
+    fn connected_peer_info_lists_only_connected_peers() {
+        let mut peer_manager = PeerManager::new("test".to_string(), PeerId::random());
+        let heartbeating_peer = PeerId::random();
+        let disconnected_peer = PeerId::random();
+        let new_peer = PeerId::random();
+        peer_manager.insert_peer_info(heartbeating_peer);
+        peer_manager.insert_peer_info(disconnected_peer);
+        peer_manager.update_peer_reputation(heartbeating_peer, ReputationEvent::RequestTimeout);
+
+        let connected_peers = peer_manager.connected_peer_info([heartbeating_peer, new_peer].iter());
+        assert_eq!(connected_peers.len(), 2);
+        assert!(connected_peers.iter().all(|peer| peer.peer_id != disconnected_peer));
+
+        let heartbeating = connected_peers.iter().find(|peer| peer.peer_id == heartbeating_peer).unwrap();
+        assert_eq!(heartbeating.last_heartbeat_secs, Some(0));
+        assert!(heartbeating.reputation < peer_info::DEFAULT_REPUTATION);
+        // connected, but no PeerInfo yet
+        let new = connected_peers.iter().find(|peer| peer.peer_id == new_peer).unwrap();
+        assert_eq!(new.last_heartbeat_secs, None);
+        assert_eq!(new.reputation, peer_info::DEFAULT_REPUTATION);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn heartbeat_with_implausible_block_height_is_rejected() {
+        let mut peer_manager = PeerManager::new("test".to_string(), PeerId::random());
+        let peer_id = PeerId::random();
+        let heartbeat = |block_height: u32| TeeAttestation { block_height, ..TeeAttestation::default() };
+
+        // the first heartbeat must be near this node's own block height
+        assert!(peer_manager.update_peer_heartbeat(peer_id, heartbeat(u32::MAX), 95, 10).is_err());
+        assert!(peer_manager.update_peer_heartbeat(peer_id, heartbeat(80), 95, 10).is_err());
+        assert!(!peer_manager.peer_info.contains_key(&peer_id));
+
+        // and sets the peer's baseline
+        peer_manager.update_peer_heartbeat(peer_id, heartbeat(100), 95, 10).unwrap();
+        tokio::time::advance(Duration::from_secs(5)).await;
+        peer_manager.update_peer_heartbeat(peer_id, heartbeat(105), 100, 10).unwrap();
+
+        tokio::time::advance(Duration::from_secs(5)).await;
+        assert!(peer_manager.update_peer_heartbeat(peer_id, heartbeat(u32::MAX), 105, 10).is_err());
+
+        // rejected heartbeats don't update peer_info
+        let heartbeat_data = &peer_manager.peer_info[&peer_id].heartbeat_data;
+        assert_eq!(heartbeat_data.tee_payload.block_height, 105);
+        assert_eq!(heartbeat_data.duration_since_last_heartbeat(), Duration::from_secs(5));
+
+        // within tolerance of the blocks elapsed since the last heartbeat
+        peer_manager.update_peer_heartbeat(peer_id, heartbeat(120), 105, 10).unwrap();
+        assert_eq!(peer_manager.peer_info[&peer_id].heartbeat_data.tee_payload.block_height, 120);
+    }
+
+    #[test]
+    fn negative_events_lower_peer_reputation() {
+        let mut peer_manager = PeerManager::new("test".to_string(), PeerId::random());
+        let reliable_peer = PeerId::random();
+        let faulty_peer = PeerId::random();
+        peer_manager.insert_peer_info(reliable_peer);
+        peer_manager.insert_peer_info(faulty_peer);
+
+        peer_manager.update_peer_reputation(reliable_peer, ReputationEvent::ValidCapsuleFrag);
+        peer_manager.update_peer_reputation(reliable_peer, ReputationEvent::TimelyHeartbeat);
+        peer_manager.update_peer_reputation(faulty_peer, ReputationEvent::RequestTimeout);
+        peer_manager.update_peer_reputation(faulty_peer, ReputationEvent::VerificationFailure);
+
+        let reputations = peer_manager.peer_reputations();
+        assert!(reputations[&reliable_peer] > peer_info::DEFAULT_REPUTATION);
+        assert!(reputations[&faulty_peer] < peer_info::DEFAULT_REPUTATION);
+
+        // scores stay within [0, 1]
+        for _ in 0..10 {
+            peer_manager.update_peer_reputation(faulty_peer, ReputationEvent::HeartbeatFailure);
+        }
+        assert_eq!(peer_manager.peer_reputations()[&faulty_peer], 0.0);
+    }
+
+    #[test]
+    fn reputation_events_for_untracked_peers_are_ignored() {
+        let mut peer_manager = PeerManager::new("test".to_string(), PeerId::random());
+        let never_connected = PeerId::random();
+
+        peer_manager.update_peer_reputation(never_connected, ReputationEvent::VerificationFailure);
+        assert!(!peer_manager.peer_info.contains_key(&never_connected));
+        assert!(peer_manager.peer_reputations().is_empty());
+    }
+
+    #[test]
+    fn rejoining_reincarnated_vessel_is_detected_as_duplicate() {
+        let mut peer_manager = PeerManager::new("test".to_string(), PeerId::random());
+        let failed_vessel = PeerId::random();
+        let agent_name = ReverieNameWithNonce("auron".to_string(), 0);
+
+        // heartbeats before any reincarnation are not duplicates
+        assert!(peer_manager.detect_duplicate_vessel(&failed_vessel).is_none());
+
+        // vessel fails and its agent is reincarnated, then the vessel rejoins
+        peer_manager.mark_vessel_reincarnated(failed_vessel, agent_name.clone());
+        match peer_manager.detect_duplicate_vessel(&failed_vessel) {
+            Some(NetworkEvent::DuplicateVesselDetected { agent_name: name, peer_id }) => {
+                assert_eq!(name, agent_name);
+                assert_eq!(peer_id, failed_vessel);
+            }
+            event => panic!("expected DuplicateVesselDetected, got: {:?}", event),
+        }
+        assert_eq!(peer_manager.duplicate_vessels_detected, 1);
+
+        // fires once per reincarnation, not on every later heartbeat
+        assert!(peer_manager.detect_duplicate_vessel(&failed_vessel).is_none());
+        assert_eq!(peer_manager.duplicate_vessels_detected, 1);
+    }
+
+    #[test]
+    fn vessel_reveries_only_returns_reveries_targeting_this_node() {
+        let local_peer_id = PeerId::random();
+        let other_peer_id = PeerId::random();
+        let mut peer_manager = PeerManager::new("test".to_string(), local_peer_id);
+
+        let held = reverie_message(other_peer_id, local_peer_id);
+        let not_held = reverie_message(local_peer_id, other_peer_id);
+        peer_manager.insert_reverie(&held.reverie.id, held.clone());
+        peer_manager.insert_reverie(&not_held.reverie.id, not_held.clone());
+
+        assert_eq!(peer_manager.vessel_reveries(), vec![held]);
+    }
+
+    #[test]
+    fn delete_vessel_secrets_removes_only_vessel_reveries() {
+        let local_peer_id = PeerId::random();
+        let other_peer_id = PeerId::random();
+        let mut peer_manager = PeerManager::new("test".to_string(), local_peer_id);
+
+        let held = reverie_message(other_peer_id, local_peer_id);
+        let not_held = reverie_message(local_peer_id, other_peer_id);
+        peer_manager.insert_reverie(&held.reverie.id, held.clone());
+        peer_manager.insert_reverie(&not_held.reverie.id, not_held.clone());
+        peer_manager.vessel_agent = Some(AgentVesselInfo {
+            reverie_id: held.reverie.id.clone(),
+            reverie_type: ReverieType::SovereignAgent(ReverieNameWithNonce("auron".to_string(), 0)),
+            threshold: 2,
+            total_frags: 3,
+            current_vessel_peer_id: local_peer_id,
+            next_vessel_peer_id: other_peer_id,
+        });
+        peer_manager.transition_to(VesselStatus::ActiveVessel).unwrap();
+
+        let deleted = peer_manager.delete_vessel_secrets();
+
+        assert_eq!(deleted, vec![held.reverie.id.clone()]);
+        assert!(peer_manager.get_reverie(&held.reverie.id).is_none());
+        assert!(peer_manager.get_reverie(&not_held.reverie.id).is_some());
+        assert!(peer_manager.vessel_agent.is_none());
+        assert_eq!(peer_manager.vessel_status, VesselStatus::EmptyVessel);
+        assert!(peer_manager.vessel_reveries().is_empty());
+    }
+
+    #[test]
+    fn release_vessel_agent_removes_only_the_handed_off_agent() {
+        let local_peer_id = PeerId::random();
+        let successor_peer_id = PeerId::random();
+        let mut peer_manager = PeerManager::new("test".to_string(), local_peer_id);
+        let agent_name = ReverieNameWithNonce("auron".to_string(), 0);
+
+        let mut agent = reverie_message(PeerId::random(), local_peer_id);
+        agent.reverie.reverie_type = ReverieType::SovereignAgent(agent_name.clone());
+        let memory = reverie_message(PeerId::random(), local_peer_id);
+        peer_manager.insert_reverie(&agent.reverie.id, agent.clone());
+        peer_manager.insert_reverie(&memory.reverie.id, memory.clone());
+        peer_manager.vessel_agent = Some(AgentVesselInfo {
+            reverie_id: agent.reverie.id.clone(),
+            reverie_type: agent.reverie.reverie_type.clone(),
+            threshold: 2,
+            total_frags: 3,
+            current_vessel_peer_id: local_peer_id,
+            next_vessel_peer_id: successor_peer_id,
+        });
+        peer_manager.transition_to(VesselStatus::ActiveVessel).unwrap();
+
+        // a different incarnation of the agent is not released
+        let other_incarnation = agent_name.increment_nonce();
+        assert!(peer_manager.release_vessel_agent(&other_incarnation).is_empty());
+        assert!(peer_manager.vessel_agent.is_some());
+
+        assert_eq!(peer_manager.release_vessel_agent(&agent_name), vec![agent.reverie.id.clone()]);
+        assert!(peer_manager.get_reverie(&agent.reverie.id).is_none());
+        assert!(peer_manager.vessel_agent.is_none());
+        // still the vessel for its other reveries
+        assert_eq!(peer_manager.vessel_reveries(), vec![memory]);
+        assert_eq!(peer_manager.vessel_status, VesselStatus::ActiveVessel);
+    }
+
+    #[test]
+    fn only_next_vessel_and_kfrag_providers_can_stand_down_an_agent() {
+        let local_peer_id = PeerId::random();
+        let next_vessel = PeerId::random();
+        let kfrag_provider = PeerId::random();
+        let stranger = PeerId::random();
+        let mut peer_manager = PeerManager::new("test".to_string(), local_peer_id);
+        let agent_name = ReverieNameWithNonce("auron".to_string(), 0);
+
+        let mut agent = reverie_message(PeerId::random(), local_peer_id);
+        agent.reverie.reverie_type = ReverieType::SovereignAgent(agent_name.clone());
+        agent.keyfrag_providers = vec![kfrag_provider];
+        peer_manager.insert_reverie(&agent.reverie.id, agent.clone());
+        peer_manager.vessel_agent = Some(AgentVesselInfo {
+            reverie_id: agent.reverie.id.clone(),
+            reverie_type: agent.reverie.reverie_type.clone(),
+            threshold: 2,
+            total_frags: 3,
+            current_vessel_peer_id: local_peer_id,
+            next_vessel_peer_id: next_vessel,
+        });
+
+        assert!(peer_manager.can_stand_down_agent(&agent_name, &next_vessel));
+        assert!(peer_manager.can_stand_down_agent(&agent_name, &kfrag_provider));
+        assert!(!peer_manager.can_stand_down_agent(&agent_name, &stranger));
+        // knowing the agent's name isn't enough to stand down another agent
+        assert!(!peer_manager.can_stand_down_agent(&agent_name.increment_nonce(), &next_vessel));
+    }
+
+    #[test]
+    fn reverie_type_index_changes_on_save_expiry_and_delete() {
+        let local_peer_id = PeerId::random();
+        let mut peer_manager = PeerManager::new("test".to_string(), local_peer_id);
+        let now = 1_000;
+        assert_eq!(peer_manager.changed_reverie_type_index("Memory", now), None);
+
+        let mut held = reverie_message(PeerId::random(), local_peer_id);
+        held.reverie.expires_at = Some(now + 10);
+        let not_held = reverie_message(local_peer_id, PeerId::random());
+        peer_manager.insert_reverie(&held.reverie.id, held.clone());
+        peer_manager.insert_reverie(&not_held.reverie.id, not_held);
+
+        let entries = peer_manager.changed_reverie_type_index("Memory", now).unwrap();
+        assert_eq!(entries, vec![ReverieTypeIndexEntry {
+            reverie_id: held.reverie.id.clone(),
+            expires_at: Some(now + 10),
+            description: held.reverie.description.clone(),
+            tags: vec![],
+        }]);
+        // Unchanged index isn't republished, other kinds are unaffected
+        assert_eq!(peer_manager.changed_reverie_type_index("Memory", now), None);
+        assert_eq!(peer_manager.changed_reverie_type_index("Tools", now), None);
+
+        // Expired reveries are withdrawn from the index
+        assert_eq!(peer_manager.changed_reverie_type_index("Memory", now + 10), Some(vec![]));
+
+        peer_manager.insert_reverie(&held.reverie.id, ReverieMessage {
+            reverie: Reverie { expires_at: None, ..held.reverie.clone() },
+            ..held.clone()
+        });
+        assert_eq!(peer_manager.changed_reverie_type_index("Memory", now).unwrap().len(), 1);
+        peer_manager.delete_vessel_secrets();
+        assert_eq!(peer_manager.changed_reverie_type_index("Memory", now), Some(vec![]));
+    }
+
+    #[test]
+    fn vessel_status_transitions_empty_to_active_and_back() {
+        let mut peer_manager = PeerManager::new("test".to_string(), PeerId::random());
+        assert_eq!(peer_manager.vessel_status(), VesselStatus::EmptyVessel);
+
+        peer_manager.transition_to(VesselStatus::ActiveVessel).unwrap();
+        assert_eq!(peer_manager.vessel_status(), VesselStatus::ActiveVessel);
+        // Hosting another agent keeps the node active
+        peer_manager.transition_to(VesselStatus::ActiveVessel).unwrap();
+
+        peer_manager.transition_to(VesselStatus::EmptyVessel).unwrap();
+        assert_eq!(peer_manager.vessel_status(), VesselStatus::EmptyVessel);
+    }
+
+    #[test]
+    fn vessel_status_rejects_illegal_transitions() {
+        let mut peer_manager = PeerManager::new("test".to_string(), PeerId::random());
+        peer_manager.transition_to(VesselStatus::ActiveVessel).unwrap();
+
+        // An active vessel must empty before retiring
+        assert!(peer_manager.transition_to(VesselStatus::NeverVessel).is_err());
+        assert_eq!(peer_manager.vessel_status(), VesselStatus::ActiveVessel);
+
+        peer_manager.transition_to(VesselStatus::EmptyVessel).unwrap();
+        peer_manager.transition_to(VesselStatus::NeverVessel).unwrap();
+        // A NeverVessel never hosts agents
+        assert!(peer_manager.transition_to(VesselStatus::ActiveVessel).is_err());
+        assert!(peer_manager.transition_to(VesselStatus::EmptyVessel).is_err());
+        assert_eq!(peer_manager.vessel_status(), VesselStatus::NeverVessel);
+    }
+
+    #[test]
+    fn respawned_reverie_state_is_pruned() {
+        let mut peer_manager = PeerManager::new("test".to_string(), PeerId::random());
+        let dead_vessel = PeerId::random();
+        let kfrag_provider = PeerId::random();
+        let prev_reverie_id: ReverieId = "reverie_prev".to_string();
+        let other_reverie_id: ReverieId = "reverie_other".to_string();
+
+        peer_manager.insert_peer_info(dead_vessel);
+        peer_manager.insert_peer_info(kfrag_provider);
+        peer_manager.insert_kfrag_provider(kfrag_provider, prev_reverie_id.clone(), 0);
+        peer_manager.insert_kfrag_provider(kfrag_provider, other_reverie_id.clone(), 1);
+        peer_manager.insert_cfrags(&prev_reverie_id, capsule_frag(&prev_reverie_id, None));
+        peer_manager.insert_cfrags(&other_reverie_id, capsule_frag(&other_reverie_id, None));
+        peer_manager.mark_vessel_reincarnated(dead_vessel, ReverieNameWithNonce("auron".to_string(), 0));
+
+        peer_manager.prune_respawned_reverie(&prev_reverie_id, &dead_vessel);
+
+        assert!(peer_manager.get_kfrag_providers(&prev_reverie_id).is_none());
+        assert!(peer_manager.get_cfrags(&prev_reverie_id).is_none());
+        assert!(!peer_manager.peer_info.contains_key(&dead_vessel));
+        assert!(peer_manager.peers_to_reverie_frags[&kfrag_provider]
+            .iter()
+            .all(|rf| rf.reverie_id != prev_reverie_id));
+
+        // other Reveries and their providers are untouched
+        assert!(peer_manager.get_kfrag_providers(&other_reverie_id).unwrap().contains(&kfrag_provider));
+        assert!(peer_manager.get_cfrags(&other_reverie_id).is_some());
+        assert!(peer_manager.peer_info.contains_key(&kfrag_provider));
+        // the dead vessel is still caught as a duplicate if it rejoins
+        assert!(peer_manager.detect_duplicate_vessel(&dead_vessel).is_some());
+    }
+
+    #[test]
+    fn bogus_kfrag_providers_do_not_pollute_provider_set() {
+        let vessel = PeerId::random();
+        let mut peer_manager = PeerManager::new("test".to_string(), vessel);
+        let confirmed_provider = PeerId::random();
+        let bogus_provider = PeerId::random();
+
+        let mut reverie_msg = reverie_message(PeerId::random(), vessel);
+        reverie_msg.keyfrag_providers = vec![confirmed_provider, bogus_provider];
+        let reverie_id = reverie_msg.reverie.id.clone();
+
+        peer_manager.insert_reverie(&reverie_id, reverie_msg.clone());
+        peer_manager.insert_kfrag_provider(confirmed_provider, reverie_id.clone(), 0);
+
+        // claimed providers are never recorded as kfrag providers
+        let providers = peer_manager.get_kfrag_providers(&reverie_id).unwrap();
+        assert!(!providers.contains(&bogus_provider));
+        assert!(!peer_manager.peers_to_reverie_frags.contains_key(&bogus_provider));
+
+        // and are dropped before requesting cfrags
+        let verified = peer_manager.verify_kfrag_providers(reverie_msg.clone());
+        assert_eq!(verified.keyfrag_providers, vec![confirmed_provider]);
+
+        // claims in Reveries for other vessels are unconfirmed here, so are dropped too
+        let other_msg = ReverieMessage { target_peer_id: PeerId::random(), ..reverie_msg.clone() };
+        let unchecked = peer_manager.verify_kfrag_providers(other_msg);
+        assert_eq!(unchecked.keyfrag_providers, vec![confirmed_provider]);
+        let mut unknown_msg = reverie_message(PeerId::random(), PeerId::random());
+        unknown_msg.keyfrag_providers = vec![bogus_provider];
+        let unknown_msg = peer_manager.verify_kfrag_providers(unknown_msg);
+        assert!(unknown_msg.keyfrag_providers.is_empty());
+    }
+
+    #[test]
+    fn kfrag_providers_are_grouped_by_frag_num() {
+        let mut peer_manager = PeerManager::new("test".to_string(), PeerId::random());
+        let reverie_id: ReverieId = "reverie_1234".to_string();
+        let other_reverie_id: ReverieId = "reverie_other".to_string();
+        let (provider_0, provider_1, replacement_1) = (PeerId::random(), PeerId::random(), PeerId::random());
+
+        peer_manager.insert_kfrag_provider(provider_0, reverie_id.clone(), 0);
+        peer_manager.insert_kfrag_provider(provider_1, reverie_id.clone(), 1);
+        peer_manager.insert_kfrag_provider(replacement_1, reverie_id.clone(), 1);
+        // fragments of other Reveries held by the same provider aren't mixed in
+        peer_manager.insert_kfrag_provider(provider_0, other_reverie_id.clone(), 2);
+
+        let providers_by_frag_num = peer_manager.get_kfrag_providers_by_frag_num(&reverie_id);
+        assert_eq!(providers_by_frag_num.len(), 2);
+        assert_eq!(providers_by_frag_num[&0], HashSet::from([provider_0]));
+        assert_eq!(providers_by_frag_num[&1], HashSet::from([provider_1, replacement_1]));
+        assert!(peer_manager.get_kfrag_providers_by_frag_num(&"reverie_unknown".to_string()).is_empty());
+    }
+}
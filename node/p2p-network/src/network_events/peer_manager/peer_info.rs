@@ -13,6 +13,8 @@ pub(crate) struct PeerInfo {
     pub heartbeat_data: heartbeat_data::HeartBeatData,
     pub agent_vessel: Option<AgentVesselInfo>,
     pub client_version: Option<String>,
+    // 0.0 (unreliable) to 1.0 (reliable), kept in memory for the process lifetime
+    pub reputation: f64,
 }
 
 impl PeerInfo {
@@ -22,6 +24,49 @@ impl PeerInfo {
             heartbeat_data: heartbeat_data::HeartBeatData::new(heartbeat_avg_window),
             agent_vessel: None,
             client_version: None,
+            reputation: DEFAULT_REPUTATION,
+        }
+    }
+
+    pub fn update_reputation(&mut self, event: ReputationEvent) {
+        self.reputation = (self.reputation + event.score_delta()).clamp(0.0, 1.0);
+    }
+}
+
+/// Reputation of peers with no recorded events
+pub const DEFAULT_REPUTATION: f64 = 0.5;
+
+/// Peer behaviour that raises or lowers its reputation
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ReputationEvent {
+    /// Sent a cfrag that verified against the capsule
+    ValidCapsuleFrag,
+    /// Sent a heartbeat before the respawn deadline
+    TimelyHeartbeat,
+    /// Timed out or failed to respond to a request
+    RequestTimeout,
+    /// Sent a cfrag or heartbeat attestation that failed to deserialize or verify
+    VerificationFailure,
+    /// Missed heartbeats past the respawn deadline
+    HeartbeatFailure,
+    /// Sent a heartbeat with a block_height implausibly far from its last one, or from ours
+    ImplausibleHeartbeat,
+    /// Sent a heartbeat with an attestation over the max size
+    OversizedHeartbeat,
+}
+
+impl ReputationEvent {
+    /// Failures cost more than successes earn, so a few bad events
+    /// outweigh a long run of heartbeats
+    pub fn score_delta(&self) -> f64 {
+        match self {
+            ReputationEvent::ValidCapsuleFrag => 0.05,
+            ReputationEvent::TimelyHeartbeat => 0.01,
+            ReputationEvent::RequestTimeout => -0.1,
+            ReputationEvent::VerificationFailure => -0.25,
+            ReputationEvent::HeartbeatFailure => -0.2,
+            ReputationEvent::ImplausibleHeartbeat => -0.25,
+            ReputationEvent::OversizedHeartbeat => -0.25,
         }
     }
 }
@@ -0,0 +1,250 @@
+use color_eyre::{Result, eyre::anyhow};
+use std::collections::{BTreeMap, HashMap};
+use std::time::{Duration, Instant};
+
+use crate::types::{
+    ReverieCiphertextChunk,
+    ReverieId,
+    ReverieMessage,
+};
+
+/// Partially received ciphertexts are dropped after this long without a new chunk
+pub const STALE_CHUNKS_TIMEOUT: Duration = Duration::from_secs(120);
+
+#[derive(Debug)]
+struct PartialCiphertext {
+    reverie_msg: ReverieMessage,
+    total: usize,
+    chunks: BTreeMap<usize, Vec<u8>>,
+    received_bytes: usize,
+    last_updated: Instant,
+}
+
+/// Reassembles Reverie ciphertexts sent as chunks, which may arrive out of order
+#[derive(Debug, Default)]
+pub(crate) struct ReverieChunkAssembler {
+    partial: HashMap<ReverieId, PartialCiphertext>,
+}
+
+impl ReverieChunkAssembler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a chunk, returning the ReverieMessage with its full ciphertext
+    /// once every chunk has arrived. Duplicate chunks are ignored, and chunks
+    /// whose header differs from the first chunk's are rejected.
+    pub fn insert(
+        &mut self,
+        chunk: ReverieCiphertextChunk,
+        max_ciphertext_size: usize
+    ) -> Result<Option<ReverieMessage>> {
+
+        let ReverieCiphertextChunk { reverie_msg, index, total, bytes } = chunk;
+        let reverie_id = reverie_msg.reverie.id.clone();
+
+        if index >= total {
+            return Err(anyhow!("Chunk {}/{} out of range for {}", index, total, reverie_id));
+        }
+
+        if let Some(partial) = self.partial.get(&reverie_id) {
+            if partial.total != total {
+                let expected_total = partial.total;
+                self.partial.remove(&reverie_id);
+                return Err(anyhow!("Chunk total changed from {} to {} for {}", expected_total, total, reverie_id));
+            }
+            // every chunk carries the same header, so a chunk can't swap in another
+            // Reverie's metadata, vessel or providers under the first chunk's ciphertext
+            if partial.reverie_msg != reverie_msg {
+                return Err(anyhow!("Chunk {}/{} header differs from the first chunk's for {}", index, total, reverie_id));
+            }
+        }
+
+        let partial = self.partial
+            .entry(reverie_id.clone())
+            .or_insert_with(|| PartialCiphertext {
+                reverie_msg,
+                total,
+                chunks: BTreeMap::new(),
+                received_bytes: 0,
+                last_updated: Instant::now(),
+            });
+        if partial.chunks.contains_key(&index) {
+            return Ok(None);
+        }
+
+        partial.received_bytes += bytes.len();
+        if partial.received_bytes > max_ciphertext_size {
+            self.partial.remove(&reverie_id);
+            return Err(anyhow!("Chunked ciphertext for {} exceeds the max size of {} bytes", reverie_id, max_ciphertext_size));
+        }
+
+        partial.chunks.insert(index, bytes);
+        partial.last_updated = Instant::now();
+
+        if partial.chunks.len() < partial.total {
+            return Ok(None);
+        }
+
+        let PartialCiphertext { mut reverie_msg, chunks, .. } = self.partial
+            .remove(&reverie_id)
+            .expect("partial ciphertext to exist");
+
+        // BTreeMap iterates chunks in index order
+        reverie_msg.reverie.umbral_ciphertext = chunks.into_values()
+            .flatten()
+            .collect::<Vec<u8>>()
+            .into_boxed_slice();
+
+        Ok(Some(reverie_msg))
+    }
+
+    /// Chunk indexes not yet received for a partially received ciphertext
+    pub fn missing_chunks(&self, reverie_id: &ReverieId) -> Option<Vec<usize>> {
+        self.partial.get(reverie_id).map(|partial| {
+            (0..partial.total)
+                .filter(|index| !partial.chunks.contains_key(index))
+                .collect()
+        })
+    }
+
+    /// Drops partial ciphertexts that received no chunks for `max_age`,
+    /// so missing chunks don't hold memory indefinitely
+    pub fn purge_stale(&mut self, max_age: Duration) -> Vec<ReverieId> {
+        let stale_reverie_ids = self.partial.iter()
+            .filter(|(_, partial)| partial.last_updated.elapsed() >= max_age)
+            .map(|(reverie_id, _)| reverie_id.clone())
+            .collect::<Vec<ReverieId>>();
+
+        for reverie_id in stale_reverie_ids.iter() {
+            self.partial.remove(reverie_id);
+        }
+
+        stale_reverie_ids
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{
+        split_reverie_ciphertext,
+        AccessCondition,
+        Reverie,
+        ReverieType,
+        CIPHERTEXT_CHUNK_SIZE,
+    };
+    use libp2p::PeerId;
+    use rand::seq::SliceRandom;
+    use runtime::reencrypt::UmbralKey;
+
+    const MAX_TEST_CIPHERTEXT_SIZE: usize = 8 * 1024 * 1024;
+
+    fn reverie_message(umbral_key: &UmbralKey, plaintext: &Vec<u8>) -> ReverieMessage {
+        let (capsule, ciphertext) = umbral_key.encrypt_bytes(plaintext).unwrap();
+        ReverieMessage {
+            reverie: Reverie::new(
+                "large memory".to_string(),
+                ReverieType::Memory,
+                2,
+                3,
+                umbral_key.public_key,
+                umbral_key.verifying_public_key,
+                AccessCondition::Umbral(umbral_key.public_key),
+                capsule,
+                ciphertext,
+            ),
+            source_peer_id: PeerId::random(),
+            target_peer_id: PeerId::random(),
+            keyfrag_providers: vec![],
+        }
+    }
+
+    #[test]
+    fn multi_megabyte_ciphertext_reassembles_out_of_order_and_decrypts() {
+        let umbral_key = UmbralKey::new(None);
+        let plaintext = (0..3 * 1024 * 1024).map(|i| (i % 251) as u8).collect::<Vec<u8>>();
+        let reverie_msg = reverie_message(&umbral_key, &plaintext);
+
+        let mut chunks = split_reverie_ciphertext(&reverie_msg, CIPHERTEXT_CHUNK_SIZE);
+        assert!(chunks.len() > 10);
+        chunks.shuffle(&mut rand::thread_rng());
+
+        let mut assembler = ReverieChunkAssembler::new();
+        let (last_chunk, chunks) = chunks.split_last().unwrap();
+        for chunk in chunks {
+            let assembled = assembler.insert(chunk.clone(), MAX_TEST_CIPHERTEXT_SIZE).unwrap();
+            assert!(assembled.is_none());
+        }
+        // duplicates don't complete or corrupt the ciphertext
+        assert!(assembler.insert(chunks[0].clone(), MAX_TEST_CIPHERTEXT_SIZE).unwrap().is_none());
+
+        let assembled = assembler.insert(last_chunk.clone(), MAX_TEST_CIPHERTEXT_SIZE)
+            .unwrap()
+            .expect("all chunks received");
+
+        assert_eq!(assembled, reverie_msg);
+        let capsule = assembled.reverie.encode_capsule().unwrap();
+        let decrypted = umbral_key.decrypt_original(&capsule, &assembled.reverie.umbral_ciphertext).unwrap();
+        assert_eq!(decrypted.to_vec(), plaintext);
+    }
+
+    #[test]
+    fn missing_chunks_are_reported_and_purged() {
+        let umbral_key = UmbralKey::new(None);
+        let reverie_msg = reverie_message(&umbral_key, &vec![7u8; 10_000]);
+        let chunks = split_reverie_ciphertext(&reverie_msg, 1_000);
+
+        let mut assembler = ReverieChunkAssembler::new();
+        for chunk in chunks.iter().filter(|c| c.index != 3) {
+            assert!(assembler.insert(chunk.clone(), MAX_TEST_CIPHERTEXT_SIZE).unwrap().is_none());
+        }
+        assert_eq!(assembler.missing_chunks(&reverie_msg.reverie.id), Some(vec![3]));
+
+        assert!(assembler.purge_stale(STALE_CHUNKS_TIMEOUT).is_empty());
+        assert_eq!(assembler.purge_stale(Duration::ZERO), vec![reverie_msg.reverie.id.clone()]);
+        assert_eq!(assembler.missing_chunks(&reverie_msg.reverie.id), None);
+    }
+
+    #[test]
+    fn rejects_oversized_and_invalid_chunks() {
+        let umbral_key = UmbralKey::new(None);
+        let reverie_msg = reverie_message(&umbral_key, &vec![7u8; 10_000]);
+        let chunks = split_reverie_ciphertext(&reverie_msg, 1_000);
+
+        let mut assembler = ReverieChunkAssembler::new();
+        let mut out_of_range = chunks[0].clone();
+        out_of_range.index = out_of_range.total;
+        assert!(assembler.insert(out_of_range, MAX_TEST_CIPHERTEXT_SIZE).is_err());
+
+        let result = chunks.into_iter()
+            .map(|chunk| assembler.insert(chunk, 5_000))
+            .find(|r| r.is_err());
+        assert!(result.unwrap().unwrap_err().to_string().contains("exceeds the max size"));
+        assert_eq!(assembler.missing_chunks(&reverie_msg.reverie.id), None);
+    }
+
+    #[test]
+    fn rejects_chunks_with_a_different_header() {
+        let umbral_key = UmbralKey::new(None);
+        let reverie_msg = reverie_message(&umbral_key, &vec![7u8; 10_000]);
+        let chunks = split_reverie_ciphertext(&reverie_msg, 1_000);
+
+        let mut assembler = ReverieChunkAssembler::new();
+        assert!(assembler.insert(chunks[0].clone(), MAX_TEST_CIPHERTEXT_SIZE).unwrap().is_none());
+
+        let mut forged = chunks[1].clone();
+        forged.reverie_msg.target_peer_id = PeerId::random();
+        let err = assembler.insert(forged, MAX_TEST_CIPHERTEXT_SIZE).unwrap_err();
+        assert!(err.to_string().contains("header differs"));
+
+        // the forged chunk isn't kept, and the genuine chunks still reassemble
+        let assembled = chunks.into_iter()
+            .skip(1)
+            .filter_map(|chunk| assembler.insert(chunk, MAX_TEST_CIPHERTEXT_SIZE).unwrap())
+            .next()
+            .expect("all chunks received");
+        assert_eq!(assembled, reverie_msg);
+    }
+}
@@ -0,0 +1,171 @@
+use std::collections::{BTreeMap, HashMap};
+use std::hash::Hash;
+use color_eyre::eyre::anyhow;
+use tokio::time::{Duration, Instant};
+
+use crate::SendError;
+use crate::types::PendingRequestStats;
+use super::PendingRequests;
+
+/// Pending requests keyed by query or request id, with the time each was made,
+/// so entries whose response never arrives can be reported and swept.
+pub(super) struct PendingMap<K, V> {
+    entries: HashMap<K, (Instant, V)>,
+}
+
+impl<K, V> Default for PendingMap<K, V> {
+    fn default() -> Self {
+        Self { entries: HashMap::new() }
+    }
+}
+
+impl<K: Eq + Hash, V> PendingMap<K, V> {
+    pub(super) fn insert(&mut self, key: K, value: V) -> Option<V> {
+        self.entries.insert(key, (Instant::now(), value)).map(|(_, value)| value)
+    }
+
+    pub(super) fn get_mut(&mut self, key: &K) -> Option<&mut V> {
+        self.entries.get_mut(key).map(|(_, value)| value)
+    }
+
+    pub(super) fn remove(&mut self, key: &K) -> Option<V> {
+        self.entries.remove(key).map(|(_, value)| value)
+    }
+
+    pub(super) fn contains_key(&self, key: &K) -> bool {
+        self.entries.contains_key(key)
+    }
+
+    pub(super) fn keys(&self) -> impl Iterator<Item = &K> {
+        self.entries.keys()
+    }
+
+    pub(super) fn stats(&self, now: Instant) -> PendingRequestStats {
+        PendingRequestStats {
+            count: self.entries.len(),
+            oldest_age_secs: self.entries.values()
+                .map(|(made_at, _)| now.saturating_duration_since(*made_at).as_secs())
+                .max(),
+        }
+    }
+
+    /// Removes and returns entries made more than `max_age` before `now`
+    pub(super) fn remove_older_than(&mut self, max_age: Duration, now: Instant) -> Vec<V> {
+        let (stale, fresh): (HashMap<K, (Instant, V)>, HashMap<K, (Instant, V)>) = std::mem::take(&mut self.entries)
+            .into_iter()
+            .partition(|(_, (made_at, _))| now.saturating_duration_since(*made_at) > max_age);
+        self.entries = fresh;
+        stale.into_values().map(|(_, value)| value).collect()
+    }
+}
+
+impl PendingRequests {
+    /// Count and oldest age of each kind of pending request
+    pub(super) fn stats(&self, now: Instant) -> BTreeMap<String, PendingRequestStats> {
+        BTreeMap::from([
+            ("get_providers".to_string(), self.get_providers.stats(now)),
+            ("get_reverie_type_entries".to_string(), self.get_reverie_type_entries.stats(now)),
+            ("get_node_vessels".to_string(), self.get_node_vessels.stats(now)),
+            ("get_reverie_agent_name".to_string(), self.get_reverie_agent_name.stats(now)),
+            ("get_reverie_peer_id".to_string(), self.get_reverie_peer_id.stats(now)),
+            ("get_reverie_from_network".to_string(), self.get_reverie_from_network.stats(now)),
+            ("get_reverie_holder_records".to_string(), self.get_reverie_holder_records.stats(now)),
+            ("request_fragments".to_string(), self.request_fragments.stats(now)),
+            ("request_reveries".to_string(), self.request_reveries.stats(now)),
+            ("vessel_handoffs".to_string(), self.vessel_handoffs.stats(now)),
+            ("respawns".to_string(), self.respawns.stats(now)),
+            ("respawn_votes".to_string(), self.respawn_votes.stats(now)),
+            ("respawn_vote_requests".to_string(), self.respawn_vote_requests.stats(now)),
+        ])
+    }
+
+    /// Resolves requests older than `max_age` as failed or empty, so callers stop waiting on
+    /// responses that never arrived. Respawns are left alone, they end with the respawn itself,
+    /// and respawn votes expire after `respawn_vote_timeout`.
+    /// Returns the number of requests swept.
+    pub(super) fn sweep_stale(&mut self, max_age: Duration, now: Instant) -> usize {
+        let mut swept = 0;
+        // Partial results are still useful, send providers found so far
+        for pending in self.get_providers.remove_older_than(max_age, now) {
+            pending.sender.send(pending.providers).ok();
+            swept += 1;
+        }
+        for sender in self.get_reverie_type_entries.remove_older_than(max_age, now) {
+            sender.send(vec![]).ok();
+            swept += 1;
+        }
+        // Dropping the sender ends the receiver's stream
+        swept += self.get_node_vessels.remove_older_than(max_age, now).len();
+        for sender in self.get_reverie_agent_name.remove_older_than(max_age, now) {
+            sender.send(None).ok();
+            swept += 1;
+        }
+        for sender in self.get_reverie_peer_id.remove_older_than(max_age, now) {
+            sender.send(None).ok();
+            swept += 1;
+        }
+        for sender in self.get_reverie_from_network.remove_older_than(max_age, now) {
+            sender.send(Err(anyhow!("Timed out getting reverie from the network"))).ok();
+            swept += 1;
+        }
+        // Dropping the sender tells the NodeClient no record was found
+        swept += self.get_reverie_holder_records.remove_older_than(max_age, now).len();
+        for pending in self.request_fragments.remove_older_than(max_age, now) {
+            pending.sender.send(Err(SendError(format!("Timed out requesting cfrag for {}", pending.reverie_id)))).ok();
+            swept += 1;
+        }
+        for sender in self.request_reveries.remove_older_than(max_age, now) {
+            sender.send(Err(SendError("Timed out requesting reverie from holder".to_string()))).ok();
+            swept += 1;
+        }
+        for sender in self.vessel_handoffs.remove_older_than(max_age, now) {
+            sender.send(Err(SendError("Timed out requesting vessel handoff".to_string()))).ok();
+            swept += 1;
+        }
+        // The vote itself expires after `respawn_vote_timeout`
+        swept += self.respawn_vote_requests.remove_older_than(max_age, now).len();
+        swept
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+    use libp2p::{kad, PeerId};
+    use tokio::sync::oneshot;
+    use super::super::PendingProviders;
+
+    #[tokio::test]
+    async fn outstanding_provider_query_is_counted_until_swept() {
+        let mut pending = PendingRequests::new();
+        let now = Instant::now();
+        assert_eq!(pending.stats(now)["get_providers"], PendingRequestStats::default());
+
+        let local_peer_id = PeerId::random();
+        let query_id = kad::Behaviour::new(local_peer_id, kad::store::MemoryStore::new(local_peer_id))
+            .get_providers(kad::RecordKey::new(&"reverie_test"));
+        let (sender, receiver) = oneshot::channel();
+        let provider = PeerId::random();
+        pending.get_providers.insert(query_id, PendingProviders {
+            sender,
+            providers: HashSet::from([provider]),
+        });
+
+        let later = now + Duration::from_secs(30);
+        assert_eq!(pending.stats(later)["get_providers"], PendingRequestStats {
+            count: 1,
+            oldest_age_secs: Some(30),
+        });
+
+        // Not stale yet
+        assert_eq!(pending.sweep_stale(Duration::from_secs(60), later), 0);
+        assert_eq!(pending.stats(later)["get_providers"].count, 1);
+
+        // Swept once stale, resolving the query with the providers found so far
+        let much_later = now + Duration::from_secs(61);
+        assert_eq!(pending.sweep_stale(Duration::from_secs(60), much_later), 1);
+        assert_eq!(pending.stats(much_later)["get_providers"], PendingRequestStats::default());
+        assert_eq!(receiver.await.unwrap(), HashSet::from([provider]));
+    }
+}
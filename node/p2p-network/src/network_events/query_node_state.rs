@@ -1,4 +1,5 @@
 use crate::{short_peer_id, get_node_name};
+use crate::types::ReverieType;
 use super::NetworkEvents;
 
 impl NetworkEvents {
@@ -59,14 +60,30 @@ impl NetworkEvents {
                 })
             }).collect::<Vec<serde_json::Value>>();
 
-        let agent_in_vessel = &self.peer_manager.vessel_agent;
+        let agent_in_vessel = self.peer_manager.vessel_agent.as_ref().map(|agent_vessel| {
+            let agent_name_nonce = match &agent_vessel.reverie_type {
+                ReverieType::Agent(agent_name_nonce)
+                | ReverieType::SovereignAgent(agent_name_nonce) => agent_name_nonce.to_string(),
+                reverie_type => reverie_type.to_string(),
+            };
+            serde_json::json!({
+                "agent_name_nonce": agent_name_nonce,
+                "threshold": agent_vessel.threshold,
+                "total_frags": agent_vessel.total_frags,
+                "current_vessel_peer_id": agent_vessel.current_vessel_peer_id,
+                "current_vessel_node_name": get_node_name(&agent_vessel.current_vessel_peer_id),
+                "next_vessel_peer_id": agent_vessel.next_vessel_peer_id,
+                "next_vessel_node_name":  get_node_name(&agent_vessel.next_vessel_peer_id),
+            })
+        });
 
         let node_state = serde_json::json!({
             "_node_name": self.node_id.node_name,
             "_peer_id": self.node_id.peer_id,
             "_umbral_public_key": self.node_id.umbral_key.public_key,
-            "_pending_respawns": self.pending.respawns,
+            "_pending_respawns": self.pending.respawns.keys().collect::<Vec<_>>(),
             "_agent_in_vessel": agent_in_vessel,
+            "_duplicate_vessels_detected": self.peer_manager.duplicate_vessels_detected,
             "peer_manager": {
                 // get all held agent cfrags
                 "1_cfrags_summary": self.peer_manager.held_cfrags_summary(),
@@ -33,12 +33,18 @@ use crate::types::{
     SignedVesselStatus,
     ReverieId,
     ReverieType,
+    ReputationEvent,
+    FragmentRequestEnum,
+    CIPHERTEXT_CHUNK_SIZE,
+    split_reverie_ciphertext,
 };
 use crate::node_client::container_manager::{ContainerManager, RestartReason};
 use crate::env_var::NODE_SEED_NUM;
 use crate::behaviour::Behaviour;
 use runtime::reencrypt::UmbralKey;
 use super::peer_manager::PeerManager;
+use super::respawn_votes::{RespawnVote, RespawnVoteOutcome};
+use super::shutdown_rebroadcast::{AfterRebroadcast, ShutdownRebroadcast};
 use super::NetworkEvents;
 use tokio::time;
 use time::Duration;
@@ -46,8 +52,12 @@ use time::Duration;
 
 impl NetworkEvents {
 
+    /// Cleans up after a confirmed respawn: the failed vessel and the previous Reverie's
+    /// kfrag providers and cfrags are dropped. Kfrags are sent over request-response rather
+    /// than gossipsub topics, so there are no topic subscriptions to drop.
     pub(crate) fn mark_pending_respawn_complete(
         &mut self,
+        prev_reverie_id: ReverieId,
         prev_peer_id: PeerId,
         prev_agent_name_nonce: ReverieNameWithNonce,
     ) {
@@ -58,9 +68,76 @@ impl NetworkEvents {
         );
         self.pending.respawns.remove(&respawn_id);
 
-        // remove peer from PeerManager
+        // remove peer and the previous Reverie from PeerManager
         self.remove_peer(&prev_peer_id);
-        self.peer_manager.remove_peer_info(&prev_peer_id);
+        self.peer_manager.prune_respawned_reverie(&prev_reverie_id, &prev_peer_id);
+        self.remove_reverie_holder_kademlia(&[prev_reverie_id]);
+    }
+
+    /// Operator override for planned vessel migration: dispatches a RespawnRequest
+    /// for the agent as if its current vessel had failed its heartbeats.
+    /// Only the agent's next vessel holds the access condition to get the cfrags.
+    pub(crate) async fn force_reincarnate(&mut self, agent_name_nonce: ReverieNameWithNonce) -> Result<RespawnId> {
+
+        let agent_vessel = self.peer_manager.peer_info.values()
+            .filter_map(|peer_info| peer_info.agent_vessel.as_ref())
+            .find(|agent_vessel| match &agent_vessel.reverie_type {
+                ReverieType::Agent(name) | ReverieType::SovereignAgent(name) => name == &agent_name_nonce,
+                _ => false,
+            })
+            .cloned()
+            .ok_or_else(|| anyhow!("No vessel found for agent {}", agent_name_nonce))?;
+
+        if agent_vessel.next_vessel_peer_id != self.node_id.peer_id {
+            return Err(anyhow!(
+                "Agent {} can only be reincarnated by its next vessel: {}",
+                agent_name_nonce,
+                get_node_name(&agent_vessel.next_vessel_peer_id)
+            ));
+        }
+
+        // Same RespawnId as the heartbeat failure path, so neither can double-trigger
+        let respawn_id = RespawnId::new(&agent_name_nonce, &agent_vessel.current_vessel_peer_id);
+        if self.pending.respawns.contains_key(&respawn_id) {
+            return Err(anyhow!("Respawn already pending for agent {}", agent_name_nonce));
+        }
+        self.pending.respawns.insert(respawn_id.clone(), ());
+
+        info!("{}", format!("Forcing reincarnation of agent {}", agent_name_nonce).yellow());
+        // the current vessel is still alive, tell it to stand down when it next heartbeats
+        self.peer_manager.mark_vessel_reincarnated(agent_vessel.current_vessel_peer_id, agent_name_nonce);
+        self.send_network_event(NetworkEvent::RespawnRequest(agent_vessel)).await?;
+
+        Ok(respawn_id)
+    }
+
+    /// Reincarnates an agent its current vessel is handing off to this node.
+    /// Only the agent's current vessel can hand it off, after re-keying it to this node.
+    pub(crate) async fn accept_vessel_handoff(
+        &mut self,
+        current_vessel_peer_id: PeerId,
+        agent_name_nonce: ReverieNameWithNonce
+    ) -> Result<RespawnId> {
+
+        let is_current_vessel = self.peer_manager.peer_info.get(&current_vessel_peer_id)
+            .and_then(|peer_info| peer_info.agent_vessel.as_ref())
+            .is_some_and(|agent_vessel| match &agent_vessel.reverie_type {
+                ReverieType::Agent(name) | ReverieType::SovereignAgent(name) => name == &agent_name_nonce,
+                _ => false,
+            });
+        if !is_current_vessel {
+            return Err(anyhow!(
+                "{} is not the current vessel of agent {}",
+                get_node_name(&current_vessel_peer_id),
+                agent_name_nonce
+            ));
+        }
+
+        let respawn_id = self.force_reincarnate(agent_name_nonce).await?;
+        // The previous vessel releases the agent itself once the handoff is confirmed,
+        // so its heartbeats aren't flagged as a duplicate vessel
+        self.peer_manager.reincarnated_vessels.remove(&current_vessel_peer_id);
+        Ok(respawn_id)
     }
 
     pub(crate) async fn handle_peer_heartbeat_failure(&mut self) -> Result<()> {
@@ -70,6 +147,25 @@ impl NetworkEvents {
             .config
             .max_time_before_rotation();
 
+        // Votes that never reached a decision are dropped, and re-run if the vessel is still offline
+        let expired_votes = self.pending.respawn_votes
+            .remove_older_than(self.network_config.respawn_vote_timeout, time::Instant::now());
+        for vote in expired_votes {
+            warn!("{} Respawn vote for {} expired", self.nname(), get_node_name(&vote.agent_vessel.current_vessel_peer_id));
+        }
+
+        // Oversized heartbeats were dropped unparsed, so these peers' heartbeats stay stale
+        let rejected_heartbeats = self.swarm.behaviour_mut().heartbeat.drain_rejected_heartbeats();
+        for (peer_id, oversized) in rejected_heartbeats {
+            warn!("{} Dropped heartbeat from {}: {}", self.nname(), get_node_name(&peer_id), oversized);
+            self.peer_manager.update_peer_reputation(peer_id, ReputationEvent::OversizedHeartbeat);
+        }
+        let invalid_attestations = self.swarm.behaviour_mut().heartbeat.drain_invalid_attestations();
+        for (peer_id, reason) in invalid_attestations {
+            warn!("{} Dropped heartbeat from {} with invalid attestation: {}", self.nname(), get_node_name(&peer_id), reason);
+            self.peer_manager.update_peer_reputation(peer_id, ReputationEvent::VerificationFailure);
+        }
+
         let connected_peers: HashSet<&PeerId> = self.swarm.connected_peers().collect();
         let peer_info = self.peer_manager.peer_info.clone();
 
@@ -95,6 +191,7 @@ impl NetworkEvents {
             if self.peer_manager.is_peer_offline(peer_id, max_time_before_respawn, false) {
 
                 info!("{}", format!("{} {} heartbeat failed. Respawn pending.", node_name, peer_id).magenta());
+                self.peer_manager.update_peer_reputation(*peer_id, ReputationEvent::HeartbeatFailure);
 
                 // Only the next_vessel and kfrag_providers store previous vessel's agent_vessel info
                 if let Some(AgentVesselInfo {
@@ -117,19 +214,17 @@ impl NetworkEvents {
                     if is_agent {
 
                         info!("{}", format!("Reincarnating agent: {}", prev_agent).yellow());
+                        let respawn_id = RespawnId::new(&prev_agent, &current_vessel_peer_id);
                         // all kfrag_providers mark agent as respawning
-                        self.pending.respawns.insert(RespawnId::new(&prev_agent, &current_vessel_peer_id));
+                        self.pending.respawns.insert(respawn_id.clone(), ());
 
                         // If this node is the next vessel for the agent
                         if self.node_id.peer_id == *next_vessel_peer_id {
-                            // Dispatch a RespawnRequest event to NetworkEvents
-                            info!("Dispatching RespawnRequest for agent {} into new vessel {}",
-                                prev_agent.to_string().green(),
-                                node_name.green()
-                            );
-                            // Only the correct next_vessel has the valid signature to get the cfrags and respawn
-                            self.network_event_sender.send(
-                                NetworkEvent::RespawnRequest(
+                            // Only respawn once a majority of peers also see the vessel offline,
+                            // otherwise a node partitioned from the vessel could respawn it alone
+                            if !self.pending.respawn_votes.contains_key(&respawn_id) {
+                                self.start_respawn_vote(
+                                    respawn_id,
                                     AgentVesselInfo {
                                         reverie_id: reverie_id.clone(),
                                         reverie_type: reverie_type.clone(),
@@ -138,14 +233,15 @@ impl NetworkEvents {
                                         next_vessel_peer_id: *next_vessel_peer_id,
                                         current_vessel_peer_id: current_vessel_peer_id.clone()
                                     }
-                                )
-                            ).await?;
-                            // Only next_vessel removes peer from PeerManagers
-                            self.remove_peer(&peer_id);
+                                ).await?;
+                            }
                             // If node is not the next vessel: wait for next vessel to re-broadcast cfrags
                             // Once respawn is finished:
                             // - New vessel will tell other nodes to update PeerManager and
                             // - update pending.respawns: self.pending.respawns.remove(&respawn_id);
+                        } else {
+                            // watch for the failed vessel coming back with the agent still running
+                            self.peer_manager.mark_vessel_reincarnated(*current_vessel_peer_id, prev_agent.clone());
                         }
                     }
                 } else {
@@ -157,13 +253,211 @@ impl NetworkEvents {
         Ok(())
     }
 
+    /// Asks every other connected peer whether it also sees the failed vessel offline.
+    /// Peers only known from past heartbeats can't answer, so are not part of the vote.
+    /// With no other peers, this node's own observation is enough to respawn.
+    async fn start_respawn_vote(&mut self, respawn_id: RespawnId, agent_vessel: AgentVesselInfo) -> Result<()> {
+
+        let failed_peer_id = agent_vessel.current_vessel_peer_id;
+        let electorate: HashSet<PeerId> = self.swarm.connected_peers()
+            .filter(|peer_id| **peer_id != failed_peer_id && **peer_id != self.node_id.peer_id)
+            .cloned()
+            .collect();
+
+        info!("{}", format!(
+            "Asking {} peers to confirm {} is offline",
+            electorate.len(),
+            get_node_name(&failed_peer_id)
+        ).yellow());
+
+        for peer_id in electorate.iter() {
+            let request_id = self.swarm.behaviour_mut()
+                .request_response
+                .send_request(peer_id, FragmentRequestEnum::RespawnVoteRequest(respawn_id.clone(), failed_peer_id));
+            self.pending.respawn_vote_requests.insert(request_id, respawn_id.clone());
+        }
+
+        let vote = RespawnVote::new(agent_vessel, electorate);
+        let outcome = vote.outcome();
+        self.pending.respawn_votes.insert(respawn_id.clone(), vote);
+        self.handle_respawn_vote_outcome(respawn_id, outcome).await
+    }
+
+    /// Counts a peer's answer to a RespawnVoteRequest, respawning the agent once a quorum agrees
+    pub(crate) async fn record_respawn_vote(&mut self, respawn_id: RespawnId, voter: PeerId, offline: bool) -> Result<()> {
+        let outcome = match self.pending.respawn_votes.get_mut(&respawn_id) {
+            Some(vote) => vote.record_vote(voter, offline),
+            // respawn already dispatched, or the vote expired
+            None => None,
+        };
+        match outcome {
+            Some(outcome) => self.handle_respawn_vote_outcome(respawn_id, outcome).await,
+            None => Ok(()),
+        }
+    }
+
+    /// Counts a peer whose RespawnVoteRequest failed as abstaining
+    pub(crate) async fn record_respawn_abstention(&mut self, respawn_id: RespawnId, voter: PeerId) -> Result<()> {
+        let outcome = match self.pending.respawn_votes.get_mut(&respawn_id) {
+            Some(vote) => vote.record_abstention(voter),
+            None => None,
+        };
+        match outcome {
+            Some(outcome) => self.handle_respawn_vote_outcome(respawn_id, outcome).await,
+            None => Ok(()),
+        }
+    }
+
+    async fn handle_respawn_vote_outcome(&mut self, respawn_id: RespawnId, outcome: RespawnVoteOutcome) -> Result<()> {
+        match outcome {
+            RespawnVoteOutcome::Pending => Ok(()),
+            RespawnVoteOutcome::Rejected => {
+                // Peers may just not have timed the vessel out yet, a new vote starts
+                // on the next heartbeat check if it is still offline here
+                warn!("{} No quorum for respawn {}, peers still see the vessel online", self.nname(), respawn_id.get_request_id());
+                self.pending.respawn_votes.remove(&respawn_id);
+                Ok(())
+            }
+            RespawnVoteOutcome::Confirmed => {
+                let agent_vessel = match self.pending.respawn_votes.remove(&respawn_id) {
+                    Some(vote) => vote.agent_vessel,
+                    None => return Ok(()),
+                };
+                self.dispatch_respawn(agent_vessel).await
+            }
+        }
+    }
+
+    async fn dispatch_respawn(&mut self, agent_vessel: AgentVesselInfo) -> Result<()> {
+        let failed_peer_id = agent_vessel.current_vessel_peer_id;
+        if let ReverieType::Agent(prev_agent) | ReverieType::SovereignAgent(prev_agent) = &agent_vessel.reverie_type {
+            // watch for the failed vessel coming back with the agent still running
+            self.peer_manager.mark_vessel_reincarnated(failed_peer_id, prev_agent.clone());
+            // Dispatch a RespawnRequest event to NetworkEvents
+            info!("Dispatching RespawnRequest for agent {} into new vessel {}",
+                prev_agent.to_string().green(),
+                self.node_id.node_name.green()
+            );
+        }
+        // Only the correct next_vessel has the valid signature to get the cfrags and respawn
+        self.send_network_event(NetworkEvent::RespawnRequest(agent_vessel)).await?;
+        // Only next_vessel removes peer from PeerManagers
+        self.remove_peer(&failed_peer_id);
+        Ok(())
+    }
+
     pub(crate) async fn handle_internal_heartbeat_failure(&mut self, heartbeat_config: HeartbeatConfig) {
         // Internal heartbeat failure is when a node fails to send heartbeats to external
         // nodes. It realizes it is no longer connected to the network.
         info!("{}", format!("{:?}", heartbeat_config).red());
-        info!("{}", "Todo: Delete agent secrets, prevent duplicate agents.".yellow());
+        if self.pending.shutdown_rebroadcast.is_some() {
+            return
+        }
         info!("{}", "Initiating recovery...".yellow());
 
+        // Re-broadcast reveries this node is the vessel for, so the next vessel can
+        // reincarnate the agent. The event loop finishes recovery once kfrag providers
+        // confirm or the timeout elapses, see `finish_shutdown_rebroadcast`.
+        let rebroadcast_timeout = heartbeat_config.send_timeout;
+        self.start_shutdown_rebroadcast(AfterRebroadcast::Restart, rebroadcast_timeout);
+    }
+
+    /// Sends the reveries this node is the vessel for to their kfrag providers, so the
+    /// ciphertexts outlive this node. Responses are tracked in `pending.shutdown_rebroadcast`.
+    pub(super) fn start_shutdown_rebroadcast(&mut self, then: AfterRebroadcast, timeout: Duration) {
+
+        let mut rebroadcast = ShutdownRebroadcast::new(then, time::Instant::now() + timeout);
+        let vessel_reveries = self.peer_manager.vessel_reveries();
+        if !vessel_reveries.is_empty() {
+            info!("{}", format!("Re-broadcasting {} vessel reveries to kfrag providers", vessel_reveries.len()).yellow());
+        }
+
+        for reverie_msg in vessel_reveries {
+            let reverie_id = reverie_msg.reverie.id.clone();
+            rebroadcast.add_reverie(reverie_id.clone());
+
+            let kfrag_providers: Vec<PeerId> = reverie_msg.keyfrag_providers.iter()
+                .filter(|peer_id| **peer_id != self.node_id.peer_id)
+                .cloned()
+                .collect();
+            if kfrag_providers.is_empty() {
+                warn!("{} No kfrag providers to re-broadcast reverie {} to", self.nname(), reverie_id);
+            }
+
+            for kfrag_provider in kfrag_providers {
+                // Large ciphertexts are sent in chunks, as in SendReverieToSpecificPeer
+                let requests: Vec<FragmentRequestEnum> = match reverie_msg.reverie.umbral_ciphertext.len() > CIPHERTEXT_CHUNK_SIZE {
+                    true => split_reverie_ciphertext(&reverie_msg, CIPHERTEXT_CHUNK_SIZE)
+                        .into_iter()
+                        .map(FragmentRequestEnum::SaveCiphertextChunkRequest)
+                        .collect(),
+                    false => vec![FragmentRequestEnum::SaveCiphertextRequest(reverie_msg.clone())],
+                };
+                for request in requests {
+                    let request_id = self.swarm.behaviour_mut()
+                        .request_response
+                        .send_request(&kfrag_provider, request);
+                    rebroadcast.add_request(request_id, reverie_id.clone(), kfrag_provider);
+                }
+            }
+        }
+
+        self.pending.shutdown_rebroadcast = Some(rebroadcast);
+    }
+
+    /// Records a kfrag provider's answer to a re-broadcast request.
+    /// Returns false if the request isn't part of a re-broadcast.
+    pub(super) fn record_shutdown_rebroadcast_response(
+        &mut self,
+        request_id: &request_response::OutboundRequestId,
+        acknowledged: bool
+    ) -> bool {
+        self.pending.shutdown_rebroadcast.as_mut()
+            .map(|rebroadcast| rebroadcast.record_response(request_id, acknowledged))
+            .unwrap_or(false)
+    }
+
+    /// Called by the event loop after each event. Once the re-broadcast settles, deletes
+    /// vessel secrets if it was confirmed and restarts, or exits on shutdown.
+    /// Returns true when the event loop should exit.
+    pub(super) async fn finish_shutdown_rebroadcast(&mut self) -> bool {
+        match self.complete_shutdown_rebroadcast() {
+            Some(AfterRebroadcast::Restart) => {
+                self.restart_after_heartbeat_failure().await;
+                false
+            }
+            Some(AfterRebroadcast::Exit) => true,
+            None => false,
+        }
+    }
+
+    /// Takes the re-broadcast once it has settled. Local secrets are only deleted if every
+    /// reverie was confirmed, otherwise the reverie could be lost from the network entirely.
+    pub(super) fn complete_shutdown_rebroadcast(&mut self) -> Option<AfterRebroadcast> {
+        let settled = self.pending.shutdown_rebroadcast.as_ref()
+            .map(|rebroadcast| rebroadcast.is_settled(time::Instant::now()))
+            .unwrap_or(false);
+        if !settled {
+            return None
+        }
+        let rebroadcast = self.pending.shutdown_rebroadcast.take()?;
+
+        if !rebroadcast.is_confirmed() {
+            warn!("{}", format!(
+                "Re-broadcast of {} vessel reveries unconfirmed, keeping local secrets",
+                rebroadcast.unconfirmed()
+            ).red());
+        } else if let AfterRebroadcast::Restart = rebroadcast.then {
+            let deleted_reverie_ids = self.peer_manager.delete_vessel_secrets();
+            info!("{}", format!("Deleted secrets for {} vessel reveries", deleted_reverie_ids.len()).yellow());
+            self.remove_reverie_holder_kademlia(&deleted_reverie_ids);
+            self.update_reverie_type_indexes();
+        }
+        Some(rebroadcast.then)
+    }
+
+    async fn restart_after_heartbeat_failure(&mut self) {
+
         let peers: Vec<PeerId> = self.swarm.connected_peers().cloned().collect();
         let total_peers = peers.len();
         info!("{}", format!("Disconnecting from {} peers", total_peers).yellow());
@@ -190,15 +484,9 @@ impl NetworkEvents {
             .trigger_restart(RestartReason::ScheduledHeartbeatFailure)
             .await.ok();
 
-        // Shutdown LLM runtime (if in Vessel Mode), but continue attempting
-        // to broadcast the agent_secrets reencryption fragments and ciphertexts.
-        //
         // If the node never reconnects to the network, then nodes will
         // form consensus that the Vessel is dead, and begin reincarnating the Agent
         // from it's last public agent_secret ciphertexts on the Kademlia network.
-        //
-        // In this case, the node should also delete it's Agent secrets to prevent
-        // duplicate instances of an agent running simultaneously
     }
 
     pub(crate) async fn simulate_heartbeat_failure(&mut self) {
@@ -1,28 +1,35 @@
 use colored::Colorize;
 use color_eyre::{Result, eyre::anyhow};
+use libp2p::PeerId;
 use libp2p::request_response;
-use libp2p::request_response::{Event, Message};
-use tracing::{info, warn};
+use libp2p::request_response::{Event, Message, OutboundFailure};
+use tracing::{info, debug, warn};
 use sha3::{Digest, Keccak256};
 
 use crate::SendError;
 use crate::types::{
     NetworkEvent,
+    NodeEvent,
     FragmentRequestEnum,
     FragmentResponseEnum,
     AgentVesselInfo,
-    NodeKeysWithVesselStatus,
-    VesselStatus,
     ReverieKeyfrag,
     ReverieCapsulefrag,
     ReverieKeyfragMessage,
     ReverieMessage,
+    ReverieId,
     ReverieType,
     AccessKey,
+    AccessCondition,
+    ReputationEvent,
+    CIPHERTEXT_OVERHEAD,
+    REVERIE_WIRE_VERSION,
 };
 use crate::{short_peer_id, get_node_name, get_node_name2};
 use crate::types::create_digest_hash;
-use super::NetworkEvents;
+use tokio::time::Instant;
+use super::{NetworkEvents, PendingFragmentRequest};
+use super::access_decisions::AccessDecisionKey;
 
 type RequestResponseEvent = Event<FragmentRequestEnum, FragmentResponseEnum>;
 
@@ -52,68 +59,20 @@ impl NetworkEvents {
                         info!("{}", format!("{} Inbound RequestFragmentRequest {reverie_id}", self.nname()).yellow());
                         info!("{}", format!("Signature: {access_key}").yellow());
 
-                        let cfrag = match self.peer_manager.get_cfrags(&reverie_id) {
-                            Some(cfrag) => cfrag,
-                            None => return Err(anyhow!("{} No cfrag found for {}", self.nname(), reverie_id)),
-                        };
-
-                        // TODO: refactor to a AccessCondition / Auth module for generalizing access control and conditions
-                        // Verify signature is from intended recipient/target before sending capsule fragment
-                        // TODO: add nonce and timestamp to digest
-                        match &access_key {
-                            AccessKey::NearContract(
-                                contract_account_id,
-                                spender_account_id,
-                                amount
-                            ) => {
-
-                                let can_spend = self.near_runtime.can_spend(
-                                    contract_account_id,
-                                    &reverie_id,
-                                    spender_account_id,
-                                    *amount
-                                ).await?;
-
-                                if can_spend {
-                                    info!("{}", format!("Near Contract Access granted!").green());
-                                } else {
-                                    return Err(anyhow!("Near Contract Access denied!"));
-                                }
-                            }
-                            AccessKey::EcdsaSignature(signature) => {
-                                match access_key.verify_access(&cfrag.access_condition, reverie_id) {
-                                    false => return Err(anyhow!("Invalid signature for fragment request for {reverie_id}".to_string())),
-                                    true => info!("{}", format!("Signature verified!").green())
-                                }
-                            }
-                            AccessKey::UmbralSignature(signature) => {
-                                match access_key.verify_access(&cfrag.access_condition, reverie_id) {
-                                    false => return Err(anyhow!("Invalid signature for fragment request for {reverie_id}".to_string())),
-                                    true => info!("{}", format!("Signature verified!").green())
-                                }
-                            }
-                            AccessKey::Ed25519Signature(signature) => {
-                                return Err(anyhow!("Ed25519 signatures are not implemented yet"));
-                            }
-                            AccessKey::EthContract(
-                                contract_address,
-                                contract_method_name,
-                                contract_args
-                            ) => {
-                                return Err(anyhow!("EthContract access keys are not implemented yet"));
+                        let cfrag_bytes = match self.authorize_fragment_request(reverie_id.clone(), &access_key).await {
+                            Ok(cfrag) => serde_json::to_vec::<ReverieCapsulefrag>(&cfrag)
+                                .map_err(|e| SendError(e.to_string())),
+                            Err(e) => {
+                                warn!("{} Rejected GetFragmentRequest for {} from {}: {}", self.nname(), reverie_id, get_node_name2(&peer), e);
+                                Err(SendError(e.to_string()))
                             }
                         };
 
-                        let cfrag_bytes = serde_json::to_vec::<ReverieCapsulefrag>(&cfrag)
-                            .map_err(|e| SendError(e.to_string()));
-
-                        self.swarm.behaviour_mut()
-                            .request_response
-                            .send_response(
-                                channel,
-                                FragmentResponseEnum::GetFragmentResponse(cfrag_bytes)
-                            )
-                            .expect("Connection to peer to be still open.");
+                        self.send_inbound_response(
+                            channel,
+                            FragmentResponseEnum::GetFragmentResponse(cfrag_bytes),
+                            &peer
+                        );
                     },
 
                     FragmentRequestEnum::SaveFragmentRequest(
@@ -131,16 +90,23 @@ impl NetworkEvents {
                             short_peer_id(&source_peer_id).yellow()
                         );
 
-                        // 1) When a node receives a Kfrag, verify the Kfrag
-                        let keyfrag: umbral_pre::KeyFrag = serde_json::from_slice(&reverie_keyfrag.umbral_keyfrag).expect("serde err");
-                        let verified_kfrag = keyfrag.verify(
-                            &reverie_keyfrag.source_verifying_pubkey,
-                            Some(&reverie_keyfrag.source_pubkey),
-                            Some(&reverie_keyfrag.target_pubkey)
-                        ).map_err(SendError::from).expect("keyfrag verification failed");
-
-                        let capsule = serde_json::from_slice(&reverie_keyfrag.umbral_capsule).expect("serde err");
-                        let cfrag = umbral_pre::reencrypt(&capsule, verified_kfrag).unverify();
+                        // 1) When a node receives a Kfrag, verify the Kfrag and re-encrypt the capsule.
+                        // Oversized or malformed keyfrags are rejected before/while deserializing them
+                        let cfrag_bytes = reverie_keyfrag.check_payload_size(self.network_config.max_reverie_payload_size)
+                            .and_then(|_| reverie_keyfrag.reencrypt_capsule())
+                            .and_then(|cfrag| Ok(serde_json::to_vec(&cfrag.unverify())?));
+                        let cfrag_bytes = match cfrag_bytes {
+                            Ok(cfrag_bytes) => cfrag_bytes,
+                            Err(e) => {
+                                warn!("{} Rejected SaveFragmentRequest for {} from {}: {}", self.nname(), reverie_keyfrag.id, get_node_name2(&peer), e);
+                                self.send_inbound_response(
+                                    channel,
+                                    FragmentResponseEnum::SaveFragmentFailedResponse(SendError(e.to_string())),
+                                    &peer
+                                );
+                                return Ok(())
+                            }
+                        };
 
                         // 2) Save Agent metadata if ReverieType is Agent
                         if let ReverieType::Agent(..) | ReverieType::SovereignAgent(..) = reverie_keyfrag.reverie_type {
@@ -165,20 +131,28 @@ impl NetworkEvents {
                         self.peer_manager.insert_cfrags(
                             &reverie_keyfrag.id,
                             ReverieCapsulefrag {
+                                version: REVERIE_WIRE_VERSION,
                                 id: reverie_keyfrag.id.clone(),
                                 reverie_type: reverie_keyfrag.reverie_type,
                                 frag_num: reverie_keyfrag.frag_num,
                                 threshold: reverie_keyfrag.threshold,
-                                umbral_capsule_frag: serde_json::to_vec(&cfrag).expect("serde err"),
+                                umbral_capsule_frag: cfrag_bytes,
                                 source_pubkey: reverie_keyfrag.source_pubkey, // source vessel
                                 source_verifying_pubkey: reverie_keyfrag.source_verifying_pubkey, // source vessel verifying key
                                 target_pubkey: reverie_keyfrag.target_pubkey, // target vessel
                                 target_verifying_pubkey: reverie_keyfrag.target_verifying_pubkey, // target vessel verifying key
                                 access_condition: reverie_keyfrag.access_condition, // access condition to be checked against to request cfrags
                                 kfrag_provider_peer_id: self.node_id.peer_id,
+                                expires_at: reverie_keyfrag.expires_at,
                             }
                         );
 
+                        self.emit_node_event(NodeEvent::KeyfragSaved {
+                            reverie_id: reverie_keyfrag.id.clone(),
+                            frag_num: reverie_keyfrag.frag_num,
+                            source_peer_id,
+                        });
+
                         // 3) Notify target vessel that this node is a KfragProvider for this ReverieId
                         let request_id = self.swarm.behaviour_mut().request_response
                             .send_request(
@@ -191,11 +165,11 @@ impl NetworkEvents {
                             );
 
                         // 4). Respond to broadcaster node, acknowledging receipt of Kfrag
-                        self.swarm.behaviour_mut().request_response
-                            .send_response(
-                                channel,
-                                FragmentResponseEnum::SaveFragmentResponse
-                            ).expect("Connection to peer to be still open.");
+                        self.send_inbound_response(
+                            channel,
+                            FragmentResponseEnum::SaveFragmentResponse,
+                            &peer
+                        );
 
                     },
 
@@ -206,25 +180,36 @@ impl NetworkEvents {
                         kfrag_provider_peer_id
                     ) => {
 
-                        info!(
-                            "\n{} Adding peer to kfrags_providers({}, {}, {})",
-                            self.nname(),
-                            reverie_id,
-                            frag_num,
-                            short_peer_id(&kfrag_provider_peer_id)
-                        );
+                        // The provider is the peer sending the request, the peer id in the
+                        // payload is set by the sender and could name any other node
+                        if kfrag_provider_peer_id != peer {
+                            warn!("{} Ignoring ProvidingFragmentRequest from {} claiming to be {}",
+                                self.nname(), get_node_name2(&peer), get_node_name2(&kfrag_provider_peer_id));
+                        } else {
+                            info!(
+                                "\n{} Adding peer to kfrags_providers({}, {}, {})",
+                                self.nname(),
+                                reverie_id,
+                                frag_num,
+                                short_peer_id(&peer)
+                            );
 
-                        // 1). Add to PeerManager locally on this node
-                        self.peer_manager.insert_kfrag_provider(kfrag_provider_peer_id.clone(), reverie_id, frag_num);
-                        self.peer_manager.insert_peer_info(kfrag_provider_peer_id.clone());
+                            // 1). Add to PeerManager locally on this node
+                            self.peer_manager.insert_kfrag_provider(peer, reverie_id.clone(), frag_num);
+                            self.peer_manager.insert_peer_info(peer);
+                            self.emit_node_event(NodeEvent::KfragProviderAdded {
+                                reverie_id,
+                                frag_num,
+                                kfrag_provider: peer,
+                            });
+                        }
 
                         // 2). Respond to Kfrag Provider and peer as Provider
-                        self.swarm.behaviour_mut().request_response
-                            .send_response(
-                                channel,
-                                FragmentResponseEnum::ProvidingFragmentResponse
-                            )
-                            .expect("Connection to peer to be still open.");
+                        self.send_inbound_response(
+                            channel,
+                            FragmentResponseEnum::ProvidingFragmentResponse,
+                            &peer
+                        );
 
                     },
 
@@ -243,56 +228,137 @@ impl NetworkEvents {
                             short_peer_id(&source_peer_id).yellow()
                         );
 
-                        // 1) Save Agent metadata if need be
-                        if let ReverieType::Agent(..) | ReverieType::SovereignAgent(..) = reverie.reverie_type {
+                        // Save Reverie locally, and publish this node as its holder
+                        let reverie_id = reverie.id.clone();
+                        let saved = reverie.check_payload_size(self.network_config.max_reverie_payload_size)
+                            .and_then(|_| self.save_vessel_reverie(ReverieMessage {
+                                reverie,
+                                source_peer_id,
+                                target_peer_id,
+                                keyfrag_providers,
+                            }));
+
+                        // Respond to broadcaster node and acknowledge receipt of Reverie/Ciphertext
+                        let response = match saved {
+                            Ok(()) => FragmentResponseEnum::SaveCiphertextResponse,
+                            Err(e) => {
+                                warn!("{} Rejected SaveCiphertextRequest for {} from {}: {}", self.nname(), reverie_id, get_node_name2(&peer), e);
+                                FragmentResponseEnum::SaveCiphertextFailedResponse(SendError(e.to_string()))
+                            }
+                        };
+                        self.send_inbound_response(channel, response, &peer);
+                    }
 
-                            let agent_metadata = AgentVesselInfo {
-                                reverie_id: reverie.id.clone(),
-                                reverie_type: reverie.reverie_type.clone(),
-                                threshold: reverie.threshold,
-                                total_frags: reverie.total_frags,
-                                current_vessel_peer_id: source_peer_id,
-                                next_vessel_peer_id: target_peer_id,
-                            };
+                    FragmentRequestEnum::SaveCiphertextChunkRequest(chunk) => {
 
-                            self.peer_manager.set_peer_info_agent_vessel(&agent_metadata);
-                            self.peer_manager.insert_reverie_metadata(
-                                &reverie.id,
-                                agent_metadata.clone()
-                            );
+                        let reverie_id = chunk.reverie_msg.reverie.id.clone();
+                        debug!("{} Received ciphertext chunk {}/{} for {}",
+                            self.nname(),
+                            chunk.index + 1,
+                            chunk.total,
+                            reverie_id
+                        );
 
-                            // Put signed vessel status on Kademlia
-                            self.put_signed_vessel_status_kademlia(
-                                NodeKeysWithVesselStatus {
-                                    peer_id: self.node_id.peer_id,
-                                    umbral_public_key: self.node_id.umbral_key.public_key,
-                                    umbral_verifying_public_key: self.node_id.umbral_key.verifying_public_key,
-                                    vessel_status: VesselStatus::ActiveVessel,
+                        let max_ciphertext_size = self.network_config.max_reverie_payload_size + CIPHERTEXT_OVERHEAD;
+                        let saved = self.peer_manager
+                            .insert_reverie_chunk(chunk, max_ciphertext_size)
+                            .and_then(|assembled| match assembled {
+                                // Save Reverie once every chunk has arrived, and publish this node as its holder
+                                Some(reverie_msg) => {
+                                    info!("\n\t{}\n\tfrom peer: {} {}",
+                                        format!("Reassembled chunked ciphertext for {}", reverie_id).green(),
+                                        get_node_name(&reverie_msg.source_peer_id).yellow(),
+                                        short_peer_id(&reverie_msg.source_peer_id).yellow()
+                                    );
+                                    reverie_msg.reverie.check_payload_size(self.network_config.max_reverie_payload_size)?;
+                                    self.save_vessel_reverie(reverie_msg)
                                 }
-                            ).expect("Failed to put signed vessel status on Kademlia");
+                                None => Ok(()),
+                            });
+
+                        // Acknowledge each chunk
+                        let response = match saved {
+                            Ok(()) => FragmentResponseEnum::SaveCiphertextChunkResponse,
+                            Err(e) => {
+                                warn!("{} Rejected SaveCiphertextChunkRequest for {} from {}: {}", self.nname(), reverie_id, get_node_name2(&peer), e);
+                                FragmentResponseEnum::SaveCiphertextFailedResponse(SendError(e.to_string()))
+                            }
+                        };
+                        self.send_inbound_response(channel, response, &peer);
+                    }
+
+                    FragmentRequestEnum::GetCiphertextRequest(reverie_id) => {
+                        info!("{}", format!("{} Inbound GetCiphertextRequest {reverie_id}", self.nname()).yellow());
+                        // Holders which no longer hold the reverie answer with an error, not silence
+                        let reverie_msg = self.peer_manager.get_reverie(&reverie_id)
+                            .cloned()
+                            .ok_or_else(|| SendError(format!("{} does not hold {}", self.nname(), reverie_id)));
+
+                        self.send_inbound_response(
+                            channel,
+                            FragmentResponseEnum::GetCiphertextResponse(reverie_msg),
+                            &peer
+                        );
+                    }
+
+                    FragmentRequestEnum::StandDownVesselRequest(agent_name) => {
+                        // Only stand down if this node still holds the agent, so a stale
+                        // request can't wipe a vessel hosting a different agent, and only
+                        // when asked by a peer that reincarnates it, not any connected peer
+                        if !self.peer_manager.is_vessel_for_agent(&agent_name) {
+                            info!("{} Ignoring StandDownVesselRequest, not the vessel for {}", self.nname(), agent_name);
+                        } else if !self.peer_manager.can_stand_down_agent(&agent_name, &peer) {
+                            warn!("{} Ignoring StandDownVesselRequest for {} from {}, not its next vessel or a kfrag provider",
+                                self.nname(), agent_name, get_node_name2(&peer));
+                        } else {
+                            warn!("{}", format!(
+                                "{} Agent {} was reincarnated in another vessel, standing down",
+                                self.nname(),
+                                agent_name
+                            ).red());
+                            // Other reveries this node holds are kept
+                            let deleted_reverie_ids = self.peer_manager.release_vessel_agent(&agent_name);
+                            info!("{}", format!("Deleted secrets for {} reveries of agent {}", deleted_reverie_ids.len(), agent_name).yellow());
+                            self.remove_reverie_holder_kademlia(&deleted_reverie_ids);
+                            self.update_reverie_type_indexes();
                         }
 
-                        // 2) Save Reverie locally on this node
-                        self.peer_manager.insert_reverie(
-                            &reverie.id,
-                            ReverieMessage {
-                                reverie: reverie.clone(),
-                                source_peer_id,
-                                target_peer_id,
-                                keyfrag_providers,
-                            },
+                        self.send_inbound_response(
+                            channel,
+                            FragmentResponseEnum::StandDownVesselResponse,
+                            &peer
                         );
+                    }
 
-                        // 3) Put reverie holder's PeerId on Kademlia
-                        self.put_reverie_holder_kademlia(reverie.id, target_peer_id)
-                            .map_err(|e| anyhow!("Failed to put: {}", e))?;
+                    FragmentRequestEnum::HandoffVesselRequest(agent_name) => {
+                        info!("{}", format!("{} Inbound HandoffVesselRequest for {} from {}", self.nname(), agent_name, get_node_name2(&peer)).yellow());
+                        let respawn_id = self.accept_vessel_handoff(peer, agent_name).await
+                            .map_err(|e| {
+                                warn!("{} Refused handoff from {}: {}", self.nname(), get_node_name2(&peer), e);
+                                SendError(e.to_string())
+                            });
+
+                        self.send_inbound_response(
+                            channel,
+                            FragmentResponseEnum::HandoffVesselResponse(respawn_id),
+                            &peer
+                        );
+                    }
 
-                        // 4). Respond to broadcaster node and acknowledge receipt of Reverie/Ciphertext
-                        self.swarm.behaviour_mut().request_response
-                            .send_response(
-                                channel,
-                                FragmentResponseEnum::SaveCiphertextResponse
-                            ).map_err(|e| anyhow!("Failed to send: {:?}", e))?;
+                    FragmentRequestEnum::RespawnVoteRequest(respawn_id, failed_peer_id) => {
+                        let max_time_before_respawn = self.swarm.behaviour()
+                            .heartbeat
+                            .config
+                            .max_time_before_rotation();
+                        // Unknown peers count as offline, e.g. already removed after failing heartbeats
+                        let offline = self.peer_manager.is_peer_offline(&failed_peer_id, max_time_before_respawn, false);
+                        info!("{} Voting {} is {}", self.nname(), get_node_name(&failed_peer_id), if offline { "offline" } else { "online" });
+
+                        self.send_inbound_response(
+                            channel,
+                            FragmentResponseEnum::RespawnVoteResponse(respawn_id, offline),
+                            &peer
+                        );
                     }
 
                     FragmentRequestEnum::MarkRespawnCompleteRequest {
@@ -301,13 +367,29 @@ impl NetworkEvents {
                         prev_agent_name
                     } => {
                         info!("Inbound MarkRespawnCompleteRequest");
-                        self.mark_pending_respawn_complete(prev_peer_id, prev_agent_name);
+                        // Only the agent's next vessel respawns it, so any other peer
+                        // could otherwise prune a live Reverie's kfrag providers and cfrags
+                        if !self.peer_manager.is_next_vessel_for_reverie(&prev_reverie_id, &peer) {
+                            warn!("{} Ignoring MarkRespawnCompleteRequest for {} from {}, not its next vessel",
+                                self.nname(), prev_reverie_id, get_node_name2(&peer));
+                        } else {
+                            self.mark_pending_respawn_complete(
+                                prev_reverie_id.clone(),
+                                prev_peer_id,
+                                prev_agent_name.clone()
+                            );
+                            self.emit_node_event(NodeEvent::RespawnComplete {
+                                prev_reverie_id,
+                                prev_peer_id,
+                                prev_agent_name,
+                            });
+                        }
 
-                        self.swarm.behaviour_mut().request_response
-                            .send_response(
-                                channel,
-                                FragmentResponseEnum::MarkRespawnCompleteResponse
-                            ).map_err(|e| anyhow!("Failed to send: {:?}", e))?;
+                        self.send_inbound_response(
+                            channel,
+                            FragmentResponseEnum::MarkRespawnCompleteResponse,
+                            &peer
+                        );
                     }
                 }
             }
@@ -332,13 +414,14 @@ impl NetworkEvents {
                 match response {
                     FragmentResponseEnum::GetFragmentResponse(cfrag_bytes) => {
                         info!("{}", format!("RequestId({request_id}) Received GetFragmentResponse from {peer_name}").green());
-                        // get sender channel associated with the request-response id
-                        let sender = self.pending.request_fragments
-                            .remove(&request_id)
-                            .expect("request_response: Request pending.");
-
-                        // send fragment to it
-                        sender.send(cfrag_bytes).ok();
+                        // get sender channel associated with the request-response id,
+                        // gone if the request was swept as stale before the response arrived
+                        match self.pending.request_fragments.remove(&request_id) {
+                            Some(pending_request) => {
+                                pending_request.sender.send(cfrag_bytes).ok();
+                            }
+                            None => warn!("RequestId({}) no longer pending for response from {}", request_id, peer_name),
+                        }
                     }
                     FragmentResponseEnum::ProvidingFragmentResponse => {
                         info!("{}", format!("RequestId({request_id}) Received ProvidingFragmentResponse from {peer_name}").green());
@@ -346,20 +429,108 @@ impl NetworkEvents {
                     FragmentResponseEnum::SaveFragmentResponse => {
                         info!("{}", format!("RequestId({request_id}) Received SaveFragmentResponse from {peer_name}").green());
                     }
+                    FragmentResponseEnum::SaveFragmentFailedResponse(e) => {
+                        warn!("RequestId({}) {} rejected keyfrag: {}", request_id, peer_name, e);
+                    }
                     FragmentResponseEnum::SaveCiphertextResponse => {
                         info!("{}", format!("RequestId({request_id}) Received SaveCiphertextResponse from {peer_name}").green());
+                        self.record_shutdown_rebroadcast_response(&request_id, true);
+                    }
+                    FragmentResponseEnum::SaveCiphertextChunkResponse => {
+                        debug!("{}", format!("RequestId({request_id}) Received SaveCiphertextChunkResponse from {peer_name}"));
+                        self.record_shutdown_rebroadcast_response(&request_id, true);
+                    }
+                    FragmentResponseEnum::SaveCiphertextFailedResponse(e) => {
+                        warn!("RequestId({}) {} rejected ciphertext: {}", request_id, peer_name, e);
+                        self.record_shutdown_rebroadcast_response(&request_id, false);
+                    }
+                    FragmentResponseEnum::GetCiphertextResponse(reverie_msg) => {
+                        info!("{}", format!("RequestId({request_id}) Received GetCiphertextResponse from {peer_name}").green());
+                        match self.pending.request_reveries.remove(&request_id) {
+                            Some(sender) => {
+                                let reverie_msg = reverie_msg
+                                    .map(|reverie_msg| self.peer_manager.verify_kfrag_providers(reverie_msg));
+                                sender.send(reverie_msg).ok();
+                            }
+                            None => warn!("RequestId({}) no longer pending for response from {}", request_id, peer_name),
+                        }
+                    }
+                    FragmentResponseEnum::StandDownVesselResponse => {
+                        info!("{}", format!("RequestId({request_id}) Received StandDownVesselResponse from {peer_name}").green());
+                    }
+                    FragmentResponseEnum::HandoffVesselResponse(respawn_id) => {
+                        info!("{}", format!("RequestId({request_id}) Received HandoffVesselResponse from {peer_name}").green());
+                        match self.pending.vessel_handoffs.remove(&request_id) {
+                            Some(sender) => {
+                                sender.send(respawn_id).ok();
+                            }
+                            None => warn!("RequestId({}) no longer pending for response from {}", request_id, peer_name),
+                        }
                     }
                     FragmentResponseEnum::MarkRespawnCompleteResponse => {
                         info!("{}", format!("RequestId({request_id}) Received MarkRespawnCompleteResponse from {peer_name}").green());
                     }
+                    FragmentResponseEnum::RespawnVoteResponse(respawn_id, offline) => {
+                        info!("{}", format!("RequestId({request_id}) Received RespawnVoteResponse from {peer_name}: offline={offline}").green());
+                        self.pending.respawn_vote_requests.remove(&request_id);
+                        if let Err(e) = self.record_respawn_vote(respawn_id, peer, offline).await {
+                            warn!("{} Failed to record respawn vote from {}: {}", self.nname(), peer_name, e);
+                        }
+                    }
                 }
             },
-            Event::InboundFailure { .. } => {}
+            Event::InboundFailure { request_id, error, peer, .. } => {
+                // includes requests that fail to decode, e.g. a mismatched REVERIE_WIRE_VERSION
+                warn!("{} RequestId({}) from {} failed: {}", self.nname(), request_id, get_node_name2(&peer), error);
+            }
             Event::OutboundFailure { request_id, error, peer, ..  } => {
+                self.peer_manager.update_peer_reputation(peer, ReputationEvent::RequestTimeout);
+                if self.record_shutdown_rebroadcast_response(&request_id, false) {
+                    warn!("{} Re-broadcast to {} failed: {}", self.nname(), get_node_name2(&peer), error);
+                    return Ok(())
+                }
+                if let Some(sender) = self.pending.request_reveries.remove(&request_id) {
+                    sender.send(Err(SendError(error.to_string()))).ok();
+                    return Ok(())
+                }
+                if let Some(sender) = self.pending.vessel_handoffs.remove(&request_id) {
+                    sender.send(Err(SendError(error.to_string()))).ok();
+                    return Ok(())
+                }
+                if let Some(respawn_id) = self.pending.respawn_vote_requests.remove(&request_id) {
+                    warn!("{} Respawn vote request to {} failed, counting an abstention: {}", self.nname(), get_node_name2(&peer), error);
+                    return self.record_respawn_abstention(respawn_id, peer).await
+                }
                 match self.pending.request_fragments.remove(&request_id) {
                     None => tracing::warn!("RequestId({}) not found for {}", request_id, peer),
-                    Some(sender) => {
-                        sender.send(Err(SendError(error.to_string()))).ok();
+                    Some(pending_request) => {
+                        let can_retry = matches!(error, OutboundFailure::Timeout)
+                            && pending_request.attempts <= self.network_config.cfrag_request_max_retries;
+
+                        if can_retry {
+                            warn!(
+                                "{} RequestId({}) to {} timed out, retrying (attempt {})",
+                                self.nname(),
+                                request_id,
+                                get_node_name2(&peer),
+                                pending_request.attempts + 1
+                            );
+                            let retry_request_id = self.swarm.behaviour_mut()
+                                .request_response
+                                .send_request(
+                                    &pending_request.kfrag_provider_peer_id,
+                                    FragmentRequestEnum::GetFragmentRequest(
+                                        pending_request.reverie_id.clone(),
+                                        pending_request.access_key.clone()
+                                    )
+                                );
+                            self.pending.request_fragments.insert(retry_request_id, PendingFragmentRequest {
+                                attempts: pending_request.attempts + 1,
+                                ..pending_request
+                            });
+                        } else {
+                            pending_request.sender.send(Err(SendError(error.to_string()))).ok();
+                        }
                     }
                 }
             }
@@ -369,3 +540,143 @@ impl NetworkEvents {
     }
 }
 
+impl NetworkEvents {
+    /// Checks an inbound GetFragmentRequest's access key against the access condition
+    /// of the cfrag held for `reverie_id`, returning the cfrag if access is granted
+    async fn authorize_fragment_request(
+        &mut self,
+        reverie_id: ReverieId,
+        access_key: &AccessKey,
+    ) -> Result<ReverieCapsulefrag> {
+
+        let cfrag = self.peer_manager.get_releasable_cfrags(
+            &reverie_id,
+            chrono::Utc::now().timestamp()
+        )?.clone();
+
+        // TODO: refactor to a AccessCondition / Auth module for generalizing access control and conditions
+        // Verify signature is from intended recipient/target before sending capsule fragment
+        // TODO: add nonce and timestamp to digest
+        match access_key {
+            AccessKey::NearContract(
+                contract_account_id,
+                spender_account_id,
+                amount
+            ) => {
+
+                let decision_key = AccessDecisionKey {
+                    reverie_id: reverie_id.clone(),
+                    condition: format!("near:{}", contract_account_id),
+                    spender: spender_account_id.clone(),
+                    amount: *amount,
+                };
+                // Only contracts on the node's trusted list are asked
+                let can_spend = self.access_decisions.decide(
+                    decision_key,
+                    Instant::now(),
+                    || self.near_runtime.can_spend_on_trusted_contract(
+                        contract_account_id,
+                        &reverie_id,
+                        spender_account_id,
+                        *amount
+                    )
+                ).await?;
+
+                if can_spend {
+                    info!("{}", format!("Near Contract Access granted!").green());
+                } else {
+                    return Err(anyhow!("Near Contract Access denied!"));
+                }
+            }
+            AccessKey::EcdsaSignature(signature) => {
+                match access_key.verify_access(&cfrag.access_condition, &reverie_id) {
+                    false => return Err(anyhow!("Invalid signature for fragment request for {reverie_id}".to_string())),
+                    true => info!("{}", format!("Signature verified!").green())
+                }
+            }
+            AccessKey::UmbralSignature(signature) => {
+                match access_key.verify_access(&cfrag.access_condition, &reverie_id) {
+                    false => return Err(anyhow!("Invalid signature for fragment request for {reverie_id}".to_string())),
+                    true => info!("{}", format!("Signature verified!").green())
+                }
+            }
+            AccessKey::P256Signature(signature) => {
+                match access_key.verify_access(&cfrag.access_condition, &reverie_id) {
+                    false => return Err(anyhow!("Invalid signature for fragment request for {reverie_id}".to_string())),
+                    true => info!("{}", format!("Signature verified!").green())
+                }
+            }
+            AccessKey::Ed25519Signature(signature) => {
+                return Err(anyhow!("Ed25519 signatures are not implemented yet"));
+            }
+            AccessKey::EthContract(
+                contract_address,
+                contract_method_name,
+                contract_args
+            ) => {
+                return Err(anyhow!("EthContract access keys are not implemented yet"));
+            }
+            AccessKey::EthEvent {
+                contract_address,
+                event_signature,
+                reverie_id: paid_reverie_id,
+                payer,
+                ..
+            } => {
+                // The event must come from the contract and event named in the reverie's access condition
+                match &cfrag.access_condition {
+                    AccessCondition::EthEvent(expected_address, expected_signature)
+                        if expected_address == contract_address && expected_signature == event_signature => {}
+                    _ => return Err(anyhow!("EthEvent access key does not match access condition for {reverie_id}")),
+                }
+                // Don't accept a payment made for a different reverie
+                if *paid_reverie_id != reverie_id {
+                    return Err(anyhow!("EthEvent access key is for {paid_reverie_id}, not {reverie_id}"));
+                }
+                // Only the payer can claim their payment event
+                if !access_key.verify_payer(&reverie_id) {
+                    return Err(anyhow!("EthEvent access key is not signed by payer {payer} for {reverie_id}"));
+                }
+
+                // Payment events are bound to the payer, not an amount
+                let decision_key = AccessDecisionKey {
+                    reverie_id: reverie_id.clone(),
+                    condition: format!("{}:{}", contract_address, event_signature),
+                    spender: payer.to_string(),
+                    amount: 0,
+                };
+                let has_event = self.access_decisions.decide(
+                    decision_key,
+                    Instant::now(),
+                    || self.evm_runtime.has_event_log(
+                        *contract_address,
+                        event_signature,
+                        &reverie_id,
+                        *payer
+                    )
+                ).await?;
+
+                if has_event {
+                    info!("{}", format!("EthEvent Access granted!").green());
+                } else {
+                    return Err(anyhow!("EthEvent Access denied!"));
+                }
+            }
+        }
+
+        Ok(cfrag)
+    }
+
+    /// Responds to an inbound request, logging rather than failing if the peer has
+    /// disconnected, so a peer can't take the node down by hanging up early
+    fn send_inbound_response(
+        &mut self,
+        channel: request_response::ResponseChannel<FragmentResponseEnum>,
+        response: FragmentResponseEnum,
+        peer: &PeerId,
+    ) {
+        if let Err(response) = self.swarm.behaviour_mut().request_response.send_response(channel, response) {
+            warn!("{} Failed to send {:?} to {}, connection closed", self.nname(), response, get_node_name2(peer));
+        }
+    }
+}
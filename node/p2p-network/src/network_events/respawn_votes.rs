@@ -0,0 +1,148 @@
+use std::collections::HashSet;
+use libp2p::PeerId;
+
+use crate::types::AgentVesselInfo;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum RespawnVoteOutcome {
+    /// Not enough votes yet either way
+    Pending,
+    /// A majority sees the vessel offline, the agent can be respawned
+    Confirmed,
+    /// A majority can't be reached even if every remaining peer votes offline
+    Rejected,
+}
+
+/// A round of votes on whether a vessel has failed, started by its next vessel.
+/// The next vessel's own observation counts as the first offline vote, and a majority
+/// of it plus the peers asked must see the vessel offline before the agent is respawned.
+/// Peers that could not be asked abstain, which counts against a respawn.
+pub(super) struct RespawnVote {
+    pub(super) agent_vessel: AgentVesselInfo,
+    electorate: HashSet<PeerId>,
+    offline_votes: HashSet<PeerId>,
+    online_votes: HashSet<PeerId>,
+    abstentions: HashSet<PeerId>,
+}
+
+impl RespawnVote {
+    pub(super) fn new(agent_vessel: AgentVesselInfo, electorate: HashSet<PeerId>) -> Self {
+        Self {
+            agent_vessel,
+            electorate,
+            offline_votes: HashSet::new(),
+            online_votes: HashSet::new(),
+            abstentions: HashSet::new(),
+        }
+    }
+
+    pub(super) fn quorum(&self) -> usize {
+        (self.electorate.len() + 1) / 2 + 1
+    }
+
+    /// Records a peer's vote, returning the new outcome. Returns None if the peer wasn't
+    /// asked, already voted, or the vote was already decided.
+    pub(super) fn record_vote(&mut self, voter: PeerId, offline: bool) -> Option<RespawnVoteOutcome> {
+        if !self.can_vote(&voter) {
+            return None
+        }
+        match offline {
+            true => self.offline_votes.insert(voter),
+            false => self.online_votes.insert(voter),
+        };
+        Some(self.outcome())
+    }
+
+    /// Records that a peer's RespawnVoteRequest failed, so it won't vote.
+    /// Returns None under the same conditions as `record_vote`.
+    pub(super) fn record_abstention(&mut self, voter: PeerId) -> Option<RespawnVoteOutcome> {
+        if !self.can_vote(&voter) {
+            return None
+        }
+        self.abstentions.insert(voter);
+        Some(self.outcome())
+    }
+
+    fn can_vote(&self, voter: &PeerId) -> bool {
+        self.outcome() == RespawnVoteOutcome::Pending
+            && self.electorate.contains(voter)
+            && !self.offline_votes.contains(voter)
+            && !self.online_votes.contains(voter)
+            && !self.abstentions.contains(voter)
+    }
+
+    pub(super) fn outcome(&self) -> RespawnVoteOutcome {
+        // includes this node's own vote
+        let offline = self.offline_votes.len() + 1;
+        let undecided = self.electorate.len()
+            - self.offline_votes.len()
+            - self.online_votes.len()
+            - self.abstentions.len();
+        if offline >= self.quorum() {
+            RespawnVoteOutcome::Confirmed
+        } else if offline + undecided < self.quorum() {
+            RespawnVoteOutcome::Rejected
+        } else {
+            RespawnVoteOutcome::Pending
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{ReverieNameWithNonce, ReverieType};
+
+    fn agent_vessel() -> AgentVesselInfo {
+        AgentVesselInfo {
+            reverie_id: "reverie_1234".to_string(),
+            reverie_type: ReverieType::SovereignAgent(ReverieNameWithNonce("auron".to_string(), 0)),
+            threshold: 2,
+            total_frags: 3,
+            current_vessel_peer_id: PeerId::random(),
+            next_vessel_peer_id: PeerId::random(),
+        }
+    }
+
+    #[test]
+    fn partitioned_minority_cannot_confirm_respawn() {
+        let peers: Vec<PeerId> = (0..4).map(|_| PeerId::random()).collect();
+        let mut vote = RespawnVote::new(agent_vessel(), peers.iter().cloned().collect());
+        assert_eq!(vote.quorum(), 3);
+
+        // only one other peer, on the same side of the partition, sees the vessel fail
+        assert_eq!(vote.record_vote(peers[0], true), Some(RespawnVoteOutcome::Pending));
+        assert_eq!(vote.record_vote(peers[1], false), Some(RespawnVoteOutcome::Pending));
+        // repeated votes aren't counted twice
+        assert_eq!(vote.record_vote(peers[0], true), None);
+        assert_eq!(vote.record_vote(PeerId::random(), true), None);
+        assert_eq!(vote.record_vote(peers[2], false), Some(RespawnVoteOutcome::Rejected));
+        // a late offline vote can't turn a rejected vote into a respawn
+        assert_eq!(vote.record_vote(peers[3], true), None);
+        assert_eq!(vote.outcome(), RespawnVoteOutcome::Rejected);
+    }
+
+    #[test]
+    fn majority_confirms_respawn() {
+        let peers: Vec<PeerId> = (0..4).map(|_| PeerId::random()).collect();
+        let mut vote = RespawnVote::new(agent_vessel(), peers.iter().cloned().collect());
+        assert_eq!(vote.record_vote(peers[0], true), Some(RespawnVoteOutcome::Pending));
+        assert_eq!(vote.record_vote(peers[1], true), Some(RespawnVoteOutcome::Confirmed));
+
+        // with no other peers, the next vessel's own observation is a majority
+        let vote = RespawnVote::new(agent_vessel(), HashSet::new());
+        assert_eq!(vote.outcome(), RespawnVoteOutcome::Confirmed);
+    }
+
+    #[test]
+    fn unreachable_peers_abstain() {
+        let peers: Vec<PeerId> = (0..4).map(|_| PeerId::random()).collect();
+        let mut vote = RespawnVote::new(agent_vessel(), peers.iter().cloned().collect());
+        assert_eq!(vote.record_vote(peers[0], true), Some(RespawnVoteOutcome::Pending));
+        assert_eq!(vote.record_abstention(peers[1]), Some(RespawnVoteOutcome::Pending));
+        // an abstaining peer can't vote later
+        assert_eq!(vote.record_vote(peers[1], true), None);
+        // abstentions still count against a respawn, so the vote can't hang on them
+        assert_eq!(vote.record_abstention(peers[2]), Some(RespawnVoteOutcome::Rejected));
+    }
+}
@@ -0,0 +1,128 @@
+use std::collections::{HashMap, HashSet};
+use std::hash::Hash;
+use libp2p::{request_response::OutboundRequestId, PeerId};
+use tokio::time::Instant;
+
+use crate::types::ReverieId;
+
+/// What the event loop does once a re-broadcast settles
+pub(super) enum AfterRebroadcast {
+    /// Internal heartbeat failure: delete vessel secrets if confirmed, then restart the container
+    Restart,
+    /// Graceful shutdown: exit the event loop
+    Exit,
+}
+
+/// Vessel reveries sent to their kfrag providers before this node gives up its copies.
+/// A reverie is confirmed once any provider acknowledges every request sent to it,
+/// one per ciphertext chunk for large reveries. Driven to completion by the event loop.
+pub(super) struct ShutdownRebroadcast<R = OutboundRequestId> {
+    pub(super) deadline: Instant,
+    pub(super) then: AfterRebroadcast,
+    requests: HashMap<R, (ReverieId, PeerId)>,
+    failed: HashSet<(ReverieId, PeerId)>,
+    unconfirmed: HashSet<ReverieId>,
+}
+
+impl<R: Eq + Hash> ShutdownRebroadcast<R> {
+    pub(super) fn new(then: AfterRebroadcast, deadline: Instant) -> Self {
+        Self {
+            deadline,
+            then,
+            requests: HashMap::new(),
+            failed: HashSet::new(),
+            unconfirmed: HashSet::new(),
+        }
+    }
+
+    /// Adds a reverie that must be confirmed before vessel secrets can be deleted
+    pub(super) fn add_reverie(&mut self, reverie_id: ReverieId) {
+        self.unconfirmed.insert(reverie_id);
+    }
+
+    pub(super) fn add_request(&mut self, request_id: R, reverie_id: ReverieId, kfrag_provider: PeerId) {
+        self.requests.insert(request_id, (reverie_id, kfrag_provider));
+    }
+
+    /// Records a kfrag provider's response or failure.
+    /// Returns false if the request isn't part of this re-broadcast.
+    pub(super) fn record_response(&mut self, request_id: &R, acknowledged: bool) -> bool {
+        let sent_to = match self.requests.remove(request_id) {
+            Some(sent_to) => sent_to,
+            None => return false,
+        };
+        if !acknowledged {
+            self.failed.insert(sent_to);
+            return true
+        }
+        let awaiting_provider = self.requests.values().any(|pending| pending == &sent_to);
+        if !awaiting_provider && !self.failed.contains(&sent_to) {
+            self.unconfirmed.remove(&sent_to.0);
+        }
+        true
+    }
+
+    pub(super) fn is_confirmed(&self) -> bool {
+        self.unconfirmed.is_empty()
+    }
+
+    pub(super) fn unconfirmed(&self) -> usize {
+        self.unconfirmed.len()
+    }
+
+    /// Every reverie is confirmed, every provider has answered, or the deadline has passed
+    pub(super) fn is_settled(&self, now: Instant) -> bool {
+        self.is_confirmed() || self.requests.is_empty() || now >= self.deadline
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::time::Duration;
+
+    fn new_rebroadcast() -> ShutdownRebroadcast<u32> {
+        ShutdownRebroadcast::new(AfterRebroadcast::Exit, Instant::now() + Duration::from_secs(60))
+    }
+
+    #[test]
+    fn reverie_is_confirmed_once_a_provider_acknowledges_every_chunk() {
+        let (provider_a, provider_b) = (PeerId::random(), PeerId::random());
+        let mut rebroadcast = new_rebroadcast();
+        rebroadcast.add_reverie("reverie_1".to_string());
+        // two chunks to each provider
+        rebroadcast.add_request(1, "reverie_1".to_string(), provider_a);
+        rebroadcast.add_request(2, "reverie_1".to_string(), provider_a);
+        rebroadcast.add_request(3, "reverie_1".to_string(), provider_b);
+        rebroadcast.add_request(4, "reverie_1".to_string(), provider_b);
+
+        // provider_a rejects a chunk, so its copy doesn't count
+        assert!(rebroadcast.record_response(&1, false));
+        assert!(rebroadcast.record_response(&2, true));
+        assert!(rebroadcast.record_response(&3, true));
+        assert!(!rebroadcast.is_confirmed());
+        assert!(!rebroadcast.is_settled(Instant::now()));
+
+        assert!(rebroadcast.record_response(&4, true));
+        assert!(rebroadcast.is_confirmed());
+        assert!(rebroadcast.is_settled(Instant::now()));
+        // unknown requests are ignored
+        assert!(!rebroadcast.record_response(&5, true));
+    }
+
+    #[test]
+    fn unanswered_rebroadcast_settles_unconfirmed() {
+        let mut rebroadcast = new_rebroadcast();
+        rebroadcast.add_reverie("reverie_1".to_string());
+        rebroadcast.add_request(1, "reverie_1".to_string(), PeerId::random());
+        assert!(!rebroadcast.is_settled(Instant::now()));
+        assert!(rebroadcast.is_settled(rebroadcast.deadline));
+        assert!(!rebroadcast.is_confirmed());
+
+        // a reverie with no kfrag providers to send to is never confirmed
+        let mut rebroadcast = new_rebroadcast();
+        rebroadcast.add_reverie("reverie_2".to_string());
+        assert!(rebroadcast.is_settled(Instant::now()));
+        assert!(!rebroadcast.is_confirmed());
+    }
+}
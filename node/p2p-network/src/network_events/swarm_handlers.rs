@@ -1,11 +1,17 @@
 use color_eyre::Result;
 use colored::Colorize;
+use libp2p::{dcutr, mdns, relay};
 use libp2p::swarm::SwarmEvent;
-use libp2p::swarm::DialError;
+use libp2p::swarm::{DialError, ListenError};
 use tracing::{trace, info, warn, debug};
 
 use crate::behaviour::BehaviourEvent;
 use crate::get_node_name2;
+use crate::types::{
+    FragmentRequestEnum,
+    NetworkEvent,
+    ReputationEvent,
+};
 use super::NetworkEvents;
 
 
@@ -30,9 +36,25 @@ impl NetworkEvents {
 
             //// Heartbeat Protocol events
             SwarmEvent::Behaviour(BehaviourEvent::Heartbeat(tee_event)) => {
-                self.peer_manager.update_peer_heartbeat(
+                if let Some(event) = self.peer_manager.detect_duplicate_vessel(&tee_event.peer_id) {
+                    self.handle_duplicate_vessel(event).await?;
+                }
+                if let Err(e) = self.peer_manager.update_peer_heartbeat(
+                    tee_event.peer_id,
+                    tee_event.latest_tee_attestation,
+                    self.swarm.behaviour().heartbeat.current_heartbeat_payload.block_height,
+                    self.network_config.heartbeat_block_height_tolerance,
+                ) {
+                    warn!("{} Ignoring heartbeat: {}", self.nname(), e);
+                    self.peer_manager.update_peer_reputation(
+                        tee_event.peer_id,
+                        ReputationEvent::ImplausibleHeartbeat
+                    );
+                    return Ok(())
+                }
+                self.peer_manager.update_peer_reputation(
                     tee_event.peer_id,
-                    tee_event.latest_tee_attestation
+                    ReputationEvent::TimelyHeartbeat
                 );
 
                 if let Some(tee_str) = self.peer_manager.make_heartbeat_tee_log(tee_event.peer_id) {
@@ -52,6 +74,45 @@ impl NetworkEvents {
                 }
             }
 
+            //// mDNS events for zero-config peer discovery on the local network
+            SwarmEvent::Behaviour(BehaviourEvent::Mdns(mdns::Event::Discovered(peers))) => {
+                for (peer_id, addr) in peers {
+                    debug!("{} mDNS discovered peer: {:?} at {}", self.nname(), peer_id, addr);
+                    self.swarm.behaviour_mut().kademlia.add_address(&peer_id, addr.clone());
+                    if !self.swarm.is_connected(&peer_id) {
+                        if let Err(e) = self.swarm.dial(addr) {
+                            warn!("{} Failed to dial mDNS peer {:?}: {}", self.nname(), peer_id, e);
+                        }
+                    }
+                }
+            }
+            SwarmEvent::Behaviour(BehaviourEvent::Mdns(mdns::Event::Expired(peers))) => {
+                for (peer_id, addr) in peers {
+                    debug!("{} mDNS record expired for peer: {:?} at {}", self.nname(), peer_id, addr);
+                }
+            }
+
+            //// Relay client events for NAT'd nodes reachable through a relay's circuit
+            SwarmEvent::Behaviour(BehaviourEvent::RelayClient(relay_event)) => match relay_event {
+                relay::client::Event::ReservationReqAccepted { relay_peer_id, renewal, .. } => {
+                    if !renewal {
+                        info!("{} Reserved relay circuit on {}", self.nname(), get_node_name2(&relay_peer_id));
+                    }
+                }
+                relay::client::Event::OutboundCircuitEstablished { relay_peer_id, .. } => {
+                    debug!("{} Outbound relay circuit established via {}", self.nname(), get_node_name2(&relay_peer_id));
+                }
+                relay::client::Event::InboundCircuitEstablished { src_peer_id, .. } => {
+                    debug!("{} Inbound relay circuit established from {}", self.nname(), get_node_name2(&src_peer_id));
+                }
+            },
+
+            //// DCUtR events, upgrading relayed connections to direct ones
+            SwarmEvent::Behaviour(BehaviourEvent::Dcutr(dcutr::Event { remote_peer_id, result })) => match result {
+                Ok(_) => info!("{} Hole punched direct connection to {}", self.nname(), get_node_name2(&remote_peer_id)),
+                Err(e) => warn!("{} Hole punch to {} failed, staying relayed: {}", self.nname(), get_node_name2(&remote_peer_id), e),
+            },
+
             //// Swarm connection events
             SwarmEvent::NewListenAddr { address, .. } => {
                 debug!("{} {}", self.nname(), format!("New listen address: {}", address));
@@ -67,6 +128,15 @@ impl NetworkEvents {
                 self.peer_manager.insert_peer_info(peer_id);
             }
             SwarmEvent::ExpiredListenAddr { .. } => { }
+            SwarmEvent::IncomingConnectionError { peer_id, error: ListenError::Denied { cause }, .. } => {
+                warn!("{} Denied incoming connection from peer {:?}: {}", self.nname(), peer_id, cause);
+                // Don't keep state for peers we refused and aren't otherwise connected to
+                if let Some(peer_id) = peer_id {
+                    if !self.swarm.is_connected(&peer_id) {
+                        self.peer_manager.remove_peer_info(&peer_id);
+                    }
+                }
+            }
             SwarmEvent::IncomingConnectionError { .. } => { }
             SwarmEvent::OutgoingConnectionError { peer_id, error, .. } => {
                 let err_msg = match error {
@@ -91,5 +161,23 @@ impl NetworkEvents {
 
         Ok(())
     }
-}
 
+    /// Tells the rejoining vessel to stand down, since its agent already lives in another vessel
+    async fn handle_duplicate_vessel(&mut self, event: NetworkEvent) -> Result<()> {
+        if let NetworkEvent::DuplicateVesselDetected { agent_name, peer_id } = &event {
+            tracing::error!("{}", format!(
+                "{} DUPLICATE VESSEL: {} rejoined still holding reincarnated agent {}. Instructing it to stand down. ({} detected)",
+                self.nname(),
+                get_node_name2(peer_id),
+                agent_name,
+                self.peer_manager.duplicate_vessels_detected
+            ).red());
+
+            self.swarm.behaviour_mut()
+                .request_response
+                .send_request(peer_id, FragmentRequestEnum::StandDownVesselRequest(agent_name.clone()));
+        }
+        self.send_network_event(event).await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,130 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use tokio::sync::mpsc;
+use tracing::warn;
+use super::NodeCommand;
+
+/// Warn once queued commands reach this fraction of the channel's capacity
+const NEAR_FULL_RATIO: f64 = 0.9;
+
+/// Bounded sender for NodeCommands to the network event loop, which tracks how many
+/// commands are queued or waiting on a full channel, so backpressure is observable.
+#[derive(Debug, Clone)]
+pub struct CommandSender {
+    sender: mpsc::Sender<NodeCommand>,
+    // callers blocked in send() waiting for the channel to free up capacity
+    waiting_senders: Arc<AtomicUsize>,
+}
+
+/// Decrements the waiting count when send() completes or its future is dropped
+struct WaitingGuard<'a>(&'a AtomicUsize);
+
+impl Drop for WaitingGuard<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+impl CommandSender {
+    pub fn new(sender: mpsc::Sender<NodeCommand>) -> Self {
+        Self {
+            sender,
+            waiting_senders: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
+    pub async fn send(&self, command: NodeCommand) -> Result<(), mpsc::error::SendError<NodeCommand>> {
+        self.warn_if_near_full();
+        self.waiting_senders.fetch_add(1, Ordering::SeqCst);
+        let _guard = WaitingGuard(&self.waiting_senders);
+        self.sender.send(command).await
+    }
+
+    pub fn try_send(&self, command: NodeCommand) -> Result<(), mpsc::error::TrySendError<NodeCommand>> {
+        self.warn_if_near_full();
+        self.sender.try_send(command)
+    }
+
+    /// Resolves once the network event loop has dropped its command receiver
+    pub async fn closed(&self) {
+        self.sender.closed().await
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.sender.max_capacity()
+    }
+
+    /// Commands queued in the channel, not yet picked up by the network event loop
+    pub fn queued(&self) -> usize {
+        self.sender.max_capacity() - self.sender.capacity()
+    }
+
+    /// Queued commands plus callers still waiting for space in the channel
+    pub fn pending_depth(&self) -> usize {
+        self.queued() + self.waiting_senders.load(Ordering::SeqCst)
+    }
+
+    pub fn metrics(&self) -> serde_json::Value {
+        serde_json::json!({
+            "capacity": self.capacity(),
+            "queued": self.queued(),
+            "pending_depth": self.pending_depth(),
+        })
+    }
+
+    fn warn_if_near_full(&self) {
+        let queued = self.queued();
+        if queued as f64 >= self.capacity() as f64 * NEAR_FULL_RATIO {
+            warn!(
+                "NodeCommand channel near full: {}/{} queued, {} senders waiting",
+                queued,
+                self.capacity(),
+                self.waiting_senders.load(Ordering::SeqCst)
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use tokio::sync::oneshot;
+
+    fn get_node_state() -> NodeCommand {
+        let (sender, _receiver) = oneshot::channel();
+        NodeCommand::GetNodeState { sender }
+    }
+
+    #[tokio::test]
+    async fn pending_depth_reflects_filled_channel() {
+        let (sender, mut receiver) = mpsc::channel(4);
+        let command_sender = CommandSender::new(sender);
+        assert_eq!(command_sender.pending_depth(), 0);
+
+        for _ in 0..4 {
+            command_sender.send(get_node_state()).await.unwrap();
+        }
+        assert_eq!(command_sender.queued(), 4);
+        assert_eq!(command_sender.pending_depth(), 4);
+        assert!(command_sender.try_send(get_node_state()).is_err());
+
+        // A 5th send blocks on the full channel and is counted as pending
+        let blocked_sender = command_sender.clone();
+        let blocked = tokio::spawn(async move {
+            blocked_sender.send(get_node_state()).await.unwrap();
+        });
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(command_sender.pending_depth(), 5);
+
+        // Draining the channel unblocks the waiting sender
+        receiver.recv().await.unwrap();
+        blocked.await.unwrap();
+        assert_eq!(command_sender.pending_depth(), 4);
+
+        while command_sender.queued() > 0 {
+            receiver.recv().await.unwrap();
+        }
+        assert_eq!(command_sender.pending_depth(), 0);
+    }
+}
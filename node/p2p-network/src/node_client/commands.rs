@@ -1,4 +1,4 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use color_eyre::{Result, eyre::anyhow};
 use libp2p::{
     request_response::ResponseChannel,
@@ -8,18 +8,28 @@ use libp2p::{
 use tokio::sync::{mpsc, oneshot};
 use crate::SendError;
 use crate::types::{
+    ConnectedPeer,
     ReverieNameWithNonce,
     FragmentNumber,
     FragmentResponseEnum,
     ReverieKeyfragMessage,
     NodeKeysWithVesselStatus,
+    NodeReadiness,
+    PendingRequestStats,
+    RespawnId,
     ReverieId,
     ReverieMessage,
     ReverieType,
+    ReverieTypeEntriesKey,
+    ReverieTypeIndexEntry,
+    ReverieHolderKey,
+    SignedReverieHolder,
     AgentVesselInfo,
     AccessKey,
+    ReputationEvent,
 };
 use super::container_manager::RestartReason;
+use runtime::reencrypt::UmbralKey;
 
 
 pub enum NodeCommand {
@@ -36,6 +46,18 @@ pub enum NodeCommand {
         sender: oneshot::Sender<Option<ReverieId>>,
     },
 
+    /// Gets the vessels providing a ReverieType kind's index key from Kademlia
+    GetReverieTypeProviders {
+        reverie_type: ReverieType,
+        sender: oneshot::Sender<HashSet<PeerId>>,
+    },
+
+    /// Gets the ReverieIds of a ReverieType kind a vessel holds from Kademlia
+    GetReverieTypeEntries {
+        entries_key: ReverieTypeEntriesKey,
+        sender: oneshot::Sender<Vec<ReverieTypeIndexEntry>>,
+    },
+
     /// Gets the Reverie for an agent from Kademlia
     GetReverie {
         reverie_id: ReverieId,
@@ -60,6 +82,25 @@ pub enum NodeCommand {
         reverie_msg: ReverieMessage,
     },
 
+    /// Gets the peers providing a Reverie's holder key on Kademlia
+    GetReverieHolders {
+        reverie_id: ReverieId,
+        sender: oneshot::Sender<HashSet<PeerId>>,
+    },
+
+    /// Gets a holder's signed record of the Reverie copy it holds from Kademlia
+    GetReverieHolderRecord {
+        holder_key: ReverieHolderKey,
+        sender: oneshot::Sender<SignedReverieHolder>,
+    },
+
+    /// Requests a holder's copy of a Reverie
+    RequestReverieFromHolder {
+        reverie_id: ReverieId,
+        holder_peer_id: PeerId,
+        sender: oneshot::Sender<Result<ReverieMessage, SendError>>,
+    },
+
     /// Request Capsule Fragments for threshold decryption
     RequestCapsuleFragment {
         reverie_id: ReverieId,
@@ -73,11 +114,53 @@ pub enum NodeCommand {
         sender: oneshot::Sender<Result<String, Box<dyn std::error::Error + Send>>>,
     },
 
+    /// Re-dials bootstrap addresses if no peer is connected, otherwise re-runs Kademlia
+    /// bootstrap. Replies true once connected and the bootstrap query has started.
+    RetryBootstrap {
+        bootstrap_addrs: Vec<Multiaddr>,
+        sender: oneshot::Sender<bool>,
+    },
+
     SimulateNodeFailure {
         sender: oneshot::Sender<RestartReason>,
         reason: RestartReason,
     },
 
+    /// Reincarnates an agent in this node (its next vessel) as if its current vessel had failed
+    ForceReincarnate {
+        agent_name_nonce: ReverieNameWithNonce,
+        sender: oneshot::Sender<Result<RespawnId>>,
+    },
+
+    /// Asks the chosen successor vessel to reincarnate an agent this node is handing off
+    RequestVesselHandoff {
+        agent_name_nonce: ReverieNameWithNonce,
+        target_peer_id: PeerId,
+        sender: oneshot::Sender<Result<RespawnId, SendError>>,
+    },
+
+    /// Drops this node's agent and its ciphertexts once another vessel has taken it over
+    ReleaseVesselAgent {
+        agent_name_nonce: ReverieNameWithNonce,
+        sender: oneshot::Sender<Vec<ReverieId>>,
+    },
+
+    /// Gets the agent this node is currently the vessel for
+    GetVesselAgent {
+        sender: oneshot::Sender<Option<AgentVesselInfo>>,
+    },
+
+    /// Gets Reveries this node holds as the target vessel
+    GetVesselReveries {
+        sender: oneshot::Sender<Vec<ReverieMessage>>,
+    },
+
+    /// Replaces the node's Umbral key and republishes its pubkeys on Kademlia
+    RotateUmbralKey {
+        umbral_key: UmbralKey,
+        sender: oneshot::Sender<Result<()>>,
+    },
+
     GetNodeState {
         sender: oneshot::Sender<serde_json::Value>,
     },
@@ -86,6 +169,37 @@ pub enum NodeCommand {
         responder: oneshot::Sender<Vec<PeerId>>,
     },
 
+    /// Gets heartbeat recency and reputation of each connected peer
+    GetConnectedPeerInfo {
+        sender: oneshot::Sender<Vec<ConnectedPeer>>,
+    },
+
+    GetReadiness {
+        sender: oneshot::Sender<NodeReadiness>,
+    },
+
+    /// Gets the count and oldest age of each kind of pending network request
+    GetPendingRequests {
+        sender: oneshot::Sender<BTreeMap<String, PendingRequestStats>>,
+    },
+
+    /// Gets the confirmed kfrag providers of a Reverie, by the fragment they hold
+    GetKfragProvidersByFragNum {
+        reverie_id: ReverieId,
+        sender: oneshot::Sender<HashMap<FragmentNumber, HashSet<PeerId>>>,
+    },
+
+    /// Gets the reputation score of each known peer
+    GetPeerReputations {
+        sender: oneshot::Sender<HashMap<PeerId, f64>>,
+    },
+
+    /// Records an event that raises or lowers a peer's reputation
+    ReportPeerReputation {
+        peer_id: PeerId,
+        event: ReputationEvent,
+    },
+
     MarkPendingRespawnComplete {
         prev_reverie_id: ReverieId,
         prev_peer_id: PeerId,
@@ -28,6 +28,7 @@ use elliptic_curve::pkcs8::DecodePublicKey;
 use crate::utils::pubkeys::generate_peer_keys;
 use crate::env_var::EnvVars;
 use llm_proxy::api_key_delegation_server::{
+    AddApiKeyRequest,
     ApiKeyPayload,
     ApiKeyIdentifier,
 };
@@ -154,6 +155,7 @@ impl NodeClient {
         api_key: String,
         spender: String,
         spender_type: String,
+        overwrite: bool,
     ) -> Result<()> {
 
         println!("Attempting to add API key to proxy for reverie_id: {}", reverie_id);
@@ -173,15 +175,18 @@ impl NodeClient {
             "/add_api_key"
         );
 
-        let payload = ApiKeyPayload::new(reverie_id.clone(), api_key_type, api_key, spender, spender_type);
-        let body_bytes = serde_json::to_vec(&payload)?;
+        let request = AddApiKeyRequest {
+            payload: ApiKeyPayload::new(reverie_id.clone(), api_key_type, api_key, spender, spender_type),
+            overwrite,
+        };
+        let body_bytes = serde_json::to_vec(&request)?;
 
         let signature_headers = create_request_signature_headers(node_id_keypair, "POST", "/add_api_key", &body_bytes)?;
 
         let response = client
             .post(&proxy_internal_api_url)
             .headers(signature_headers)
-            .json(&payload)
+            .json(&request)
             .send()
             .await
             .map_err(|e| {
@@ -197,7 +202,7 @@ impl NodeClient {
             })?;
 
         if response.status().is_success() {
-            info!("Successfully added API key via proxy for reverie_id: {}", payload.reverie_id);
+            info!("Successfully added API key via proxy for reverie_id: {}", request.payload.reverie_id);
             Ok(())
         } else {
             let status = response.status();
@@ -8,19 +8,23 @@ use std::str::FromStr;
 use sha3::{Digest, Keccak256};
 use runtime::llm::{
     MCPToolUsageMetrics,
-    call_anthropic,
-    call_deepseek,
+    McpPluginConfig,
+    LlmProvider,
+    call_llm_with_fallback,
 };
 use runtime::near_runtime::{
     AccessCondition as NearRuntimeAccessCondition,
     ReverieMetadata as NearReverieMetadata,
 };
 use crate::types::{
+    PreKeyfragParams,
     Reverie,
     ReverieId,
     ReverieType,
     AccessCondition as P2PNetworkAccessCondition,
     AccessKey,
+    create_spawn_challenge,
+    verify_spawn_signature,
 };
 use crate::env_var::EnvVars;
 use crate::usage_db::{UsageDbPool, read_usage_data_for_reverie};
@@ -28,17 +32,47 @@ use super::NodeClient;
 
 // ===============================================
 
-#[derive(Deserialize, Debug, Clone, Serialize)]
-pub struct AnthropicQuery {
-    pub prompt: String,
-    pub tools: Option<serde_json::Value>,
-    pub stream: Option<bool>,
-}
+pub use runtime::llm::AnthropicQuery;
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct ExecuteWithMemoryReverieResult {
     pub claude: Option<serde_json::Value>,
     pub deepseek: Option<serde_json::Value>,
+    // Provider that answered the query, after falling back past any failed providers
+    #[serde(default)]
+    pub served_by: Option<LlmProvider>,
+    // Token and MCP tool usage across the LLM calls made
+    #[serde(default)]
+    pub tool_metrics: MCPToolUsageMetrics,
+}
+
+/// Keys whose values are redacted when displaying decrypted Reverie contents
+pub const REDACTED_SECRET_KEYS: [&str; 6] = [
+    "anthropic_api_key",
+    "openai_api_key",
+    "deepseek_api_key",
+    "secret_key",
+    "private_key",
+    "credentials",
+];
+
+/// Recursively replaces the values of known secret keys with "[REDACTED]"
+pub fn redact_secrets(value: &mut serde_json::Value) {
+    match value {
+        serde_json::Value::Object(map) => {
+            for (key, v) in map.iter_mut() {
+                if REDACTED_SECRET_KEYS.contains(&key.as_str()) && !v.is_null() {
+                    *v = serde_json::Value::String("[REDACTED]".to_string());
+                } else {
+                    redact_secrets(v);
+                }
+            }
+        }
+        serde_json::Value::Array(values) => {
+            values.iter_mut().for_each(redact_secrets);
+        }
+        _ => {}
+    }
 }
 
 // ===============================================
@@ -52,28 +86,103 @@ impl NodeClient {
         threshold: usize,
         total_frags: usize,
         access_condition: P2PNetworkAccessCondition, // access condition for using the memory
+        spawn_signature: Option<AccessKey>, // proves the caller controls the access condition's key
+        preferred_kfrag_providers: Vec<libp2p::PeerId>, // trusted peers to hold keyfrags, if available
+        description: String,
+        tags: Vec<String>,
+        keyfrag_params: PreKeyfragParams,
+        expires_at: Option<i64>, // unix timestamp after which kfrag providers stop releasing cfrags
+    ) -> Result<Reverie> {
+        self.spawn_secrets_reverie(
+            memory_secrets,
+            ReverieType::Memory,
+            threshold,
+            total_frags,
+            access_condition,
+            spawn_signature,
+            preferred_kfrag_providers,
+            description,
+            tags,
+            keyfrag_params,
+            expires_at,
+        ).await
+    }
+
+    /// Encrypts an MCP plugin config (endpoints, credentials and tools) with PRE and
+    /// broadcasts its fragments, so access to the plugin can be rented out
+    /// without revealing its credentials.
+    pub async fn spawn_mcp_plugin_reverie(
+        &mut self,
+        mcp_plugin: McpPluginConfig,
+        threshold: usize,
+        total_frags: usize,
+        access_condition: P2PNetworkAccessCondition, // access condition for using the plugin
+        spawn_signature: Option<AccessKey>, // signs the plugin config, as JSON
+        preferred_kfrag_providers: Vec<libp2p::PeerId>,
+        description: String,
+        tags: Vec<String>,
+        keyfrag_params: PreKeyfragParams,
+        expires_at: Option<i64>, // unix timestamp after which kfrag providers stop releasing cfrags
+    ) -> Result<Reverie> {
+        mcp_plugin.validate()?;
+        self.spawn_secrets_reverie(
+            serde_json::to_value(&mcp_plugin)?,
+            ReverieType::McpPlugin,
+            threshold,
+            total_frags,
+            access_condition,
+            spawn_signature,
+            preferred_kfrag_providers,
+            description,
+            tags,
+            keyfrag_params,
+            expires_at,
+        ).await
+    }
+
+    async fn spawn_secrets_reverie(
+        &mut self,
+        memory_secrets: serde_json::Value,
+        reverie_type: ReverieType,
+        threshold: usize,
+        total_frags: usize,
+        access_condition: P2PNetworkAccessCondition,
+        spawn_signature: Option<AccessKey>,
+        preferred_kfrag_providers: Vec<libp2p::PeerId>,
+        description: String,
+        tags: Vec<String>,
+        keyfrag_params: PreKeyfragParams,
+        expires_at: Option<i64>, // unix timestamp after which kfrag providers stop releasing cfrags
     ) -> Result<Reverie> {
 
         if threshold > total_frags {
             return Err(anyhow!("Threshold must be less than or equal to total fragments"));
         }
 
+        // Reject reveries bound to keys the caller doesn't control
+        let spawn_challenge = create_spawn_challenge(&memory_secrets, &access_condition);
+        verify_spawn_signature(spawn_signature.as_ref(), &access_condition, &spawn_challenge)?;
+
         // get list of target vessel and kfrag provider nodes
         let (
             target_vessel,
             target_kfrag_providers
-        ) = self.get_prospect_vessels(false).await?;
+        ) = self.get_prospect_vessels(false, &preferred_kfrag_providers).await?;
 
         // 1. Create a "Reverie"––an encrypted memory or executable
         let reverie = self.create_reverie(
             memory_secrets,
-            ReverieType::Memory,
+            reverie_type,
+            description,
+            tags,
             threshold,
             total_frags,
             target_vessel.umbral_public_key,
             target_vessel.umbral_verifying_public_key,
             access_condition // access_condition to be checked to request cfrags
-        )?;
+        )?
+        .with_keyfrag_params(keyfrag_params)
+        .with_expiry(expires_at);
 
         // 2a. Write Reverie metadata onchain
         if let P2PNetworkAccessCondition::NearContract(_, _, _) = &reverie.access_condition {
@@ -110,7 +219,8 @@ impl NodeClient {
         let broadcast_keyfrags_outcome = self.broadcast_reverie_keyfrags(
             &reverie,
             target_vessel.peer_id,
-            target_kfrag_providers
+            target_kfrag_providers,
+            &preferred_kfrag_providers,
         ).await?;
 
         // let (
@@ -126,6 +236,55 @@ impl NodeClient {
         Ok(reverie)
     }
 
+    /// Transfers on-chain ownership of a reverie, the right to revoke or rekey it,
+    /// from this node's NEAR signer to `new_owner`
+    pub async fn transfer_reverie_ownership(
+        &self,
+        reverie_id: &ReverieId,
+        new_owner: &str,
+    ) -> Result<()> {
+        let env_vars = EnvVars::load();
+        let outcome = self.near_runtime.transfer_reverie_ownership(
+            &env_vars.NEAR.NEAR_CONTRACT_ACCOUNT_ID,
+            &env_vars.NEAR.NEAR_SIGNER_ACCOUNT_ID,
+            &env_vars.NEAR.NEAR_SIGNER_PRIVATE_KEY,
+            reverie_id,
+            new_owner,
+        ).await?;
+        info!("transfer_reverie_ownership NEAR outcome: {:?}", outcome.status);
+        match outcome.status {
+            near_primitives::views::FinalExecutionStatus::SuccessValue(_) => Ok(()),
+            status => Err(anyhow!("transfer_reverie_ownership NEAR transaction failed: {:?}", status)),
+        }
+    }
+
+    /// Checks this node's NEAR signer currently owns the reverie on-chain,
+    /// before revoking or rekeying it
+    pub(crate) async fn ensure_reverie_owner(&self, reverie_id: &ReverieId) -> Result<()> {
+        let env_vars = EnvVars::load();
+        self.near_runtime.ensure_reverie_owner(
+            &env_vars.NEAR.NEAR_CONTRACT_ACCOUNT_ID,
+            reverie_id,
+            &env_vars.NEAR.NEAR_SIGNER_ACCOUNT_ID,
+        ).await
+    }
+
+    /// Revokes a reverie on-chain. Refused unless this node's NEAR signer still owns it.
+    pub async fn revoke_reverie(&self, reverie_id: &ReverieId) -> Result<()> {
+        let env_vars = EnvVars::load();
+        let outcome = self.near_runtime.revoke_reverie(
+            &env_vars.NEAR.NEAR_CONTRACT_ACCOUNT_ID,
+            &env_vars.NEAR.NEAR_SIGNER_ACCOUNT_ID,
+            &env_vars.NEAR.NEAR_SIGNER_PRIVATE_KEY,
+            reverie_id,
+        ).await?;
+        info!("revoke_reverie NEAR outcome: {:?}", outcome.status);
+        match outcome.status {
+            near_primitives::views::FinalExecutionStatus::SuccessValue(_) => Ok(()),
+            status => Err(anyhow!("revoke_reverie NEAR transaction failed: {:?}", status)),
+        }
+    }
+
     // This needs to happen within the TEE as there is a decryption step, and the original
     // plaintext is revealed within the TEE, before executing with it as context
     async fn _reconstruct_memory_reverie<T: Serialize + DeserializeOwned>(
@@ -137,31 +296,35 @@ impl NodeClient {
     ) -> Result<(T, AccessKey)> {
 
         let reverie_msg = self.get_reverie(reverie_id, reverie_type).await?;
-        let capsule = reverie_msg.reverie.encode_capsule()?;
-        let keyfrag_providers = reverie_msg.keyfrag_providers.clone();
-
-        let cfrags_raw = self.request_cfrags(
-            reverie_id,
-            keyfrag_providers,
+        let next_agent_secrets = self.reconstruct_reverie(
+            &reverie_msg,
             access_key.clone()
-        ).await;
+        ).await?;
 
-        let (
-            verified_cfrags,
-            source_pubkey,
-            target_verifying_pubkey, // target public intended to decrypt the ciphertext
-            access_condition,
-            total_frags_received
-        ) = self.parse_cfrags(cfrags_raw, capsule.clone())?;
+        Ok((next_agent_secrets, access_key))
+    }
 
-        let next_agent_secrets = self.decrypt_cfrags(
-            capsule,
-            reverie_msg.reverie.umbral_ciphertext,
-            source_pubkey,
-            verified_cfrags
-        )?;
+    /// Requests cfrags and decrypts a Reverie, returning its contents
+    /// with known secret keys redacted.
+    pub async fn decrypt_reverie(
+        &mut self,
+        reverie_id: ReverieId,
+        reverie_type: ReverieType,
+        access_key: AccessKey,
+    ) -> Result<serde_json::Value> {
 
-        Ok((next_agent_secrets, access_key))
+        let (
+            mut reverie_json,
+            _access_key
+        ) = self._reconstruct_memory_reverie::<serde_json::Value>(
+            &reverie_id,
+            reverie_type,
+            self.node_id.peer_id,
+            access_key
+        ).await?;
+
+        redact_secrets(&mut reverie_json);
+        Ok(reverie_json)
     }
 
     pub async fn delegate_api_key(
@@ -199,6 +362,7 @@ impl NodeClient {
                 anthropic_api_key.to_string(),
                 spenders_address.to_string(),
                 spenders_address.get_type(),
+                false,
             ).await?;
         } else {
             return Err(anyhow!("No CA certificate found. Delegation failed."));
@@ -228,33 +392,114 @@ impl NodeClient {
         // Then execute LLM with Reverie as context:
         // 1. Test LLM API key from decrypted Reverie works
         println!("Decrypted secret memory, querying Claude with private contexts...");
-        let node_keypair = &self.node_id.id_keys.clone();
         let secret_context = memory_secrets_json["memories"].to_string();
 
-        // API Key must already be delegated to the vessel
-        let claude_result = match call_anthropic(
-            &anthropic_query.prompt,
-            &secret_context,
-            anthropic_query.tools,
-            anthropic_query.stream.unwrap_or(false),
+        Ok(self.execute_llm_query(&anthropic_query, &secret_context).await)
+    }
+
+    /// Decrypts an MCP plugin Reverie in this node's TEE
+    pub async fn load_mcp_plugin_reverie(
+        &mut self,
+        reverie_id: ReverieId,
+        access_key: AccessKey,
+    ) -> Result<McpPluginConfig> {
+        let (
+            mcp_plugin,
+            _access_key
+        ) = self._reconstruct_memory_reverie::<McpPluginConfig>(
+            &reverie_id,
+            ReverieType::McpPlugin,
+            self.node_id.peer_id,
+            access_key
+        ).await?;
+
+        mcp_plugin.validate()?;
+        Ok(mcp_plugin)
+    }
+
+    /// Decrypts an MCP plugin Reverie and queries the LLM with the plugin's tools added
+    /// to the query's tools. The plugin's endpoints and credentials stay in the TEE.
+    pub async fn execute_with_mcp_plugin_reverie(
+        &mut self,
+        reverie_id: ReverieId,
+        access_key: AccessKey,
+        anthropic_query: AnthropicQuery
+    ) -> Result<ExecuteWithMemoryReverieResult> {
+
+        let mcp_plugin = self.load_mcp_plugin_reverie(reverie_id, access_key).await?;
+        info!("Loaded {} tools from MCP plugin '{}'", mcp_plugin.tools.len(), mcp_plugin.name);
+
+        let anthropic_query = anthropic_query.with_mcp_plugin_tools(&mcp_plugin)?;
+        anthropic_query.validate_tools()?;
+
+        Ok(self.execute_llm_query(&anthropic_query, "").await)
+    }
+
+    async fn execute_llm_query(
+        &self,
+        anthropic_query: &AnthropicQuery,
+        secret_context: &str,
+    ) -> ExecuteWithMemoryReverieResult {
+
+        let mut tool_metrics = MCPToolUsageMetrics::default();
+
+        // API Key must already be delegated to the vessel.
+        // Falls back to the next configured provider (e.g. DeepSeek) if Anthropic fails.
+        let mut claude_result: Option<serde_json::Value> = None;
+        let mut deepseek_result: Option<serde_json::Value> = None;
+        let mut served_by: Option<LlmProvider> = None;
+
+        match call_llm_with_fallback(
+            &self.llm_server,
+            anthropic_query,
+            secret_context,
+            &mut tool_metrics,
         ).await {
-            Ok(result) => {
-                info!("\n{} {}\n", "Claude:".bright_black(), result.text.yellow());
-                serde_json::to_value(result).ok()
+            Ok(fallback) => {
+                info!("\n{} {}\n", format!("{}:", fallback.provider).bright_black(), fallback.result.text.yellow());
+                let result = serde_json::to_value(fallback.result).ok();
+                match fallback.provider {
+                    LlmProvider::Anthropic => claude_result = result,
+                    LlmProvider::DeepSeek => deepseek_result = result,
+                }
+                served_by = Some(fallback.provider);
             },
             Err(e) => {
-                warn!("Failed to call Anthropic API: {}", e);
-                None
+                warn!("Failed to call LLM providers: {}", e);
             }
         };
 
-        // 2. Skip DeepSeek API even if key is available
-        let deepseek_result: Option<serde_json::Value> = None;
-
-        Ok(ExecuteWithMemoryReverieResult {
+        ExecuteWithMemoryReverieResult {
             claude: claude_result,
             deepseek: deepseek_result,
-        })
+            served_by,
+            tool_metrics,
+        }
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn redact_secrets_replaces_nested_secret_keys() {
+        let mut value = serde_json::json!({
+            "anthropic_api_key": "sk-ant-secret",
+            "deepseek_api_key": null,
+            "memories": [{ "name": "First Beach Trip" }],
+            "corekey_ed25519": {
+                "public_key": "pubkey",
+                "secret_key": "seckey",
+            },
+        });
+
+        redact_secrets(&mut value);
+
+        assert_eq!(value["anthropic_api_key"], "[REDACTED]");
+        assert!(value["deepseek_api_key"].is_null());
+        assert_eq!(value["memories"][0]["name"], "First Beach Trip");
+        assert_eq!(value["corekey_ed25519"]["public_key"], "pubkey");
+        assert_eq!(value["corekey_ed25519"]["secret_key"], "[REDACTED]");
+    }
+}
@@ -1,16 +1,21 @@
 mod commands;
+mod command_channel;
 mod network_events_listener;
 mod llm_proxy_client;
 mod reincarnation;
+mod reverie_backup;
+mod reconstruction_metrics;
 pub mod usage_verification;
 pub(crate) mod memories;
 pub(crate) mod container_manager;
 
 pub use commands::NodeCommand;
+pub use command_channel::CommandSender;
+pub use reconstruction_metrics::{ReconstructionMetrics, ReconstructionStage};
 pub use container_manager::{ContainerManager, RestartReason};
 use futures::future::ok;
 
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::str::Split;
 use std::sync::Arc;
 use std::pin::Pin;
@@ -21,9 +26,9 @@ use futures::FutureExt;
 use futures::pin_mut;
 use hex;
 use libp2p::{core::Multiaddr, PeerId};
-use tokio::sync::{mpsc, oneshot};
+use tokio::sync::{broadcast, mpsc, oneshot, watch};
 use tokio::sync::RwLock;
-use tracing::{info, debug, error, warn};
+use tracing::{info, debug, error, warn, info_span, Instrument};
 use rand::seq::SliceRandom;
 use rand::thread_rng;
 use sha3::{Digest, Keccak256};
@@ -33,14 +38,22 @@ use p256::ecdsa::{
     Signature as P256Signature
 };
 use base64::{Engine as _, engine::general_purpose::STANDARD as base64_standard};
+use zeroize::{Zeroize, Zeroizing};
 
 use crate::{get_node_name, short_peer_id, TryPeerId};
 use crate::network_events::NodeIdentity;
 use crate::types::{
+    AgentVesselInfo,
+    ConnectedPeer,
     ReverieNameWithNonce,
     NetworkEvent,
+    NodeEvent,
+    NODE_EVENT_CHANNEL_CAPACITY,
     NodeKeysWithVesselStatus,
+    NodeReadiness,
+    PendingRequestStats,
     RespawnId,
+    check_reverie_payload_size,
     Reverie,
     ReverieCapsulefrag,
     ReverieId,
@@ -48,35 +61,49 @@ use crate::types::{
     ReverieKeyfragMessage,
     ReverieMessage,
     ReverieType,
+    ReverieTypeEntriesKey,
+    ReverieTypeIndexEntry,
+    ReverieHolderKey,
+    SignedReverieHolder,
+    REVERIE_WIRE_VERSION,
     VesselStatus,
     AccessCondition,
     AccessKey,
+    DistributionPolicy,
+    FragmentNumber,
+    ReputationEvent,
+    DEFAULT_REPUTATION,
 };
 use crate::SendError;
 use crate::behaviour::heartbeat_behaviour::TeePayloadOutEvent;
-use crate::node_client::usage_verification::verify_usage_report;
-use crate::usage_db::{UsageDbPool, store_usage_payload};
+use crate::node_client::usage_verification::{verify_usage_report, InFlightUsageReports};
+use crate::usage_db::{UsageDbPool, UsageStore, store_usage_payload};
 use crate::env_var::EnvVars;
 
 use runtime::reencrypt::{UmbralKey, VerifiedCapsuleFrag};
-use runtime::llm::AgentSecretsJson;
+use runtime::llm::{AgentSecretsJson, LlmServer};
 use llm_proxy::usage::SignedUsageReport;
 use crate::utils::pubkeys::encode_libp2p_pubkey_to_pem;
 use std::path::Path;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use tokio::time::timeout as tokio_timeout;
 use libp2p::identity::Keypair as IdentityKeypair;
 use nanoid;
 use std::process::Command;
 use regex::Regex;
 
-use runtime::near_runtime::NearRuntime;
+use runtime::near_runtime::{NearRuntime, ReverieMetadata};
+
+/// Timeout for each Kademlia lookup made by `list_reveries_by_type`
+const REVERIE_TYPE_INDEX_QUERY_TIMEOUT: Duration = Duration::from_secs(10);
 
 // Define a simple error type for NodeClient operations, can be expanded
 #[derive(Debug)]
 pub enum NodeClientError {
     CommandSendError(String),
     ResponseReceiveError(String),
+    /// Fewer than `threshold` cfrags survived verification, more can be re-requested
+    InsufficientValidCfrags { received: usize, threshold: usize },
     Other(String), // For miscellaneous errors
 }
 
@@ -85,6 +112,9 @@ impl std::fmt::Display for NodeClientError {
         match self {
             NodeClientError::CommandSendError(s) => write!(f, "CommandSendError: {}", s),
             NodeClientError::ResponseReceiveError(s) => write!(f, "ResponseReceiveError: {}", s),
+            NodeClientError::InsufficientValidCfrags { received, threshold } => {
+                write!(f, "InsufficientValidCfrags: {} valid cfrags, need {}", received, threshold)
+            }
             NodeClientError::Other(s) => write!(f, "NodeClientError: {}", s),
         }
     }
@@ -99,20 +129,83 @@ impl From<SendError> for NodeClientError {
     }
 }
 
+/// Outcome of broadcasting a Reverie's keyfrags to its kfrag providers
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KeyfragBroadcastReport {
+    /// Fragments held by a provider, including ones sent by an earlier broadcast
+    pub placed: Vec<(FragmentNumber, PeerId)>,
+    /// Fragments with no connected provider to hold them
+    pub failed: Vec<FragmentNumber>,
+}
+
+/// Keyfrags generated and sent per Reverie. Retried broadcasts reuse the same keyfrags,
+/// as cfrags from different keyfrag sets can't be combined, and skip ones already sent.
+#[derive(Debug, Default)]
+struct KeyfragBroadcasts {
+    keyfrags: HashMap<ReverieId, Vec<ReverieKeyfrag>>,
+    sent: HashSet<(ReverieId, FragmentNumber, PeerId)>,
+    // target vessel of each broadcast Reverie, notified by providers of replacement keyfrags
+    vessels: HashMap<ReverieId, PeerId>,
+    // broadcast Reveries, kept for export as a ReverieBundle
+    reveries: HashMap<ReverieId, Reverie>,
+}
+
+impl KeyfragBroadcasts {
+    /// Stops tracking a broadcast Reverie, zeroizing its keyfrags
+    fn forget(&mut self, reverie_id: &ReverieId) {
+        if let Some(mut kfrags) = self.keyfrags.remove(reverie_id) {
+            for kfrag in kfrags.iter_mut() {
+                kfrag.umbral_keyfrag.zeroize();
+            }
+        }
+        self.sent.retain(|(id, ..)| id != reverie_id);
+        self.vessels.remove(reverie_id);
+        self.reveries.remove(reverie_id);
+    }
+}
+
+/// A Reverie broadcast by this node with fragments to re-send to fresh providers
+struct UnderProvidedReverie {
+    reverie_id: ReverieId,
+    target_vessel_peer_id: PeerId,
+    keyfrags: Vec<ReverieKeyfrag>,
+    lost_frag_nums: Vec<FragmentNumber>,
+    providers: HashSet<PeerId>,
+}
+
 #[derive(Clone)]
 pub struct NodeClient {
     pub node_id: NodeIdentity,
-    pub command_sender: mpsc::Sender<NodeCommand>,
+    pub command_sender: CommandSender,
     // hb subscriptions for rpc clients
     pub heartbeat_receiver: async_channel::Receiver<TeePayloadOutEvent>,
-    // keep private in TEE
-    umbral_key: UmbralKey,
+    // lifecycle event subscriptions for rpc clients
+    node_event_sender: broadcast::Sender<NodeEvent>,
+    // keep private in TEE, shared between clones so key rotation is seen by all of them
+    umbral_key: Arc<std::sync::RwLock<UmbralKey>>,
     // Proxy's public key for verifying usage reports
     pub llm_proxy_public_key: Arc<RwLock<Option<P256VerifyingKey>>>,
     // Proxy's Hudsucker CA certificate PEM for establishing TLS connections with llm-proxy
     pub llm_proxy_ca_cert: Arc<RwLock<Option<reqwest::Certificate>>>,
     pub usage_db_pool: UsageDbPool,
+    // Spend per reverie and spender, recorded from verified usage reports
+    pub usage_store: UsageStore,
+    // Usage reports being charged, so retried copies of a report are only charged once
+    usage_reports_in_flight: InFlightUsageReports,
     pub near_runtime: Arc<NearRuntime>,
+    // Python LLM server used to execute queries with memory reveries
+    pub llm_server: LlmServer,
+    // Max size in bytes of secrets encrypted into a Reverie
+    pub max_reverie_payload_size: usize,
+    // Connected empty vessels required beyond total_frags to spawn a Reverie
+    pub spawn_peer_margin: usize,
+    // Stage durations of reconstructing Reveries from cfrags, and the total allowed
+    pub reconstruction_metrics: ReconstructionMetrics,
+    pub reconstruction_sla: Duration,
+    // Dedups keyfrags sent by broadcast_reverie_keyfrags
+    keyfrag_broadcasts: Arc<std::sync::Mutex<KeyfragBroadcasts>>,
+    // Signals the network event loop to shut down
+    shutdown_sender: Arc<watch::Sender<bool>>,
 }
 
 impl NodeClient {
@@ -121,40 +214,115 @@ impl NodeClient {
         command_sender: mpsc::Sender<NodeCommand>,
         umbral_key: UmbralKey,
         heartbeat_receiver: async_channel::Receiver<TeePayloadOutEvent>,
+        node_event_sender: broadcast::Sender<NodeEvent>,
         usage_db_pool: UsageDbPool,
         near_runtime: Arc<NearRuntime>,
+        max_reverie_payload_size: usize,
+        spawn_peer_margin: usize,
+        reconstruction_sla: Duration,
+        shutdown_sender: watch::Sender<bool>,
     ) -> Self {
         Self {
             node_id,
-            command_sender,
+            command_sender: CommandSender::new(command_sender),
             heartbeat_receiver,
-            umbral_key,
+            node_event_sender,
+            umbral_key: Arc::new(std::sync::RwLock::new(umbral_key)),
             llm_proxy_public_key: Arc::new(RwLock::new(None)),
             llm_proxy_ca_cert: Arc::new(RwLock::new(None)),
+            usage_store: UsageStore::new(usage_db_pool.clone()),
+            usage_reports_in_flight: InFlightUsageReports::default(),
             usage_db_pool,
             near_runtime,
+            llm_server: LlmServer::from_env(),
+            max_reverie_payload_size,
+            spawn_peer_margin,
+            reconstruction_metrics: ReconstructionMetrics::default(),
+            reconstruction_sla,
+            keyfrag_broadcasts: Arc::new(std::sync::Mutex::new(KeyfragBroadcasts::default())),
+            shutdown_sender: Arc::new(shutdown_sender),
         }
     }
 
-    pub fn create_reverie<T: Serialize + DeserializeOwned>(
-        &mut self,
+    /// Current Umbral key. Cloned out of the lock, as `rotate_umbral_key` may swap it.
+    fn umbral_key(&self) -> UmbralKey {
+        self.umbral_key.read().expect("umbral_key lock poisoned").clone()
+    }
+
+    /// Signs a digest with this node's Umbral signer, as an AccessKey::UmbralSignature.
+    pub fn sign_digest(&self, digest: &[u8]) -> AccessKey {
+        let umbral_signature = self.umbral_key().sign(digest);
+        AccessKey::UmbralSignature(
+            serde_json::to_vec(&umbral_signature)
+                .expect("Failed to serialize umbral signature")
+        )
+    }
+
+    /// Signs the Keccak256 digest of a reverie_id, the access signature kfrag providers
+    /// check against an AccessCondition::Umbral before returning cfrags.
+    pub fn sign_access(&self, reverie_id: &ReverieId) -> AccessKey {
+        let digest = Keccak256::digest(reverie_id.as_bytes());
+        self.sign_digest(&digest)
+    }
+
+    pub fn create_reverie<T: Serialize>(
+        &self,
         secrets: T,
         reverie_type: ReverieType,
+        description: String,
+        tags: Vec<String>,
         threshold: usize,
         total_frags: usize,
         target_public_key: umbral_pre::PublicKey,
         verifying_public_key: umbral_pre::PublicKey,
         access_condition: AccessCondition,
     ) -> Result<Reverie> {
+        self.create_reverie_with_key(
+            &self.umbral_key(),
+            secrets,
+            reverie_type,
+            description,
+            tags,
+            threshold,
+            total_frags,
+            target_public_key,
+            verifying_public_key,
+            access_condition,
+        )
+    }
+
+    /// Encrypts a Reverie with the given Umbral key instead of this node's current key,
+    /// e.g. a key staged by `rotate_umbral_key` that hasn't been committed yet.
+    pub(crate) fn create_reverie_with_key<T: Serialize>(
+        &self,
+        umbral_key: &UmbralKey,
+        secrets: T,
+        reverie_type: ReverieType,
+        description: String,
+        tags: Vec<String>,
+        threshold: usize,
+        total_frags: usize,
+        target_public_key: umbral_pre::PublicKey,
+        verifying_public_key: umbral_pre::PublicKey,
+        access_condition: AccessCondition,
+    ) -> Result<Reverie> {
+
+        let plaintext = serde_json::to_vec(&secrets)?;
+        check_reverie_payload_size(plaintext.len(), self.max_reverie_payload_size)?;
 
         let (
             capsule,
             ciphertext
-        ) = self.umbral_key.encrypt_bytes(&serde_json::to_vec(&secrets)?)?;
+        ) = umbral_key.encrypt_bytes(&plaintext)?;
+
+        // Debug builds only: decrypting every payload on spawn is wasteful for large reveries.
+        // Compares raw bytes, so any reverie type (agent secrets or memories) is checked.
+        if cfg!(debug_assertions) {
+            umbral_key.verify_ciphertext_roundtrip(&capsule, &ciphertext, &plaintext)?;
+        }
 
-        // secrets description: generated by an LLM who looks at the secrets/MCP/executable
         let reverie = Reverie::new(
-            "some secret description".to_string(),
+            description,
             reverie_type,
             threshold,
             total_frags,
@@ -163,7 +331,7 @@ impl NodeClient {
             access_condition,
             capsule,
             ciphertext
-        );
+        ).with_tags(tags);
 
         Ok(reverie)
     }
@@ -172,6 +340,11 @@ impl NodeClient {
         self.heartbeat_receiver.clone()
     }
 
+    /// Receives NodeEvents emitted after subscribing
+    pub fn subscribe_node_events(&self) -> broadcast::Receiver<NodeEvent> {
+        self.node_event_sender.subscribe()
+    }
+
     pub async fn get_connected_peers(&self) -> Result<Vec<PeerId>, NodeClientError> {
         let (tx, rx) = oneshot::channel();
         self.command_sender
@@ -185,18 +358,27 @@ impl NodeClient {
     pub fn create_reverie_keyfrags(
         &self,
         reverie: &Reverie,
+    ) -> Result<Vec<ReverieKeyfrag>> {
+        self.create_reverie_keyfrags_with_key(&self.umbral_key(), reverie)
+    }
+
+    /// Generates keyfrags delegating from the given Umbral key rather than this node's current key.
+    pub(crate) fn create_reverie_keyfrags_with_key(
+        &self,
+        umbral_key: &UmbralKey,
+        reverie: &Reverie,
     ) -> Result<Vec<ReverieKeyfrag>> {
         // Alice generates reencryption key fragments for MPC nodes
         info!("Generating {}-of-{} keyfrags for reverie: {}", reverie.threshold, reverie.total_frags, reverie.id);
 
-        let kfrags = self.umbral_key.generate_pre_keyfrags(
+        let kfrags = umbral_key.generate_pre_keyfrags_with_params(
             &reverie.target_public_key,
             reverie.threshold,
             reverie.total_frags,
-            true, // verify kfrag corresponds to a given delegating pubkey
-            false, // verify kfrag belongs to a given target pubkey
+            reverie.keyfrag_params,
         ).iter().enumerate().map(|(i, kfrag)| {
             ReverieKeyfrag {
+                version: REVERIE_WIRE_VERSION,
                 id: reverie.id.clone(),
                 reverie_type: reverie.reverie_type.clone(),
                 frag_num: i,
@@ -204,52 +386,100 @@ impl NodeClient {
                 total_frags: reverie.total_frags,
                 umbral_keyfrag: serde_json::to_vec(&kfrag).expect(""),
                 umbral_capsule: reverie.umbral_capsule.clone(),
-                source_pubkey: self.umbral_key.public_key,
+                source_pubkey: umbral_key.public_key,
                 target_pubkey: reverie.target_public_key,
-                source_verifying_pubkey: self.umbral_key.verifying_public_key,
+                source_verifying_pubkey: umbral_key.verifying_public_key,
                 target_verifying_pubkey: reverie.verifying_public_key,
                 access_condition: reverie.access_condition.clone(),
+                expires_at: reverie.expires_at,
             }
         }).collect::<Vec<ReverieKeyfrag>>();
 
         Ok(kfrags)
     }
 
+    /// Picks a target vessel and candidate kfrag providers from the empty vessels.
+    /// `preferred_kfrag_providers` are only picked as the target vessel when no other
+    /// empty vessel is available, leaving them free to hold keyfrags.
     pub async fn get_prospect_vessels(
         &self,
-        shuffle: bool
+        shuffle: bool,
+        preferred_kfrag_providers: &[PeerId],
     ) -> Result<(NodeKeysWithVesselStatus, Vec<NodeKeysWithVesselStatus>)> {
 
-        let peer_nodes = self.get_node_vessels(shuffle).await
+        let mut peer_nodes = self.get_node_vessels(shuffle).await
             .into_iter()
             .filter(|v| v.vessel_status == VesselStatus::EmptyVessel)
             .collect::<Vec<NodeKeysWithVesselStatus>>();
 
-        let (
-            target_vessel,
-            target_kfrag_providers
-        ) = peer_nodes
-            .split_first()
-            .ok_or(anyhow!("No Peers found."))?;
+        // Prefer reliable peers as the target vessel and kfrag providers
+        let reputations = self.get_peer_reputations().await;
+        sort_by_reputation(&mut peer_nodes, |v| v.peer_id, &reputations);
+        // Stable sort, so reputation order is kept among non-preferred peers
+        peer_nodes.sort_by_key(|v| preferred_kfrag_providers.contains(&v.peer_id));
 
-        if target_kfrag_providers.contains(&target_vessel) {
-            return Err(anyhow!("Target vessel cannot also be a kfrag provider"));
-        }
+        split_target_vessel(peer_nodes)
+    }
+
+    /// Checked before spawning, so a spawn fails before any encryption or keyfrag generation
+    /// when fewer than `total_frags + spawn_peer_margin` connected empty vessels are available
+    /// to hold the keyfrags and ciphertext. Returns the number of connected empty vessels.
+    pub async fn check_enough_spawn_vessels(&self, total_frags: usize) -> Result<usize> {
+        let connected_peers: HashSet<PeerId> = self.get_connected_peers().await
+            .map_err(|e| anyhow!(e.to_string()))?
+            .into_iter()
+            .collect();
 
-        Ok((target_vessel.clone(), target_kfrag_providers.to_vec()))
+        let empty_vessels = self.get_node_vessels(false).await
+            .into_iter()
+            .filter(|v| v.vessel_status == VesselStatus::EmptyVessel && connected_peers.contains(&v.peer_id))
+            .map(|v| v.peer_id)
+            .collect::<HashSet<PeerId>>();
+
+        let required = total_frags + self.spawn_peer_margin;
+        if empty_vessels.len() < required {
+            return Err(anyhow!(
+                "Not enough connected empty vessels to spawn {} keyfrags: need {}, connected {}",
+                total_frags,
+                required,
+                empty_vessels.len()
+            ));
+        }
+        Ok(empty_vessels.len())
     }
 
+    /// Sends one keyfrag to each connected kfrag provider. Providers that disconnected
+    /// since selection are skipped and their fragments reported as failed. Errors if
+    /// fewer than `threshold` fragments can be placed, as the Reverie would be unrecoverable.
+    /// `preferred_kfrag_providers` are given fragments first, other providers only fill
+    /// the remaining fragments if there aren't `total_frags` preferred providers. Errors if
+    /// a preferred provider isn't available to hold a fragment.
     pub async fn broadcast_reverie_keyfrags(
-        &mut self,
+        &self,
         reverie: &Reverie,
         target_vessel_peer_id: PeerId,
-        mut target_kfrag_providers: Vec<NodeKeysWithVesselStatus>
-    ) -> Result<(), SendError> {
-
-        let umbral_ciphertext = reverie.umbral_ciphertext.clone();
+        mut target_kfrag_providers: Vec<NodeKeysWithVesselStatus>,
+        preferred_kfrag_providers: &[PeerId],
+    ) -> Result<KeyfragBroadcastReport, SendError> {
+
+        // A provider holds at most one fragment, and the vessel holds none
+        let mut unique_providers = HashSet::new();
+        target_kfrag_providers.retain(|v| {
+            v.peer_id != target_vessel_peer_id && unique_providers.insert(v.peer_id)
+        });
+        prioritize_preferred_peers(&mut target_kfrag_providers, |v| v.peer_id, preferred_kfrag_providers);
 
-        // Split into fragments
-        let kfrags = self.create_reverie_keyfrags(&reverie)?;
+        let preferred: HashSet<&PeerId> = preferred_kfrag_providers.iter().collect();
+        let available_preferred = target_kfrag_providers.iter()
+            .filter(|v| preferred.contains(&v.peer_id))
+            .count();
+        if available_preferred < preferred.len().min(reverie.total_frags) {
+            return Err(SendError(format!(
+                "Only {} of {} preferred kfrag providers are available to hold keyfrags",
+                available_preferred,
+                preferred.len()
+            )));
+        }
 
         if target_kfrag_providers.len() < reverie.total_frags {
             return Err(SendError(format!(
@@ -263,13 +493,79 @@ impl NodeClient {
                 .take(reverie.total_frags)
                 .collect();
         }
+        let target_kfrag_providers = assign_frag_nums(target_kfrag_providers);
         info!("Kfrag providers: {}", target_kfrag_providers.len());
         info!("Total frags: {}", reverie.total_frags);
 
+        // Split into fragments, reusing keyfrags from an earlier broadcast of this Reverie
+        let cached_kfrags = self.keyfrag_broadcasts.lock().unwrap().keyfrags.get(&reverie.id).cloned();
+        let kfrags = match cached_kfrags {
+            Some(kfrags) => kfrags,
+            None => {
+                let kfrags = self.create_reverie_keyfrags(&reverie)?;
+                self.keyfrag_broadcasts.lock().unwrap().keyfrags.insert(reverie.id.clone(), kfrags.clone());
+                kfrags
+            }
+        };
+
+        // Providers may have disconnected since they were selected
+        let connected_peers: HashSet<PeerId> = self.get_connected_peers().await
+            .map_err(|e| SendError(e.to_string()))?
+            .into_iter()
+            .collect();
+
+        let mut report = KeyfragBroadcastReport::default();
+        let mut placements = vec![];
+        for reverie_keyfrag in kfrags {
+            let frag_num = reverie_keyfrag.frag_num;
+            match target_kfrag_providers.get(frag_num) {
+                Some(provider) if connected_peers.contains(&provider.peer_id) => {
+                    report.placed.push((frag_num, provider.peer_id));
+                    placements.push((reverie_keyfrag, provider.peer_id));
+                }
+                _ => {
+                    warn!("No connected kfrag provider for frag_num({}) of {}", frag_num, reverie.id);
+                    report.failed.push(frag_num);
+                }
+            }
+        }
+
+        if report.placed.len() < reverie.threshold {
+            return Err(SendError(format!(
+                "Only {} of {} required kfrag providers connected, failed frags: {:?}",
+                report.placed.len(),
+                reverie.threshold,
+                report.failed
+            )));
+        }
+
+        // Skip keyfrags a previous broadcast already sent to the same provider
+        let placements = {
+            let mut keyfrag_broadcasts = self.keyfrag_broadcasts.lock().unwrap();
+            keyfrag_broadcasts.vessels.insert(reverie.id.clone(), target_vessel_peer_id);
+            keyfrag_broadcasts.reveries.insert(reverie.id.clone(), reverie.clone());
+            placements.into_iter()
+                .filter(|(reverie_keyfrag, keyfrag_provider)| {
+                    let is_new = keyfrag_broadcasts.sent.insert((
+                        reverie.id.clone(),
+                        reverie_keyfrag.frag_num,
+                        *keyfrag_provider
+                    ));
+                    if !is_new {
+                        debug!("kfrag({}) of {} already sent to {}", reverie_keyfrag.frag_num, reverie.id, short_peer_id(keyfrag_provider));
+                    }
+                    is_new
+                })
+                .collect::<Vec<(ReverieKeyfrag, PeerId)>>()
+        };
+
+        let keyfrag_providers = report.placed.iter()
+            .map(|(_, peer_id)| *peer_id)
+            .collect::<Vec<PeerId>>();
+
         // Create futures for broadcasting Kfrags to peer nodes
         let send_kfrag_futures = futures::future::try_join_all(
-            kfrags.into_iter().enumerate().map(|(i, reverie_keyfrag)| {
-                let keyfrag_provider = target_kfrag_providers[i].peer_id;
+            placements.into_iter().map(|(reverie_keyfrag, keyfrag_provider)| {
                 let source_peer_id = self.node_id.peer_id.clone();
                 self.command_sender.send(
                     NodeCommand::SendReverieKeyfrag {
@@ -284,62 +580,155 @@ impl NodeClient {
             })
         );
 
-        match reverie.reverie_type {
-            ReverieType::SovereignAgent(..) => {
-                // Send Ciphertext to target vessel if ReverieType::SovereignAgent
-                let (f1, f2) = futures::future::join(
-                    // Send all Kfrags
-                    send_kfrag_futures,
-                    // Send Reverie to the target vessel
-                    self.command_sender.send(
-                        NodeCommand::SendReverieToSpecificPeer {
-                            ciphertext_holder: target_vessel_peer_id, // Ciphertext Holder
-                            reverie_msg: ReverieMessage {
-                                reverie: reverie.clone(),
-                                source_peer_id: self.node_id.peer_id,
-                                target_peer_id: target_vessel_peer_id,
-                                keyfrag_providers: target_kfrag_providers.iter()
-                                    .map(|v| v.peer_id).collect(),
-                            },
-                        }
-                    )
-                ).await;
-                // ensure f1, f2 both return Ok(())
-                f1.and(f2).map_err(SendError::from)
-            }
-            _ => {
-                let (f1, f2, f3) = futures::future::join3(
-                    // Send all Kfrags
-                    send_kfrag_futures,
-                    // Save Reverie on DHT for other ReverieTypes (Agent, Memory, Retrieval, Tools)
-                    self.command_sender.send(
-                        NodeCommand::SaveReverieOnNetwork {
-                            reverie_msg: ReverieMessage {
-                                reverie: reverie.clone(),
-                                source_peer_id: self.node_id.peer_id,
-                                target_peer_id: target_vessel_peer_id,
-                                keyfrag_providers: target_kfrag_providers.iter()
-                                    .map(|v| v.peer_id).collect(),
-                            },
-                        }
-                    ),
-                    // Still need to send to the target vessel
-                    // so that it can track prev_vessel node for failure/respawning.
-                    self.command_sender.send(
-                        NodeCommand::SendReverieToSpecificPeer {
-                            ciphertext_holder: target_vessel_peer_id, // Ciphertext Holder
-                            reverie_msg: ReverieMessage {
-                                reverie: reverie.clone(),
+        let policy = DistributionPolicy::from(&reverie.reverie_type);
+        let reverie_msg = ReverieMessage {
+            reverie: reverie.clone(),
+            source_peer_id: self.node_id.peer_id,
+            target_peer_id: target_vessel_peer_id,
+            keyfrag_providers,
+        };
+        let (f1, f2, f3) = futures::future::join3(
+            // Send all Kfrags
+            send_kfrag_futures,
+            // Save Reverie on DHT
+            async {
+                if !policy.save_on_network {
+                    return Ok(());
+                }
+                self.command_sender.send(
+                    NodeCommand::SaveReverieOnNetwork {
+                        reverie_msg: reverie_msg.clone(),
+                    }
+                ).await
+            },
+            // Send Reverie to the target vessel (Ciphertext Holder)
+            async {
+                if !policy.send_to_vessel {
+                    return Ok(());
+                }
+                self.command_sender.send(
+                    NodeCommand::SendReverieToSpecificPeer {
+                        ciphertext_holder: target_vessel_peer_id,
+                        reverie_msg: reverie_msg.clone(),
+                    }
+                ).await
+            },
+        ).await;
+        // ensure f1, f2, f3 all return Ok(())
+        f1.and(f2).and(f3).map(|_| report).map_err(SendError::from)
+    }
+
+    /// Heals Reveries this node broadcast keyfrags for, whose providers have gradually
+    /// dropped off the network. Once fewer than `threshold + safety_margin` fragments are
+    /// held by connected providers, the lost fragments are re-sent to fresh empty vessels.
+    /// The original keyfrags are re-sent, so cfrags from old and new providers still combine.
+    pub async fn replace_lost_kfrag_providers(
+        &self,
+        safety_margin: usize,
+    ) -> Result<HashMap<ReverieId, KeyfragBroadcastReport>> {
+
+        let connected_peers: HashSet<PeerId> = self.get_connected_peers().await
+            .map_err(|e| anyhow!(e.to_string()))?
+            .into_iter()
+            .collect();
+
+        let now = chrono::Utc::now().timestamp();
+        let under_provided = {
+            let keyfrag_broadcasts = self.keyfrag_broadcasts.lock().unwrap();
+            keyfrag_broadcasts.vessels.iter()
+                .filter_map(|(reverie_id, target_vessel_peer_id)| {
+                    let keyfrags = keyfrag_broadcasts.keyfrags.get(reverie_id)?;
+                    let first_keyfrag = keyfrags.first()?;
+                    if first_keyfrag.expires_at.is_some_and(|expires_at| now >= expires_at) {
+                        return None;
+                    }
+                    let placements = keyfrag_broadcasts.sent.iter()
+                        .filter(|(id, ..)| id == reverie_id)
+                        .map(|(_, frag_num, peer_id)| (*frag_num, *peer_id))
+                        .collect::<Vec<(FragmentNumber, PeerId)>>();
+                    let lost_frag_nums = frag_nums_to_replace(
+                        &placements,
+                        &connected_peers,
+                        first_keyfrag.threshold,
+                        first_keyfrag.total_frags,
+                        safety_margin,
+                    );
+                    (!lost_frag_nums.is_empty()).then(|| UnderProvidedReverie {
+                        reverie_id: reverie_id.clone(),
+                        target_vessel_peer_id: *target_vessel_peer_id,
+                        keyfrags: keyfrags.clone(),
+                        lost_frag_nums,
+                        providers: placements.iter().map(|(_, peer_id)| *peer_id).collect(),
+                    })
+                })
+                .collect::<Vec<UnderProvidedReverie>>()
+        };
+        if under_provided.is_empty() {
+            return Ok(HashMap::new());
+        }
+
+        // Fresh providers are connected empty vessels, most reputable first
+        let mut seen_peer_ids = HashSet::new();
+        let mut empty_vessels = self.get_node_vessels(false).await
+            .into_iter()
+            .filter(|v| v.vessel_status == VesselStatus::EmptyVessel)
+            .map(|v| v.peer_id)
+            .filter(|peer_id| *peer_id != self.node_id.peer_id && connected_peers.contains(peer_id))
+            .filter(|peer_id| seen_peer_ids.insert(*peer_id))
+            .collect::<Vec<PeerId>>();
+        let reputations = self.get_peer_reputations().await;
+        sort_by_reputation(&mut empty_vessels, |peer_id| *peer_id, &reputations);
+
+        let mut reports = HashMap::new();
+        for reverie in under_provided {
+            // A provider holds at most one fragment of a Reverie, and the vessel holds none
+            let mut fresh_providers = empty_vessels.iter().filter(|peer_id| {
+                **peer_id != reverie.target_vessel_peer_id && !reverie.providers.contains(peer_id)
+            });
+
+            let mut report = KeyfragBroadcastReport::default();
+            for frag_num in reverie.lost_frag_nums {
+                let reverie_keyfrag = reverie.keyfrags.iter().find(|k| k.frag_num == frag_num);
+                match (reverie_keyfrag, fresh_providers.next()) {
+                    (Some(reverie_keyfrag), Some(keyfrag_provider)) => {
+                        self.command_sender.send(NodeCommand::SendReverieKeyfrag {
+                            keyfrag_provider: *keyfrag_provider,
+                            reverie_keyfrag_msg: ReverieKeyfragMessage {
+                                reverie_keyfrag: reverie_keyfrag.clone(),
                                 source_peer_id: self.node_id.peer_id,
-                                target_peer_id: target_vessel_peer_id,
-                                keyfrag_providers: target_kfrag_providers.iter()
-                                    .map(|v| v.peer_id).collect(),
+                                target_peer_id: reverie.target_vessel_peer_id,
                             },
-                        }
-                    )
-                ).await;
-                // ensure f1, f2, f3 all return Ok(())
-                f1.and(f2).and(f3).map_err(SendError::from)
+                        }).await?;
+                        self.keyfrag_broadcasts.lock().unwrap().sent.insert((
+                            reverie.reverie_id.clone(),
+                            frag_num,
+                            *keyfrag_provider
+                        ));
+                        report.placed.push((frag_num, *keyfrag_provider));
+                    }
+                    _ => report.failed.push(frag_num),
+                }
+            }
+
+            info!("Replaced lost kfrag providers of {}: {:?}", reverie.reverie_id, report);
+            if !report.failed.is_empty() {
+                warn!("No fresh kfrag provider for frags {:?} of {}", report.failed, reverie.reverie_id);
+            }
+            reports.insert(reverie.reverie_id, report);
+        }
+
+        Ok(reports)
+    }
+
+    /// Runs `replace_lost_kfrag_providers` every `sweep_interval`
+    pub async fn run_kfrag_provider_sweeps(&self, sweep_interval: Duration, safety_margin: usize) {
+        let mut sweeper = tokio::time::interval(sweep_interval);
+        // The first tick completes immediately, before any keyfrags are broadcast
+        sweeper.tick().await;
+        loop {
+            sweeper.tick().await;
+            if let Err(e) = self.replace_lost_kfrag_providers(safety_margin).await {
+                warn!("Kfrag provider sweep failed: {}", e);
             }
         }
     }
@@ -365,7 +754,16 @@ impl NodeClient {
         pks
     }
 
+    /// Vessels hold SovereignAgent Reveries locally. Other Reveries are read from their
+    /// holders, falling back to the DHT record if no holder vouches for a copy.
     pub async fn get_reverie(&self, reverie_id: &ReverieId, reverie_type: ReverieType) -> Result<ReverieMessage> {
+        if !matches!(reverie_type, ReverieType::SovereignAgent(..)) {
+            if let Some(reverie_msg) = self.get_reverie_from_holders(reverie_id).await? {
+                return Ok(reverie_msg)
+            }
+            debug!("No holder copies of {}, getting it from the DHT record", reverie_id);
+        }
+
         let (sender, receiver) = oneshot::channel();
 
         self.command_sender
@@ -379,13 +777,113 @@ impl NodeClient {
         receiver.await.map_err(SendError::from)?
     }
 
+    /// Gets the peers holding a copy of a Reverie, from their Kademlia holder records
+    pub async fn get_reverie_holders(&self, reverie_id: &ReverieId) -> Result<HashSet<PeerId>> {
+        let (sender, receiver) = oneshot::channel();
+        self.command_sender
+            .send(NodeCommand::GetReverieHolders {
+                reverie_id: reverie_id.clone(),
+                sender,
+            })
+            .await?;
+
+        Ok(receiver.await.map_err(SendError::from)?)
+    }
+
+    /// Gets a Reverie from every reachable holder instead of a single DHT record, and returns
+    /// the first copy whose ciphertext and capsule match a majority of the copies received.
+    /// A holder's copy only votes if it matches the holder's signed record on Kademlia, so
+    /// peers can't vote without having vouched for a copy. Unreachable or unsigned holders
+    /// are skipped, holders serving a copy other than the one they signed or than the
+    /// majority lose reputation. Returns None if no holder's copy can vote.
+    pub async fn get_reverie_from_holders(&self, reverie_id: &ReverieId) -> Result<Option<ReverieMessage>> {
+        let holders = self.get_reverie_holders(reverie_id).await?;
+
+        let requests = holders.into_iter()
+            .map(|holder_peer_id| {
+                let reverie_id2 = reverie_id.clone();
+                let nc = self.clone();
+                async move {
+                    let (reverie_msg, signed_holder) = futures::join!(
+                        nc.request_reverie_from_holder(&reverie_id2, holder_peer_id),
+                        nc.get_reverie_holder_record(&reverie_id2, holder_peer_id),
+                    );
+                    Ok::<_, Error>((holder_peer_id, reverie_msg?, signed_holder))
+                }.boxed()
+            });
+
+        let mut copies = vec![];
+        for copy in futures::future::join_all(requests).await {
+            match copy {
+                Ok((holder_peer_id, reverie_msg, Some(signed_holder))) => {
+                    if signed_holder.matches(&reverie_msg.reverie) {
+                        copies.push((holder_peer_id, reverie_msg));
+                    } else {
+                        warn!("Holder {} served a copy of {} it didn't sign", get_node_name(&holder_peer_id), reverie_id);
+                        self.report_peer_reputation(holder_peer_id, ReputationEvent::VerificationFailure);
+                    }
+                }
+                Ok((holder_peer_id, _, None)) => {
+                    debug!("No signed holder record of {} from {}", reverie_id, get_node_name(&holder_peer_id));
+                }
+                Err(e) => debug!("Holder copy of {} unavailable: {}", reverie_id, e),
+            }
+        }
+        if copies.is_empty() {
+            return Ok(None)
+        }
+
+        let reverie_msg = select_consistent_reverie(&copies)
+            .ok_or_else(|| anyhow!("No consistent copy of {} among {} holder(s)", reverie_id, copies.len()))?;
+
+        for (holder_peer_id, copy) in &copies {
+            if !same_ciphertext(copy, reverie_msg) {
+                warn!("Holder {} served an inconsistent copy of {}", get_node_name(holder_peer_id), reverie_id);
+                self.report_peer_reputation(*holder_peer_id, ReputationEvent::VerificationFailure);
+            }
+        }
+
+        Ok(Some(reverie_msg.clone()))
+    }
+
+    /// Gets a holder's signed record of its copy of a Reverie, None if it has none
+    async fn get_reverie_holder_record(&self, reverie_id: &ReverieId, holder_peer_id: PeerId) -> Option<SignedReverieHolder> {
+        let (sender, receiver) = oneshot::channel();
+        self.command_sender
+            .send(NodeCommand::GetReverieHolderRecord {
+                holder_key: ReverieHolderKey::new(reverie_id.clone(), holder_peer_id),
+                sender,
+            })
+            .await.ok()?;
+
+        receiver.await.ok()
+    }
+
+    /// Asks one holder for its copy of a Reverie over request-response
+    async fn request_reverie_from_holder(&self, reverie_id: &ReverieId, holder_peer_id: PeerId) -> Result<ReverieMessage> {
+        let (sender, receiver) = oneshot::channel();
+        self.command_sender
+            .send(NodeCommand::RequestReverieFromHolder {
+                reverie_id: reverie_id.clone(),
+                holder_peer_id,
+                sender
+            })
+            .await?;
+
+        Ok(receiver.await.map_err(|e| anyhow!(e.to_string()))??)
+    }
+
     pub async fn request_cfrags(
-        &mut self,
+        &self,
         reverie_id: &ReverieId,
         keyfrag_providers: Vec<PeerId>,
         access_key: AccessKey
     ) -> Vec<Result<Vec<u8>, SendError>> {
 
+        let mut keyfrag_providers = keyfrag_providers;
+        let reputations = self.get_peer_reputations().await;
+        sort_by_reputation(&mut keyfrag_providers, |peer_id| *peer_id, &reputations);
+
         let requests = keyfrag_providers.iter()
             .map(|kfrag_provider_peer_id| {
 
@@ -410,11 +908,88 @@ impl NodeClient {
                 }.boxed()
             });
 
-        if let Ok(cfrags) = futures::future::try_join_all(requests).await {
-            cfrags
-        } else {
-            vec![]
+        // A failed request only loses that provider's cfrag, which can be re-requested elsewhere
+        futures::future::join_all(requests).await
+            .into_iter()
+            .map(|cfrag| cfrag.unwrap_or_else(|e| Err(SendError(e.to_string()))))
+            .collect()
+    }
+
+    /// Gets the confirmed kfrag providers of a Reverie, by the fragment they hold.
+    /// Only the target vessel of a Reverie receives provider confirmations.
+    pub async fn get_kfrag_providers_by_frag_num(&self, reverie_id: &ReverieId) -> HashMap<FragmentNumber, HashSet<PeerId>> {
+        let (sender, receiver) = oneshot::channel();
+        if self.command_sender.send(NodeCommand::GetKfragProvidersByFragNum {
+            reverie_id: reverie_id.clone(),
+            sender,
+        }).await.is_err() {
+            return HashMap::new();
         }
+        receiver.await.unwrap_or_default()
+    }
+
+    /// Re-requests fragments missing from `verified_cfrags` from providers not asked yet,
+    /// e.g. replacements of providers that dropped off or returned an invalid cfrag.
+    /// Only cfrags of the missing frag_nums are kept.
+    async fn request_missing_cfrags(
+        &self,
+        reverie_msg: &ReverieMessage,
+        access_key: AccessKey,
+        capsule: &umbral_pre::Capsule,
+        verified_cfrags: &mut BTreeMap<FragmentNumber, (ReverieCapsulefrag, VerifiedCapsuleFrag)>,
+    ) {
+        let reverie_id = &reverie_msg.reverie.id;
+        let providers_by_frag_num = self.get_kfrag_providers_by_frag_num(reverie_id).await;
+        let backup_providers = backup_cfrag_providers(
+            &providers_by_frag_num,
+            verified_cfrags,
+            &reverie_msg.keyfrag_providers,
+        );
+        if backup_providers.is_empty() {
+            warn!("{} No backup kfrag providers for missing frags of {}", self.nname(), reverie_id);
+            return
+        }
+
+        info!("{} Re-requesting {} missing frags of {} from {} backup providers",
+            self.nname(),
+            reverie_msg.reverie.threshold.saturating_sub(verified_cfrags.len()),
+            reverie_id,
+            backup_providers.len()
+        );
+        let cfrags_raw = self.request_cfrags(reverie_id, backup_providers, access_key).await;
+        self.verify_cfrags(cfrags_raw, capsule, verified_cfrags);
+    }
+
+    /// Requests cfrags for a Reverie known by its name and nonce rather than its id.
+    /// Resolves the ReverieId on the DHT, then gets the Reverie for its kfrag providers and capsule.
+    pub async fn request_cfrags_by_name(
+        &self,
+        reverie_name_nonce: &ReverieNameWithNonce,
+        access_key: AccessKey
+    ) -> Result<(ReverieMessage, Vec<Result<Vec<u8>, SendError>>)> {
+
+        let reverie_id = self.get_reverie_id_by_name(reverie_name_nonce).await
+            .ok_or_else(|| anyhow!("No ReverieId found on the DHT for {}", reverie_name_nonce))?;
+
+        // Vessels hold SovereignAgent Reveries locally, Agent Reveries are on the DHT
+        let reverie_msg = match self.get_reverie(
+            &reverie_id,
+            ReverieType::SovereignAgent(reverie_name_nonce.clone())
+        ).await {
+            Ok(reverie_msg) => reverie_msg,
+            Err(_) => self.get_reverie(
+                &reverie_id,
+                ReverieType::Agent(reverie_name_nonce.clone())
+            ).await?,
+        };
+
+        let cfrags_raw = self.request_cfrags(
+            &reverie_id,
+            reverie_msg.keyfrag_providers.clone(),
+            access_key
+        ).await;
+
+        Ok((reverie_msg, cfrags_raw))
     }
 
     fn parse_cfrags(
@@ -428,45 +1003,100 @@ impl NodeClient {
         AccessCondition,
         usize
     )> {
+        let mut verified_cfrags = BTreeMap::new();
+        self.verify_cfrags(cfrags_raw, &capsule, &mut verified_cfrags);
+        self.combine_cfrags(verified_cfrags)
+    }
 
-        let mut verified_cfrags: Vec<VerifiedCapsuleFrag> = Vec::new();
-        let mut reverie_cfrags: Vec<ReverieCapsulefrag> = Vec::new();
-
-        let mut required_threshold = 0;
-        let mut total_frags_received = 0;
-
+    /// Deserializes and verifies cfrags into `verified_cfrags`, keeping one cfrag per frag_num.
+    /// Failed requests and invalid cfrags are skipped rather than failing the reconstruction,
+    /// so the fragments they should have covered can be re-requested from other providers.
+    fn verify_cfrags(
+        &self,
+        cfrags_raw: Vec<Result<Vec<u8>, SendError>>,
+        capsule: &umbral_pre::Capsule,
+        verified_cfrags: &mut BTreeMap<FragmentNumber, (ReverieCapsulefrag, VerifiedCapsuleFrag)>,
+    ) {
         for cfrag_result in cfrags_raw.into_iter() {
 
             // Deserialize capsule fragments
-            let reverie_cfrag: ReverieCapsulefrag = serde_json::from_slice(&cfrag_result?)?;
-            let cfrag = reverie_cfrag.encode_capsule_frag()?;
-            let new_vessel_pubkey  = reverie_cfrag.target_pubkey;
-
-            info!("Success! cfrag({}) from {}\ntotal frags: {}",
-                reverie_cfrag.frag_num,
-                get_node_name(&reverie_cfrag.kfrag_provider_peer_id),
-                total_frags_received
-            );
+            let reverie_cfrag = match cfrag_result
+                .map_err(|e| anyhow!(e.to_string()))
+                .and_then(|cfrag_bytes| Ok(serde_json::from_slice::<ReverieCapsulefrag>(&cfrag_bytes)?)) {
+                Ok(reverie_cfrag) => reverie_cfrag,
+                Err(e) => {
+                    warn!("Skipping cfrag: {}", e);
+                    continue
+                }
+            };
+            if verified_cfrags.contains_key(&reverie_cfrag.frag_num) {
+                debug!("Already have cfrag({}), skipping duplicate", reverie_cfrag.frag_num);
+                continue
+            }
+            let cfrag = match reverie_cfrag.encode_capsule_frag() {
+                Ok(cfrag) => cfrag,
+                Err(e) => {
+                    self.report_peer_reputation(reverie_cfrag.kfrag_provider_peer_id, ReputationEvent::VerificationFailure);
+                    warn!("Skipping undecodable cfrag({}) from {}: {}",
+                        reverie_cfrag.frag_num, get_node_name(&reverie_cfrag.kfrag_provider_peer_id), e);
+                    continue
+                }
+            };
 
             // Target vessel must check that cfrags are valid.
-            let verified_cfrag = cfrag.verify(
-                &capsule,
+            let verified_cfrag = match cfrag.verify(
+                capsule,
                 &reverie_cfrag.source_verifying_pubkey, // verifying pk
                 &reverie_cfrag.source_pubkey, // source pubkey
                 &reverie_cfrag.target_pubkey // target pubkey
-            ).map_err(|(e, _)| anyhow!(e.to_string()))?;
+            ) {
+                Ok(verified_cfrag) => {
+                    self.report_peer_reputation(reverie_cfrag.kfrag_provider_peer_id, ReputationEvent::ValidCapsuleFrag);
+                    verified_cfrag
+                }
+                Err((e, _)) => {
+                    self.report_peer_reputation(reverie_cfrag.kfrag_provider_peer_id, ReputationEvent::VerificationFailure);
+                    warn!("Skipping invalid cfrag({}) from {}: {}",
+                        reverie_cfrag.frag_num, get_node_name(&reverie_cfrag.kfrag_provider_peer_id), e);
+                    continue
+                }
+            };
 
-            total_frags_received += 1;
-            required_threshold = reverie_cfrag.threshold;
-            verified_cfrags.push(verified_cfrag);
-            reverie_cfrags.push(reverie_cfrag);
+            info!("Success! cfrag({}) from {}\ntotal frags: {}",
+                reverie_cfrag.frag_num,
+                get_node_name(&reverie_cfrag.kfrag_provider_peer_id),
+                verified_cfrags.len() + 1
+            );
+            verified_cfrags.insert(reverie_cfrag.frag_num, (reverie_cfrag, verified_cfrag));
         }
+    }
+
+    /// Checks there are at least `threshold` verified cfrags, all from the same Reverie
+    fn combine_cfrags(
+        &self,
+        verified_cfrags: BTreeMap<FragmentNumber, (ReverieCapsulefrag, VerifiedCapsuleFrag)>,
+    ) -> Result<(
+        Vec<VerifiedCapsuleFrag>,
+        umbral_pre::PublicKey,
+        umbral_pre::PublicKey,
+        AccessCondition,
+        usize
+    )> {
+
+        let total_frags_received = verified_cfrags.len();
+        let (reverie_cfrags, verified_cfrags): (Vec<ReverieCapsulefrag>, Vec<VerifiedCapsuleFrag>) = verified_cfrags
+            .into_values()
+            .unzip();
+        let required_threshold = reverie_cfrags.first().map_or(0, |cfrag| cfrag.threshold);
 
         info!("Received {}/{} required CapsuleFrags", total_frags_received, required_threshold);
 
         if total_frags_received < required_threshold {
             warn!("Not enough fragments. Need {required_threshold}, received {total_frags_received}");
-            return Err(anyhow!("Insufficient cfrags fragments received"))
+            return Err(NodeClientError::InsufficientValidCfrags {
+                received: total_frags_received,
+                threshold: required_threshold,
+            }.into())
         }
 
         // delegator pubkey
@@ -493,38 +1123,113 @@ impl NodeClient {
         ))
     }
 
+    /// Returns `NodeClientError::InsufficientValidCfrags` when there are fewer than
+    /// `threshold` verified cfrags, so callers can re-request cfrags rather than abort
+    /// as they would on a decryption failure.
     fn decrypt_cfrags<T: Serialize + DeserializeOwned>(
         &self,
         capsule: umbral_pre::Capsule,
         ciphertext: Box<[u8]>,
         source_pubkey: umbral_pre::PublicKey,
         verified_cfrags: Vec<VerifiedCapsuleFrag>,
+        threshold: usize,
     ) -> Result<T, Error> {
 
+        if verified_cfrags.len() < threshold {
+            warn!("{} Cannot decrypt with {} valid cfrags, need {}", self.nname(), verified_cfrags.len(), threshold);
+            return Err(NodeClientError::InsufficientValidCfrags {
+                received: verified_cfrags.len(),
+                threshold,
+            }.into())
+        }
+
         // Bob (next target vessel) uses his umbral_key to open the capsule by using at
         // least threshold cfrags, then decrypts the re-encrypted ciphertext.
-        match self.umbral_key.decrypt_reencrypted(
+        match self.umbral_key().decrypt_reencrypted(
             &source_pubkey, // delegator pubkey
             &capsule, // capsule,
             verified_cfrags, // verified capsule fragments
             ciphertext // ciphertext
         ) {
             Ok(plaintext_bob) => {
-                let decrypted_data: serde_json::Value = serde_json::from_slice(&plaintext_bob)?;
-                let secrets_str = serde_json::to_string_pretty(&decrypted_data)?;
-                println!("Decrypted (re-encrypted) secrets:\n{}", format!("{}", secrets_str).bright_black());
-                let secrets_json = serde_json::from_slice(&plaintext_bob)?;
-                Ok(secrets_json)
+                // Plaintext is wiped once deserialized; the returned secrets are the only copy
+                let mut plaintext_bob = Zeroizing::new(plaintext_bob);
+                let secrets = deserialize_and_zeroize::<T>(&mut plaintext_bob)?;
+                info!("Decrypted (re-encrypted) secrets for {}", self.node_id.node_name);
+                Ok(secrets)
             },
             Err(e) => {
                 error!("{}", e);
-                warn!("Not decryptable by user {} with: {}", self.node_id.node_name, self.umbral_key.public_key);
+                warn!("Not decryptable by user {} with: {}", self.node_id.node_name, self.umbral_key().public_key);
                 warn!("Target decryptor pubkey: {}", source_pubkey);
                 Err(anyhow!(e.to_string()))
             }
         }
     }
 
+    /// Requests cfrags from a Reverie's kfrag providers, verifies them and decrypts the
+    /// Reverie. Each stage runs in a tracing span and its duration is recorded in
+    /// `reconstruction_metrics`, warning if the whole reconstruction exceeds `reconstruction_sla`.
+    /// If invalid or missing cfrags leave it short of `threshold`, the missing fragments are
+    /// re-requested from backup providers during parse_cfrags.
+    async fn reconstruct_reverie<T: Serialize + DeserializeOwned>(
+        &self,
+        reverie_msg: &ReverieMessage,
+        access_key: AccessKey,
+    ) -> Result<T> {
+
+        let reverie_id = &reverie_msg.reverie.id;
+        let reverie_type = reverie_msg.reverie.reverie_type.kind();
+        let started_at = Instant::now();
+
+        let secrets = async {
+            let capsule = reverie_msg.reverie.encode_capsule()?;
+
+            let stage_started_at = Instant::now();
+            let cfrags_raw = self.request_cfrags(
+                reverie_id,
+                reverie_msg.keyfrag_providers.clone(),
+                access_key.clone(),
+            ).instrument(info_span!("request_cfrags")).await;
+            self.reconstruction_metrics.record(ReconstructionStage::RequestCfrags, reverie_type, stage_started_at.elapsed());
+
+            let stage_started_at = Instant::now();
+            let parsed_cfrags = async {
+                let mut verified_cfrags = BTreeMap::new();
+                self.verify_cfrags(cfrags_raw, &capsule, &mut verified_cfrags);
+                if verified_cfrags.len() < reverie_msg.reverie.threshold {
+                    self.request_missing_cfrags(reverie_msg, access_key, &capsule, &mut verified_cfrags).await;
+                }
+                self.combine_cfrags(verified_cfrags)
+            }.instrument(info_span!("parse_cfrags")).await;
+            self.reconstruction_metrics.record(ReconstructionStage::ParseCfrags, reverie_type, stage_started_at.elapsed());
+            let (
+                verified_cfrags,
+                source_pubkey,
+                ..
+            ) = parsed_cfrags?;
+
+            let stage_started_at = Instant::now();
+            let secrets = info_span!("decrypt_cfrags").in_scope(|| self.decrypt_cfrags(
+                capsule,
+                reverie_msg.reverie.umbral_ciphertext.clone(),
+                source_pubkey,
+                verified_cfrags,
+                reverie_msg.reverie.threshold,
+            ));
+            self.reconstruction_metrics.record(ReconstructionStage::DecryptCfrags, reverie_type, stage_started_at.elapsed());
+            secrets
+        }.instrument(info_span!("reconstruct_reverie", %reverie_id, reverie_type)).await?;
+
+        let elapsed = started_at.elapsed();
+        self.reconstruction_metrics.record(ReconstructionStage::Total, reverie_type, elapsed);
+        if elapsed > self.reconstruction_sla {
+            warn!("{} Reconstructing {} {} took {:.2?}, over the {:.2?} SLA",
+                self.nname(), reverie_type, reverie_id, elapsed, self.reconstruction_sla);
+        }
+        Ok(secrets)
+    }
+
     fn nname(&self) -> String {
         format!("{}{}", self.node_id.node_name.yellow(), ">".blue())
     }
@@ -536,9 +1241,59 @@ impl NodeClient {
                 reverie_name_nonce: reverie_name_nonce.clone(),
                 sender: sender,
             })
-            .await.expect("Command receiver not to bee dropped");
+            .await.ok()?;
+
+        receiver.await.ok()?
+    }
+
+    /// ReverieIds of a ReverieType held by vessels on the network, found through the provider
+    /// records for the type's index key. Matches on the type's kind, ignoring agent names,
+    /// e.g. any `ReverieType::SovereignAgent(..)` lists every sovereign agent.
+    pub async fn list_reveries_by_type(&self, reverie_type: &ReverieType) -> Result<Vec<ReverieId>> {
+        Ok(self.list_reverie_entries_by_type(reverie_type).await?
+            .into_iter()
+            .map(|entry| entry.reverie_id)
+            .collect())
+    }
+
+    /// Unexpired reveries of a ReverieType kind across all vessels, with their description
+    /// and tags for discovery, sorted by reverie_id
+    pub async fn list_reverie_entries_by_type(&self, reverie_type: &ReverieType) -> Result<Vec<ReverieTypeIndexEntry>> {
+        let (sender, receiver) = oneshot::channel();
+        self.command_sender
+            .send(NodeCommand::GetReverieTypeProviders {
+                reverie_type: reverie_type.clone(),
+                sender,
+            })
+            .await.map_err(|e| anyhow!(e.to_string()))?;
+
+        let vessels = tokio_timeout(REVERIE_TYPE_INDEX_QUERY_TIMEOUT, receiver).await
+            .map_err(|_| anyhow!("Timed out finding vessels for {} reveries", reverie_type.kind()))?
+            .map_err(|e| anyhow!(e.to_string()))?;
+
+        let now = chrono::Utc::now().timestamp();
+        let mut reverie_entries = Vec::new();
+        for vessel_peer_id in vessels {
+            let (sender, receiver) = oneshot::channel();
+            self.command_sender
+                .send(NodeCommand::GetReverieTypeEntries {
+                    entries_key: ReverieTypeEntriesKey::new(reverie_type.kind(), vessel_peer_id),
+                    sender,
+                })
+                .await.map_err(|e| anyhow!(e.to_string()))?;
+
+            match tokio_timeout(REVERIE_TYPE_INDEX_QUERY_TIMEOUT, receiver).await {
+                Ok(Ok(entries)) => reverie_entries.extend(
+                    entries.into_iter().filter(|entry| !entry.is_expired(now))
+                ),
+                // the vessel's entries record was withdrawn or not found
+                _ => debug!("No {} reveries found for vessel {}", reverie_type.kind(), short_peer_id(&vessel_peer_id)),
+            }
+        }
 
-        receiver.await.expect("get reverie receiver not to drop")
+        reverie_entries.sort_by(|a, b| a.reverie_id.cmp(&b.reverie_id));
+        reverie_entries.dedup_by(|a, b| a.reverie_id == b.reverie_id);
+        Ok(reverie_entries)
     }
 
     pub async fn simulate_node_failure(&mut self) -> Result<RestartReason> {
@@ -551,15 +1306,103 @@ impl NodeClient {
         receiver.await.map_err(|e| anyhow!(e.to_string()))
     }
 
+    /// Reads a reverie's metadata (type, description, access condition) from the NEAR contract,
+    /// so a vessel can confirm the access condition before requesting cfrags.
+    pub async fn get_onchain_reverie_metadata(
+        &self,
+        contract_id: &str,
+        reverie_id: &ReverieId,
+    ) -> Result<Option<ReverieMetadata>> {
+        self.near_runtime.get_reverie_metadata(contract_id, reverie_id).await
+    }
+
+    /// Reveries a spender has a balance on in the NEAR contract, for listing a user's delegations.
+    pub async fn get_onchain_reveries_for_spender(
+        &self,
+        contract_id: &str,
+        spender: &str,
+    ) -> Result<Vec<(ReverieId, u128)>> {
+        self.near_runtime.get_reveries_for_spender(contract_id, spender).await
+    }
+
+    pub async fn get_readiness(&self) -> Result<NodeReadiness> {
+        let (sender, receiver) = oneshot::channel();
+        self.command_sender.send(NodeCommand::GetReadiness {
+            sender: sender,
+        }).await.map_err(|e| anyhow!(e.to_string()))?;
+
+        receiver.await.map_err(|e| anyhow!(e.to_string()))
+    }
+
+    /// Count and oldest age of each kind of pending network request, to diagnose
+    /// queries and channels stuck waiting on a response
+    pub async fn get_pending_requests(&self) -> Result<BTreeMap<String, PendingRequestStats>> {
+        let (sender, receiver) = oneshot::channel();
+        self.command_sender.send(NodeCommand::GetPendingRequests {
+            sender: sender,
+        }).await.map_err(|e| anyhow!(e.to_string()))?;
+
+        receiver.await.map_err(|e| anyhow!(e.to_string()))
+    }
+
+    /// Connected peers with their vessel status from Kademlia, heartbeat recency and reputation
+    pub async fn list_connected_peers(&self) -> Result<Vec<ConnectedPeer>> {
+        let (sender, receiver) = oneshot::channel();
+        self.command_sender.send(NodeCommand::GetConnectedPeerInfo {
+            sender: sender,
+        }).await.map_err(|e| anyhow!(e.to_string()))?;
+
+        let mut connected_peers = receiver.await.map_err(|e| anyhow!(e.to_string()))?;
+
+        let vessel_statuses: HashMap<PeerId, VesselStatus> = self.get_node_vessels(false).await
+            .into_iter()
+            .map(|node_keys| (node_keys.peer_id, node_keys.vessel_status))
+            .collect();
+        for peer in connected_peers.iter_mut() {
+            peer.vessel_status = vessel_statuses.get(&peer.peer_id).copied();
+        }
+        Ok(connected_peers)
+    }
+
+    /// Reputation scores of known peers, empty if the network loop is unavailable
+    pub async fn get_peer_reputations(&self) -> HashMap<PeerId, f64> {
+        let (sender, receiver) = oneshot::channel();
+        if self.command_sender.send(NodeCommand::GetPeerReputations { sender }).await.is_err() {
+            return HashMap::new();
+        }
+        receiver.await.unwrap_or_default()
+    }
+
+    /// Best-effort, so cfrag parsing never waits on the network loop
+    fn report_peer_reputation(&self, peer_id: PeerId, event: ReputationEvent) {
+        self.command_sender
+            .try_send(NodeCommand::ReportPeerReputation { peer_id, event })
+            .ok();
+    }
+
+    /// The agent this node is currently the vessel for, if any
+    pub async fn current_vessel_agent(&self) -> Result<Option<AgentVesselInfo>> {
+        let (sender, receiver) = oneshot::channel();
+        self.command_sender.send(NodeCommand::GetVesselAgent {
+            sender: sender,
+        }).await.map_err(|e| anyhow!(e.to_string()))?;
+
+        receiver.await.map_err(|e| anyhow!(e.to_string()))
+    }
+
     pub async fn get_node_state(&self) -> Result<serde_json::Value> {
         let (sender, receiver) = oneshot::channel();
         self.command_sender.send(NodeCommand::GetNodeState {
             sender: sender,
         }).await.ok();
 
-        let node_info = receiver.await
+        let mut node_info = receiver.await
             .map_err(|e| anyhow!(e.to_string()))?;
 
+        if let Some(node_state) = node_info.as_object_mut() {
+            node_state.insert("_command_channel".to_string(), self.command_sender.metrics());
+            node_state.insert("_reconstruction".to_string(), self.reconstruction_metrics.metrics());
+        }
         Ok(node_info)
     }
 
@@ -642,6 +1485,120 @@ impl NodeClient {
     }
 }
 
+/// Orders peers from highest to lowest reputation. The sort is stable,
+/// so peers with equal scores keep their (possibly shuffled) order.
+fn sort_by_reputation<T>(
+    peers: &mut [T],
+    peer_id: impl Fn(&T) -> PeerId,
+    reputations: &HashMap<PeerId, f64>
+) {
+    let score = |p: &T| *reputations.get(&peer_id(p)).unwrap_or(&DEFAULT_REPUTATION);
+    peers.sort_by(|a, b| score(b).total_cmp(&score(a)));
+}
+
+/// Takes the first peer as the target vessel, and the rest as kfrag providers.
+/// Peers are compared by peer_id only, as Kademlia can return several vessel statuses
+/// for one peer (e.g. before and after a key rotation): the target's peer_id is always
+/// excluded from the providers, and each provider is kept once, in order.
+fn split_target_vessel(
+    peer_nodes: Vec<NodeKeysWithVesselStatus>
+) -> Result<(NodeKeysWithVesselStatus, Vec<NodeKeysWithVesselStatus>)> {
+    let mut peer_nodes = peer_nodes.into_iter();
+    let target_vessel = peer_nodes.next().ok_or(anyhow!("No Peers found."))?;
+
+    let mut seen_peer_ids = HashSet::from([target_vessel.peer_id]);
+    let target_kfrag_providers = peer_nodes
+        .filter(|v| seen_peer_ids.insert(v.peer_id))
+        .collect::<Vec<NodeKeysWithVesselStatus>>();
+
+    Ok((target_vessel, target_kfrag_providers))
+}
+
+/// Orders the selected kfrag providers by peer_id, so frag_num `i` goes to the i-th
+/// provider. Every node assigns the same frag_nums for the same set of providers, and
+/// each of the `total_frags` numbers is claimed exactly once, whatever the nodes' seeds.
+fn assign_frag_nums(
+    mut kfrag_providers: Vec<NodeKeysWithVesselStatus>
+) -> Vec<NodeKeysWithVesselStatus> {
+    kfrag_providers.sort_by_key(|v| v.peer_id);
+    kfrag_providers
+}
+
+/// Fragments without a connected provider, once fewer than `threshold + safety_margin`
+/// distinct fragments are held by connected providers. Empty while the margin holds.
+fn frag_nums_to_replace(
+    placements: &[(FragmentNumber, PeerId)],
+    connected_peers: &HashSet<PeerId>,
+    threshold: usize,
+    total_frags: usize,
+    safety_margin: usize,
+) -> Vec<FragmentNumber> {
+    let live_frag_nums = placements.iter()
+        .filter(|(_, peer_id)| connected_peers.contains(peer_id))
+        .map(|(frag_num, _)| *frag_num)
+        .collect::<HashSet<FragmentNumber>>();
+
+    if live_frag_nums.len() >= (threshold + safety_margin).min(total_frags) {
+        return vec![];
+    }
+    (0..total_frags)
+        .filter(|frag_num| !live_frag_nums.contains(frag_num))
+        .collect()
+}
+
+/// Providers of the fragments missing from `verified_cfrags` that weren't already asked
+/// for a cfrag. A provider is only listed once, even if it holds several missing fragments.
+fn backup_cfrag_providers<T>(
+    providers_by_frag_num: &HashMap<FragmentNumber, HashSet<PeerId>>,
+    verified_cfrags: &BTreeMap<FragmentNumber, T>,
+    asked_providers: &[PeerId],
+) -> Vec<PeerId> {
+    let mut backup_providers = providers_by_frag_num.iter()
+        .filter(|(frag_num, _)| !verified_cfrags.contains_key(frag_num))
+        .flat_map(|(_, peer_ids)| peer_ids.iter().copied())
+        .filter(|peer_id| !asked_providers.contains(peer_id))
+        .collect::<Vec<PeerId>>();
+    backup_providers.sort();
+    backup_providers.dedup();
+    backup_providers
+}
+
+/// The first copy, in response order, whose ciphertext and capsule match more than half
+/// of the copies, or None if no copy has a majority (e.g. two holders disagree).
+fn select_consistent_reverie(copies: &[(PeerId, ReverieMessage)]) -> Option<&ReverieMessage> {
+    copies.iter()
+        .map(|(_, reverie_msg)| reverie_msg)
+        .find(|reverie_msg| {
+            let matching = copies.iter()
+                .filter(|(_, copy)| same_ciphertext(copy, reverie_msg))
+                .count();
+            matching * 2 > copies.len()
+        })
+}
+
+fn same_ciphertext(a: &ReverieMessage, b: &ReverieMessage) -> bool {
+    a.reverie.umbral_ciphertext == b.reverie.umbral_ciphertext
+        && a.reverie.umbral_capsule == b.reverie.umbral_capsule
+}
+
+/// Moves preferred peers to the front, keeping the existing (reputation) order within
+/// preferred and non-preferred peers.
+fn prioritize_preferred_peers<T>(
+    peers: &mut [T],
+    peer_id: impl Fn(&T) -> PeerId,
+    preferred: &[PeerId]
+) {
+    peers.sort_by_key(|p| !preferred.contains(&peer_id(p)));
+}
+
+/// Deserializes decrypted secrets, then zeroizes the plaintext buffer
+/// regardless of whether deserialization succeeded.
+fn deserialize_and_zeroize<T: DeserializeOwned>(plaintext: &mut [u8]) -> Result<T, Error> {
+    let secrets = serde_json::from_slice::<T>(plaintext);
+    plaintext.zeroize();
+    secrets.map_err(|e| anyhow!("Failed to deserialize decrypted secrets: {}", e))
+}
+
 // Helper function to parse the docker compose command string
 // Expected format: "docker compose -f <file_path> <other_args>"
 fn parse_docker_compose_command(command_str: String) -> Result<(String, Vec<String>), Error> {
@@ -680,6 +1637,7 @@ fn parse_docker_compose_command(command_str: String) -> Result<(String, Vec<Stri
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_utils::{test_node_client, mock_network};
 
     #[test]
     fn test_parse_docker_compose_command_valid_simple() {
@@ -773,5 +1731,1106 @@ mod tests {
         let cmd2 = "docker-compose -f ".to_string();
         assert!(parse_docker_compose_command(cmd2).is_err());
     }
-}
 
+    #[test]
+    fn test_deserialize_and_zeroize_wipes_plaintext() {
+        let secrets = serde_json::json!({ "anthropic_api_key": "sk-ant-secret" });
+        let mut plaintext = serde_json::to_vec(&secrets).unwrap();
+
+        let decrypted: serde_json::Value = deserialize_and_zeroize(&mut plaintext).unwrap();
+
+        assert_eq!(decrypted, secrets);
+        assert!(plaintext.iter().all(|b| *b == 0), "plaintext buffer should be zeroized");
+    }
+
+    #[test]
+    fn test_deserialize_and_zeroize_wipes_plaintext_on_error() {
+        let mut plaintext = b"not json: sk-ant-secret".to_vec();
+
+        let result = deserialize_and_zeroize::<serde_json::Value>(&mut plaintext);
+
+        assert!(result.is_err());
+        assert!(!result.unwrap_err().to_string().contains("sk-ant-secret"));
+        assert!(plaintext.iter().all(|b| *b == 0), "plaintext buffer should be zeroized");
+    }
+
+    #[test]
+    fn sign_access_passes_kfrag_provider_access_check() {
+        let umbral_key = UmbralKey::new(None);
+        let (node_client, _command_receiver) = test_node_client(umbral_key.clone());
+        let reverie_id: ReverieId = "reverie_test".to_string();
+        // Same check the request-response handler runs before returning a cfrag
+        let access_condition = AccessCondition::Umbral(umbral_key.verifying_public_key);
+
+        let access_key = node_client.sign_access(&reverie_id);
+        assert!(access_key.verify_access(&access_condition, &reverie_id));
+        assert!(!access_key.verify_access(&access_condition, "other_reverie"));
+
+        let other_key = UmbralKey::new(None);
+        let wrong_condition = AccessCondition::Umbral(other_key.verifying_public_key);
+        assert!(!access_key.verify_access(&wrong_condition, &reverie_id));
+    }
+
+    #[tokio::test]
+    async fn create_reverie_accepts_non_agent_memory_payload() {
+        let vessel_key = UmbralKey::new(None);
+        let (node_client, _command_receiver) = test_node_client(UmbralKey::new(None));
+
+        // Not shaped like AgentSecretsJson
+        let memory = serde_json::json!({ "notes": ["met auron at the harbour"], "mood": 7 });
+        let reverie = node_client.create_reverie(
+            memory.clone(),
+            ReverieType::Memory,
+            "harbour memories".to_string(),
+            vec!["memory".to_string(), "harbour".to_string()],
+            2,
+            3,
+            vessel_key.public_key,
+            vessel_key.verifying_public_key,
+            AccessCondition::Umbral(vessel_key.public_key),
+        ).unwrap();
+
+        let capsule = reverie.encode_capsule().unwrap();
+        let plaintext = node_client.umbral_key()
+            .decrypt_original(&capsule, &reverie.umbral_ciphertext)
+            .unwrap();
+        assert_eq!(serde_json::from_slice::<serde_json::Value>(&plaintext).unwrap(), memory);
+
+        // description and tags survive the trip over the wire
+        let received: Reverie = serde_json::from_slice(&serde_json::to_vec(&reverie).unwrap()).unwrap();
+        assert_eq!(received.description, "harbour memories");
+        assert_eq!(received.tags, vec!["memory".to_string(), "harbour".to_string()]);
+    }
+
+    /// A SovereignAgent Reverie targeting `vessel_key`, with the cfrag each of its
+    /// kfrag providers would return
+    fn reverie_with_cfrags(
+        vessel_key: &UmbralKey,
+        target_peer_id: PeerId,
+        secrets: &serde_json::Value,
+    ) -> (ReverieMessage, HashMap<PeerId, Vec<u8>>) {
+        let reverie_type = ReverieType::SovereignAgent(ReverieNameWithNonce("auron".to_string(), 1));
+        reverie_type_with_cfrags(vessel_key, target_peer_id, reverie_type, secrets)
+    }
+
+    fn reverie_type_with_cfrags(
+        vessel_key: &UmbralKey,
+        target_peer_id: PeerId,
+        reverie_type: ReverieType,
+        secrets: &serde_json::Value,
+    ) -> (ReverieMessage, HashMap<PeerId, Vec<u8>>) {
+        let source_key = UmbralKey::new(None);
+        let (capsule, ciphertext) = source_key.encrypt_bytes(&serde_json::to_vec(secrets).unwrap()).unwrap();
+        let reverie = Reverie::new(
+            "test reverie".to_string(),
+            reverie_type,
+            2,
+            3,
+            vessel_key.public_key,
+            vessel_key.verifying_public_key,
+            AccessCondition::Umbral(vessel_key.public_key),
+            capsule.clone(),
+            ciphertext,
+        );
+
+        // kfrag providers re-encrypt the capsule with their keyfrags
+        let keyfrag_providers: Vec<PeerId> = (0..3).map(|_| PeerId::random()).collect();
+        let cfrags: HashMap<PeerId, Vec<u8>> = source_key
+            .generate_pre_keyfrags(&vessel_key.public_key, 2, 3, true, false)
+            .into_iter()
+            .zip(keyfrag_providers.iter())
+            .enumerate()
+            .map(|(frag_num, (kfrag, provider))| {
+                let verified_kfrag = kfrag.verify(
+                    &source_key.verifying_public_key,
+                    Some(&source_key.public_key),
+                    None
+                ).map_err(|(e, _)| e).unwrap();
+                let cfrag = umbral_pre::reencrypt(&capsule, verified_kfrag).unverify();
+                let reverie_cfrag = ReverieCapsulefrag {
+                    version: REVERIE_WIRE_VERSION,
+                    id: reverie.id.clone(),
+                    reverie_type: reverie.reverie_type.clone(),
+                    frag_num,
+                    threshold: 2,
+                    umbral_capsule_frag: serde_json::to_vec(&cfrag).unwrap(),
+                    source_pubkey: source_key.public_key,
+                    source_verifying_pubkey: source_key.verifying_public_key,
+                    target_pubkey: vessel_key.public_key,
+                    target_verifying_pubkey: vessel_key.verifying_public_key,
+                    access_condition: reverie.access_condition.clone(),
+                    kfrag_provider_peer_id: *provider,
+                    expires_at: None,
+                };
+                (*provider, serde_json::to_vec(&reverie_cfrag).unwrap())
+            })
+            .collect();
+
+        let reverie_msg = ReverieMessage {
+            reverie,
+            source_peer_id: PeerId::random(),
+            target_peer_id,
+            keyfrag_providers,
+        };
+        (reverie_msg, cfrags)
+    }
+
+    #[tokio::test]
+    async fn request_cfrags_by_name_resolves_id_and_reconstructs() {
+        let vessel_key = UmbralKey::new(None);
+        let (node_client, command_receiver) = test_node_client(vessel_key.clone());
+
+        let secrets = serde_json::json!({ "context": "agent secrets" });
+        let reverie_name_nonce = ReverieNameWithNonce("auron".to_string(), 1);
+        let (reverie_msg, cfrags) = reverie_with_cfrags(&vessel_key, node_client.node_id.peer_id, &secrets);
+        let reverie = reverie_msg.reverie.clone();
+
+        // Stand-in for NetworkEvents answering the DHT lookups and cfrag requests
+        let reverie_id = reverie.id.clone();
+        mock_network(command_receiver, move |command| match command {
+            NodeCommand::GetReverieIdByName { reverie_name_nonce: name, sender } => {
+                let found = (name == ReverieNameWithNonce("auron".to_string(), 1)).then(|| reverie_id.clone());
+                sender.send(found).ok();
+                None
+            }
+            NodeCommand::GetReverie { sender, .. } => {
+                sender.send(Ok(reverie_msg.clone())).ok();
+                None
+            }
+            NodeCommand::RequestCapsuleFragment { kfrag_provider_peer_id, sender, .. } => {
+                sender.send(Ok(cfrags[&kfrag_provider_peer_id].clone())).ok();
+                None
+            }
+            command => Some(command),
+        });
+
+        let access_key = AccessKey::UmbralSignature(vec![]);
+        let unknown_name = ReverieNameWithNonce("unknown".to_string(), 0);
+        let err = node_client.request_cfrags_by_name(&unknown_name, access_key.clone()).await.unwrap_err();
+        assert!(err.to_string().contains("No ReverieId found"));
+
+        let (reverie_msg, cfrags_raw) = node_client
+            .request_cfrags_by_name(&reverie_name_nonce, access_key)
+            .await
+            .unwrap();
+        assert_eq!(reverie_msg.reverie.id, reverie.id);
+        assert_eq!(cfrags_raw.len(), 3);
+
+        let capsule = reverie_msg.reverie.encode_capsule().unwrap();
+        let (verified_cfrags, source_pubkey, ..) = node_client.parse_cfrags(cfrags_raw, capsule.clone()).unwrap();
+        let decrypted: serde_json::Value = node_client.decrypt_cfrags(
+            capsule,
+            reverie_msg.reverie.umbral_ciphertext,
+            source_pubkey,
+            verified_cfrags,
+            reverie_msg.reverie.threshold,
+        ).unwrap();
+        assert_eq!(decrypted, secrets);
+    }
+
+    #[tokio::test]
+    async fn rekey_is_denied_unless_this_node_owns_the_reverie_on_chain() {
+        use runtime::near_runtime::NearConfig;
+        use runtime::test_utils::spawn_mock_near_rpc_responses;
+
+        let vessel_key = UmbralKey::new(None);
+        let (mut node_client, command_receiver) = test_node_client(vessel_key.clone());
+        // the contract reports another account as the reverie's owner
+        let near_rpc_url = spawn_mock_near_rpc_responses(vec![
+            serde_json::to_vec(&serde_json::json!("someone-else.testnet")).unwrap(),
+        ]).await.unwrap();
+        node_client.near_runtime = Arc::new(NearRuntime::new(NearConfig { near_rpc_url, ..NearConfig::default() }).unwrap());
+
+        let secrets = serde_json::json!({ "context": "agent secrets" });
+        let (reverie_msg, _cfrags) = reverie_with_cfrags(&vessel_key, node_client.node_id.peer_id, &secrets);
+        let mut forwarded = mock_network(command_receiver, move |command| match command {
+            NodeCommand::GetVesselReveries { sender } => {
+                sender.send(vec![reverie_msg.clone()]).ok();
+                None
+            }
+            command => Some(command),
+        });
+
+        assert!(node_client.rotate_umbral_key().await.is_err());
+
+        // no cfrags were requested and no key was published, the old key is kept
+        assert_eq!(node_client.umbral_key().public_key, vessel_key.public_key);
+        assert!(forwarded.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn decrypt_cfrags_below_threshold_is_insufficient_valid_cfrags() {
+        let vessel_key = UmbralKey::new(None);
+        let (node_client, _command_receiver) = test_node_client(vessel_key.clone());
+
+        let secrets = serde_json::json!({ "context": "agent secrets" });
+        let (reverie_msg, cfrags) = reverie_with_cfrags(&vessel_key, node_client.node_id.peer_id, &secrets);
+        let capsule = reverie_msg.reverie.encode_capsule().unwrap();
+
+        // only one cfrag survives verification, threshold is 2
+        let provider = reverie_msg.keyfrag_providers[0];
+        let mut verified_cfrags = BTreeMap::new();
+        node_client.verify_cfrags(vec![Ok(cfrags[&provider].clone())], &capsule, &mut verified_cfrags);
+        let (reverie_cfrags, verified_cfrags): (Vec<ReverieCapsulefrag>, Vec<VerifiedCapsuleFrag>) = verified_cfrags
+            .into_values()
+            .unzip();
+        assert_eq!(verified_cfrags.len(), 1);
+
+        let err = node_client.decrypt_cfrags::<serde_json::Value>(
+            capsule,
+            reverie_msg.reverie.umbral_ciphertext.clone(),
+            reverie_cfrags[0].source_pubkey,
+            verified_cfrags,
+            reverie_msg.reverie.threshold,
+        ).unwrap_err();
+
+        match err.downcast_ref::<NodeClientError>() {
+            Some(NodeClientError::InsufficientValidCfrags { received, threshold }) => {
+                assert_eq!((*received, *threshold), (1, 2));
+            }
+            other => panic!("expected InsufficientValidCfrags, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn invalid_cfrag_is_recovered_from_backup_provider() {
+        let vessel_key = UmbralKey::new(None);
+        let (node_client, command_receiver) = test_node_client(vessel_key.clone());
+
+        let secrets = serde_json::json!({ "context": "agent secrets" });
+        let (mut reverie_msg, mut cfrags) = reverie_with_cfrags(&vessel_key, node_client.node_id.peer_id, &secrets);
+        // frag_num i is held by the i-th provider, only the first two are in the ReverieMessage
+        let providers = reverie_msg.keyfrag_providers.clone();
+        reverie_msg.keyfrag_providers.truncate(2);
+
+        // the provider of frag 1 returns a cfrag that fails verification
+        let mut garbage_cfrag: ReverieCapsulefrag = serde_json::from_slice(&cfrags[&providers[1]]).unwrap();
+        garbage_cfrag.source_verifying_pubkey = UmbralKey::new(None).verifying_public_key;
+        cfrags.insert(providers[1], serde_json::to_vec(&garbage_cfrag).unwrap());
+
+        let providers_by_frag_num: HashMap<FragmentNumber, HashSet<PeerId>> = providers.iter()
+            .enumerate()
+            .map(|(frag_num, peer_id)| (frag_num, HashSet::from([*peer_id])))
+            .collect();
+        let requested = Arc::new(std::sync::Mutex::new(vec![]));
+        let reputation_events = Arc::new(std::sync::Mutex::new(vec![]));
+        let (requested2, reputation_events2) = (requested.clone(), reputation_events.clone());
+        mock_network(command_receiver, move |command| match command {
+            NodeCommand::RequestCapsuleFragment { kfrag_provider_peer_id, sender, .. } => {
+                requested2.lock().unwrap().push(kfrag_provider_peer_id);
+                sender.send(Ok(cfrags[&kfrag_provider_peer_id].clone())).ok();
+                None
+            }
+            NodeCommand::GetKfragProvidersByFragNum { sender, .. } => {
+                sender.send(providers_by_frag_num.clone()).ok();
+                None
+            }
+            NodeCommand::ReportPeerReputation { peer_id, event } => {
+                reputation_events2.lock().unwrap().push((peer_id, event));
+                None
+            }
+            NodeCommand::GetPeerReputations { sender } => {
+                sender.send(HashMap::new()).ok();
+                None
+            }
+            command => Some(command),
+        });
+
+        let decrypted: serde_json::Value = node_client
+            .reconstruct_reverie(&reverie_msg, node_client.sign_access(&reverie_msg.reverie.id))
+            .await
+            .unwrap();
+        assert_eq!(decrypted, secrets);
+
+        // only the backup provider of the missing fragment was asked again
+        let requested = requested.lock().unwrap().clone();
+        assert_eq!(requested.len(), 3, "{:?}", requested);
+        assert_eq!(requested[2], providers[2]);
+        assert!(reputation_events.lock().unwrap().contains(&(providers[1], ReputationEvent::VerificationFailure)));
+    }
+
+    #[test]
+    fn backup_cfrag_providers_skip_covered_frags_and_asked_providers() {
+        let (provider_0, provider_1, replacement_1, provider_2) =
+            (PeerId::random(), PeerId::random(), PeerId::random(), PeerId::random());
+        let providers_by_frag_num = HashMap::from([
+            (0, HashSet::from([provider_0])),
+            (1, HashSet::from([provider_1, replacement_1])),
+            (2, HashSet::from([provider_2])),
+        ]);
+        let verified_cfrags = BTreeMap::from([(0, ()), (2, ())]);
+
+        let backup_providers = backup_cfrag_providers(&providers_by_frag_num, &verified_cfrags, &[provider_0, provider_1]);
+        assert_eq!(backup_providers, vec![replacement_1]);
+
+        // nothing to re-request once every fragment is covered
+        let verified_cfrags = BTreeMap::from([(0, ()), (1, ()), (2, ())]);
+        assert!(backup_cfrag_providers(&providers_by_frag_num, &verified_cfrags, &[]).is_empty());
+    }
+
+    #[tokio::test]
+    async fn reverie_is_retrieved_from_consistent_holders() {
+        let vessel_key = UmbralKey::new(None);
+        let (node_client, command_receiver) = test_node_client(vessel_key.clone());
+
+        let secrets = serde_json::json!({ "context": "agent secrets" });
+        let (reverie_msg, _cfrags) = reverie_with_cfrags(&vessel_key, node_client.node_id.peer_id, &secrets);
+        let reverie_id = reverie_msg.reverie.id.clone();
+        let mut corrupted_msg = reverie_msg.clone();
+        corrupted_msg.reverie.umbral_ciphertext[0] ^= 0xff;
+
+        let holder_keys: Vec<libp2p::identity::Keypair> = (0..5)
+            .map(|_| libp2p::identity::Keypair::generate_ed25519())
+            .collect();
+        let holder_ids: Vec<PeerId> = holder_keys.iter().map(|keys| keys.public().to_peer_id()).collect();
+        // two holders serve the copy they signed, one serves a corrupted copy of the one it
+        // signed, one serves a corrupted copy without signing a record, one is unreachable
+        let (corrupted_holder, unsigned_holder, unreachable_holder) = (holder_ids[2], holder_ids[3], holder_ids[4]);
+        let holders: HashSet<PeerId> = holder_ids.iter().cloned().collect();
+        let holder_records: HashMap<PeerId, SignedReverieHolder> = holder_keys.iter()
+            .zip(holder_ids.iter())
+            .filter(|(_, holder_peer_id)| **holder_peer_id != unsigned_holder)
+            .map(|(keys, holder_peer_id)| {
+                let holder_key = ReverieHolderKey::new(reverie_id.clone(), *holder_peer_id);
+                (*holder_peer_id, SignedReverieHolder::new(&holder_key, &reverie_msg.reverie, keys).unwrap())
+            })
+            .collect();
+
+        let reputation_events = Arc::new(std::sync::Mutex::new(vec![]));
+        let reputation_events2 = reputation_events.clone();
+        let reverie_msg2 = reverie_msg.clone();
+        mock_network(command_receiver, move |command| match command {
+            NodeCommand::GetReverieHolders { sender, .. } => {
+                sender.send(holders.clone()).ok();
+                None
+            }
+            NodeCommand::GetReverieHolderRecord { holder_key, sender } => {
+                if let Some(signed_holder) = holder_records.get(&holder_key.holder_peer_id) {
+                    sender.send(signed_holder.clone()).ok();
+                }
+                None
+            }
+            NodeCommand::RequestReverieFromHolder { holder_peer_id, sender, .. } => {
+                let response = if holder_peer_id == corrupted_holder || holder_peer_id == unsigned_holder {
+                    Ok(corrupted_msg.clone())
+                } else if holder_peer_id == unreachable_holder {
+                    Err(SendError("Timeout".to_string()))
+                } else {
+                    Ok(reverie_msg2.clone())
+                };
+                sender.send(response).ok();
+                None
+            }
+            NodeCommand::ReportPeerReputation { peer_id, event } => {
+                reputation_events2.lock().unwrap().push((peer_id, event));
+                None
+            }
+            command => Some(command),
+        });
+
+        // Memory reveries are read from their holders, only the corrupted holder
+        // contradicted its own signed record, the unsigned holder's copy didn't vote
+        let retrieved = node_client.get_reverie(&reverie_id, ReverieType::Memory).await.unwrap();
+        assert_eq!(retrieved, reverie_msg);
+        assert_eq!(
+            *reputation_events.lock().unwrap(),
+            vec![(corrupted_holder, ReputationEvent::VerificationFailure)]
+        );
+    }
+
+    #[tokio::test]
+    async fn reverie_without_holder_records_is_read_from_the_dht() {
+        let vessel_key = UmbralKey::new(None);
+        let (node_client, command_receiver) = test_node_client(vessel_key.clone());
+        let secrets = serde_json::json!({ "context": "agent secrets" });
+        let (reverie_msg, _cfrags) = reverie_with_cfrags(&vessel_key, node_client.node_id.peer_id, &secrets);
+
+        let reverie_msg2 = reverie_msg.clone();
+        mock_network(command_receiver, move |command| match command {
+            NodeCommand::GetReverieHolders { sender, .. } => {
+                sender.send(HashSet::new()).ok();
+                None
+            }
+            NodeCommand::GetReverie { sender, .. } => {
+                sender.send(Ok(reverie_msg2.clone())).ok();
+                None
+            }
+            command => Some(command),
+        });
+
+        let retrieved = node_client.get_reverie(&reverie_msg.reverie.id, ReverieType::Memory).await.unwrap();
+        assert_eq!(retrieved, reverie_msg);
+    }
+
+    #[test]
+    fn consistent_reverie_needs_a_majority_of_copies() {
+        let vessel_key = UmbralKey::new(None);
+        let secrets = serde_json::json!({ "context": "agent secrets" });
+        let (reverie_msg, _) = reverie_with_cfrags(&vessel_key, PeerId::random(), &secrets);
+        let mut corrupted_msg = reverie_msg.clone();
+        corrupted_msg.reverie.umbral_capsule[0] ^= 0xff;
+
+        // the corrupted copy arrived first, but only the consistent copy has a majority
+        let copies = vec![
+            (PeerId::random(), corrupted_msg.clone()),
+            (PeerId::random(), reverie_msg.clone()),
+            (PeerId::random(), reverie_msg.clone()),
+        ];
+        assert_eq!(select_consistent_reverie(&copies), Some(&reverie_msg));
+
+        // a single copy has nothing to disagree with
+        assert_eq!(select_consistent_reverie(&copies[..1]), Some(&corrupted_msg));
+
+        // two holders disagreeing can't be resolved
+        assert_eq!(select_consistent_reverie(&copies[..2]), None);
+        assert_eq!(select_consistent_reverie(&[]), None);
+    }
+
+    #[tokio::test]
+    async fn reconstruction_records_stage_timings() {
+        let vessel_key = UmbralKey::new(None);
+        let (node_client, command_receiver) = test_node_client(vessel_key.clone());
+
+        let secrets = serde_json::json!({ "context": "agent secrets" });
+        let (reverie_msg, cfrags) = reverie_with_cfrags(&vessel_key, node_client.node_id.peer_id, &secrets);
+
+        mock_network(command_receiver, move |command| match command {
+            NodeCommand::RequestCapsuleFragment { kfrag_provider_peer_id, sender, .. } => {
+                sender.send(Ok(cfrags[&kfrag_provider_peer_id].clone())).ok();
+                None
+            }
+            command => Some(command),
+        });
+
+        let reverie_type = reverie_msg.reverie.reverie_type.kind();
+        assert!(node_client.reconstruction_metrics.histogram(ReconstructionStage::Total, reverie_type).is_none());
+
+        let decrypted: serde_json::Value = node_client
+            .reconstruct_reverie(&reverie_msg, node_client.sign_access(&reverie_msg.reverie.id))
+            .await
+            .unwrap();
+        assert_eq!(decrypted, secrets);
+
+        for stage in [
+            ReconstructionStage::RequestCfrags,
+            ReconstructionStage::ParseCfrags,
+            ReconstructionStage::DecryptCfrags,
+            ReconstructionStage::Total,
+        ] {
+            let histogram = node_client.reconstruction_metrics.histogram(stage, reverie_type).unwrap();
+            assert_eq!(histogram.count, 1, "{} not recorded", stage.as_str());
+        }
+        let metrics = node_client.reconstruction_metrics.metrics();
+        assert_eq!(metrics["SovereignAgent"]["total"]["count"], 1);
+    }
+
+    #[tokio::test]
+    async fn mcp_plugin_reverie_decrypts_to_mcp_tool_definitions() {
+        let vessel_key = UmbralKey::new(None);
+        let (node_client, command_receiver) = test_node_client(vessel_key.clone());
+
+        let mcp_plugin = serde_json::json!({
+            "name": "github",
+            "endpoints": [{ "name": "github-mcp", "url": "https://mcp.example.com/github" }],
+            "credentials": { "GITHUB_TOKEN": "ghp_secret" },
+            "tools": [{
+                "name": "search_issues",
+                "description": "Search GitHub issues",
+                "inputSchema": { "type": "object", "properties": { "query": { "type": "string" } } }
+            }]
+        });
+        let (reverie_msg, cfrags) = reverie_type_with_cfrags(
+            &vessel_key,
+            node_client.node_id.peer_id,
+            ReverieType::McpPlugin,
+            &mcp_plugin
+        );
+
+        mock_network(command_receiver, move |command| match command {
+            NodeCommand::RequestCapsuleFragment { kfrag_provider_peer_id, sender, .. } => {
+                sender.send(Ok(cfrags[&kfrag_provider_peer_id].clone())).ok();
+                None
+            }
+            command => Some(command),
+        });
+
+        let decrypted: runtime::llm::McpPluginConfig = node_client
+            .reconstruct_reverie(&reverie_msg, node_client.sign_access(&reverie_msg.reverie.id))
+            .await
+            .unwrap();
+        decrypted.validate().unwrap();
+        assert_eq!(decrypted.credentials["GITHUB_TOKEN"], "ghp_secret");
+
+        // loaded into the query's tools, alongside the caller's own tools
+        let query = runtime::llm::AnthropicQuery {
+            prompt: "Find open issues about heartbeats".to_string(),
+            tools: Some(serde_json::json!([{ "name": "get_time", "input_schema": { "type": "object" } }])),
+            stream: None,
+        }.with_mcp_plugin_tools(&decrypted).unwrap();
+        query.validate_tools().unwrap();
+        let tools = query.tools.unwrap();
+        assert_eq!(tools[1]["name"], "search_issues");
+        assert!(!tools.to_string().contains("ghp_secret"));
+    }
+
+    #[tokio::test]
+    async fn list_connected_peers_fills_vessel_statuses_from_kademlia() {
+        let (node_client, command_receiver) = test_node_client(UmbralKey::new(None));
+        let vessel_peer = PeerId::random();
+        let unlisted_peer = PeerId::random();
+
+        mock_network(command_receiver, move |command| match command {
+            NodeCommand::GetConnectedPeerInfo { sender } => {
+                let connected_peers = [vessel_peer, unlisted_peer].iter()
+                    .map(|peer_id| ConnectedPeer {
+                        peer_id: *peer_id,
+                        node_name: get_node_name(peer_id),
+                        vessel_status: None,
+                        last_heartbeat_secs: Some(1),
+                        reputation: DEFAULT_REPUTATION,
+                    })
+                    .collect();
+                sender.send(connected_peers).ok();
+                None
+            }
+            NodeCommand::GetNodeVesselStatusesFromKademlia { sender } => {
+                let umbral_key = UmbralKey::new(None);
+                sender.try_send(NodeKeysWithVesselStatus {
+                    peer_id: vessel_peer,
+                    umbral_public_key: umbral_key.public_key,
+                    umbral_verifying_public_key: umbral_key.verifying_public_key,
+                    vessel_status: VesselStatus::ActiveVessel,
+                }).ok();
+                None
+            }
+            command => Some(command),
+        });
+
+        let connected_peers = node_client.list_connected_peers().await.unwrap();
+        assert_eq!(connected_peers.len(), 2);
+        let vessel = connected_peers.iter().find(|peer| peer.peer_id == vessel_peer).unwrap();
+        assert_eq!(vessel.vessel_status, Some(VesselStatus::ActiveVessel));
+        // no signed status on Kademlia
+        let unlisted = connected_peers.iter().find(|peer| peer.peer_id == unlisted_peer).unwrap();
+        assert_eq!(unlisted.vessel_status, None);
+    }
+
+    #[tokio::test]
+    async fn prospect_vessels_deprioritize_low_reputation_peers() {
+        let (node_client, command_receiver) = test_node_client(UmbralKey::new(None));
+
+        let peers: Vec<PeerId> = (0..4).map(|_| PeerId::random()).collect();
+        let faulty_peer = peers[0];
+        let reputations: HashMap<PeerId, f64> = peers.iter()
+            .map(|peer_id| (*peer_id, if *peer_id == faulty_peer { 0.1 } else { DEFAULT_REPUTATION }))
+            .collect();
+
+        let peers2 = peers.clone();
+        mock_network(command_receiver, move |command| match command {
+            NodeCommand::GetNodeVesselStatusesFromKademlia { sender } => {
+                for peer_id in peers2.iter() {
+                    let umbral_key = UmbralKey::new(None);
+                    sender.try_send(NodeKeysWithVesselStatus {
+                        peer_id: *peer_id,
+                        umbral_public_key: umbral_key.public_key,
+                        umbral_verifying_public_key: umbral_key.verifying_public_key,
+                        vessel_status: VesselStatus::EmptyVessel,
+                    }).ok();
+                }
+                None
+            }
+            NodeCommand::GetPeerReputations { sender } => {
+                sender.send(reputations.clone()).ok();
+                None
+            }
+            command => Some(command),
+        });
+
+        let (target_vessel, kfrag_providers) = node_client.get_prospect_vessels(true, &[]).await.unwrap();
+        assert_ne!(target_vessel.peer_id, faulty_peer);
+        assert_eq!(kfrag_providers.last().unwrap().peer_id, faulty_peer);
+    }
+
+    #[test]
+    fn sort_by_reputation_orders_unknown_peers_at_default_score() {
+        let trusted = PeerId::random();
+        let unknown = PeerId::random();
+        let faulty = PeerId::random();
+        let reputations = HashMap::from([(trusted, 0.9), (faulty, 0.2)]);
+
+        let mut peers = vec![faulty, unknown, trusted];
+        sort_by_reputation(&mut peers, |peer_id| *peer_id, &reputations);
+        assert_eq!(peers, vec![trusted, unknown, faulty]);
+    }
+
+    #[tokio::test]
+    async fn keyfrag_broadcast_skips_dropped_provider_and_dedups_retries() {
+        let vessel_key = UmbralKey::new(None);
+        let (mut node_client, command_receiver) = test_node_client(UmbralKey::new(None));
+
+        let (capsule, ciphertext) = vessel_key.encrypt_bytes(&b"secrets".to_vec()).unwrap();
+        let reverie = Reverie::new(
+            "test reverie".to_string(),
+            ReverieType::Memory,
+            2,
+            3,
+            vessel_key.public_key,
+            vessel_key.verifying_public_key,
+            AccessCondition::Umbral(vessel_key.public_key),
+            capsule,
+            ciphertext,
+        );
+
+        let target_vessel = PeerId::random();
+        let kfrag_providers = (0..3).map(|_| {
+            NodeKeysWithVesselStatus {
+                peer_id: PeerId::random(),
+                umbral_public_key: vessel_key.public_key,
+                umbral_verifying_public_key: vessel_key.verifying_public_key,
+                vessel_status: VesselStatus::EmptyVessel,
+            }
+        }).collect::<Vec<NodeKeysWithVesselStatus>>();
+        let kfrag_providers = assign_frag_nums(kfrag_providers);
+        // provider of frag_num(1) disconnects after being selected
+        let dropped_provider = kfrag_providers[1].peer_id;
+
+        let connected_peers = kfrag_providers.iter()
+            .map(|v| v.peer_id)
+            .filter(|peer_id| *peer_id != dropped_provider)
+            .collect::<Vec<PeerId>>();
+        let mut forwarded = mock_network(command_receiver, move |command| match command {
+            NodeCommand::GetConnectedPeers { responder } => {
+                responder.send(connected_peers.clone()).ok();
+                None
+            }
+            command => Some(command),
+        });
+
+        let report = node_client
+            .broadcast_reverie_keyfrags(&reverie, target_vessel, kfrag_providers.clone(), &[])
+            .await
+            .unwrap();
+        assert_eq!(report.failed, vec![1]);
+        assert_eq!(report.placed, vec![
+            (0, kfrag_providers[0].peer_id),
+            (2, kfrag_providers[2].peer_id),
+        ]);
+
+        // retrying the broadcast doesn't resend placed keyfrags
+        let retry_report = node_client
+            .broadcast_reverie_keyfrags(&reverie, target_vessel, kfrag_providers, &[])
+            .await
+            .unwrap();
+        assert_eq!(retry_report, report);
+
+        let commands = forwarded_commands(&node_client, &mut forwarded).await;
+        assert_eq!(sent_keyfrags(&commands), report.placed);
+    }
+
+    /// Commands the mock network forwarded instead of answering. The mock handles commands
+    /// in order, so once a get_connected_peers round trip returns, every command sent before
+    /// it has been forwarded.
+    async fn forwarded_commands(
+        node_client: &NodeClient,
+        forwarded: &mut mpsc::UnboundedReceiver<NodeCommand>,
+    ) -> Vec<NodeCommand> {
+        node_client.get_connected_peers().await.unwrap();
+        let mut commands = vec![];
+        while let Ok(command) = forwarded.try_recv() {
+            commands.push(command);
+        }
+        commands
+    }
+
+    /// (frag_num, kfrag provider) of each SendReverieKeyfrag, in the order they were sent
+    fn sent_keyfrags(commands: &[NodeCommand]) -> Vec<(FragmentNumber, PeerId)> {
+        commands.iter()
+            .filter_map(|command| match command {
+                NodeCommand::SendReverieKeyfrag { keyfrag_provider, reverie_keyfrag_msg } => {
+                    Some((reverie_keyfrag_msg.reverie_keyfrag.frag_num, *keyfrag_provider))
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn keyfrag_broadcast_distributes_ciphertext_by_reverie_type() {
+        let vessel_key = UmbralKey::new(None);
+        let agent_name_nonce = ReverieNameWithNonce("auron".to_string(), 0);
+        let cases = [
+            // Isolated in the vessel, never on the DHT
+            (ReverieType::SovereignAgent(agent_name_nonce.clone()), vec!["SendReverieToSpecificPeer"]),
+            (ReverieType::Agent(agent_name_nonce), vec!["SaveReverieOnNetwork", "SendReverieToSpecificPeer"]),
+            // Only fetched from the DHT
+            (ReverieType::APIKey("anthropic".to_string()), vec!["SaveReverieOnNetwork"]),
+            (ReverieType::Memory, vec!["SaveReverieOnNetwork", "SendReverieToSpecificPeer"]),
+            (ReverieType::Tools, vec!["SaveReverieOnNetwork"]),
+            (ReverieType::GithubRepo, vec!["SaveReverieOnNetwork"]),
+            (ReverieType::McpPlugin, vec!["SaveReverieOnNetwork", "SendReverieToSpecificPeer"]),
+        ];
+
+        for (reverie_type, expected_commands) in cases {
+            let (mut node_client, command_receiver) = test_node_client(UmbralKey::new(None));
+            let (capsule, ciphertext) = vessel_key.encrypt_bytes(&b"secrets".to_vec()).unwrap();
+            let reverie = Reverie::new(
+                "test reverie".to_string(),
+                reverie_type.clone(),
+                2,
+                3,
+                vessel_key.public_key,
+                vessel_key.verifying_public_key,
+                AccessCondition::Umbral(vessel_key.public_key),
+                capsule,
+                ciphertext,
+            );
+            let target_vessel = PeerId::random();
+            let kfrag_providers = (0..3).map(|_| {
+                NodeKeysWithVesselStatus {
+                    peer_id: PeerId::random(),
+                    umbral_public_key: vessel_key.public_key,
+                    umbral_verifying_public_key: vessel_key.verifying_public_key,
+                    vessel_status: VesselStatus::EmptyVessel,
+                }
+            }).collect::<Vec<NodeKeysWithVesselStatus>>();
+            let connected_peers = kfrag_providers.iter().map(|v| v.peer_id).collect::<Vec<PeerId>>();
+
+            let mut forwarded = mock_network(command_receiver, move |command| match command {
+                NodeCommand::GetConnectedPeers { responder } => {
+                    responder.send(connected_peers.clone()).ok();
+                    None
+                }
+                command => Some(command),
+            });
+
+            node_client
+                .broadcast_reverie_keyfrags(&reverie, target_vessel, kfrag_providers, &[])
+                .await
+                .unwrap();
+
+            let commands = forwarded_commands(&node_client, &mut forwarded).await;
+            let mut ciphertext_commands = commands.iter()
+                .filter_map(|command| match command {
+                    NodeCommand::SaveReverieOnNetwork { .. } => Some("SaveReverieOnNetwork"),
+                    NodeCommand::SendReverieToSpecificPeer { ciphertext_holder, .. } => {
+                        assert_eq!(*ciphertext_holder, target_vessel);
+                        Some("SendReverieToSpecificPeer")
+                    }
+                    _ => None,
+                })
+                .collect::<Vec<&str>>();
+            // The ciphertext goes to each of the type's destinations exactly once
+            ciphertext_commands.sort();
+            assert_eq!(ciphertext_commands, expected_commands, "{:?}", reverie_type);
+            let policy = DistributionPolicy::from(&reverie_type);
+            assert_eq!(ciphertext_commands.len(), policy.save_on_network as usize + policy.send_to_vessel as usize);
+            // and each keyfrag to a different provider
+            let kfrag_providers = sent_keyfrags(&commands).into_iter()
+                .map(|(_, peer_id)| peer_id)
+                .collect::<HashSet<PeerId>>();
+            assert_eq!(kfrag_providers.len(), 3, "{:?}", reverie_type);
+        }
+    }
+
+    #[test]
+    fn assign_frag_nums_covers_every_fragment_for_colliding_seeds() {
+        use crate::utils::pubkeys::generate_peer_keys;
+        let total_frags = 3;
+        // seeds 1, 4 and 7 are congruent mod total_frags, so `seed % total_frags` collides
+        let providers = [7, 1, 4].into_iter().map(|seed| {
+            let (peer_id, _, _, umbral_key) = generate_peer_keys(Some(seed));
+            NodeKeysWithVesselStatus {
+                peer_id,
+                umbral_public_key: umbral_key.public_key,
+                umbral_verifying_public_key: umbral_key.verifying_public_key,
+                vessel_status: VesselStatus::EmptyVessel,
+            }
+        }).collect::<Vec<NodeKeysWithVesselStatus>>();
+
+        let assigned = assign_frag_nums(providers.clone());
+        // Every frag_num has a provider, and no provider is given two frag_nums
+        let frag_num_peers = (0..total_frags)
+            .map(|frag_num| assigned.get(frag_num).map(|v| v.peer_id))
+            .collect::<Option<Vec<PeerId>>>()
+            .expect("every frag_num is assigned a provider");
+        assert_eq!(
+            frag_num_peers.iter().cloned().collect::<HashSet<PeerId>>(),
+            providers.iter().map(|v| v.peer_id).collect::<HashSet<PeerId>>()
+        );
+        assert!(assigned.get(total_frags).is_none());
+
+        // A respawning node given the providers in another order assigns the same frag_nums
+        let mut reordered = providers;
+        reordered.reverse();
+        assert_eq!(assign_frag_nums(reordered), assigned);
+    }
+
+    #[test]
+    fn split_target_vessel_excludes_target_from_providers_by_peer_id() {
+        let node_keys = |peer_id: PeerId| {
+            let umbral_key = UmbralKey::new(None);
+            NodeKeysWithVesselStatus {
+                peer_id,
+                umbral_public_key: umbral_key.public_key,
+                umbral_verifying_public_key: umbral_key.verifying_public_key,
+                vessel_status: VesselStatus::EmptyVessel,
+            }
+        };
+        let target = PeerId::random();
+        let provider = PeerId::random();
+
+        // The target and a provider each appear twice, with different keys
+        let candidates = vec![
+            node_keys(target),
+            node_keys(provider),
+            node_keys(target),
+            node_keys(provider),
+            node_keys(target),
+        ];
+        let (target_vessel, kfrag_providers) = split_target_vessel(candidates.clone()).unwrap();
+
+        assert_eq!(target_vessel, candidates[0]);
+        assert_eq!(kfrag_providers, vec![candidates[1].clone()]);
+        assert!(kfrag_providers.iter().all(|v| v.peer_id != target_vessel.peer_id));
+
+        assert!(split_target_vessel(vec![]).is_err());
+    }
+
+    #[tokio::test]
+    async fn keyfrag_broadcast_prefers_pinned_providers() {
+        let vessel_key = UmbralKey::new(None);
+        let (mut node_client, command_receiver) = test_node_client(UmbralKey::new(None));
+
+        let new_reverie = || {
+            let (capsule, ciphertext) = vessel_key.encrypt_bytes(&b"secrets".to_vec()).unwrap();
+            Reverie::new(
+                "test reverie".to_string(),
+                ReverieType::Memory,
+                2,
+                3,
+                vessel_key.public_key,
+                vessel_key.verifying_public_key,
+                AccessCondition::Umbral(vessel_key.public_key),
+                capsule,
+                ciphertext,
+            )
+        };
+
+        let target_vessel = PeerId::random();
+        let kfrag_providers = (0..6).map(|_| {
+            NodeKeysWithVesselStatus {
+                peer_id: PeerId::random(),
+                umbral_public_key: vessel_key.public_key,
+                umbral_verifying_public_key: vessel_key.verifying_public_key,
+                vessel_status: VesselStatus::EmptyVessel,
+            }
+        }).collect::<Vec<NodeKeysWithVesselStatus>>();
+
+        let connected_peers = kfrag_providers.iter().map(|v| v.peer_id).collect::<Vec<PeerId>>();
+        mock_network(command_receiver, move |command| match command {
+            NodeCommand::GetConnectedPeers { responder } => {
+                responder.send(connected_peers.clone()).ok();
+                None
+            }
+            command => Some(command),
+        });
+        // Frag_nums are assigned by sorted peer_id, so only which peers hold fragments is fixed
+        let placed_peers = |report: &KeyfragBroadcastReport| {
+            report.placed.iter().map(|(_, peer_id)| *peer_id).collect::<HashSet<PeerId>>()
+        };
+        let placed_frag_nums = |report: &KeyfragBroadcastReport| {
+            report.placed.iter().map(|(frag_num, _)| *frag_num).collect::<HashSet<FragmentNumber>>()
+        };
+
+        // Enough preferred providers: every fragment goes to them
+        let preferred = vec![kfrag_providers[5].peer_id, kfrag_providers[3].peer_id, kfrag_providers[4].peer_id];
+        let report = node_client
+            .broadcast_reverie_keyfrags(&new_reverie(), target_vessel, kfrag_providers.clone(), &preferred)
+            .await
+            .unwrap();
+        assert_eq!(placed_peers(&report), preferred.iter().cloned().collect());
+        assert_eq!(placed_frag_nums(&report), HashSet::from([0, 1, 2]));
+
+        // Too few preferred providers: the rest fall back to the highest ranked other providers
+        let preferred = vec![kfrag_providers[4].peer_id];
+        let report = node_client
+            .broadcast_reverie_keyfrags(&new_reverie(), target_vessel, kfrag_providers.clone(), &preferred)
+            .await
+            .unwrap();
+        assert_eq!(
+            placed_peers(&report),
+            HashSet::from([kfrag_providers[4].peer_id, kfrag_providers[0].peer_id, kfrag_providers[1].peer_id])
+        );
+        assert_eq!(placed_frag_nums(&report), HashSet::from([0, 1, 2]));
+
+        // A preferred provider that is the target vessel can't hold a fragment
+        let result = node_client
+            .broadcast_reverie_keyfrags(&new_reverie(), kfrag_providers[4].peer_id, kfrag_providers.clone(), &preferred)
+            .await;
+        assert!(result.is_err());
+
+        // As can a preferred provider that isn't an available empty vessel
+        let result = node_client
+            .broadcast_reverie_keyfrags(&new_reverie(), target_vessel, kfrag_providers.clone(), &[PeerId::random()])
+            .await;
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn lost_kfrag_providers_are_replaced_below_safety_margin() {
+        let vessel_key = UmbralKey::new(None);
+        let (mut node_client, command_receiver) = test_node_client(UmbralKey::new(None));
+
+        let (capsule, ciphertext) = vessel_key.encrypt_bytes(&b"secrets".to_vec()).unwrap();
+        // 2-of-4: with a safety margin of 1, at least 3 fragments must stay connected
+        let reverie = Reverie::new(
+            "test reverie".to_string(),
+            ReverieType::Memory,
+            2,
+            4,
+            vessel_key.public_key,
+            vessel_key.verifying_public_key,
+            AccessCondition::Umbral(vessel_key.public_key),
+            capsule,
+            ciphertext,
+        );
+        let safety_margin = 1;
+
+        let empty_vessel = |peer_id: PeerId| NodeKeysWithVesselStatus {
+            peer_id,
+            umbral_public_key: vessel_key.public_key,
+            umbral_verifying_public_key: vessel_key.verifying_public_key,
+            vessel_status: VesselStatus::EmptyVessel,
+        };
+        let target_vessel = PeerId::random();
+        let kfrag_providers = assign_frag_nums((0..4).map(|_| empty_vessel(PeerId::random())).collect());
+        let fresh_vessels = (0..2).map(|_| empty_vessel(PeerId::random())).collect::<Vec<_>>();
+
+        // Kademlia lists the original providers and the target vessel alongside fresh vessels
+        let mut node_vessels = kfrag_providers.clone();
+        node_vessels.push(empty_vessel(target_vessel));
+        node_vessels.extend(fresh_vessels.clone());
+
+        let connected_peers = Arc::new(std::sync::Mutex::new(
+            node_vessels.iter().map(|v| v.peer_id).collect::<Vec<PeerId>>()
+        ));
+        let connected_peers2 = connected_peers.clone();
+        let mut forwarded = mock_network(command_receiver, move |command| match command {
+            NodeCommand::GetConnectedPeers { responder } => {
+                responder.send(connected_peers2.lock().unwrap().clone()).ok();
+                None
+            }
+            NodeCommand::GetNodeVesselStatusesFromKademlia { sender } => {
+                for vessel in node_vessels.iter() {
+                    sender.try_send(vessel.clone()).ok();
+                }
+                None
+            }
+            NodeCommand::GetPeerReputations { sender } => {
+                sender.send(HashMap::new()).ok();
+                None
+            }
+            NodeCommand::SendReverieKeyfrag { ref reverie_keyfrag_msg, .. } => {
+                assert_eq!(reverie_keyfrag_msg.target_peer_id, target_vessel);
+                Some(command)
+            }
+            command => Some(command),
+        });
+
+        node_client
+            .broadcast_reverie_keyfrags(&reverie, target_vessel, kfrag_providers.clone(), &[])
+            .await
+            .unwrap();
+        forwarded_commands(&node_client, &mut forwarded).await;
+
+        let disconnect = |peer_id: PeerId| connected_peers.lock().unwrap().retain(|p| *p != peer_id);
+
+        // 3 of 4 providers still connected: the margin holds, nothing is re-sent
+        disconnect(kfrag_providers[1].peer_id);
+        let reports = node_client.replace_lost_kfrag_providers(safety_margin).await.unwrap();
+        assert!(reports.is_empty());
+
+        // 2 of 4 connected: both lost fragments go to the fresh vessels
+        disconnect(kfrag_providers[3].peer_id);
+        let reports = node_client.replace_lost_kfrag_providers(safety_margin).await.unwrap();
+        let report = &reports[&reverie.id];
+        assert!(report.failed.is_empty());
+        let replaced_frag_nums = report.placed.iter().map(|(frag_num, _)| *frag_num).collect::<Vec<_>>();
+        assert_eq!(replaced_frag_nums, vec![1, 3]);
+        let replacement_providers = report.placed.iter().map(|(_, peer_id)| *peer_id).collect::<HashSet<_>>();
+        assert_eq!(replacement_providers, fresh_vessels.iter().map(|v| v.peer_id).collect::<HashSet<_>>());
+
+        let commands = forwarded_commands(&node_client, &mut forwarded).await;
+        assert_eq!(sent_keyfrags(&commands), report.placed);
+
+        // The replacements restore the margin
+        let reports = node_client.replace_lost_kfrag_providers(safety_margin).await.unwrap();
+        assert!(reports.is_empty());
+    }
+
+    #[test]
+    fn frag_nums_to_replace_counts_distinct_connected_fragments() {
+        let peers = (0..4).map(|_| PeerId::random()).collect::<Vec<PeerId>>();
+        // frag 0 was re-sent to a second provider, frag 2 never placed
+        let placements = vec![(0, peers[0]), (0, peers[1]), (1, peers[2]), (3, peers[3])];
+        let connected = peers.iter().cloned().collect::<HashSet<PeerId>>();
+
+        // 3 distinct fragments connected, duplicates of frag 0 don't count twice
+        assert!(frag_nums_to_replace(&placements, &connected, 2, 4, 1).is_empty());
+        assert_eq!(frag_nums_to_replace(&placements, &connected, 2, 4, 2), vec![2]);
+        // Only frags 0 and 1 connected
+        let connected = HashSet::from([peers[1], peers[2]]);
+        assert_eq!(frag_nums_to_replace(&placements, &connected, 2, 4, 1), vec![2, 3]);
+
+        // The margin is capped at total_frags, so holding every fragment is enough
+        let placements = vec![(0, peers[0]), (1, peers[1]), (2, peers[2])];
+        let connected = peers.iter().cloned().collect::<HashSet<PeerId>>();
+        assert!(frag_nums_to_replace(&placements, &connected, 2, 3, 5).is_empty());
+    }
+
+    #[tokio::test]
+    async fn spawn_fails_fast_with_too_few_connected_empty_vessels() {
+        let (node_client, command_receiver) = test_node_client(UmbralKey::new(None));
+
+        let vessel = |peer_id: PeerId, vessel_status: VesselStatus| {
+            let umbral_key = UmbralKey::new(None);
+            NodeKeysWithVesselStatus {
+                peer_id,
+                umbral_public_key: umbral_key.public_key,
+                umbral_verifying_public_key: umbral_key.verifying_public_key,
+                vessel_status,
+            }
+        };
+        let connected_peers = (0..4).map(|_| PeerId::random()).collect::<Vec<PeerId>>();
+        let mut node_vessels = connected_peers[..3].iter()
+            .map(|peer_id| vessel(*peer_id, VesselStatus::EmptyVessel))
+            .collect::<Vec<_>>();
+        // A duplicate record, a disconnected vessel and an occupied vessel don't count
+        node_vessels.push(vessel(connected_peers[0], VesselStatus::EmptyVessel));
+        node_vessels.push(vessel(PeerId::random(), VesselStatus::EmptyVessel));
+        node_vessels.push(vessel(connected_peers[3], VesselStatus::ActiveVessel));
+
+        let mut forwarded = mock_network(command_receiver, move |command| match command {
+            NodeCommand::GetConnectedPeers { responder } => {
+                responder.send(connected_peers.clone()).ok();
+                None
+            }
+            NodeCommand::GetNodeVesselStatusesFromKademlia { sender } => {
+                for v in node_vessels.iter() {
+                    sender.try_send(v.clone()).ok();
+                }
+                None
+            }
+            command => Some(command),
+        });
+
+        // 3 keyfrags and a target vessel need 4 connected empty vessels
+        let err = node_client.check_enough_spawn_vessels(3).await.unwrap_err();
+        assert!(err.to_string().contains("need 4, connected 3"), "{}", err);
+        assert_eq!(node_client.check_enough_spawn_vessels(2).await.unwrap(), 3);
+
+        // No keyfrags were generated or sent
+        assert!(node_client.keyfrag_broadcasts.lock().unwrap().keyfrags.is_empty());
+        assert!(sent_keyfrags(&forwarded_commands(&node_client, &mut forwarded).await).is_empty());
+    }
+}
@@ -1,10 +1,12 @@
-use color_eyre::Result;
+use std::time::Duration;
+use color_eyre::{Result, eyre::anyhow};
 use futures::FutureExt;
 use libp2p::{PeerId, Multiaddr};
 use tokio::sync::{mpsc, oneshot};
-use tracing::{info, error};
+use tracing::{info, warn, error};
 use p256::ecdsa::VerifyingKey as P256VerifyingKey;
 
+use crate::create_network::NetworkConfig;
 use crate::env_var::EnvVars;
 use crate::{short_peer_id, SendError};
 use crate::network_events::NodeIdentity;
@@ -45,6 +47,46 @@ impl NodeClient {
         Ok(())
     }
 
+    /// Bootstrap peers may start after this node, failing its initial dials. Re-dials them
+    /// with backoff until a connection is established, then re-runs Kademlia bootstrap.
+    pub async fn retry_bootstrap_until_connected(
+        &self,
+        bootstrap_addrs: Vec<Multiaddr>,
+        network_config: NetworkConfig,
+    ) -> Result<()> {
+        let mut attempt = 0;
+        loop {
+            tokio::time::sleep(network_config.bootstrap_retry_backoff(attempt)).await;
+
+            let (sender, receiver) = oneshot::channel();
+            self.command_sender
+                .send(NodeCommand::RetryBootstrap {
+                    bootstrap_addrs: bootstrap_addrs.clone(),
+                    sender
+                })
+                .await?;
+
+            if receiver.await? {
+                info!("Connected to the network, re-ran Kademlia bootstrap after {} retries", attempt);
+                return Ok(());
+            }
+            attempt = attempt.saturating_add(1);
+            warn!("No bootstrap peers reachable yet, retry {} in {:?}",
+                attempt,
+                network_config.bootstrap_retry_backoff(attempt)
+            );
+        }
+    }
+
+    /// Signals the network event loop to flush vessel reveries to Kademlia and exit,
+    /// waiting up to `timeout` for it to finish.
+    pub async fn shutdown(&self, timeout: Duration) -> Result<()> {
+        self.shutdown_sender.send_replace(true);
+        tokio::time::timeout(timeout, self.command_sender.closed())
+            .await
+            .map_err(|_| anyhow!("Network event loop did not shut down within {:?}", timeout))
+    }
+
     pub async fn listen_to_network_events(
         &mut self,
         mut network_event_receiver: mpsc::Receiver<NetworkEvent>
@@ -74,6 +116,12 @@ impl NodeClient {
                             Err(e) => error!("Error handling respawn request: {:?}", e),
                         };
                     }
+                    Some(NetworkEvent::DuplicateVesselDetected { agent_name, peer_id }) => {
+                        warn!("Duplicate vessel {} detected for agent {}, told it to stand down",
+                            short_peer_id(&peer_id),
+                            agent_name
+                        );
+                    }
                     event => panic!("Error <network_event_receiver>: {:?}", event),
                 }
             }
@@ -0,0 +1,93 @@
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Upper bounds of the reconstruction duration histogram buckets, in milliseconds.
+/// Durations above the last bucket are only counted in `count` and `sum_ms`.
+const BUCKET_BOUNDS_MS: [u64; 9] = [10, 50, 100, 250, 500, 1_000, 2_500, 5_000, 10_000];
+
+/// Stages of reconstructing a Reverie's plaintext from its kfrag providers' cfrags
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ReconstructionStage {
+    RequestCfrags,
+    ParseCfrags,
+    DecryptCfrags,
+    Total,
+}
+
+impl ReconstructionStage {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ReconstructionStage::RequestCfrags => "request_cfrags",
+            ReconstructionStage::ParseCfrags => "parse_cfrags",
+            ReconstructionStage::DecryptCfrags => "decrypt_cfrags",
+            ReconstructionStage::Total => "total",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DurationHistogram {
+    /// Cumulative count of durations at or under each bound in BUCKET_BOUNDS_MS
+    pub buckets: [u64; BUCKET_BOUNDS_MS.len()],
+    pub count: u64,
+    pub sum_ms: u64,
+}
+
+impl DurationHistogram {
+    fn observe(&mut self, elapsed: Duration) {
+        let elapsed_ms = elapsed.as_millis() as u64;
+        for (bucket, bound) in self.buckets.iter_mut().zip(BUCKET_BOUNDS_MS.iter()) {
+            if elapsed_ms <= *bound {
+                *bucket += 1;
+            }
+        }
+        self.count += 1;
+        self.sum_ms += elapsed_ms;
+    }
+}
+
+/// Duration histograms for each reconstruction stage, labeled by reverie type.
+/// Shared between NodeClient clones.
+#[derive(Debug, Clone, Default)]
+pub struct ReconstructionMetrics {
+    histograms: Arc<Mutex<BTreeMap<(ReconstructionStage, &'static str), DurationHistogram>>>,
+}
+
+impl ReconstructionMetrics {
+    pub fn record(&self, stage: ReconstructionStage, reverie_type: &'static str, elapsed: Duration) {
+        self.histograms.lock()
+            .expect("reconstruction metrics lock poisoned")
+            .entry((stage, reverie_type))
+            .or_default()
+            .observe(elapsed);
+    }
+
+    pub fn histogram(&self, stage: ReconstructionStage, reverie_type: &'static str) -> Option<DurationHistogram> {
+        self.histograms.lock()
+            .expect("reconstruction metrics lock poisoned")
+            .get(&(stage, reverie_type))
+            .cloned()
+    }
+
+    pub fn metrics(&self) -> serde_json::Value {
+        let histograms = self.histograms.lock().expect("reconstruction metrics lock poisoned");
+        let mut metrics = serde_json::Map::new();
+        for ((stage, reverie_type), histogram) in histograms.iter() {
+            let buckets: serde_json::Map<String, serde_json::Value> = BUCKET_BOUNDS_MS.iter()
+                .zip(histogram.buckets.iter())
+                .map(|(bound, count)| (format!("le_{}ms", bound), serde_json::json!(count)))
+                .collect();
+            metrics.entry(reverie_type.to_string())
+                .or_insert_with(|| serde_json::json!({}))
+                .as_object_mut()
+                .expect("reverie_type entry is an object")
+                .insert(stage.as_str().to_string(), serde_json::json!({
+                    "buckets": buckets,
+                    "count": histogram.count,
+                    "sum_ms": histogram.sum_ms,
+                }));
+        }
+        serde_json::Value::Object(metrics)
+    }
+}
@@ -2,10 +2,13 @@ use color_eyre::{Result, eyre::anyhow};
 use colored::Colorize;
 use libp2p::PeerId;
 use tracing::{info, debug, error, warn};
-use sha3::{Digest, Keccak256};
+use serde::{Serialize, de::DeserializeOwned};
+use tokio::sync::oneshot;
+use std::time::Duration;
 
 use crate::network_events::NodeIdentity;
 use crate::types::{
+    PreKeyfragParams,
     ReverieNameWithNonce,
     NetworkEvent,
     RespawnId,
@@ -15,14 +18,23 @@ use crate::types::{
     ReverieType,
     AgentVesselInfo,
     AccessCondition,
-    AccessKey,
+    ReverieKeyfrag,
+    ReverieKeyfragMessage,
+    ReverieMessage,
+    check_reverie_payload_size,
 };
+use crate::SendError;
 use crate::behaviour::heartbeat_behaviour::TeePayloadOutEvent;
 use runtime::reencrypt::{UmbralKey, VerifiedCapsuleFrag};
 use runtime::llm::AgentSecretsJson;
 
 use super::commands::NodeCommand;
-use super::NodeClient;
+use super::{NodeClient, deserialize_and_zeroize};
+use crate::get_node_name;
+
+/// Time a successor vessel has to receive and reincarnate a handed off agent
+const HANDOFF_CONFIRMATION_TIMEOUT: Duration = Duration::from_secs(30);
+const HANDOFF_POLL_INTERVAL: Duration = Duration::from_secs(1);
 
 
 
@@ -35,12 +47,23 @@ impl NodeClient {
         agent_secrets: AgentSecretsJson,
         threshold: usize,
         total_frags: usize,
+        description: String,
+        tags: Vec<String>,
+        keyfrag_params: PreKeyfragParams,
+        expires_at: Option<i64>, // unix timestamp after which kfrag providers stop releasing cfrags
     ) -> Result<NodeKeysWithVesselStatus> {
 
         if threshold > total_frags {
             return Err(anyhow!("Threshold must be less than or equal to total fragments"));
         }
 
+        // reject malformed API keys before they are encrypted and broadcast
+        agent_secrets.validate_api_keys()?;
+        if agent_secrets.api_keys().is_empty() {
+            warn!("Spawning agent {} without any LLM API keys", agent_secrets.agent_name);
+        }
+        debug!("Spawning agent: {:?}", agent_secrets.redacted());
+
         let agent_name_nonce = ReverieNameWithNonce(
             agent_secrets.agent_name.clone(),
             agent_secrets.agent_nonce.clone()
@@ -50,17 +73,19 @@ impl NodeClient {
         let (
             target_vessel,
             target_kfrag_providers
-        ) = self.get_prospect_vessels(false).await?;
+        ) = self.get_prospect_vessels(false, &[]).await?;
 
         // Create a "Reverie"––an encrypted memory that alters how a Host behaves
+        let plaintext = serde_json::to_vec(&agent_secrets)?;
+        check_reverie_payload_size(plaintext.len(), self.max_reverie_payload_size)?;
+
         let (
             capsule,
             ciphertext
-        ) = self.umbral_key.encrypt_bytes(&serde_json::to_vec(&agent_secrets)?)?;
+        ) = self.umbral_key().encrypt_bytes(&plaintext)?;
 
         let reverie = Reverie::new(
-            "secrets description".to_string(),
-            // secrets description: generated by an LLM who looks at the secrets/MCP/executable
+            description,
             ReverieType::SovereignAgent(agent_name_nonce),
             threshold,
             total_frags,
@@ -69,9 +94,12 @@ impl NodeClient {
             AccessCondition::Umbral(target_vessel.umbral_verifying_public_key),
             capsule,
             ciphertext
-        );
+        )
+        .with_tags(tags)
+        .with_keyfrag_params(keyfrag_params)
+        .with_expiry(expires_at);
 
-        self.broadcast_reverie_keyfrags(&reverie, target_vessel.peer_id, target_kfrag_providers).await?;
+        self.broadcast_reverie_keyfrags(&reverie, target_vessel.peer_id, target_kfrag_providers, &[]).await?;
 
         info!("RequestResponse broadcast of kfrags complete.");
 
@@ -89,9 +117,13 @@ impl NodeClient {
     ) -> Result<()> {
 
         let prev_agent_name_nonce = match &prev_reverie_type {
-            ReverieType::SovereignAgent(agent_name_nonce) => agent_name_nonce.clone(),
-            _ => return Err(anyhow!("Previous Reverie is not a SovereignAgent")),
+            ReverieType::SovereignAgent(agent_name_nonce)
+                | ReverieType::Agent(agent_name_nonce) => agent_name_nonce.clone(),
+            _ => return Err(anyhow!("Previous Reverie is not an agent")),
         };
+        // the next incarnation keeps the agent's ReverieType variant
+        let next_reverie_type = prev_reverie_type.next_incarnation()
+            .ok_or_else(|| anyhow!("Previous Reverie is not an agent"))?;
         info!("\nHandle respawn request: {:?}", prev_agent_name_nonce);
         info!("total_frags: {:?}", total_frags);
         info!("next_vessel_peer_id: {:?}", next_vessel_peer_id);
@@ -101,11 +133,10 @@ impl NodeClient {
         let next_agent = prev_agent_name_nonce.increment_nonce();
         let next_nonce = next_agent.nonce();
 
-        let mut agent_secrets_json = self.reconstruct_agent_secrets_cfrags(
-            prev_reverie_id.clone(),
-            prev_reverie_type,
-            prev_failed_vessel_peer_id
-        ).await?;
+        // Fetched here rather than through reconstruct_agent_secrets_cfrags,
+        // so the respawned reverie keeps its description and tags
+        let prev_reverie_msg = self.get_reverie(&prev_reverie_id, prev_reverie_type).await?;
+        let mut agent_secrets_json: AgentSecretsJson = self.reconstruct_vessel_reverie(&prev_reverie_msg).await?;
 
         agent_secrets_json.agent_nonce = next_nonce;
 
@@ -115,6 +146,9 @@ impl NodeClient {
         // 3. mark respawn complete / old vessel died
 
         // 1. Test LLM API key from decrypted Reverie works
+        if let Err(e) = agent_secrets_json.validate_api_keys() {
+            warn!("Respawning agent {} with malformed API keys: {}", agent_secrets_json.agent_name, e);
+        }
         if let Some(_anthropic_api_key) = agent_secrets_json.anthropic_api_key.clone() {
             info!("Decrypted LLM API keys, querying LLM (paused)");
             // let response = runtime::llm::test_claude_query(
@@ -129,26 +163,31 @@ impl NodeClient {
         let (
             target_vessel,
             target_kfrag_providers
-        ) = self.get_prospect_vessels(false).await?;
+        ) = self.get_prospect_vessels(false, &[]).await?;
 
         // 3. re-encrypt secrets + provide TEE attestation of it
         let reverie = self.create_reverie(
             agent_secrets_json.clone(),
-            ReverieType::SovereignAgent(next_agent.clone()),
+            next_reverie_type,
+            prev_reverie_msg.reverie.description.clone(),
+            prev_reverie_msg.reverie.tags.clone(),
             threshold,
             total_frags,
             target_vessel.umbral_public_key,
             // in this case verifying_public_key is the same as the access_key
             target_vessel.umbral_verifying_public_key,
             AccessCondition::Umbral(target_vessel.umbral_verifying_public_key),
-        )?;
+        )?
+        .with_keyfrag_params(prev_reverie_msg.reverie.keyfrag_params)
+        .with_expiry(prev_reverie_msg.reverie.expires_at);
         info!("Encrypted Secrets:\n{}", format!("{}", hex::encode(reverie.umbral_ciphertext.clone())).black());
 
         // 4. broadcast keyfrags to new providers
         self.broadcast_reverie_keyfrags(
             &reverie,
             target_vessel.peer_id,
-            target_kfrag_providers
+            target_kfrag_providers,
+            &[],
         ).await?;
 
         // 5. mark respawn complete / old vessel died
@@ -172,37 +211,310 @@ impl NodeClient {
     ) -> Result<AgentSecretsJson> {
 
         let reverie_msg = self.get_reverie(&prev_reverie_id, prev_reverie_type).await?;
-        let prev_kfrag_providers = reverie_msg.keyfrag_providers.clone();
-        let capsule = reverie_msg.reverie.encode_capsule()?;
-
-        // target vessel creates signature by signing the digest hash of reverie_id
-        let digest = Keccak256::digest(reverie_msg.reverie.id.clone().as_bytes());
-        // Sign with our umbral signer key (corresponds to the verifying key)
-        let umbral_signature = self.umbral_key.sign(&digest);
-        let cfrags_raw = self.request_cfrags(
-            &prev_reverie_id,
-            prev_kfrag_providers,
-            AccessKey::UmbralSignature(
-                serde_json::to_vec(&umbral_signature)
-                    .expect("Failed to serialize umbral signature"))
-        ).await;
+        self.reconstruct_vessel_reverie(&reverie_msg).await
+    }
 
-        let (
-            verified_cfrags,
-            delegator_pubkey,
-            target_verifying_pubkey,
-            access_pubkey,
-            total_frags_received
-        ) = self.parse_cfrags(cfrags_raw, capsule.clone())?;
-
-        let next_agent_secrets = self.decrypt_cfrags(
-            capsule,
-            reverie_msg.reverie.umbral_ciphertext,
-            delegator_pubkey,
-            verified_cfrags,
-        );
+    /// Requests cfrags for a Reverie targeting this node as the vessel, signing
+    /// the reverie_id with our Umbral signer to meet its access condition, then decrypts it.
+    async fn reconstruct_vessel_reverie<T: Serialize + DeserializeOwned>(
+        &self,
+        reverie_msg: &ReverieMessage,
+    ) -> Result<T> {
+
+        // target vessel signs the reverie_id with our umbral signer key (corresponds to the verifying key)
+        self.reconstruct_reverie(
+            reverie_msg,
+            self.sign_access(&reverie_msg.reverie.id),
+        ).await
+    }
 
-        next_agent_secrets
+    /// Replaces this node's Umbral key with a fresh one and returns the new public key.
+    ///
+    /// Cfrags for Reveries where this node is the target vessel are re-encrypted to
+    /// the old key, so those Reveries are first reconstructed with the old key, then
+    /// re-encrypted to the new key under the same reverie_id and sent back out to
+    /// their kfrag providers. Reveries gated by a non-Umbral access condition are
+    /// skipped, as re-keying them needs that access key. Refused unless this node's
+    /// NEAR signer owns every reverie being re-keyed on-chain.
+    ///
+    /// The new key is staged and only replaces the old one once its pubkeys are
+    /// published and every reverie is re-keyed. On error the old key is kept.
+    pub async fn rotate_umbral_key(&self) -> Result<umbral_pre::PublicKey> {
+
+        // 1. Reconstruct vessel reveries while we still hold the old key
+        let vessel_reveries = self.get_vessel_reveries().await?;
+        let mut reconstructed = vec![];
+        for reverie_msg in vessel_reveries {
+            if let AccessCondition::Umbral(..) = reverie_msg.reverie.access_condition {
+                // checked before any reverie is re-keyed, so a denial leaves every reverie on the old key
+                self.ensure_reverie_owner(&reverie_msg.reverie.id).await?;
+                let secrets: serde_json::Value = self.reconstruct_vessel_reverie(&reverie_msg).await?;
+                reconstructed.push((reverie_msg, secrets));
+            } else {
+                warn!("Skipping re-key of reverie {}: access condition is not Umbral", reverie_msg.reverie.id);
+            }
+        }
+
+        // 2. Stage a new key, and re-encrypt each reverie to it before anything is sent
+        let old_umbral_key = self.umbral_key();
+        let new_umbral_key = UmbralKey::new(None);
+        let mut rekeyed = vec![];
+        for (prev_reverie_msg, secrets) in reconstructed {
+            let prev_reverie = &prev_reverie_msg.reverie;
+            let mut reverie = self.create_reverie_with_key(
+                &new_umbral_key,
+                secrets,
+                prev_reverie.reverie_type.clone(),
+                prev_reverie.description.clone(),
+                prev_reverie.tags.clone(),
+                prev_reverie.threshold,
+                prev_reverie.total_frags,
+                new_umbral_key.public_key,
+                new_umbral_key.verifying_public_key,
+                AccessCondition::Umbral(new_umbral_key.verifying_public_key),
+            )?;
+            // Keep the reverie_id so agent name => reverie_id records stay valid,
+            // and kfrag providers overwrite the fragments they hold for it.
+            reverie.id = prev_reverie.id.clone();
+            reverie.expires_at = prev_reverie.expires_at;
+            reverie.keyfrag_params = prev_reverie.keyfrag_params;
+
+            if prev_reverie_msg.keyfrag_providers.len() < reverie.total_frags {
+                return Err(anyhow!(
+                    "Reverie {} has {} kfrag providers, need: {}",
+                    reverie.id,
+                    prev_reverie_msg.keyfrag_providers.len(),
+                    reverie.total_frags
+                ));
+            }
+            let kfrags = self.create_reverie_keyfrags_with_key(&new_umbral_key, &reverie)?;
+            rekeyed.push((reverie, kfrags, prev_reverie_msg));
+        }
+
+        // 3. Publish the new pubkeys on Kademlia and re-distribute kfrags.
+        // If either fails, republish the old key, which this node still holds.
+        let published = async {
+            self.publish_umbral_key(new_umbral_key.clone()).await?;
+            for (reverie, kfrags, prev_reverie_msg) in rekeyed {
+                self.rekey_vessel_reverie(reverie, kfrags, prev_reverie_msg).await?;
+            }
+            Ok::<_, color_eyre::Report>(())
+        }.await;
+
+        if let Err(e) = published {
+            error!("Umbral key rotation failed, restoring old key: {}", e);
+            if let Err(restore_err) = self.publish_umbral_key(old_umbral_key).await {
+                error!("Failed to republish old Umbral key: {}", restore_err);
+            }
+            return Err(e);
+        }
+
+        // 4. Commit the new key
+        *self.umbral_key.write().expect("umbral_key lock poisoned") = new_umbral_key.clone();
+
+        info!("{}", format!("Rotated Umbral key: {}", new_umbral_key.public_key).green());
+        Ok(new_umbral_key.public_key)
     }
 
+    /// Sets the Umbral key the network event loop uses, and publishes its pubkeys on Kademlia.
+    async fn publish_umbral_key(&self, umbral_key: UmbralKey) -> Result<()> {
+        let (sender, receiver) = oneshot::channel();
+        self.command_sender.send(NodeCommand::RotateUmbralKey {
+            umbral_key,
+            sender,
+        }).await?;
+        receiver.await.map_err(SendError::from)??;
+        Ok(())
+    }
+
+    /// Migrates the agent this node is the current vessel for to a chosen successor,
+    /// e.g. before planned maintenance, rather than waiting for heartbeats to time out.
+    ///
+    /// The agent's reverie is re-keyed to the successor, which is then asked to reincarnate
+    /// it. Once the successor publishes the agent's next incarnation, this node zeroizes its
+    /// copy of the agent's keyfrags and drops the agent. If the successor doesn't receive or
+    /// refuses the agent within HANDOFF_CONFIRMATION_TIMEOUT, the handoff is aborted and this
+    /// node keeps the agent. Once the successor accepts, this node drops the agent even if the
+    /// next incarnation isn't confirmed in time, so the agent never runs in two vessels.
+    pub async fn handoff_vessel(&self, target_peer_id: PeerId) -> Result<()> {
+
+        let agent_vessel = self.current_vessel_agent().await?
+            .filter(|agent_vessel| agent_vessel.current_vessel_peer_id == self.node_id.peer_id)
+            .ok_or_else(|| anyhow!("No agent to hand off, this node is not a current vessel"))?;
+        let agent_name_nonce = match &agent_vessel.reverie_type {
+            ReverieType::Agent(name) | ReverieType::SovereignAgent(name) => name.clone(),
+            _ => return Err(anyhow!("Reverie {} is not an agent", agent_vessel.reverie_id)),
+        };
+        let next_reverie_type = agent_vessel.reverie_type.next_incarnation()
+            .ok_or_else(|| anyhow!("Reverie {} is not an agent", agent_vessel.reverie_id))?;
+        if target_peer_id == self.node_id.peer_id {
+            return Err(anyhow!("Cannot hand off agent {} to this node", agent_name_nonce));
+        }
+        if !self.get_connected_peers().await?.contains(&target_peer_id) {
+            return Err(anyhow!("Successor {} is not connected", get_node_name(&target_peer_id)));
+        }
+        // re-keying the agent to the successor is reserved to its on-chain owner
+        self.ensure_reverie_owner(&agent_vessel.reverie_id).await?;
+        let successor = self.get_node_vessels(false).await
+            .into_iter()
+            .find(|vessel| vessel.peer_id == target_peer_id)
+            .ok_or_else(|| anyhow!("No Umbral keys found for successor {}", get_node_name(&target_peer_id)))?;
+
+        info!("{}", format!("Handing off agent {} to {}", agent_name_nonce, get_node_name(&target_peer_id)).yellow());
+
+        // 1. Decrypt the agent, which this node encrypted when it became the current vessel
+        let prev_reverie_msg = self.get_reverie(&agent_vessel.reverie_id, agent_vessel.reverie_type.clone()).await?;
+        let prev_reverie = &prev_reverie_msg.reverie;
+        let capsule: umbral_pre::Capsule = serde_json::from_slice(&prev_reverie.umbral_capsule)?;
+        let mut plaintext = self.umbral_key()
+            .decrypt_original(&capsule, &prev_reverie.umbral_ciphertext)?
+            .into_vec();
+        let agent_secrets: AgentSecretsJson = deserialize_and_zeroize(&mut plaintext)?;
+
+        // 2. Re-key the agent to the successor, keeping its incarnation
+        let reverie = self.create_reverie(
+            agent_secrets,
+            agent_vessel.reverie_type.clone(),
+            prev_reverie.description.clone(),
+            prev_reverie.tags.clone(),
+            prev_reverie.threshold,
+            prev_reverie.total_frags,
+            successor.umbral_public_key,
+            successor.umbral_verifying_public_key,
+            AccessCondition::Umbral(successor.umbral_verifying_public_key),
+        )?
+        .with_keyfrag_params(prev_reverie.keyfrag_params)
+        .with_expiry(prev_reverie.expires_at);
+
+        // the successor holds the ciphertext, so it can't also hold a keyfrag
+        let (next_vessel, kfrag_providers) = self.get_prospect_vessels(false, &[]).await?;
+        let kfrag_providers = std::iter::once(next_vessel)
+            .chain(kfrag_providers)
+            .filter(|provider| provider.peer_id != target_peer_id)
+            .collect();
+        self.broadcast_reverie_keyfrags(
+            &reverie,
+            target_peer_id,
+            kfrag_providers,
+            &prev_reverie_msg.keyfrag_providers,
+        ).await?;
+
+        // 3. Ask the successor to reincarnate the agent, once it holds the re-keyed reverie
+        let deadline = tokio::time::Instant::now() + HANDOFF_CONFIRMATION_TIMEOUT;
+        while self.request_reverie_from_holder(&reverie.id, target_peer_id).await.is_err() {
+            if tokio::time::Instant::now() > deadline {
+                return Err(anyhow!("Handoff aborted: {} did not receive agent {}", get_node_name(&target_peer_id), agent_name_nonce));
+            }
+            tokio::time::sleep(HANDOFF_POLL_INTERVAL).await;
+        }
+
+        let (sender, receiver) = oneshot::channel();
+        self.command_sender.send(NodeCommand::RequestVesselHandoff {
+            agent_name_nonce: agent_name_nonce.clone(),
+            target_peer_id,
+            sender,
+        }).await?;
+        receiver.await.map_err(SendError::from)?
+            .map_err(|e| anyhow!("Handoff aborted: {} refused agent {}: {}", get_node_name(&target_peer_id), agent_name_nonce, e))?;
+
+        // 4. Confirm the successor reconstructed the agent: only it can publish the next incarnation.
+        // This node doesn't hold it, so it's read from the network rather than locally.
+        let next_agent = agent_name_nonce.increment_nonce();
+        let confirmed = loop {
+            if let Some(reverie_id) = self.get_reverie_id_by_name(&next_agent).await {
+                let next_reverie_msg = match next_reverie_type {
+                    // held by the next vessel, found through its signed holder record on the DHT
+                    ReverieType::SovereignAgent(..) => self.get_reverie_from_holders(&reverie_id).await.ok().flatten(),
+                    _ => self.get_reverie(&reverie_id, next_reverie_type.clone()).await.ok(),
+                };
+                if next_reverie_msg.is_some_and(|reverie_msg| {
+                    reverie_msg.source_peer_id == target_peer_id
+                        && reverie_msg.reverie.reverie_type == next_reverie_type
+                }) {
+                    break true
+                }
+            }
+            if tokio::time::Instant::now() > deadline {
+                break false
+            }
+            tokio::time::sleep(HANDOFF_POLL_INTERVAL).await;
+        };
+
+        // 5. Zeroize this node's keyfrags for the agent and drop it from vessel metadata.
+        // The successor has accepted and is reincarnating the agent, so this node
+        // stands down even if the next incarnation wasn't confirmed in time.
+        {
+            let mut keyfrag_broadcasts = self.keyfrag_broadcasts.lock().unwrap();
+            keyfrag_broadcasts.forget(&prev_reverie.id);
+            keyfrag_broadcasts.forget(&reverie.id);
+        }
+        let (sender, receiver) = oneshot::channel();
+        self.command_sender.send(NodeCommand::ReleaseVesselAgent {
+            agent_name_nonce: agent_name_nonce.clone(),
+            sender,
+        }).await?;
+        receiver.await.map_err(SendError::from)?;
+
+        if !confirmed {
+            return Err(anyhow!(
+                "{} accepted agent {} but did not confirm reincarnating it, released it anyway",
+                get_node_name(&target_peer_id),
+                agent_name_nonce
+            ));
+        }
+        info!("{}", format!("Handed off agent {} to {}", agent_name_nonce, get_node_name(&target_peer_id)).green());
+        Ok(())
+    }
+
+    /// Reincarnates an agent on this node, which must be the agent's next vessel,
+    /// without waiting for its current vessel's heartbeats to fail.
+    pub async fn force_reincarnate(&self, agent_name: String, agent_nonce: usize) -> Result<RespawnId> {
+        let (sender, receiver) = oneshot::channel();
+        self.command_sender.send(NodeCommand::ForceReincarnate {
+            agent_name_nonce: ReverieNameWithNonce(agent_name, agent_nonce),
+            sender,
+        }).await?;
+        receiver.await.map_err(|e| anyhow!(e.to_string()))?
+    }
+
+    async fn get_vessel_reveries(&self) -> Result<Vec<ReverieMessage>> {
+        let (sender, receiver) = oneshot::channel();
+        self.command_sender.send(NodeCommand::GetVesselReveries { sender }).await?;
+        receiver.await.map_err(|e| anyhow!(e.to_string()))
+    }
+
+    /// Sends kfrags of a re-keyed reverie to its previous kfrag providers, and stores
+    /// the new ciphertext locally. The original broadcaster is kept as source_peer_id
+    /// so respawn tracking is unchanged.
+    async fn rekey_vessel_reverie(
+        &self,
+        reverie: Reverie,
+        kfrags: Vec<ReverieKeyfrag>,
+        prev_reverie_msg: ReverieMessage,
+    ) -> Result<()> {
+
+        let kfrag_providers = prev_reverie_msg.keyfrag_providers;
+        for (reverie_keyfrag, keyfrag_provider) in kfrags.into_iter().zip(kfrag_providers.iter()) {
+            self.command_sender.send(NodeCommand::SendReverieKeyfrag {
+                keyfrag_provider: *keyfrag_provider,
+                reverie_keyfrag_msg: ReverieKeyfragMessage {
+                    reverie_keyfrag,
+                    source_peer_id: prev_reverie_msg.source_peer_id,
+                    target_peer_id: self.node_id.peer_id,
+                },
+            }).await?;
+        }
+
+        self.command_sender.send(NodeCommand::SendReverieToSpecificPeer {
+            ciphertext_holder: self.node_id.peer_id,
+            reverie_msg: ReverieMessage {
+                reverie,
+                source_peer_id: prev_reverie_msg.source_peer_id,
+                target_peer_id: self.node_id.peer_id,
+                keyfrag_providers: kfrag_providers,
+            },
+        }).await?;
+
+        Ok(())
+    }
 }
@@ -0,0 +1,88 @@
+use color_eyre::{Result, eyre::anyhow};
+use libp2p::PeerId;
+use std::collections::HashSet;
+use tracing::info;
+
+use crate::types::{
+    FragmentNumber,
+    ReverieBundle,
+    ReverieId,
+    VesselStatus,
+};
+use super::{
+    KeyfragBroadcastReport,
+    NodeClient,
+    sort_by_reputation,
+};
+
+
+impl NodeClient {
+
+    /// Exports a Reverie this node broadcast as a ReverieBundle for off-network backup, signed
+    /// with the node's identity key so it can still be imported after the node restarts.
+    /// The bundle holds the ciphertext, capsule and fragment placements, never the plaintext or keyfrags.
+    pub fn export_reverie(&self, reverie_id: &ReverieId) -> Result<ReverieBundle> {
+        let (reverie, target_vessel_peer_id, mut kfrag_providers) = {
+            let keyfrag_broadcasts = self.keyfrag_broadcasts.lock().unwrap();
+            match (
+                keyfrag_broadcasts.reveries.get(reverie_id),
+                keyfrag_broadcasts.vessels.get(reverie_id),
+            ) {
+                (Some(reverie), Some(vessel)) => {
+                    let kfrag_providers = keyfrag_broadcasts.sent.iter()
+                        .filter(|(id, ..)| id == reverie_id)
+                        .map(|(_, frag_num, peer_id)| (*frag_num, *peer_id))
+                        .collect::<Vec<(FragmentNumber, PeerId)>>();
+                    (reverie.clone(), *vessel, kfrag_providers)
+                }
+                _ => return Err(anyhow!("Reverie {} was not broadcast by this node", reverie_id)),
+            }
+        };
+        kfrag_providers.sort();
+
+        ReverieBundle::new(reverie, target_vessel_peer_id, kfrag_providers, &self.node_id.id_keys)
+    }
+
+    /// Restores a ReverieBundle exported by this node, re-broadcasting its keyfrags.
+    /// Previous providers, from the bundle and any placements since, are presumed lost,
+    /// so connected empty vessels that never held a fragment are given fragments first.
+    pub async fn import_reverie(&mut self, bundle: ReverieBundle) -> Result<KeyfragBroadcastReport> {
+
+        bundle.verify(&self.node_id.peer_id)?;
+        let reverie = bundle.reverie;
+
+        let prev_providers: HashSet<PeerId> = {
+            let mut keyfrag_broadcasts = self.keyfrag_broadcasts.lock().unwrap();
+            let prev_providers = keyfrag_broadcasts.sent.iter()
+                .filter(|(id, ..)| id == &reverie.id)
+                .map(|(_, _, peer_id)| *peer_id)
+                .chain(bundle.kfrag_providers.iter().map(|(_, peer_id)| *peer_id))
+                .collect();
+            // forget earlier placements, so all fragments are re-sent
+            keyfrag_broadcasts.sent.retain(|(id, ..)| id != &reverie.id);
+            prev_providers
+        };
+
+        let mut empty_vessels = self.get_node_vessels(false).await
+            .into_iter()
+            .filter(|v| v.vessel_status == VesselStatus::EmptyVessel && v.peer_id != self.node_id.peer_id)
+            .collect::<Vec<_>>();
+        let reputations = self.get_peer_reputations().await;
+        sort_by_reputation(&mut empty_vessels, |v| v.peer_id, &reputations);
+
+        let fresh_providers = empty_vessels.iter()
+            .map(|v| v.peer_id)
+            .filter(|peer_id| !prev_providers.contains(peer_id))
+            .collect::<Vec<PeerId>>();
+
+        let report = self.broadcast_reverie_keyfrags(
+            &reverie,
+            bundle.target_vessel_peer_id,
+            empty_vessels,
+            &fresh_providers,
+        ).await.map_err(|e| anyhow!(e.to_string()))?;
+
+        info!("Imported reverie {}: {:?}", reverie.id, report);
+        Ok(report)
+    }
+}
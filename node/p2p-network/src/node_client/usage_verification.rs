@@ -5,13 +5,16 @@ use dcap_rs::types::VerifiedOutput;
 use elliptic_curve::pkcs8::DecodePublicKey;
 use p256::ecdsa::{VerifyingKey, Signature};
 use std::{
+    collections::HashSet,
     error::Error as StdError,
     fmt,
     fs,
     path::Path,
     time::Duration,
 };
+use std::sync::{Arc, Mutex};
 use std::time::{SystemTime, UNIX_EPOCH};
+use near_primitives::views::FinalExecutionStatus;
 use serde::{Deserialize, Serialize};
 use signature::Verifier;
 use sha2::{Sha256, Digest};
@@ -25,7 +28,8 @@ use llm_proxy::usage::{
 };
 use runtime::tee_attestation::{self, QuoteV4, QuoteBody, TcbStatus};
 use runtime::llm::MCPToolUsageMetrics;
-use crate::usage_db::{UsageDbPool, store_usage_payload, read_usage_data_for_reverie};
+use crate::usage_db::{UsageDbPool, store_usage_payload, usage_report_exists, read_usage_data_for_reverie};
+use crate::env_var::EnvVars;
 use super::{NodeClient, NodeCommand};
 
 
@@ -88,40 +92,104 @@ impl From<runtime::tee_attestation::DcapError> for VerificationError {
     }
 }
 
+/// Ids of usage reports currently being charged, shared between NodeClient clones
+#[derive(Debug, Clone, Default)]
+pub struct InFlightUsageReports(Arc<Mutex<HashSet<String>>>);
+
+impl InFlightUsageReports {
+    /// Reserves a report id, or None if another copy of the report is being charged.
+    /// The id is released when the returned reservation is dropped.
+    pub fn reserve(&self, request_id: &str) -> Option<UsageReportReservation> {
+        let mut in_flight = self.0.lock().expect("in-flight usage reports lock poisoned");
+        if !in_flight.insert(request_id.to_string()) {
+            return None;
+        }
+        Some(UsageReportReservation {
+            reports: self.clone(),
+            request_id: request_id.to_string(),
+        })
+    }
+}
+
+pub struct UsageReportReservation {
+    reports: InFlightUsageReports,
+    request_id: String,
+}
+
+impl Drop for UsageReportReservation {
+    fn drop(&mut self) {
+        if let Ok(mut in_flight) = self.reports.0.lock() {
+            in_flight.remove(&self.request_id);
+        }
+    }
+}
 
 impl NodeClient {
-    /// Verifies and potentially stores a usage report received from a proxy.
-    /// TODO: move to network_events and dispatch on-chain
+    /// Verifies a usage report received from a proxy against the proxy's registered key,
+    /// stores it, then records the spend on NEAR for NEAR contract spenders.
+    /// Reports that fail verification are rejected before anything is recorded.
     pub async fn report_usage(&mut self, signed_usage_report: SignedUsageReport) -> Result<String> {
         info!("Received usage report: Payload(first 50)={:?}, Signature(first 10)={:?}",
               &signed_usage_report.payload[..signed_usage_report.payload.len().min(50)],
               &signed_usage_report.signature[..signed_usage_report.signature.len().min(10)]);
 
-        // Get the public key from self.proxy_public_key
-        let proxy_public_key = self.llm_proxy_public_key.read().await;
-        let opt_public_key = proxy_public_key.as_ref();
-        let public_key = match opt_public_key {
-            Some(ref key) => key,
+        let payload = {
+            let proxy_public_key = self.llm_proxy_public_key.read().await;
+            verify_report_from_registered_proxy(&signed_usage_report, proxy_public_key.as_ref())?
+        };
+        info!("NodeClient: Usage report verified successfully.");
+
+        // Proxies retry failed reports, so a report must only be charged once.
+        // The id is reserved before the spend so concurrent copies can't both pass the check,
+        // and released when this returns, by which point a successful report is stored.
+        let _reservation = match self.usage_reports_in_flight.reserve(&payload.request_id) {
+            Some(reservation) => reservation,
             None => {
-                error!("NodeClient: LLM Proxy public key not available for usage report verification. Has it registered yet?");
-                return Err(anyhow!("LLM Proxy public key not available. Cannot verify usage report."));
+                info!("NodeClient: Usage report {} is already being recorded.", payload.request_id);
+                return Ok("Usage report already being recorded.".to_string());
             }
         };
+        if usage_report_exists(&self.usage_db_pool, &payload.request_id)? {
+            info!("NodeClient: Usage report {} already recorded.", payload.request_id);
+            return Ok("Usage report already recorded.".to_string());
+        }
 
-        match verify_usage_report(&signed_usage_report, public_key) { // Pass the borrowed key
-            Ok(payload) => {
-                info!("NodeClient: Usage report verified successfully.");
-                // Verification successful, now store the payload
-                if let Err(db_err) = store_usage_payload(&self.usage_db_pool, &payload) {
-                    error!("NodeClient: Failed to store verified usage payload in DB: {}", db_err);
+        // Record spend on NEAR only once the report is known to come from the proxy
+        let cost = usage_cost(&payload.usage);
+        if let Some((reverie_id, user_id)) = near_spender(&payload.usage) {
+            let env_vars = EnvVars::load();
+            let amount_to_spend = cost as u128;
+            let outcome = self.near_runtime.record_spend(
+                &env_vars.NEAR.NEAR_CONTRACT_ACCOUNT_ID,
+                &env_vars.NEAR.NEAR_SIGNER_ACCOUNT_ID,
+                &env_vars.NEAR.NEAR_SIGNER_PRIVATE_KEY,
+                &reverie_id,
+                &user_id,
+                amount_to_spend,
+            ).await?;
+            info!("record_spend NEAR outcome: {:?}", outcome.status);
+            if !matches!(outcome.status, FinalExecutionStatus::SuccessValue(_)) {
+                return Err(anyhow!("record_spend NEAR transaction failed: {:?}", outcome.status));
+            }
+        }
+
+        // Store the payload only after the spend succeeded, so a failed spend
+        // leaves nothing behind and the retried report is recorded once
+        match store_usage_payload(&self.usage_db_pool, &payload) {
+            Ok(true) => {
+                if let (Some(reverie_id), Some(spender)) = (&payload.usage.reverie_id, &payload.usage.spender) {
+                    if let Err(db_err) = self.usage_store.record_usage_at(reverie_id, spender, &payload.usage, cost, payload.timestamp) {
+                        error!("NodeClient: Failed to record usage spend in DB: {}", db_err);
+                    }
                 }
-                Ok("Usage report verified.".to_string())
             }
-            Err(verification_error) => {
-                error!("NodeClient: Usage report verification failed: {}", verification_error);
-                Err(anyhow!("Usage report verification failed: {}", verification_error))
+            Ok(false) => {}
+            Err(db_err) => {
+                error!("NodeClient: Failed to store verified usage payload in DB: {}", db_err);
             }
         }
+
+        Ok("Usage report verified.".to_string())
     }
 
     pub fn read_usage_data_for_reverie(&self, reverie_id: &str) -> Result<MCPToolUsageMetrics> {
@@ -145,10 +213,11 @@ impl NodeClient {
                         cache_read_tokens: record.usage.cache_read_input_tokens,
                         tool_name: record.usage.tool_use.as_ref().map(|tu| tu.name.clone()),
                         tool_type: record.usage.tool_use.as_ref().map(|tu| tu.tool_type.clone()),
-                        linked_tool_id: record.linked_tool_use_id,
+                        linked_tool_ids: record.linked_tool_use_ids,
                         reverie_id: record.usage.reverie_id,
                         spender_address: record.usage.spender,
                         spender_type: record.usage.spender_type,
+                        provider: None,
                     };
                     metrics.add_usage_record(usage_record);
                 }
@@ -165,6 +234,51 @@ impl NodeClient {
     }
 }
 
+/// Verifies a usage report against the public key the proxy registered via `register_llm_proxy_key`.
+/// Rejects the report if no proxy has registered yet, or if its signature doesn't verify.
+pub fn verify_report_from_registered_proxy(
+    report: &SignedUsageReport,
+    registered_key: Option<&VerifyingKey>,
+) -> Result<UsageReportPayload> {
+    let public_key = match registered_key {
+        Some(key) => key,
+        None => {
+            error!("NodeClient: LLM Proxy public key not available for usage report verification. Has it registered yet?");
+            return Err(anyhow!("LLM Proxy public key not available. Cannot verify usage report."));
+        }
+    };
+
+    verify_usage_report(report, public_key).map_err(|verification_error| {
+        error!("NodeClient: Usage report verification failed: {}", verification_error);
+        anyhow!("Usage report verification failed: {}", verification_error)
+    })
+}
+
+/// Returns the (reverie_id, NEAR account) to charge, if the spender is a NEAR contract user.
+/// NEAR spenders are recorded as `AccessKey::NearContract(contract, user, amount)`.
+fn near_spender(usage: &UsageData) -> Option<(String, String)> {
+    if usage.spender_type.as_deref() != Some("near_contract") {
+        return None;
+    }
+    let reverie_id = usage.reverie_id.clone()?;
+    let user_id = usage.spender.as_ref()?
+        .strip_prefix("NearContract(")?
+        .strip_suffix(")")?
+        .split(", ")
+        .nth(1)?
+        .to_string();
+
+    Some((reverie_id, user_id))
+}
+
+/// Spend is charged per token, including cache reads and writes.
+fn usage_cost(usage: &UsageData) -> u64 {
+    usage.input_tokens
+        + usage.output_tokens
+        + usage.cache_creation_input_tokens.unwrap_or(0)
+        + usage.cache_read_input_tokens.unwrap_or(0)
+}
+
 pub fn verify_usage_report(
     report: &SignedUsageReport,
     key: &VerifyingKey,
@@ -267,7 +381,7 @@ mod tests {
         let payload = UsageReportPayload {
             usage,
             timestamp: 1745468461, // Fixed timestamp for consistent tests
-            linked_tool_use_id: None,
+            linked_tool_use_ids: vec![],
             request_id: String::from("request_121234"),
         };
 
@@ -387,4 +501,58 @@ mod tests {
         // Verification should fail
         assert!(result.is_err(), "Verification succeeded with tampered signature");
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_registered_proxy_key_accepts_valid_and_rejects_forged_report() {
+        let (proxy_signing_key, proxy_verifying_key) = generate_test_keypair();
+        let (forger_signing_key, _) = generate_test_keypair();
+
+        let (_, valid_report) = create_mock_signed_report(&proxy_signing_key);
+        let (_, forged_report) = create_mock_signed_report(&forger_signing_key);
+
+        let accepted = verify_report_from_registered_proxy(&valid_report, Some(&proxy_verifying_key));
+        assert!(accepted.is_ok(), "Report signed by the registered proxy was rejected");
+
+        let rejected = verify_report_from_registered_proxy(&forged_report, Some(&proxy_verifying_key));
+        assert!(rejected.is_err(), "Forged report was accepted");
+    }
+
+    #[test]
+    fn test_report_rejected_before_proxy_registers() {
+        let (signing_key, _) = generate_test_keypair();
+        let (_, signed_report) = create_mock_signed_report(&signing_key);
+
+        let result = verify_report_from_registered_proxy(&signed_report, None);
+        assert!(result.is_err(), "Report accepted without a registered proxy key");
+    }
+
+    #[test]
+    fn test_near_spender_parses_near_contract_access_key() {
+        let mut usage = UsageData::new();
+        usage.reverie_id = Some("reverie_123".to_string());
+        usage.spender = Some("NearContract(reverie.testnet, alice.testnet, 100)".to_string());
+        usage.spender_type = Some("near_contract".to_string());
+
+        assert_eq!(
+            near_spender(&usage),
+            Some(("reverie_123".to_string(), "alice.testnet".to_string()))
+        );
+
+        usage.spender_type = Some("ecdsa".to_string());
+        assert_eq!(near_spender(&usage), None);
+    }
+
+    #[test]
+    fn test_usage_report_id_is_reserved_until_released() {
+        let in_flight = InFlightUsageReports::default();
+
+        let reservation = in_flight.reserve("request_121234").expect("first copy reserves the id");
+        // a concurrent copy of the same report isn't charged again
+        assert!(in_flight.reserve("request_121234").is_none());
+        assert!(in_flight.reserve("request_other").is_some());
+
+        // released once the first copy finishes, e.g. after a failed spend
+        drop(reservation);
+        assert!(in_flight.reserve("request_121234").is_some());
+    }
+}
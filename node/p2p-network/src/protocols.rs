@@ -0,0 +1,90 @@
+//! Protocol identifiers for every behaviour, built as `{prefix}/{name}/{version}`.
+//! Nodes with different prefixes or versions will not negotiate a protocol with each other,
+//! so all behaviours must build their identifiers from here.
+use std::sync::LazyLock;
+
+/// Default prefix, overridden by `REVERIES_PROTOCOL_PREFIX` at build time or runtime
+pub const DEFAULT_PROTOCOL_PREFIX: &str = "/reveries";
+
+pub const HEARTBEAT_PROTOCOL_VERSION: &str = "0.0.1";
+pub const KADEMLIA_PROTOCOL_VERSION: &str = "1.0.0";
+pub const KFRAGS_REQUESTS_PROTOCOL_VERSION: &str = "1.0.0";
+pub const IDENTIFY_PROTOCOL_VERSION: &str = "1.0.0";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProtocolIds {
+    pub prefix: String,
+    pub heartbeat: String,
+    pub kademlia: String,
+    pub kfrags_requests: String,
+    pub identify: String,
+}
+
+impl ProtocolIds {
+    pub fn new(prefix: &str) -> Self {
+        let prefix = format!("/{}", prefix.trim_matches('/'));
+        let versioned = |name: &str, version: &str| format!("{}/{}/{}", prefix, name, version);
+        Self {
+            heartbeat: versioned("heartbeat", HEARTBEAT_PROTOCOL_VERSION),
+            kademlia: versioned("kad", KADEMLIA_PROTOCOL_VERSION),
+            kfrags_requests: versioned("kfrags-requests", KFRAGS_REQUESTS_PROTOCOL_VERSION),
+            identify: versioned("identify", IDENTIFY_PROTOCOL_VERSION),
+            prefix,
+        }
+    }
+
+    /// Runtime `REVERIES_PROTOCOL_PREFIX` takes precedence over the build-time one
+    pub fn from_env() -> Self {
+        let prefix = std::env::var("REVERIES_PROTOCOL_PREFIX")
+            .ok()
+            .or(option_env!("REVERIES_PROTOCOL_PREFIX").map(String::from))
+            .filter(|prefix| !prefix.trim_matches('/').is_empty())
+            .unwrap_or_else(|| DEFAULT_PROTOCOL_PREFIX.to_string());
+        Self::new(&prefix)
+    }
+
+    pub fn all(&self) -> [&str; 4] {
+        [
+            &self.heartbeat,
+            &self.kademlia,
+            &self.kfrags_requests,
+            &self.identify,
+        ]
+    }
+}
+
+static PROTOCOL_IDS: LazyLock<ProtocolIds> = LazyLock::new(ProtocolIds::from_env);
+
+/// Protocol identifiers used by this node's behaviours
+pub fn protocol_ids() -> &'static ProtocolIds {
+    &PROTOCOL_IDS
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn all_protocols_share_prefix() {
+        let ids = ProtocolIds::new("custom-net/");
+        assert_eq!(ids.prefix, "/custom-net");
+        for id in ids.all() {
+            assert!(id.starts_with("/custom-net/"), "{} is missing the prefix", id);
+        }
+
+        // The heartbeat and kfrag request behaviours read the same global ids
+        let ids = protocol_ids();
+        for id in ids.all() {
+            assert!(id.starts_with(&format!("{}/", ids.prefix)));
+        }
+    }
+
+    #[test]
+    fn protocol_ids_reflect_version_constants() {
+        let ids = ProtocolIds::new(DEFAULT_PROTOCOL_PREFIX);
+        assert_eq!(ids.heartbeat, format!("/reveries/heartbeat/{}", HEARTBEAT_PROTOCOL_VERSION));
+        assert_eq!(ids.kademlia, format!("/reveries/kad/{}", KADEMLIA_PROTOCOL_VERSION));
+        assert_eq!(ids.kfrags_requests, format!("/reveries/kfrags-requests/{}", KFRAGS_REQUESTS_PROTOCOL_VERSION));
+        assert_eq!(ids.identify, format!("/reveries/identify/{}", IDENTIFY_PROTOCOL_VERSION));
+    }
+}
@@ -0,0 +1,62 @@
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{broadcast, mpsc, watch};
+use runtime::near_runtime::{NearConfig, NearRuntime};
+use runtime::reencrypt::UmbralKey;
+
+use crate::network_events::NodeIdentity;
+use crate::node_client::{NodeClient, NodeCommand};
+use crate::types::{NODE_EVENT_CHANNEL_CAPACITY, DEFAULT_MAX_REVERIE_PAYLOAD_SIZE};
+
+/// A NodeClient with no network behind it, commands it sends arrive on the returned receiver
+pub fn test_node_client(umbral_key: UmbralKey) -> (NodeClient, mpsc::Receiver<NodeCommand>) {
+    let id_keys = libp2p::identity::Keypair::generate_ed25519();
+    let peer_id = id_keys.public().to_peer_id();
+    let node_id = NodeIdentity::new("test".to_string(), peer_id, id_keys, 0, umbral_key.clone());
+    let (command_sender, command_receiver) = mpsc::channel(10);
+    let (_heartbeat_sender, heartbeat_receiver) = async_channel::bounded(1);
+    let (node_event_sender, _) = broadcast::channel(NODE_EVENT_CHANNEL_CAPACITY);
+    let usage_db_pool = Arc::new(
+        r2d2::Pool::builder()
+            .max_size(1)
+            .build(r2d2_sqlite::SqliteConnectionManager::memory())
+            .unwrap()
+    );
+    let near_runtime = Arc::new(NearRuntime::new(NearConfig::default()).unwrap());
+
+    let node_client = NodeClient::new(
+        node_id,
+        command_sender,
+        umbral_key,
+        heartbeat_receiver,
+        node_event_sender,
+        usage_db_pool,
+        near_runtime,
+        DEFAULT_MAX_REVERIE_PAYLOAD_SIZE,
+        1, // spawn_peer_margin
+        Duration::from_secs(5), // reconstruction_sla
+        watch::channel(false).0,
+    );
+    (node_client, command_receiver)
+}
+
+/// Stand-in for NetworkEvents: `respond` answers the commands it handles and hands back
+/// the rest, which are forwarded to the returned receiver in the order they were sent.
+/// Tests wait on the forwarded commands instead of sleeping for fire-and-forget sends.
+pub fn mock_network<F>(
+    mut command_receiver: mpsc::Receiver<NodeCommand>,
+    mut respond: F,
+) -> mpsc::UnboundedReceiver<NodeCommand>
+where
+    F: FnMut(NodeCommand) -> Option<NodeCommand> + Send + 'static,
+{
+    let (unhandled_sender, unhandled_receiver) = mpsc::unbounded_channel();
+    tokio::spawn(async move {
+        while let Some(command) = command_receiver.recv().await {
+            if let Some(unhandled) = respond(command) {
+                unhandled_sender.send(unhandled).ok();
+            }
+        }
+    });
+    unhandled_receiver
+}
@@ -1,7 +1,8 @@
 use color_eyre::{eyre::anyhow, Result};
 use serde::{Deserialize, Serialize};
 use umbral_pre::Capsule;
-use libp2p::{PeerId, kad};
+use libp2p::{PeerId, identity, kad};
+use sha3::{Digest, Keccak256};
 
 use crate::utils::{
     reverie_id,
@@ -10,7 +11,9 @@ use crate::utils::{
 use crate::types::{
     ReverieNameWithNonce,
     PeerIdToNodeStatusKey,
+    Reverie,
     ReverieId,
+    ReverieType,
     PEER_ID_TO_NODE_STATUS,
 };
 
@@ -25,6 +28,8 @@ pub enum KademliaKey {
     PeerIdToNodeStatusKey(PeerIdToNodeStatusKey),
     ReverieIdToNameKey(ReverieIdToNameKey),
     ReverieIdToReverie(ReverieId),
+    ReverieTypeEntriesKey(ReverieTypeEntriesKey),
+    ReverieHolderKey(ReverieHolderKey),
     Unknown(String),
 }
 
@@ -35,6 +40,20 @@ impl From<&str> for KademliaKey {
             s if s.starts_with(PEER_ID_TO_NODE_STATUS) => {
                 KademliaKey::PeerIdToNodeStatusKey(PeerIdToNodeStatusKey::from_string(s).unwrap())
             }
+            // reverie type -> vessel's reverieIds queries, matched before the REVERIE_ID_PREFIX
+            s if s.starts_with(REVERIE_TYPE_ENTRIES_KADKEY_PREFIX) => {
+                match ReverieTypeEntriesKey::from_string(s) {
+                    Ok(key) => KademliaKey::ReverieTypeEntriesKey(key),
+                    Err(_) => KademliaKey::Unknown(s.to_string()),
+                }
+            }
+            // reverieId and holder -> signed holder record queries, matched before the REVERIE_ID_PREFIX
+            s if s.starts_with(REVERIE_HOLDER_KADKEY_PREFIX) => {
+                match ReverieHolderKey::from_string(s) {
+                    Ok(key) => KademliaKey::ReverieHolderKey(key),
+                    Err(_) => KademliaKey::Unknown(s.to_string()),
+                }
+            }
             // reverieId -> Reverie queries
             s if s.starts_with(REVERIE_ID_PREFIX) => {
                 KademliaKey::ReverieIdToReverie(ReverieId::from(s))
@@ -114,3 +133,285 @@ impl From<ReverieId> for ReverieIdToPeerId {
     }
 }
 
+
+pub const REVERIE_TYPE_INDEX_KADKEY_PREFIX: &'static str = "reverie_type_index_";
+
+/// Provider record key for a ReverieType kind. Every vessel holding a reverie
+/// of that kind provides it, so `get_providers` finds the vessels to ask.
+#[derive(Debug, Clone, Hash, Eq, PartialEq, Deserialize, Serialize)]
+pub struct ReverieTypeIndexKey(pub String);
+
+impl From<&ReverieType> for ReverieTypeIndexKey {
+    fn from(reverie_type: &ReverieType) -> Self {
+        ReverieTypeIndexKey(reverie_type.kind().to_string())
+    }
+}
+impl KademliaKeyTrait for ReverieTypeIndexKey {
+    fn to_string(&self) -> String {
+        format!("{}{}", REVERIE_TYPE_INDEX_KADKEY_PREFIX, self.0)
+    }
+    fn to_kad_key(&self) -> kad::RecordKey {
+        kad::RecordKey::new(&self.to_string())
+    }
+}
+
+
+pub const REVERIE_TYPE_ENTRIES_KADKEY_PREFIX: &'static str = "reverie_type_entries_";
+
+/// Record key for the reverieIds of one ReverieType kind held by a vessel,
+/// only put by the vessel itself.
+#[derive(Debug, Clone, Hash, Eq, PartialEq, Deserialize, Serialize)]
+pub struct ReverieTypeEntriesKey {
+    pub reverie_type_kind: String,
+    pub vessel_peer_id: PeerId,
+}
+
+impl ReverieTypeEntriesKey {
+    pub fn new<S: Into<String>>(reverie_type_kind: S, vessel_peer_id: PeerId) -> Self {
+        Self {
+            reverie_type_kind: reverie_type_kind.into(),
+            vessel_peer_id,
+        }
+    }
+
+    pub fn from_string<S: Into<String>>(s: S) -> Result<Self> {
+        let s: String = s.into();
+        let (reverie_type_kind, peer_id) = s
+            .strip_prefix(REVERIE_TYPE_ENTRIES_KADKEY_PREFIX)
+            .and_then(|rest| rest.rsplit_once('_'))
+            .ok_or(anyhow!("Invalid ReverieTypeEntriesKey: {}. Must begin with {}", s, REVERIE_TYPE_ENTRIES_KADKEY_PREFIX))?;
+
+        Ok(Self::new(reverie_type_kind, peer_id.parse::<PeerId>()?))
+    }
+}
+impl KademliaKeyTrait for ReverieTypeEntriesKey {
+    fn to_string(&self) -> String {
+        format!("{}{}_{}", REVERIE_TYPE_ENTRIES_KADKEY_PREFIX, self.reverie_type_kind, self.vessel_peer_id)
+    }
+    fn to_kad_key(&self) -> kad::RecordKey {
+        kad::RecordKey::new(&self.to_string())
+    }
+}
+
+/// Value of a ReverieTypeEntriesKey record
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct ReverieTypeIndexEntry {
+    pub reverie_id: ReverieId,
+    pub expires_at: Option<i64>,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+impl ReverieTypeIndexEntry {
+    pub fn is_expired(&self, now: i64) -> bool {
+        self.expires_at.is_some_and(|expires_at| now >= expires_at)
+    }
+}
+
+/// ReverieTypeIndexEntries signed by the vessel's identity key, so peers storing
+/// or forwarding the record can't substitute their own entries.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SignedReverieTypeEntries {
+    pub entries: Vec<ReverieTypeIndexEntry>,
+    pub signature: Vec<u8>,
+}
+
+impl SignedReverieTypeEntries {
+    pub fn new(
+        key: &ReverieTypeEntriesKey,
+        entries: Vec<ReverieTypeIndexEntry>,
+        id_keys: &identity::Keypair
+    ) -> Result<Self> {
+        let signature = id_keys.sign(&Self::signed_bytes(key, &entries)?)?;
+        Ok(Self {
+            entries,
+            signature,
+        })
+    }
+
+    /// Checks the entries were signed by the key's vessel, for the key's ReverieType kind
+    pub fn verify(&self, key: &ReverieTypeEntriesKey) -> Result<()> {
+        let public_key = identity::PublicKey::try_decode_protobuf(&key.vessel_peer_id.to_bytes())
+            .map_err(|e| anyhow!("Failed to decode public key: {}", e))?;
+
+        if !public_key.verify(&Self::signed_bytes(key, &self.entries)?, &self.signature) {
+            return Err(anyhow!("Invalid reverie type entries signature"));
+        }
+        Ok(())
+    }
+
+    fn signed_bytes(key: &ReverieTypeEntriesKey, entries: &[ReverieTypeIndexEntry]) -> Result<Vec<u8>> {
+        Ok(serde_json::to_vec(&(key.to_string(), entries))?)
+    }
+}
+
+
+pub const REVERIE_HOLDER_KADKEY_PREFIX: &'static str = "reverie_holder_";
+
+/// Record key for one holder's copy of a Reverie, only put by the holder itself
+#[derive(Debug, Clone, Hash, Eq, PartialEq, Deserialize, Serialize)]
+pub struct ReverieHolderKey {
+    pub reverie_id: ReverieId,
+    pub holder_peer_id: PeerId,
+}
+
+impl ReverieHolderKey {
+    pub fn new(reverie_id: ReverieId, holder_peer_id: PeerId) -> Self {
+        Self {
+            reverie_id,
+            holder_peer_id,
+        }
+    }
+
+    pub fn from_string<S: Into<String>>(s: S) -> Result<Self> {
+        let s: String = s.into();
+        let (reverie_id, peer_id) = s
+            .strip_prefix(REVERIE_HOLDER_KADKEY_PREFIX)
+            .and_then(|rest| rest.rsplit_once('_'))
+            .ok_or(anyhow!("Invalid ReverieHolderKey: {}. Must begin with {}", s, REVERIE_HOLDER_KADKEY_PREFIX))?;
+
+        Ok(Self::new(reverie_id.to_string(), peer_id.parse::<PeerId>()?))
+    }
+}
+impl KademliaKeyTrait for ReverieHolderKey {
+    fn to_string(&self) -> String {
+        format!("{}{}_{}", REVERIE_HOLDER_KADKEY_PREFIX, self.reverie_id, self.holder_peer_id)
+    }
+    fn to_kad_key(&self) -> kad::RecordKey {
+        kad::RecordKey::new(&self.to_string())
+    }
+}
+
+/// Digest of the ciphertext and capsule a holder stores for a Reverie, signed by the
+/// holder's identity key. A holder's copy only counts when reading from holders if it
+/// matches the record the holder signed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SignedReverieHolder {
+    pub ciphertext_digest: Vec<u8>,
+    pub signature: Vec<u8>,
+}
+
+impl SignedReverieHolder {
+    pub fn new(key: &ReverieHolderKey, reverie: &Reverie, id_keys: &identity::Keypair) -> Result<Self> {
+        let ciphertext_digest = Self::ciphertext_digest(reverie);
+        let signature = id_keys.sign(&Self::signed_bytes(key, &ciphertext_digest)?)?;
+        Ok(Self {
+            ciphertext_digest,
+            signature,
+        })
+    }
+
+    /// Checks the record was signed by the key's holder, for the key's Reverie
+    pub fn verify(&self, key: &ReverieHolderKey) -> Result<()> {
+        let public_key = identity::PublicKey::try_decode_protobuf(&key.holder_peer_id.to_bytes())
+            .map_err(|e| anyhow!("Failed to decode public key: {}", e))?;
+
+        if !public_key.verify(&Self::signed_bytes(key, &self.ciphertext_digest)?, &self.signature) {
+            return Err(anyhow!("Invalid reverie holder signature"));
+        }
+        Ok(())
+    }
+
+    /// Whether a copy of the Reverie has the ciphertext and capsule the holder signed
+    pub fn matches(&self, reverie: &Reverie) -> bool {
+        self.ciphertext_digest == Self::ciphertext_digest(reverie)
+    }
+
+    fn ciphertext_digest(reverie: &Reverie) -> Vec<u8> {
+        let mut hasher = Keccak256::new();
+        hasher.update(&reverie.umbral_ciphertext);
+        hasher.update(&reverie.umbral_capsule);
+        hasher.finalize().to_vec()
+    }
+
+    fn signed_bytes(key: &ReverieHolderKey, ciphertext_digest: &[u8]) -> Result<Vec<u8>> {
+        Ok(serde_json::to_vec(&(key.to_string(), ciphertext_digest))?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use runtime::reencrypt::UmbralKey;
+    use crate::types::AccessCondition;
+
+    #[test]
+    fn reverie_type_entries_key_roundtrips_through_kademlia_key() {
+        let key = ReverieTypeEntriesKey::new(ReverieType::Memory.kind(), PeerId::random());
+
+        match KademliaKey::from(&key.to_kad_key()) {
+            KademliaKey::ReverieTypeEntriesKey(parsed) => assert_eq!(parsed, key),
+            other => panic!("expected ReverieTypeEntriesKey, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn reverie_type_entries_only_verify_for_the_signing_vessel() -> Result<()> {
+        let vessel_keys = identity::Keypair::generate_ed25519();
+        let other_keys = identity::Keypair::generate_ed25519();
+        let key = ReverieTypeEntriesKey::new(ReverieType::Memory.kind(), vessel_keys.public().to_peer_id());
+        let entries = vec![ReverieTypeIndexEntry {
+            reverie_id: "reverie_1234".to_string(),
+            expires_at: None,
+            description: String::new(),
+            tags: vec![],
+        }];
+
+        let signed = SignedReverieTypeEntries::new(&key, entries.clone(), &vessel_keys)?;
+        assert!(signed.verify(&key).is_ok());
+
+        // Entries signed by another peer, as if they had re-published the vessel's record
+        let forged = SignedReverieTypeEntries::new(&key, entries.clone(), &other_keys)?;
+        assert!(forged.verify(&key).is_err());
+
+        // Or moved under another ReverieType kind of the same vessel
+        let other_kind = ReverieTypeEntriesKey::new(ReverieType::SovereignAgent(ReverieNameWithNonce("auron".to_string(), 0)).kind(), key.vessel_peer_id);
+        assert!(signed.verify(&other_kind).is_err());
+
+        // Or with the entries altered after signing
+        let mut tampered = signed.clone();
+        tampered.entries[0].reverie_id = "reverie_5678".to_string();
+        assert!(tampered.verify(&key).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn reverie_holder_records_only_verify_for_the_signing_holder() -> Result<()> {
+        let holder_keys = identity::Keypair::generate_ed25519();
+        let other_keys = identity::Keypair::generate_ed25519();
+        let umbral_key = UmbralKey::new(None);
+        let (capsule, ciphertext) = umbral_key.encrypt_bytes(&b"secrets".to_vec())?;
+        let reverie = Reverie::new(
+            "test reverie".to_string(),
+            ReverieType::Memory,
+            2,
+            3,
+            umbral_key.public_key,
+            umbral_key.verifying_public_key,
+            AccessCondition::Umbral(umbral_key.public_key),
+            capsule,
+            ciphertext,
+        );
+        let key = ReverieHolderKey::new(reverie.id.clone(), holder_keys.public().to_peer_id());
+        match KademliaKey::from(&key.to_kad_key()) {
+            KademliaKey::ReverieHolderKey(parsed) => assert_eq!(parsed, key),
+            other => panic!("expected ReverieHolderKey, got {:?}", other),
+        }
+
+        let signed = SignedReverieHolder::new(&key, &reverie, &holder_keys)?;
+        assert!(signed.verify(&key).is_ok());
+        assert!(signed.matches(&reverie));
+
+        // Signed by another peer, as if it vouched for the holder's copy
+        let forged = SignedReverieHolder::new(&key, &reverie, &other_keys)?;
+        assert!(forged.verify(&key).is_err());
+
+        // A copy with a different ciphertext doesn't match the holder's record
+        let mut corrupted = reverie.clone();
+        corrupted.umbral_ciphertext[0] ^= 0xff;
+        assert!(!signed.matches(&corrupted));
+        Ok(())
+    }
+}
@@ -1,4 +1,5 @@
 mod network_event;
+mod node_event;
 mod node_status;
 mod near_types;
 mod reverie_name;
@@ -7,6 +8,7 @@ mod signatures;
 mod kademlia_keys;
 
 pub use network_event::*;
+pub use node_event::*;
 pub use node_status::*;
 pub use near_types::*;
 pub use reverie_name::*;
@@ -15,6 +17,7 @@ pub use signatures::*;
 pub use kademlia_keys::*;
 
 pub use crate::network_events::peer_manager::peer_info::AgentVesselInfo;
+pub use crate::network_events::peer_manager::peer_info::{ReputationEvent, DEFAULT_REPUTATION};
 
 pub use crate::node_client::memories::ExecuteWithMemoryReverieResult;
 pub use crate::node_client::memories::AnthropicQuery;
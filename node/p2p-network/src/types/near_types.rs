@@ -23,6 +23,9 @@ impl TryFrom<&P2PNetworkAccessCondition> for NearRuntimeAccessCondition {
             P2PNetworkAccessCondition::Ed25519(pk_str) => {
                 Ok(NearRuntimeAccessCondition::Ed25519(pk_str.clone()))
             }
+            P2PNetworkAccessCondition::P256(pk_str) => {
+                Err(anyhow!("P256 access conditions are not supported by the NEAR runtime: {}", pk_str))
+            }
             P2PNetworkAccessCondition::NearContract(contract_account_id, user_account_id, amount) => {
                 Ok(NearRuntimeAccessCondition::Contract {
                     contract_account_id: contract_account_id.to_string(),
@@ -34,6 +37,9 @@ impl TryFrom<&P2PNetworkAccessCondition> for NearRuntimeAccessCondition {
             P2PNetworkAccessCondition::EthContract(addr, name, args) => {
                 unimplemented!("EthContract access conditions are not implemented yet");
             }
+            P2PNetworkAccessCondition::EthEvent(addr, event_signature) => {
+                Err(anyhow!("EthEvent access conditions are not supported by the NEAR runtime: {} {}", addr, event_signature))
+            }
         }
     }
 }
@@ -10,6 +10,8 @@ use crate::types::{
     ReverieKeyfrag,
     ReverieKeyfragMessage,
     ReverieMessage,
+    ReverieCiphertextChunk,
+    RespawnId,
     AccessKey,
 };
 use crate::SendError;
@@ -20,6 +22,12 @@ pub enum NetworkEvent {
     RespawnRequest(
         AgentVesselInfo,
     ),
+    /// A vessel that failed and had its agent reincarnated is sending heartbeats again,
+    /// so two vessels may be running the same agent
+    DuplicateVesselDetected {
+        agent_name: ReverieNameWithNonce,
+        peer_id: PeerId,
+    },
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
@@ -50,6 +58,27 @@ pub enum FragmentRequestEnum {
     SaveCiphertextRequest(
         ReverieMessage,
     ),
+    /// Encryptor sends node one chunk of a large Reverie/Ciphertext to save
+    SaveCiphertextChunkRequest(
+        ReverieCiphertextChunk,
+    ),
+    /// Asks a reverie holder for its copy of the Reverie/Ciphertext
+    GetCiphertextRequest(
+        ReverieId,
+    ),
+    /// Tells a rejoining vessel whose agent was already reincarnated to delete its secrets
+    StandDownVesselRequest(
+        ReverieNameWithNonce,
+    ),
+    /// Current vessel asks its chosen successor to reincarnate the agent, for a planned handoff
+    HandoffVesselRequest(
+        ReverieNameWithNonce,
+    ),
+    /// Next vessel asks a peer whether it also sees the failed vessel's heartbeats time out
+    RespawnVoteRequest(
+        RespawnId,
+        PeerId, // failed vessel
+    ),
     /// Mark Respawn Complete
     MarkRespawnCompleteRequest {
         prev_reverie_id: ReverieId,
@@ -67,9 +96,33 @@ pub enum FragmentResponseEnum {
 
     SaveFragmentResponse,
 
+    /// Provider couldn't deserialize or verify the keyfrag, and didn't save it
+    SaveFragmentFailedResponse(SendError),
+
     ProvidingFragmentResponse,
 
     SaveCiphertextResponse,
 
+    SaveCiphertextChunkResponse,
+
+    /// Vessel rejected the ciphertext or one of its chunks, and didn't save it
+    SaveCiphertextFailedResponse(SendError),
+
+    GetCiphertextResponse(
+        Result<ReverieMessage, SendError>,
+    ),
+
+    StandDownVesselResponse,
+
+    /// Successor started reincarnating the handed off agent, or refused to
+    HandoffVesselResponse(
+        Result<RespawnId, SendError>,
+    ),
+
     MarkRespawnCompleteResponse,
+
+    RespawnVoteResponse(
+        RespawnId,
+        bool, // failed vessel is offline
+    ),
 }
\ No newline at end of file
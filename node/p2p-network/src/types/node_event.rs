@@ -0,0 +1,127 @@
+use libp2p::PeerId;
+use serde::{Deserialize, Serialize};
+use crate::types::{
+    AgentVesselInfo,
+    FragmentNumber,
+    NetworkEvent,
+    ReverieId,
+    ReverieNameWithNonce,
+    ReverieType,
+};
+
+/// Capacity of the NodeEvent broadcast channel, slow subscribers skip the oldest events
+pub const NODE_EVENT_CHANNEL_CAPACITY: usize = 256;
+
+/// Protocol lifecycle events streamed to RPC subscribers (`subscribe_node_events`).
+/// Only ids and peer ids are included, never keyfrags, ciphertexts or access keys.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "event", content = "data")]
+pub enum NodeEvent {
+    /// Kademlia bootstrap completed, the node has joined the network and is ready
+    Bootstrapped {
+        connected_peers: usize,
+    },
+    /// This node saved a Reverie's ciphertext as its vessel
+    ReverieSaved {
+        reverie_id: ReverieId,
+        reverie_type: ReverieType,
+        source_peer_id: PeerId,
+        target_peer_id: PeerId,
+    },
+    /// This node saved a keyfrag, becoming a kfrag provider for the Reverie
+    KeyfragSaved {
+        reverie_id: ReverieId,
+        frag_num: FragmentNumber,
+        source_peer_id: PeerId,
+    },
+    /// A peer told this node it provides a keyfrag for a Reverie this node holds
+    KfragProviderAdded {
+        reverie_id: ReverieId,
+        frag_num: FragmentNumber,
+        kfrag_provider: PeerId,
+    },
+    /// This node is respawning an agent whose vessel failed
+    RespawnRequired {
+        reverie_id: ReverieId,
+        reverie_type: ReverieType,
+        current_vessel_peer_id: PeerId,
+        next_vessel_peer_id: PeerId,
+    },
+    /// An agent was respawned into a new vessel
+    RespawnComplete {
+        prev_reverie_id: ReverieId,
+        prev_peer_id: PeerId,
+        prev_agent_name: ReverieNameWithNonce,
+    },
+    /// A reincarnated agent's old vessel rejoined still holding the agent
+    DuplicateVesselDetected {
+        agent_name: ReverieNameWithNonce,
+        peer_id: PeerId,
+    },
+}
+
+impl From<&NetworkEvent> for NodeEvent {
+    fn from(event: &NetworkEvent) -> Self {
+        match event {
+            NetworkEvent::RespawnRequest(AgentVesselInfo {
+                reverie_id,
+                reverie_type,
+                current_vessel_peer_id,
+                next_vessel_peer_id,
+                ..
+            }) => NodeEvent::RespawnRequired {
+                reverie_id: reverie_id.clone(),
+                reverie_type: reverie_type.clone(),
+                current_vessel_peer_id: *current_vessel_peer_id,
+                next_vessel_peer_id: *next_vessel_peer_id,
+            },
+            NetworkEvent::DuplicateVesselDetected { agent_name, peer_id } => {
+                NodeEvent::DuplicateVesselDetected {
+                    agent_name: agent_name.clone(),
+                    peer_id: *peer_id,
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_node_event_serializes_with_event_tag() {
+        let source_peer_id = PeerId::random();
+        let target_peer_id = PeerId::random();
+        let event = NodeEvent::ReverieSaved {
+            reverie_id: "reverie_123".to_string(),
+            reverie_type: ReverieType::Memory,
+            source_peer_id,
+            target_peer_id,
+        };
+
+        let json = serde_json::to_value(&event).unwrap();
+        assert_eq!(json["event"], "ReverieSaved");
+        assert_eq!(json["data"]["reverie_id"], "reverie_123");
+        assert_eq!(serde_json::from_value::<NodeEvent>(json).unwrap(), event);
+    }
+
+    #[test]
+    fn test_respawn_request_converts_without_secrets() {
+        let agent_vessel = AgentVesselInfo {
+            reverie_id: "reverie_123".to_string(),
+            reverie_type: ReverieType::SovereignAgent(ReverieNameWithNonce("auron".to_string(), 0)),
+            threshold: 2,
+            total_frags: 3,
+            current_vessel_peer_id: PeerId::random(),
+            next_vessel_peer_id: PeerId::random(),
+        };
+        let event = NodeEvent::from(&NetworkEvent::RespawnRequest(agent_vessel.clone()));
+        assert_eq!(event, NodeEvent::RespawnRequired {
+            reverie_id: agent_vessel.reverie_id,
+            reverie_type: agent_vessel.reverie_type,
+            current_vessel_peer_id: agent_vessel.current_vessel_peer_id,
+            next_vessel_peer_id: agent_vessel.next_vessel_peer_id,
+        });
+    }
+}
@@ -97,6 +97,21 @@ pub enum VesselStatus {
     ActiveVessel
 }
 
+impl VesselStatus {
+    /// Allowed transitions: EmptyVessel <-> ActiveVessel, and EmptyVessel -> NeverVessel.
+    /// An ActiveVessel must empty before retiring, and a NeverVessel never hosts agents.
+    /// Staying in the same status is always allowed.
+    pub fn can_transition_to(&self, next: VesselStatus) -> bool {
+        match (self, next) {
+            (current, next) if *current == next => true,
+            (VesselStatus::EmptyVessel, VesselStatus::ActiveVessel) => true,
+            (VesselStatus::ActiveVessel, VesselStatus::EmptyVessel) => true,
+            (VesselStatus::EmptyVessel, VesselStatus::NeverVessel) => true,
+            _ => false,
+        }
+    }
+}
+
 impl Display for NodeKeysWithVesselStatus  {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "NodeKeysWithVesselStatus({}, {})", self.peer_id, self.umbral_public_key)
@@ -154,4 +169,65 @@ impl SignedVesselStatus {
         }
         Ok(())
     }
-}
\ No newline at end of file
+}
+
+/// Readiness of a node to serve requests: connected to at least one peer,
+/// and Kademlia bootstrap has completed.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NodeReadiness {
+    pub ready: bool,
+    pub connected_peers: usize,
+    pub kademlia_bootstrapped: bool,
+}
+
+impl NodeReadiness {
+    pub fn new(connected_peers: usize, kademlia_bootstrapped: bool) -> Self {
+        Self {
+            ready: connected_peers > 0 && kademlia_bootstrapped,
+            connected_peers,
+            kademlia_bootstrapped,
+        }
+    }
+}
+
+/// Outstanding entries of one kind of pending network request, to diagnose
+/// queries whose response never arrived.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PendingRequestStats {
+    pub count: usize,
+    /// Seconds since the oldest outstanding entry was made
+    pub oldest_age_secs: Option<u64>,
+}
+
+/// A peer this node is connected to, for inspecting the network
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ConnectedPeer {
+    pub peer_id: PeerId,
+    pub node_name: String,
+    /// From the peer's signed status on Kademlia, None if no record was found
+    pub vessel_status: Option<VesselStatus>,
+    /// Seconds since the peer's last heartbeat, None if it hasn't sent one yet
+    pub last_heartbeat_secs: Option<u64>,
+    pub reputation: f64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn node_not_ready_before_bootstrap() {
+        assert!(!NodeReadiness::new(0, false).ready);
+        assert!(!NodeReadiness::new(3, false).ready);
+    }
+
+    #[test]
+    fn node_not_ready_without_peers() {
+        assert!(!NodeReadiness::new(0, true).ready);
+    }
+
+    #[test]
+    fn node_ready_after_bootstrap_with_connected_peer() {
+        assert!(NodeReadiness::new(1, true).ready);
+    }
+}
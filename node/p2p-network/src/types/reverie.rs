@@ -1,14 +1,17 @@
 
 use color_eyre::{eyre::anyhow, Result};
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
 use umbral_pre::Capsule;
-use libp2p::{PeerId, kad};
+use libp2p::{PeerId, kad, identity};
+use sha3::{Digest, Keccak256};
+pub use runtime::reencrypt::PreKeyfragParams;
 
 use crate::utils::{
     reverie_id,
     REVERIE_ID_PREFIX,
 };
 use crate::types::{
+    FragmentNumber,
     ReverieNameWithNonce,
     PeerIdToNodeStatusKey,
     AccessCondition,
@@ -18,6 +21,45 @@ use crate::types::{
 
 pub type ReverieId = String;
 
+/// Default max size in bytes of a Reverie's plaintext secrets
+pub const DEFAULT_MAX_REVERIE_PAYLOAD_SIZE: usize = 1024 * 1024;
+
+/// Upper bound on bytes that encryption adds to the plaintext (nonce and auth tag)
+pub const CIPHERTEXT_OVERHEAD: usize = 64;
+
+pub fn check_reverie_payload_size(payload_size: usize, max_payload_size: usize) -> Result<()> {
+    if payload_size > max_payload_size {
+        return Err(anyhow!(
+            "Reverie payload of {} bytes exceeds the max size of {} bytes",
+            payload_size,
+            max_payload_size
+        ));
+    }
+    Ok(())
+}
+
+/// Well-formed Umbral KeyFrags and Capsules serialize to a few hundred bytes of JSON
+pub const MAX_UMBRAL_FRAGMENT_JSON_SIZE: usize = 4 * 1024;
+
+/// Wire format version of Reverie, ReverieKeyfrag and ReverieCapsulefrag.
+/// Bump when their fields change, so peers on other versions reject them
+/// rather than misreading them.
+pub const REVERIE_WIRE_VERSION: u16 = 1;
+
+/// Rejects wire types of other versions. `version` is serialized first, so this
+/// fails before fields that changed between versions are deserialized.
+fn deserialize_wire_version<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u16, D::Error> {
+    let version = u16::deserialize(deserializer)?;
+    if version != REVERIE_WIRE_VERSION {
+        return Err(serde::de::Error::custom(format!(
+            "unsupported Reverie wire format version {}, expected {}",
+            version,
+            REVERIE_WIRE_VERSION
+        )));
+    }
+    Ok(version)
+}
+
 impl KademliaKeyTrait for ReverieId {
     fn to_string(&self) -> String {
         format!("{}", REVERIE_ID_PREFIX)
@@ -30,9 +72,14 @@ impl KademliaKeyTrait for ReverieId {
 /// An encrypted memory module, used by an agent
 #[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
 pub struct Reverie {
+    #[serde(deserialize_with = "deserialize_wire_version")]
+    pub version: u16,
     pub id: ReverieId,
     pub reverie_type: ReverieType,
     pub description: String,
+    // labels for discovering reveries, listed alongside the description
+    #[serde(default)]
+    pub tags: Vec<String>,
     pub threshold: usize,
     pub total_frags: usize,
     // umbral keys for a node to decrypt the ciphertext
@@ -42,10 +89,18 @@ pub struct Reverie {
     pub access_condition: AccessCondition,
     pub umbral_capsule: Vec<u8>,
     pub umbral_ciphertext: Box<[u8]>,
+    // unix timestamp after which kfrag providers stop releasing cfrags
+    #[serde(default)]
+    pub expires_at: Option<i64>,
+    // keys the reverie's kfrags are signed to, checked by kfrag providers
+    #[serde(default)]
+    pub keyfrag_params: PreKeyfragParams,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
 pub struct ReverieKeyfrag {
+    #[serde(deserialize_with = "deserialize_wire_version")]
+    pub version: u16,
     pub id: ReverieId,
     pub reverie_type: ReverieType,
     pub frag_num: usize,
@@ -60,6 +115,9 @@ pub struct ReverieKeyfrag {
     pub target_verifying_pubkey: umbral_pre::PublicKey, // target verifying key
     // access condition for user permission to access/execute a reverie
     pub access_condition: AccessCondition,
+    // unix timestamp after which kfrag providers stop releasing cfrags
+    #[serde(default)]
+    pub expires_at: Option<i64>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
@@ -70,6 +128,41 @@ pub struct ReverieMessage {
     pub keyfrag_providers: Vec<PeerId>,
 }
 
+/// Ciphertexts larger than this are sent in chunks, keeping each
+/// request well under the request-response message size limit
+pub const CIPHERTEXT_CHUNK_SIZE: usize = 256 * 1024;
+
+/// One part of a Reverie's ciphertext. Each chunk carries the ReverieMessage
+/// (with an empty ciphertext), so chunks can be reassembled in any order.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReverieCiphertextChunk {
+    pub reverie_msg: ReverieMessage,
+    pub index: usize,
+    pub total: usize,
+    pub bytes: Vec<u8>,
+}
+
+/// Splits a Reverie's ciphertext into chunks of at most `chunk_size` bytes
+pub fn split_reverie_ciphertext(reverie_msg: &ReverieMessage, chunk_size: usize) -> Vec<ReverieCiphertextChunk> {
+    let mut header = reverie_msg.clone();
+    header.reverie.umbral_ciphertext = Box::new([]);
+
+    let parts = reverie_msg.reverie.umbral_ciphertext
+        .chunks(chunk_size.max(1))
+        .collect::<Vec<&[u8]>>();
+    let total = parts.len();
+
+    parts.into_iter()
+        .enumerate()
+        .map(|(index, bytes)| ReverieCiphertextChunk {
+            reverie_msg: header.clone(),
+            index,
+            total,
+            bytes: bytes.to_vec(),
+        })
+        .collect()
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct ReverieKeyfragMessage {
     pub reverie_keyfrag: ReverieKeyfrag,
@@ -77,8 +170,73 @@ pub struct ReverieKeyfragMessage {
     pub target_peer_id: PeerId,
 }
 
+/// Off-network backup of a Reverie: its ciphertext, capsule and metadata, without plaintext
+/// or keyfrags, and where its fragments were placed. Signed by the identity key of the node
+/// that created the Reverie, which persists across restarts, as only that node can generate
+/// keyfrags for it when the bundle is imported.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReverieBundle {
+    pub reverie: Reverie,
+    pub target_vessel_peer_id: PeerId,
+    // (frag_num, kfrag provider) placements when the bundle was exported
+    pub kfrag_providers: Vec<(FragmentNumber, PeerId)>,
+    pub source_peer_id: PeerId,
+    // source_peer_id's identity key signature over `ReverieBundle::digest`
+    pub signature: Vec<u8>,
+}
+
+impl ReverieBundle {
+    pub fn new(
+        reverie: Reverie,
+        target_vessel_peer_id: PeerId,
+        kfrag_providers: Vec<(FragmentNumber, PeerId)>,
+        id_keys: &identity::Keypair,
+    ) -> Result<Self> {
+        let source_peer_id = id_keys.public().to_peer_id();
+        let digest = Self::digest(&reverie, &target_vessel_peer_id, &kfrag_providers, &source_peer_id)?;
+        let signature = id_keys.sign(&digest)?;
+        Ok(Self {
+            reverie,
+            target_vessel_peer_id,
+            kfrag_providers,
+            source_peer_id,
+            signature,
+        })
+    }
+
+    pub fn digest(
+        reverie: &Reverie,
+        target_vessel_peer_id: &PeerId,
+        kfrag_providers: &[(FragmentNumber, PeerId)],
+        source_peer_id: &PeerId,
+    ) -> Result<Vec<u8>> {
+        let mut hasher = Keccak256::new();
+        hasher.update(serde_json::to_vec(reverie)?);
+        hasher.update(target_vessel_peer_id.to_bytes());
+        hasher.update(serde_json::to_vec(kfrag_providers)?);
+        hasher.update(source_peer_id.to_bytes());
+        Ok(hasher.finalize().to_vec())
+    }
+
+    /// Checks the bundle is unmodified and was signed by `peer_id`
+    pub fn verify(&self, peer_id: &PeerId) -> Result<()> {
+        if &self.source_peer_id != peer_id {
+            return Err(anyhow!("Reverie bundle {} was not exported by this node", self.reverie.id));
+        }
+        let public_key = identity::PublicKey::try_decode_protobuf(&peer_id.to_bytes())
+            .map_err(|e| anyhow!("Failed to decode public key: {}", e))?;
+        let digest = Self::digest(&self.reverie, &self.target_vessel_peer_id, &self.kfrag_providers, &self.source_peer_id)?;
+        if !public_key.verify(&digest, &self.signature) {
+            return Err(anyhow!("Reverie bundle {} signature does not match its contents", self.reverie.id));
+        }
+        Ok(())
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
 pub struct ReverieCapsulefrag {
+    #[serde(deserialize_with = "deserialize_wire_version")]
+    pub version: u16,
     pub id: ReverieId,
     pub reverie_type: ReverieType,
     pub frag_num: usize,
@@ -92,6 +250,9 @@ pub struct ReverieCapsulefrag {
     // access condition for user permission to access/execute a reverie
     pub access_condition: AccessCondition,
     pub kfrag_provider_peer_id: PeerId,
+    // unix timestamp after which this cfrag is no longer released
+    #[serde(default)]
+    pub expires_at: Option<i64>,
 }
 
 impl ReverieCapsulefrag {
@@ -99,6 +260,10 @@ impl ReverieCapsulefrag {
         serde_json::from_slice(&self.umbral_capsule_frag)
             .map_err(|e| anyhow!("Error deserializing CapsuleFrag: {}", e))
     }
+
+    pub fn is_expired(&self, now: i64) -> bool {
+        self.expires_at.is_some_and(|expires_at| now >= expires_at)
+    }
 }
 
 #[derive(Debug, Clone, Hash, PartialEq, Eq, Deserialize, Serialize)]
@@ -109,12 +274,85 @@ pub enum ReverieType {
     Memory,
     Tools,
     GithubRepo,
+    /// MCP server config (endpoints, credentials and tool definitions), loaded into
+    /// the LLM's tools when decrypted in the target vessel
+    McpPlugin,
 }
 
 impl ReverieType {
+    /// Kinds of ReverieType, as returned by `kind()`
+    pub const KINDS: [&'static str; 7] = ["SovereignAgent", "Agent", "APIKey", "Memory", "Tools", "GithubRepo", "McpPlugin"];
+
     pub fn to_string(&self) -> String {
         self.clone().into()
     }
+
+    /// Variant name without its agent name or api key, used to index reveries by type
+    pub fn kind(&self) -> &'static str {
+        match self {
+            ReverieType::SovereignAgent(..) => "SovereignAgent",
+            ReverieType::Agent(..) => "Agent",
+            ReverieType::APIKey(..) => "APIKey",
+            ReverieType::Memory => "Memory",
+            ReverieType::Tools => "Tools",
+            ReverieType::GithubRepo => "GithubRepo",
+            ReverieType::McpPlugin => "McpPlugin",
+        }
+    }
+
+    /// ReverieType of an agent's next incarnation: the same variant with the nonce
+    /// incremented. None for reveries that aren't agents.
+    pub fn next_incarnation(&self) -> Option<ReverieType> {
+        match self {
+            ReverieType::SovereignAgent(name) => Some(ReverieType::SovereignAgent(name.increment_nonce())),
+            ReverieType::Agent(name) => Some(ReverieType::Agent(name.increment_nonce())),
+            _ => None,
+        }
+    }
+}
+
+/// Where a Reverie's ciphertext goes when its keyfrags are broadcast. Kfrags always go to
+/// the kfrag providers, only the ciphertext's destinations depend on the ReverieType.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DistributionPolicy {
+    /// Put the ciphertext on the DHT, where any node with cfrags can fetch it.
+    /// The broadcaster also puts agent metadata and the agent name's ReverieId with it.
+    pub save_on_network: bool,
+    /// Send the ciphertext to the target vessel, which indexes it by type, tracks the
+    /// previous vessel for respawns, and re-broadcasts it before shutting down.
+    pub send_to_vessel: bool,
+}
+
+impl From<&ReverieType> for DistributionPolicy {
+    fn from(reverie_type: &ReverieType) -> Self {
+        match reverie_type {
+            // Isolated in the vessel's TEE, never put on the DHT
+            ReverieType::SovereignAgent(..) => DistributionPolicy {
+                save_on_network: false,
+                send_to_vessel: true,
+            },
+            // Run by the vessel, which tracks it for respawns, while the next vessel
+            // fetches it from the DHT to respawn it
+            ReverieType::Agent(..) => DistributionPolicy {
+                save_on_network: true,
+                send_to_vessel: true,
+            },
+            // Fetched from the DHT by whoever meets the access condition, and listed
+            // by type in the vessel's reverie type index
+            ReverieType::Memory
+            | ReverieType::McpPlugin => DistributionPolicy {
+                save_on_network: true,
+                send_to_vessel: true,
+            },
+            // Only ever fetched from the DHT, a copy in the vessel would go unused
+            ReverieType::APIKey(..)
+            | ReverieType::Tools
+            | ReverieType::GithubRepo => DistributionPolicy {
+                save_on_network: true,
+                send_to_vessel: false,
+            },
+        }
+    }
 }
 
 impl Into<String> for ReverieType {
@@ -126,6 +364,7 @@ impl Into<String> for ReverieType {
             ReverieType::Memory => "Memory".to_string(),
             ReverieType::Tools => "Tools".to_string(),
             ReverieType::GithubRepo => "GithubRepo".to_string(),
+            ReverieType::McpPlugin => "McpPlugin".to_string(),
         }
     }
 }
@@ -143,23 +382,315 @@ impl Reverie {
         ciphertext: Box<[u8]>
     ) -> Self {
         Self {
+            version: REVERIE_WIRE_VERSION,
             id: reverie_id(),
             reverie_type: reverie_type,
             description: description,
+            tags: vec![],
             threshold: threshold,
             total_frags: total_frags,
             target_public_key: target_public_key,
             verifying_public_key: verifying_public_key,
             access_condition: access_condition,
             umbral_capsule: serde_json::to_vec(&capsule).expect("Failed to serialize capsule"),
-            umbral_ciphertext: ciphertext
+            umbral_ciphertext: ciphertext,
+            expires_at: None,
+            keyfrag_params: PreKeyfragParams::default(),
         }
     }
 
+    pub fn with_tags(mut self, tags: Vec<String>) -> Self {
+        self.tags = tags;
+        self
+    }
+
+    pub fn with_keyfrag_params(mut self, keyfrag_params: PreKeyfragParams) -> Self {
+        self.keyfrag_params = keyfrag_params;
+        self
+    }
+
+    /// Sets a unix timestamp after which kfrag providers refuse to release cfrags
+    pub fn with_expiry(mut self, expires_at: Option<i64>) -> Self {
+        self.expires_at = expires_at;
+        self
+    }
+
     pub fn encode_capsule(&self) -> Result<umbral_pre::Capsule> {
         serde_json::from_slice(&self.umbral_capsule)
             .map_err(|e| anyhow!("Error deserializing Capsule: {}", e))
     }
+
+    /// Rejects ciphertexts larger than an encrypted `max_payload_size` plaintext
+    pub fn check_payload_size(&self, max_payload_size: usize) -> Result<()> {
+        check_reverie_payload_size(
+            self.umbral_ciphertext.len(),
+            max_payload_size + CIPHERTEXT_OVERHEAD
+        )
+    }
+}
+
+impl ReverieKeyfrag {
+    /// Keyfrags and capsules are small and fixed-size, so anything over the
+    /// max payload size is malformed or malicious
+    pub fn check_payload_size(&self, max_payload_size: usize) -> Result<()> {
+        check_reverie_payload_size(
+            self.umbral_keyfrag.len() + self.umbral_capsule.len(),
+            max_payload_size
+        )
+    }
+
+    /// Deserializes the untrusted keyfrag and capsule, verifies the keyfrag against the
+    /// source and target keys, and re-encrypts the capsule into this provider's cfrag.
+    /// Oversized, truncated or garbage fields are errors rather than panics, and
+    /// serde_json's recursion limit bounds how deeply nested they can be.
+    pub fn reencrypt_capsule(&self) -> Result<umbral_pre::VerifiedCapsuleFrag> {
+        for (field, bytes) in [("keyfrag", &self.umbral_keyfrag), ("capsule", &self.umbral_capsule)] {
+            if bytes.len() > MAX_UMBRAL_FRAGMENT_JSON_SIZE {
+                return Err(anyhow!(
+                    "Umbral {} of {} bytes exceeds the max size of {} bytes",
+                    field,
+                    bytes.len(),
+                    MAX_UMBRAL_FRAGMENT_JSON_SIZE
+                ));
+            }
+        }
+
+        let keyfrag: umbral_pre::KeyFrag = serde_json::from_slice(&self.umbral_keyfrag)
+            .map_err(|e| anyhow!("Malformed Umbral keyfrag: {}", e))?;
+        let capsule: umbral_pre::Capsule = serde_json::from_slice(&self.umbral_capsule)
+            .map_err(|e| anyhow!("Malformed Umbral capsule: {}", e))?;
+
+        let verified_kfrag = keyfrag.verify(
+            &self.source_verifying_pubkey,
+            Some(&self.source_pubkey),
+            Some(&self.target_pubkey)
+        ).map_err(|(e, _)| anyhow!("Keyfrag verification failed: {}", e))?;
+
+        Ok(umbral_pre::reencrypt(&capsule, verified_kfrag))
+    }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use runtime::reencrypt::UmbralKey;
 
+    const MAX_PAYLOAD_SIZE: usize = 1024;
+
+    fn reverie_with_plaintext_size(plaintext_size: usize) -> Reverie {
+        let umbral_key = UmbralKey::new(None);
+        let (capsule, ciphertext) = umbral_key.encrypt_bytes(&vec![7u8; plaintext_size]).unwrap();
+        Reverie::new(
+            "test reverie".to_string(),
+            ReverieType::Memory,
+            2,
+            3,
+            umbral_key.public_key,
+            umbral_key.verifying_public_key,
+            AccessCondition::Umbral(umbral_key.public_key),
+            capsule,
+            ciphertext,
+        )
+    }
+
+    #[test]
+    fn next_incarnation_keeps_the_agent_variant() {
+        let agent = ReverieNameWithNonce("agent".to_string(), 1);
+        assert_eq!(
+            ReverieType::Agent(agent.clone()).next_incarnation(),
+            Some(ReverieType::Agent(ReverieNameWithNonce("agent".to_string(), 2)))
+        );
+        assert_eq!(
+            ReverieType::SovereignAgent(agent).next_incarnation(),
+            Some(ReverieType::SovereignAgent(ReverieNameWithNonce("agent".to_string(), 2)))
+        );
+        assert_eq!(ReverieType::Memory.next_incarnation(), None);
+    }
+
+    #[test]
+    fn payload_just_under_limit_is_accepted() {
+        assert!(check_reverie_payload_size(MAX_PAYLOAD_SIZE - 1, MAX_PAYLOAD_SIZE).is_ok());
+        assert!(check_reverie_payload_size(MAX_PAYLOAD_SIZE, MAX_PAYLOAD_SIZE).is_ok());
+        assert!(reverie_with_plaintext_size(MAX_PAYLOAD_SIZE).check_payload_size(MAX_PAYLOAD_SIZE).is_ok());
+    }
+
+    #[test]
+    fn payload_just_over_limit_is_rejected() {
+        let err = check_reverie_payload_size(MAX_PAYLOAD_SIZE + 1, MAX_PAYLOAD_SIZE).unwrap_err();
+        assert!(err.to_string().contains("exceeds the max size"));
+        assert!(reverie_with_plaintext_size(MAX_PAYLOAD_SIZE + CIPHERTEXT_OVERHEAD + 1)
+            .check_payload_size(MAX_PAYLOAD_SIZE)
+            .is_err());
+    }
+
+    #[test]
+    fn reverie_bundle_verifies_only_unmodified_and_with_signing_key() {
+        use crate::utils::pubkeys::derive_node_identity;
+
+        let kfrag_providers = vec![(0, PeerId::random()), (1, PeerId::random())];
+        let bundle = ReverieBundle::new(
+            reverie_with_plaintext_size(32),
+            PeerId::random(),
+            kfrag_providers,
+            &derive_node_identity(7).keypair,
+        ).unwrap();
+        // The identity key is derived from the node's seed, so it still verifies after a restart
+        let peer_id = derive_node_identity(7).peer_id;
+        assert!(bundle.verify(&peer_id).is_ok());
+
+        // another node
+        assert!(bundle.verify(&PeerId::random()).is_err());
+        let mut forged = ReverieBundle::new(
+            bundle.reverie.clone(),
+            bundle.target_vessel_peer_id,
+            bundle.kfrag_providers.clone(),
+            &identity::Keypair::generate_ed25519(),
+        ).unwrap();
+        forged.source_peer_id = peer_id;
+        assert!(forged.verify(&peer_id).is_err());
+
+        // tampered metadata, vessel or placements
+        let mut tampered = bundle.clone();
+        tampered.reverie.threshold = 1;
+        assert!(tampered.verify(&peer_id).is_err());
+        let mut tampered = bundle.clone();
+        tampered.target_vessel_peer_id = PeerId::random();
+        assert!(tampered.verify(&peer_id).is_err());
+        let mut tampered = bundle.clone();
+        tampered.kfrag_providers.pop();
+        assert!(tampered.verify(&peer_id).is_err());
+    }
+
+    fn reverie_keyfrag(reverie: &Reverie) -> ReverieKeyfrag {
+        ReverieKeyfrag {
+            version: REVERIE_WIRE_VERSION,
+            id: reverie.id.clone(),
+            reverie_type: reverie.reverie_type.clone(),
+            frag_num: 1,
+            threshold: reverie.threshold,
+            total_frags: reverie.total_frags,
+            umbral_keyfrag: vec![1, 2, 3],
+            umbral_capsule: vec![4, 5, 6],
+            source_pubkey: reverie.target_public_key,
+            source_verifying_pubkey: reverie.verifying_public_key,
+            target_pubkey: reverie.target_public_key,
+            target_verifying_pubkey: reverie.verifying_public_key,
+            access_condition: reverie.access_condition.clone(),
+            expires_at: Some(1_700_000_000),
+        }
+    }
+
+    fn reverie_cfrag(reverie: &Reverie) -> ReverieCapsulefrag {
+        ReverieCapsulefrag {
+            version: REVERIE_WIRE_VERSION,
+            id: reverie.id.clone(),
+            reverie_type: reverie.reverie_type.clone(),
+            frag_num: 1,
+            threshold: reverie.threshold,
+            umbral_capsule_frag: vec![7, 8, 9],
+            source_pubkey: reverie.target_public_key,
+            source_verifying_pubkey: reverie.verifying_public_key,
+            target_pubkey: reverie.target_public_key,
+            target_verifying_pubkey: reverie.verifying_public_key,
+            access_condition: reverie.access_condition.clone(),
+            kfrag_provider_peer_id: PeerId::random(),
+            expires_at: None,
+        }
+    }
+
+    /// Serializes to JSON, pins the leading fields of the wire format, and deserializes back
+    fn assert_wire_round_trip<T>(value: &T, wire_prefix: &str)
+    where
+        T: Serialize + for<'de> Deserialize<'de> + PartialEq + std::fmt::Debug,
+    {
+        let json = serde_json::to_string(value).unwrap();
+        assert!(json.starts_with(wire_prefix), "{}", json);
+
+        let decoded: T = serde_json::from_str(&json).unwrap();
+        assert_eq!(&decoded, value);
+    }
+
+    #[test]
+    fn malformed_keyfrags_are_rejected_without_panicking() {
+        let source_key = UmbralKey::new(None);
+        let target_key = UmbralKey::new(None);
+        let (capsule, _ciphertext) = source_key.encrypt_bytes(&b"secrets".to_vec()).unwrap();
+        let kfrag = source_key.generate_pre_keyfrags(&target_key.public_key, 2, 3, true, false).remove(0);
+
+        let mut reverie_keyfrag = reverie_keyfrag(&reverie_with_plaintext_size(32));
+        reverie_keyfrag.umbral_keyfrag = serde_json::to_vec(&kfrag).unwrap();
+        reverie_keyfrag.umbral_capsule = serde_json::to_vec(&capsule).unwrap();
+        reverie_keyfrag.source_pubkey = source_key.public_key;
+        reverie_keyfrag.source_verifying_pubkey = source_key.verifying_public_key;
+        reverie_keyfrag.target_pubkey = target_key.public_key;
+        assert!(reverie_keyfrag.reencrypt_capsule().is_ok());
+
+        let well_formed = reverie_keyfrag.clone();
+        let truncated_keyfrag = well_formed.umbral_keyfrag[..well_formed.umbral_keyfrag.len() / 2].to_vec();
+        let truncated_capsule = well_formed.umbral_capsule[..well_formed.umbral_capsule.len() / 2].to_vec();
+        let nested = format!("{}{}", "[".repeat(1000), "]".repeat(1000)).into_bytes();
+
+        let malformed = [
+            ReverieKeyfrag { umbral_keyfrag: truncated_keyfrag, ..well_formed.clone() },
+            ReverieKeyfrag { umbral_keyfrag: vec![0xff; 64], ..well_formed.clone() },
+            ReverieKeyfrag { umbral_keyfrag: vec![], ..well_formed.clone() },
+            ReverieKeyfrag { umbral_keyfrag: nested, ..well_formed.clone() },
+            ReverieKeyfrag { umbral_keyfrag: vec![b'1'; MAX_UMBRAL_FRAGMENT_JSON_SIZE + 1], ..well_formed.clone() },
+            ReverieKeyfrag { umbral_capsule: truncated_capsule, ..well_formed.clone() },
+            ReverieKeyfrag { umbral_capsule: b"\"garbage\"".to_vec(), ..well_formed.clone() },
+            // well-formed, but for another source key
+            ReverieKeyfrag { source_verifying_pubkey: target_key.verifying_public_key, ..well_formed.clone() },
+        ];
+        for reverie_keyfrag in malformed {
+            assert!(reverie_keyfrag.reencrypt_capsule().is_err());
+        }
+    }
+
+    #[test]
+    fn reverie_wire_types_round_trip() {
+        let reverie = reverie_with_plaintext_size(32);
+        let id = &reverie.id;
+        assert_wire_round_trip(
+            &reverie,
+            &format!(r#"{{"version":1,"id":"{id}","reverie_type":"Memory","description":"test reverie","tags":[],"threshold":2,"total_frags":3,"#)
+        );
+        assert_wire_round_trip(
+            &reverie_keyfrag(&reverie),
+            &format!(r#"{{"version":1,"id":"{id}","reverie_type":"Memory","frag_num":1,"threshold":2,"total_frags":3,"#)
+        );
+        assert_wire_round_trip(
+            &reverie_cfrag(&reverie),
+            &format!(r#"{{"version":1,"id":"{id}","reverie_type":"Memory","frag_num":1,"threshold":2,"#)
+        );
+    }
+
+    #[test]
+    fn reverie_without_keyfrag_params_signs_the_delegating_key() {
+        let reverie = reverie_with_plaintext_size(32)
+            .with_keyfrag_params(PreKeyfragParams { sign_delegating_key: true, sign_receiving_key: true });
+
+        let mut json = serde_json::to_value(&reverie).unwrap();
+        json.as_object_mut().unwrap().remove("keyfrag_params");
+        let decoded: Reverie = serde_json::from_value(json).unwrap();
+        assert_eq!(decoded.keyfrag_params, PreKeyfragParams { sign_delegating_key: true, sign_receiving_key: false });
+    }
+
+    #[test]
+    fn mismatched_wire_version_is_rejected() {
+        let reverie = reverie_with_plaintext_size(32);
+
+        let mut json = serde_json::to_value(&reverie).unwrap();
+        json["version"] = serde_json::json!(REVERIE_WIRE_VERSION + 1);
+        let err = serde_json::from_value::<Reverie>(json).unwrap_err();
+        assert!(err.to_string().contains("unsupported Reverie wire format version 2"), "{}", err);
+
+        let mut json = serde_json::to_value(reverie_keyfrag(&reverie)).unwrap();
+        json["version"] = serde_json::json!(0);
+        assert!(serde_json::from_value::<ReverieKeyfrag>(json).is_err());
+
+        // peers from before versioning send no version at all
+        let mut json = serde_json::to_value(reverie_cfrag(&reverie)).unwrap();
+        json.as_object_mut().unwrap().remove("version");
+        assert!(serde_json::from_value::<ReverieCapsulefrag>(json).is_err());
+    }
+}
@@ -27,6 +27,8 @@ pub enum AccessKey {
     UmbralSignature(SignatureBytes),
     /// Requires valid ED25519 signature (e.g. from Solana or NEAR)
     Ed25519Signature(SignatureBytes),
+    /// Requires valid secp256r1 (P256) ECDSA signature (e.g. from passkeys or the LLM proxy)
+    P256Signature(SignatureBytes),
     /// NEAR Contract Access Condition
     NearContract(
         String, // contract address
@@ -39,6 +41,15 @@ pub enum AccessKey {
         ContractMethod, // contract method name
         ContractArgs // contract calldata
     ),
+    /// Ethereum event log Access Condition (e.g. an on-chain payment event for the reverie).
+    /// The payer signs the reverie_id so another requester can't replay their payment.
+    EthEvent {
+        contract_address: alloy_primitives::Address,
+        event_signature: String,
+        reverie_id: ReverieId,
+        payer: alloy_primitives::Address,
+        payer_signature: SignatureBytes,
+    },
 }
 
 impl From<Signature> for AccessKey {
@@ -49,7 +60,7 @@ impl From<Signature> for AccessKey {
 
 impl From<String> for AccessKey {
     fn from(sig: String) -> Self {
-        let re = Regex::new(r"^(Umbral|Ecdsa|Ed25519)\((.*)\)$").unwrap();
+        let re = Regex::new(r"^(Umbral|Ecdsa|Ed25519|P256)\((.*)\)$").unwrap();
 
         if let Some(captures) = re.captures(&sig) {
             let prefix = captures.get(1).unwrap().as_str();
@@ -60,6 +71,7 @@ impl From<String> for AccessKey {
                 "Umbral" => AccessKey::UmbralSignature(bytes),
                 "Ecdsa" => AccessKey::EcdsaSignature(bytes),
                 "Ed25519" => AccessKey::Ed25519Signature(bytes),
+                "P256" => AccessKey::P256Signature(bytes),
                 _ => unreachable!(), // We know the regex only matches these prefixes
             }
         } else {
@@ -82,6 +94,9 @@ impl AccessKey {
             AccessKey::Ed25519Signature(sig_bytes) => {
                 unimplemented!("Ed25519 signatures are not implemented yet");
             },
+            AccessKey::P256Signature(sig_bytes) => {
+                return Err(anyhow!("Deserializing P256 signatures is not meaningful in this context"));
+            },
             AccessKey::NearContract(
                 address,
                 method_name,
@@ -96,6 +111,9 @@ impl AccessKey {
             ) => {
                 unimplemented!("EthContract access keys are not implemented yet");
             },
+            AccessKey::EthEvent { .. } => {
+                return Err(anyhow!("EthEvent access keys do not carry a signature"));
+            },
         }.map_err(|e| anyhow!("Failed to deserialize signature: {}", e))
     }
 
@@ -148,8 +166,47 @@ impl AccessKey {
                     false
                 }
             },
-            AccessKey::Ed25519Signature(_) => {
-                unimplemented!("Ed25519 signatures are not implemented yet");
+            AccessKey::Ed25519Signature(sig_bytes) => {
+                if let AccessCondition::Ed25519(pubkey_hex) = access_condition {
+                    let verifying_key = hex::decode(pubkey_hex.trim_start_matches("0x"))
+                        .ok()
+                        .and_then(|bytes| <[u8; ed25519_dalek::PUBLIC_KEY_LENGTH]>::try_from(bytes).ok())
+                        .and_then(|bytes| ed25519_dalek::VerifyingKey::from_bytes(&bytes).ok());
+                    let signature = ed25519_dalek::Signature::from_slice(sig_bytes).ok();
+                    match (verifying_key, signature) {
+                        (Some(verifying_key), Some(signature)) => {
+                            verifying_key.verify_strict(message_hash, &signature).is_ok()
+                        }
+                        _ => {
+                            tracing::warn!("Failed to parse Ed25519 public key or signature");
+                            false
+                        }
+                    }
+                } else {
+                    tracing::warn!("AccessKey::Ed25519Signature cannot be used for AccessCondition::{}", access_condition.get_type());
+                    false
+                }
+            },
+            AccessKey::P256Signature(sig_bytes) => {
+                if let AccessCondition::P256(pubkey_hex) = access_condition {
+                    use p256::ecdsa::signature::Verifier;
+                    let verifying_key = hex::decode(pubkey_hex.trim_start_matches("0x"))
+                        .ok()
+                        .and_then(|bytes| p256::ecdsa::VerifyingKey::from_sec1_bytes(&bytes).ok());
+                    let signature = p256::ecdsa::Signature::from_slice(sig_bytes).ok();
+                    match (verifying_key, signature) {
+                        (Some(verifying_key), Some(signature)) => {
+                            verifying_key.verify(message_hash, &signature).is_ok()
+                        }
+                        _ => {
+                            tracing::warn!("Failed to parse P256 public key or signature");
+                            false
+                        }
+                    }
+                } else {
+                    tracing::warn!("AccessKey::P256Signature cannot be used for AccessCondition::{}", access_condition.get_type());
+                    false
+                }
             },
             AccessKey::NearContract(
                 contract_address,
@@ -165,10 +222,74 @@ impl AccessKey {
             ) => {
                 unimplemented!("EthContract access keys are not implemented yet");
             },
+            AccessKey::EthEvent { .. } => {
+                // Requires querying event logs on-chain, see EvmRuntime::has_event_log
+                tracing::warn!("AccessKey::EthEvent must be verified against on-chain event logs");
+                false
+            },
+        }
+    }
+
+    /// Checks an EthEvent key's payer signed keccak256(reverie_id), so the payment
+    /// event can only be claimed by the account that made it
+    pub fn verify_payer<T: ToString>(&self, reverie_id: T) -> bool {
+        match self {
+            AccessKey::EthEvent { payer, payer_signature, .. } => {
+                AccessKey::EcdsaSignature(payer_signature.clone())
+                    .verify_access(&AccessCondition::Ecdsa(*payer), reverie_id)
+            }
+            _ => false,
         }
     }
 }
 
+/// Challenge a spawner signs to prove they control the key in the reverie's AccessCondition.
+/// Signatures are made over keccak256(challenge), the same as for fragment requests.
+pub fn create_spawn_challenge(memory_secrets: &serde_json::Value, access_condition: &AccessCondition) -> String {
+    let digest = Keccak256::digest(format!("{}{}", memory_secrets, access_condition).as_bytes());
+    hex::encode(digest)
+}
+
+/// Verifies the spawn signature for key-based AccessConditions (Umbral, Ecdsa, Ed25519, P256).
+/// Contract-based AccessConditions are gated on-chain and need no signature.
+pub fn verify_spawn_signature(
+    spawn_signature: Option<&AccessKey>,
+    access_condition: &AccessCondition,
+    challenge: &str,
+) -> Result<()> {
+    match access_condition {
+        AccessCondition::Umbral(_) |
+        AccessCondition::Ecdsa(_) |
+        AccessCondition::Ed25519(_) |
+        AccessCondition::P256(_) => {
+            let spawn_signature = spawn_signature.ok_or_else(|| anyhow!(
+                "Spawning with a {} AccessCondition requires a signature over the spawn challenge",
+                access_condition.get_type()
+            ))?;
+            // Only signature keys can prove control of the AccessCondition's key;
+            // contract and event keys would reach unimplemented verifiers.
+            match spawn_signature {
+                AccessKey::UmbralSignature(_) |
+                AccessKey::EcdsaSignature(_) |
+                AccessKey::Ed25519Signature(_) |
+                AccessKey::P256Signature(_) => {}
+                AccessKey::NearContract(..) |
+                AccessKey::EthContract(..) |
+                AccessKey::EthEvent { .. } => {
+                    return Err(anyhow!("Spawn signature must be a signature AccessKey, got {}", spawn_signature));
+                }
+            }
+            match spawn_signature.verify_access(access_condition, challenge) {
+                true => Ok(()),
+                false => Err(anyhow!("Spawn signature does not match AccessCondition {}", access_condition)),
+            }
+        }
+        AccessCondition::NearContract(..) |
+        AccessCondition::EthContract(..) |
+        AccessCondition::EthEvent(..) => Ok(()),
+    }
+}
+
 pub fn create_digest_hash(reverie_id: &ReverieId, nonce: usize, timestamp: usize) -> B256 {
     let digest = Keccak256::digest(reverie_id.as_bytes());
     let hash = B256::from_slice(digest.as_slice());
@@ -187,6 +308,9 @@ impl std::fmt::Display for AccessKey {
             AccessKey::Ed25519Signature(sig_bytes) => {
                 format!("Ed25519Signature(0x{})", hex::encode(sig_bytes.clone()))
             }
+            AccessKey::P256Signature(sig_bytes) => {
+                format!("P256Signature(0x{})", hex::encode(sig_bytes.clone()))
+            }
             AccessKey::NearContract(
                 address,
                 method_name,
@@ -201,6 +325,15 @@ impl std::fmt::Display for AccessKey {
             ) => {
                 format!("EthContract(0x{}, {}, {})", address, method_name, calldata)
             }
+            AccessKey::EthEvent {
+                contract_address,
+                event_signature,
+                reverie_id,
+                payer,
+                ..
+            } => {
+                format!("EthEvent({}, {}, {}, {})", contract_address, event_signature, reverie_id, payer)
+            }
         };
         write!(f, "{}", hex_sig)
     }
@@ -212,8 +345,10 @@ impl AccessKey {
             AccessKey::UmbralSignature(_) => "umbral".to_string(),
             AccessKey::EcdsaSignature(_) => "ecdsa".to_string(),
             AccessKey::Ed25519Signature(_) => "ed25519".to_string(),
+            AccessKey::P256Signature(_) => "p256".to_string(),
             AccessKey::NearContract(_, _, _) => "near_contract".to_string(),
             AccessKey::EthContract(_, _, _) => "eth_contract".to_string(),
+            AccessKey::EthEvent { .. } => "eth_event".to_string(),
         }
     }
 }
@@ -226,6 +361,8 @@ pub enum AccessCondition {
     Ecdsa(Address),
     /// Requires a signature matching the ED25519 address
     Ed25519(String),
+    /// Requires a signature matching the P256 public key (hex encoded SEC1 bytes)
+    P256(String),
     /// Requires NEAR contract evaluation (preconditions)
     NearContract(
         // contract address
@@ -244,6 +381,13 @@ pub enum AccessCondition {
         // access function args
         ContractArgs
     ),
+    /// Requires an event log emitted by an ETH contract (e.g. a payment event)
+    EthEvent(
+        // contract address
+        alloy_primitives::Address,
+        // event signature, e.g. "ReveriePaid(string,address)"
+        String
+    ),
 }
 
 
@@ -259,6 +403,9 @@ impl std::fmt::Display for AccessCondition {
             AccessCondition::Ed25519(pubkey) => {
                 format!("ed25519:{}", pubkey.to_string())
             }
+            AccessCondition::P256(pubkey) => {
+                format!("p256:{}", pubkey.to_string())
+            }
             AccessCondition::NearContract(
                 address,
                 access_function_name,
@@ -273,6 +420,9 @@ impl std::fmt::Display for AccessCondition {
             ) => {
                 format!("eth_contract:{}:{}:{}", address.to_string(), access_function_name, access_function_args)
             }
+            AccessCondition::EthEvent(address, event_signature) => {
+                format!("eth_event:{}:{}", address.to_string(), event_signature)
+            }
         };
         write!(f, "{}", hex_sig)
     }
@@ -284,8 +434,10 @@ impl AccessCondition {
             AccessCondition::Umbral(_) => "Umbral".to_string(),
             AccessCondition::Ecdsa(_) => "Ecdsa".to_string(),
             AccessCondition::Ed25519(_) => "Ed25519".to_string(),
+            AccessCondition::P256(_) => "P256".to_string(),
             AccessCondition::NearContract(_, _, _) => "NearContract".to_string(),
             AccessCondition::EthContract(_, _, _) => "EthContract".to_string(),
+            AccessCondition::EthEvent(_, _) => "EthEvent".to_string(),
         }
     }
 }
@@ -317,6 +469,20 @@ impl FromStr for AccessCondition {
                 "ed25519" => {
                     Ok(AccessCondition::Ed25519(value.to_string()))
                 }
+                "p256" => {
+                    let bytes = hex::decode(value.trim_start_matches("0x"))
+                        .map_err(|e| anyhow!("Invalid hex for P256 key '{}': {}", value, e))?;
+                    p256::ecdsa::VerifyingKey::from_sec1_bytes(&bytes)
+                        .map_err(|e| anyhow!("Failed to deserialize P256 public key from bytes: {}", e))?;
+                    Ok(AccessCondition::P256(value.to_string()))
+                }
+                "eth_event" => {
+                    let (addr, event_signature) = value.split_once(':')
+                        .ok_or_else(|| anyhow!("Invalid AccessCondition format: expected 'eth_event:address:event_signature', got '{}'", s))?;
+                    let addr = Address::from_str(addr)
+                        .map_err(|e| anyhow!("Failed to parse contract address from '{}': {}", addr, e))?;
+                    Ok(AccessCondition::EthEvent(addr, event_signature.to_string()))
+                }
                 "contract" => {
                     // TODO: implement contract FromStr for AccessCondition
                     unimplemented!("contract serialization for AccessCondition not implemented yet");
@@ -411,6 +577,126 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_eth_event_access_condition_from_str() -> Result<()> {
+        let address = Address::from_str("0x4200000000000000000000000000000000000006")?;
+        let access_condition = AccessCondition::EthEvent(address, "ReveriePaid(string,address)".to_string());
+
+        let parsed = AccessCondition::from_str(&access_condition.to_string())?;
+        assert_eq!(parsed, access_condition);
+        assert!(AccessCondition::from_str("eth_event:0x4200000000000000000000000000000000000006").is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_eth_event_access_key_requires_payer_signature() -> Result<()> {
+        let contract_address = Address::from_str("0x4200000000000000000000000000000000000006")?;
+        let payer = create_test_signer().await?;
+        let other_signer = create_test_signer().await?;
+        let reverie_id = "reverie_1234";
+        let hash = B256::from_slice(&Keccak256::digest(reverie_id.as_bytes()));
+
+        let eth_event_key = |payer_signature: Vec<u8>| AccessKey::EthEvent {
+            contract_address,
+            event_signature: "ReveriePaid(string,address)".to_string(),
+            reverie_id: reverie_id.to_string(),
+            payer: payer.address(),
+            payer_signature,
+        };
+        let signed_by_payer = eth_event_key(payer.sign_hash(&hash).await?.as_bytes().to_vec());
+        let signed_by_other = eth_event_key(other_signer.sign_hash(&hash).await?.as_bytes().to_vec());
+
+        assert!(signed_by_payer.verify_payer(reverie_id));
+        assert!(!signed_by_other.verify_payer(reverie_id));
+        assert!(!signed_by_payer.verify_payer("reverie_other"));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_verify_spawn_signature() -> Result<()> {
+        let memory_secrets = serde_json::json!({ "memories": "secret context" });
+
+        // Ecdsa
+        let signer = create_test_signer().await?;
+        let other_signer = create_test_signer().await?;
+        let access_condition = AccessCondition::Ecdsa(signer.address());
+        let challenge = create_spawn_challenge(&memory_secrets, &access_condition);
+        let hash = B256::from_slice(&Keccak256::digest(challenge.as_bytes()));
+        let signature = AccessKey::EcdsaSignature(signer.sign_hash(&hash).await?.as_bytes().to_vec());
+        let wrong_signature = AccessKey::EcdsaSignature(other_signer.sign_hash(&hash).await?.as_bytes().to_vec());
+        assert!(verify_spawn_signature(Some(&signature), &access_condition, &challenge).is_ok());
+        assert!(verify_spawn_signature(Some(&wrong_signature), &access_condition, &challenge).is_err());
+        assert!(verify_spawn_signature(None, &access_condition, &challenge).is_err());
+        // Signature over a different reverie can't be reused
+        let other_challenge = create_spawn_challenge(&serde_json::json!({}), &access_condition);
+        assert!(verify_spawn_signature(Some(&signature), &access_condition, &other_challenge).is_err());
+
+        // Umbral
+        let umbral_signer = umbral_pre::Signer::new(umbral_pre::SecretKey::random());
+        let access_condition = AccessCondition::Umbral(umbral_signer.verifying_key());
+        let challenge = create_spawn_challenge(&memory_secrets, &access_condition);
+        let umbral_sig = umbral_signer.sign(&Keccak256::digest(challenge.as_bytes()));
+        let signature = AccessKey::UmbralSignature(serde_json::to_vec(&umbral_sig)?);
+        let other_umbral_signer = umbral_pre::Signer::new(umbral_pre::SecretKey::random());
+        let wrong_sig = other_umbral_signer.sign(&Keccak256::digest(challenge.as_bytes()));
+        let wrong_signature = AccessKey::UmbralSignature(serde_json::to_vec(&wrong_sig)?);
+        assert!(verify_spawn_signature(Some(&signature), &access_condition, &challenge).is_ok());
+        assert!(verify_spawn_signature(Some(&wrong_signature), &access_condition, &challenge).is_err());
+
+        // Ed25519
+        use ed25519_dalek::Signer as _;
+        let ed_signer = ed25519_dalek::SigningKey::from_bytes(&[7u8; 32]);
+        let access_condition = AccessCondition::Ed25519(hex::encode(ed_signer.verifying_key().to_bytes()));
+        let challenge = create_spawn_challenge(&memory_secrets, &access_condition);
+        let ed_sig = ed_signer.sign(&Keccak256::digest(challenge.as_bytes()));
+        let signature = AccessKey::Ed25519Signature(ed_sig.to_bytes().to_vec());
+        let wrong_sig = ed25519_dalek::SigningKey::from_bytes(&[8u8; 32])
+            .sign(&Keccak256::digest(challenge.as_bytes()));
+        let wrong_signature = AccessKey::Ed25519Signature(wrong_sig.to_bytes().to_vec());
+        assert!(verify_spawn_signature(Some(&signature), &access_condition, &challenge).is_ok());
+        assert!(verify_spawn_signature(Some(&wrong_signature), &access_condition, &challenge).is_err());
+        // Signature type must match the AccessCondition
+        assert!(verify_spawn_signature(Some(&signature), &AccessCondition::Ecdsa(signer.address()), &challenge).is_err());
+        // Contract keys are rejected rather than passed to verify_access
+        let near_key = AccessKey::NearContract("reverie.testnet".to_string(), "alice.testnet".to_string(), 1);
+        let eth_key = AccessKey::EthContract(signer.address(), "hasAccess".to_string(), serde_json::json!({}));
+        assert!(verify_spawn_signature(Some(&near_key), &access_condition, &challenge).is_err());
+        assert!(verify_spawn_signature(Some(&eth_key), &access_condition, &challenge).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_p256_signature_verification() -> Result<()> {
+        use p256::ecdsa::{SigningKey, Signature as P256Signature, signature::Signer as _};
+
+        let signing_key = SigningKey::from_slice(&[7u8; 32])?;
+        let pubkey_hex = hex::encode(signing_key.verifying_key().to_encoded_point(true).as_bytes());
+        let access_condition = AccessCondition::from_str(&format!("p256:{}", pubkey_hex))?;
+        assert_eq!(access_condition, AccessCondition::P256(pubkey_hex));
+
+        let reverie_id = "reverie_p256";
+        let signature: P256Signature = signing_key.sign(&Keccak256::digest(reverie_id.as_bytes()));
+        let access_key = AccessKey::P256Signature(signature.to_bytes().to_vec());
+        assert!(access_key.verify_access(&access_condition, reverie_id));
+        assert!(!access_key.verify_access(&access_condition, "reverie_other"));
+
+        // Signature from another P256 key
+        let other_signing_key = SigningKey::from_slice(&[8u8; 32])?;
+        let other_signature: P256Signature = other_signing_key.sign(&Keccak256::digest(reverie_id.as_bytes()));
+        let wrong_access_key = AccessKey::P256Signature(other_signature.to_bytes().to_vec());
+        assert!(!wrong_access_key.verify_access(&access_condition, reverie_id));
+
+        // Signature type must match the AccessCondition
+        let ed25519_condition = AccessCondition::Ed25519(hex::encode([1u8; 32]));
+        assert!(!access_key.verify_access(&ed25519_condition, reverie_id));
+        assert!(AccessCondition::from_str("p256:deadbeef").is_err());
+
+        let roundtrip = AccessKey::from(format!("P256({})", hex::encode(signature.to_bytes())));
+        assert_eq!(roundtrip, access_key);
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_ecdsa_signature_verification() -> Result<()> {
         let signer = create_test_signer().await?;
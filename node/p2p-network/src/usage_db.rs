@@ -6,8 +6,9 @@ use std::path::Path;
 use std::sync::Arc;
 use tracing::{info, error, warn, trace};
 use color_eyre::eyre::{Result, anyhow};
+use serde::{Deserialize, Serialize};
 
-use llm_proxy::usage::UsageReportPayload;
+use llm_proxy::usage::{UsageData, UsageReportPayload};
 
 pub type UsageDbPool = Arc<Pool<SqliteConnectionManager>>;
 
@@ -36,6 +37,22 @@ CREATE INDEX IF NOT EXISTS idx_usage_reports_tool_id ON usage_reports(tool_id);
 CREATE INDEX IF NOT EXISTS idx_usage_reports_linked_tool_id ON usage_reports(linked_tool_id);
 CREATE INDEX IF NOT EXISTS idx_usage_reports_reverie_id ON usage_reports(reverie_id);
 CREATE INDEX IF NOT EXISTS idx_usage_reports_spender_address ON usage_reports(spender_address);
+
+CREATE TABLE IF NOT EXISTS usage_spend (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    reverie_id TEXT NOT NULL,
+    spender TEXT NOT NULL,
+    timestamp INTEGER NOT NULL,
+    input_tokens INTEGER NOT NULL,
+    output_tokens INTEGER NOT NULL,
+    cache_creation_tokens INTEGER NOT NULL,
+    cache_read_tokens INTEGER NOT NULL,
+    cost INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_usage_spend_reverie_id ON usage_spend(reverie_id);
+CREATE INDEX IF NOT EXISTS idx_usage_spend_spender ON usage_spend(spender);
+CREATE INDEX IF NOT EXISTS idx_usage_spend_timestamp ON usage_spend(timestamp);
 ";
 
 /// Initializes the SQLite database pool for usage reports.
@@ -131,7 +148,7 @@ pub fn read_usage_data_for_reverie(pool: &UsageDbPool, reverie_id: &str) -> Resu
         Ok(llm_proxy::usage::UsageReportPayload {
             usage: usage_data,
             timestamp: row.get(1)?,
-            linked_tool_use_id: row.get(10)?,
+            linked_tool_use_ids: decode_linked_tool_use_ids(row.get(10)?),
             request_id: row.get(0)?,
         })
     })?;
@@ -154,8 +171,37 @@ pub fn read_usage_data_for_reverie(pool: &UsageDbPool, reverie_id: &str) -> Resu
     Ok(results)
 }
 
+/// linked_tool_use_ids are stored as a JSON array, NULL when the request had no tool_results
+fn encode_linked_tool_use_ids(linked_tool_use_ids: &[String]) -> Option<String> {
+    if linked_tool_use_ids.is_empty() {
+        None
+    } else {
+        serde_json::to_string(linked_tool_use_ids).ok()
+    }
+}
+
+/// Rows written before multiple tool_results were linked hold a single tool_use_id
+fn decode_linked_tool_use_ids(linked_tool_id: Option<String>) -> Vec<String> {
+    match linked_tool_id {
+        None => vec![],
+        Some(value) => serde_json::from_str::<Vec<String>>(&value).unwrap_or_else(|_| vec![value]),
+    }
+}
+
+/// Whether a usage report with this request_id has already been stored.
+pub fn usage_report_exists(pool: &UsageDbPool, request_id: &str) -> Result<bool> {
+    let conn = pool.get()?;
+    let count: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM usage_reports WHERE request_id = ?1",
+        params![request_id],
+        |row| row.get(0),
+    )?;
+    Ok(count > 0)
+}
+
 /// Stores a verified usage report payload in the database.
-pub fn store_usage_payload(pool: &UsageDbPool, payload: &UsageReportPayload) -> Result<()> {
+/// Idempotent on request_id: returns false if the report was already stored.
+pub fn store_usage_payload(pool: &UsageDbPool, payload: &UsageReportPayload) -> Result<bool> {
     trace!("Storing usage report payload to DB: request_id={}, timestamp={}", payload.request_id, payload.timestamp);
     let conn = pool.get()?;
 
@@ -166,8 +212,8 @@ pub fn store_usage_payload(pool: &UsageDbPool, payload: &UsageReportPayload) ->
         .map(|tu| serde_json::to_string(&tu.input).unwrap_or_else(|_| "null".to_string()));
     let tool_type = payload.usage.tool_use.as_ref().map(|tu| tu.tool_type.clone());
 
-    conn.execute(
-        "INSERT INTO usage_reports (
+    let inserted = conn.execute(
+        "INSERT OR IGNORE INTO usage_reports (
             request_id, timestamp, input_tokens, output_tokens, cache_creation_tokens, cache_read_tokens,
             tool_id, tool_name, tool_input, tool_type, linked_tool_id,
             reverie_id, spender_address, spender_type -- Added DB columns
@@ -183,13 +229,292 @@ pub fn store_usage_payload(pool: &UsageDbPool, payload: &UsageReportPayload) ->
             tool_name,
             tool_input_json,
             tool_type,
-            payload.linked_tool_use_id,
+            encode_linked_tool_use_ids(&payload.linked_tool_use_ids),
             payload.usage.reverie_id,
             payload.usage.spender,
             payload.usage.spender_type,
         ],
     )?;
 
+    if inserted == 0 {
+        warn!("Usage report already stored, skipping duplicate: request_id={}", payload.request_id);
+        return Ok(false);
+    }
     trace!("Successfully stored usage report payload for request_id: {}", payload.request_id);
-    Ok(())
-}
\ No newline at end of file
+    Ok(true)
+}
+
+
+/// A single spend entry recorded for a reverie and spender.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UsageEntry {
+    pub reverie_id: String,
+    pub spender: String,
+    pub timestamp: i64,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub cache_creation_tokens: u64,
+    pub cache_read_tokens: u64,
+    pub cost: u64,
+}
+
+/// Aggregated usage over a set of spend entries.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UsageTotals {
+    pub num_records: u64,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub cache_creation_tokens: u64,
+    pub cache_read_tokens: u64,
+    pub cost: u64,
+}
+
+const SELECT_USAGE_TOTALS: &str = "
+SELECT COUNT(*),
+       COALESCE(SUM(input_tokens), 0),
+       COALESCE(SUM(output_tokens), 0),
+       COALESCE(SUM(cache_creation_tokens), 0),
+       COALESCE(SUM(cache_read_tokens), 0),
+       COALESCE(SUM(cost), 0)
+FROM usage_spend";
+
+/// Typed interface over the usage database for recording and querying spend
+/// by reverie and spender. Tables are created by `init_usage_db`.
+#[derive(Clone)]
+pub struct UsageStore {
+    pool: UsageDbPool,
+}
+
+impl UsageStore {
+    pub fn new(pool: UsageDbPool) -> Self {
+        Self { pool }
+    }
+
+    /// Records usage at the current time.
+    pub fn record_usage(&self, reverie_id: &str, spender: &str, usage: &UsageData, cost: u64) -> Result<()> {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)?
+            .as_secs() as i64;
+        self.record_usage_at(reverie_id, spender, usage, cost, now)
+    }
+
+    /// Records usage at the given unix timestamp, e.g. the timestamp of a usage report.
+    pub fn record_usage_at(
+        &self,
+        reverie_id: &str,
+        spender: &str,
+        usage: &UsageData,
+        cost: u64,
+        timestamp: i64,
+    ) -> Result<()> {
+        let conn = self.pool.get()?;
+        conn.execute(
+            "INSERT INTO usage_spend (
+                reverie_id, spender, timestamp, input_tokens, output_tokens,
+                cache_creation_tokens, cache_read_tokens, cost
+            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![
+                reverie_id,
+                spender,
+                timestamp,
+                usage.input_tokens,
+                usage.output_tokens,
+                usage.cache_creation_input_tokens.unwrap_or(0),
+                usage.cache_read_input_tokens.unwrap_or(0),
+                cost,
+            ],
+        )?;
+        trace!("Recorded usage for reverie_id: {}, spender: {}, cost: {}", reverie_id, spender, cost);
+        Ok(())
+    }
+
+    pub fn total_by_reverie(&self, reverie_id: &str) -> Result<UsageTotals> {
+        self.query_totals(&format!("{} WHERE reverie_id = ?1", SELECT_USAGE_TOTALS), reverie_id)
+    }
+
+    pub fn total_by_spender(&self, spender: &str) -> Result<UsageTotals> {
+        self.query_totals(&format!("{} WHERE spender = ?1", SELECT_USAGE_TOTALS), spender)
+    }
+
+    /// Usage entries with `start <= timestamp < end`, oldest first.
+    pub fn usage_between(&self, start: i64, end: i64) -> Result<Vec<UsageEntry>> {
+        let conn = self.pool.get()?;
+        let mut stmt = conn.prepare(
+            "SELECT reverie_id, spender, timestamp, input_tokens, output_tokens,
+                    cache_creation_tokens, cache_read_tokens, cost
+             FROM usage_spend
+             WHERE timestamp >= ?1 AND timestamp < ?2
+             ORDER BY timestamp ASC, id ASC"
+        )?;
+
+        let rows = stmt.query_map(params![start, end], |row| {
+            Ok(UsageEntry {
+                reverie_id: row.get(0)?,
+                spender: row.get(1)?,
+                timestamp: row.get(2)?,
+                input_tokens: row.get(3)?,
+                output_tokens: row.get(4)?,
+                cache_creation_tokens: row.get(5)?,
+                cache_read_tokens: row.get(6)?,
+                cost: row.get(7)?,
+            })
+        })?;
+
+        rows.collect::<Result<Vec<UsageEntry>, _>>()
+            .map_err(|e| anyhow!("Failed to process database row: {}", e))
+    }
+
+    fn query_totals(&self, query: &str, key: &str) -> Result<UsageTotals> {
+        let conn = self.pool.get()?;
+        let totals = conn.query_row(query, params![key], |row| {
+            Ok(UsageTotals {
+                num_records: row.get(0)?,
+                input_tokens: row.get(1)?,
+                output_tokens: row.get(2)?,
+                cache_creation_tokens: row.get(3)?,
+                cache_read_tokens: row.get(4)?,
+                cost: row.get(5)?,
+            })
+        })?;
+        Ok(totals)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn in_memory_store() -> UsageStore {
+        // One connection, as each in-memory connection is its own database
+        let pool = Pool::builder()
+            .max_size(1)
+            .build(SqliteConnectionManager::memory())
+            .unwrap();
+        pool.get().unwrap().execute_batch(DB_SCHEMA).unwrap();
+        UsageStore::new(Arc::new(pool))
+    }
+
+    fn usage(input_tokens: u64, output_tokens: u64, cache_read_tokens: Option<u64>) -> UsageData {
+        let mut usage = UsageData::new();
+        usage.input_tokens = input_tokens;
+        usage.output_tokens = output_tokens;
+        usage.cache_read_input_tokens = cache_read_tokens;
+        usage
+    }
+
+    #[test]
+    fn test_record_usage_is_queryable() {
+        let store = in_memory_store();
+        store.record_usage("reverie_1", "alice", &usage(10, 20, Some(5)), 35).unwrap();
+
+        let totals = store.total_by_reverie("reverie_1").unwrap();
+        assert_eq!(totals, UsageTotals {
+            num_records: 1,
+            input_tokens: 10,
+            output_tokens: 20,
+            cache_creation_tokens: 0,
+            cache_read_tokens: 5,
+            cost: 35,
+        });
+    }
+
+    #[test]
+    fn test_total_by_reverie_aggregates_across_spenders() {
+        let store = in_memory_store();
+        store.record_usage_at("reverie_1", "alice", &usage(10, 20, None), 30, 100).unwrap();
+        store.record_usage_at("reverie_1", "bob", &usage(1, 2, Some(3)), 6, 200).unwrap();
+        store.record_usage_at("reverie_2", "alice", &usage(100, 200, None), 300, 300).unwrap();
+
+        let totals = store.total_by_reverie("reverie_1").unwrap();
+        assert_eq!(totals.num_records, 2);
+        assert_eq!(totals.input_tokens, 11);
+        assert_eq!(totals.output_tokens, 22);
+        assert_eq!(totals.cache_read_tokens, 3);
+        assert_eq!(totals.cost, 36);
+
+        assert_eq!(store.total_by_reverie("reverie_missing").unwrap(), UsageTotals::default());
+    }
+
+    #[test]
+    fn test_total_by_spender_aggregates_across_reveries() {
+        let store = in_memory_store();
+        store.record_usage_at("reverie_1", "alice", &usage(10, 20, None), 30, 100).unwrap();
+        store.record_usage_at("reverie_1", "bob", &usage(1, 2, None), 3, 200).unwrap();
+        store.record_usage_at("reverie_2", "alice", &usage(100, 200, None), 300, 300).unwrap();
+
+        let totals = store.total_by_spender("alice").unwrap();
+        assert_eq!(totals.num_records, 2);
+        assert_eq!(totals.input_tokens, 110);
+        assert_eq!(totals.output_tokens, 220);
+        assert_eq!(totals.cost, 330);
+    }
+
+    #[test]
+    fn test_usage_between_is_start_inclusive_end_exclusive() {
+        let store = in_memory_store();
+        store.record_usage_at("reverie_1", "alice", &usage(1, 1, None), 2, 100).unwrap();
+        store.record_usage_at("reverie_1", "bob", &usage(2, 2, None), 4, 200).unwrap();
+        store.record_usage_at("reverie_2", "alice", &usage(3, 3, None), 6, 300).unwrap();
+
+        let entries = store.usage_between(100, 300).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].timestamp, 100);
+        assert_eq!(entries[0].spender, "alice");
+        assert_eq!(entries[1].timestamp, 200);
+        assert_eq!(entries[1].cost, 4);
+
+        assert!(store.usage_between(400, 500).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_usage_payload_roundtrips_linked_tool_use_ids() {
+        let pool = Arc::new(Pool::builder()
+            .max_size(1)
+            .build(SqliteConnectionManager::memory())
+            .unwrap());
+        pool.get().unwrap().execute_batch(DB_SCHEMA).unwrap();
+
+        let mut linked_usage = usage(10, 20, None);
+        linked_usage.reverie_id = Some("reverie_1".to_string());
+        let payload = UsageReportPayload {
+            usage: linked_usage,
+            timestamp: 100,
+            linked_tool_use_ids: vec!["toolu_01".to_string(), "toolu_02".to_string()],
+            request_id: "request_1".to_string(),
+        };
+        store_usage_payload(&pool, &payload).unwrap();
+
+        let records = read_usage_data_for_reverie(&pool, "reverie_1").unwrap();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].linked_tool_use_ids, payload.linked_tool_use_ids);
+
+        // Legacy rows hold a single tool_use_id
+        assert_eq!(decode_linked_tool_use_ids(Some("toolu_01".to_string())), vec!["toolu_01"]);
+        assert!(decode_linked_tool_use_ids(None).is_empty());
+    }
+
+    #[test]
+    fn test_store_usage_payload_is_idempotent_on_request_id() {
+        let pool = Arc::new(Pool::builder()
+            .max_size(1)
+            .build(SqliteConnectionManager::memory())
+            .unwrap());
+        pool.get().unwrap().execute_batch(DB_SCHEMA).unwrap();
+
+        let mut report_usage = usage(10, 20, None);
+        report_usage.reverie_id = Some("reverie_1".to_string());
+        let payload = UsageReportPayload {
+            usage: report_usage,
+            timestamp: 100,
+            linked_tool_use_ids: vec![],
+            request_id: "request_1".to_string(),
+        };
+
+        assert!(!usage_report_exists(&pool, "request_1").unwrap());
+        assert!(store_usage_payload(&pool, &payload).unwrap());
+        assert!(usage_report_exists(&pool, "request_1").unwrap());
+        // A retried report is not stored twice
+        assert!(!store_usage_payload(&pool, &payload).unwrap());
+        assert_eq!(read_usage_data_for_reverie(&pool, "reverie_1").unwrap().len(), 1);
+    }
+}
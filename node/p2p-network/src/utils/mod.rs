@@ -1,3 +1,4 @@
+pub mod node_names;
 pub mod pubkeys;
 
 use nanoid::nanoid;
@@ -0,0 +1,122 @@
+use std::collections::HashMap;
+use std::sync::{LazyLock, RwLock};
+use color_eyre::{Result, eyre::anyhow};
+use libp2p::PeerId;
+use tracing::{debug, warn};
+
+use crate::short_peer_id;
+
+/// Names for the seeded dev and test nodes (`--secret-key-seed 1..12`)
+const DEFAULT_NODE_NAMES: [(&str, &str); 12] = [
+    ("12D3KooWPjceQrSwdWXPyLLeABRXmuqt69Rg3sBYbU1Nft9HyQ6X", "ABE"),
+    ("12D3KooWH3uVF6wv47WnArKHk5p6cvgCJEb74UTmxztmQDc298L3", "BOB"),
+    ("12D3KooWQYhTNQdmr3ArTeUHRYzFg94BKyTkoWBDWez9kSCVe2Xo", "CARL"),
+    ("12D3KooWLJtG8fd2hkQzTn96MrLvThmnNQjTUFZwGEsLRz5EmSzc", "DAN"),
+    ("12D3KooWSHj3RRbBjD15g6wekV8y3mm57Pobmps2g2WJm6F67Lay", "EMMA"),
+    ("12D3KooWDMCQbZZvLgHiHntG1KwcHoqHPAxL37KvhgibWqFtpqUY", "FAYE"),
+    ("12D3KooWLnZUpcaBwbz9uD1XsyyHnbXUrJRmxnsMiRnuCmvPix67", "GREG"),
+    ("12D3KooWQ8vrERR8bnPByEjjtqV6hTWehaf8TmK7qR1cUsyrPpfZ", "HANA"),
+    ("12D3KooWNRk8VBuTJTYyTbnJC7Nj2UN5jij4dJMo8wtSGT2hRzRP", "IAN"),
+    ("12D3KooWFHNBwTxUgeHRcD3g4ieiXBmZGVyp6TKGWRKKEqYgCC1C", "JACK"),
+    ("12D3KooWHbEputWi1fJAxoYgmvvDe3yP7acTACqmXKGYwMgN2daQ", "KASS"),
+    ("12D3KooWCxnyz1JxC9y1RniRQVFe2cLaLHsYNc2SnXbM7yq5JBbJ", "LARRY"),
+];
+
+/// Env var pointing to a JSON file of `{ "<peer_id>": "<name>" }` labels
+pub const NODE_NAMES_PATH: &str = "NODE_NAMES_PATH";
+
+static NODE_NAMES: LazyLock<RwLock<NodeNames>> = LazyLock::new(|| RwLock::new(NodeNames::load()));
+
+/// Human readable labels for PeerIds, used in logs and node state
+#[derive(Debug, Clone, Default)]
+pub struct NodeNames(HashMap<PeerId, String>);
+
+impl NodeNames {
+    pub fn default_names() -> Self {
+        Self(DEFAULT_NODE_NAMES.iter()
+            .map(|(peer_id, name)| (peer_id.parse().expect("valid default PeerId"), name.to_string()))
+            .collect())
+    }
+
+    pub fn from_json(json: &str) -> Result<Self> {
+        let names: HashMap<String, String> = serde_json::from_str(json)?;
+        names.into_iter()
+            .map(|(peer_id, name)| {
+                let peer_id = peer_id.parse::<PeerId>()
+                    .map_err(|e| anyhow!("Invalid PeerId {} in node names: {}", peer_id, e))?;
+                Ok((peer_id, name))
+            })
+            .collect::<Result<HashMap<PeerId, String>>>()
+            .map(Self)
+    }
+
+    /// Loads names from the file at `NODE_NAMES_PATH`, or the default dev names if unset
+    pub fn load() -> Self {
+        dotenv::dotenv().ok();
+        let path = match std::env::var(NODE_NAMES_PATH) {
+            Ok(path) => path,
+            Err(_) => {
+                debug!("{} env var not set, using default node names", NODE_NAMES_PATH);
+                return Self::default_names();
+            }
+        };
+
+        match std::fs::read_to_string(&path).map_err(|e| anyhow!(e)).and_then(|json| Self::from_json(&json)) {
+            Ok(node_names) => node_names,
+            Err(e) => {
+                warn!("Failed to load node names from {}: {}. Using default node names", path, e);
+                Self::default_names()
+            }
+        }
+    }
+
+    pub fn get(&self, peer_id: &PeerId) -> Option<&str> {
+        self.0.get(peer_id).map(String::as_str)
+    }
+
+    /// Configured name for the peer, otherwise its short PeerId
+    pub fn resolve(&self, peer_id: &PeerId) -> String {
+        match self.get(peer_id) {
+            Some(name) => name.to_string(),
+            None => short_peer_id(peer_id),
+        }
+    }
+}
+
+/// Replaces the node names used by `get_node_name`
+pub fn set_node_names(node_names: NodeNames) {
+    *NODE_NAMES.write().unwrap() = node_names;
+}
+
+pub fn resolve_node_name(peer_id: &PeerId) -> String {
+    NODE_NAMES.read().unwrap().resolve(peer_id)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn custom_node_names_resolve_and_fall_back_to_short_peer_id() {
+        let labelled = PeerId::random();
+        let unlabelled = PeerId::random();
+        let json = serde_json::json!({ labelled.to_base58(): "fleet-01" }).to_string();
+
+        let node_names = NodeNames::from_json(&json).unwrap();
+        assert_eq!(node_names.resolve(&labelled), "fleet-01");
+        assert_eq!(node_names.resolve(&unlabelled), short_peer_id(&unlabelled));
+    }
+
+    #[test]
+    fn default_node_names_keep_dev_labels() {
+        let bob: PeerId = "12D3KooWH3uVF6wv47WnArKHk5p6cvgCJEb74UTmxztmQDc298L3".parse().unwrap();
+        assert_eq!(NodeNames::default_names().resolve(&bob), "BOB");
+    }
+
+    #[test]
+    fn invalid_peer_ids_are_rejected() {
+        let json = r#"{ "not-a-peer-id": "fleet-01" }"#;
+        assert!(NodeNames::from_json(json).is_err());
+    }
+}
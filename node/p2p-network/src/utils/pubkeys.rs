@@ -13,27 +13,57 @@ use libp2p_identity::PublicKey;
 use crate::env_var::NODE_SEED_NUM;
 
 
-pub fn generate_peer_keys<'a>(secret_key_seed: Option<usize>) -> (
+/// Identity of a node derived from its seed, for tooling and key ceremonies that need
+/// a node's PeerId and public keys without starting it.
+#[derive(Debug, Clone)]
+pub struct NodeIdentityBundle {
+    pub peer_id: PeerId,
+    pub keypair: identity::Keypair,
+    pub node_name: String,
+    pub umbral_public_key: umbral_pre::PublicKey,
+}
+
+/// Derives a node's identity from `seed`, as `--secret-key-seed` does when starting a node.
+/// Deterministic across versions: the same seed always yields the same PeerId, keypair,
+/// node name and Umbral public key. The Umbral verifying key is random per process, so it
+/// is not included.
+pub fn derive_node_identity(seed: usize) -> NodeIdentityBundle {
+    let (keypair, umbral_key) = seeded_keys(seed);
+    let peer_id = keypair.public().to_peer_id();
+    NodeIdentityBundle {
+        peer_id,
+        node_name: crate::get_node_name(&peer_id),
+        keypair,
+        umbral_public_key: umbral_key.public_key,
+    }
+}
+
+/// The seed is written little-endian into the 32 secret key bytes. Seeds below 256 keep
+/// the keys of the original single-byte encoding.
+fn seeded_keys(seed: usize) -> (identity::Keypair, runtime::reencrypt::UmbralKey) {
+    let mut bytes = [0u8; 32];
+    bytes[..8].copy_from_slice(&(seed as u64).to_le_bytes());
+
+    let id_keys = identity::Keypair::ed25519_from_bytes(bytes).unwrap();
+    let umbral_key = runtime::reencrypt::UmbralKey::new(Some(bytes.as_slice()));
+    (id_keys, umbral_key)
+}
+
+pub fn generate_peer_keys(secret_key_seed: Option<usize>) -> (
     libp2p::PeerId,
     identity::Keypair,
-    &'a str,
+    String,
     runtime::reencrypt::UmbralKey
 ) {
 
     // Create a public/private key pair, either random or based on a seed.
     let (id_keys, umbral_key) = match secret_key_seed {
         Some(seed) => {
-            let mut bytes = [0u8; 32];
-            bytes[0] = seed as u8;
-
-            // set seed for working out frag_num this this peer
+            // seed is only used to derive keys: frag_nums are assigned by sorted peer_id
             NODE_SEED_NUM.with(|n| {
                 *n.borrow_mut() = seed;
             });
-
-            let id_keys = identity::Keypair::ed25519_from_bytes(bytes).unwrap();
-            let umbral_key = runtime::reencrypt::UmbralKey::new(Some(bytes.as_slice()));
-            (id_keys, umbral_key)
+            seeded_keys(seed)
         },
         None => {
             let id_keys = identity::Keypair::generate_ed25519();
@@ -69,4 +99,44 @@ pub fn encode_libp2p_pubkey_to_pem(keypair: &IdentityKeypair) -> Result<String>
             Err(anyhow!("Cannot encode non-Ed25519 public key to PEM currently."))
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ed25519_bytes(keypair: &identity::Keypair) -> [u8; 64] {
+        keypair.clone().try_into_ed25519().unwrap().to_bytes()
+    }
+
+    #[test]
+    fn derive_node_identity_is_deterministic() {
+        let first = derive_node_identity(3);
+        let second = derive_node_identity(3);
+        assert_eq!(first.peer_id, second.peer_id);
+        assert_eq!(ed25519_bytes(&first.keypair), ed25519_bytes(&second.keypair));
+        assert_eq!(first.node_name, second.node_name);
+        assert_eq!(first.umbral_public_key, second.umbral_public_key);
+
+        // Matches the keys a node started with the same seed uses
+        let (peer_id, _, node_name, umbral_key) = generate_peer_keys(Some(3));
+        assert_eq!(first.peer_id, peer_id);
+        assert_eq!(first.node_name, node_name);
+        assert_eq!(first.umbral_public_key, umbral_key.public_key);
+
+        let other = derive_node_identity(4);
+        assert_ne!(first.peer_id, other.peer_id);
+        assert_ne!(first.umbral_public_key, other.umbral_public_key);
+    }
+
+    #[test]
+    fn derive_node_identity_is_stable_across_versions() {
+        // Bootstrap PeerId of seed 1 used by the e2e tests and deployments
+        assert_eq!(
+            derive_node_identity(1).peer_id.to_string(),
+            "12D3KooWPjceQrSwdWXPyLLeABRXmuqt69Rg3sBYbU1Nft9HyQ6X"
+        );
+        // Seeds above 255 no longer wrap onto single byte seeds
+        assert_ne!(derive_node_identity(257).peer_id, derive_node_identity(1).peer_id);
+    }
+}
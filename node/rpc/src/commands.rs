@@ -1,6 +1,8 @@
 use clap::Parser;
 use serde::{Deserialize, Serialize};
 use libp2p::Multiaddr;
+use std::num::NonZeroUsize;
+use runtime::tee_verifier::TeeVerifierConfig;
 
 #[derive(Parser, Debug, Serialize, Deserialize)]
 #[clap(name = "libp2p example")]
@@ -23,4 +25,85 @@ pub struct Opt {
     // Format: "docker compose -f <docker_compose_file_path> <other_args>"
     #[clap(long)]
     pub docker_compose_cmd: Option<String>,
+
+    /// Discover peers on the local network via mDNS (for local dev setups)
+    #[clap(long, default_value_t = false)]
+    pub enable_mdns: bool,
+
+    /// Reach and be reached through relays with hole punching, for nodes behind NAT.
+    /// Pass a `/p2p-circuit` listen address to reserve a circuit on a relay.
+    #[clap(long, default_value_t = false)]
+    pub enable_relay_client: bool,
+
+    /// Max inbound connections still being negotiated
+    #[clap(long)]
+    pub max_pending_incoming: Option<u32>,
+
+    /// Max established inbound connections, further inbound connections are denied
+    #[clap(long)]
+    pub max_established_incoming: Option<u32>,
+
+    /// Max established connections in total (inbound and outbound)
+    #[clap(long)]
+    pub max_established_total: Option<u32>,
+
+    /// Max established connections to a single peer
+    #[clap(long)]
+    pub max_established_per_peer: Option<u32>,
+
+    /// Seconds until Kademlia records stored on peers expire, unless republished
+    #[clap(long)]
+    pub record_ttl_secs: Option<u64>,
+
+    /// Seconds between republishing this node's own Kademlia records
+    #[clap(long)]
+    pub record_republish_interval_secs: Option<u64>,
+
+    /// Max size in bytes of a Reverie's plaintext secrets
+    #[clap(long)]
+    pub max_reverie_payload_size: Option<usize>,
+
+    /// Capacity of the internal command channel to the network event loop
+    #[clap(long)]
+    pub command_channel_capacity: Option<usize>,
+
+    /// Milliseconds before re-dialing unreachable bootstrap peers, doubled each attempt
+    #[clap(long)]
+    pub bootstrap_retry_initial_backoff_ms: Option<u64>,
+
+    /// Max milliseconds between bootstrap dial attempts
+    #[clap(long)]
+    pub bootstrap_retry_max_backoff_ms: Option<u64>,
+
+    /// Connected kfrag providers to keep above a Reverie's threshold before replacing lost ones
+    #[clap(long)]
+    pub kfrag_provider_safety_margin: Option<usize>,
+
+    /// Connected empty vessels required beyond total_frags before a Reverie can be spawned
+    #[clap(long)]
+    pub spawn_peer_margin: Option<usize>,
+
+    /// Number of closest peers each Kademlia record is replicated to
+    #[clap(long)]
+    pub kademlia_replication_factor: Option<NonZeroUsize>,
+
+    /// Max concurrent requests per Kademlia query
+    #[clap(long)]
+    pub kademlia_query_parallelism: Option<NonZeroUsize>,
+
+    /// Seconds allowed on Ctrl+C for re-publishing vessel reveries before exiting
+    #[clap(long)]
+    pub shutdown_timeout_secs: Option<u64>,
+
+    /// Verifier for peers' heartbeat TEE attestations: dcap, mock or mock-reject
+    #[clap(long)]
+    pub tee_verifier: Option<TeeVerifierConfig>,
+
+    /// Max spawn RPCs this node accepts per rate limit window, 0 for no limit
+    #[clap(long)]
+    pub spawn_rate_limit: Option<usize>,
+
+    /// Seconds of the sliding window spawn RPCs are counted over
+    #[clap(long)]
+    pub spawn_rate_limit_window_secs: Option<u64>,
 }
@@ -0,0 +1,136 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use tokio::sync::broadcast;
+
+/// Heartbeats kept for subscribers resuming after a disconnect
+pub const DEFAULT_HEARTBEAT_REPLAY_CAPACITY: usize = 256;
+
+/// Last block_height a subscriber saw from each peer, keyed by peer id string
+pub type HeartbeatResumeToken = HashMap<String, u32>;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BufferedHeartbeat {
+    pub peer_id: String,
+    pub block_height: u32,
+    pub payload: serde_json::Value,
+}
+
+/// Fans heartbeats out to every `subscribe_hb` subscriber, and keeps the most recent
+/// `capacity` of them so a subscriber reconnecting with a resume token is
+/// replayed the heartbeats it missed.
+pub struct HeartbeatReplay {
+    capacity: usize,
+    buffer: Mutex<VecDeque<BufferedHeartbeat>>,
+    sender: broadcast::Sender<BufferedHeartbeat>,
+}
+
+impl HeartbeatReplay {
+    pub fn new(capacity: usize) -> Self {
+        let (sender, _) = broadcast::channel(capacity.max(1));
+        Self {
+            capacity,
+            buffer: Mutex::new(VecDeque::with_capacity(capacity)),
+            sender,
+        }
+    }
+
+    pub fn push(&self, heartbeat: BufferedHeartbeat) {
+        let mut buffer = self.buffer.lock().unwrap();
+        buffer.push_back(heartbeat.clone());
+        while buffer.len() > self.capacity {
+            buffer.pop_front();
+        }
+        // sent while holding the lock, so a subscriber sees each heartbeat exactly once,
+        // either replayed or live. Err means there are no subscribers
+        self.sender.send(heartbeat).ok();
+    }
+
+    /// Number of subscribers currently receiving live heartbeats
+    pub fn subscriber_count(&self) -> usize {
+        self.sender.receiver_count()
+    }
+
+    /// Live heartbeats from now on, and with a resume token, the buffered heartbeats
+    /// newer than the token's block_height for each peer. Peers missing from
+    /// the token have all their buffered heartbeats replayed.
+    pub fn subscribe(
+        &self,
+        resume_token: Option<&HeartbeatResumeToken>,
+    ) -> (Vec<BufferedHeartbeat>, broadcast::Receiver<BufferedHeartbeat>) {
+        let buffer = self.buffer.lock().unwrap();
+        let receiver = self.sender.subscribe();
+        let missed = match resume_token {
+            None => vec![],
+            Some(resume_token) => buffer.iter()
+                .filter(|heartbeat| resume_token
+                    .get(&heartbeat.peer_id)
+                    .map_or(true, |last_seen| heartbeat.block_height > *last_seen))
+                .cloned()
+                .collect(),
+        };
+        (missed, receiver)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn heartbeat(peer_id: &str, block_height: u32) -> BufferedHeartbeat {
+        BufferedHeartbeat {
+            peer_id: peer_id.to_string(),
+            block_height,
+            payload: serde_json::json!({ "block_height": block_height }),
+        }
+    }
+
+    fn block_heights(heartbeats: &[BufferedHeartbeat], peer_id: &str) -> Vec<u32> {
+        heartbeats.iter()
+            .filter(|heartbeat| heartbeat.peer_id == peer_id)
+            .map(|heartbeat| heartbeat.block_height)
+            .collect()
+    }
+
+    #[test]
+    fn reconnecting_with_a_token_replays_missed_heartbeats() {
+        let replay = HeartbeatReplay::new(16);
+
+        let (missed, mut receiver) = replay.subscribe(None);
+        assert!(missed.is_empty());
+        for block_height in 1..=3 {
+            replay.push(heartbeat("peer_a", block_height));
+            replay.push(heartbeat("peer_b", block_height));
+        }
+        let mut resume_token = HeartbeatResumeToken::new();
+        while let Ok(heartbeat) = receiver.try_recv() {
+            resume_token.insert(heartbeat.peer_id, heartbeat.block_height);
+        }
+        // subscriber disconnects, having missed peer_b's last heartbeat
+        drop(receiver);
+        resume_token.insert("peer_b".to_string(), 2);
+
+        for block_height in 4..=5 {
+            replay.push(heartbeat("peer_a", block_height));
+            replay.push(heartbeat("peer_b", block_height));
+        }
+
+        let (missed, mut receiver) = replay.subscribe(Some(&resume_token));
+        assert_eq!(block_heights(&missed, "peer_a"), vec![4, 5]);
+        assert_eq!(block_heights(&missed, "peer_b"), vec![3, 4, 5]);
+
+        // then continues with live heartbeats, without repeats
+        replay.push(heartbeat("peer_a", 6));
+        assert_eq!(receiver.try_recv().unwrap(), heartbeat("peer_a", 6));
+        assert!(receiver.try_recv().is_err());
+    }
+
+    #[test]
+    fn replay_buffer_is_bounded() {
+        let replay = HeartbeatReplay::new(3);
+        for block_height in 1..=10 {
+            replay.push(heartbeat("peer_a", block_height));
+        }
+        let (missed, _receiver) = replay.subscribe(Some(&HeartbeatResumeToken::new()));
+        assert_eq!(block_heights(&missed, "peer_a"), vec![8, 9, 10]);
+    }
+}
@@ -1,4 +1,6 @@
 pub mod rpc_server;
 pub mod rpc_client;
+pub mod spawn_rate_limit;
+pub mod heartbeat_replay;
 pub use rpc_client::*;
 pub use rpc_server::*;
\ No newline at end of file
@@ -1,17 +1,20 @@
 mod rpc_server;
 mod rpc_client;
 mod commands;
+mod spawn_rate_limit;
+mod heartbeat_replay;
 
 use color_eyre::Result;
 use clap::Parser;
-use libp2p::{Multiaddr, multiaddr::Protocol};
+use libp2p::Multiaddr;
 use std::env;
 use rpc_server::run_server;
+use spawn_rate_limit::{SpawnRateLimiter, DEFAULT_SPAWN_RATE_LIMIT, DEFAULT_SPAWN_RATE_LIMIT_WINDOW};
 use tokio::time::Duration;
 use tracing::{info, error};
 
 use commands::Opt;
-use p2p_network::create_network;
+use p2p_network::create_network::{self, NetworkConfig};
 
 
 #[tokio::main]
@@ -26,34 +29,72 @@ async fn main() -> Result<()> {
             show_path: true,
             show_time: false,
             show_crate_name: false,
+            json: env::var("LOG_FORMAT").map(|f| f == "json").unwrap_or(false),
             ..Default::default()
         });
     }
 
     let opt = Opt::parse();
 
-    let bootstrap_nodes: Vec<(String, Multiaddr)> = opt.bootstrap_peers
+    // Multiaddr formats: /ip4/node1/tcp/port/p2p/peer_id, /dns4/host/tcp/port[/p2p/peer_id], /dnsaddr/host
+    let bootstrap_addrs: Vec<Multiaddr> = opt.bootstrap_peers
         .iter()
-        .filter_map(|addr_str| {
-            // Parse multiaddr format: /ip4/node1/tcp/port/p2p/peer_id
-            let addr: Multiaddr = addr_str.parse().ok()?;
-            // Extract peer ID from the multiaddr
-            let peer_id = addr.iter().find_map(|p| {
-                match p {
-                    Protocol::P2p(peer_id) => Some(peer_id.to_string()),
-                    _ => None,
-                }
-            })?;
-
-            Some((peer_id, addr))
+        .filter_map(|addr_str| match addr_str.parse::<Multiaddr>() {
+            Ok(addr) => Some(addr),
+            Err(e) => {
+                error!("Invalid bootstrap multiaddr {}: {}", addr_str, e);
+                None
+            }
         })
         .collect();
 
+    let default_config = NetworkConfig::default();
+    let network_config = NetworkConfig {
+        enable_mdns: opt.enable_mdns,
+        enable_relay_client: opt.enable_relay_client,
+        max_pending_incoming: opt.max_pending_incoming.or(default_config.max_pending_incoming),
+        max_established_incoming: opt.max_established_incoming.or(default_config.max_established_incoming),
+        max_established_total: opt.max_established_total.or(default_config.max_established_total),
+        max_established_per_peer: opt.max_established_per_peer.or(default_config.max_established_per_peer),
+        record_ttl: opt.record_ttl_secs
+            .map(Duration::from_secs)
+            .unwrap_or(default_config.record_ttl),
+        record_republish_interval: opt.record_republish_interval_secs
+            .map(Duration::from_secs)
+            .unwrap_or(default_config.record_republish_interval),
+        max_reverie_payload_size: opt.max_reverie_payload_size
+            .unwrap_or(default_config.max_reverie_payload_size),
+        command_channel_capacity: opt.command_channel_capacity
+            .unwrap_or(default_config.command_channel_capacity),
+        bootstrap_retry_initial_backoff: opt.bootstrap_retry_initial_backoff_ms
+            .map(Duration::from_millis)
+            .unwrap_or(default_config.bootstrap_retry_initial_backoff),
+        bootstrap_retry_max_backoff: opt.bootstrap_retry_max_backoff_ms
+            .map(Duration::from_millis)
+            .unwrap_or(default_config.bootstrap_retry_max_backoff),
+        kfrag_provider_safety_margin: opt.kfrag_provider_safety_margin
+            .unwrap_or(default_config.kfrag_provider_safety_margin),
+        spawn_peer_margin: opt.spawn_peer_margin
+            .unwrap_or(default_config.spawn_peer_margin),
+        kademlia_replication_factor: opt.kademlia_replication_factor
+            .unwrap_or(default_config.kademlia_replication_factor),
+        kademlia_query_parallelism: opt.kademlia_query_parallelism
+            .unwrap_or(default_config.kademlia_query_parallelism),
+        shutdown_timeout: opt.shutdown_timeout_secs
+            .map(Duration::from_secs)
+            .unwrap_or(default_config.shutdown_timeout),
+        tee_verifier: opt.tee_verifier
+            .unwrap_or(default_config.tee_verifier),
+        ..default_config
+    };
+    let shutdown_timeout = network_config.shutdown_timeout;
+
     // Create the network and start the node client
     let node_client = create_network::new(
         opt.secret_key_seed,
         opt.listen_address,
-        bootstrap_nodes,
+        bootstrap_addrs,
+        network_config,
     ).await?;
 
     let mut rpc_server_running = false;
@@ -61,7 +102,13 @@ async fn main() -> Result<()> {
     // then run an RPC server if provided an RPC port
     if let Some(port) = opt.rpc_port {
         info!("Starting RPC server on port {}...", port);
-        run_server(port, node_client.clone()).await?;
+        let spawn_rate_limiter = SpawnRateLimiter::new(
+            opt.spawn_rate_limit.unwrap_or(DEFAULT_SPAWN_RATE_LIMIT),
+            opt.spawn_rate_limit_window_secs
+                .map(Duration::from_secs)
+                .unwrap_or(DEFAULT_SPAWN_RATE_LIMIT_WINDOW),
+        );
+        run_server(port, node_client.clone(), spawn_rate_limiter).await?;
         info!("RPC server running at: {}", port);
         rpc_server_running = true;
 
@@ -91,6 +138,9 @@ async fn main() -> Result<()> {
         info!("p2p-node running with RPC server. Press Ctrl+C to exit.");
         tokio::signal::ctrl_c().await?;
         info!("Ctrl+C received, shutting down p2p-node.");
+        if let Err(e) = node_client.shutdown(shutdown_timeout).await {
+            error!("Failed to shut down network event loop cleanly: {}", e);
+        }
     } else {
         info!("p2p-node setup complete (no RPC server). Application will now exit if no other foreground tasks are running.");
         // If there are other non-RPC related long-running tasks, they would keep it alive.
@@ -18,6 +18,7 @@ use jsonrpsee::server::{
 use std::sync::Arc;
 use serde::{Deserialize, Serialize};
 use tokio::time::interval;
+use tokio::sync::broadcast::error::RecvError;
 use tokio_stream::wrappers::IntervalStream;
 use alloy_primitives::Address;
 use tracing::{info, warn, error, debug};
@@ -29,15 +30,25 @@ use p2p_network::types::{
     ReverieType,
     AccessKey,
     AnthropicQuery,
+    ReverieBundle,
+    PreKeyfragParams,
 };
 use p2p_network::node_client::NodeClient;
 use p2p_network::get_node_name;
-use runtime::llm::AgentSecretsJson;
+use runtime::llm::{AgentSecretsJson, McpPluginConfig};
 use runtime::QuoteBody;
 use llm_proxy::usage::SignedUsageReport;
 use libp2p::identity::Keypair as IdentityKeypair;
+use libp2p::PeerId;
 use p2p_network::env_var::EnvVars;
 use p2p_network::utils::pubkeys::generate_peer_keys;
+use crate::spawn_rate_limit::SpawnRateLimiter;
+use crate::heartbeat_replay::{
+    BufferedHeartbeat,
+    HeartbeatReplay,
+    HeartbeatResumeToken,
+    DEFAULT_HEARTBEAT_REPLAY_CAPACITY,
+};
 
 pub struct RpcServer {
     pub server: Server,
@@ -92,6 +103,14 @@ impl RpcServer {
         })
     }
 
+    pub fn add_alias(
+        &mut self,
+        alias: &'static str,
+        existing_method: &'static str
+    ) -> Result<(), RegisterMethodError> {
+        self.rpc_module.register_alias(alias, existing_method)
+    }
+
     pub async fn start(self) -> Result<SocketAddr> {
         let addr = self.server.local_addr()?;
         tokio::spawn(
@@ -107,7 +126,11 @@ impl RpcServer {
     }
 }
 
-pub async fn run_server(rpc_port: usize, network_client: NodeClient) -> Result<SocketAddr> {
+pub async fn run_server(
+    rpc_port: usize,
+    network_client: NodeClient,
+    spawn_rate_limiter: SpawnRateLimiter,
+) -> Result<SocketAddr> {
 
     let mut rpc_server = RpcServer::new(
         rpc_port,
@@ -124,6 +147,18 @@ pub async fn run_server(rpc_port: usize, network_client: NodeClient) -> Result<S
         }
     )?;
 
+    // Liveness: responds as soon as the RPC server is up
+    rpc_server.add_alias("get_health", "health")?;
+
+    // Readiness: connected to at least one peer and Kademlia bootstrap has completed
+    rpc_server.add_route(
+        "get_readiness",
+        |_, nc, _| async move {
+            nc.get_readiness()
+                .await.map_err(RpcError::from)
+        }
+    )?;
+
      rpc_server.add_route_mut(
         "get_proxy_public_key",
         |_, nc, _| async move {
@@ -139,21 +174,43 @@ pub async fn run_server(rpc_port: usize, network_client: NodeClient) -> Result<S
         }
     )?;
 
+    // Shared by every spawn route
+    let spawn_rate_limiter = Arc::new(spawn_rate_limiter);
+
+    let rate_limiter = spawn_rate_limiter.clone();
     rpc_server.add_route_mut(
         "spawn_agent",
-        |params, mut nc, _| async move {
+        move |params, mut nc, _| {
+            let rate_limiter = rate_limiter.clone();
+            async move {
 
-            let (
-                agent_secrets_json,
-                threshold,
-                total_frags,
-            ) = params.parse::<(AgentSecretsJson, usize, usize)>()?;
-
-            nc.spawn_agent(
-                agent_secrets_json,
-                threshold,
-                total_frags,
-            ).await.map_err(RpcError::from)
+                rate_limiter.check(std::time::Instant::now())?;
+
+                let mut params = params.sequence();
+                let agent_secrets_json: AgentSecretsJson = params.next()?;
+                let threshold: usize = params.next()?;
+                let total_frags: usize = params.next()?;
+                // optional, shown in reverie listings
+                let description = params.optional_next::<String>()?.unwrap_or_default();
+                let tags = params.optional_next::<Vec<String>>()?.unwrap_or_default();
+                // optional, which keys the kfrags are signed to
+                let keyfrag_params = params.optional_next::<PreKeyfragParams>()?.unwrap_or_default();
+                // optional, unix timestamp after which kfrag providers stop releasing cfrags
+                let expires_at = params.optional_next::<i64>()?;
+
+                // Fail before encrypting if the keyfrags can't all be placed
+                nc.check_enough_spawn_vessels(total_frags).await.map_err(RpcError::from)?;
+
+                Ok::<_, ErrorObjectOwned>(nc.spawn_agent(
+                    agent_secrets_json,
+                    threshold,
+                    total_frags,
+                    description,
+                    tags,
+                    keyfrag_params,
+                    expires_at,
+                ).await.map_err(RpcError::from)?)
+            }
         }
     )?;
 
@@ -165,6 +222,60 @@ pub async fn run_server(rpc_port: usize, network_client: NodeClient) -> Result<S
         }
     )?;
 
+	rpc_server.add_route(
+        "rotate_umbral_key",
+        |_, nc, _| async move {
+            nc.rotate_umbral_key()
+                .await.map_err(RpcError::from)
+        }
+    )?;
+
+	rpc_server.add_route(
+        "force_reincarnate",
+        |params, nc, _| async move {
+            let (agent_name, agent_nonce) = params.parse::<(String, usize)>()?;
+            nc.force_reincarnate(agent_name, agent_nonce)
+                .await.map_err(RpcError::from)
+        }
+    )?;
+
+	rpc_server.add_route(
+        "handoff_vessel",
+        |params, nc, _| async move {
+            let target_peer_id = params.one::<String>()?
+                .parse::<PeerId>()
+                .map_err(|e| RpcError(format!("Invalid successor peer id: {}", e)))?;
+            nc.handoff_vessel(target_peer_id)
+                .await.map_err(RpcError::from)
+        }
+    )?;
+
+	rpc_server.add_route(
+        "current_vessel_agent",
+        |_, nc, _| async move {
+            nc.current_vessel_agent()
+                .await.map_err(RpcError::from)
+        }
+    )?;
+
+	rpc_server.add_route(
+        "get_pending_requests",
+        |_, nc, _| async move {
+            nc.get_pending_requests()
+                .await.map_err(RpcError::from)
+        }
+    )?;
+
+    // Typed peer list with vessel status, heartbeat recency and reputation.
+    // Named separately from get_connected_peers, which returns bare peer ids
+	rpc_server.add_route(
+        "list_connected_peers",
+        |_, nc, _| async move {
+            nc.list_connected_peers()
+                .await.map_err(RpcError::from)
+        }
+    )?;
+
 	rpc_server.add_route(
         "get_node_state",
         move |_, nc, _| async move {
@@ -173,23 +284,109 @@ pub async fn run_server(rpc_port: usize, network_client: NodeClient) -> Result<S
         }
     )?;
 
+    let rate_limiter = spawn_rate_limiter.clone();
     rpc_server.add_route_mut(
         "spawn_memory_reverie",
-        |params, mut nc, _| async move {
+        move |params, mut nc, _| {
+            let rate_limiter = rate_limiter.clone();
+            async move {
 
-            let (
-                memory_secrets_json,
-                threshold,
-                total_frags,
-                access_condition, // access condition for using the memory
-            ) = params.parse::<(serde_json::Value, usize, usize, AccessCondition)>()?;
-
-            nc.spawn_memory_reverie(
-                memory_secrets_json,
-                threshold,
-                total_frags,
-                access_condition
-            ).await.map_err(RpcError::from)
+                rate_limiter.check(std::time::Instant::now())?;
+
+                let mut params = params.sequence();
+                let memory_secrets_json: serde_json::Value = params.next()?;
+                let threshold: usize = params.next()?;
+                let total_frags: usize = params.next()?;
+                // access condition for using the memory
+                let access_condition: AccessCondition = params.next()?;
+                // signature over the spawn challenge by the access condition's key
+                let spawn_signature = params.optional_next::<AccessKey>()?;
+                // peer ids to place keyfrags on first
+                let preferred_kfrag_providers = params.optional_next::<Vec<String>>()?;
+                // optional, shown in reverie listings
+                let description = params.optional_next::<String>()?.unwrap_or_default();
+                let tags = params.optional_next::<Vec<String>>()?.unwrap_or_default();
+                // optional, which keys the kfrags are signed to
+                let keyfrag_params = params.optional_next::<PreKeyfragParams>()?.unwrap_or_default();
+                // optional, unix timestamp after which kfrag providers stop releasing cfrags
+                let expires_at = params.optional_next::<i64>()?;
+
+                let preferred_kfrag_providers = preferred_kfrag_providers
+                    .unwrap_or_default()
+                    .iter()
+                    .map(|peer_id| peer_id.parse::<PeerId>())
+                    .collect::<Result<Vec<PeerId>, _>>()
+                    .map_err(|e| RpcError(format!("Invalid preferred kfrag provider peer id: {}", e)))?;
+
+                // Fail before encrypting if the keyfrags can't all be placed
+                nc.check_enough_spawn_vessels(total_frags).await.map_err(RpcError::from)?;
+
+                Ok::<_, ErrorObjectOwned>(nc.spawn_memory_reverie(
+                    memory_secrets_json,
+                    threshold,
+                    total_frags,
+                    access_condition,
+                    spawn_signature,
+                    preferred_kfrag_providers,
+                    description,
+                    tags,
+                    keyfrag_params,
+                    expires_at,
+                ).await.map_err(RpcError::from)?)
+            }
+        }
+    )?;
+
+    let rate_limiter = spawn_rate_limiter.clone();
+    rpc_server.add_route_mut(
+        "spawn_mcp_plugin_reverie",
+        move |params, mut nc, _| {
+            let rate_limiter = rate_limiter.clone();
+            async move {
+
+                rate_limiter.check(std::time::Instant::now())?;
+
+                let mut params = params.sequence();
+                let mcp_plugin: McpPluginConfig = params.next()?;
+                let threshold: usize = params.next()?;
+                let total_frags: usize = params.next()?;
+                // access condition for using the plugin
+                let access_condition: AccessCondition = params.next()?;
+                // signature over the spawn challenge by the access condition's key
+                let spawn_signature = params.optional_next::<AccessKey>()?;
+                // peer ids to place keyfrags on first
+                let preferred_kfrag_providers = params.optional_next::<Vec<String>>()?;
+                // optional, shown in reverie listings
+                let description = params.optional_next::<String>()?.unwrap_or_default();
+                let tags = params.optional_next::<Vec<String>>()?.unwrap_or_default();
+                // optional, which keys the kfrags are signed to
+                let keyfrag_params = params.optional_next::<PreKeyfragParams>()?.unwrap_or_default();
+                // optional, unix timestamp after which kfrag providers stop releasing cfrags
+                let expires_at = params.optional_next::<i64>()?;
+
+                let preferred_kfrag_providers = preferred_kfrag_providers
+                    .unwrap_or_default()
+                    .iter()
+                    .map(|peer_id| peer_id.parse::<PeerId>())
+                    .collect::<Result<Vec<PeerId>, _>>()
+                    .map_err(|e| RpcError(format!("Invalid preferred kfrag provider peer id: {}", e)))?;
+
+                // Fail before encrypting if the keyfrags can't all be placed
+                nc.check_enough_spawn_vessels(total_frags).await.map_err(RpcError::from)?;
+
+                Ok::<_, ErrorObjectOwned>(nc.spawn_mcp_plugin_reverie(
+                    mcp_plugin,
+                    threshold,
+                    total_frags,
+                    access_condition,
+                    spawn_signature,
+                    preferred_kfrag_providers,
+                    description,
+                    tags,
+                    keyfrag_params,
+                    expires_at,
+                ).await.map_err(RpcError::from)?)
+            }
         }
     )?;
 
@@ -225,6 +422,8 @@ pub async fn run_server(rpc_port: usize, network_client: NodeClient) -> Result<S
                 AnthropicQuery
             )>().expect("error parsing params");
 
+            anthropic_query.validate_tools().map_err(RpcError::from)?;
+
             nc.execute_with_memory_reverie(
                 reverie_id,
                 reverie_type,
@@ -234,6 +433,85 @@ pub async fn run_server(rpc_port: usize, network_client: NodeClient) -> Result<S
         }
     )?;
 
+    rpc_server.add_route_mut(
+        "execute_with_mcp_plugin_reverie",
+        |params, mut nc, _| async move {
+            let (
+                reverie_id,
+                access_key,
+                anthropic_query,
+            ) = params.parse::<(
+                ReverieId,
+                AccessKey,
+                AnthropicQuery
+            )>()?;
+
+            anthropic_query.validate_tools().map_err(RpcError::from)?;
+
+            nc.execute_with_mcp_plugin_reverie(
+                reverie_id,
+                access_key,
+                anthropic_query,
+            ).await.map_err(RpcError::from)
+        }
+    )?;
+
+    rpc_server.add_route_mut(
+        "decrypt_reverie",
+        |params, mut nc, _| async move {
+            let (
+                reverie_id,
+                reverie_type,
+                access_key,
+            ) = params.parse::<(
+                ReverieId,
+                ReverieType,
+                AccessKey,
+            )>()?;
+
+            nc.decrypt_reverie(
+                reverie_id,
+                reverie_type,
+                access_key,
+            ).await.map_err(RpcError::from)
+        }
+    )?;
+
+    rpc_server.add_route(
+        "transfer_reverie_ownership",
+        |params, nc, _| async move {
+            let (reverie_id, new_owner) = params.parse::<(ReverieId, String)>()?;
+            nc.transfer_reverie_ownership(&reverie_id, &new_owner)
+                .await.map_err(RpcError::from)
+        }
+    )?;
+
+    rpc_server.add_route(
+        "revoke_reverie",
+        |params, nc, _| async move {
+            let reverie_id = params.one::<ReverieId>()?;
+            nc.revoke_reverie(&reverie_id)
+                .await.map_err(RpcError::from)
+        }
+    )?;
+
+    rpc_server.add_route(
+        "export_reverie",
+        |params, nc, _| async move {
+            let reverie_id = params.one::<ReverieId>()?;
+            nc.export_reverie(&reverie_id).map_err(RpcError::from)
+        }
+    )?;
+
+    rpc_server.add_route_mut(
+        "import_reverie",
+        |params, mut nc, _| async move {
+            let bundle = params.one::<ReverieBundle>()?;
+            nc.import_reverie(bundle)
+                .await.map_err(RpcError::from)
+        }
+    )?;
+
     rpc_server.add_route_mut(
         "report_usage",
         |params, mut nc, _| async move {
@@ -260,6 +538,52 @@ pub async fn run_server(rpc_port: usize, network_client: NodeClient) -> Result<S
         }
     )?;
 
+    rpc_server.add_route(
+        "get_onchain_reverie_metadata",
+        |params, nc, _| async move {
+            let (
+                contract_id,
+                reverie_id,
+            ) = params.parse::<(String, ReverieId)>()?;
+
+            nc.get_onchain_reverie_metadata(&contract_id, &reverie_id)
+                .await.map_err(RpcError::from)
+        }
+    )?;
+
+    rpc_server.add_route(
+        "get_onchain_reveries_for_spender",
+        |params, nc, _| async move {
+            let (
+                contract_id,
+                spender,
+            ) = params.parse::<(String, String)>()?;
+
+            nc.get_onchain_reveries_for_spender(&contract_id, &spender)
+                .await.map_err(RpcError::from)
+        }
+    )?;
+
+    rpc_server.add_route(
+        "list_reveries_by_type",
+        |params, nc, _| async move {
+            let (reverie_type,) = params.parse::<(ReverieType,)>()?;
+
+            nc.list_reveries_by_type(&reverie_type)
+                .await.map_err(RpcError::from)
+        }
+    )?;
+
+    rpc_server.add_route(
+        "list_reverie_entries_by_type",
+        |params, nc, _| async move {
+            let (reverie_type,) = params.parse::<(ReverieType,)>()?;
+
+            nc.list_reverie_entries_by_type(&reverie_type)
+                .await.map_err(RpcError::from)
+        }
+    )?;
+
     rpc_server.add_route(
         "get_connected_peers",
         |_, nc_arc: Arc<NodeClient>, _| async move {
@@ -331,19 +655,21 @@ pub async fn run_server(rpc_port: usize, network_client: NodeClient) -> Result<S
     rpc_server.add_route_mut(
         "add_proxy_api_key",
         |params, mut nc, _| async move {
-            let (
-                reverie_id,
-                api_key_name,
-                api_key,
-                spender_address,
-                spender_address_type
-            ) = params.parse::<(String, String, String, String, String)>()?;
+            let mut params = params.sequence();
+            let reverie_id = params.next::<String>()?;
+            let api_key_name = params.next::<String>()?;
+            let api_key = params.next::<String>()?;
+            let spender_address = params.next::<String>()?;
+            let spender_address_type = params.next::<String>()?;
+            // Optional: replace an existing key for reverie_id instead of returning a conflict
+            let overwrite = params.optional_next::<bool>()?.unwrap_or(false);
             nc.add_proxy_api_key(
                 reverie_id,
                 api_key_name,
                 api_key,
                 spender_address,
-                spender_address_type
+                spender_address_type,
+                overwrite,
             ).await.map_err(RpcError::from)
         }
     )?;
@@ -383,41 +709,114 @@ pub async fn run_server(rpc_port: usize, network_client: NodeClient) -> Result<S
         }
     )?;
 
+    // A single task drains the heartbeat channel into the replay buffer,
+    // which fans heartbeats out to every subscriber
+    let heartbeat_replay = Arc::new(HeartbeatReplay::new(DEFAULT_HEARTBEAT_REPLAY_CAPACITY));
+    let heartbeat_receiver = network_client.get_hb_channel();
     let nc = network_client.clone();
+    let replay = heartbeat_replay.clone();
+    tokio::spawn(async move {
+        while let Ok(tee_payload) = heartbeat_receiver.recv().await {
+
+            // node state is only worth fetching for live subscribers,
+            // replayed heartbeats are sent without it
+            let node_state_result = match replay.subscriber_count() {
+                0 => None,
+                _ => nc.get_node_state().await.ok(),
+            };
+            let block_height = tee_payload.latest_tee_attestation.block_height;
+
+            let tee_quote_v4 = match tee_payload.latest_tee_attestation.tee_attestation_bytes {
+                None => None,
+                Some(tee_bytes) => {
+                    Some(parse_tee_attestation_bytes(tee_bytes))
+                }
+            };
+
+            let json_payload = serde_json::json!({
+                "tee_attestation": {
+                    "peer_id": tee_payload.peer_id,
+                    "peer_name": get_node_name(&tee_payload.peer_id),
+                    "block_height": block_height,
+                    "tee_quote_v4": tee_quote_v4
+                },
+                "node_state": node_state_result,
+                "time": get_time_now(),
+            });
+            replay.push(BufferedHeartbeat {
+                peer_id: tee_payload.peer_id.to_string(),
+                block_height,
+                payload: json_payload,
+            });
+        }
+    });
+
     rpc_server.rpc_module.register_subscription(
         "subscribe_hb",
         "notify_hb",
         "unsubscribe_hb",
         move |params, pending_sink, _, _| {
 
-			let _n = params.one::<usize>().expect("params err");
-            let heartbeat_receiver = nc.get_hb_channel();
-            let nc2 = nc.clone();
+            let subscribed = (|| {
+                let mut params = params.sequence();
+                let _n = params.next::<usize>()?;
+                // Optional: last block_height seen per peer id, to replay heartbeats missed while disconnected
+                params.optional_next::<HeartbeatResumeToken>()
+            })().map(|resume_token| heartbeat_replay.subscribe(resume_token.as_ref()));
 
             async move {
 
+                let (missed_heartbeats, mut heartbeat_receiver) = match subscribed {
+                    Ok(subscribed) => subscribed,
+                    Err(e) => {
+                        warn!("subscribe_hb: invalid params: {}", e);
+                        pending_sink.reject(e).await;
+                        return Ok(());
+                    }
+                };
+
                 let stream = async_stream::stream! {
-                    while let Ok(tee_payload) = heartbeat_receiver.recv().await {
+                    for heartbeat in missed_heartbeats {
+                        yield Some(heartbeat.payload)
+                    }
+                    loop {
+                        match heartbeat_receiver.recv().await {
+                            Ok(heartbeat) => yield Some(heartbeat.payload),
+                            Err(RecvError::Lagged(skipped)) => {
+                                warn!("subscribe_hb: subscriber lagged, skipped {} heartbeats", skipped);
+                            }
+                            Err(RecvError::Closed) => break,
+                        }
+                    }
+                };
+                pin_mut!(stream);
+
+                pipe_from_stream_and_drop(pending_sink, stream)
+                    .await.map_err(Into::into)
+            }
+        }
+    )?;
+
+    let nc = network_client.clone();
+    rpc_server.rpc_module.register_subscription(
+        "subscribe_node_events",
+        "notify_node_events",
+        "unsubscribe_node_events",
+        move |_params, pending_sink, _, _| {
+
+            let mut node_event_receiver = nc.subscribe_node_events();
 
-                        let node_state_result = nc2.get_node_state().await.ok();
+            async move {
 
-                        let tee_quote_v4 = match tee_payload.latest_tee_attestation.tee_attestation_bytes {
-                            None => None,
-                            Some(tee_bytes) => {
-                                Some(parse_tee_attestation_bytes(tee_bytes))
+                let stream = async_stream::stream! {
+                    loop {
+                        match node_event_receiver.recv().await {
+                            Ok(node_event) => yield node_event,
+                            Err(RecvError::Lagged(skipped)) => {
+                                warn!("subscribe_node_events: subscriber lagged, skipped {} events", skipped);
                             }
-                        };
-
-                        let json_payload = serde_json::json!({
-                            "tee_attestation": {
-                                "peer_id": tee_payload.peer_id,
-                                "peer_name": get_node_name(&tee_payload.peer_id),
-                                "tee_quote_v4": tee_quote_v4
-                            },
-                            "node_state": node_state_result,
-                            "time": get_time_now(),
-                        });
-                        yield Some(json_payload)
+                            Err(RecvError::Closed) => break,
+                        }
                     }
                 };
                 pin_mut!(stream);
@@ -496,11 +895,11 @@ impl std::fmt::Display for RpcError {
 
 impl std::error::Error for RpcError {}
 
-impl Into<ErrorObjectOwned> for RpcError {
-    fn into(self) -> ErrorObjectOwned {
+impl From<RpcError> for ErrorObjectOwned {
+    fn from(e: RpcError) -> Self {
         ErrorObject::owned(
             ErrorCode::code(&ErrorCode::InternalError),
-            self.to_string(),
+            e.to_string(),
             Some("RpcError")
         )
     }
@@ -528,4 +927,69 @@ impl From<serde_json::Error> for RpcError {
     fn from(e: serde_json::Error) -> Self {
         RpcError(e.to_string())
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use jsonrpsee::core::client::ClientT;
+    use jsonrpsee::rpc_params;
+    use p2p_network::node_client::NodeCommand;
+    use p2p_network::test_utils::{test_node_client, mock_network};
+    use p2p_network::types::{NodeKeysWithVesselStatus, VesselStatus};
+    use runtime::reencrypt::UmbralKey;
+    use crate::rpc_client::create_http_rpc_client;
+    use crate::spawn_rate_limit::DEFAULT_SPAWN_RATE_LIMIT_WINDOW;
+
+    #[tokio::test]
+    async fn spawn_route_fails_fast_with_too_few_connected_empty_vessels() {
+        let (node_client, command_receiver) = test_node_client(UmbralKey::new(None));
+
+        let connected_peers = (0..3).map(|_| PeerId::random()).collect::<Vec<PeerId>>();
+        let node_vessels = connected_peers.iter()
+            .map(|peer_id| {
+                let umbral_key = UmbralKey::new(None);
+                NodeKeysWithVesselStatus {
+                    peer_id: *peer_id,
+                    umbral_public_key: umbral_key.public_key,
+                    umbral_verifying_public_key: umbral_key.verifying_public_key,
+                    vessel_status: VesselStatus::EmptyVessel,
+                }
+            })
+            .collect::<Vec<NodeKeysWithVesselStatus>>();
+        let mut forwarded = mock_network(command_receiver, move |command| match command {
+            NodeCommand::GetConnectedPeers { responder } => {
+                responder.send(connected_peers.clone()).ok();
+                None
+            }
+            NodeCommand::GetNodeVesselStatusesFromKademlia { sender } => {
+                for v in node_vessels.iter() {
+                    sender.try_send(v.clone()).ok();
+                }
+                None
+            }
+            command => Some(command),
+        });
+
+        let spawn_rate_limiter = SpawnRateLimiter::new(0, DEFAULT_SPAWN_RATE_LIMIT_WINDOW);
+        let server_addr = run_server(0, node_client.clone(), spawn_rate_limiter).await.unwrap();
+        let client = create_http_rpc_client(&SocketAddr::from(([127, 0, 0, 1], server_addr.port()))).await.unwrap();
+
+        // 3 keyfrags and a target vessel need 4 connected empty vessels
+        let vessel_key = UmbralKey::new(None);
+        let err = client.request::<serde_json::Value, _>(
+            "spawn_memory_reverie",
+            rpc_params![
+                serde_json::json!({ "notes": ["met auron at the harbour"] }),
+                2,
+                3,
+                AccessCondition::Umbral(vessel_key.public_key)
+            ]
+        ).await.unwrap_err();
+        assert!(err.to_string().contains("need 4, connected 3"), "{}", err);
+
+        // The route returned before encrypting, so no keyfrags or ciphertext were sent.
+        // The mock handles commands in order, so this round trip flushes any that were.
+        node_client.get_connected_peers().await.unwrap();
+        assert!(forwarded.try_recv().is_err());
+    }
+}
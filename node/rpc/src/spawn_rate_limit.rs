@@ -0,0 +1,114 @@
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use jsonrpsee::types::{ErrorObjectOwned, ErrorObject, ErrorCode};
+
+pub const DEFAULT_SPAWN_RATE_LIMIT: usize = 30;
+pub const DEFAULT_SPAWN_RATE_LIMIT_WINDOW: Duration = Duration::from_secs(60);
+
+/// Node-wide limit on spawn RPCs, so a client can't flood a node with spawns
+/// and exhaust the network's empty vessels and key material.
+/// Spawns are counted over a sliding window, a `max_spawns` of 0 disables the limit.
+pub struct SpawnRateLimiter {
+    max_spawns: usize,
+    window: Duration,
+    recent_spawns: Mutex<VecDeque<Instant>>,
+}
+
+impl SpawnRateLimiter {
+    pub fn new(max_spawns: usize, window: Duration) -> Self {
+        Self {
+            max_spawns,
+            window,
+            recent_spawns: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    /// Records a spawn at `now`, unless `max_spawns` were already made within the window
+    pub fn check(&self, now: Instant) -> Result<(), SpawnRateLimited> {
+        if self.max_spawns == 0 {
+            return Ok(())
+        }
+        let mut recent_spawns = self.recent_spawns.lock().unwrap();
+        while let Some(oldest) = recent_spawns.front() {
+            if now.saturating_duration_since(*oldest) < self.window {
+                break
+            }
+            recent_spawns.pop_front();
+        }
+
+        if let Some(oldest) = recent_spawns.front().filter(|_| recent_spawns.len() >= self.max_spawns) {
+            return Err(SpawnRateLimited {
+                max_spawns: self.max_spawns,
+                window: self.window,
+                retry_after: self.window.saturating_sub(now.saturating_duration_since(*oldest)),
+            })
+        }
+        recent_spawns.push_back(now);
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SpawnRateLimited {
+    pub max_spawns: usize,
+    pub window: Duration,
+    pub retry_after: Duration,
+}
+
+impl std::fmt::Display for SpawnRateLimited {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "Spawn rate limit of {} per {}s exceeded, retry in {}s",
+            self.max_spawns,
+            self.window.as_secs(),
+            self.retry_after.as_secs().max(1)
+        )
+    }
+}
+
+impl std::error::Error for SpawnRateLimited {}
+
+/// The JSON-RPC equivalent of HTTP 429 Too Many Requests
+impl From<SpawnRateLimited> for ErrorObjectOwned {
+    fn from(e: SpawnRateLimited) -> Self {
+        ErrorObject::owned(
+            ErrorCode::ServerIsBusy.code(),
+            e.to_string(),
+            Some(serde_json::json!({ "retry_after_secs": e.retry_after.as_secs().max(1) }))
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rapid_spawns_are_limited_until_the_window_passes() {
+        let limiter = SpawnRateLimiter::new(3, Duration::from_secs(60));
+        let start = Instant::now();
+
+        for i in 0..3 {
+            assert!(limiter.check(start + Duration::from_millis(i)).is_ok());
+        }
+        let limited = limiter.check(start + Duration::from_secs(1)).unwrap_err();
+        assert_eq!(limited.retry_after, Duration::from_secs(59));
+
+        let error = ErrorObjectOwned::from(limited);
+        assert_eq!(error.code(), ErrorCode::ServerIsBusy.code());
+        assert!(error.message().contains("Spawn rate limit of 3 per 60s exceeded"), "{}", error.message());
+
+        // rejected spawns don't count, the first spawn leaving the window frees a slot
+        assert!(limiter.check(start + Duration::from_secs(60)).is_ok());
+        assert!(limiter.check(start + Duration::from_secs(60)).is_err());
+    }
+
+    #[test]
+    fn zero_max_spawns_disables_the_limit() {
+        let limiter = SpawnRateLimiter::new(0, Duration::from_secs(60));
+        let now = Instant::now();
+        assert!((0..100).all(|_| limiter.check(now).is_ok()));
+    }
+}
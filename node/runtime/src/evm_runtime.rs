@@ -1,4 +1,4 @@
-use color_eyre::Result;
+use color_eyre::{Result, eyre::eyre};
 use std::sync::Arc;
 use tracing::{info, warn, error};
 use std::str::FromStr;
@@ -6,12 +6,14 @@ use dotenv::dotenv;
 
 use alloy::{
     network::{Ethereum, TransactionBuilder},
-    primitives::{Address, U256},
+    primitives::{keccak256, Address, Bytes, B256, U256},
     providers::{Provider, ProviderBuilder, RootProvider},
     rpc::{
         client::RpcClient,
         types::{
             request::TransactionRequest,
+            Filter,
+            Log,
             TransactionReceipt,
         },
     },
@@ -36,27 +38,53 @@ sol! {
     }
 }
 
+// Payments contract mirroring the NEAR contract's deposit/can_spend/record_spend flow
+sol! {
+    #[sol(rpc)]
+    contract ReveriePayments {
+        function deposit(string reverieId) public payable;
+        function getBalance(string reverieId, address user) public view returns (uint256 balance);
+        function canSpend(string reverieId, address user, uint256 amount) public view returns (bool);
+        function recordSpend(string reverieId, address user, uint256 amountToSpend) public;
+    }
+}
+
+/// Number of recent blocks searched when looking for an access event log
+pub const DEFAULT_EVENT_BLOCK_WINDOW: u64 = 5_000;
+
+/// Extra gas added on top of the estimate for contract transactions, in percent
+const GAS_LIMIT_BUFFER_PERCENT: u64 = 20;
+
 #[derive(Clone, Debug)]
 pub struct EvmConfig {
     pub rpc_url: String,
     pub chain_id: Option<u64>,
+    pub event_block_window: u64,
 }
 
 impl EvmConfig {
     pub fn new() -> Result<Self> {
         dotenv().ok();
-        let rpc_url = std::env::var("BASE_SEPOLIA_RPC_URL").unwrap_or_else(|_| {
-            warn!("BASE_SEPOLIA_RPC_URL not set, using default https://sepolia.base.org");
-            "https://sepolia.base.org".to_string()
-        });
+        // EVM_RPC_URL points the runtime at another chain, e.g. a local anvil node
+        let rpc_url = std::env::var("EVM_RPC_URL")
+            .or_else(|_| std::env::var("BASE_SEPOLIA_RPC_URL"))
+            .unwrap_or_else(|_| {
+                warn!("EVM_RPC_URL and BASE_SEPOLIA_RPC_URL not set, using default https://sepolia.base.org");
+                "https://sepolia.base.org".to_string()
+            });
         let chain_id = std::env::var("EVM_CHAIN_ID")
             .ok()
             .and_then(|id_str| id_str.parse::<u64>().ok())
             .or(Some(84532));
+        let event_block_window = std::env::var("EVM_EVENT_BLOCK_WINDOW")
+            .ok()
+            .and_then(|window| window.parse::<u64>().ok())
+            .unwrap_or(DEFAULT_EVENT_BLOCK_WINDOW);
 
         Ok(Self {
             rpc_url,
             chain_id,
+            event_block_window,
         })
     }
 }
@@ -68,6 +96,7 @@ impl Default for EvmConfig {
             Self {
                 rpc_url: "https://sepolia.base.org".to_string(),
                 chain_id: Some(84532),
+                event_block_window: DEFAULT_EVENT_BLOCK_WINDOW,
             }
         })
     }
@@ -92,10 +121,50 @@ impl EvmRuntime {
         let rpc_client = RpcClient::new_http(rpc_url_parsed);
         let provider = RootProvider::<Ethereum>::new(rpc_client);
 
-        Ok(Self {
+        Ok(Self::from_provider(provider, config))
+    }
+
+    /// Wraps an existing provider, e.g. a mocked client in tests
+    pub fn from_provider(provider: RootProvider<Ethereum>, config: EvmConfig) -> Self {
+        Self {
             provider: Arc::new(provider),
             config,
-        })
+        }
+    }
+
+    /// Searches the last `event_block_window` blocks for a log emitted by `contract_address`
+    /// matching `event_signature` (e.g. "ReveriePaid(string,address)"), with the reverie_id
+    /// as its first indexed topic and the payer as its second. Returns true if such a log is found.
+    pub async fn has_event_log(
+        &self,
+        contract_address: Address,
+        event_signature: &str,
+        reverie_id: &str,
+        payer: Address,
+    ) -> Result<bool> {
+        let latest_block = self.provider.get_block_number().await?;
+        let from_block = latest_block.saturating_sub(self.config.event_block_window);
+        let event_topic = keccak256(event_signature.as_bytes());
+        let reverie_topic = keccak256(reverie_id.as_bytes());
+        let payer_topic = payer.into_word();
+
+        info!(
+            "Querying {} logs on {:?} for reverie {} paid by {:?} in blocks {}..={}",
+            event_signature, contract_address, reverie_id, payer, from_block, latest_block
+        );
+        let filter = Filter::new()
+            .address(contract_address)
+            .event_signature(event_topic)
+            .topic1(reverie_topic)
+            .topic2(payer_topic)
+            .from_block(from_block)
+            .to_block(latest_block);
+
+        let logs = self.provider.get_logs(&filter).await?;
+        // Re-check each log rather than trusting the RPC node to have applied the filter
+        Ok(logs.iter().any(|log| {
+            log_matches_event(log, contract_address, event_topic, reverie_topic, payer_topic)
+        }))
     }
 
     pub async fn get_balance(&self, address_str: &str) -> Result<U256> {
@@ -173,6 +242,53 @@ impl EvmRuntime {
         Ok(receipt)
     }
 
+    /// Signs and sends a contract call from `signer_hex_key`, returning its receipt.
+    /// The nonce is read from the pending block and the gas limit is the estimate plus
+    /// GAS_LIMIT_BUFFER_PERCENT; fees are filled in by the provider.
+    async fn _send_contract_transaction(
+        &self,
+        signer_hex_key: &str,
+        contract_address: Address,
+        calldata: Bytes,
+        value: U256,
+    ) -> Result<TransactionReceipt> {
+        let signer: PrivateKeySigner = signer_hex_key.parse()?;
+        let signer_address = signer.address();
+
+        let provider_with_signer = ProviderBuilder::new()
+            .wallet(signer)
+            .connect_client(RpcClient::new_http(self.config.rpc_url.parse()?));
+
+        let nonce = provider_with_signer
+            .get_transaction_count(signer_address)
+            .pending()
+            .await?;
+
+        let mut tx = TransactionRequest::default()
+            .with_from(signer_address)
+            .with_to(contract_address)
+            .with_input(calldata)
+            .with_value(value)
+            .with_nonce(nonce);
+
+        if let Some(chain_id_val) = self.config.chain_id {
+            tx.set_chain_id(chain_id_val);
+        }
+
+        let gas_estimate = provider_with_signer.estimate_gas(tx.clone()).await?;
+        tx.set_gas_limit(gas_limit_with_buffer(gas_estimate));
+
+        info!("Sending contract transaction from {:?} to {:?} (nonce: {})", signer_address, contract_address, nonce);
+        let pending_tx = provider_with_signer.send_transaction(tx).await?;
+        info!("Contract transaction sent, hash: {:?}", pending_tx.tx_hash());
+        let receipt = pending_tx.get_receipt().await?;
+
+        if !receipt.status() {
+            return Err(eyre!("Contract transaction {:?} reverted", receipt.transaction_hash));
+        }
+        Ok(receipt)
+    }
+
 //     pub async fn send_transaction(
 //         &self,
 //         wallet: &PrivateKeySigner,
@@ -223,6 +339,109 @@ impl EvmRuntime {
 
 }
 
+/// EVM equivalents of the NearRuntime payment methods, calling a ReveriePayments contract.
+/// Users are identified by their address rather than a NEAR account id.
+impl EvmRuntime {
+    pub async fn deposit(
+        &self,
+        contract_id: &str,
+        signer_hex_key: &str,
+        reverie_id: &str,
+        amount_to_deposit: U256,
+    ) -> Result<TransactionReceipt> {
+        info!(
+            "Calling deposit on contract {} with amount: {} for reverie {}",
+            contract_id, amount_to_deposit, reverie_id
+        );
+        let contract_address = Address::from_str(contract_id)?;
+        let payments = ReveriePayments::new(contract_address, self.provider.clone());
+        let calldata = payments.deposit(reverie_id.to_string()).calldata().clone();
+
+        self._send_contract_transaction(
+            signer_hex_key,
+            contract_address,
+            calldata,
+            amount_to_deposit,
+        ).await
+    }
+
+    /// Deposited balance of a user for a reverie, not to be confused with `get_balance`
+    /// which reads an address's ETH balance.
+    pub async fn get_reverie_balance(&self, contract_id: &str, reverie_id: &str, user_id: &str) -> Result<U256> {
+        info!("Calling getBalance on contract {} for reverie {} user: {}", contract_id, reverie_id, user_id);
+        let contract_address = Address::from_str(contract_id)?;
+        let user_address = Address::from_str(user_id)?;
+
+        let payments = ReveriePayments::new(contract_address, self.provider.clone());
+        let balance = payments.getBalance(reverie_id.to_string(), user_address).call().await?;
+        Ok(balance)
+    }
+
+    pub async fn can_spend(
+        &self,
+        contract_id: &str,
+        reverie_id: &str,
+        user_id: &str,
+        amount_to_check: U256,
+    ) -> Result<bool> {
+        info!(
+            "Calling canSpend on contract {} for reverie {} user: {} with amount: {}",
+            contract_id, reverie_id, user_id, amount_to_check
+        );
+        let contract_address = Address::from_str(contract_id)?;
+        let user_address = Address::from_str(user_id)?;
+
+        let payments = ReveriePayments::new(contract_address, self.provider.clone());
+        let can_spend = payments.canSpend(reverie_id.to_string(), user_address, amount_to_check).call().await?;
+        Ok(can_spend)
+    }
+
+    /// The signer must be the contract's trusted account, as with NearRuntime::record_spend
+    pub async fn record_spend(
+        &self,
+        contract_id: &str,
+        signer_hex_key: &str,
+        reverie_id: &str,
+        user_id: &str,
+        amount_to_spend: U256,
+    ) -> Result<TransactionReceipt> {
+        info!("Calling recordSpend on contract {} for reverie {} user: {} amount: {}", contract_id, reverie_id, user_id, amount_to_spend);
+        let contract_address = Address::from_str(contract_id)?;
+        let user_address = Address::from_str(user_id)?;
+
+        let payments = ReveriePayments::new(contract_address, self.provider.clone());
+        let calldata = payments
+            .recordSpend(reverie_id.to_string(), user_address, amount_to_spend)
+            .calldata()
+            .clone();
+
+        self._send_contract_transaction(
+            signer_hex_key,
+            contract_address,
+            calldata,
+            U256::ZERO,
+        ).await
+    }
+}
+
+fn gas_limit_with_buffer(gas_estimate: u64) -> u64 {
+    gas_estimate.saturating_add(gas_estimate.saturating_mul(GAS_LIMIT_BUFFER_PERCENT) / 100)
+}
+
+fn log_matches_event(
+    log: &Log,
+    contract_address: Address,
+    event_topic: B256,
+    reverie_topic: B256,
+    payer_topic: B256,
+) -> bool {
+    let topics = log.topics();
+    log.address() == contract_address
+        && topics.first() == Some(&event_topic)
+        && topics.get(1) == Some(&reverie_topic)
+        && topics.get(2) == Some(&payer_topic)
+}
+
 pub async fn evm_example() -> Result<()> {
     let config = EvmConfig::default();
     let runtime = EvmRuntime::new(config).await?;
@@ -247,6 +466,8 @@ pub async fn evm_example() -> Result<()> {
 mod tests {
     use super::*;
     use alloy::primitives::utils::{parse_ether, format_ether};
+    use alloy::primitives::{Bytes, U64};
+    use alloy::transports::mock::Asserter;
     use std::env;
 
     fn setup_test_logger() {
@@ -257,6 +478,140 @@ mod tests {
         env::var(var_name).map_err(|_| color_eyre::eyre::eyre!("Mandatory environment variable {} not set", var_name))
     }
 
+    fn mocked_runtime(asserter: Asserter) -> EvmRuntime {
+        let provider = RootProvider::<Ethereum>::new(RpcClient::mocked(asserter));
+        let config = EvmConfig {
+            rpc_url: "http://localhost:8545".to_string(),
+            chain_id: Some(84532),
+            event_block_window: DEFAULT_EVENT_BLOCK_WINDOW,
+        };
+        EvmRuntime::from_provider(provider, config)
+    }
+
+    // anvil's default dev accounts, funded on a fresh `anvil` node
+    const ANVIL_TRUSTED_KEY: &str = "0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80";
+    const ANVIL_USER_KEY: &str = "0x59c6995e998f97a5a0044966f0945389dc9e86dae88c7a8412f4603b6b78690d";
+
+    /// Runtime against a local anvil node with a ReveriePayments contract deployed by
+    /// ANVIL_TRUSTED_KEY, at ANVIL_PAYMENTS_CONTRACT_ADDRESS
+    async fn anvil_runtime() -> Result<(EvmRuntime, String)> {
+        dotenv().ok();
+        let contract_id = get_mandatory_env_var("ANVIL_PAYMENTS_CONTRACT_ADDRESS")?;
+        let config = EvmConfig {
+            rpc_url: env::var("ANVIL_RPC_URL").unwrap_or_else(|_| "http://localhost:8545".to_string()),
+            chain_id: Some(31337),
+            event_block_window: DEFAULT_EVENT_BLOCK_WINDOW,
+        };
+        Ok((EvmRuntime::new(config).await?, contract_id))
+    }
+
+    fn event_log(contract_address: Address, event_signature: &str, reverie_id: &str, payer: Address) -> Log {
+        Log {
+            inner: alloy::primitives::Log::new_unchecked(
+                contract_address,
+                vec![
+                    keccak256(event_signature.as_bytes()),
+                    keccak256(reverie_id.as_bytes()),
+                    payer.into_word(),
+                ],
+                Bytes::new(),
+            ),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_has_event_log_with_mocked_provider() -> Result<()> {
+        let contract_address = Address::from_str("0x4200000000000000000000000000000000000006")?;
+        let event_signature = "ReveriePaid(string,address)";
+        let reverie_id = "reverie_1234";
+        let payer = Address::from_str("0x70997970C51812dc3A010C7d01b50e0d17dc79C8")?;
+        let other_payer = Address::from_str("0x3C44CdDdB6a900fa2b585dd299e03d12FA4293BC")?;
+
+        // Matching log for this reverie and payer
+        let asserter = Asserter::new();
+        asserter.push_success(&U64::from(10_000));
+        asserter.push_success(&vec![event_log(contract_address, event_signature, reverie_id, payer)]);
+        let runtime = mocked_runtime(asserter);
+        assert!(runtime.has_event_log(contract_address, event_signature, reverie_id, payer).await?);
+
+        // Payment event exists, but for a different reverie
+        let asserter = Asserter::new();
+        asserter.push_success(&U64::from(10_000));
+        asserter.push_success(&vec![event_log(contract_address, event_signature, "reverie_other", payer)]);
+        let runtime = mocked_runtime(asserter);
+        assert!(!runtime.has_event_log(contract_address, event_signature, reverie_id, payer).await?);
+
+        // Someone else's payment for this reverie can't be replayed
+        let asserter = Asserter::new();
+        asserter.push_success(&U64::from(10_000));
+        asserter.push_success(&vec![event_log(contract_address, event_signature, reverie_id, other_payer)]);
+        let runtime = mocked_runtime(asserter);
+        assert!(!runtime.has_event_log(contract_address, event_signature, reverie_id, payer).await?);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_can_spend_with_mocked_provider() -> Result<()> {
+        let contract_id = "0x5FbDB2315678afecb367f032d93F642f64180aa3";
+        let user_id = "0x70997970C51812dc3A010C7d01b50e0d17dc79C8";
+
+        let asserter = Asserter::new();
+        asserter.push_success(&Bytes::from(B256::with_last_byte(1).to_vec()));
+        let runtime = mocked_runtime(asserter);
+        assert!(runtime.can_spend(contract_id, "reverie_1234", user_id, U256::from(100)).await?);
+
+        // user ids must be EVM addresses
+        let runtime = mocked_runtime(Asserter::new());
+        assert!(runtime.can_spend(contract_id, "reverie_1234", "alice.testnet", U256::from(100)).await.is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_gas_limit_with_buffer() {
+        assert_eq!(gas_limit_with_buffer(100_000), 120_000);
+        assert_eq!(gas_limit_with_buffer(u64::MAX), u64::MAX);
+        assert!(gas_limit_with_buffer(u64::MAX / 10) > u64::MAX / 10);
+    }
+
+    #[tokio::test]
+    #[ignore] // Requires a local anvil node with a deployed ReveriePayments contract
+    async fn test_deposit_and_record_spend_on_anvil() -> Result<()> {
+        setup_test_logger();
+        let (runtime, contract_id) = anvil_runtime().await?;
+        let reverie_id = format!("reverie_{}", chrono::Utc::now().timestamp_millis());
+        let user_id = ANVIL_USER_KEY.parse::<PrivateKeySigner>()?.address().to_string();
+
+        let deposit_amount = parse_ether("1").unwrap();
+        let spend_amount = parse_ether("0.25").unwrap();
+
+        let receipt = runtime.deposit(&contract_id, ANVIL_USER_KEY, &reverie_id, deposit_amount).await?;
+        assert!(receipt.status(), "deposit transaction failed");
+        assert!(runtime.can_spend(&contract_id, &reverie_id, &user_id, spend_amount).await?);
+
+        let receipt = runtime.record_spend(&contract_id, ANVIL_TRUSTED_KEY, &reverie_id, &user_id, spend_amount).await?;
+        assert!(receipt.status(), "recordSpend transaction failed");
+
+        let balance = runtime.get_reverie_balance(&contract_id, &reverie_id, &user_id).await?;
+        assert_eq!(balance, deposit_amount - spend_amount);
+        assert!(!runtime.can_spend(&contract_id, &reverie_id, &user_id, deposit_amount).await?);
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[ignore] // Requires a local anvil node with a deployed ReveriePayments contract
+    async fn test_get_reverie_balance_on_anvil() -> Result<()> {
+        setup_test_logger();
+        let (runtime, contract_id) = anvil_runtime().await?;
+        let user_id = ANVIL_USER_KEY.parse::<PrivateKeySigner>()?.address().to_string();
+
+        // nothing deposited for an unknown reverie
+        let balance = runtime.get_reverie_balance(&contract_id, "reverie_never_deposited", &user_id).await?;
+        assert_eq!(balance, U256::ZERO);
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_get_erc20_balance_of_weth() -> Result<()> {
         setup_test_logger();
@@ -2,8 +2,12 @@ pub mod reencrypt;
 pub mod llm;
 pub mod tee_attestation;
 pub mod tee_mock_attestation;
+pub mod tee_verifier;
 
 pub mod near_runtime;
 pub mod evm_runtime;
 
+#[cfg(any(test, feature = "test-utils"))]
+pub mod test_utils;
+
 pub use dcap_rs::types::quotes::body::QuoteBody;
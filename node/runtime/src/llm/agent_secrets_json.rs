@@ -1,6 +1,11 @@
+use std::io::Read;
+use std::path::PathBuf;
 use serde::{Deserialize, Serialize};
+use color_eyre::{Result, eyre::anyhow};
 use libp2p::identity::{ed25519, secp256k1};
 
+const REDACTED: &str = "[REDACTED]";
+
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct AgentKeypair {
     public_key: String,
@@ -24,6 +29,143 @@ pub struct AgentSecretsJson {
     pub context: String,
 }
 
+/// Providers whose API keys an agent can carry
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ApiKeyProvider {
+    Anthropic,
+    OpenAI,
+    DeepSeek,
+}
+
+impl ApiKeyProvider {
+    /// Prefix of every key the provider issues
+    pub fn key_prefix(&self) -> &'static str {
+        match self {
+            ApiKeyProvider::Anthropic => "sk-ant-",
+            ApiKeyProvider::OpenAI => "sk-",
+            ApiKeyProvider::DeepSeek => "sk-",
+        }
+    }
+
+    /// Checks the key has the provider's prefix followed by key characters only.
+    /// Anthropic keys also start with "sk-", so they are rejected for other providers.
+    pub fn validate_key(&self, api_key: &str) -> Result<()> {
+        let body = api_key.strip_prefix(self.key_prefix())
+            .ok_or_else(|| anyhow!("{:?} API key must start with '{}'", self, self.key_prefix()))?;
+
+        if *self != ApiKeyProvider::Anthropic && api_key.starts_with(ApiKeyProvider::Anthropic.key_prefix()) {
+            return Err(anyhow!("{:?} API key looks like an Anthropic key", self));
+        }
+        if body.is_empty() {
+            return Err(anyhow!("{:?} API key is empty after the '{}' prefix", self, self.key_prefix()));
+        }
+        if !body.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+            return Err(anyhow!("{:?} API key contains invalid characters", self));
+        }
+        Ok(())
+    }
+}
+
+impl AgentSecretsJson {
+    /// The API keys present, with their provider
+    pub fn api_keys(&self) -> Vec<(ApiKeyProvider, &str)> {
+        [
+            (ApiKeyProvider::Anthropic, &self.anthropic_api_key),
+            (ApiKeyProvider::OpenAI, &self.openai_api_key),
+            (ApiKeyProvider::DeepSeek, &self.deepseek_api_key),
+        ]
+        .into_iter()
+        .filter_map(|(provider, api_key)| api_key.as_deref().map(|k| (provider, k)))
+        .collect()
+    }
+
+    /// Checks each API key present matches its provider's key format.
+    /// Errors list every malformed key, without including the keys themselves.
+    pub fn validate_api_keys(&self) -> Result<()> {
+        let errors = self.api_keys()
+            .into_iter()
+            .filter_map(|(provider, api_key)| provider.validate_key(api_key).err())
+            .map(|e| e.to_string())
+            .collect::<Vec<String>>();
+
+        if !errors.is_empty() {
+            return Err(anyhow!("Invalid agent API keys: {}", errors.join("; ")));
+        }
+        Ok(())
+    }
+
+    /// Copy with API keys and secret keys replaced by "[REDACTED]", for logging
+    pub fn redacted(&self) -> Self {
+        let redact = |api_key: &Option<String>| api_key.as_ref().map(|_| REDACTED.to_string());
+        Self {
+            corekey_secp256k1: self.corekey_secp256k1.redacted(),
+            corekey_ed25519: self.corekey_ed25519.redacted(),
+            anthropic_api_key: redact(&self.anthropic_api_key),
+            openai_api_key: redact(&self.openai_api_key),
+            deepseek_api_key: redact(&self.deepseek_api_key),
+            ..self.clone()
+        }
+    }
+}
+
+impl AgentKeypair {
+    fn redacted(&self) -> Self {
+        Self {
+            public_key: self.public_key.clone(),
+            secret_key: REDACTED.to_string(),
+        }
+    }
+}
+
+/// Where an agent's secrets are read from when spawning it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AgentSecretsSource {
+    /// AgentSecretsJson passed inline
+    Json(String),
+    /// Path to an AgentSecretsJson file
+    File(PathBuf),
+    /// AgentSecretsJson piped in on stdin
+    Stdin,
+    /// Dummy secrets for a named test agent, see `read_agent_secrets`.
+    /// Only meant for tests and local networks.
+    Seed(usize),
+}
+
+impl AgentSecretsSource {
+    pub fn read(&self) -> Result<AgentSecretsJson> {
+        self.read_from(std::io::stdin().lock())
+    }
+
+    fn read_from(&self, mut stdin: impl Read) -> Result<AgentSecretsJson> {
+        let secrets_json = match self {
+            AgentSecretsSource::Json(json) => json.clone(),
+            AgentSecretsSource::File(path) => std::fs::read_to_string(path)
+                .map_err(|e| anyhow!("Failed to read agent secrets file {}: {}", path.display(), e))?,
+            AgentSecretsSource::Stdin => {
+                let mut json = String::new();
+                stdin.read_to_string(&mut json)
+                    .map_err(|e| anyhow!("Failed to read agent secrets from stdin: {}", e))?;
+                json
+            }
+            AgentSecretsSource::Seed(seed) => return Ok(read_agent_secrets(*seed)),
+        };
+        // serde errors can quote the input, so they are not included
+        serde_json::from_str(&secrets_json)
+            .map_err(|e| anyhow!("Invalid AgentSecretsJson from {}, at line {} column {}", self.describe(), e.line(), e.column()))
+    }
+
+    fn describe(&self) -> String {
+        match self {
+            AgentSecretsSource::Json(_) => "inline JSON".to_string(),
+            AgentSecretsSource::File(path) => format!("file {}", path.display()),
+            AgentSecretsSource::Stdin => "stdin".to_string(),
+            AgentSecretsSource::Seed(seed) => format!("seed {}", seed),
+        }
+    }
+}
+
+/// Generates dummy secrets for a named test agent: 1 = auron, 2 = beatrix, ...
+/// API keys are read from the environment and the agent's keypairs are random.
 pub fn read_agent_secrets(seed: usize) -> AgentSecretsJson {
 
     let agent_name = match seed {
@@ -38,9 +180,11 @@ pub fn read_agent_secrets(seed: usize) -> AgentSecretsJson {
     };
 
     dotenv::dotenv().ok();
-    let anthropic_api_key = std::env::var("ANTHROPIC_API_KEY").ok();
-    let openai_api_key = std::env::var("OPENAI_API_KEY").ok();
-    let deepseek_api_key = std::env::var("DEEPSEEK_API_KEY").ok();
+    // unset and empty env vars both mean no key
+    let read_api_key = |var: &str| std::env::var(var).ok().filter(|k| !k.is_empty());
+    let anthropic_api_key = read_api_key("ANTHROPIC_API_KEY");
+    let openai_api_key = read_api_key("OPENAI_API_KEY");
+    let deepseek_api_key = read_api_key("DEEPSEEK_API_KEY");
 
     let keypair_secp256k1 = secp256k1::Keypair::generate();
     let keypair_ed25519 = ed25519::Keypair::generate();
@@ -73,4 +217,103 @@ pub fn read_agent_secrets(seed: usize) -> AgentSecretsJson {
         social_accounts: social_accounts,
         context: format!("Your name is {}, your profession is a pizza chef", agent_name),
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn agent_secrets(
+        anthropic_api_key: Option<&str>,
+        openai_api_key: Option<&str>,
+        deepseek_api_key: Option<&str>,
+    ) -> AgentSecretsJson {
+        AgentSecretsJson {
+            anthropic_api_key: anthropic_api_key.map(String::from),
+            openai_api_key: openai_api_key.map(String::from),
+            deepseek_api_key: deepseek_api_key.map(String::from),
+            ..read_agent_secrets(1)
+        }
+    }
+
+    #[test]
+    fn test_valid_api_keys_per_provider() {
+        assert!(ApiKeyProvider::Anthropic.validate_key("sk-ant-api03-AbC_123-xyz").is_ok());
+        assert!(ApiKeyProvider::OpenAI.validate_key("sk-proj-AbC123_xyz").is_ok());
+        assert!(ApiKeyProvider::DeepSeek.validate_key("sk-0123456789abcdef").is_ok());
+
+        let secrets = agent_secrets(Some("sk-ant-api03-abc"), Some("sk-proj-abc"), None);
+        assert!(secrets.validate_api_keys().is_ok());
+        // no keys is not an error
+        assert!(agent_secrets(None, None, None).validate_api_keys().is_ok());
+    }
+
+    #[test]
+    fn test_malformed_api_keys_per_provider() {
+        // wrong prefix
+        assert!(ApiKeyProvider::Anthropic.validate_key("sk-proj-abc").is_err());
+        assert!(ApiKeyProvider::OpenAI.validate_key("pk-abc").is_err());
+        assert!(ApiKeyProvider::DeepSeek.validate_key("abc").is_err());
+        // Anthropic key under another provider
+        assert!(ApiKeyProvider::OpenAI.validate_key("sk-ant-api03-abc").is_err());
+        assert!(ApiKeyProvider::DeepSeek.validate_key("sk-ant-api03-abc").is_err());
+        // empty after prefix, or not shaped like a key
+        assert!(ApiKeyProvider::Anthropic.validate_key("sk-ant-").is_err());
+        assert!(ApiKeyProvider::OpenAI.validate_key("sk-abc def").is_err());
+        assert!(ApiKeyProvider::DeepSeek.validate_key("sk-abc\n").is_err());
+
+        let secrets = agent_secrets(Some("sk-proj-abc"), Some("sk-ant-api03-abc"), Some("sk-ok"));
+        let err = secrets.validate_api_keys().unwrap_err().to_string();
+        assert!(err.contains("Anthropic") && err.contains("OpenAI"), "{}", err);
+        assert!(!err.contains("DeepSeek"), "{}", err);
+        // the keys themselves are not leaked into the error
+        assert!(!err.contains("sk-proj-abc") && !err.contains("sk-ant-api03-abc"), "{}", err);
+    }
+
+    #[test]
+    fn test_agent_secrets_sources() -> Result<()> {
+        let secrets = agent_secrets(Some("sk-ant-api03-abc"), None, None);
+        let secrets_json = serde_json::to_string(&secrets)?;
+        let expected = serde_json::to_value(&secrets)?;
+
+        let from_json = AgentSecretsSource::Json(secrets_json.clone()).read_from(std::io::empty())?;
+        assert_eq!(serde_json::to_value(&from_json)?, expected);
+
+        let path = std::env::temp_dir().join(format!("agent_secrets_{}.json", std::process::id()));
+        std::fs::write(&path, &secrets_json)?;
+        let from_file = AgentSecretsSource::File(path.clone()).read_from(std::io::empty());
+        std::fs::remove_file(&path)?;
+        assert_eq!(serde_json::to_value(&from_file?)?, expected);
+
+        let from_stdin = AgentSecretsSource::Stdin.read_from(secrets_json.as_bytes())?;
+        assert_eq!(serde_json::to_value(&from_stdin)?, expected);
+
+        let from_seed = AgentSecretsSource::Seed(2).read_from(std::io::empty())?;
+        assert_eq!(from_seed.agent_name, "beatrix");
+        assert_eq!(from_seed.agent_nonce, 0);
+
+        // Missing files and malformed JSON are errors, which don't echo the secrets back
+        assert!(AgentSecretsSource::File(path).read_from(std::io::empty()).is_err());
+        let err = AgentSecretsSource::Stdin.read_from(&secrets_json.as_bytes()[1..]).unwrap_err().to_string();
+        assert!(err.contains("stdin") && !err.contains("sk-ant-api03-abc"), "{}", err);
+        Ok(())
+    }
+
+    #[test]
+    fn test_redacted_hides_api_keys_and_secret_keys() {
+        let secrets = agent_secrets(Some("sk-ant-api03-abc"), None, Some("sk-deepseek"));
+        let redacted = secrets.redacted();
+
+        assert_eq!(redacted.anthropic_api_key.as_deref(), Some(REDACTED));
+        assert_eq!(redacted.openai_api_key, None);
+        assert_eq!(redacted.deepseek_api_key.as_deref(), Some(REDACTED));
+        assert_eq!(redacted.corekey_secp256k1.secret_key, REDACTED);
+        assert_eq!(redacted.corekey_ed25519.secret_key, REDACTED);
+        assert_eq!(redacted.corekey_ed25519.public_key, secrets.corekey_ed25519.public_key);
+        assert_eq!(redacted.agent_name, secrets.agent_name);
+
+        let logged = format!("{:?}", redacted);
+        assert!(!logged.contains("sk-ant-api03-abc") && !logged.contains("sk-deepseek"));
+        assert!(!logged.contains(&secrets.corekey_ed25519.secret_key));
+    }
+}
@@ -0,0 +1,126 @@
+use color_eyre::{Result, eyre::anyhow};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+
+/// An MCP server a plugin's tools are served from
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct McpServerEndpoint {
+    pub name: String,
+    pub url: String,
+}
+
+/// An MCP tool definition. MCP servers list tools with `inputSchema`,
+/// Anthropic tools use `input_schema`, both are accepted.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct McpToolDefinition {
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(alias = "inputSchema")]
+    pub input_schema: serde_json::Value,
+}
+
+/// Plaintext of a McpPlugin Reverie: MCP server endpoints, the credentials to call them,
+/// and the tools they serve. Only decrypted inside the target vessel's TEE, where the
+/// tools are added to the LLM's tool set and the credentials never leave the enclave.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct McpPluginConfig {
+    pub name: String,
+    pub endpoints: Vec<McpServerEndpoint>,
+    /// e.g. {"GITHUB_TOKEN": "ghp_..."}, sent to the MCP servers and never to the LLM
+    #[serde(default)]
+    pub credentials: BTreeMap<String, String>,
+    pub tools: Vec<McpToolDefinition>,
+}
+
+impl McpPluginConfig {
+    /// Checks the plugin has an endpoint, and uniquely named tools with object input schemas
+    pub fn validate(&self) -> Result<()> {
+        if self.endpoints.is_empty() {
+            return Err(anyhow!("MCP plugin '{}' has no endpoints", self.name));
+        }
+        if self.tools.is_empty() {
+            return Err(anyhow!("MCP plugin '{}' has no tools", self.name));
+        }
+        let mut tool_names = HashSet::new();
+        for (i, tool) in self.tools.iter().enumerate() {
+            if tool.name.is_empty() {
+                return Err(anyhow!("Invalid MCP plugin tools[{}]: empty 'name'", i));
+            }
+            if !tool_names.insert(tool.name.as_str()) {
+                return Err(anyhow!("Invalid MCP plugin tools[{}]: duplicate tool '{}'", i, tool.name));
+            }
+            if !tool.input_schema.is_object() {
+                return Err(anyhow!("Invalid MCP plugin tools[{}]: 'input_schema' must be an object", i));
+            }
+        }
+        Ok(())
+    }
+
+    /// The plugin's tools in the format of `AnthropicQuery.tools`, without endpoints or credentials
+    pub fn anthropic_tools(&self) -> Vec<serde_json::Value> {
+        self.tools.iter()
+            .map(|tool| {
+                let mut anthropic_tool = serde_json::json!({
+                    "name": tool.name,
+                    "input_schema": tool.input_schema,
+                });
+                if let Some(description) = &tool.description {
+                    anthropic_tool["description"] = serde_json::json!(description);
+                }
+                anthropic_tool
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn github_plugin() -> serde_json::Value {
+        serde_json::json!({
+            "name": "github",
+            "endpoints": [{ "name": "github-mcp", "url": "https://mcp.example.com/github" }],
+            "credentials": { "GITHUB_TOKEN": "ghp_secret" },
+            "tools": [{
+                "name": "search_issues",
+                "description": "Search GitHub issues",
+                "inputSchema": {
+                    "type": "object",
+                    "properties": { "query": { "type": "string" } },
+                    "required": ["query"]
+                }
+            }]
+        })
+    }
+
+    #[test]
+    fn mcp_server_tool_listing_converts_to_anthropic_tools() {
+        let plugin: McpPluginConfig = serde_json::from_value(github_plugin()).unwrap();
+        plugin.validate().unwrap();
+
+        let tools = plugin.anthropic_tools();
+        assert_eq!(tools.len(), 1);
+        assert_eq!(tools[0]["name"], "search_issues");
+        assert_eq!(tools[0]["input_schema"]["required"][0], "query");
+        // credentials stay out of the tools sent to the LLM
+        assert!(!serde_json::to_string(&tools).unwrap().contains("ghp_secret"));
+    }
+
+    #[test]
+    fn invalid_plugins_are_rejected() {
+        let mut no_endpoints: McpPluginConfig = serde_json::from_value(github_plugin()).unwrap();
+        no_endpoints.endpoints.clear();
+        assert!(no_endpoints.validate().is_err());
+
+        let mut duplicate_tools: McpPluginConfig = serde_json::from_value(github_plugin()).unwrap();
+        duplicate_tools.tools.push(duplicate_tools.tools[0].clone());
+        let err = duplicate_tools.validate().unwrap_err().to_string();
+        assert!(err.contains("duplicate"), "{}", err);
+
+        let mut bad_schema: McpPluginConfig = serde_json::from_value(github_plugin()).unwrap();
+        bad_schema.tools[0].input_schema = serde_json::json!("string");
+        assert!(bad_schema.validate().is_err());
+    }
+}
@@ -1,3 +1,5 @@
+use std::collections::BTreeMap;
+use std::time::Duration;
 use serde::{Deserialize, Serialize};
 
 #[derive(Default, Clone, Debug, Serialize, Deserialize)]
@@ -7,6 +9,29 @@ pub struct MCPToolUsageMetrics {
     total_cache_creation_tokens: u64,
     total_cache_read_tokens: u64,
     usage_records: Vec<UsageRecord>,
+    #[serde(default)]
+    tool_invocations: Vec<ToolInvocation>,
+}
+
+/// A single MCP tool invocation made while answering an LLM query
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ToolInvocation {
+    pub tool_use_id: String,
+    pub tool_name: String,
+    pub latency_ms: u64,
+}
+
+/// Invocation count and latency for one MCP tool
+#[derive(Default, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ToolInvocationStats {
+    pub count: u64,
+    pub total_latency_ms: u64,
+}
+
+impl ToolInvocationStats {
+    pub fn average_latency_ms(&self) -> u64 {
+        self.total_latency_ms.checked_div(self.count).unwrap_or(0)
+    }
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -19,10 +44,22 @@ pub struct UsageRecord {
     pub cache_read_tokens: Option<u64>,
     pub tool_name: Option<String>,
     pub tool_type: Option<String>,
-    pub linked_tool_id: Option<String>,
+    #[serde(default)]
+    pub linked_tool_ids: Vec<String>,
     pub reverie_id: Option<String>,
     pub spender_address: Option<String>,
     pub spender_type: Option<String>,
+    /// LLM provider that served the request, e.g. "deepseek" after a fallback from "anthropic"
+    #[serde(default)]
+    pub provider: Option<String>,
+}
+
+/// Token usage totals for one LLM provider
+#[derive(Default, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ProviderUsage {
+    pub requests: u64,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
 }
 
 impl MCPToolUsageMetrics {
@@ -37,9 +74,48 @@ impl MCPToolUsageMetrics {
         self.usage_records.push(record);
     }
 
+    /// Record an MCP tool invocation and how long the LLM call using it took
+    pub fn record_tool_invocation(&mut self, tool_use_id: String, tool_name: String, latency: Duration) {
+        self.tool_invocations.push(ToolInvocation {
+            tool_use_id,
+            tool_name,
+            latency_ms: latency.as_millis() as u64,
+        });
+    }
+
+    /// Most recent MCP tool invocation, if any
+    pub fn last_tool_invocation(&self) -> Option<&ToolInvocation> {
+        self.tool_invocations.last()
+    }
+
+    /// Invocation count and latency per tool name
+    pub fn tool_stats(&self) -> BTreeMap<String, ToolInvocationStats> {
+        let mut stats: BTreeMap<String, ToolInvocationStats> = BTreeMap::new();
+        for invocation in self.tool_invocations.iter() {
+            let tool_stats = stats.entry(invocation.tool_name.clone()).or_default();
+            tool_stats.count += 1;
+            tool_stats.total_latency_ms += invocation.latency_ms;
+        }
+        stats
+    }
+
+    /// Token usage per provider, so usage served by a fallback provider is reported separately
+    pub fn usage_by_provider(&self) -> BTreeMap<String, ProviderUsage> {
+        let mut usage: BTreeMap<String, ProviderUsage> = BTreeMap::new();
+        for record in self.usage_records.iter() {
+            let provider = record.provider.clone().unwrap_or_else(|| "unknown".to_string());
+            let provider_usage = usage.entry(provider).or_default();
+            provider_usage.requests += 1;
+            provider_usage.input_tokens += record.input_tokens;
+            provider_usage.output_tokens += record.output_tokens;
+        }
+        usage
+    }
+
     /// Clear all usage data
     pub fn clear_usage_data(&mut self) {
         self.usage_records.clear();
+        self.tool_invocations.clear();
         self.total_input_tokens = 0;
         self.total_output_tokens = 0;
         self.total_cache_creation_tokens = 0;
@@ -69,6 +145,29 @@ impl MCPToolUsageMetrics {
             }
         }
 
+        let provider_usage = self.usage_by_provider();
+        if !provider_usage.is_empty() {
+            report.push_str("Usage by provider:\n");
+            for (provider, usage) in provider_usage.iter() {
+                report.push_str(&format!("  {}: {} requests, Tokens: {}/{}\n",
+                    provider,
+                    usage.requests,
+                    usage.input_tokens,
+                    usage.output_tokens));
+            }
+        }
+
+        let tool_stats = self.tool_stats();
+        if !tool_stats.is_empty() {
+            report.push_str("MCP tool invocations:\n");
+            for (tool_name, stats) in tool_stats.iter() {
+                report.push_str(&format!("  {}: {} calls, avg latency {}ms\n",
+                    tool_name,
+                    stats.count,
+                    stats.average_latency_ms()));
+            }
+        }
+
         report.push_str(&format!("{}\n", separator));
         report
     }
@@ -1,22 +1,276 @@
 mod mcp_tool_usage;
+mod mcp_plugin;
 mod agent_secrets_json;
 
 use color_eyre::{Result, eyre::anyhow};
 use serde::{Deserialize, Serialize};
 use reqwest;
-pub use mcp_tool_usage::{MCPToolUsageMetrics, UsageRecord};
-pub use agent_secrets_json::{AgentSecretsJson, AgentKeypair, read_agent_secrets};
+use std::sync::LazyLock;
+use std::str::FromStr;
+use tracing::{debug, warn};
+pub use mcp_tool_usage::{MCPToolUsageMetrics, UsageRecord, ToolInvocation, ToolInvocationStats, ProviderUsage};
+pub use mcp_plugin::{McpPluginConfig, McpServerEndpoint, McpToolDefinition};
+pub use agent_secrets_json::{AgentSecretsJson, AgentSecretsSource, AgentKeypair, ApiKeyProvider, read_agent_secrets};
 
 
 
 
+const DEFAULT_PYTHON_LLM_SERVER_URL: &str = "http://localhost:6000";
+const DEFAULT_LLM_PROVIDER_FALLBACKS: [LlmProvider; 2] = [LlmProvider::Anthropic, LlmProvider::DeepSeek];
+
+/// Client for the Python LLM server. Holds one `reqwest::Client` so connections are reused.
+#[derive(Debug, Clone)]
+pub struct LlmServer {
+    client: reqwest::Client,
+    base_url: String,
+    /// Providers tried in order by `call_llm_with_fallback`
+    fallback_providers: Vec<LlmProvider>,
+}
+
+impl LlmServer {
+    pub fn new(base_url: &str) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            base_url: base_url.trim_end_matches('/').to_string(),
+            fallback_providers: DEFAULT_LLM_PROVIDER_FALLBACKS.to_vec(),
+        }
+    }
+
+    pub fn with_fallback_providers(mut self, providers: Vec<LlmProvider>) -> Self {
+        self.fallback_providers = providers;
+        self
+    }
+
+    /// Reads the base URL from PYTHON_LLM_SERVER_URL, defaulting to localhost:6000,
+    /// and the provider fallback order from LLM_PROVIDER_FALLBACKS (e.g. "anthropic,deepseek")
+    pub fn from_env() -> Self {
+        dotenv::dotenv().ok();
+        let base_url = std::env::var("PYTHON_LLM_SERVER_URL").unwrap_or_else(|_| {
+            debug!("PYTHON_LLM_SERVER_URL env var not set, defaulting to: {}", DEFAULT_PYTHON_LLM_SERVER_URL);
+            DEFAULT_PYTHON_LLM_SERVER_URL.to_string()
+        });
+        let llm_server = Self::new(&base_url);
+        match std::env::var("LLM_PROVIDER_FALLBACKS").map(|providers| LlmProvider::parse_list(&providers)) {
+            Ok(Ok(providers)) if !providers.is_empty() => llm_server.with_fallback_providers(providers),
+            Ok(Err(e)) => {
+                warn!("Invalid LLM_PROVIDER_FALLBACKS, using defaults: {}", e);
+                llm_server
+            }
+            _ => llm_server,
+        }
+    }
+
+    pub fn fallback_providers(&self) -> &[LlmProvider] {
+        &self.fallback_providers
+    }
+
+    pub fn api_url(&self, api_type: &str) -> String {
+        format!("{}/{}", self.base_url, api_type)
+    }
+}
+
+/// Shared LLM server client for the `call_anthropic` and `call_deepseek` shims
+static DEFAULT_LLM_SERVER: LazyLock<LlmServer> = LazyLock::new(LlmServer::from_env);
+
+/// LLM query forwarded to the Python LLM server
+#[derive(Deserialize, Debug, Clone, Serialize)]
+pub struct AnthropicQuery {
+    pub prompt: String,
+    pub tools: Option<serde_json::Value>,
+    pub stream: Option<bool>,
+}
+
+impl AnthropicQuery {
+    /// Checks `tools` is an array of objects each with a `name` and `input_schema`,
+    /// so malformed tool schemas are rejected before reaching the LLM provider.
+    pub fn validate_tools(&self) -> Result<()> {
+        let tools = match &self.tools {
+            None => return Ok(()),
+            Some(tools) => tools,
+        };
+        let tools = tools.as_array()
+            .ok_or_else(|| anyhow!("Invalid tools: expected an array of tool objects, got: {}", tools))?;
+
+        for (i, tool) in tools.iter().enumerate() {
+            let tool = tool.as_object()
+                .ok_or_else(|| anyhow!("Invalid tools[{}]: expected a tool object, got: {}", i, tool))?;
+            match tool.get("name") {
+                Some(serde_json::Value::String(name)) if !name.is_empty() => {}
+                _ => return Err(anyhow!("Invalid tools[{}]: missing string field 'name'", i)),
+            }
+            match tool.get("input_schema") {
+                Some(serde_json::Value::Object(_)) => {}
+                _ => return Err(anyhow!("Invalid tools[{}]: missing object field 'input_schema'", i)),
+            }
+        }
+        Ok(())
+    }
+
+    /// Adds an MCP plugin's tools to the query's tools
+    pub fn with_mcp_plugin_tools(mut self, plugin: &McpPluginConfig) -> Result<Self> {
+        let mut tools = match self.tools.take() {
+            None => vec![],
+            Some(serde_json::Value::Array(tools)) => tools,
+            Some(tools) => return Err(anyhow!("Invalid tools: expected an array of tool objects, got: {}", tools)),
+        };
+        tools.extend(plugin.anthropic_tools());
+        self.tools = Some(serde_json::Value::Array(tools));
+        Ok(self)
+    }
+}
+
+/// LLM providers served by the Python LLM server, each on its own route
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub enum LlmProvider {
+    Anthropic,
+    DeepSeek,
+}
+
+impl LlmProvider {
+    pub fn api_type(&self) -> &'static str {
+        match self {
+            LlmProvider::Anthropic => "anthropic",
+            LlmProvider::DeepSeek => "deepseek",
+        }
+    }
+
+    pub fn api_url(&self, llm_server: &LlmServer) -> String {
+        llm_server.api_url(self.api_type())
+    }
+
+    /// Parses a comma separated list of providers, e.g. "anthropic,deepseek"
+    pub fn parse_list(providers: &str) -> Result<Vec<LlmProvider>> {
+        providers.split(',')
+            .map(str::trim)
+            .filter(|p| !p.is_empty())
+            .map(LlmProvider::from_str)
+            .collect()
+    }
+}
+
+impl FromStr for LlmProvider {
+    type Err = color_eyre::Report;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "anthropic" => Ok(LlmProvider::Anthropic),
+            "deepseek" => Ok(LlmProvider::DeepSeek),
+            _ => Err(anyhow!("Unknown LLM provider: '{}'", s)),
+        }
+    }
+}
+
+impl std::fmt::Display for LlmProvider {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.api_type())
+    }
+}
+
 /// Result that tracks success and token usage
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct LlmResult {
     pub text: String,
+    #[serde(default)]
+    pub usage: Option<LlmUsage>,
+    /// Name of the tool used to answer the query, if any
+    #[serde(default)]
+    pub tool_name: Option<String>,
+    /// MCP tool calls the LLM server made to answer the query, each timed on its own
+    #[serde(default)]
+    pub tool_invocations: Vec<ToolInvocation>,
+}
+
+/// Result of `call_llm_with_fallback`, with the provider that served the request
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct LlmFallbackResult {
+    pub provider: LlmProvider,
+    pub result: LlmResult,
+    /// Providers that failed before `provider` succeeded, with their errors
+    pub failed_providers: Vec<(LlmProvider, String)>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct LlmUsage {
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub cache_creation_input_tokens: Option<u64>,
+    pub cache_read_input_tokens: Option<u64>,
+}
+
+/// Queries an LLM provider via the Python LLM server, recording token and tool usage into `metrics`.
+pub async fn call_llm(
+    llm_server: &LlmServer,
+    provider: LlmProvider,
+    query: &AnthropicQuery,
+    context: &str,
+    metrics: &mut MCPToolUsageMetrics,
+) -> Result<LlmResult> {
+
+    let result = call_python_llm_server(
+        llm_server,
+        provider.api_type(),
+        &query.prompt,
+        context,
+        query.tools.clone(),
+        query.stream.unwrap_or(false),
+    ).await?;
+
+    for invocation in result.tool_invocations.iter() {
+        metrics.record_tool_invocation(
+            invocation.tool_use_id.clone(),
+            invocation.tool_name.clone(),
+            std::time::Duration::from_millis(invocation.latency_ms),
+        );
+    }
+
+    let now = chrono::Utc::now();
+    let request_id = format!("{}-{}", provider.api_type(), now.timestamp_millis());
+
+    let usage = result.usage.clone().unwrap_or_default();
+    metrics.add_usage_record(UsageRecord {
+        request_id,
+        timestamp: now.timestamp(),
+        input_tokens: usage.input_tokens,
+        output_tokens: usage.output_tokens,
+        cache_creation_tokens: usage.cache_creation_input_tokens,
+        cache_read_tokens: usage.cache_read_input_tokens,
+        tool_name: result.tool_name.clone(),
+        tool_type: result.tool_name.as_ref().map(|_| "mcp".to_string()),
+        linked_tool_ids: vec![],
+        reverie_id: None,
+        spender_address: None,
+        spender_type: None,
+        provider: Some(provider.api_type().to_string()),
+    });
+
+    Ok(result)
+}
+
+/// Queries each of the LLM server's fallback providers in order until one succeeds,
+/// e.g. falling back to DeepSeek when Anthropic is overloaded. Usage is recorded per provider.
+pub async fn call_llm_with_fallback(
+    llm_server: &LlmServer,
+    query: &AnthropicQuery,
+    context: &str,
+    metrics: &mut MCPToolUsageMetrics,
+) -> Result<LlmFallbackResult> {
+    let mut failed_providers = Vec::new();
+    for provider in llm_server.fallback_providers().iter().copied() {
+        match call_llm(llm_server, provider, query, context, metrics).await {
+            Ok(result) => {
+                return Ok(LlmFallbackResult { provider, result, failed_providers });
+            }
+            Err(e) => {
+                warn!("LLM provider {} failed, trying the next provider: {}", provider, e);
+                failed_providers.push((provider, e.to_string()));
+            }
+        }
+    }
+    Err(anyhow!("All LLM providers failed: {:?}", failed_providers))
 }
 
 pub async fn call_python_llm_server(
+    llm_server: &LlmServer,
     api_type: &str,
     prompt: &str,
     context: &str,
@@ -24,8 +278,7 @@ pub async fn call_python_llm_server(
     stream: bool
 ) -> Result<LlmResult> {
 
-    let client = reqwest::Client::new();
-    let api_url = format!("http://localhost:6000/{}", api_type);
+    let api_url = llm_server.api_url(api_type);
     let payload = serde_json::json!({
         "prompt": prompt,
         "context": context,
@@ -33,14 +286,15 @@ pub async fn call_python_llm_server(
         "tools": tools
     });
 
-    let response = client.post(&api_url)
+    let response = llm_server.client.post(&api_url)
         .json(&payload)
         .send()
         .await?;
 
     if !response.status().is_success() {
+        let status = response.status();
         let error_text = response.text().await?;
-        return Err(anyhow!("LLM API request failed: {}", error_text));
+        return Err(anyhow!("LLM API request failed with {}: {}", status, error_text));
     }
 
     let response_data: LlmResult = response.json().await?;
@@ -53,7 +307,7 @@ pub async fn call_anthropic(
     tools: Option<serde_json::Value>,
     stream: bool,
 ) -> Result<LlmResult> {
-    call_python_llm_server("anthropic", prompt, context, tools, stream).await
+    call_python_llm_server(&DEFAULT_LLM_SERVER, LlmProvider::Anthropic.api_type(), prompt, context, tools, stream).await
 }
 
 pub async fn call_deepseek(
@@ -62,5 +316,159 @@ pub async fn call_deepseek(
     tools: Option<serde_json::Value>,
     stream: bool,
 ) -> Result<LlmResult> {
-    call_python_llm_server("deepseek", prompt, context, tools, stream).await
-}
\ No newline at end of file
+    call_python_llm_server(&DEFAULT_LLM_SERVER, LlmProvider::DeepSeek.api_type(), prompt, context, tools, stream).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::spawn_mock_http_server;
+
+    #[test]
+    fn test_llm_provider_api_urls() {
+        let llm_server = LlmServer::new(DEFAULT_PYTHON_LLM_SERVER_URL);
+        assert_eq!(LlmProvider::Anthropic.api_url(&llm_server), "http://localhost:6000/anthropic");
+        assert_eq!(LlmProvider::DeepSeek.api_url(&llm_server), "http://localhost:6000/deepseek");
+    }
+
+    #[test]
+    fn test_configured_llm_server_url_is_used() {
+        let llm_server = LlmServer::new("http://llm-backend:8080/");
+        assert_eq!(LlmProvider::Anthropic.api_url(&llm_server), "http://llm-backend:8080/anthropic");
+        assert_eq!(llm_server.api_url("deepseek"), "http://llm-backend:8080/deepseek");
+    }
+
+    #[test]
+    fn test_llm_result_without_usage_deserializes() {
+        let result: LlmResult = serde_json::from_str(r#"{"text": "hello"}"#).unwrap();
+        assert_eq!(result.text, "hello");
+        assert!(result.usage.is_none());
+        assert!(result.tool_name.is_none());
+        assert!(result.tool_invocations.is_empty());
+    }
+
+    async fn spawn_mock_llm_server(response_body: serde_json::Value) -> String {
+        spawn_mock_llm_server_responses(vec![("200 OK", response_body)]).await
+    }
+
+    /// Serves one (status, body) response per connection, in order
+    async fn spawn_mock_llm_server_responses(responses: Vec<(&'static str, serde_json::Value)>) -> String {
+        let responses = responses.into_iter()
+            .map(|(status, body)| (status, body.to_string()))
+            .collect();
+        spawn_mock_http_server(responses).await
+    }
+
+    #[tokio::test]
+    async fn test_call_llm_records_tool_invocation() {
+        let base_url = spawn_mock_llm_server(serde_json::json!({
+            "text": "It is sunny in Tokyo",
+            "tool_name": "get_weather",
+            "tool_invocations": [
+                { "tool_use_id": "toolu_01A", "tool_name": "get_weather", "latency_ms": 42 }
+            ],
+            "usage": {
+                "input_tokens": 120,
+                "output_tokens": 30,
+                "cache_creation_input_tokens": null,
+                "cache_read_input_tokens": 10
+            }
+        })).await;
+        let llm_server = LlmServer::new(&base_url);
+
+        let query = AnthropicQuery {
+            prompt: "What is the weather in Tokyo?".to_string(),
+            tools: Some(serde_json::json!([{ "name": "get_weather" }])),
+            stream: None,
+        };
+        let mut metrics = MCPToolUsageMetrics::default();
+
+        let result = call_llm(&llm_server, LlmProvider::Anthropic, &query, "", &mut metrics).await.unwrap();
+        assert_eq!(result.text, "It is sunny in Tokyo");
+
+        let tool_stats = metrics.tool_stats();
+        assert_eq!(tool_stats.len(), 1);
+        assert_eq!(tool_stats["get_weather"].count, 1);
+        // latency is the tool call's, as timed by the LLM server, not the whole LLM call's
+        assert_eq!(tool_stats["get_weather"].total_latency_ms, 42);
+
+        let invocation = metrics.last_tool_invocation().unwrap();
+        assert_eq!(invocation.tool_name, "get_weather");
+        assert_eq!(invocation.tool_use_id, "toolu_01A");
+    }
+
+    #[tokio::test]
+    async fn test_call_llm_falls_back_to_next_provider() {
+        let base_url = spawn_mock_llm_server_responses(vec![
+            ("529 Site Overloaded", serde_json::json!({ "error": "overloaded" })),
+            ("200 OK", serde_json::json!({
+                "text": "Answered by DeepSeek",
+                "usage": { "input_tokens": 50, "output_tokens": 20 }
+            })),
+        ]).await;
+        let llm_server = LlmServer::new(&base_url)
+            .with_fallback_providers(vec![LlmProvider::Anthropic, LlmProvider::DeepSeek]);
+
+        let query = AnthropicQuery {
+            prompt: "Hello".to_string(),
+            tools: None,
+            stream: None,
+        };
+        let mut metrics = MCPToolUsageMetrics::default();
+
+        let fallback = call_llm_with_fallback(&llm_server, &query, "", &mut metrics).await.unwrap();
+        assert_eq!(fallback.provider, LlmProvider::DeepSeek);
+        assert_eq!(fallback.result.text, "Answered by DeepSeek");
+        assert_eq!(fallback.failed_providers.len(), 1);
+        assert_eq!(fallback.failed_providers[0].0, LlmProvider::Anthropic);
+        assert!(fallback.failed_providers[0].1.contains("529"));
+
+        // Usage is only attributed to the provider that served the request
+        let usage = metrics.usage_by_provider();
+        assert_eq!(usage.len(), 1);
+        assert_eq!(usage["deepseek"].requests, 1);
+        assert_eq!(usage["deepseek"].input_tokens, 50);
+        assert_eq!(usage["deepseek"].output_tokens, 20);
+    }
+
+    #[test]
+    fn test_validate_tools() {
+        let query = |tools: Option<serde_json::Value>| AnthropicQuery {
+            prompt: "What's the weather?".to_string(),
+            tools,
+            stream: None,
+        };
+
+        assert!(query(None).validate_tools().is_ok());
+        let well_formed = query(Some(serde_json::json!([{
+            "name": "get_weather",
+            "description": "Get the current weather",
+            "input_schema": { "type": "object", "properties": { "location": { "type": "string" } } }
+        }])));
+        assert!(well_formed.validate_tools().is_ok());
+
+        let not_an_array = query(Some(serde_json::json!({ "name": "get_weather" })));
+        let err = not_an_array.validate_tools().unwrap_err().to_string();
+        assert!(err.contains("expected an array"), "{}", err);
+
+        let missing_schema = query(Some(serde_json::json!([
+            { "name": "get_weather", "input_schema": { "type": "object" } },
+            { "name": "get_time" }
+        ])));
+        let err = missing_schema.validate_tools().unwrap_err().to_string();
+        assert!(err.contains("tools[1]") && err.contains("input_schema"), "{}", err);
+
+        let missing_name = query(Some(serde_json::json!([{ "input_schema": { "type": "object" } }])));
+        let err = missing_name.validate_tools().unwrap_err().to_string();
+        assert!(err.contains("tools[0]") && err.contains("'name'"), "{}", err);
+    }
+
+    #[test]
+    fn test_parse_llm_provider_list() {
+        assert_eq!(
+            LlmProvider::parse_list("Anthropic, deepseek").unwrap(),
+            vec![LlmProvider::Anthropic, LlmProvider::DeepSeek]
+        );
+        assert!(LlmProvider::parse_list("anthropic,gpt").is_err());
+    }
+}
@@ -1,4 +1,5 @@
 use color_eyre::{eyre, Result};
+use serde::{Deserialize, Serialize};
 use umbral_pre::{
     decrypt_original,
     decrypt_reencrypted,
@@ -16,6 +17,24 @@ pub use umbral_pre::{
     VerifiedCapsuleFrag
 };
 
+/// Which public keys kfrags are signed to. A signed key must be supplied to
+/// `KeyFrag::verify`, letting kfrag providers check a kfrag re-encrypts from the
+/// delegating (source) key, or to the receiving (target) key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PreKeyfragParams {
+    pub sign_delegating_key: bool,
+    pub sign_receiving_key: bool,
+}
+
+impl Default for PreKeyfragParams {
+    fn default() -> Self {
+        Self {
+            sign_delegating_key: true,
+            sign_receiving_key: false,
+        }
+    }
+}
+
 // Proxy Re-Encryption Key
 #[derive(Clone)]
 pub struct UmbralKey {
@@ -104,6 +123,21 @@ impl UmbralKey {
         );
     }
 
+    /// Non-panicking `check_ciphertext_decryptable`: verifies the ciphertext decrypts
+    /// back to the original bytes, without assuming anything about their format
+    pub fn verify_ciphertext_roundtrip(
+        &self,
+        capsule: &Capsule,
+        ciphertext: &Box<[u8]>,
+        plaintext_original: &[u8],
+    ) -> Result<()> {
+        let decrypted_plaintext = self.decrypt_original(capsule, ciphertext)?;
+        if &decrypted_plaintext as &[u8] != plaintext_original {
+            return Err(eyre::anyhow!("Decrypted ciphertext does not match original plaintext"));
+        }
+        Ok(())
+    }
+
     /// If `sign_delegating_key` or `sign_receiving_key` are `true`,
     /// the reencrypting party will be able to verify that a [`KeyFrag`](`crate::KeyFrag`)
     /// corresponds to given delegating or receiving public keys
@@ -138,6 +172,22 @@ impl UmbralKey {
             .collect::<Vec<KeyFrag>>()
     }
 
+    pub fn generate_pre_keyfrags_with_params(
+        &self,
+        target_pubkey: &PublicKey,
+        threshold: usize,
+        total_shares: usize,
+        params: PreKeyfragParams,
+    ) -> Vec<KeyFrag> {
+        self.generate_pre_keyfrags(
+            target_pubkey,
+            threshold,
+            total_shares,
+            params.sign_delegating_key,
+            params.sign_receiving_key,
+        )
+    }
+
     pub fn sign(&self, digest: &[u8]) -> umbral_pre::Signature {
         self.signer.sign(digest)
     }
@@ -262,6 +312,46 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn keyfrags_verify_with_the_keys_they_are_signed_to() {
+        let alice = UmbralKey::new(None);
+        let bob = UmbralKey::new(None);
+
+        for sign_delegating_key in [false, true] {
+            for sign_receiving_key in [false, true] {
+                let params = PreKeyfragParams { sign_delegating_key, sign_receiving_key };
+                let kfrag = alice.generate_pre_keyfrags_with_params(&bob.public_key, 2, 3, params)
+                    .remove(0);
+
+                // only the signed keys are required
+                let delegating_pk = sign_delegating_key.then_some(&alice.public_key);
+                let receiving_pk = sign_receiving_key.then_some(&bob.public_key);
+                assert!(
+                    kfrag.clone().verify(&alice.verifying_public_key, delegating_pk, receiving_pk).is_ok(),
+                    "{:?}", params
+                );
+                // supplying every key, as kfrag providers do, always verifies
+                assert!(
+                    kfrag.clone().verify(&alice.verifying_public_key, Some(&alice.public_key), Some(&bob.public_key)).is_ok(),
+                    "{:?}", params
+                );
+
+                // a signed key must be supplied, and match
+                let other = UmbralKey::new(None);
+                if sign_delegating_key {
+                    assert!(kfrag.clone().verify(&alice.verifying_public_key, None, receiving_pk).is_err());
+                    assert!(kfrag.clone().verify(&alice.verifying_public_key, Some(&other.public_key), receiving_pk).is_err());
+                }
+                if sign_receiving_key {
+                    assert!(kfrag.clone().verify(&alice.verifying_public_key, delegating_pk, None).is_err());
+                    assert!(kfrag.clone().verify(&alice.verifying_public_key, delegating_pk, Some(&other.public_key)).is_err());
+                }
+                // kfrags are always signed by the delegator's signer
+                assert!(kfrag.verify(&other.verifying_public_key, delegating_pk, receiving_pk).is_err());
+            }
+        }
+    }
+
     #[test]
     fn test_signature_verification() -> Result<()> {
         // Create an UmbralKey
@@ -0,0 +1,137 @@
+use std::sync::Arc;
+use color_eyre::Result;
+use color_eyre::eyre::anyhow;
+use serde::{Deserialize, Serialize};
+
+use crate::tee_attestation::{perform_dcap_verification, QuoteBody, QuoteV4};
+
+/// Offset and end of report_data in the mock SGX quote: 48 byte header + 320 bytes into the body
+const MOCK_REPORT_DATA_RANGE: std::ops::Range<usize> = 368..432;
+
+/// Vendor-independent result of verifying a TEE attestation quote
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AttestationReport {
+    /// e.g. "TDX", "SGX", or "mock"
+    pub tee_type: String,
+    /// Data the attested enclave bound into the quote, e.g. a payload hash
+    pub report_data: Vec<u8>,
+}
+
+/// Verifies attestation quotes from one kind of TEE, so vendors other than
+/// Intel DCAP (e.g. AMD SEV-SNP or AWS Nitro) can be added alongside it.
+pub trait TeeVerifier: std::fmt::Debug + Send + Sync {
+    fn verify(&self, quote_bytes: &[u8]) -> Result<AttestationReport>;
+
+    /// Whether a heartbeat without an attestation fails verification
+    fn requires_attestation(&self) -> bool {
+        true
+    }
+}
+
+/// Intel TDX/SGX QuoteV4s, verified with DCAP. Verification is skipped on builds
+/// without the `tdx_enabled` feature, see `perform_dcap_verification`.
+#[derive(Debug, Clone, Default)]
+pub struct DcapVerifier;
+
+impl TeeVerifier for DcapVerifier {
+    fn verify(&self, quote_bytes: &[u8]) -> Result<AttestationReport> {
+        // QuoteV4::from_bytes panics on truncated quotes
+        let quote = std::panic::catch_unwind(|| QuoteV4::from_bytes(quote_bytes))
+            .map_err(|_| anyhow!("Malformed QuoteV4 of {} bytes", quote_bytes.len()))?;
+
+        perform_dcap_verification(&quote)?;
+
+        let (tee_type, report_data) = match &quote.quote_body {
+            QuoteBody::TD10QuoteBody(td_report) => ("TDX", td_report.report_data.to_vec()),
+            QuoteBody::SGXQuoteBody(sgx_report) => ("SGX", sgx_report.report_data.to_vec()),
+        };
+        Ok(AttestationReport {
+            tee_type: tee_type.to_string(),
+            report_data,
+        })
+    }
+}
+
+/// Accepts or rejects every quote, for tests and nodes running the mock attestation
+#[derive(Debug, Clone)]
+pub struct MockTeeVerifier {
+    pub accept: bool,
+}
+
+impl TeeVerifier for MockTeeVerifier {
+    fn verify(&self, quote_bytes: &[u8]) -> Result<AttestationReport> {
+        if !self.accept {
+            return Err(anyhow!("Mock TEE verifier rejects all attestations"));
+        }
+        Ok(AttestationReport {
+            tee_type: "mock".to_string(),
+            report_data: quote_bytes.get(MOCK_REPORT_DATA_RANGE).unwrap_or_default().to_vec(),
+        })
+    }
+
+    fn requires_attestation(&self) -> bool {
+        false
+    }
+}
+
+/// Selects the TeeVerifier used to check peers' heartbeat attestations
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum TeeVerifierConfig {
+    #[default]
+    Dcap,
+    Mock { accept: bool },
+}
+
+impl TeeVerifierConfig {
+    pub fn verifier(&self) -> Arc<dyn TeeVerifier> {
+        match self {
+            TeeVerifierConfig::Dcap => Arc::new(DcapVerifier),
+            TeeVerifierConfig::Mock { accept } => Arc::new(MockTeeVerifier { accept: *accept }),
+        }
+    }
+}
+
+impl std::str::FromStr for TeeVerifierConfig {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "dcap" => Ok(TeeVerifierConfig::Dcap),
+            "mock" => Ok(TeeVerifierConfig::Mock { accept: true }),
+            "mock-reject" => Ok(TeeVerifierConfig::Mock { accept: false }),
+            _ => Err(format!("Unknown TEE verifier {}, expected dcap, mock or mock-reject", s)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tee_attestation::{generate_tee_attestation_with_data, hash_payload_for_tdx_report_data};
+
+    #[test]
+    fn mock_verifier_accepts_or_rejects_as_configured() -> Result<()> {
+        let report_data = hash_payload_for_tdx_report_data(b"heartbeat");
+        let (_quote, quote_bytes) = generate_tee_attestation_with_data(report_data, false)?;
+
+        let accepting = "mock".parse::<TeeVerifierConfig>().unwrap().verifier();
+        let report = accepting.verify(&quote_bytes)?;
+        assert_eq!(report.tee_type, "mock");
+        assert_eq!(report.report_data, report_data.to_vec());
+
+        let rejecting = "mock-reject".parse::<TeeVerifierConfig>().unwrap().verifier();
+        assert!(rejecting.verify(&quote_bytes).is_err());
+
+        assert!("sev-snp".parse::<TeeVerifierConfig>().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn dcap_verifier_rejects_truncated_quotes() -> Result<()> {
+        let (_quote, quote_bytes) = generate_tee_attestation_with_data([0; 64], false)?;
+        let verifier = TeeVerifierConfig::default().verifier();
+        assert!(verifier.verify(&quote_bytes[..100]).is_err());
+        assert!(verifier.verify(&[]).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,63 @@
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+/// Serves one (status, JSON body) response per connection, in order, standing in for
+/// the NEAR RPC node or the Python LLM server. Returns the server's base URL.
+pub async fn spawn_mock_http_server(responses: Vec<(&'static str, String)>) -> String {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.expect("mock server bind");
+    let addr = listener.local_addr().expect("mock server addr");
+
+    tokio::spawn(async move {
+        for (status, body) in responses {
+            let (mut socket, _) = listener.accept().await.expect("mock server accept");
+            let mut request = Vec::new();
+            let mut buf = [0u8; 4096];
+            // Read headers and body before responding
+            loop {
+                let n = socket.read(&mut buf).await.expect("mock server read");
+                request.extend_from_slice(&buf[..n]);
+                let request_str = String::from_utf8_lossy(&request);
+                if let Some(header_end) = request_str.find("\r\n\r\n") {
+                    let content_length = request_str[..header_end]
+                        .lines()
+                        .find_map(|l| l.to_lowercase().strip_prefix("content-length:").map(|v| v.trim().to_string()))
+                        .and_then(|v| v.parse::<usize>().ok())
+                        .unwrap_or(0);
+                    if request.len() >= header_end + 4 + content_length || n == 0 {
+                        break;
+                    }
+                }
+                if n == 0 {
+                    break;
+                }
+            }
+
+            let response = format!(
+                "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                status,
+                body.len(),
+                body
+            );
+            socket.write_all(response.as_bytes()).await.expect("mock server write");
+        }
+    });
+
+    format!("http://{}", addr)
+}
+
+/// Serves one NEAR JSON-RPC query response per connection, in order, each returning
+/// `call_results[i]` as the view call's result bytes. Returns the server's base URL.
+pub async fn spawn_mock_near_rpc_responses(call_results: Vec<Vec<u8>>) -> color_eyre::Result<String> {
+    let responses = call_results.into_iter()
+        .map(|call_result_bytes| ("200 OK", serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": "dontcare",
+            "result": {
+                "result": call_result_bytes,
+                "logs": [],
+                "block_height": 1,
+                "block_hash": "11111111111111111111111111111111"
+            }
+        }).to_string()))
+        .collect();
+    Ok(spawn_mock_http_server(responses).await)
+}
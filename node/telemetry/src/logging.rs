@@ -5,7 +5,9 @@ use tracing_appender::{
     rolling,
 };
 use tracing_subscriber::{
+    fmt::MakeWriter,
     layer::SubscriberExt,
+    registry::LookupSpan,
     util::SubscriberInitExt,
     EnvFilter,
     Layer,
@@ -24,7 +26,9 @@ pub struct LoggerConfig {
     pub show_crate_name: bool,
     pub show_time: bool,
     pub show_path: bool,
-    pub logs_dir: Option<String>
+    pub logs_dir: Option<String>,
+    /// Output logs as newline-delimited JSON (with span fields) for log aggregators
+    pub json: bool,
 }
 
 impl Default for LoggerConfig {
@@ -34,7 +38,8 @@ impl Default for LoggerConfig {
             show_crate_name: false,
             show_time: false,
             show_path: true,
-            logs_dir: None
+            logs_dir: None,
+            json: false,
         }
     }
 }
@@ -56,7 +61,16 @@ pub fn init_logger(config: LoggerConfig) -> Vec<WorkerGuard> {
 
     let mut guards = Vec::new();
 
-    if is_test_env {
+    if is_test_env && config.json {
+        registry()
+            .with(json_layer(&config, TestWriter::new()).with_filter(env_filter))
+            .try_init()
+            .map_err(|e| eprintln!("Failed to set global default subscriber for tests: {}", e))
+            .ok();
+
+        tracing::info!("Tracing logger (JSON, for tests with TestWriter) initialized");
+
+    } else if is_test_env {
         let subscriber_builder = fmt::Subscriber::builder()
             .with_max_level(Level::DEBUG)
             .with_env_filter(env_filter)
@@ -75,11 +89,15 @@ pub fn init_logger(config: LoggerConfig) -> Vec<WorkerGuard> {
 
     } else {
         // --- Non-Test Environment: Use your AnsiTermLayer and optional file logging ---
-        let stdout_layer = AnsiTermLayer {
-            show_log_level: config.show_log_level,
-            show_crate_name: config.show_crate_name,
-            show_time: config.show_time,
-            show_path: config.show_path,
+        let stdout_layer = if config.json {
+            json_layer(&config, std::io::stdout)
+        } else {
+            AnsiTermLayer {
+                show_log_level: config.show_log_level,
+                show_crate_name: config.show_crate_name,
+                show_time: config.show_time,
+                show_path: config.show_path,
+            }.boxed()
         }.with_filter(env_filter);
 
         if let Some(dir) = config.logs_dir {
@@ -111,6 +129,32 @@ pub fn init_logger(config: LoggerConfig) -> Vec<WorkerGuard> {
     guards
 }
 
+/// JSON formatting layer, one JSON object per line.
+/// Includes the current span and the list of entered spans with their fields.
+/// `show_time` toggles the timestamp, `show_log_level` the level,
+/// `show_crate_name` the target, and `show_path` the filename and line number.
+pub fn json_layer<S, W>(config: &LoggerConfig, make_writer: W) -> Box<dyn Layer<S> + Send + Sync>
+    where
+        S: tracing::Subscriber + for<'a> LookupSpan<'a>,
+        W: for<'w> MakeWriter<'w> + Send + Sync + 'static,
+{
+    let layer = fmt::layer()
+        .json()
+        .with_current_span(true)
+        .with_span_list(true)
+        .with_writer(make_writer)
+        .with_level(config.show_log_level)
+        .with_target(config.show_crate_name)
+        .with_file(config.show_path)
+        .with_line_number(config.show_path);
+
+    if config.show_time {
+        layer.boxed()
+    } else {
+        layer.without_time().boxed()
+    }
+}
+
 /// The AnsiVisitor
 #[derive(Debug)]
 pub struct AnsiVisitor;
@@ -241,3 +285,90 @@ impl<S> Layer<S> for AnsiTermLayer
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Clone, Default)]
+    struct BufferWriter(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for BufferWriter {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl<'w> MakeWriter<'w> for BufferWriter {
+        type Writer = BufferWriter;
+
+        fn make_writer(&'w self) -> Self::Writer {
+            self.clone()
+        }
+    }
+
+    fn capture_json_logs(config: LoggerConfig) -> Vec<serde_json::Value> {
+        let writer = BufferWriter::default();
+        let subscriber = registry().with(json_layer(&config, writer.clone()));
+
+        tracing::subscriber::with_default(subscriber, || {
+            let span = tracing::info_span!("reverie", reverie_id = "reverie_123");
+            let _entered = span.enter();
+            tracing::info!(peer = "node1", "sending kfrags");
+        });
+
+        let output = String::from_utf8(writer.0.lock().unwrap().clone()).unwrap();
+        output.lines()
+            .map(|line| serde_json::from_str(line).expect("log line is not valid JSON"))
+            .collect()
+    }
+
+    #[test]
+    fn json_logs_parse_with_expected_fields() {
+        let logs = capture_json_logs(LoggerConfig {
+            json: true,
+            show_time: true,
+            show_crate_name: true,
+            ..Default::default()
+        });
+        assert_eq!(logs.len(), 1);
+        let log = &logs[0];
+
+        assert_eq!(log["level"], "INFO");
+        assert_eq!(log["fields"]["message"], "sending kfrags");
+        assert_eq!(log["fields"]["peer"], "node1");
+        assert_eq!(log["span"]["name"], "reverie");
+        assert_eq!(log["span"]["reverie_id"], "reverie_123");
+        assert_eq!(log["spans"][0]["reverie_id"], "reverie_123");
+        assert!(log["timestamp"].is_string());
+        assert!(log["target"].is_string());
+        assert!(log["filename"].as_str().unwrap().ends_with("logging.rs"));
+        assert!(log["line_number"].is_u64());
+    }
+
+    #[test]
+    fn json_logs_omit_disabled_fields() {
+        let logs = capture_json_logs(LoggerConfig {
+            json: true,
+            show_time: false,
+            show_log_level: false,
+            show_crate_name: false,
+            show_path: false,
+            ..Default::default()
+        });
+        let log = &logs[0];
+
+        assert_eq!(log["fields"]["message"], "sending kfrags");
+        assert!(log.get("timestamp").is_none());
+        assert!(log.get("level").is_none());
+        assert!(log.get("target").is_none());
+        assert!(log.get("filename").is_none());
+        assert!(log.get("line_number").is_none());
+    }
+}
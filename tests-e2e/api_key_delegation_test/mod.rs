@@ -17,12 +17,15 @@ fn init() {
     });
 }
 
+use alloy_primitives::B256;
+use alloy_signer::Signer;
 use alloy_signer_local::PrivateKeySigner;
 use color_eyre::{Result, eyre::Error};
 use serde_json::json;
+use sha3::{Digest, Keccak256};
 use tokio::sync::OnceCell;
 // internal imports
-use p2p_network::types::AnthropicQuery;
+use p2p_network::types::{AnthropicQuery, AccessCondition, AccessKey, create_spawn_challenge};
 use utils_docker::init_test_logger;
 
 /// filepath from the perspective of the root of the repo
@@ -39,6 +42,18 @@ pub async fn create_signer() -> Result<PrivateKeySigner> {
     Ok(wallet)
 }
 
+/// Signs the spawn challenge, proving the spawner controls the AccessCondition's key
+pub async fn sign_spawn_challenge(
+    signer: &PrivateKeySigner,
+    memory_secrets: &serde_json::Value,
+    access_condition: &AccessCondition,
+) -> Result<AccessKey> {
+    let challenge = create_spawn_challenge(memory_secrets, access_condition);
+    let hash = B256::from_slice(Keccak256::digest(challenge.as_bytes()).as_slice());
+    let signature = signer.sign_hash(&hash).await?;
+    Ok(AccessKey::EcdsaSignature(signature.as_bytes().to_vec()))
+}
+
 pub async fn setup_test_environment_once_async() -> Result<()> {
 
     TEST_ENV_SETUP_ONCE.get_or_try_init(|| async {
@@ -98,7 +98,9 @@ pub async fn test_api_key_delegation_near_contract() -> Result<()> {
                 api_keys_secrets,
                 threshold,
                 total_frags,
-                access_condition_dev
+                access_condition_dev,
+                None::<AccessKey>, // NEAR contract conditions are gated on-chain
+                None::<Vec<String>>
             ]
         )
     ).await??;
@@ -149,6 +151,7 @@ pub async fn test_api_key_delegation_near_contract() -> Result<()> {
     /////////////////////////////////////////////////////////
     println!("Step 4: Delegate secret context/memories...");
     let memory_secrets = TEST_MEMORY_REVERIE.get().unwrap();
+    let spawn_signature_user = sign_spawn_challenge(&signer_user, memory_secrets, &access_condition_user).await?;
     let memory_reverie_result: Reverie = tokio::time::timeout(
         Duration::from_secs(5),
         clients[&9901].request(
@@ -157,7 +160,9 @@ pub async fn test_api_key_delegation_near_contract() -> Result<()> {
                 memory_secrets.clone(),
                 threshold,
                 total_frags,
-                access_condition_user
+                access_condition_user,
+                spawn_signature_user,
+                None::<Vec<String>>
             ]
         )
     ).await??;
@@ -71,6 +71,23 @@ pub async fn test_api_key_delegation_ecdsa() -> Result<()> {
         "anthropic_api_key": std::env::var("ANTHROPIC_API_KEY").ok(),
         "deepseek_api_key": std::env::var("DEEPSEEK_API_KEY").ok(),
     });
+
+    // Spawning a reverie bound to the dev's key, signed by someone else, is rejected
+    let wrong_spawn_signature = sign_spawn_challenge(&signer_user, &api_keys_secrets, &access_condition_dev).await?;
+    let rejected_spawn: Result<Reverie, _> = clients[&9901].request(
+        "spawn_memory_reverie",
+        jsonrpsee::rpc_params![
+            api_keys_secrets.clone(),
+            threshold,
+            total_frags,
+            access_condition_dev.clone(),
+            wrong_spawn_signature,
+            None::<Vec<String>>
+        ]
+    ).await;
+    assert!(rejected_spawn.is_err(), "Spawn with mismatched signature should be rejected");
+
+    let spawn_signature_dev = sign_spawn_challenge(&signer_dev, &api_keys_secrets, &access_condition_dev).await?;
     let api_key_reverie: Reverie = tokio::time::timeout(
         Duration::from_secs(5),
         clients[&9901].request(
@@ -79,7 +96,9 @@ pub async fn test_api_key_delegation_ecdsa() -> Result<()> {
                 api_keys_secrets,
                 threshold,
                 total_frags,
-                access_condition_dev
+                access_condition_dev,
+                spawn_signature_dev,
+                None::<Vec<String>>
             ]
         )
     ).await??;
@@ -108,6 +127,7 @@ pub async fn test_api_key_delegation_ecdsa() -> Result<()> {
     /////////////////////////////////////////////////////////
     println!("Step 3: Delegate secret context/memories...");
     let memory_secrets = TEST_MEMORY_REVERIE.get().unwrap();
+    let spawn_signature_user = sign_spawn_challenge(&signer_user, memory_secrets, &access_condition_user).await?;
     let memory_reverie_result: Reverie = tokio::time::timeout(
         Duration::from_secs(5),
         clients[&9901].request(
@@ -116,7 +136,9 @@ pub async fn test_api_key_delegation_ecdsa() -> Result<()> {
                 memory_secrets.clone(),
                 threshold,
                 total_frags,
-                access_condition_user
+                access_condition_user,
+                spawn_signature_user,
+                None::<Vec<String>>
             ]
         )
     ).await??;
@@ -188,3 +210,93 @@ pub async fn test_api_key_delegation_ecdsa() -> Result<()> {
     defer! { shutdown_docker_environment(3); }
     Ok(())
 }
+
+//////////////////////////////////////
+//// Decrypt Reverie via CLI
+//////////////////////////////////////
+
+#[tokio::test]
+#[serial_test::serial]
+pub async fn test_decrypt_reverie_via_cli() -> Result<()> {
+    crate::init();
+
+    setup_test_environment_once_async().await?;
+
+    let test_nodes = TestNodes::new(5)
+        .start_test_network().await?
+        .create_rpc_clients().await?;
+
+    let clients = test_nodes.rpc_clients.clone();
+
+    let signer_user = create_signer().await?;
+    let access_condition_user = AccessCondition::Ecdsa(signer_user.address());
+
+    println!("Step 1: Spawn memory reverie with secret memories and an API key...");
+    let mut memory_secrets = TEST_MEMORY_REVERIE.get().unwrap().clone();
+    memory_secrets["anthropic_api_key"] = json!("sk-ant-should-be-redacted");
+    let spawn_signature_user = sign_spawn_challenge(&signer_user, &memory_secrets, &access_condition_user).await?;
+
+    let memory_reverie: Reverie = tokio::time::timeout(
+        Duration::from_secs(5),
+        clients[&9901].request(
+            "spawn_memory_reverie",
+            jsonrpsee::rpc_params![
+                memory_secrets.clone(),
+                2, // threshold
+                3, // total_frags
+                access_condition_user,
+                spawn_signature_user,
+                None::<Vec<String>>
+            ]
+        )
+    ).await??;
+    time::sleep(Duration::from_millis(1000)).await;
+
+    let signature = {
+        let hash = create_digest_hash(&memory_reverie.id, 0, 0);
+        signer_user.sign_hash(&hash).await?
+    };
+
+    println!("Step 2: Decrypt reverie with the CLI...");
+    let output = std::process::Command::new("cargo")
+        .current_dir("..")
+        .args([
+            "run", "--bin", "cmd",
+            "--",
+            "--rpc-server-address", "127.0.0.1:9902",
+            "decrypt-reverie",
+            "--reverie-id", &memory_reverie.id,
+            "--reverie-type", "Memory",
+            "--signature", &format!("Ecdsa({})", alloy_primitives::hex::encode(signature.as_bytes())),
+        ])
+        .output()?;
+
+    let cli_output = format!(
+        "{}{}",
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr)
+    );
+    println!("{}", cli_output);
+
+    if !output.status.success() {
+        return Err(anyhow!("decrypt-reverie CLI command failed: {}", output.status));
+    }
+    assert!(cli_output.contains("First Beach Trip"), "CLI did not print decrypted memories");
+    assert!(!cli_output.contains("sk-ant-should-be-redacted"), "CLI printed an unredacted API key");
+
+    println!("Step 3: Decrypted contents match the original memories...");
+    let decrypted: serde_json::Value = clients[&9902].request(
+        "decrypt_reverie",
+        jsonrpsee::rpc_params![
+            memory_reverie.id.clone(),
+            ReverieType::Memory,
+            AccessKey::from(signature)
+        ]
+    ).await?;
+
+    assert_eq!(decrypted["memories"], memory_secrets["memories"]);
+    assert_eq!(decrypted["anthropic_api_key"], "[REDACTED]");
+
+    defer! { test_nodes.cleanup_ports(); }
+    Ok(())
+}
@@ -0,0 +1,63 @@
+#[path = "../utils_network.rs"]
+mod utils_network;
+
+use std::process::Command;
+use color_eyre::{Result, eyre::anyhow};
+use scopeguard::defer;
+
+use utils_network::{TestNodes, Port};
+
+
+#[tokio::test]
+#[serial_test::serial]
+pub async fn test_benchmark_single_iteration() -> Result<()> {
+
+    // the spawning node, a vessel and 3 kfrag providers
+    let test_nodes = TestNodes::new(5)
+        .start_test_network().await?
+        .create_rpc_clients().await?;
+
+    defer! { test_nodes.cleanup_ports(); }
+
+    test_nodes.clone().wait_for_network_readiness(4, 60, 500).await?;
+
+    let source_port: Port = 9902;
+    let ports = test_nodes.rpc_ports.iter()
+        .map(|port| port.to_string())
+        .collect::<Vec<String>>()
+        .join(",");
+
+    println!("Running a single benchmark iteration against {}...", source_port);
+    let output = Command::new("cargo")
+        .current_dir("..")
+        .args([
+            "run", "--bin", "cmd",
+            "--",
+            "--rpc-server-address", &format!("127.0.0.1:{}", source_port),
+            "benchmark",
+            "--threshold", "2",
+            "--total-frags", "3",
+            "--iterations", "1",
+            "--ports", &ports,
+        ])
+        .output()?;
+
+    let logs = format!(
+        "{}{}",
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr)
+    );
+    if !output.status.success() {
+        return Err(anyhow!("benchmark exited with {}:\n{}", output.status, logs));
+    }
+
+    // every stage was measured once
+    for stage in ["spawn", "distribution", "reconstruction"] {
+        let row = logs.lines()
+            .find(|line| line.trim_start().starts_with(stage))
+            .ok_or_else(|| anyhow!("no {} latencies in benchmark output:\n{}", stage, logs))?;
+        assert!(row.contains("ms"), "{} latencies not measured: {}", stage, row);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,207 @@
+#[path = "../utils_network.rs"]
+mod utils_network;
+
+use std::time::Duration;
+use color_eyre::Result;
+use jsonrpsee::core::client::ClientT;
+use libp2p::futures::StreamExt;
+use libp2p::request_response::{Event, Message};
+use libp2p::swarm::SwarmEvent;
+use libp2p::{noise, tcp, yamux, Multiaddr, PeerId, Swarm};
+use scopeguard::defer;
+
+use p2p_network::create_network::NetworkConfig;
+use p2p_network::types::{
+    AccessCondition,
+    AccessKey,
+    FragmentRequestEnum,
+    FragmentResponseEnum,
+    Reverie,
+    ReverieCiphertextChunk,
+    ReverieKeyfrag,
+    ReverieKeyfragMessage,
+    ReverieMessage,
+    ReverieType,
+    DEFAULT_MAX_REVERIE_PAYLOAD_SIZE,
+    REVERIE_WIRE_VERSION,
+};
+use runtime::reencrypt::UmbralKey;
+use utils_network::{TestNodes, BOOTSTRAP_PEER};
+
+
+type RequestResponseSwarm = Swarm<libp2p::request_response::cbor::Behaviour<FragmentRequestEnum, FragmentResponseEnum>>;
+
+fn request_response_swarm() -> RequestResponseSwarm {
+    libp2p::SwarmBuilder::with_new_identity()
+        .with_tokio()
+        .with_tcp(
+            tcp::Config::default(),
+            noise::Config::new,
+            yamux::Config::default
+        ).unwrap()
+        .with_behaviour(|_| NetworkConfig::default().request_response_behaviour()).unwrap()
+        .with_swarm_config(|c| c.with_idle_connection_timeout(Duration::from_secs(60)))
+        .build()
+}
+
+/// Waits for a response to each of `num_requests` requests, panicking if any goes unanswered
+async fn collect_responses(swarm: &mut RequestResponseSwarm, num_requests: usize) -> Result<Vec<FragmentResponseEnum>> {
+    let responses = tokio::time::timeout(Duration::from_secs(20), async {
+        let mut responses = vec![];
+        while responses.len() < num_requests {
+            match swarm.select_next_some().await {
+                SwarmEvent::Behaviour(Event::Message { message: Message::Response { response, .. }, .. }) => {
+                    responses.push(response);
+                }
+                SwarmEvent::Behaviour(Event::OutboundFailure { error, .. }) => {
+                    panic!("malformed request went unanswered: {}", error);
+                }
+                _ => {}
+            }
+        }
+        responses
+    }).await?;
+    Ok(responses)
+}
+
+#[tokio::test]
+#[serial_test::serial]
+pub async fn test_malformed_fragment_requests_do_not_crash_node() -> Result<()> {
+
+    let test_nodes = TestNodes::new(3)
+        .start_test_network().await?
+        .create_rpc_clients().await?;
+
+    defer! { test_nodes.cleanup_ports(); }
+
+    // Talk to node 1 directly over the fragment request-response protocol
+    let node_peer_id: PeerId = BOOTSTRAP_PEER.parse()?;
+    let node_addr: Multiaddr = format!("/ip4/127.0.0.1/tcp/{}", test_nodes.listen_ports[0]).parse()?;
+    let mut swarm = request_response_swarm();
+    swarm.add_peer_address(node_peer_id, node_addr.clone());
+    let local_peer_id = *swarm.local_peer_id();
+
+    let source_key = UmbralKey::new(None);
+    let target_key = UmbralKey::new(None);
+    let (capsule, _ciphertext) = source_key.encrypt_bytes(&b"secrets".to_vec())?;
+    let kfrag = source_key.generate_pre_keyfrags(&target_key.public_key, 2, 3, true, false).remove(0);
+    let kfrag_json = serde_json::to_vec(&kfrag)?;
+    let capsule_json = serde_json::to_vec(&capsule)?;
+
+    let save_fragment_request = |umbral_keyfrag: Vec<u8>, umbral_capsule: Vec<u8>| {
+        FragmentRequestEnum::SaveFragmentRequest(ReverieKeyfragMessage {
+            reverie_keyfrag: ReverieKeyfrag {
+                version: REVERIE_WIRE_VERSION,
+                id: "reverie_malformed".to_string(),
+                reverie_type: ReverieType::Memory,
+                frag_num: 0,
+                threshold: 2,
+                total_frags: 3,
+                umbral_keyfrag,
+                umbral_capsule,
+                source_pubkey: source_key.public_key,
+                source_verifying_pubkey: source_key.verifying_public_key,
+                target_pubkey: target_key.public_key,
+                target_verifying_pubkey: target_key.verifying_public_key,
+                access_condition: AccessCondition::Umbral(target_key.public_key),
+                expires_at: None,
+            },
+            source_peer_id: local_peer_id,
+            target_peer_id: node_peer_id,
+        })
+    };
+
+    let malformed_save_requests = vec![
+        save_fragment_request(kfrag_json[..kfrag_json.len() / 2].to_vec(), capsule_json.clone()),
+        save_fragment_request(vec![0xff; 128], capsule_json.clone()),
+        save_fragment_request(kfrag_json.clone(), capsule_json[..capsule_json.len() / 2].to_vec()),
+        save_fragment_request(kfrag_json.clone(), b"\"garbage\"".to_vec()),
+    ];
+    let malformed_get_requests = vec![
+        FragmentRequestEnum::GetFragmentRequest("reverie_malformed".to_string(), AccessKey::UmbralSignature(vec![0xff; 7])),
+        FragmentRequestEnum::GetFragmentRequest("".to_string(), AccessKey::EcdsaSignature(vec![])),
+    ];
+
+    let num_requests = malformed_save_requests.len() + malformed_get_requests.len();
+    for request in malformed_save_requests.into_iter().chain(malformed_get_requests) {
+        swarm.behaviour_mut().send_request(&node_peer_id, request);
+    }
+
+    // Each malformed request is answered with a failure, rather than crashing the node
+    let responses = collect_responses(&mut swarm, num_requests).await?;
+
+    let rejected_saves = responses.iter()
+        .filter(|response| matches!(response, FragmentResponseEnum::SaveFragmentFailedResponse(_)))
+        .count();
+    let rejected_gets = responses.iter()
+        .filter(|response| matches!(response, FragmentResponseEnum::GetFragmentResponse(Err(_))))
+        .count();
+    assert_eq!(rejected_saves, 4, "{:?}", responses);
+    assert_eq!(rejected_gets, 2, "{:?}", responses);
+
+    // A chunked ciphertext is rejected once its chunks pass the max payload size
+    let (capsule, ciphertext) = source_key.encrypt_bytes(&b"secrets".to_vec())?;
+    let reverie_msg = ReverieMessage {
+        reverie: Reverie::new(
+            "oversized".to_string(),
+            ReverieType::Memory,
+            2,
+            3,
+            target_key.public_key,
+            target_key.verifying_public_key,
+            AccessCondition::Umbral(target_key.public_key),
+            capsule,
+            ciphertext,
+        ),
+        source_peer_id: local_peer_id,
+        target_peer_id: node_peer_id,
+        keyfrag_providers: vec![],
+    };
+    let num_chunks = 3;
+    for index in 0..num_chunks {
+        swarm.behaviour_mut().send_request(&node_peer_id, FragmentRequestEnum::SaveCiphertextChunkRequest(
+            ReverieCiphertextChunk {
+                reverie_msg: reverie_msg.clone(),
+                index,
+                total: num_chunks,
+                bytes: vec![0; DEFAULT_MAX_REVERIE_PAYLOAD_SIZE / 2],
+            }
+        ));
+    }
+    let responses = collect_responses(&mut swarm, num_chunks).await?;
+    let rejected_chunks = responses.iter()
+        .filter(|response| matches!(response, FragmentResponseEnum::SaveCiphertextFailedResponse(_)))
+        .count();
+    assert_eq!(rejected_chunks, 1, "{:?}", responses);
+
+    // A peer hanging up before its response is sent doesn't take the node down either
+    let mut hangup_swarm = request_response_swarm();
+    hangup_swarm.add_peer_address(node_peer_id, node_addr);
+    hangup_swarm.behaviour_mut().send_request(
+        &node_peer_id,
+        FragmentRequestEnum::GetCiphertextRequest("reverie_malformed".to_string())
+    );
+    tokio::time::timeout(Duration::from_secs(10), async {
+        loop {
+            if let SwarmEvent::ConnectionEstablished { .. } = hangup_swarm.select_next_some().await {
+                break
+            }
+        }
+    }).await?;
+    // Drive the swarm just long enough to write the request, then hang up
+    let _ = tokio::time::timeout(Duration::from_millis(200), async {
+        loop {
+            hangup_swarm.select_next_some().await;
+        }
+    }).await;
+    drop(hangup_swarm);
+
+    // Node is still up
+    tokio::time::sleep(Duration::from_secs(1)).await;
+    let health: serde_json::Value = test_nodes.rpc_clients[&9901]
+        .request("health", jsonrpsee::rpc_params![])
+        .await?;
+    assert_eq!(health["status"], "ok");
+
+    Ok(())
+}
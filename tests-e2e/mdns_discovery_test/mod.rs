@@ -0,0 +1,25 @@
+#[path = "../utils_network.rs"]
+mod utils_network;
+
+use color_eyre::Result;
+use scopeguard::defer;
+
+use utils_network::TestNodes;
+
+
+#[tokio::test]
+#[serial_test::serial]
+pub async fn test_nodes_discover_each_other_via_mdns() -> Result<()> {
+
+    // No bootstrap peers: nodes must find each other through mDNS alone.
+    // create_rpc_clients() waits until every node reports (num_nodes - 1) connected peers
+    let test_nodes = TestNodes::new(2)
+        .with_mdns_discovery()
+        .start_test_network().await?
+        .create_rpc_clients().await?;
+
+    defer! { test_nodes.cleanup_ports(); }
+
+    assert_eq!(test_nodes.rpc_clients.len(), 2, "Both nodes should be reachable");
+    Ok(())
+}
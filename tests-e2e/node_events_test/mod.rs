@@ -0,0 +1,89 @@
+#[path = "../utils_network.rs"]
+mod utils_network;
+
+use std::{
+    net::SocketAddr,
+    time::Duration,
+};
+use color_eyre::{Result, eyre::anyhow};
+use jsonrpsee::core::client::{ClientT, SubscriptionClientT, Subscription};
+use scopeguard::defer;
+use tokio::sync::mpsc;
+use tokio::time;
+
+use p2p_network::types::{NodeEvent, NodeKeysWithVesselStatus};
+use rpc::rpc_client::create_ws_rpc_client;
+use runtime::llm::read_agent_secrets;
+use utils_network::{TestNodes, Port};
+
+
+/// Subscribes to node events on every port, forwarding them as (port, event)
+async fn subscribe_node_events(ports: Vec<Port>) -> Result<mpsc::Receiver<(Port, NodeEvent)>> {
+    let (sender, receiver) = mpsc::channel(100);
+    for port in ports {
+        let ws_client = create_ws_rpc_client(&SocketAddr::from(([127, 0, 0, 1], port))).await?;
+        let mut subscription: Subscription<NodeEvent> = ws_client
+            .subscribe(
+                "subscribe_node_events",
+                jsonrpsee::rpc_params![],
+                "unsubscribe_node_events"
+            ).await?;
+
+        let sender = sender.clone();
+        tokio::spawn(async move {
+            // keep the client alive for as long as the subscription
+            let _ws_client = ws_client;
+            while let Some(Ok(node_event)) = subscription.next().await {
+                if sender.send((port, node_event)).await.is_err() {
+                    break;
+                }
+            }
+        });
+    }
+    Ok(receiver)
+}
+
+#[tokio::test]
+#[serial_test::serial]
+pub async fn test_node_events_stream_reverie_saved() -> Result<()> {
+
+    let test_nodes = TestNodes::new(5)
+        .start_test_network().await?
+        .create_rpc_clients().await?;
+
+    defer! { test_nodes.cleanup_ports(); }
+
+    let mut node_events = subscribe_node_events(test_nodes.all_ports()).await?;
+
+    let vessel: NodeKeysWithVesselStatus = test_nodes.rpc_clients[&9901]
+        .request(
+            "spawn_agent",
+            jsonrpsee::rpc_params![
+                read_agent_secrets(0),
+                2, // threshold
+                3  // total_frags
+            ],
+        )
+        .await?;
+
+    // The target vessel reports saving the agent's reverie
+    let reverie_saved = time::timeout(Duration::from_secs(10), async {
+        while let Some((port, node_event)) = node_events.recv().await {
+            println!("Node(port: {}) event: {:?}", port, node_event);
+            if let NodeEvent::ReverieSaved { target_peer_id, .. } = &node_event {
+                if *target_peer_id == vessel.peer_id {
+                    return Some(node_event);
+                }
+            }
+        }
+        None
+    }).await;
+
+    match reverie_saved {
+        Ok(Some(NodeEvent::ReverieSaved { reverie_type, .. })) => {
+            println!("Received ReverieSaved for {:?}", reverie_type);
+            Ok(())
+        }
+        _ => Err(anyhow!("No ReverieSaved event received from vessel {}", vessel.peer_id)),
+    }
+}
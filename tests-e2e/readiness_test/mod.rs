@@ -0,0 +1,97 @@
+#[path = "../utils_network.rs"]
+mod utils_network;
+
+use std::time::Duration;
+use color_eyre::{Result, eyre::anyhow};
+use jsonrpsee::core::client::ClientT;
+use jsonrpsee::http_client::HttpClient;
+use scopeguard::defer;
+use tokio::time;
+
+use p2p_network::types::NodeReadiness;
+use utils_network::TestNodes;
+
+
+async fn get_readiness(client: &HttpClient) -> Result<NodeReadiness> {
+    Ok(client.request("get_readiness", jsonrpsee::rpc_params![]).await?)
+}
+
+#[tokio::test]
+#[serial_test::serial]
+pub async fn test_readiness_false_before_bootstrap() -> Result<()> {
+
+    // A lone bootstrap node has no peers to bootstrap Kademlia with
+    let test_nodes = TestNodes::new(1)
+        .start_test_network().await?
+        .create_rpc_clients().await?;
+
+    defer! { test_nodes.cleanup_ports(); }
+
+    let client = &test_nodes.rpc_clients[&9901];
+    let health: serde_json::Value = client.request("get_health", jsonrpsee::rpc_params![]).await?;
+    assert_eq!(health["status"], "ok");
+
+    let readiness = get_readiness(client).await?;
+    assert_eq!(readiness.connected_peers, 0);
+    assert!(!readiness.kademlia_bootstrapped);
+    assert!(!readiness.ready, "Node without peers should not be ready");
+    Ok(())
+}
+
+#[tokio::test]
+#[serial_test::serial]
+pub async fn test_readiness_true_after_peer_connects() -> Result<()> {
+
+    let test_nodes = TestNodes::new(2)
+        .start_test_network().await?
+        .create_rpc_clients().await?;
+
+    defer! { test_nodes.cleanup_ports(); }
+
+    for (port, client) in test_nodes.rpc_clients.iter() {
+        let mut readiness = get_readiness(client).await?;
+        for _ in 0..20 {
+            if readiness.ready {
+                break
+            }
+            time::sleep(Duration::from_millis(500)).await;
+            readiness = get_readiness(client).await?;
+        }
+        if !readiness.ready {
+            return Err(anyhow!("Node on port {} never became ready: {:?}", port, readiness));
+        }
+        assert!(readiness.connected_peers >= 1);
+        assert!(readiness.kademlia_bootstrapped);
+    }
+    Ok(())
+}
+
+#[tokio::test]
+#[serial_test::serial]
+pub async fn test_nodes_join_network_when_bootstrap_node_starts_late() -> Result<()> {
+
+    // Nodes 2 and 3 start first, their initial dials to the bootstrap node fail
+    let test_nodes = TestNodes::new(3)
+        .with_delayed_bootstrap_node(Duration::from_secs(5))
+        .start_test_network().await?
+        .create_rpc_clients().await?;
+
+    defer! { test_nodes.cleanup_ports(); }
+
+    for (port, client) in test_nodes.rpc_clients.iter() {
+        let mut readiness = get_readiness(client).await?;
+        for _ in 0..20 {
+            if readiness.ready {
+                break
+            }
+            time::sleep(Duration::from_millis(500)).await;
+            readiness = get_readiness(client).await?;
+        }
+        if !readiness.ready {
+            return Err(anyhow!("Node on port {} never joined the network: {:?}", port, readiness));
+        }
+        // Discovered through the bootstrap node's Kademlia routing table, after retrying
+        assert!(readiness.connected_peers >= 2);
+    }
+    Ok(())
+}
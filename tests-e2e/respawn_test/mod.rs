@@ -19,7 +19,9 @@ use scopeguard::defer;
 use tracing::{info, warn};
 
 use p2p_network::types::{
+    AgentVesselInfo,
     ReverieNameWithNonce,
+    ReverieType,
     NodeKeysWithVesselStatus,
 };
 use p2p_network::node_client::RestartReason;
@@ -120,18 +122,16 @@ async fn wait_for_agent_respawn(
         time::sleep(Duration::from_secs(2)).await;
         info!("Checking for agent respawning...");
 
-        match client.request::<Value, _>("get_node_state", jsonrpsee::rpc_params![]).await {
-            Ok(state) => {
-                if let Some(vessel_agent) = state.get("_agent_in_vessel") {
-                    if let Some(agent_name_nonce) = vessel_agent.get("agent_name_nonce") {
-                        println!("[Test] Node taken over as vessel: {}",
-                              serde_json::to_string_pretty(vessel_agent)?);
-
-                        let agent_str = agent_name_nonce.to_string();
-                        return Ok(ReverieNameWithNonce::from(agent_str));
-                    }
-                }
-                println!("json: {}", serde_json::to_string_pretty(&state)?);
+        match client.request::<Option<AgentVesselInfo>, _>("current_vessel_agent", jsonrpsee::rpc_params![]).await {
+            Ok(Some(AgentVesselInfo {
+                reverie_type: ReverieType::SovereignAgent(agent_name_nonce)
+                    | ReverieType::Agent(agent_name_nonce),
+                ..
+            })) => {
+                println!("[Test] Node taken over as vessel: {}", agent_name_nonce);
+                return Ok(agent_name_nonce);
+            },
+            Ok(_) => {
                 println!("[Test] Agent not yet respawned, waiting...");
             },
             Err(e) => {
@@ -227,6 +227,43 @@ pub async fn test_agent_spawn_and_sending_fragments() -> Result<()> {
     Ok(())
 }
 
+#[tokio::test]
+#[serial_test::serial]
+pub async fn test_current_vessel_agent_reports_spawned_agent() -> Result<()> {
+
+    let test_nodes = TestNodes::new(5)
+        .start_test_network().await?
+        .create_rpc_clients().await?;
+
+    defer! { test_nodes.cleanup_ports(); }
+
+    let threshold = 2;
+    let total_frags = 3;
+    let next_vessel = spawn_agent_on_node(
+        &test_nodes.rpc_clients[&9901],
+        threshold,
+        total_frags,
+        1
+    ).await?;
+
+    let node_state: Value = test_nodes.rpc_clients[&9901]
+        .request("get_node_state", jsonrpsee::rpc_params![]).await?;
+    let spawner_peer_id = node_state["_peer_id"].as_str().unwrap().to_string();
+
+    // The spawning node is the agent's current vessel, handing over to the target vessel
+    let vessel_agent: Option<AgentVesselInfo> = test_nodes.rpc_clients[&9901]
+        .request("current_vessel_agent", jsonrpsee::rpc_params![]).await?;
+    let vessel_agent = vessel_agent.expect("spawning node should report its vessel agent");
+
+    assert!(matches!(vessel_agent.reverie_type, ReverieType::SovereignAgent(_)));
+    assert_eq!(vessel_agent.threshold, threshold);
+    assert_eq!(vessel_agent.total_frags, total_frags);
+    assert_eq!(vessel_agent.current_vessel_peer_id.to_string(), spawner_peer_id);
+    assert_eq!(vessel_agent.next_vessel_peer_id, next_vessel.peer_id);
+
+    Ok(())
+}
+
 #[tokio::test]
 #[serial_test::serial]
 pub async fn test_agent_respawn_after_failure() -> Result<()> {
@@ -275,4 +312,185 @@ pub async fn test_agent_respawn_after_failure() -> Result<()> {
 
     defer! { test_nodes.cleanup_ports(); }
     Ok(())
-}
\ No newline at end of file
+}
+
+#[tokio::test]
+#[serial_test::serial]
+pub async fn test_umbral_key_rotation_rekeys_vessel_reverie() -> Result<()> {
+
+    // 6 nodes: 1 sender, 1 vessel, 3 kfrag providers, 1 spare
+    let test_nodes = TestNodes::new(6)
+        .start_test_network().await?
+        .create_rpc_clients().await?;
+
+    let threshold = 2;
+    let total_frags = 3;
+    let secret_key_seed = 1;
+    let target_vessel = spawn_agent_on_node(
+        &test_nodes.rpc_clients[&9901],
+        threshold,
+        total_frags,
+        secret_key_seed
+    ).await?;
+
+    // Find the RPC client of the vessel holding the agent's Reverie
+    let vessel_peer_id = serde_json::to_value(target_vessel.peer_id)?;
+    let mut vessel_client = None;
+    for client in test_nodes.rpc_clients.values() {
+        let state: Value = client.request("get_node_state", jsonrpsee::rpc_params![]).await?;
+        if state["_peer_id"] == vessel_peer_id {
+            vessel_client = Some(client.clone());
+        }
+    }
+    let vessel_client = vessel_client.expect("target vessel not found amongst test nodes");
+
+    // Rotate the vessel's Umbral key
+    let old_pubkey = serde_json::to_value(target_vessel.umbral_public_key)?;
+    let new_pubkey: Value = vessel_client
+        .request("rotate_umbral_key", jsonrpsee::rpc_params![])
+        .await?;
+    println!("[Test] Rotated vessel Umbral key: {} => {}", old_pubkey, new_pubkey);
+    assert_ne!(new_pubkey, old_pubkey, "Umbral key was not rotated");
+
+    // Vessel publishes its new pubkey
+    let state: Value = vessel_client.request("get_node_state", jsonrpsee::rpc_params![]).await?;
+    assert_eq!(state["_umbral_public_key"], new_pubkey, "Vessel does not publish its new pubkey");
+
+    // Allow time for re-keyed fragments to reach kfrag providers
+    time::sleep(Duration::from_millis(2000)).await;
+
+    // Vessel must still be able to reconstruct the agent when the sender fails
+    let _failure_result = trigger_node_failure(&test_nodes.rpc_clients[&9901]).await?;
+    let respawned_agent = wait_for_agent_respawn(&vessel_client, 20).await?;
+
+    let expected_agent = ReverieNameWithNonce("auron".to_string(), 1);
+    assert_eq!(respawned_agent, expected_agent, "Re-keyed agent was not respawned on the vessel");
+
+    defer! { test_nodes.cleanup_ports(); }
+    Ok(())
+}
+
+#[tokio::test]
+#[serial_test::serial]
+pub async fn test_force_reincarnate_agent_on_next_vessel() -> Result<()> {
+
+    // 6 nodes: 1 sender, 1 vessel, 3 kfrag providers, 1 spare
+    let test_nodes = TestNodes::new(6)
+        .start_test_network().await?
+        .create_rpc_clients().await?;
+
+    let threshold = 2;
+    let total_frags = 3;
+    let secret_key_seed = 1;
+    let target_vessel = spawn_agent_on_node(
+        &test_nodes.rpc_clients[&9901],
+        threshold,
+        total_frags,
+        secret_key_seed
+    ).await?;
+
+    // Find the RPC client of the agent's next vessel
+    let vessel_peer_id = serde_json::to_value(target_vessel.peer_id)?;
+    let mut vessel_client = None;
+    for client in test_nodes.rpc_clients.values() {
+        let state: Value = client.request("get_node_state", jsonrpsee::rpc_params![]).await?;
+        if state["_peer_id"] == vessel_peer_id {
+            vessel_client = Some(client.clone());
+        }
+    }
+    let vessel_client = vessel_client.expect("target vessel not found amongst test nodes");
+
+    // Only the next vessel can reincarnate the agent
+    let not_vessel = test_nodes.rpc_clients[&9901]
+        .request::<Value, _>("force_reincarnate", jsonrpsee::rpc_params!["auron", 0])
+        .await;
+    assert!(not_vessel.is_err(), "Non-vessel node should not reincarnate the agent");
+
+    // Migrate the agent while the sender node is still healthy
+    let respawn_id: Value = vessel_client
+        .request("force_reincarnate", jsonrpsee::rpc_params!["auron", 0])
+        .await?;
+    println!("[Test] Forced reincarnation: {}", respawn_id);
+
+    // A second trigger while the respawn is pending is rejected
+    let double_trigger = vessel_client
+        .request::<Value, _>("force_reincarnate", jsonrpsee::rpc_params!["auron", 0])
+        .await;
+    assert!(double_trigger.is_err(), "Respawn should not be triggered twice");
+
+    let respawned_agent = wait_for_agent_respawn(&vessel_client, 20).await?;
+    let expected_agent = ReverieNameWithNonce("auron".to_string(), 1);
+    assert_eq!(respawned_agent, expected_agent, "Agent was not reincarnated on the next vessel");
+
+    defer! { test_nodes.cleanup_ports(); }
+    Ok(())
+}
+
+#[tokio::test]
+#[serial_test::serial]
+pub async fn test_handoff_vessel_to_chosen_successor() -> Result<()> {
+
+    // 7 nodes: 1 current vessel, 1 next vessel, 1 successor, and kfrag providers
+    // for both the re-keyed agent and its reincarnation in the successor
+    let test_nodes = TestNodes::new(7)
+        .start_test_network().await?
+        .create_rpc_clients().await?;
+
+    defer! { test_nodes.cleanup_ports(); }
+
+    let threshold = 2;
+    let total_frags = 3;
+    let next_vessel = spawn_agent_on_node(
+        &test_nodes.rpc_clients[&9901],
+        threshold,
+        total_frags,
+        1
+    ).await?;
+
+    // Choose a healthy successor other than the agent's next vessel
+    let current_vessel_state: Value = test_nodes.rpc_clients[&9901]
+        .request("get_node_state", jsonrpsee::rpc_params![]).await?;
+    let mut successor = None;
+    for (port, client) in test_nodes.rpc_clients.iter() {
+        let state: Value = client.request("get_node_state", jsonrpsee::rpc_params![]).await?;
+        let peer_id = state["_peer_id"].as_str().unwrap().to_string();
+        if peer_id != current_vessel_state["_peer_id"] && peer_id != next_vessel.peer_id.to_string() {
+            successor = Some((*port, peer_id));
+            break;
+        }
+    }
+    let (successor_port, successor_peer_id) = successor.expect("no successor amongst test nodes");
+
+    // Handing off to this node itself is rejected
+    let self_handoff = test_nodes.rpc_clients[&9901]
+        .request::<Value, _>("handoff_vessel", jsonrpsee::rpc_params![current_vessel_state["_peer_id"].clone()])
+        .await;
+    assert!(self_handoff.is_err(), "Vessel should not hand off to itself");
+
+    test_nodes.rpc_clients[&9901]
+        .request::<Value, _>("handoff_vessel", jsonrpsee::rpc_params![successor_peer_id.clone()])
+        .await?;
+
+    // The agent runs on the successor...
+    let successor_agent: Option<AgentVesselInfo> = test_nodes.rpc_clients[&successor_port]
+        .request("current_vessel_agent", jsonrpsee::rpc_params![]).await?;
+    let successor_agent = successor_agent.expect("successor should be the agent's current vessel");
+    assert_eq!(successor_agent.current_vessel_peer_id.to_string(), successor_peer_id);
+    assert_eq!(
+        successor_agent.reverie_type,
+        ReverieType::SovereignAgent(ReverieNameWithNonce("auron".to_string(), 1))
+    );
+
+    // ...and no longer on the previous vessel
+    let prev_vessel_agent: Option<AgentVesselInfo> = test_nodes.rpc_clients[&9901]
+        .request("current_vessel_agent", jsonrpsee::rpc_params![]).await?;
+    assert!(prev_vessel_agent.is_none(), "Previous vessel still runs the agent: {:?}", prev_vessel_agent);
+
+    // Nothing left to hand off
+    let second_handoff = test_nodes.rpc_clients[&9901]
+        .request::<Value, _>("handoff_vessel", jsonrpsee::rpc_params![successor_peer_id])
+        .await;
+    assert!(second_handoff.is_err(), "Previous vessel should have no agent to hand off");
+
+    Ok(())
+}
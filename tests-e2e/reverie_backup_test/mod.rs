@@ -0,0 +1,125 @@
+#[path = "../utils_network.rs"]
+mod utils_network;
+
+use std::time::Duration;
+use alloy_primitives::B256;
+use alloy_signer::Signer;
+use alloy_signer_local::PrivateKeySigner;
+use color_eyre::Result;
+use jsonrpsee::core::client::ClientT;
+use scopeguard::defer;
+use serde_json::{json, Value};
+use sha3::{Digest, Keccak256};
+use tokio::time;
+
+use p2p_network::node_client::KeyfragBroadcastReport;
+use p2p_network::types::{
+    AccessCondition,
+    AccessKey,
+    Reverie,
+    ReverieBundle,
+    ReverieType,
+    create_spawn_challenge,
+};
+use utils_network::{TestNodes, Port};
+
+
+#[tokio::test]
+#[serial_test::serial]
+pub async fn test_exported_reverie_is_restored_after_providers_are_lost() -> Result<()> {
+
+    // 3 providers are lost, leaving the source, the vessel and 3 fresh providers
+    let test_nodes = TestNodes::new(8)
+        .start_test_network().await?
+        .create_rpc_clients().await?;
+
+    defer! { test_nodes.cleanup_ports(); }
+
+    let clients = test_nodes.rpc_clients.clone();
+    let source_port: Port = 9902;
+
+    println!("Step 1: Spawn a memory reverie...");
+    let signer = PrivateKeySigner::random();
+    let access_condition = AccessCondition::Ecdsa(signer.address());
+    let memory_secrets = json!({ "memories": "backed up off-network" });
+    let challenge = create_spawn_challenge(&memory_secrets, &access_condition);
+    let challenge_hash = B256::from_slice(Keccak256::digest(challenge.as_bytes()).as_slice());
+    let spawn_signature = AccessKey::EcdsaSignature(signer.sign_hash(&challenge_hash).await?.as_bytes().to_vec());
+
+    let memory_reverie: Reverie = clients[&source_port]
+        .request(
+            "spawn_memory_reverie",
+            jsonrpsee::rpc_params![
+                memory_secrets.clone(),
+                2, // threshold
+                3, // total_frags
+                access_condition,
+                spawn_signature,
+                None::<Vec<String>>
+            ],
+        )
+        .await?;
+    time::sleep(Duration::from_secs(2)).await;
+
+    let access_key = {
+        let hash = B256::from_slice(Keccak256::digest(memory_reverie.id.as_bytes()).as_slice());
+        AccessKey::from(signer.sign_hash(&hash).await?)
+    };
+
+    println!("Step 2: Export the reverie bundle...");
+    let bundle: ReverieBundle = clients[&source_port]
+        .request("export_reverie", jsonrpsee::rpc_params![memory_reverie.id.clone()])
+        .await?;
+    assert_eq!(bundle.reverie.id, memory_reverie.id);
+    assert_eq!(bundle.reverie.umbral_ciphertext, memory_reverie.umbral_ciphertext);
+    // the bundle never carries the plaintext
+    assert!(!serde_json::to_string(&bundle)?.contains("backed up off-network"));
+    // it records where fragments were placed, so import doesn't rely on the node's memory
+    assert_eq!(bundle.kfrag_providers.len(), 3, "{:?}", bundle.kfrag_providers);
+
+    println!("Step 3: Stop every kfrag provider of the reverie...");
+    let mut provider_ports = vec![];
+    for (port, client) in clients.iter().filter(|(port, _)| **port != source_port) {
+        let state: Value = client.request("get_node_state", jsonrpsee::rpc_params![]).await?;
+        let holds_cfrag = state["peer_manager"]["1_cfrags_summary"]
+            .as_array()
+            .map(|cfrags| cfrags.iter().any(|cfrag| cfrag["reverie_id"] == memory_reverie.id.as_str()))
+            .unwrap_or(false);
+        if holds_cfrag {
+            provider_ports.push(*port);
+        }
+    }
+    assert_eq!(provider_ports.len(), 3, "expected 3 kfrag providers, found: {:?}", provider_ports);
+    for port in &provider_ports {
+        test_nodes.stop_node(*port);
+    }
+    time::sleep(Duration::from_secs(3)).await;
+
+    let decrypted_without_providers = time::timeout(
+        Duration::from_secs(30),
+        clients[&source_port].request::<Value, _>(
+            "decrypt_reverie",
+            jsonrpsee::rpc_params![memory_reverie.id.clone(), ReverieType::Memory, access_key.clone()]
+        )
+    ).await;
+    assert!(!matches!(decrypted_without_providers, Ok(Ok(_))), "decrypted without any kfrag providers");
+
+    println!("Step 4: Import the bundle, re-broadcasting keyfrags to fresh providers...");
+    let report: KeyfragBroadcastReport = clients[&source_port]
+        .request("import_reverie", jsonrpsee::rpc_params![bundle])
+        .await?;
+    assert_eq!(report.placed.len(), 3, "{:?}", report);
+    assert!(report.failed.is_empty(), "{:?}", report);
+    time::sleep(Duration::from_secs(2)).await;
+
+    println!("Step 5: Reconstruct the reverie from the fresh providers...");
+    let decrypted: Value = clients[&source_port]
+        .request(
+            "decrypt_reverie",
+            jsonrpsee::rpc_params![memory_reverie.id.clone(), ReverieType::Memory, access_key]
+        )
+        .await?;
+    assert_eq!(decrypted["memories"], memory_secrets["memories"]);
+
+    Ok(())
+}
@@ -0,0 +1,116 @@
+#[path = "../utils_network.rs"]
+mod utils_network;
+
+use std::time::Duration;
+use alloy_primitives::B256;
+use alloy_signer::Signer;
+use alloy_signer_local::PrivateKeySigner;
+use color_eyre::Result;
+use jsonrpsee::core::client::ClientT;
+use scopeguard::defer;
+use serde_json::json;
+use sha3::{Digest, Keccak256};
+use tokio::time;
+
+use p2p_network::types::{
+    AccessCondition,
+    AccessKey,
+    NodeKeysWithVesselStatus,
+    Reverie,
+    ReverieId,
+    ReverieNameWithNonce,
+    ReverieType,
+    ReverieTypeIndexEntry,
+    create_spawn_challenge,
+};
+use runtime::llm::read_agent_secrets;
+use utils_network::TestNodes;
+
+
+#[tokio::test]
+#[serial_test::serial]
+pub async fn test_list_reveries_by_type_returns_matching_subset() -> Result<()> {
+
+    let test_nodes = TestNodes::new(5)
+        .start_test_network().await?
+        .create_rpc_clients().await?;
+
+    defer! { test_nodes.cleanup_ports(); }
+
+    let clients = test_nodes.rpc_clients.clone();
+
+    // 1 agent reverie
+    let _vessel: NodeKeysWithVesselStatus = clients[&9901]
+        .request(
+            "spawn_agent",
+            jsonrpsee::rpc_params![
+                read_agent_secrets(0),
+                2, // threshold
+                3  // total_frags
+            ],
+        )
+        .await?;
+
+    // 2 memory reveries
+    let signer = PrivateKeySigner::random();
+    let access_condition = AccessCondition::Ecdsa(signer.address());
+    let mut memory_reverie_ids: Vec<ReverieId> = vec![];
+    for i in 0..2 {
+        let memory_secrets = json!({ "memories": format!("memory number {}", i) });
+        let challenge = create_spawn_challenge(&memory_secrets, &access_condition);
+        let hash = B256::from_slice(Keccak256::digest(challenge.as_bytes()).as_slice());
+        let spawn_signature = AccessKey::EcdsaSignature(signer.sign_hash(&hash).await?.as_bytes().to_vec());
+
+        let memory_reverie: Reverie = clients[&9902]
+            .request(
+                "spawn_memory_reverie",
+                jsonrpsee::rpc_params![
+                    memory_secrets,
+                    2, // threshold
+                    3, // total_frags
+                    access_condition.clone(),
+                    spawn_signature,
+                    None::<Vec<String>>,
+                    format!("memory number {}", i), // description
+                    vec!["test".to_string()] // tags
+                ],
+            )
+            .await?;
+        memory_reverie_ids.push(memory_reverie.id);
+    }
+    memory_reverie_ids.sort();
+
+    // Wait for vessels to publish their reverie type index on Kademlia
+    time::sleep(Duration::from_secs(3)).await;
+
+    let listed_memories: Vec<ReverieId> = clients[&9905]
+        .request("list_reveries_by_type", jsonrpsee::rpc_params![ReverieType::Memory])
+        .await?;
+    assert_eq!(listed_memories, memory_reverie_ids);
+
+    // Listed entries carry the description and tags given at spawn
+    let memory_entries: Vec<ReverieTypeIndexEntry> = clients[&9905]
+        .request("list_reverie_entries_by_type", jsonrpsee::rpc_params![ReverieType::Memory])
+        .await?;
+    assert_eq!(memory_entries.len(), 2);
+    for entry in &memory_entries {
+        assert!(memory_reverie_ids.contains(&entry.reverie_id));
+        assert!(entry.description.starts_with("memory number "), "{:?}", entry);
+        assert_eq!(entry.tags, vec!["test".to_string()]);
+    }
+
+    // Agent names are ignored when listing agents
+    let any_agent = ReverieType::SovereignAgent(ReverieNameWithNonce("any".to_string(), 0));
+    let listed_agents: Vec<ReverieId> = clients[&9905]
+        .request("list_reveries_by_type", jsonrpsee::rpc_params![any_agent])
+        .await?;
+    assert_eq!(listed_agents.len(), 1);
+    assert!(!memory_reverie_ids.contains(&listed_agents[0]));
+
+    let listed_tools: Vec<ReverieId> = clients[&9905]
+        .request("list_reveries_by_type", jsonrpsee::rpc_params![ReverieType::Tools])
+        .await?;
+    assert!(listed_tools.is_empty());
+
+    Ok(())
+}
@@ -19,7 +19,7 @@ use jsonrpsee::rpc_params;
 use serde_json::Value;
 
 // Node 1 is the bootstrap node
-static BOOTSTRAP_PEER: &str = "12D3KooWPjceQrSwdWXPyLLeABRXmuqt69Rg3sBYbU1Nft9HyQ6X";
+pub static BOOTSTRAP_PEER: &str = "12D3KooWPjceQrSwdWXPyLLeABRXmuqt69Rg3sBYbU1Nft9HyQ6X";
 static BOOTSTRAP_PEER_PORT: Port = 9001;
 static BASE_RPC_PORT: Port = 9901;
 static BASE_LISTEN_PORT: Port = 9001;
@@ -33,6 +33,7 @@ pub struct TestNodeConfig {
     pub seed: usize,
     pub bootstrap_peer: Option<String>,
     pub docker_compose_cmd: Option<String>,
+    pub enable_mdns: bool,
 }
 
 impl TestNodeConfig {
@@ -48,7 +49,8 @@ impl TestNodeConfig {
             listen_port,
             seed,
             bootstrap_peer,
-            docker_compose_cmd
+            docker_compose_cmd,
+            enable_mdns: false,
         }
     }
 }
@@ -59,6 +61,7 @@ pub struct TestNodes {
     pub rpc_ports: Vec<Port>,
     pub listen_ports: Vec<Port>,
     pub rpc_clients: HashMap<Port, HttpClient>,
+    pub bootstrap_start_delay: Option<Duration>,
 }
 
 impl TestNodes {
@@ -67,6 +70,22 @@ impl TestNodes {
         self.node_configs[index].docker_compose_cmd = Some(docker_compose_cmd.clone());
         self
     }
+
+    /// Start every node without a bootstrap list and rely on mDNS to discover peers
+    pub fn with_mdns_discovery(mut self) -> Self {
+        for node in self.node_configs.iter_mut() {
+            node.bootstrap_peer = None;
+            node.enable_mdns = true;
+        }
+        self
+    }
+
+    /// Start the bootstrap node last, `delay` after the others, so their initial
+    /// bootstrap dials fail and they must retry
+    pub fn with_delayed_bootstrap_node(mut self, delay: Duration) -> Self {
+        self.bootstrap_start_delay = Some(delay);
+        self
+    }
 }
 
 impl TestNodes {
@@ -100,6 +119,7 @@ impl TestNodes {
             rpc_ports: rpc_ports,
             listen_ports: listen_ports,
             rpc_clients: HashMap::new(),
+            bootstrap_start_delay: None,
         }
     }
 
@@ -118,10 +138,18 @@ impl TestNodes {
 
     pub async fn start_test_network(self) -> Result<Self> {
 
-        let test_nodes = &self.node_configs;
+        let mut test_nodes = self.node_configs.clone();
         let mut node_processes = Vec::with_capacity(test_nodes.len());
+        if self.bootstrap_start_delay.is_some() {
+            // Node 1 is the bootstrap node
+            test_nodes.rotate_left(1);
+        }
 
-        for node in test_nodes.clone() {
+        for node in test_nodes {
+            if let (Some(delay), None) = (self.bootstrap_start_delay, &node.bootstrap_peer) {
+                println!("Delaying bootstrap node{} start by {:?}...", node.seed, delay);
+                time::sleep(delay).await;
+            }
             let mut cmd = Command::new("cargo");
             cmd.current_dir("..")
                 .args([
@@ -142,6 +170,10 @@ impl TestNodes {
                 cmd.args(["--docker-compose-cmd", &docker_compose_cmd]);
             }
 
+            if node.enable_mdns {
+                cmd.arg("--enable-mdns");
+            }
+
             match cmd.spawn() {
                 Ok(child) => {
                     node_processes.push((node.rpc_port, child));
@@ -343,6 +375,14 @@ impl TestNodes {
         kill_processes_on_ports(&self.all_ports());
     }
 
+    /// Kills the node serving RPC on `rpc_port`, as if it dropped off the network
+    pub fn stop_node(&self, rpc_port: Port) {
+        if let Some(node) = self.node_configs.iter().find(|node| node.rpc_port == rpc_port) {
+            info!("Stopping node{} on RPC port {}...", node.seed, rpc_port);
+            kill_processes_on_ports(&[node.rpc_port, node.listen_port]);
+        }
+    }
+
     pub async fn wait_for_llm_proxy_key_registration(self, node_number: usize) -> Result<Self> {
 
         tokio::time::sleep(std::time::Duration::from_secs(1)).await;